@@ -7,6 +7,7 @@ use starknet_api::state::StorageKey;
 
 use crate::abi::constants;
 use crate::execution::execution_utils::{felt_to_stark_felt, stark_felt_to_felt};
+use crate::transaction::constants as transaction_constants;
 
 #[cfg(test)]
 #[path = "abi_utils_test.rs"]
@@ -37,6 +38,29 @@ pub fn selector_from_name(entry_point_name: &str) -> EntryPointSelector {
     }
 }
 
+/// Returns whether the given felt is in the range of entry point selectors, i.e., fits in 250 bits
+/// (the range of `starknet_keccak`).
+pub fn is_valid_selector(selector: StarkFelt) -> bool {
+    selector.bytes()[0] <= 3
+}
+
+/// Formats the given selector, along with its name if it is a standard one (constructor, default
+/// or validate entry points).
+pub fn format_selector(selector: EntryPointSelector) -> String {
+    const STANDARD_ENTRY_POINT_NAMES: [&str; 5] = [
+        constants::CONSTRUCTOR_ENTRY_POINT_NAME,
+        constants::DEFAULT_ENTRY_POINT_NAME,
+        transaction_constants::VALIDATE_ENTRY_POINT_NAME,
+        transaction_constants::VALIDATE_DECLARE_ENTRY_POINT_NAME,
+        transaction_constants::VALIDATE_DEPLOY_ENTRY_POINT_NAME,
+    ];
+
+    match STANDARD_ENTRY_POINT_NAMES.iter().find(|&&name| selector_from_name(name) == selector) {
+        Some(name) => format!("{} ({name})", selector.0),
+        None => selector.0.to_string(),
+    }
+}
+
 /// Returns the storage address of a Starknet storage variable given its name and arguments.
 pub fn get_storage_var_address(storage_var_name: &str, args: &[StarkFelt]) -> StorageKey {
     let storage_var_name_hash = starknet_keccak(storage_var_name.as_bytes());
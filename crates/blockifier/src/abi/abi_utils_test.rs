@@ -4,7 +4,7 @@ use starknet_api::core::EntryPointSelector;
 use starknet_api::hash::StarkFelt;
 use starknet_api::stark_felt;
 
-use crate::abi::abi_utils::selector_from_name;
+use crate::abi::abi_utils::{format_selector, is_valid_selector, selector_from_name};
 use crate::abi::constants as abi_constants;
 use crate::abi::sierra_types::felt_to_u128;
 use crate::transaction::constants as transaction_constants;
@@ -55,3 +55,26 @@ fn test_value_too_large_for_type() {
         "Felt 340282366920938463463374607431768211456 is too big to convert to 'u128'."
     );
 }
+
+#[test]
+fn test_selector_formatting() {
+    let validate_selector = selector_from_name(transaction_constants::VALIDATE_ENTRY_POINT_NAME);
+    assert_eq!(
+        format_selector(validate_selector),
+        format!("{} (__validate__)", validate_selector.0)
+    );
+    let default_selector = EntryPointSelector(StarkFelt::ZERO);
+    assert_eq!(format_selector(default_selector), format!("{} (__default__)", StarkFelt::ZERO));
+    let selector = selector_from_name("foo");
+    assert_eq!(format_selector(selector), selector.0.to_string());
+}
+
+#[test]
+fn test_selector_validity() {
+    let max_selector =
+        stark_felt!("0x3ffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffff");
+    assert!(is_valid_selector(max_selector));
+    assert!(!is_valid_selector(stark_felt!(
+        "0x400000000000000000000000000000000000000000000000000000000000000"
+    )));
+}
@@ -36,7 +36,7 @@ pub trait SierraType: Sized {
     fn from_memory(vm: &VirtualMachine, ptr: &mut Relocatable) -> SierraTypeResult<Self>;
 
     fn from_storage(
-        state: &mut dyn StateReader,
+        state: &dyn StateReader,
         contract_address: &ContractAddress,
         key: &StorageKey,
     ) -> SierraTypeResult<Self>;
@@ -78,7 +78,7 @@ impl SierraType for SierraU128 {
     }
 
     fn from_storage(
-        state: &mut dyn StateReader,
+        state: &dyn StateReader,
         contract_address: &ContractAddress,
         key: &StorageKey,
     ) -> SierraTypeResult<Self> {
@@ -111,7 +111,7 @@ impl SierraType for SierraU256 {
     }
 
     fn from_storage(
-        state: &mut dyn StateReader,
+        state: &dyn StateReader,
         contract_address: &ContractAddress,
         key: &StorageKey,
     ) -> SierraTypeResult<Self> {
@@ -51,6 +51,18 @@ impl GasPrices {
     }
 }
 
+/// A source of L1 gas prices, e.g., an oracle fresher than the prices a block was opened with;
+/// fee estimations may take one to override the block gas prices.
+pub trait GasPriceProvider {
+    fn gas_prices(&self) -> GasPrices;
+}
+
+impl GasPriceProvider for GasPrices {
+    fn gas_prices(&self) -> GasPrices {
+        self.clone()
+    }
+}
+
 // Block pre-processing.
 // Writes the hash of the (current_block_number - N) block under its block number in the dedicated
 // contract state, where N=STORED_BLOCK_HASH_BUFFER.
@@ -80,7 +92,7 @@ pub fn pre_process_block(
         return Err(StateError::OldBlockHashNotProvided);
     }
 
-    Ok(BlockContext { block_info, chain_info, versioned_constants })
+    Ok(BlockContext::new(block_info, chain_info, versioned_constants))
 }
 
 pub struct BlockNumberHashPair {
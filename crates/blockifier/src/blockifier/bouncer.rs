@@ -1,12 +1,10 @@
 use std::collections::HashMap;
 
-use cairo_vm::serde::deserialize_program::BuiltinName;
 use cairo_vm::vm::runners::cairo_runner::ExecutionResources as VmExecutionResources;
 
-use crate::abi::constants;
-use crate::transaction::objects::{ResourcesMapping, TransactionExecutionResult};
+use crate::transaction::objects::{Resource, ResourcesMapping, TransactionExecutionResult};
 
-#[derive(Clone, Default)]
+#[derive(Clone, Debug, Default)]
 pub struct BouncerInfo {
     pub state_diff_size: usize, // The number of felts needed to store the state diff.
     pub l1_gas_amount: usize,
@@ -25,38 +23,34 @@ impl BouncerInfo {
     ) -> TransactionExecutionResult<Self> {
         let l1_gas_amount = *tx_actual_resources
             .0
-            .get("l1_gas_usage")
+            .get(&Resource::L1Gas)
             .expect("Invalid Transaction Execution Info. Field l1_gas_usage was not found.");
 
         // TODO(Ayelet, 04/02/2024): Consider defining a constant list.
         let builtin_ordered_list = [
-            BuiltinName::output,
-            BuiltinName::pedersen,
-            BuiltinName::range_check,
-            BuiltinName::ecdsa,
-            BuiltinName::bitwise,
-            BuiltinName::ec_op,
-            BuiltinName::keccak,
-            BuiltinName::poseidon,
+            Resource::OutputBuiltin,
+            Resource::PedersenBuiltin,
+            Resource::RangeCheckBuiltin,
+            Resource::EcdsaBuiltin,
+            Resource::BitwiseBuiltin,
+            Resource::EcOpBuiltin,
+            Resource::KeccakBuiltin,
+            Resource::PoseidonBuiltin,
         ];
         let builtin_instance_counter: HashMap<String, usize> = builtin_ordered_list
             .iter()
-            .map(|name| {
+            .map(|builtin| {
                 (
-                    name.name().to_string(),
-                    tx_actual_resources.0.get(name.name()).copied().unwrap_or_default(),
+                    builtin.to_string(),
+                    tx_actual_resources.0.get(builtin).copied().unwrap_or_default(),
                 )
             })
             .collect();
         let tx_actual_resources = VmExecutionResources {
-            n_steps: tx_actual_resources
-                .0
-                .get(constants::N_STEPS_RESOURCE)
-                .copied()
-                .unwrap_or_default(),
+            n_steps: tx_actual_resources.0.get(&Resource::Steps).copied().unwrap_or_default(),
             n_memory_holes: tx_actual_resources
                 .0
-                .get("n_memory_holes")
+                .get(&Resource::MemoryHoles)
                 .copied()
                 .unwrap_or_default(),
             builtin_instance_counter,
@@ -22,7 +22,13 @@ use crate::transaction::account_transaction::AccountTransaction;
 use crate::transaction::errors::TransactionExecutionError;
 use crate::transaction::objects::TransactionExecutionInfo;
 use crate::transaction::transaction_execution::Transaction;
-use crate::transaction::transactions::{ExecutableTransaction, ValidatableTransaction};
+use crate::transaction::transactions::{
+    ExecutableTransaction, ExecutionFlags, ValidatableTransaction,
+};
+
+#[cfg(test)]
+#[path = "transaction_executor_test.rs"]
+pub mod transaction_executor_test;
 
 #[derive(Debug, Error)]
 pub enum TransactionExecutorError {
@@ -30,6 +36,15 @@ pub enum TransactionExecutorError {
     StateError(#[from] StateError),
     #[error(transparent)]
     TransactionExecutionError(#[from] TransactionExecutionError),
+    #[error(
+        "Transaction state diff size {tx_state_diff_size} exceeds the remaining block capacity \
+         ({block_state_diff_size} out of {max_state_diff_size} used)."
+    )]
+    StateDiffSizeLimitExceeded {
+        tx_state_diff_size: usize,
+        block_state_diff_size: usize,
+        max_state_diff_size: usize,
+    },
 }
 
 pub type TransactionExecutorResult<T> = Result<T, TransactionExecutorError>;
@@ -43,6 +58,11 @@ pub struct TransactionExecutor<S: StateReader> {
     pub visited_storage_entries: HashSet<StorageEntry>,
     // This member should be consistent with the state's modified keys.
     state_changes_keys: StateChangesKeys,
+    // The number of felts needed to store the state diff of the committed transactions.
+    state_diff_size: usize,
+    /// The maximal state diff size (in felts) of the block; transactions that would exceed it are
+    /// rejected. Unbounded if `None`.
+    pub max_state_diff_size: Option<usize>,
 
     // State-related fields.
     pub state: CachedState<S>,
@@ -63,6 +83,8 @@ impl<S: StateReader> TransactionExecutor<S> {
             // Note: the state might not be empty even at this point; it is the creator's
             // responsibility to tune the bouncer according to pre and post block process.
             state_changes_keys: StateChangesKeys::default(),
+            state_diff_size: 0,
+            max_state_diff_size: None,
             state,
             staged_for_commit_state: None,
         };
@@ -74,10 +96,12 @@ impl<S: StateReader> TransactionExecutor<S> {
     /// Executes the given transaction on the state maintained by the executor.
     /// Returns the execution trace, together with the compiled class hashes of executed classes
     /// (used for counting purposes).
+    /// Unless `execution_flags.validate` is set, the validation entry point of account
+    /// transactions is not run (e.g., when simulating transactions without a valid signature).
     pub fn execute(
         &mut self,
         tx: Transaction,
-        charge_fee: bool,
+        execution_flags: ExecutionFlags,
     ) -> TransactionExecutorResult<(TransactionExecutionInfo, BouncerInfo)> {
         let l1_handler_payload_size: Option<usize> =
             if let Transaction::L1HandlerTransaction(l1_handler_tx) = &tx {
@@ -88,12 +112,35 @@ impl<S: StateReader> TransactionExecutor<S> {
         let mut tx_executed_class_hashes = HashSet::<ClassHash>::new();
         let mut tx_visited_storage_entries = HashSet::<StorageEntry>::new();
         let mut transactional_state = CachedState::create_transactional(&mut self.state);
-        let validate = true;
 
         let tx_execution_result =
-            tx.execute_raw(&mut transactional_state, &self.block_context, charge_fee, validate);
+            tx.execute_raw(&mut transactional_state, &self.block_context, execution_flags);
         match tx_execution_result {
             Ok(tx_execution_info) => {
+                // Count residual state diff size (w.r.t. the OS output encoding), i.e., of the keys
+                // not already changed in the block; this is the size added to the block on commit.
+                let tx_state_changes_keys =
+                    transactional_state.get_actual_state_changes()?.into_keys();
+                let tx_unique_state_changes_keys =
+                    tx_state_changes_keys.difference(&self.state_changes_keys);
+                // Note: block-constant felts are not counted here. so the bouncer needs to
+                // tune the size limit accordingly. E.g., the felt that encodes the number of
+                // modified contracts in a block.
+                let state_diff_size =
+                    get_onchain_data_segment_length(tx_unique_state_changes_keys.count());
+
+                // Reject the transaction if it does not fit in the block.
+                if let Some(max_state_diff_size) = self.max_state_diff_size {
+                    if self.state_diff_size + state_diff_size > max_state_diff_size {
+                        transactional_state.abort();
+                        return Err(TransactionExecutorError::StateDiffSizeLimitExceeded {
+                            tx_state_diff_size: state_diff_size,
+                            block_state_diff_size: self.state_diff_size,
+                            max_state_diff_size,
+                        });
+                    }
+                }
+
                 // Prepare bouncer info; the countings here should be linear in the transactional
                 // state changes and execution info rather than the cumulative state attributes.
 
@@ -124,17 +171,6 @@ impl<S: StateReader> TransactionExecutor<S> {
                     &tx_visited_storage_entries,
                 )?;
 
-                // Count residual state diff size (w.r.t. the OS output encoding).
-                let tx_state_changes_keys =
-                    transactional_state.get_actual_state_changes()?.into_keys();
-                let tx_unique_state_changes_keys =
-                    tx_state_changes_keys.difference(&self.state_changes_keys);
-                // Note: block-constant felts are not counted here. so the bouncer needs to
-                // tune the size limit accordingly. E.g., the felt that encodes the number of
-                // modified contracts in a block.
-                let state_diff_size =
-                    get_onchain_data_segment_length(tx_unique_state_changes_keys.count());
-
                 // Finalize counting logic.
                 let actual_resources = &tx_execution_info.actual_resources;
                 let bouncer_info = BouncerInfo::calculate(
@@ -241,6 +277,9 @@ impl<S: StateReader> TransactionExecutor<S> {
         // Note: cancelling writes (0 -> 1 -> 0) will not be removed,
         // but it's fine since fee was charged for them.
         self.state_changes_keys.extend(&finalized_transactional_state.tx_unique_state_changes_keys);
+        self.state_diff_size += get_onchain_data_segment_length(
+            finalized_transactional_state.tx_unique_state_changes_keys.count(),
+        );
 
         self.staged_for_commit_state = None
     }
@@ -0,0 +1,94 @@
+use std::collections::HashMap;
+
+use assert_matches::assert_matches;
+use starknet_api::core::Nonce;
+use starknet_api::hash::StarkFelt;
+use starknet_api::stark_felt;
+use starknet_api::transaction::{Fee, TransactionVersion};
+
+use crate::blockifier::transaction_executor::{TransactionExecutor, TransactionExecutorError};
+use crate::context::BlockContext;
+use crate::invoke_tx_args;
+use crate::state::state_api::{State, StateReader};
+use crate::test_utils::contracts::FeatureContract;
+use crate::test_utils::initial_test_state::test_state;
+use crate::test_utils::{create_calldata, CairoVersion, BALANCE, MAX_FEE};
+use crate::transaction::test_utils::account_invoke_tx;
+use crate::transaction::transaction_execution::Transaction;
+use crate::transaction::transactions::ExecutionFlags;
+
+/// Transactions whose state diff does not fit in the remaining capacity of the block are rejected,
+/// leaving the state of the executor as is; keys already changed in the block are not counted.
+#[test]
+fn test_state_diff_size_limit() {
+    let account_contract = FeatureContract::AccountWithoutValidations(CairoVersion::Cairo0);
+    let test_contract = FeatureContract::TestContract(CairoVersion::Cairo0);
+    let account_address = account_contract.get_instance_address(0);
+    let block_context = BlockContext::create_for_account_testing();
+    let state = test_state(
+        &block_context.chain_info,
+        BALANCE,
+        &[(account_contract, 1), (test_contract, 1)],
+    );
+    let mut tx_executor = TransactionExecutor::new(state, block_context);
+    let invoke_tx = |nonce: u8| {
+        Transaction::AccountTransaction(account_invoke_tx(invoke_tx_args! {
+            max_fee: Fee(MAX_FEE),
+            sender_address: account_address,
+            // Writes a storage key unique to the transaction.
+            calldata: create_calldata(
+                test_contract.get_instance_address(0),
+                "test_storage_read_write",
+                &[stark_felt!(nonce + 1), stark_felt!(1_u8)],
+            ),
+            version: TransactionVersion::ONE,
+            nonce: Nonce(stark_felt!(nonce)),
+        }))
+    };
+
+    tx_executor.execute(invoke_tx(0), ExecutionFlags::default()).unwrap();
+    tx_executor.commit();
+    let block_state_diff_size = tx_executor.state_diff_size;
+    assert!(block_state_diff_size > 0);
+
+    // No capacity left.
+    tx_executor.max_state_diff_size = Some(block_state_diff_size);
+    let error = tx_executor.execute(invoke_tx(1), ExecutionFlags::default()).unwrap_err();
+    let tx_state_diff_size = assert_matches!(
+        error,
+        TransactionExecutorError::StateDiffSizeLimitExceeded {
+            tx_state_diff_size,
+            block_state_diff_size: reported_block_state_diff_size,
+            max_state_diff_size,
+        } if reported_block_state_diff_size == block_state_diff_size
+            && max_state_diff_size == block_state_diff_size
+            => tx_state_diff_size
+    );
+    assert!(tx_executor.staged_for_commit_state.is_none());
+    assert_eq!(tx_executor.state.get_nonce_at(account_address).unwrap(), Nonce(stark_felt!(1_u8)));
+
+    // Exactly enough capacity for the transaction.
+    tx_executor.max_state_diff_size = Some(block_state_diff_size + tx_state_diff_size);
+    tx_executor.execute(invoke_tx(1), ExecutionFlags::default()).unwrap();
+    tx_executor.commit();
+    assert_eq!(tx_executor.state_diff_size, block_state_diff_size + tx_state_diff_size);
+    assert_eq!(tx_executor.state.get_nonce_at(account_address).unwrap(), Nonce(stark_felt!(2_u8)));
+}
+
+/// Classes declared in the block remain reachable after finalizing it moves them to the global
+/// contract cache.
+#[test]
+fn test_declared_class_sizes_after_finalize() {
+    let block_context = BlockContext::create_for_account_testing();
+    let state = test_state(&block_context.chain_info, BALANCE, &[]);
+    let mut tx_executor = TransactionExecutor::new(state, block_context);
+    let test_contract = FeatureContract::TestContract(CairoVersion::Cairo1);
+    let (class_hash, contract_class) = (test_contract.get_class_hash(), test_contract.get_class());
+    tx_executor.state.set_contract_class(class_hash, contract_class.clone()).unwrap();
+
+    tx_executor.finalize(false);
+    assert_eq!(
+        tx_executor.state.declared_class_sizes().unwrap(),
+        HashMap::from([(class_hash, contract_class.bytecode_length())])
+    );
+}
@@ -1,6 +1,9 @@
+use std::num::NonZeroU128;
+
 use starknet_api::core::{ChainId, ContractAddress};
 
 use crate::blockifier::block::BlockInfo;
+use crate::fee::actual_cost::RevertedStepsPricing;
 use crate::transaction::objects::{FeeType, TransactionInfo, TransactionInfoCreator};
 use crate::versioned_constants::VersionedConstants;
 
@@ -16,9 +19,53 @@ pub struct BlockContext {
     pub(crate) block_info: BlockInfo,
     pub(crate) chain_info: ChainInfo,
     pub(crate) versioned_constants: VersionedConstants,
+    pub(crate) policy: BlockContextPolicy,
+}
+
+/// Execution policies of a block which deviate from the mainnet behavior; all are off by default.
+#[derive(Clone, Debug, Default)]
+pub struct BlockContextPolicy {
+    /// Whether Cairo 0 classes may use custom hints registered in the execution context.
+    pub allow_custom_hints: bool,
+    /// Whether the (non-Starknet) `GetCompiledClassHash` syscall is available to Cairo 1 classes.
+    pub allow_get_compiled_class_hash: bool,
+    /// Whether transactions failing the post-execution fee checks (actual fee beyond the sender
+    /// bounds or balance) are rejected, rather than reverted and charged the recommended fee.
+    pub reject_fee_check_failures: bool,
+    /// Whether the resources of the executed fee transfer call replace the fee transfer
+    /// approximation included in the OS resources table, in the transaction's reported actual
+    /// resources. Otherwise (as on mainnet), only the approximation is accounted for. The
+    /// approximation is authoritative for the charged fee either way: the fee is fixed before the
+    /// fee transfer executes, so this flag does not affect it.
+    pub count_fee_transfer_resources: bool,
+    /// How the steps consumed by reverted executions are charged.
+    pub reverted_steps_pricing: RevertedStepsPricing,
+    /// Whether the fee paid on L1 for an L1 handler transaction must cover its actual fee.
+    /// Otherwise (as on mainnet), any positive paid fee is accepted.
+    pub enforce_l1_handler_fee: bool,
+    /// Whether per-syscall profiling metrics of Cairo 1 syscalls are collected, and reported in
+    /// the execution info of each transaction.
+    pub collect_syscall_metrics: bool,
+    /// Whether account transactions executed without validation are charged the validation
+    /// approximation of the OS resources table, as if it had run; e.g., so that the fee estimates
+    /// of simulated transactions cover their validation.
+    pub charge_skipped_validation: bool,
 }
 
 impl BlockContext {
+    pub(crate) fn new(
+        block_info: BlockInfo,
+        chain_info: ChainInfo,
+        versioned_constants: VersionedConstants,
+    ) -> Self {
+        BlockContext {
+            block_info,
+            chain_info,
+            versioned_constants,
+            policy: BlockContextPolicy::default(),
+        }
+    }
+
     /// Note: Prefer using the recommended constructor methods as detailed in the struct
     /// documentation. This method is intended for internal use and will be deprecated in future
     /// versions.
@@ -27,11 +74,7 @@ impl BlockContext {
         chain_info: &ChainInfo,
         versioned_constants: &VersionedConstants,
     ) -> Self {
-        BlockContext {
-            block_info: block_info.clone(),
-            chain_info: chain_info.clone(),
-            versioned_constants: versioned_constants.clone(),
-        }
+        Self::new(block_info.clone(), chain_info.clone(), versioned_constants.clone())
     }
 
     pub fn block_info(&self) -> &BlockInfo {
@@ -45,6 +88,88 @@ impl BlockContext {
     pub fn versioned_constants(&self) -> &VersionedConstants {
         &self.versioned_constants
     }
+
+    pub fn policy(&self) -> &BlockContextPolicy {
+        &self.policy
+    }
+
+    pub fn set_policy(&mut self, policy: BlockContextPolicy) {
+        self.policy = policy;
+    }
+
+    pub fn allow_custom_hints(&self) -> bool {
+        self.policy.allow_custom_hints
+    }
+
+    pub fn set_allow_custom_hints(&mut self, allow_custom_hints: bool) {
+        self.policy.allow_custom_hints = allow_custom_hints;
+    }
+
+    pub fn allow_get_compiled_class_hash(&self) -> bool {
+        self.policy.allow_get_compiled_class_hash
+    }
+
+    pub fn set_allow_get_compiled_class_hash(&mut self, allow_get_compiled_class_hash: bool) {
+        self.policy.allow_get_compiled_class_hash = allow_get_compiled_class_hash;
+    }
+
+    pub fn reject_fee_check_failures(&self) -> bool {
+        self.policy.reject_fee_check_failures
+    }
+
+    pub fn set_reject_fee_check_failures(&mut self, reject_fee_check_failures: bool) {
+        self.policy.reject_fee_check_failures = reject_fee_check_failures;
+    }
+
+    pub fn count_fee_transfer_resources(&self) -> bool {
+        self.policy.count_fee_transfer_resources
+    }
+
+    pub fn set_count_fee_transfer_resources(&mut self, count_fee_transfer_resources: bool) {
+        self.policy.count_fee_transfer_resources = count_fee_transfer_resources;
+    }
+
+    pub fn collect_syscall_metrics(&self) -> bool {
+        self.policy.collect_syscall_metrics
+    }
+
+    pub fn set_collect_syscall_metrics(&mut self, collect_syscall_metrics: bool) {
+        self.policy.collect_syscall_metrics = collect_syscall_metrics;
+    }
+
+    pub fn charge_skipped_validation(&self) -> bool {
+        self.policy.charge_skipped_validation
+    }
+
+    pub fn set_charge_skipped_validation(&mut self, charge_skipped_validation: bool) {
+        self.policy.charge_skipped_validation = charge_skipped_validation;
+    }
+
+    pub fn reverted_steps_pricing(&self) -> RevertedStepsPricing {
+        self.policy.reverted_steps_pricing
+    }
+
+    pub fn set_reverted_steps_pricing(&mut self, reverted_steps_pricing: RevertedStepsPricing) {
+        self.policy.reverted_steps_pricing = reverted_steps_pricing;
+    }
+
+    pub fn enforce_l1_handler_fee(&self) -> bool {
+        self.policy.enforce_l1_handler_fee
+    }
+
+    pub fn set_enforce_l1_handler_fee(&mut self, enforce_l1_handler_fee: bool) {
+        self.policy.enforce_l1_handler_fee = enforce_l1_handler_fee;
+    }
+
+    /// The L1 gas price of the block, in the units of the given fee type (wei or fri).
+    pub fn gas_price(&self, fee_type: &FeeType) -> NonZeroU128 {
+        self.block_info.gas_prices.get_gas_price_by_fee_type(fee_type)
+    }
+
+    /// The address of the token in which fees of the given type are paid.
+    pub fn fee_token_address(&self, fee_type: &FeeType) -> ContractAddress {
+        self.chain_info.fee_token_address(fee_type)
+    }
 }
 
 impl BlockContext {
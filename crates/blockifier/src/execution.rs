@@ -2,6 +2,7 @@ pub mod call_info;
 pub mod common_hints;
 pub mod contract_address;
 pub mod contract_class;
+pub mod custom_hints;
 pub mod deprecated_entry_point_execution;
 pub mod deprecated_syscalls;
 pub mod entry_point;
@@ -1,4 +1,5 @@
 use std::collections::{HashMap, HashSet};
+use std::ops::Range;
 
 use cairo_vm::vm::runners::cairo_runner::ExecutionResources;
 use serde::{Deserialize, Serialize};
@@ -8,6 +9,7 @@ use starknet_api::state::StorageKey;
 use starknet_api::transaction::{EventContent, L2ToL1Payload};
 
 use crate::execution::entry_point::CallEntryPoint;
+use crate::execution::errors::PostExecutionError;
 use crate::fee::gas_usage::get_message_segment_length;
 use crate::state::cached_state::StorageEntry;
 use crate::transaction::errors::TransactionExecutionError;
@@ -87,6 +89,60 @@ struct ExecutionResourcesDef {
     builtin_instance_counter: HashMap<String, usize>,
 }
 
+/// The outputs a syscall handler gathers during the execution of a single call.
+#[derive(Debug, Default)]
+pub struct CallOutputs {
+    pub events: Vec<OrderedEvent>,
+    pub l2_to_l1_messages: Vec<OrderedL2ToL1Message>,
+    pub inner_calls: Vec<CallInfo>,
+    pub storage_read_values: Vec<StarkFelt>,
+    pub accessed_storage_keys: HashSet<StorageKey>,
+}
+
+/// Validates that the orders of the outputs emitted by a call, together with the order ranges
+/// consumed by its inner calls, exactly cover the given range of orders.
+pub fn validate_order_continuity(
+    output_kind: &'static str,
+    orders: impl Iterator<Item = usize>,
+    inner_call_orders: &[Range<usize>],
+    expected_orders: Range<usize>,
+) -> Result<(), PostExecutionError> {
+    let mut order_ranges: Vec<Range<usize>> =
+        orders.map(|order| order..order + 1).chain(inner_call_orders.iter().cloned()).collect();
+    order_ranges.sort_by_key(|range| range.start);
+
+    let mut expected_order = expected_orders.start;
+    for range in order_ranges {
+        if range.start != expected_order {
+            return Err(PostExecutionError::NonContiguousOrders {
+                output_kind,
+                expected_order,
+                actual_order: range.start,
+            });
+        }
+        expected_order = range.end;
+    }
+    if expected_order != expected_orders.end {
+        return Err(PostExecutionError::NonContiguousOrders {
+            output_kind,
+            expected_order,
+            actual_order: expected_orders.end,
+        });
+    }
+
+    Ok(())
+}
+
+/// The number and total size of the events emitted during an execution.
+#[derive(
+    derive_more::Add, derive_more::Sum, Clone, Copy, Debug, Default, Eq, PartialEq, Serialize,
+)]
+pub struct EventSummary {
+    pub n_events: usize,
+    pub total_event_keys: usize,
+    pub total_event_data_size: usize,
+}
+
 /// Represents the full effects of executing an entry point, including the inner calls it invoked.
 #[derive(Debug, Default, Eq, PartialEq, Serialize)]
 pub struct CallInfo {
@@ -116,6 +172,16 @@ impl CallInfo {
         class_hashes
     }
 
+    /// Returns the VM resources consumed by this call itself, excluding those of its inner calls.
+    pub fn resources_without_inner_calls(&self) -> ExecutionResources {
+        self.inner_calls
+            .iter()
+            .fold(self.resources.clone(), |resources, inner_call| {
+                &resources - &inner_call.resources
+            })
+            .filter_unused_builtins()
+    }
+
     /// Returns the set of storage entries visited during this call execution.
     // TODO: Add unit test for this method
     pub fn get_visited_storage_entries(&self) -> HashSet<StorageEntry> {
@@ -172,6 +238,18 @@ impl CallInfo {
     pub fn get_number_of_events(&self) -> usize {
         self.into_iter().map(|call_info| call_info.execution.events.len()).sum()
     }
+
+    /// Returns the number and total size of the events emitted by this call and its inner calls.
+    pub fn summarize_events(&self) -> EventSummary {
+        self.into_iter()
+            .flat_map(|call_info| &call_info.execution.events)
+            .map(|OrderedEvent { event, .. }| EventSummary {
+                n_events: 1,
+                total_event_keys: event.keys.len(),
+                total_event_data_size: event.data.0.len(),
+            })
+            .sum()
+    }
 }
 
 pub struct CallInfoIter<'a> {
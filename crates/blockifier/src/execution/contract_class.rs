@@ -17,7 +17,7 @@ use cairo_vm::vm::runners::builtin_runner::{HASH_BUILTIN_NAME, POSEIDON_BUILTIN_
 use cairo_vm::vm::runners::cairo_runner::ExecutionResources;
 use serde::de::Error as DeserializationError;
 use serde::{Deserialize, Deserializer};
-use starknet_api::core::EntryPointSelector;
+use starknet_api::core::{CompiledClassHash, EntryPointSelector};
 use starknet_api::deprecated_contract_class::{
     ContractClass as DeprecatedContractClass, EntryPoint, EntryPointOffset, EntryPointType,
     Program as DeprecatedProgram,
@@ -29,6 +29,11 @@ use crate::abi::constants::{self, CONSTRUCTOR_ENTRY_POINT_NAME};
 use crate::execution::entry_point::CallEntryPoint;
 use crate::execution::errors::{ContractClassError, PreExecutionError};
 use crate::execution::execution_utils::{felt_to_stark_felt, sn_api_to_cairo_vm_program};
+
+#[cfg(test)]
+#[path = "contract_class_test.rs"]
+pub mod test;
+
 /// Represents a runnable Starknet contract class (meaning, the program is runnable by the VM).
 /// We wrap the actual class in an Arc to avoid cloning the program when cloning the class.
 // Note: when deserializing from a SN API class JSON string, the ABI field is ignored
@@ -63,6 +68,29 @@ impl ContractClass {
             ContractClass::V1(class) => class.bytecode_length(),
         }
     }
+
+    /// Returns whether a call with the given entry point type and selector resolves to an entry
+    /// point of this class (for Cairo 0 classes, possibly the default one).
+    pub fn has_entry_point(
+        &self,
+        entry_point_type: EntryPointType,
+        selector: EntryPointSelector,
+    ) -> bool {
+        match self {
+            ContractClass::V0(class) => {
+                let Some(entry_points) = class.entry_points_by_type.get(&entry_point_type) else {
+                    return false;
+                };
+                let default_selector = selector_from_name(constants::DEFAULT_ENTRY_POINT_NAME);
+                entry_points.iter().any(|ep| ep.selector == selector)
+                    || entry_points.first().is_some_and(|ep| ep.selector == default_selector)
+            }
+            ContractClass::V1(class) => class
+                .entry_points_by_type
+                .get(&entry_point_type)
+                .is_some_and(|entry_points| entry_points.iter().any(|ep| ep.selector == selector)),
+        }
+    }
 }
 
 // V0.
@@ -251,6 +279,23 @@ pub fn estimate_casm_hash_computation_resources(
     }
 }
 
+/// Verifies that the given CASM class is the one committed to by the given compiled class hash;
+/// e.g., the compiled class hash declared along with it by a Declare V2 transaction (or above).
+pub fn verify_compiled_class_hash(
+    casm_contract_class: &CasmContractClass,
+    compiled_class_hash: CompiledClassHash,
+) -> ContractClassResult<()> {
+    let actual = CompiledClassHash(felt_to_stark_felt(&casm_contract_class.compiled_class_hash()));
+    if actual != compiled_class_hash {
+        return Err(ContractClassError::CompiledClassHashMismatch {
+            declared: compiled_class_hash,
+            actual,
+        });
+    }
+
+    Ok(())
+}
+
 #[derive(Clone, Debug, Eq, PartialEq)]
 pub struct ContractClassV1Inner {
     pub program: Program,
@@ -0,0 +1,26 @@
+use assert_matches::assert_matches;
+use cairo_lang_starknet_classes::casm_contract_class::CasmContractClass;
+use starknet_api::core::CompiledClassHash;
+use starknet_api::hash::StarkFelt;
+
+use crate::execution::contract_class::verify_compiled_class_hash;
+use crate::execution::errors::ContractClassError;
+use crate::execution::execution_utils::felt_to_stark_felt;
+use crate::test_utils::contracts::FeatureContract;
+use crate::test_utils::CairoVersion;
+
+#[test]
+fn test_verify_compiled_class_hash() {
+    let raw_class = FeatureContract::TestContract(CairoVersion::Cairo1).get_raw_class();
+    let casm_contract_class: CasmContractClass = serde_json::from_str(&raw_class).unwrap();
+    let compiled_class_hash =
+        CompiledClassHash(felt_to_stark_felt(&casm_contract_class.compiled_class_hash()));
+    verify_compiled_class_hash(&casm_contract_class, compiled_class_hash).unwrap();
+
+    let declared = CompiledClassHash(StarkFelt::ONE);
+    assert_matches!(
+        verify_compiled_class_hash(&casm_contract_class, declared).unwrap_err(),
+        ContractClassError::CompiledClassHashMismatch { declared: mismatch, actual }
+        if mismatch == declared && actual == compiled_class_hash
+    );
+}
@@ -0,0 +1,86 @@
+use std::any::Any;
+use std::collections::HashMap;
+use std::fmt;
+use std::rc::Rc;
+
+use cairo_vm::hint_processor::builtin_hint_processor::builtin_hint_processor_definition::{
+    HintFunc, HintProcessorData,
+};
+use cairo_vm::hint_processor::hint_processor_definition::HintProcessorLogic;
+use cairo_vm::types::exec_scope::ExecutionScopes;
+use cairo_vm::vm::errors::hint_errors::HintError;
+use cairo_vm::vm::vm_core::VirtualMachine;
+use thiserror::Error;
+
+use crate::execution::common_hints::extended_builtin_hint_processor;
+use crate::execution::hint_code::SYSCALL_HINTS;
+
+#[cfg(test)]
+#[path = "custom_hints_test.rs"]
+pub mod test;
+
+#[derive(Debug, Error)]
+pub enum CustomHintError {
+    #[error("Custom hint is already registered: {hint_code:?}.")]
+    AlreadyRegistered { hint_code: String },
+    #[error("Custom hint collides with a built-in hint: {hint_code:?}.")]
+    CollidesWithBuiltinHint { hint_code: String },
+}
+
+/// Application-specific hints to support in Cairo 0 classes, on top of the built-in hints.
+/// Only used if allowed by the block context, in which case the registered hints are consulted
+/// before the built-in ones.
+#[derive(Clone, Default)]
+pub struct CustomHintRegistry {
+    hints: HashMap<String, Rc<HintFunc>>,
+}
+
+impl CustomHintRegistry {
+    /// Registers the given executor for the given hint code; hints may not override built-in
+    /// (including syscall) hints.
+    pub fn register(&mut self, hint_code: &str, executor: HintFunc) -> Result<(), CustomHintError> {
+        if self.hints.contains_key(hint_code) {
+            return Err(CustomHintError::AlreadyRegistered { hint_code: hint_code.to_string() });
+        }
+        if is_builtin_hint(hint_code) {
+            return Err(CustomHintError::CollidesWithBuiltinHint {
+                hint_code: hint_code.to_string(),
+            });
+        }
+
+        self.hints.insert(hint_code.to_string(), Rc::new(executor));
+        Ok(())
+    }
+
+    pub fn hints(&self) -> &HashMap<String, Rc<HintFunc>> {
+        &self.hints
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.hints.is_empty()
+    }
+}
+
+impl fmt::Debug for CustomHintRegistry {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("CustomHintRegistry").field("hints", &self.hints.keys()).finish()
+    }
+}
+
+fn is_builtin_hint(hint_code: &str) -> bool {
+    if SYSCALL_HINTS.contains(hint_code) {
+        return true;
+    }
+
+    // The built-in hint processor identifies the hint by its code before accessing the VM, so
+    // running it on an empty VM tells whether the code is known.
+    let hint_data: Box<dyn Any> =
+        Box::new(HintProcessorData::new_default(hint_code.to_string(), HashMap::new()));
+    let result = extended_builtin_hint_processor().execute_hint(
+        &mut VirtualMachine::new(false),
+        &mut ExecutionScopes::new(),
+        &hint_data,
+        &HashMap::new(),
+    );
+    !matches!(result, Err(HintError::UnknownHint(_)))
+}
@@ -0,0 +1,108 @@
+use std::rc::Rc;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::Arc;
+
+use assert_matches::assert_matches;
+use cairo_vm::hint_processor::builtin_hint_processor::builtin_hint_processor_definition::HintFunc;
+use cairo_vm::hint_processor::builtin_hint_processor::hint_code::ADD_SEGMENT;
+use cairo_vm::vm::runners::cairo_runner::ExecutionResources;
+use rstest::rstest;
+use serde_json::json;
+use starknet_api::class_hash;
+use starknet_api::core::ClassHash;
+use starknet_api::hash::StarkHash;
+
+use crate::abi::abi_utils::selector_from_name;
+use crate::context::BlockContext;
+use crate::execution::contract_class::ContractClassV0;
+use crate::execution::custom_hints::{CustomHintError, CustomHintRegistry};
+use crate::execution::entry_point::{CallEntryPoint, EntryPointExecutionContext};
+use crate::state::state_api::State;
+use crate::test_utils::cached_state::deprecated_create_test_state;
+use crate::test_utils::{
+    get_raw_contract_class, trivial_external_entry_point, TEST_CLASS_HASH,
+    TEST_CONTRACT_CAIRO0_PATH,
+};
+
+const CUSTOM_HINT: &str = "custom_hint_for_testing()";
+
+/// Returns the Cairo 0 test contract, with the custom hint added to the `without_arg` function.
+fn test_contract_with_custom_hint() -> ContractClassV0 {
+    let mut raw_class: serde_json::Value =
+        serde_json::from_str(&get_raw_contract_class(TEST_CONTRACT_CAIRO0_PATH)).unwrap();
+    let program = &mut raw_class["program"];
+    let pc = program["identifiers"]["__main__.without_arg"]["pc"].as_u64().unwrap();
+    program["hints"][pc.to_string()] = json!([{
+        "accessible_scopes": ["__main__", "__main__.without_arg"],
+        "code": CUSTOM_HINT,
+        "flow_tracking_data": {
+            "ap_tracking": {"group": 0, "offset": 0},
+            "reference_ids": {}
+        }
+    }]);
+
+    ContractClassV0::try_from_json_string(&raw_class.to_string()).unwrap()
+}
+
+#[rstest]
+fn test_custom_hint_execution(#[values(true, false)] allow_custom_hints: bool) {
+    let mut state = deprecated_create_test_state();
+    state
+        .set_contract_class(class_hash!(TEST_CLASS_HASH), test_contract_with_custom_hint().into())
+        .unwrap();
+
+    let executed = Arc::new(AtomicBool::new(false));
+    let executed_by_hint = executed.clone();
+    let mut registry = CustomHintRegistry::default();
+    registry
+        .register(
+            CUSTOM_HINT,
+            HintFunc(Box::new(move |_, _, _, _, _| {
+                executed_by_hint.store(true, Ordering::Relaxed);
+                Ok(())
+            })),
+        )
+        .unwrap();
+
+    let mut block_context = BlockContext::create_for_testing();
+    block_context.set_allow_custom_hints(allow_custom_hints);
+    let mut context = EntryPointExecutionContext::new_invoke_for_testing(block_context);
+    context.custom_hint_registry = Rc::new(registry);
+
+    let entry_point_call = CallEntryPoint {
+        entry_point_selector: selector_from_name("without_arg"),
+        ..trivial_external_entry_point()
+    };
+    let result =
+        entry_point_call.execute(&mut state, &mut ExecutionResources::default(), &mut context);
+
+    if allow_custom_hints {
+        result.unwrap();
+    } else {
+        assert!(result.unwrap_err().to_string().contains(CUSTOM_HINT));
+    }
+    assert_eq!(executed.load(Ordering::Relaxed), allow_custom_hints);
+}
+
+#[rstest]
+#[case::builtin_hint(ADD_SEGMENT)]
+#[case::syscall_hint(
+    "syscall_handler.storage_read(segments=segments, syscall_ptr=ids.syscall_ptr)"
+)]
+#[case::common_hint(crate::execution::hint_code::NORMALIZE_ADDRESS_SET_IS_250_HINT)]
+fn test_custom_hint_collision(#[case] hint_code: &str) {
+    let mut registry = CustomHintRegistry::default();
+    let error =
+        registry.register(hint_code, HintFunc(Box::new(|_, _, _, _, _| Ok(())))).unwrap_err();
+    assert_matches!(error, CustomHintError::CollidesWithBuiltinHint { .. });
+    assert!(registry.is_empty());
+}
+
+#[test]
+fn test_custom_hint_registered_twice() {
+    let mut registry = CustomHintRegistry::default();
+    registry.register(CUSTOM_HINT, HintFunc(Box::new(|_, _, _, _, _| Ok(())))).unwrap();
+    let error =
+        registry.register(CUSTOM_HINT, HintFunc(Box::new(|_, _, _, _, _| Ok(())))).unwrap_err();
+    assert_matches!(error, CustomHintError::AlreadyRegistered { .. });
+}
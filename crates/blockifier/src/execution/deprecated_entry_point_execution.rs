@@ -11,8 +11,9 @@ use crate::abi::constants::{CONSTRUCTOR_ENTRY_POINT_NAME, DEFAULT_ENTRY_POINT_SE
 use crate::execution::call_info::{CallExecution, CallInfo};
 use crate::execution::contract_class::ContractClassV0;
 use crate::execution::deprecated_syscalls::hint_processor::DeprecatedSyscallHintProcessor;
+use crate::execution::deprecated_syscalls::TxInfoLayout;
 use crate::execution::entry_point::{
-    CallEntryPoint, EntryPointExecutionContext, EntryPointExecutionResult,
+    CallContext, CallEntryPoint, EntryPointExecutionContext, EntryPointExecutionResult,
 };
 use crate::execution::errors::{PostExecutionError, PreExecutionError};
 use crate::execution::execution_utils::{
@@ -91,14 +92,14 @@ pub fn initialize_execution_context<'a>(
 
     // Instantiate syscall handler.
     let initial_syscall_ptr = vm.add_memory_segment();
-    let syscall_handler = DeprecatedSyscallHintProcessor::new(
+    let mut syscall_handler = DeprecatedSyscallHintProcessor::new(
         state,
         resources,
         context,
         initial_syscall_ptr,
-        call.storage_address,
-        call.caller_address,
+        CallContext::from(call),
     );
+    syscall_handler.tx_info_layout = TxInfoLayout::from_program(&contract_class.program);
 
     Ok(VmExecutionContext { runner, vm, syscall_handler, initial_syscall_ptr, entry_point_pc })
 }
@@ -244,19 +245,21 @@ pub fn finalize_execution(
         .get_additional_os_syscall_resources(&syscall_handler.syscall_counter)?;
 
     let full_call_resources = &*syscall_handler.resources - &previous_resources;
+    let retdata = read_execution_retdata(&vm, retdata_size, &retdata_ptr)?;
+    let outputs = syscall_handler.finalize()?;
     Ok(CallInfo {
         call,
         execution: CallExecution {
-            retdata: read_execution_retdata(&vm, retdata_size, &retdata_ptr)?,
-            events: syscall_handler.events,
-            l2_to_l1_messages: syscall_handler.l2_to_l1_messages,
+            retdata,
+            events: outputs.events,
+            l2_to_l1_messages: outputs.l2_to_l1_messages,
             failed: false,
             gas_consumed: 0,
         },
         resources: full_call_resources.filter_unused_builtins(),
-        inner_calls: syscall_handler.inner_calls,
-        storage_read_values: syscall_handler.read_values,
-        accessed_storage_keys: syscall_handler.accessed_keys,
+        inner_calls: outputs.inner_calls,
+        storage_read_values: outputs.storage_read_values,
+        accessed_storage_keys: outputs.accessed_storage_keys,
     })
 }
 
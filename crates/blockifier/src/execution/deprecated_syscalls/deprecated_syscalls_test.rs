@@ -1,49 +1,80 @@
-use std::collections::{HashMap, HashSet};
+use std::collections::{BTreeMap, HashMap, HashSet};
+use std::rc::Rc;
+use std::sync::Arc;
 
+use assert_matches::assert_matches;
 use cairo_felt::Felt252;
+use cairo_vm::hint_processor::hint_processor_definition::HintReference;
+use cairo_vm::serde::deserialize_program::ApTracking;
+use cairo_vm::types::relocatable::{MaybeRelocatable, Relocatable};
 use cairo_vm::vm::runners::builtin_runner::RANGE_CHECK_BUILTIN_NAME;
 use cairo_vm::vm::runners::cairo_runner::ExecutionResources;
+use cairo_vm::vm::vm_core::VirtualMachine;
 use itertools::concat;
 use num_traits::Pow;
 use pretty_assertions::assert_eq;
 use rstest::rstest;
 use starknet_api::core::{
-    calculate_contract_address, ChainId, ClassHash, ContractAddress, Nonce, PatriciaKey,
+    calculate_contract_address, ChainId, ClassHash, ContractAddress, EntryPointSelector, Nonce,
+    PatriciaKey,
 };
+use starknet_api::data_availability::DataAvailabilityMode;
 use starknet_api::hash::{StarkFelt, StarkHash};
 use starknet_api::state::StorageKey;
 use starknet_api::transaction::{
-    Calldata, ContractAddressSalt, EventContent, EventData, EventKey, Fee, TransactionHash,
+    AccountDeploymentData, Calldata, ContractAddressSalt, EventContent, EventData, EventKey, Fee,
+    PaymasterData, Resource, ResourceBounds, ResourceBoundsMapping, Tip, TransactionHash,
     TransactionVersion,
 };
 use starknet_api::{calldata, class_hash, contract_address, patricia_key, stark_felt};
 use test_case::test_case;
 
 use crate::abi::abi_utils::selector_from_name;
-use crate::context::ChainInfo;
+use crate::context::{BlockContext, ChainInfo, TransactionContext};
 use crate::execution::call_info::{CallExecution, CallInfo, OrderedEvent, Retdata};
 use crate::execution::common_hints::ExecutionMode;
-use crate::execution::entry_point::{CallEntryPoint, CallType};
-use crate::execution::errors::EntryPointExecutionError;
-use crate::execution::execution_utils::felt_to_stark_felt;
-use crate::execution::syscalls::hint_processor::EmitEventError;
-use crate::state::state_api::StateReader;
+use crate::execution::contract_class::ContractClassV0;
+use crate::execution::deprecated_syscalls::hint_processor::{
+    write_segment, DeprecatedSyscallExecutionError, DeprecatedSyscallHintProcessor, SyscallCounter,
+    SyscallOverrides,
+};
+use crate::execution::deprecated_syscalls::{
+    delegate_l1_handler, deploy, get_caller_address, get_contract_address, get_tx_info,
+    library_call_l1_handler, storage_read, storage_write, DelegateCallRequest, DeployRequest,
+    DeprecatedSyscallSelector, EmptyRequest, LibraryCallRequest, SendMessageToL1Request,
+    StorageReadRequest, StorageWriteRequest, SyscallRequest, SyscallResponse, TxInfoLayout,
+};
+use crate::execution::entry_point::{
+    CallContext, CallEntryPoint, CallType, EntryPointExecutionContext, EntryPointExecutionResult,
+};
+use crate::execution::errors::{
+    EntryPointExecutionError, PostExecutionError, PreExecutionError,
+};
+use crate::execution::execution_utils::{
+    felt_range_from_ptr, felt_to_stark_felt, stark_felt_to_felt, ReadOnlySegment,
+};
+use crate::execution::syscalls::hint_processor::{
+    ArrayLengthError, EmitEventError, SyscallReadContext, SyscallUsage, L1_GAS, L2_GAS,
+};
+use crate::state::state_api::{State, StateReader};
 use crate::test_utils::cached_state::{
     deprecated_create_deploy_test_state, deprecated_create_test_state,
 };
 use crate::test_utils::contracts::FeatureContract;
 use crate::test_utils::initial_test_state::test_state;
 use crate::test_utils::{
-    trivial_external_entry_point, trivial_external_entry_point_with_address, CairoVersion,
-    CHAIN_ID_NAME, CURRENT_BLOCK_NUMBER, CURRENT_BLOCK_NUMBER_FOR_VALIDATE,
-    CURRENT_BLOCK_TIMESTAMP, CURRENT_BLOCK_TIMESTAMP_FOR_VALIDATE, TEST_CLASS_HASH,
-    TEST_CONTRACT_ADDRESS, TEST_EMPTY_CONTRACT_CLASS_HASH, TEST_SEQUENCER_ADDRESS,
+    get_raw_contract_class, trivial_external_entry_point,
+    trivial_external_entry_point_with_address, CairoVersion, CHAIN_ID_NAME, CURRENT_BLOCK_NUMBER,
+    CURRENT_BLOCK_NUMBER_FOR_VALIDATE, CURRENT_BLOCK_TIMESTAMP,
+    CURRENT_BLOCK_TIMESTAMP_FOR_VALIDATE, TEST_CLASS_HASH, TEST_CONTRACT_ADDRESS,
+    TEST_CONTRACT_ADDRESS_2, TEST_CONTRACT_CAIRO0_PATH, TEST_EMPTY_CONTRACT_CLASS_HASH,
+    TEST_SEQUENCER_ADDRESS,
 };
 use crate::transaction::constants::QUERY_VERSION_BASE_BIT;
 use crate::transaction::objects::{
-    CommonAccountFields, DeprecatedTransactionInfo, TransactionInfo,
+    CommonAccountFields, CurrentTransactionInfo, DeprecatedTransactionInfo, TransactionInfo,
 };
-use crate::versioned_constants::VersionedConstants;
+use crate::versioned_constants::{SyscallResponseLayout, VersionedConstants};
 use crate::{check_entry_point_execution_error_for_custom_hint, retdata};
 
 #[test]
@@ -261,6 +292,210 @@ fn test_call_contract() {
     assert_eq!(expected_call_info, call_info);
 }
 
+#[test]
+fn test_call_contract_of_undeployed_address() {
+    let chain_info = &ChainInfo::create_for_testing();
+    let test_contract = FeatureContract::TestContract(CairoVersion::Cairo0);
+    let mut state = test_state(chain_info, 0, &[(test_contract, 1)]);
+    let test_address = test_contract.get_instance_address(0);
+
+    let calldata = calldata![
+        stark_felt!(1234_u16),                           // Contract address.
+        selector_from_name("test_storage_read_write").0, // Function selector.
+        stark_felt!(2_u8),                               // Calldata length.
+        stark_felt!(405_u16),                            // Calldata: address.
+        stark_felt!(48_u8)                               // Calldata: value.
+    ];
+    let entry_point_call = CallEntryPoint {
+        entry_point_selector: selector_from_name("test_call_contract"),
+        calldata,
+        ..trivial_external_entry_point_with_address(test_address)
+    };
+    let error = entry_point_call.execute_directly(&mut state).unwrap_err().to_string();
+    assert!(error.contains("is not deployed"));
+}
+
+fn execute_with_syscall_overrides(
+    entry_point_call: CallEntryPoint,
+    state: &mut dyn State,
+    syscall_overrides: SyscallOverrides,
+) -> EntryPointExecutionResult<CallInfo> {
+    let mut context =
+        EntryPointExecutionContext::new_invoke_for_testing(BlockContext::create_for_testing());
+    context.syscall_overrides = Rc::new(syscall_overrides);
+    entry_point_call.execute(state, &mut ExecutionResources::default(), &mut context)
+}
+
+#[test]
+fn test_get_block_number_override() {
+    let mut state = deprecated_create_test_state();
+    let overridden_block_number = stark_felt!(7777_u16);
+    let mut syscall_overrides = SyscallOverrides::default();
+    syscall_overrides
+        .override_syscall(DeprecatedSyscallSelector::GetBlockNumber, move |_| {
+            Ok(vec![overridden_block_number])
+        })
+        .unwrap();
+
+    let entry_point_call = CallEntryPoint {
+        entry_point_selector: selector_from_name("test_get_block_number"),
+        calldata: calldata![overridden_block_number],
+        ..trivial_external_entry_point()
+    };
+    let call_info =
+        execute_with_syscall_overrides(entry_point_call.clone(), &mut state, syscall_overrides)
+            .unwrap();
+
+    // The overridden syscall is charged like the built-in one.
+    let entry_point_call = CallEntryPoint {
+        calldata: calldata![stark_felt!(CURRENT_BLOCK_NUMBER)],
+        ..entry_point_call
+    };
+    let expected_call_info = entry_point_call.execute_directly(&mut state).unwrap();
+    assert_eq!(call_info.resources, expected_call_info.resources);
+}
+
+#[test]
+fn test_invalid_syscall_override_response() {
+    let mut state = deprecated_create_test_state();
+    let mut syscall_overrides = SyscallOverrides::default();
+    syscall_overrides
+        .override_syscall(DeprecatedSyscallSelector::GetBlockNumber, |_| {
+            Ok(vec![StarkFelt::ONE, StarkFelt::TWO])
+        })
+        .unwrap();
+
+    let entry_point_call = CallEntryPoint {
+        entry_point_selector: selector_from_name("test_get_block_number"),
+        calldata: calldata![StarkFelt::ONE],
+        ..trivial_external_entry_point()
+    };
+    let error = execute_with_syscall_overrides(entry_point_call, &mut state, syscall_overrides)
+        .unwrap_err();
+    check_entry_point_execution_error_for_custom_hint!(
+        &error,
+        "The override of the GetBlockNumber syscall responded with 2 cells; expected 1.",
+    );
+}
+
+#[test_case(DeprecatedSyscallSelector::CallContract; "CallContract")]
+#[test_case(DeprecatedSyscallSelector::Deploy; "Deploy")]
+#[test_case(DeprecatedSyscallSelector::GetTxInfo; "GetTxInfo")]
+#[test_case(DeprecatedSyscallSelector::GetTxSignature; "GetTxSignature")]
+#[test_case(DeprecatedSyscallSelector::Keccak; "Cairo 1 only")]
+fn test_unsupported_syscall_override(selector: DeprecatedSyscallSelector) {
+    let mut syscall_overrides = SyscallOverrides::default();
+    let error = syscall_overrides.override_syscall(selector, |_| Ok(vec![])).unwrap_err();
+    assert_matches!(
+        error,
+        DeprecatedSyscallExecutionError::UnsupportedSyscallOverride { selector: unsupported }
+        if unsupported == selector
+    );
+    assert!(syscall_overrides.is_empty());
+}
+
+#[test_case(1234_u16, retdata![stark_felt!(5_u8), stark_felt!(6_u8)]; "Overridden contract.")]
+#[test_case(4321_u16, retdata![stark_felt!(7_u8)]; "Other contract.")]
+fn test_call_contract_override(called_address: u16, expected_retdata: Retdata) {
+    let chain_info = &ChainInfo::create_for_testing();
+    let test_contract = FeatureContract::TestContract(CairoVersion::Cairo0);
+    let mut state = test_state(chain_info, 0, &[(test_contract, 1)]);
+    let test_address = test_contract.get_instance_address(0);
+
+    // Neither of the called contracts is deployed.
+    let mut syscall_overrides = SyscallOverrides::default();
+    syscall_overrides.override_call_contract(Some(contract_address!(1234_u16)), |_| {
+        Ok(retdata![stark_felt!(5_u8), stark_felt!(6_u8)])
+    });
+    syscall_overrides.override_call_contract(None, |_| Ok(retdata![stark_felt!(7_u8)]));
+
+    let inner_entry_point_selector = selector_from_name("test_storage_read_write");
+    let inner_calldata = calldata![stark_felt!(405_u16), stark_felt!(48_u8)];
+    let calldata = calldata![
+        stark_felt!(called_address),  // Contract address.
+        inner_entry_point_selector.0, // Function selector.
+        stark_felt!(2_u8),            // Calldata length.
+        inner_calldata.0[0],          // Calldata: address.
+        inner_calldata.0[1]           // Calldata: value.
+    ];
+    let entry_point_call = CallEntryPoint {
+        entry_point_selector: selector_from_name("test_call_contract"),
+        calldata,
+        ..trivial_external_entry_point_with_address(test_address)
+    };
+    let call_info =
+        execute_with_syscall_overrides(entry_point_call, &mut state, syscall_overrides).unwrap();
+
+    assert_eq!(call_info.execution.retdata, expected_retdata);
+    let called_address = contract_address!(called_address);
+    let expected_inner_call_info = CallInfo {
+        call: CallEntryPoint {
+            class_hash: Some(ClassHash::default()),
+            code_address: Some(called_address),
+            entry_point_selector: inner_entry_point_selector,
+            calldata: inner_calldata,
+            storage_address: called_address,
+            caller_address: test_address,
+            ..trivial_external_entry_point()
+        },
+        execution: CallExecution { retdata: expected_retdata, ..Default::default() },
+        ..Default::default()
+    };
+    assert_eq!(call_info.inner_calls, vec![expected_inner_call_info]);
+    assert_eq!(call_info.storage_read_values, vec![]);
+}
+
+#[test_case(true; "delegate_l1_handler")]
+#[test_case(false; "library_call_l1_handler")]
+fn test_l1_handler_call_to_external_entry_point(delegate: bool) {
+    let mut state = deprecated_create_test_state();
+    let mut resources = ExecutionResources::default();
+    let mut context =
+        EntryPointExecutionContext::new_invoke_for_testing(BlockContext::create_for_testing());
+    let mut syscall_handler = DeprecatedSyscallHintProcessor::new(
+        &mut state,
+        &mut resources,
+        &mut context,
+        Relocatable::from((0, 0)),
+        CallContext {
+            storage_address: contract_address!(TEST_CONTRACT_ADDRESS),
+            ..Default::default()
+        },
+    );
+
+    // The test contract has no L1 handlers.
+    let function_selector = selector_from_name("without_arg");
+    let mut vm = VirtualMachine::new(false);
+    let error = if delegate {
+        let request = DelegateCallRequest {
+            contract_address: contract_address!(TEST_CONTRACT_ADDRESS),
+            function_selector,
+            calldata: calldata![],
+        };
+        delegate_l1_handler(request, &mut vm, &mut syscall_handler).unwrap_err()
+    } else {
+        let request = LibraryCallRequest {
+            class_hash: class_hash!(TEST_CLASS_HASH),
+            function_selector,
+            calldata: calldata![],
+        };
+        library_call_l1_handler(request, &mut vm, &mut syscall_handler).unwrap_err()
+    };
+
+    assert_matches!(
+        error,
+        DeprecatedSyscallExecutionError::LibraryCallExecutionError { class_hash, error, .. }
+        if class_hash == class_hash!(TEST_CLASS_HASH) && matches!(
+            *error,
+            DeprecatedSyscallExecutionError::EntryPointExecutionError(
+                EntryPointExecutionError::PreExecutionError(
+                    PreExecutionError::EntryPointNotFound(selector)
+                )
+            ) if selector == function_selector
+        )
+    );
+}
+
 #[test]
 fn test_replace_class() {
     // Negative flow.
@@ -406,6 +641,308 @@ fn test_deploy(
     assert_eq!(state.get_class_hash_at(contract_address).unwrap(), class_hash);
 }
 
+#[test_case(SyscallResponseLayout::Legacy; "Legacy layout.")]
+#[test_case(SyscallResponseLayout::OsAligned; "OS-aligned layout.")]
+fn test_deploy_response_layout(layout: SyscallResponseLayout) {
+    let mut state = deprecated_create_deploy_test_state();
+    let mut resources = ExecutionResources::default();
+    let mut context =
+        EntryPointExecutionContext::new_invoke_for_testing(BlockContext::create_for_testing());
+    context.syscall_response_layout = layout;
+    let mut syscall_handler = DeprecatedSyscallHintProcessor::new(
+        &mut state,
+        &mut resources,
+        &mut context,
+        Relocatable::from((0, 0)),
+        CallContext {
+            storage_address: contract_address!(TEST_CONTRACT_ADDRESS),
+            ..Default::default()
+        },
+    );
+
+    let mut vm = VirtualMachine::new(false);
+    let request = DeployRequest {
+        class_hash: class_hash!(TEST_EMPTY_CONTRACT_CLASS_HASH),
+        contract_address_salt: ContractAddressSalt::default(),
+        constructor_calldata: calldata![],
+        deploy_from_zero: false,
+    };
+    let response = deploy(request, &mut vm, &mut syscall_handler).unwrap();
+    let contract_address = response.contract_address;
+    let response_ptr = vm.add_memory_segment();
+    response.write(&mut vm, &mut response_ptr.clone()).unwrap();
+
+    let cells = vm.get_continuous_range(response_ptr, 3).unwrap();
+    assert_eq!(cells[0], stark_felt_to_felt(*contract_address.0.key()).into());
+    // The constructor has no retdata; only the pointer to it differs between the layouts.
+    assert_eq!(cells[1], Felt252::from(0_u8).into());
+    match layout {
+        SyscallResponseLayout::Legacy => assert_eq!(cells[2], Felt252::from(0_u8).into()),
+        SyscallResponseLayout::OsAligned => {
+            assert_matches!(cells[2], MaybeRelocatable::RelocatableValue(_))
+        }
+    }
+}
+
+#[test_case(vec![]; "Empty array.")]
+#[test_case(vec![stark_felt!(1_u8), stark_felt!(2_u8)]; "Non-empty array.")]
+fn test_write_felt_array(data: Vec<StarkFelt>) {
+    let mut state = deprecated_create_test_state();
+    let mut resources = ExecutionResources::default();
+    let mut context =
+        EntryPointExecutionContext::new_invoke_for_testing(BlockContext::create_for_testing());
+    let mut syscall_handler = DeprecatedSyscallHintProcessor::new(
+        &mut state,
+        &mut resources,
+        &mut context,
+        Relocatable::from((0, 0)),
+        CallContext {
+            storage_address: contract_address!(TEST_CONTRACT_ADDRESS),
+            ..Default::default()
+        },
+    );
+
+    let mut vm = VirtualMachine::new(false);
+    let segment = syscall_handler.write_felt_array(&mut vm, &data).unwrap();
+    let start_ptr = segment.start_ptr;
+    assert_eq!(segment.length, data.len());
+    assert_eq!(
+        syscall_handler.read_only_segments.segments(),
+        [ReadOnlySegment { start_ptr, length: data.len() }]
+    );
+    let felts = felt_range_from_ptr(&vm, start_ptr, data.len()).unwrap();
+    assert_eq!(felts, data);
+
+    // The segment is written as a (length, pointer) pair.
+    let response_ptr = vm.add_memory_segment();
+    write_segment(&mut vm, &mut response_ptr.clone(), segment).unwrap();
+    let cells = vm.get_continuous_range(response_ptr, 2).unwrap();
+    assert_eq!(cells, [Felt252::from(data.len()).into(), start_ptr.into()]);
+}
+
+#[test]
+fn test_call_context() {
+    let mut state = deprecated_create_test_state();
+    let mut resources = ExecutionResources::default();
+    let mut context =
+        EntryPointExecutionContext::new_invoke_for_testing(BlockContext::create_for_testing());
+    let call = CallEntryPoint {
+        class_hash: Some(class_hash!(TEST_CLASS_HASH)),
+        entry_point_selector: selector_from_name("without_arg"),
+        caller_address: contract_address!(TEST_CONTRACT_ADDRESS_2),
+        ..trivial_external_entry_point()
+    };
+    let mut syscall_handler = DeprecatedSyscallHintProcessor::new(
+        &mut state,
+        &mut resources,
+        &mut context,
+        Relocatable::from((0, 0)),
+        CallContext::from(&call),
+    );
+
+    assert_eq!(syscall_handler.entry_point_selector(), call.entry_point_selector);
+    assert_eq!(syscall_handler.class_hash(), class_hash!(TEST_CLASS_HASH));
+    let mut vm = VirtualMachine::new(false);
+    let response = get_contract_address(EmptyRequest, &mut vm, &mut syscall_handler).unwrap();
+    assert_eq!(response.address, call.storage_address);
+    let response = get_caller_address(EmptyRequest, &mut vm, &mut syscall_handler).unwrap();
+    assert_eq!(response.address, call.caller_address);
+}
+
+/// Dispatches every supported syscall once, checking that each advances the syscall pointer by
+/// exactly the sizes of its request and response.
+#[test]
+fn test_syscall_sizes() {
+    let chain_info = ChainInfo::create_for_testing();
+    let test_contract = FeatureContract::TestContract(CairoVersion::Cairo0);
+    // Used for the L1 handler syscalls; the Cairo 0 test contract has no L1 handlers.
+    let cairo1_test_contract = FeatureContract::TestContract(CairoVersion::Cairo1);
+    let mut state = test_state(&chain_info, 0, &[(test_contract, 1), (cairo1_test_contract, 1)]);
+    let mut resources = ExecutionResources::default();
+    let mut context =
+        EntryPointExecutionContext::new_invoke_for_testing(BlockContext::create_for_testing());
+    context.validate_syscall_sizes = true;
+
+    let mut vm = VirtualMachine::new(false);
+    // The syscall pointer is passed to the syscall hint through the frame (segment 1).
+    vm.add_memory_segment();
+    let frame_ptr = vm.add_memory_segment();
+    let syscall_ptr = vm.add_memory_segment();
+    let array_ptr = vm.add_memory_segment();
+    vm.load_data(array_ptr, &vec![Felt252::from(1_u8).into(), Felt252::from(2_u8).into()]).unwrap();
+    let ids_data = HashMap::from([("syscall_ptr".to_string(), HintReference::new_simple(0))]);
+
+    let felt = |value: StarkFelt| MaybeRelocatable::from(stark_felt_to_felt(value));
+    let address = |contract: FeatureContract| felt(*contract.get_instance_address(0).0.key());
+    let class_hash = |contract: FeatureContract| felt(contract.get_class_hash().0);
+    let selector = |name: &str| felt(selector_from_name(name).0);
+    let array = |length: u8| vec![Felt252::from(length).into(), array_ptr.into()];
+    let syscalls = [
+        (
+            DeprecatedSyscallSelector::CallContract,
+            concat([vec![address(test_contract), selector("without_arg")], array(0)]),
+        ),
+        (
+            DeprecatedSyscallSelector::DelegateCall,
+            concat([vec![address(test_contract), selector("without_arg")], array(0)]),
+        ),
+        (
+            DeprecatedSyscallSelector::DelegateL1Handler,
+            concat([vec![address(cairo1_test_contract), selector("l1_handle")], array(2)]),
+        ),
+        (
+            DeprecatedSyscallSelector::Deploy,
+            concat([
+                vec![class_hash(test_contract), Felt252::from(7_u8).into()],
+                array(2),
+                vec![Felt252::from(0_u8).into()],
+            ]),
+        ),
+        (DeprecatedSyscallSelector::EmitEvent, concat([array(1), array(2)])),
+        (DeprecatedSyscallSelector::GetBlockNumber, vec![]),
+        (DeprecatedSyscallSelector::GetBlockTimestamp, vec![]),
+        (DeprecatedSyscallSelector::GetCallerAddress, vec![]),
+        (DeprecatedSyscallSelector::GetContractAddress, vec![]),
+        (DeprecatedSyscallSelector::GetSequencerAddress, vec![]),
+        (DeprecatedSyscallSelector::GetTxInfo, vec![]),
+        (DeprecatedSyscallSelector::GetTxSignature, vec![]),
+        (
+            DeprecatedSyscallSelector::LibraryCall,
+            concat([vec![class_hash(test_contract), selector("without_arg")], array(0)]),
+        ),
+        (
+            DeprecatedSyscallSelector::LibraryCallL1Handler,
+            concat([vec![class_hash(cairo1_test_contract), selector("l1_handle")], array(2)]),
+        ),
+        (DeprecatedSyscallSelector::ReplaceClass, vec![class_hash(test_contract)]),
+        (
+            DeprecatedSyscallSelector::SendMessageToL1,
+            concat([vec![Felt252::from(1_u8).into()], array(2)]),
+        ),
+        (DeprecatedSyscallSelector::StorageRead, vec![Felt252::from(1_u8).into()]),
+        (
+            DeprecatedSyscallSelector::StorageWrite,
+            vec![Felt252::from(1_u8).into(), Felt252::from(2_u8).into()],
+        ),
+    ];
+
+    let mut syscall_handler = DeprecatedSyscallHintProcessor::new(
+        &mut state,
+        &mut resources,
+        &mut context,
+        syscall_ptr,
+        CallContext {
+            storage_address: test_contract.get_instance_address(0),
+            ..Default::default()
+        },
+    );
+    for (fp, (selector, request)) in syscalls.iter().enumerate() {
+        let raw_selector = Felt252::from_bytes_be(format!("{selector:?}").as_bytes());
+        let syscall_ptr = syscall_handler.syscall_ptr;
+        vm.load_data(syscall_ptr, &concat([vec![raw_selector.into()], request.clone()])).unwrap();
+        vm.set_fp(fp);
+        vm.load_data((frame_ptr + fp).unwrap(), &vec![syscall_ptr.into()]).unwrap();
+        syscall_handler
+            .execute_next_syscall(&mut vm, &ids_data, &ApTracking::default())
+            .unwrap_or_else(|error| panic!("{selector:?} syscall failed: {error}"));
+    }
+
+    // Event data and message payloads are also counted by their length.
+    let linear_factor = |selector| match selector {
+        DeprecatedSyscallSelector::EmitEvent | DeprecatedSyscallSelector::SendMessageToL1 => 2,
+        _ => 0,
+    };
+    let expected_syscall_counter: SyscallCounter = syscalls
+        .iter()
+        .map(|(selector, _)| {
+            (*selector, SyscallUsage { call_count: 1, linear_factor: linear_factor(*selector) })
+        })
+        .collect();
+    assert_eq!(syscall_handler.syscall_counter, expected_syscall_counter);
+}
+
+#[test_case(true; "Write after read.")]
+#[test_case(false; "Write to a fresh key.")]
+fn test_storage_write_read_values(read_before_write: bool) {
+    let key = StorageKey(patricia_key!(1234_u16));
+    let (initial_value, new_value) = (stark_felt!(7_u8), stark_felt!(18_u8));
+    let mut state = deprecated_create_test_state();
+    let contract_address = contract_address!(TEST_CONTRACT_ADDRESS);
+    if read_before_write {
+        state.set_storage_at(contract_address, key, initial_value).unwrap();
+    }
+    let mut resources = ExecutionResources::default();
+    let mut context =
+        EntryPointExecutionContext::new_invoke_for_testing(BlockContext::create_for_testing());
+    let mut syscall_handler = DeprecatedSyscallHintProcessor::new(
+        &mut state,
+        &mut resources,
+        &mut context,
+        Relocatable::from((0, 0)),
+        CallContext { storage_address: contract_address, ..Default::default() },
+    );
+
+    let mut vm = VirtualMachine::new(false);
+    if read_before_write {
+        storage_read(StorageReadRequest { address: key }, &mut vm, &mut syscall_handler).unwrap();
+    }
+    let request = StorageWriteRequest { address: key, value: new_value };
+    storage_write(request, &mut vm, &mut syscall_handler).unwrap();
+    storage_read(StorageReadRequest { address: key }, &mut vm, &mut syscall_handler).unwrap();
+
+    // Each write logs the value preceding it, between the values returned by the reads.
+    let expected_read_values = if read_before_write {
+        vec![initial_value, initial_value, new_value]
+    } else {
+        vec![StarkFelt::ZERO, new_value]
+    };
+    assert_eq!(syscall_handler.read_values, expected_read_values);
+    assert_eq!(syscall_handler.accessed_keys, HashSet::from([key]));
+}
+
+#[test_case(0, 0; "Contiguous orders.")]
+#[test_case(2, 0; "Orders consumed by an inner call.")]
+#[test_case(0, 1; "Gap in orders.")]
+#[test_case(2, 1; "Gap in orders after an inner call.")]
+fn test_finalize_order_continuity(n_inner_call_events: usize, n_skipped_events: usize) {
+    let mut state = deprecated_create_test_state();
+    let mut resources = ExecutionResources::default();
+    let mut context =
+        EntryPointExecutionContext::new_invoke_for_testing(BlockContext::create_for_testing());
+    let mut syscall_handler = DeprecatedSyscallHintProcessor::new(
+        &mut state,
+        &mut resources,
+        &mut context,
+        Relocatable::from((0, 0)),
+        CallContext {
+            storage_address: contract_address!(TEST_CONTRACT_ADDRESS),
+            ..Default::default()
+        },
+    );
+
+    syscall_handler.add_event(EventContent::default());
+    syscall_handler.execute_inner(|_, _, context| context.n_emitted_events += n_inner_call_events);
+    syscall_handler.context.n_emitted_events += n_skipped_events;
+    syscall_handler.add_event(EventContent::default());
+    let event_orders: Vec<usize> = syscall_handler.events().iter().map(|e| e.order).collect();
+    let second_event_order = 1 + n_inner_call_events + n_skipped_events;
+    assert_eq!(event_orders, [0, second_event_order]);
+
+    let result = syscall_handler.finalize();
+    if n_skipped_events == 0 {
+        assert_eq!(result.unwrap().events.len(), 2);
+    } else {
+        assert_matches!(
+            result.unwrap_err(),
+            PostExecutionError::NonContiguousOrders {
+                output_kind: "event",
+                expected_order,
+                actual_order,
+            } if expected_order == 1 + n_inner_call_events && actual_order == second_event_order
+        );
+    }
+}
+
 #[test_case(
     ExecutionMode::Execute, "block_number", calldata![stark_felt!(CURRENT_BLOCK_NUMBER)];
     "Test the syscall get_block_number in execution mode Execute")]
@@ -505,6 +1042,229 @@ fn test_tx_info(#[case] only_query: bool) {
     assert!(!result.execution.failed)
 }
 
+#[test]
+fn test_tx_info_layout_from_program() {
+    let raw_contract_class = get_raw_contract_class(TEST_CONTRACT_CAIRO0_PATH);
+    let contract_class = ContractClassV0::try_from_json_string(&raw_contract_class).unwrap();
+    assert_eq!(TxInfoLayout::from_program(&contract_class.program), TxInfoLayout::Deprecated);
+
+    // Declare the first field of the extended layout in the transaction info struct.
+    let mut raw_contract_class: serde_json::Value =
+        serde_json::from_str(&raw_contract_class).unwrap();
+    raw_contract_class["program"]["identifiers"]["starkware.starknet.common.syscalls.TxInfo"]
+        ["members"]["resource_bounds_start"] =
+        serde_json::json!({ "cairo_type": "felt*", "offset": 8 });
+    let contract_class =
+        ContractClassV0::try_from_json_string(&raw_contract_class.to_string()).unwrap();
+    assert_eq!(TxInfoLayout::from_program(&contract_class.program), TxInfoLayout::Extended);
+}
+
+#[rstest]
+fn test_extended_tx_info(#[values(true, false)] is_v3: bool) {
+    let mut state = deprecated_create_test_state();
+    let mut resources = ExecutionResources::default();
+    let common_fields = CommonAccountFields {
+        transaction_hash: TransactionHash(stark_felt!(1991_u16)),
+        nonce: Nonce(stark_felt!(3_u16)),
+        sender_address: contract_address!(TEST_CONTRACT_ADDRESS),
+        ..Default::default()
+    };
+    let tx_info = if is_v3 {
+        TransactionInfo::Current(CurrentTransactionInfo {
+            common_fields: CommonAccountFields {
+                version: TransactionVersion::THREE,
+                ..common_fields
+            },
+            resource_bounds: ResourceBoundsMapping(BTreeMap::from([
+                (Resource::L1Gas, ResourceBounds { max_amount: 13, max_price_per_unit: 61 }),
+                (Resource::L2Gas, ResourceBounds { max_amount: 0, max_price_per_unit: 0 }),
+            ])),
+            tip: Tip(7),
+            nonce_data_availability_mode: DataAvailabilityMode::L2,
+            fee_data_availability_mode: DataAvailabilityMode::L1,
+            paymaster_data: PaymasterData(vec![stark_felt!(11_u8), stark_felt!(12_u8)]),
+            account_deployment_data: AccountDeploymentData(vec![stark_felt!(21_u8)]),
+        })
+    } else {
+        TransactionInfo::Deprecated(DeprecatedTransactionInfo { common_fields, max_fee: Fee(0) })
+    };
+    let tx_context =
+        TransactionContext { block_context: BlockContext::create_for_testing(), tx_info };
+    let mut context = EntryPointExecutionContext::new_invoke(Arc::new(tx_context), true).unwrap();
+    let mut syscall_handler = DeprecatedSyscallHintProcessor::new(
+        &mut state,
+        &mut resources,
+        &mut context,
+        Relocatable::from((0, 0)),
+        CallContext {
+            storage_address: contract_address!(TEST_CONTRACT_ADDRESS),
+            ..Default::default()
+        },
+    );
+    syscall_handler.tx_info_layout = TxInfoLayout::Extended;
+
+    let mut vm = VirtualMachine::new(false);
+    let response = get_tx_info(EmptyRequest, &mut vm, &mut syscall_handler).unwrap();
+    let v3_fields_ptr = (response.tx_info_start_ptr + 8_usize).unwrap();
+    let cell = |index: usize| vm.get_maybe(&(v3_fields_ptr + index).unwrap()).unwrap();
+    let felt = |value: u64| MaybeRelocatable::from(Felt252::from(value));
+
+    if !is_v3 {
+        for index in 0..9 {
+            assert_eq!(cell(index), felt(0));
+        }
+        return;
+    }
+
+    // Reads the array given by the (start, end) pointers at the given index.
+    let array = |index: usize| {
+        let start_ptr = Relocatable::try_from(&cell(index)).unwrap();
+        let end_ptr = Relocatable::try_from(&cell(index + 1)).unwrap();
+        felt_range_from_ptr(&vm, start_ptr, (end_ptr - start_ptr).unwrap()).unwrap()
+    };
+    assert_eq!(
+        array(0),
+        vec![
+            StarkFelt::try_from(L1_GAS).unwrap(),
+            stark_felt!(13_u8),
+            stark_felt!(61_u8),
+            StarkFelt::try_from(L2_GAS).unwrap(),
+            StarkFelt::ZERO,
+            StarkFelt::ZERO,
+        ]
+    );
+    assert_eq!(cell(2), felt(7)); // Tip.
+    assert_eq!(array(3), vec![stark_felt!(11_u8), stark_felt!(12_u8)]);
+    assert_eq!(cell(5), felt(1)); // Nonce data availability mode (L2).
+    assert_eq!(cell(6), felt(0)); // Fee data availability mode (L1).
+    assert_eq!(array(7), vec![stark_felt!(21_u8)]);
+    // The extended layout ends with the account deployment data.
+    assert!(vm.get_maybe(&(v3_fields_ptr + 9_usize).unwrap()).is_none());
+}
+
+const MAX_ARRAY_LENGTH: usize = 10;
+
+#[rstest]
+#[case::exceeds_segment(
+    stark_felt!(5_u8),
+    ArrayLengthError::ExceedsSegment { syscall: DeprecatedSyscallSelector::SendMessageToL1, length: 5 }
+)]
+#[case::exceeds_max_length(
+    stark_felt!((MAX_ARRAY_LENGTH + 1) as u64),
+    ArrayLengthError::ExceedsMaxLength {
+        syscall: DeprecatedSyscallSelector::SendMessageToL1,
+        length: MAX_ARRAY_LENGTH + 1,
+        max_length: MAX_ARRAY_LENGTH,
+    }
+)]
+#[case::invalid_length(
+    stark_felt!("0x10000000000000000"),
+    ArrayLengthError::InvalidLength {
+        syscall: DeprecatedSyscallSelector::SendMessageToL1,
+        length: stark_felt!("0x10000000000000000"),
+    }
+)]
+fn test_array_length_validation(
+    #[case] length: StarkFelt,
+    #[case] expected_error: ArrayLengthError,
+) {
+    let mut vm = VirtualMachine::new(false);
+    // The payload has only 3 initialized elements, regardless of its declared length.
+    let payload_ptr = vm.add_memory_segment();
+    vm.load_data(payload_ptr, &vec![Felt252::from(1_u8).into(); 3]).unwrap();
+    let request_ptr = vm.add_memory_segment();
+    let request =
+        vec![Felt252::from(0_u8).into(), stark_felt_to_felt(length).into(), payload_ptr.into()];
+    vm.load_data(request_ptr, &request).unwrap();
+
+    let read_context = SyscallReadContext {
+        max_array_length: MAX_ARRAY_LENGTH,
+        ..SyscallReadContext::new(
+            DeprecatedSyscallSelector::SendMessageToL1,
+            VersionedConstants::latest_constants(),
+        )
+    };
+    let error =
+        SendMessageToL1Request::read(&vm, &mut request_ptr.clone(), &read_context).unwrap_err();
+    assert_matches!(error, DeprecatedSyscallExecutionError::ArrayLengthError(_));
+    assert_eq!(error.to_string(), expected_error.to_string());
+}
+
+#[test_case(
+    DeprecatedSyscallSelector::DelegateCall, selector_from_name("foo").0, true;
+    "Valid selector.")]
+#[test_case(
+    DeprecatedSyscallSelector::DelegateCall,
+    stark_felt!("0x400000000000000000000000000000000000000000000000000000000000000"), false;
+    "Out-of-range selector.")]
+#[test_case(
+    DeprecatedSyscallSelector::DelegateL1Handler,
+    stark_felt!("0x400000000000000000000000000000000000000000000000000000000000000"), false;
+    "Out-of-range selector in an L1 handler delegate call.")]
+fn test_selector_validation(
+    syscall: DeprecatedSyscallSelector,
+    selector: StarkFelt,
+    is_valid: bool,
+) {
+    let mut vm = VirtualMachine::new(false);
+    let calldata_ptr = vm.add_memory_segment();
+    let request_ptr = vm.add_memory_segment();
+    let request = vec![
+        Felt252::from(1_u8).into(), // Contract address.
+        stark_felt_to_felt(selector).into(),
+        Felt252::from(0_u8).into(), // Calldata size.
+        calldata_ptr.into(),
+    ];
+    vm.load_data(request_ptr, &request).unwrap();
+
+    // The request is read the same for both syscalls; errors are labeled by the dispatched one.
+    let read_context = SyscallReadContext::new(syscall, VersionedConstants::latest_constants());
+    let result = DelegateCallRequest::read(&vm, &mut request_ptr.clone(), &read_context);
+    if is_valid {
+        assert_eq!(result.unwrap().function_selector, EntryPointSelector(selector));
+    } else {
+        let error = result.unwrap_err();
+        assert_matches!(
+            error,
+            DeprecatedSyscallExecutionError::InvalidEntryPointSelector {
+                syscall: error_syscall,
+                selector: error_selector,
+            } if error_syscall == syscall && error_selector == selector
+        );
+        assert!(error.to_string().contains(&selector.to_string()));
+    }
+}
+
+#[test]
+fn test_invalid_deploy_from_zero() {
+    let mut vm = VirtualMachine::new(false);
+    let constructor_calldata_ptr = vm.add_memory_segment();
+    let deploy_from_zero = stark_felt!(2_u8);
+    let request_ptr = vm.add_memory_segment();
+    let request = vec![
+        Felt252::from(0_u8).into(), // Class hash.
+        Felt252::from(0_u8).into(), // Contract address salt.
+        Felt252::from(0_u8).into(), // Constructor calldata size.
+        constructor_calldata_ptr.into(),
+        stark_felt_to_felt(deploy_from_zero).into(),
+    ];
+    vm.load_data(request_ptr, &request).unwrap();
+
+    let read_context = SyscallReadContext::new(
+        DeprecatedSyscallSelector::Deploy,
+        VersionedConstants::latest_constants(),
+    );
+    let error = DeployRequest::read(&vm, &mut request_ptr.clone(), &read_context).unwrap_err();
+    assert_matches!(
+        error,
+        DeprecatedSyscallExecutionError::InvalidSyscallInput { input, .. }
+        if input == deploy_from_zero
+    );
+    let error_message = error.to_string();
+    assert!(error_message.contains(&deploy_from_zero.to_string()));
+    assert!(error_message.contains("deploy_from_zero"));
+}
+
 #[test]
 fn test_emit_event() {
     let versioned_constants = VersionedConstants::create_for_testing();
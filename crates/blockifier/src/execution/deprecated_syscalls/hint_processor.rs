@@ -1,5 +1,7 @@
 use std::any::Any;
 use std::collections::{HashMap, HashSet};
+use std::fmt;
+use std::rc::Rc;
 
 use cairo_felt::Felt252;
 use cairo_vm::hint_processor::builtin_hint_processor::builtin_hint_processor_definition::{
@@ -16,17 +18,20 @@ use cairo_vm::vm::errors::memory_errors::MemoryError;
 use cairo_vm::vm::errors::vm_errors::VirtualMachineError;
 use cairo_vm::vm::runners::cairo_runner::{ExecutionResources, ResourceTracker, RunResources};
 use cairo_vm::vm::vm_core::VirtualMachine;
+use num_traits::Zero;
 use starknet_api::core::{ClassHash, ContractAddress, EntryPointSelector};
-use starknet_api::deprecated_contract_class::EntryPointType;
 use starknet_api::hash::StarkFelt;
 use starknet_api::state::StorageKey;
-use starknet_api::transaction::Calldata;
+use starknet_api::transaction::{Calldata, EventContent};
 use starknet_api::StarknetApiError;
 use thiserror::Error;
 
+use crate::abi::abi_utils::is_valid_selector;
 use crate::blockifier::block::BlockInfo;
 use crate::context::TransactionContext;
-use crate::execution::call_info::{CallInfo, OrderedEvent, OrderedL2ToL1Message};
+use crate::execution::call_info::{
+    CallExecution, CallInfo, CallOutputs, MessageToL1, OrderedEvent, OrderedL2ToL1Message, Retdata,
+};
 use crate::execution::common_hints::{
     extended_builtin_hint_processor, ExecutionMode, HintExecutionResult,
 };
@@ -36,26 +41,39 @@ use crate::execution::deprecated_syscalls::{
     get_tx_info, get_tx_signature, library_call, library_call_l1_handler, replace_class,
     send_message_to_l1, storage_read, storage_write, DeprecatedSyscallResult,
     DeprecatedSyscallSelector, StorageReadResponse, StorageWriteResponse, SyscallRequest,
-    SyscallResponse,
+    SyscallResponse, TxInfoLayout, WriteResponseResult,
+};
+use crate::execution::entry_point::{
+    execute_class_code, CallContext, CallEntryPoint, ClassCodeCall, EntryPointExecutionContext,
+    EntryPointExecutionResult, LibraryCallKind,
 };
-use crate::execution::entry_point::{CallEntryPoint, CallType, EntryPointExecutionContext};
-use crate::execution::errors::EntryPointExecutionError;
+use crate::execution::errors::{BoundedFeltError, EntryPointExecutionError, PostExecutionError};
 use crate::execution::execution_utils::{
     felt_range_from_ptr, max_fee_for_execution_info, stark_felt_from_ptr, stark_felt_to_felt,
+    usize_from_ptr, write_maybe_relocatable, write_stark_felt, CallOutputsRecorder,
     ReadOnlySegment, ReadOnlySegments,
 };
 use crate::execution::hint_code;
-use crate::execution::syscalls::hint_processor::EmitEventError;
+use crate::execution::syscalls::hint_processor::{
+    flatten_resource_bounds, validate_array_length, ArrayLengthError, CalldataLengthError,
+    EmitEventError, SyscallReadContext, SyscallUsage,
+};
 use crate::state::errors::StateError;
 use crate::state::state_api::State;
+use crate::transaction::objects::TransactionInfo;
 
-pub type SyscallCounter = HashMap<DeprecatedSyscallSelector, usize>;
+pub type SyscallCounter = HashMap<DeprecatedSyscallSelector, SyscallUsage>;
+
+// Resource bounds, tip, paymaster data, data availability modes and account deployment data.
+const N_V3_TX_INFO_FIELDS: usize = 9;
 
 #[derive(Debug, Error)]
 pub enum DeprecatedSyscallExecutionError {
     #[error("Bad syscall_ptr; expected: {expected_ptr:?}, got: {actual_ptr:?}.")]
     BadSyscallPointer { expected_ptr: Relocatable, actual_ptr: Relocatable },
     #[error(transparent)]
+    CalldataTooLong(#[from] CalldataLengthError),
+    #[error(transparent)]
     EntryPointExecutionError(#[from] EntryPointExecutionError),
     #[error("{error}")]
     CallContractExecutionError {
@@ -63,17 +81,51 @@ pub enum DeprecatedSyscallExecutionError {
         error: Box<DeprecatedSyscallExecutionError>,
     },
     #[error(transparent)]
+    BoundedFeltError(#[from] BoundedFeltError),
+    #[error(transparent)]
     EmitEventError(#[from] EmitEventError),
+    #[error(transparent)]
+    ArrayLengthError(#[from] ArrayLengthError),
+    #[error(
+        "The {selector:?} syscall exceeds the maximum of {max_syscalls_per_tx} syscalls per \
+         transaction."
+    )]
+    ExceedsMaxSyscallsPerTx { selector: DeprecatedSyscallSelector, max_syscalls_per_tx: usize },
     #[error("{error}")]
     LibraryCallExecutionError {
         class_hash: ClassHash,
         storage_address: ContractAddress,
         error: Box<DeprecatedSyscallExecutionError>,
     },
+    #[error(
+        "Entry point selector {selector} in the {syscall:?} syscall request is out of range; \
+         selectors are at most 250 bits."
+    )]
+    InvalidEntryPointSelector { syscall: DeprecatedSyscallSelector, selector: StarkFelt },
     #[error("Invalid syscall input: {input:?}; {info}")]
     InvalidSyscallInput { input: StarkFelt, info: String },
     #[error("Invalid syscall selector: {0:?}.")]
     InvalidDeprecatedSyscallSelector(StarkFelt),
+    #[error(
+        "The {selector:?} syscall advanced the syscall pointer by {actual_size} cells; expected \
+         {expected_size}."
+    )]
+    InvalidSyscallSize {
+        selector: DeprecatedSyscallSelector,
+        expected_size: usize,
+        actual_size: usize,
+    },
+    #[error(
+        "The override of the {selector:?} syscall responded with {actual_size} cells; expected \
+         {expected_size}."
+    )]
+    InvalidSyscallOverrideResponse {
+        selector: DeprecatedSyscallSelector,
+        expected_size: usize,
+        actual_size: usize,
+    },
+    #[error("The {selector:?} syscall cannot be overridden; its response is not made of felts.")]
+    UnsupportedSyscallOverride { selector: DeprecatedSyscallSelector },
     #[error(transparent)]
     MathError(#[from] cairo_vm::types::errors::math_errors::MathError),
     #[error(transparent)]
@@ -115,6 +167,119 @@ impl DeprecatedSyscallExecutionError {
             error: Box::new(self),
         }
     }
+
+    /// Returns the state error this error originates from, if any; see
+    /// [`crate::execution::syscalls::hint_processor::SyscallExecutionError::state_error`].
+    pub fn state_error(&self) -> Option<&StateError> {
+        match self {
+            Self::StateError(error) => Some(error),
+            Self::EntryPointExecutionError(error) => error.state_error(),
+            Self::CallContractExecutionError { error, .. }
+            | Self::LibraryCallExecutionError { error, .. } => error.state_error(),
+            _ => None,
+        }
+    }
+}
+
+/// Produces the response of an overridden syscall (as written to the syscall segment), given the
+/// handler of the calling entry point.
+pub type SyscallResponseOverride =
+    Rc<dyn Fn(&mut DeprecatedSyscallHintProcessor<'_>) -> OverriddenResponseResult>;
+
+/// The cells of an overridden syscall response.
+pub type OverriddenResponseResult = DeprecatedSyscallResult<Vec<StarkFelt>>;
+
+/// Produces the return data of an overridden contract call, given the called entry point.
+pub type CallContractOverride = Rc<dyn Fn(&CallEntryPoint) -> DeprecatedSyscallResult<Retdata>>;
+
+/// Stubbed syscalls for Cairo 0 classes, used instead of the built-in ones; e.g., for
+/// unit-testing contracts without setting up the state they would otherwise query.
+/// Overridden syscalls are still counted, and the outputs they produce through the handler (e.g.,
+/// events) are still ordered along with the rest of the transaction outputs.
+/// Cairo 1 syscalls are not overridable: their responses also consume gas and may fail with
+/// revert data, which a stubbed list of felts cannot express.
+#[derive(Clone, Default)]
+pub struct SyscallOverrides {
+    responses: HashMap<DeprecatedSyscallSelector, SyscallResponseOverride>,
+    // Keyed by the called contract; the override under `None` applies to all other contracts.
+    call_contract: HashMap<Option<ContractAddress>, CallContractOverride>,
+}
+
+impl SyscallOverrides {
+    /// Overrides the response of the given syscall; only applicable to syscalls whose responses
+    /// consist of felts (i.e., not to calls, deployments or the transaction info and signature).
+    pub fn override_syscall<Response>(
+        &mut self,
+        selector: DeprecatedSyscallSelector,
+        response: Response,
+    ) -> DeprecatedSyscallResult<()>
+    where
+        Response: Fn(&mut DeprecatedSyscallHintProcessor<'_>) -> OverriddenResponseResult + 'static,
+    {
+        if !Self::is_overridable(selector) {
+            return Err(DeprecatedSyscallExecutionError::UnsupportedSyscallOverride { selector });
+        }
+        self.responses.insert(selector, Rc::new(response));
+
+        Ok(())
+    }
+
+    /// Overrides the `call_contract` syscall, for calls to the given contract, or to any contract
+    /// without an override of its own if none is given.
+    pub fn override_call_contract(
+        &mut self,
+        contract_address: Option<ContractAddress>,
+        retdata: impl Fn(&CallEntryPoint) -> DeprecatedSyscallResult<Retdata> + 'static,
+    ) {
+        self.call_contract.insert(contract_address, Rc::new(retdata));
+    }
+
+    pub fn response_override(
+        &self,
+        selector: DeprecatedSyscallSelector,
+    ) -> Option<SyscallResponseOverride> {
+        self.responses.get(&selector).cloned()
+    }
+
+    pub fn call_contract_override(
+        &self,
+        contract_address: ContractAddress,
+    ) -> Option<CallContractOverride> {
+        self.call_contract
+            .get(&Some(contract_address))
+            .or_else(|| self.call_contract.get(&None))
+            .cloned()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.responses.is_empty() && self.call_contract.is_empty()
+    }
+
+    /// Returns whether the Cairo 0 syscall has a response made of felts only.
+    fn is_overridable(selector: DeprecatedSyscallSelector) -> bool {
+        matches!(
+            selector,
+            DeprecatedSyscallSelector::EmitEvent
+                | DeprecatedSyscallSelector::GetBlockNumber
+                | DeprecatedSyscallSelector::GetBlockTimestamp
+                | DeprecatedSyscallSelector::GetCallerAddress
+                | DeprecatedSyscallSelector::GetContractAddress
+                | DeprecatedSyscallSelector::GetSequencerAddress
+                | DeprecatedSyscallSelector::ReplaceClass
+                | DeprecatedSyscallSelector::SendMessageToL1
+                | DeprecatedSyscallSelector::StorageRead
+                | DeprecatedSyscallSelector::StorageWrite
+        )
+    }
+}
+
+impl fmt::Debug for SyscallOverrides {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("SyscallOverrides")
+            .field("responses", &self.responses.keys())
+            .field("call_contract", &self.call_contract.keys())
+            .finish()
+    }
 }
 
 /// Executes Starknet syscalls (stateful protocol hints) during the execution of an entry point
@@ -124,28 +289,32 @@ pub struct DeprecatedSyscallHintProcessor<'a> {
     pub state: &'a mut dyn State,
     pub resources: &'a mut ExecutionResources,
     pub context: &'a mut EntryPointExecutionContext,
-    pub storage_address: ContractAddress,
-    pub caller_address: ContractAddress,
+    call_context: CallContext,
 
     // Execution results.
-    /// Inner calls invoked by the current execution.
-    pub inner_calls: Vec<CallInfo>,
-    pub events: Vec<OrderedEvent>,
-    pub l2_to_l1_messages: Vec<OrderedL2ToL1Message>,
+    /// The events, messages and inner calls of the current execution.
+    outputs: CallOutputsRecorder,
     pub syscall_counter: SyscallCounter,
 
     // Fields needed for execution and validation.
     pub read_only_segments: ReadOnlySegments,
     pub syscall_ptr: Relocatable,
+    /// The layout of the transaction info struct expected by the executing class.
+    pub tx_info_layout: TxInfoLayout,
 
     // Additional information gathered during execution.
+    /// The storage values read during execution, in syscall order: the value returned by each
+    /// `storage_read`, and the value preceding each `storage_write` (from which the OS builds the
+    /// `DictAccess` entry of the write).
     pub read_values: Vec<StarkFelt>,
     pub accessed_keys: HashSet<StorageKey>,
 
     // Additional fields.
-    // Invariant: must only contain allowed hints.
+    // Invariant: must only contain allowed hints (built-in, or custom hints if allowed).
     builtin_hint_processor: BuiltinHintProcessor,
-    // Transaction info. and signature segments; allocated on-demand.
+    syscall_overrides: Rc<SyscallOverrides>,
+    // Transaction info. and signature segments; allocated on-demand, once per call frame (inner
+    // calls run in VMs of their own, hence allocate their own copies).
     tx_signature_start_ptr: Option<Relocatable>,
     tx_info_start_ptr: Option<Relocatable>,
 }
@@ -156,29 +325,60 @@ impl<'a> DeprecatedSyscallHintProcessor<'a> {
         resources: &'a mut ExecutionResources,
         context: &'a mut EntryPointExecutionContext,
         initial_syscall_ptr: Relocatable,
-        storage_address: ContractAddress,
-        caller_address: ContractAddress,
+        call_context: CallContext,
     ) -> Self {
+        let mut builtin_hint_processor = extended_builtin_hint_processor();
+        if context.tx_context.block_context.allow_custom_hints() {
+            for (hint_code, hint_func) in context.custom_hint_registry.hints() {
+                builtin_hint_processor.add_hint(hint_code.clone(), hint_func.clone());
+            }
+        }
+
+        let syscall_overrides = context.syscall_overrides.clone();
+        let outputs = CallOutputsRecorder::new(context);
         DeprecatedSyscallHintProcessor {
             state,
             resources,
             context,
-            storage_address,
-            caller_address,
-            inner_calls: vec![],
-            events: vec![],
-            l2_to_l1_messages: vec![],
+            call_context,
+            outputs,
             syscall_counter: SyscallCounter::default(),
             read_only_segments: ReadOnlySegments::default(),
             syscall_ptr: initial_syscall_ptr,
+            tx_info_layout: TxInfoLayout::default(),
             read_values: vec![],
             accessed_keys: HashSet::new(),
-            builtin_hint_processor: extended_builtin_hint_processor(),
+            builtin_hint_processor,
+            syscall_overrides,
             tx_signature_start_ptr: None,
             tx_info_start_ptr: None,
         }
     }
 
+    pub fn call_context(&self) -> &CallContext {
+        &self.call_context
+    }
+
+    pub fn storage_address(&self) -> ContractAddress {
+        self.call_context.storage_address
+    }
+
+    pub fn caller_address(&self) -> ContractAddress {
+        self.call_context.caller_address
+    }
+
+    pub fn entry_point_selector(&self) -> EntryPointSelector {
+        self.call_context.entry_point_selector
+    }
+
+    pub fn class_hash(&self) -> ClassHash {
+        self.call_context.class_hash
+    }
+
+    pub fn syscall_overrides(&self) -> &SyscallOverrides {
+        &self.syscall_overrides
+    }
+
     pub fn execution_mode(&self) -> ExecutionMode {
         self.context.execution_mode
     }
@@ -210,6 +410,48 @@ impl<'a> DeprecatedSyscallHintProcessor<'a> {
         Ok(())
     }
 
+    pub fn events(&self) -> &[OrderedEvent] {
+        self.outputs.events()
+    }
+
+    pub fn l2_to_l1_messages(&self) -> &[OrderedL2ToL1Message] {
+        self.outputs.l2_to_l1_messages()
+    }
+
+    pub fn inner_calls(&self) -> &[CallInfo] {
+        self.outputs.inner_calls()
+    }
+
+    pub fn add_event(&mut self, event: EventContent) {
+        self.outputs.add_event(self.context, event);
+    }
+
+    pub fn add_l2_to_l1_message(&mut self, message: MessageToL1) {
+        self.outputs.add_l2_to_l1_message(self.context, message);
+    }
+
+    pub fn add_inner_call(&mut self, call_info: CallInfo) {
+        self.outputs.add_inner_call(call_info);
+    }
+
+    /// See [`CallOutputsRecorder::execute_inner`].
+    pub fn execute_inner<T>(
+        &mut self,
+        execute: impl FnOnce(
+            &mut dyn State,
+            &mut ExecutionResources,
+            &mut EntryPointExecutionContext,
+        ) -> T,
+    ) -> T {
+        let (state, resources) = (&mut *self.state, &mut *self.resources);
+        self.outputs.execute_inner(self.context, |context| execute(state, resources, context))
+    }
+
+    /// See [`CallOutputsRecorder::finalize`].
+    pub fn finalize(self) -> Result<CallOutputs, PostExecutionError> {
+        self.outputs.finalize(self.context, self.read_values, self.accessed_keys)
+    }
+
     /// Infers and executes the next syscall.
     /// Must comply with the API of a hint function, as defined by the `HintProcessor`.
     pub fn execute_next_syscall(
@@ -222,41 +464,58 @@ impl<'a> DeprecatedSyscallHintProcessor<'a> {
         self.verify_syscall_ptr(initial_syscall_ptr)?;
 
         let selector = DeprecatedSyscallSelector::try_from(self.read_next_syscall_selector(vm)?)?;
+        self.count_syscall(selector)?;
         self.increment_syscall_count(&selector);
 
         match selector {
-            DeprecatedSyscallSelector::CallContract => self.execute_syscall(vm, call_contract),
-            DeprecatedSyscallSelector::DelegateCall => self.execute_syscall(vm, delegate_call),
+            DeprecatedSyscallSelector::CallContract => {
+                self.execute_syscall(vm, selector, call_contract)
+            }
+            DeprecatedSyscallSelector::DelegateCall => {
+                self.execute_syscall(vm, selector, delegate_call)
+            }
             DeprecatedSyscallSelector::DelegateL1Handler => {
-                self.execute_syscall(vm, delegate_l1_handler)
+                self.execute_syscall(vm, selector, delegate_l1_handler)
+            }
+            DeprecatedSyscallSelector::Deploy => self.execute_syscall(vm, selector, deploy),
+            DeprecatedSyscallSelector::EmitEvent => self.execute_syscall(vm, selector, emit_event),
+            DeprecatedSyscallSelector::GetBlockNumber => {
+                self.execute_syscall(vm, selector, get_block_number)
             }
-            DeprecatedSyscallSelector::Deploy => self.execute_syscall(vm, deploy),
-            DeprecatedSyscallSelector::EmitEvent => self.execute_syscall(vm, emit_event),
-            DeprecatedSyscallSelector::GetBlockNumber => self.execute_syscall(vm, get_block_number),
             DeprecatedSyscallSelector::GetBlockTimestamp => {
-                self.execute_syscall(vm, get_block_timestamp)
+                self.execute_syscall(vm, selector, get_block_timestamp)
             }
             DeprecatedSyscallSelector::GetCallerAddress => {
-                self.execute_syscall(vm, get_caller_address)
+                self.execute_syscall(vm, selector, get_caller_address)
             }
             DeprecatedSyscallSelector::GetContractAddress => {
-                self.execute_syscall(vm, get_contract_address)
+                self.execute_syscall(vm, selector, get_contract_address)
             }
             DeprecatedSyscallSelector::GetSequencerAddress => {
-                self.execute_syscall(vm, get_sequencer_address)
+                self.execute_syscall(vm, selector, get_sequencer_address)
+            }
+            DeprecatedSyscallSelector::GetTxInfo => self.execute_syscall(vm, selector, get_tx_info),
+            DeprecatedSyscallSelector::GetTxSignature => {
+                self.execute_syscall(vm, selector, get_tx_signature)
+            }
+            DeprecatedSyscallSelector::LibraryCall => {
+                self.execute_syscall(vm, selector, library_call)
             }
-            DeprecatedSyscallSelector::GetTxInfo => self.execute_syscall(vm, get_tx_info),
-            DeprecatedSyscallSelector::GetTxSignature => self.execute_syscall(vm, get_tx_signature),
-            DeprecatedSyscallSelector::LibraryCall => self.execute_syscall(vm, library_call),
             DeprecatedSyscallSelector::LibraryCallL1Handler => {
-                self.execute_syscall(vm, library_call_l1_handler)
+                self.execute_syscall(vm, selector, library_call_l1_handler)
+            }
+            DeprecatedSyscallSelector::ReplaceClass => {
+                self.execute_syscall(vm, selector, replace_class)
             }
-            DeprecatedSyscallSelector::ReplaceClass => self.execute_syscall(vm, replace_class),
             DeprecatedSyscallSelector::SendMessageToL1 => {
-                self.execute_syscall(vm, send_message_to_l1)
+                self.execute_syscall(vm, selector, send_message_to_l1)
+            }
+            DeprecatedSyscallSelector::StorageRead => {
+                self.execute_syscall(vm, selector, storage_read)
+            }
+            DeprecatedSyscallSelector::StorageWrite => {
+                self.execute_syscall(vm, selector, storage_write)
             }
-            DeprecatedSyscallSelector::StorageRead => self.execute_syscall(vm, storage_read),
-            DeprecatedSyscallSelector::StorageWrite => self.execute_syscall(vm, storage_write),
             _ => Err(HintError::UnknownHint(
                 format!("Unsupported syscall selector {selector:?}.").into(),
             )),
@@ -294,6 +553,7 @@ impl<'a> DeprecatedSyscallHintProcessor<'a> {
     fn execute_syscall<Request, Response, ExecuteCallback>(
         &mut self,
         vm: &mut VirtualMachine,
+        selector: DeprecatedSyscallSelector,
         execute_callback: ExecuteCallback,
     ) -> HintExecutionResult
     where
@@ -305,10 +565,43 @@ impl<'a> DeprecatedSyscallHintProcessor<'a> {
             &mut DeprecatedSyscallHintProcessor<'_>,
         ) -> DeprecatedSyscallResult<Response>,
     {
-        let request = Request::read(vm, &mut self.syscall_ptr)?;
+        let request_ptr = self.syscall_ptr;
+        let read_context = SyscallReadContext::new(selector, self.context.versioned_constants());
+        let request = Request::read(vm, &mut self.syscall_ptr, &read_context)?;
+
+        match self.syscall_overrides.response_override(selector) {
+            Some(response_override) => {
+                let response = response_override(self)?;
+                if response.len() != Response::SIZE {
+                    return Err(DeprecatedSyscallExecutionError::InvalidSyscallOverrideResponse {
+                        selector,
+                        expected_size: Response::SIZE,
+                        actual_size: response.len(),
+                    }
+                    .into());
+                }
+                for value in response {
+                    write_stark_felt(vm, &mut self.syscall_ptr, value)?;
+                }
+            }
+            None => {
+                let response = execute_callback(request, vm, self)?;
+                response.write(vm, &mut self.syscall_ptr)?;
+            }
+        }
 
-        let response = execute_callback(request, vm, self)?;
-        response.write(vm, &mut self.syscall_ptr)?;
+        if self.context.validate_syscall_sizes {
+            let expected_size = Request::SIZE + Response::SIZE;
+            let actual_size = (self.syscall_ptr - request_ptr)?;
+            if actual_size != expected_size {
+                return Err(DeprecatedSyscallExecutionError::InvalidSyscallSize {
+                    selector,
+                    expected_size,
+                    actual_size,
+                }
+                .into());
+            }
+        }
 
         Ok(())
     }
@@ -322,21 +615,53 @@ impl<'a> DeprecatedSyscallHintProcessor<'a> {
         Ok(selector)
     }
 
+    /// Counts the syscall towards the syscall limit of the transaction, shared by all its calls.
+    fn count_syscall(
+        &mut self,
+        selector: DeprecatedSyscallSelector,
+    ) -> DeprecatedSyscallResult<()> {
+        match self.context.count_syscall() {
+            Some(max_syscalls_per_tx) => {
+                Err(DeprecatedSyscallExecutionError::ExceedsMaxSyscallsPerTx {
+                    selector,
+                    max_syscalls_per_tx,
+                })
+            }
+            None => Ok(()),
+        }
+    }
+
     fn increment_syscall_count(&mut self, selector: &DeprecatedSyscallSelector) {
-        let syscall_count = self.syscall_counter.entry(*selector).or_default();
-        *syscall_count += 1;
+        let syscall_usage = self.syscall_counter.entry(*selector).or_default();
+        syscall_usage.call_count += 1;
+    }
+
+    /// Adds the given input size to the usage of the syscall, for its OS resources to scale by.
+    pub fn increment_linear_factor_by(&mut self, selector: &DeprecatedSyscallSelector, n: usize) {
+        let syscall_usage = self.syscall_counter.entry(*selector).or_default();
+        syscall_usage.linear_factor += n;
+    }
+
+    /// Allocates a new segment holding the given felts, and registers it as read-only.
+    pub fn write_felt_array(
+        &mut self,
+        vm: &mut VirtualMachine,
+        data: &[StarkFelt],
+    ) -> DeprecatedSyscallResult<ReadOnlySegment> {
+        let data = data.iter().map(|&x| MaybeRelocatable::from(stark_felt_to_felt(x))).collect();
+        let start_ptr = self.read_only_segments.allocate(vm, &data)?;
+
+        Ok(ReadOnlySegment { start_ptr, length: data.len() })
     }
 
     fn allocate_tx_signature_segment(
         &mut self,
         vm: &mut VirtualMachine,
     ) -> DeprecatedSyscallResult<Relocatable> {
-        let signature = &self.context.tx_context.tx_info.signature().0;
-        let signature =
-            signature.iter().map(|&x| MaybeRelocatable::from(stark_felt_to_felt(x))).collect();
-        let signature_segment_start_ptr = self.read_only_segments.allocate(vm, &signature)?;
+        let tx_context = self.context.tx_context.clone();
+        let signature_segment = self.write_felt_array(vm, &tx_context.tx_info.signature().0)?;
 
-        Ok(signature_segment_start_ptr)
+        Ok(signature_segment.start_ptr)
     }
 
     fn allocate_tx_info_segment(
@@ -344,9 +669,10 @@ impl<'a> DeprecatedSyscallHintProcessor<'a> {
         vm: &mut VirtualMachine,
     ) -> DeprecatedSyscallResult<Relocatable> {
         let tx_signature_start_ptr = self.get_or_allocate_tx_signature_segment(vm)?;
-        let TransactionContext { block_context, tx_info } = self.context.tx_context.as_ref();
+        let tx_context = self.context.tx_context.clone();
+        let TransactionContext { block_context, tx_info } = tx_context.as_ref();
         let tx_signature_length = tx_info.signature().0.len();
-        let tx_info: Vec<MaybeRelocatable> = vec![
+        let mut tx_data: Vec<MaybeRelocatable> = vec![
             stark_felt_to_felt(tx_info.signed_version().0).into(),
             stark_felt_to_felt(*tx_info.sender_address().0.key()).into(),
             max_fee_for_execution_info(tx_info).into(),
@@ -356,17 +682,48 @@ impl<'a> DeprecatedSyscallHintProcessor<'a> {
             Felt252::from_bytes_be(block_context.chain_info.chain_id.0.as_bytes()).into(),
             stark_felt_to_felt(tx_info.nonce().0).into(),
         ];
+        if self.tx_info_layout == TxInfoLayout::Extended {
+            let v3_tx_data = self.allocate_v3_tx_info_fields(vm, tx_info)?;
+            tx_data.extend(v3_tx_data);
+        }
 
-        let tx_info_start_ptr = self.read_only_segments.allocate(vm, &tx_info)?;
+        let tx_info_start_ptr = self.read_only_segments.allocate(vm, &tx_data)?;
         Ok(tx_info_start_ptr)
     }
 
+    /// Returns the fields of the extended transaction info layout, allocating their arrays.
+    fn allocate_v3_tx_info_fields(
+        &mut self,
+        vm: &mut VirtualMachine,
+        tx_info: &TransactionInfo,
+    ) -> DeprecatedSyscallResult<Vec<MaybeRelocatable>> {
+        let TransactionInfo::Current(tx_info) = tx_info else {
+            return Ok(vec![Felt252::zero().into(); N_V3_TX_INFO_FIELDS]);
+        };
+
+        let resource_bounds = self.write_felt_array(vm, &flatten_resource_bounds(tx_info)?)?;
+        let paymaster_data = self.write_felt_array(vm, &tx_info.paymaster_data.0)?;
+        let account_deployment_data =
+            self.write_felt_array(vm, &tx_info.account_deployment_data.0)?;
+        Ok(vec![
+            resource_bounds.start_ptr.into(),
+            resource_bounds.end_ptr()?.into(),
+            Felt252::from(tx_info.tip.0).into(),
+            paymaster_data.start_ptr.into(),
+            paymaster_data.end_ptr()?.into(),
+            stark_felt_to_felt(tx_info.nonce_data_availability_mode.into()).into(),
+            stark_felt_to_felt(tx_info.fee_data_availability_mode.into()).into(),
+            account_deployment_data.start_ptr.into(),
+            account_deployment_data.end_ptr()?.into(),
+        ])
+    }
+
     pub fn get_contract_storage_at(
         &mut self,
         key: StorageKey,
     ) -> DeprecatedSyscallResult<StorageReadResponse> {
         self.accessed_keys.insert(key);
-        let value = self.state.get_storage_at(self.storage_address, key)?;
+        let value = self.state.get_storage_at(self.storage_address(), key)?;
         self.read_values.push(value);
 
         Ok(StorageReadResponse { value })
@@ -378,7 +735,7 @@ impl<'a> DeprecatedSyscallHintProcessor<'a> {
         value: StarkFelt,
     ) -> DeprecatedSyscallResult<StorageWriteResponse> {
         self.accessed_keys.insert(key);
-        self.state.set_storage_at(self.storage_address, key, value)?;
+        self.state.set_storage_at(self.storage_address(), key, value)?;
 
         Ok(StorageWriteResponse {})
     }
@@ -423,7 +780,7 @@ impl HintProcessorLogic for DeprecatedSyscallHintProcessor<'_> {
     }
 }
 
-pub fn felt_to_bool(felt: StarkFelt) -> DeprecatedSyscallResult<bool> {
+pub fn felt_to_bool(felt: StarkFelt, error_info: &str) -> DeprecatedSyscallResult<bool> {
     if felt == StarkFelt::from(0_u8) {
         Ok(false)
     } else if felt == StarkFelt::from(1_u8) {
@@ -431,9 +788,7 @@ pub fn felt_to_bool(felt: StarkFelt) -> DeprecatedSyscallResult<bool> {
     } else {
         Err(DeprecatedSyscallExecutionError::InvalidSyscallInput {
             input: felt,
-            info: String::from(
-                "The deploy_from_zero field in the deploy system call must be 0 or 1.",
-            ),
+            info: error_info.into(),
         })
     }
 }
@@ -441,16 +796,30 @@ pub fn felt_to_bool(felt: StarkFelt) -> DeprecatedSyscallResult<bool> {
 pub fn read_calldata(
     vm: &VirtualMachine,
     ptr: &mut Relocatable,
+    read_context: &SyscallReadContext,
 ) -> DeprecatedSyscallResult<Calldata> {
-    Ok(Calldata(read_felt_array::<DeprecatedSyscallExecutionError>(vm, ptr)?.into()))
+    // Verified before the calldata is copied out of the VM memory.
+    let (calldata_start_ptr, calldata_length) =
+        read_array_bounds::<DeprecatedSyscallExecutionError>(vm, ptr, read_context)?;
+    read_context.verify_calldata_length(calldata_length)?;
+
+    Ok(Calldata(felt_range_from_ptr(vm, calldata_start_ptr, calldata_length)?.into()))
 }
 
 pub fn read_call_params(
     vm: &VirtualMachine,
     ptr: &mut Relocatable,
+    read_context: &SyscallReadContext,
 ) -> DeprecatedSyscallResult<(EntryPointSelector, Calldata)> {
-    let function_selector = EntryPointSelector(stark_felt_from_ptr(vm, ptr)?);
-    let calldata = read_calldata(vm, ptr)?;
+    let raw_selector = stark_felt_from_ptr(vm, ptr)?;
+    if !is_valid_selector(raw_selector) {
+        return Err(DeprecatedSyscallExecutionError::InvalidEntryPointSelector {
+            syscall: read_context.selector,
+            selector: raw_selector,
+        });
+    }
+    let function_selector = EntryPointSelector(raw_selector);
+    let calldata = read_calldata(vm, ptr, read_context)?;
 
     Ok((function_selector, calldata))
 }
@@ -460,15 +829,48 @@ pub fn execute_inner_call(
     vm: &mut VirtualMachine,
     syscall_handler: &mut DeprecatedSyscallHintProcessor<'_>,
 ) -> DeprecatedSyscallResult<ReadOnlySegment> {
-    let call_info =
-        call.execute(syscall_handler.state, syscall_handler.resources, syscall_handler.context)?;
-    let retdata = &call_info.execution.retdata.0;
-    let retdata: Vec<MaybeRelocatable> =
-        retdata.iter().map(|&x| MaybeRelocatable::from(stark_felt_to_felt(x))).collect();
-    let retdata_segment_start_ptr = syscall_handler.read_only_segments.allocate(vm, &retdata)?;
-
-    syscall_handler.inner_calls.push(call_info);
-    Ok(ReadOnlySegment { start_ptr: retdata_segment_start_ptr, length: retdata.len() })
+    execute_inner_call_with(vm, syscall_handler, |state, resources, context| {
+        call.execute(state, resources, context)
+    })
+}
+
+/// Same as [`execute_inner_call`], with the inner call executed by the given function.
+fn execute_inner_call_with(
+    vm: &mut VirtualMachine,
+    syscall_handler: &mut DeprecatedSyscallHintProcessor<'_>,
+    execute: impl FnOnce(
+        &mut dyn State,
+        &mut ExecutionResources,
+        &mut EntryPointExecutionContext,
+    ) -> EntryPointExecutionResult<CallInfo>,
+) -> DeprecatedSyscallResult<ReadOnlySegment> {
+    let call_info = syscall_handler.execute_inner(execute)?;
+    let retdata_segment = syscall_handler.write_felt_array(vm, &call_info.execution.retdata.0)?;
+
+    syscall_handler.add_inner_call(call_info);
+    Ok(retdata_segment)
+}
+
+/// Executes an overridden call: records it as an inner call returning the overriding return data,
+/// without executing the called contract.
+pub fn execute_overridden_call(
+    mut call: CallEntryPoint,
+    call_override: CallContractOverride,
+    vm: &mut VirtualMachine,
+    syscall_handler: &mut DeprecatedSyscallHintProcessor<'_>,
+) -> DeprecatedSyscallResult<ReadOnlySegment> {
+    let retdata = call_override(&call)?;
+    let retdata_segment = syscall_handler.write_felt_array(vm, &retdata.0)?;
+
+    // The class hash of the called contract, as resolved for executed calls; undeployed
+    // contracts may be overridden as well.
+    call.class_hash = Some(syscall_handler.state.get_class_hash_at(call.storage_address)?);
+    syscall_handler.add_inner_call(CallInfo {
+        call,
+        execution: CallExecution { retdata, ..Default::default() },
+        ..Default::default()
+    });
+    Ok(retdata_segment)
 }
 
 pub fn execute_library_call(
@@ -476,40 +878,84 @@ pub fn execute_library_call(
     vm: &mut VirtualMachine,
     class_hash: ClassHash,
     code_address: Option<ContractAddress>,
-    call_to_external: bool,
+    kind: LibraryCallKind,
     entry_point_selector: EntryPointSelector,
     calldata: Calldata,
 ) -> DeprecatedSyscallResult<ReadOnlySegment> {
-    let entry_point_type =
-        if call_to_external { EntryPointType::External } else { EntryPointType::L1Handler };
-    let entry_point = CallEntryPoint {
-        class_hash: Some(class_hash),
-        code_address,
-        entry_point_type,
+    // The call context remains the same in a library call.
+    let class_code_call = ClassCodeCall {
+        class_hash,
+        kind,
         entry_point_selector,
         calldata,
-        // The call context remains the same in a library call.
-        storage_address: syscall_handler.storage_address,
-        caller_address: syscall_handler.caller_address,
-        call_type: CallType::Delegate,
+        storage_address: syscall_handler.storage_address(),
+        caller_address: syscall_handler.caller_address(),
+        code_address,
         initial_gas: syscall_handler.context.get_gas_cost("initial_gas_cost"),
     };
 
-    execute_inner_call(entry_point, vm, syscall_handler).map_err(|error| {
-        error.as_lib_call_execution_error(class_hash, syscall_handler.storage_address)
+    execute_inner_call_with(vm, syscall_handler, |state, resources, context| {
+        execute_class_code(state, resources, context, class_code_call)
+    })
+    .map_err(|error| {
+        error.as_lib_call_execution_error(class_hash, syscall_handler.storage_address())
     })
 }
 
 pub fn read_felt_array<TErr>(
     vm: &VirtualMachine,
     ptr: &mut Relocatable,
+    read_context: &SyscallReadContext,
 ) -> Result<Vec<StarkFelt>, TErr>
 where
-    TErr: From<StarknetApiError> + From<VirtualMachineError> + From<MemoryError> + From<MathError>,
+    TErr: From<StarknetApiError>
+        + From<VirtualMachineError>
+        + From<MemoryError>
+        + From<MathError>
+        + From<ArrayLengthError>
+        + From<BoundedFeltError>,
+{
+    let (array_data_start_ptr, array_size) = read_array_bounds::<TErr>(vm, ptr, read_context)?;
+
+    Ok(felt_range_from_ptr(vm, array_data_start_ptr, array_size)?)
+}
+
+/// Reads the (start pointer, validated length) pair of an array in a syscall request, without
+/// reading the array itself.
+fn read_array_bounds<TErr>(
+    vm: &VirtualMachine,
+    ptr: &mut Relocatable,
+    read_context: &SyscallReadContext,
+) -> Result<(Relocatable, usize), TErr>
+where
+    TErr: From<StarknetApiError>
+        + From<VirtualMachineError>
+        + From<MemoryError>
+        + From<MathError>
+        + From<ArrayLengthError>
+        + From<BoundedFeltError>,
 {
-    let array_size = stark_felt_from_ptr(vm, ptr)?;
+    let array_size = usize_from_ptr(vm, ptr).map_err(|error| match error {
+        BoundedFeltError::ExceedsBound { felt, .. } => {
+            ArrayLengthError::InvalidLength { syscall: read_context.selector, length: felt }.into()
+        }
+        error => TErr::from(error),
+    })?;
     let array_data_start_ptr = vm.get_relocatable(*ptr)?;
     *ptr = (*ptr + 1)?;
+    validate_array_length(vm, array_data_start_ptr, array_size, read_context)?;
+
+    Ok((array_data_start_ptr, array_size))
+}
+
+/// Writes the given segment as a (length, pointer) pair, the layout Cairo 0 expects for arrays.
+pub fn write_segment(
+    vm: &mut VirtualMachine,
+    ptr: &mut Relocatable,
+    segment: ReadOnlySegment,
+) -> WriteResponseResult {
+    write_maybe_relocatable(vm, ptr, segment.length)?;
+    write_maybe_relocatable(vm, ptr, segment.start_ptr)?;
 
-    Ok(felt_range_from_ptr(vm, array_data_start_ptr, usize::try_from(array_size)?)?)
+    Ok(())
 }
@@ -1,4 +1,4 @@
-use cairo_felt::Felt252;
+use cairo_vm::types::program::Program;
 use cairo_vm::types::relocatable::Relocatable;
 use cairo_vm::vm::vm_core::VirtualMachine;
 use serde::Deserialize;
@@ -15,17 +15,23 @@ use starknet_api::transaction::{
 use strum_macros::EnumIter;
 
 use self::hint_processor::{
-    execute_inner_call, execute_library_call, felt_to_bool, read_call_params, read_calldata,
-    read_felt_array, DeprecatedSyscallExecutionError, DeprecatedSyscallHintProcessor,
+    execute_inner_call, execute_library_call, execute_overridden_call, felt_to_bool,
+    read_call_params, read_calldata, read_felt_array, write_segment,
+    DeprecatedSyscallExecutionError, DeprecatedSyscallHintProcessor,
 };
 use super::syscalls::exceeds_event_size_limit;
-use crate::execution::call_info::{MessageToL1, OrderedEvent, OrderedL2ToL1Message};
+use crate::execution::call_info::MessageToL1;
 use crate::execution::common_hints::ExecutionMode;
-use crate::execution::entry_point::{CallEntryPoint, CallType, ConstructorContext};
+use crate::execution::entry_point::{
+    CallEntryPoint, CallType, ConstructorContext, LibraryCallKind,
+};
+use crate::execution::errors::{EntryPointExecutionError, PreExecutionError};
 use crate::execution::execution_utils::{
-    execute_deployment, stark_felt_from_ptr, write_maybe_relocatable, write_stark_felt,
+    execute_deployment, stark_felt_from_ptr, write_maybe_relocatable, write_stark_felt, write_u64,
     ReadOnlySegment,
 };
+use crate::execution::syscalls::hint_processor::SyscallReadContext;
+use crate::versioned_constants::SyscallResponseLayout;
 
 #[cfg(test)]
 #[path = "deprecated_syscalls_test.rs"]
@@ -46,6 +52,7 @@ pub enum DeprecatedSyscallSelector {
     GetBlockNumber,
     GetBlockTimestamp,
     GetCallerAddress,
+    GetCompiledClassHash,
     GetContractAddress,
     GetExecutionInfo,
     GetSequencerAddress,
@@ -87,6 +94,7 @@ impl TryFrom<StarkFelt> for DeprecatedSyscallSelector {
             b"GetBlockNumber" => Ok(Self::GetBlockNumber),
             b"GetBlockTimestamp" => Ok(Self::GetBlockTimestamp),
             b"GetCallerAddress" => Ok(Self::GetCallerAddress),
+            b"GetCompiledClassHash" => Ok(Self::GetCompiledClassHash),
             b"GetContractAddress" => Ok(Self::GetContractAddress),
             b"GetExecutionInfo" => Ok(Self::GetExecutionInfo),
             b"GetSequencerAddress" => Ok(Self::GetSequencerAddress),
@@ -117,10 +125,20 @@ impl TryFrom<StarkFelt> for DeprecatedSyscallSelector {
 }
 
 pub trait SyscallRequest: Sized {
-    fn read(_vm: &VirtualMachine, _ptr: &mut Relocatable) -> DeprecatedSyscallResult<Self>;
+    /// The number of cells the request occupies in the syscall segment, excluding the selector.
+    const SIZE: usize;
+
+    fn read(
+        _vm: &VirtualMachine,
+        _ptr: &mut Relocatable,
+        _read_context: &SyscallReadContext,
+    ) -> DeprecatedSyscallResult<Self>;
 }
 
 pub trait SyscallResponse {
+    /// The number of cells the response occupies in the syscall segment.
+    const SIZE: usize;
+
     fn write(self, _vm: &mut VirtualMachine, _ptr: &mut Relocatable) -> WriteResponseResult;
 }
 
@@ -130,7 +148,13 @@ pub trait SyscallResponse {
 pub struct EmptyRequest;
 
 impl SyscallRequest for EmptyRequest {
-    fn read(_vm: &VirtualMachine, _ptr: &mut Relocatable) -> DeprecatedSyscallResult<EmptyRequest> {
+    const SIZE: usize = 0;
+
+    fn read(
+        _vm: &VirtualMachine,
+        _ptr: &mut Relocatable,
+        _read_context: &SyscallReadContext,
+    ) -> DeprecatedSyscallResult<EmptyRequest> {
         Ok(EmptyRequest)
     }
 }
@@ -139,6 +163,8 @@ impl SyscallRequest for EmptyRequest {
 pub struct EmptyResponse;
 
 impl SyscallResponse for EmptyResponse {
+    const SIZE: usize = 0;
+
     fn write(self, _vm: &mut VirtualMachine, _ptr: &mut Relocatable) -> WriteResponseResult {
         Ok(())
     }
@@ -150,10 +176,10 @@ pub struct SingleSegmentResponse {
 }
 
 impl SyscallResponse for SingleSegmentResponse {
+    const SIZE: usize = 2;
+
     fn write(self, vm: &mut VirtualMachine, ptr: &mut Relocatable) -> WriteResponseResult {
-        write_maybe_relocatable(vm, ptr, self.segment.length)?;
-        write_maybe_relocatable(vm, ptr, self.segment.start_ptr)?;
-        Ok(())
+        write_segment(vm, ptr, self.segment)
     }
 }
 
@@ -167,12 +193,15 @@ pub struct CallContractRequest {
 }
 
 impl SyscallRequest for CallContractRequest {
+    const SIZE: usize = 4;
+
     fn read(
         vm: &VirtualMachine,
         ptr: &mut Relocatable,
+        read_context: &SyscallReadContext,
     ) -> DeprecatedSyscallResult<CallContractRequest> {
         let contract_address = ContractAddress::try_from(stark_felt_from_ptr(vm, ptr)?)?;
-        let (function_selector, calldata) = read_call_params(vm, ptr)?;
+        let (function_selector, calldata) = read_call_params(vm, ptr, read_context)?;
 
         Ok(CallContractRequest { contract_address, function_selector, calldata })
     }
@@ -187,7 +216,7 @@ pub fn call_contract(
 ) -> DeprecatedSyscallResult<CallContractResponse> {
     let storage_address = request.contract_address;
     // Check that the call is legal if in Validate execution mode.
-    if syscall_handler.is_validate_mode() && syscall_handler.storage_address != storage_address {
+    if syscall_handler.is_validate_mode() && syscall_handler.storage_address() != storage_address {
         let error = DeprecatedSyscallExecutionError::InvalidSyscallInExecutionMode {
             syscall_name: "call_contract".to_string(),
             execution_mode: syscall_handler.execution_mode(),
@@ -201,10 +230,24 @@ pub fn call_contract(
         entry_point_selector: request.function_selector,
         calldata: request.calldata,
         storage_address,
-        caller_address: syscall_handler.storage_address,
+        caller_address: syscall_handler.storage_address(),
         call_type: CallType::Call,
         initial_gas: syscall_handler.context.get_gas_cost("initial_gas_cost"),
     };
+    if let Some(call_override) =
+        syscall_handler.syscall_overrides().call_contract_override(storage_address)
+    {
+        let retdata_segment =
+            execute_overridden_call(entry_point, call_override, vm, syscall_handler)
+                .map_err(|error| error.as_call_contract_execution_error(storage_address))?;
+        return Ok(CallContractResponse { segment: retdata_segment });
+    }
+    if syscall_handler.state.get_class_hash_at(storage_address)? == ClassHash::default() {
+        return Err(DeprecatedSyscallExecutionError::from(EntryPointExecutionError::from(
+            PreExecutionError::UninitializedStorageAddress(storage_address),
+        ))
+        .as_call_contract_execution_error(storage_address));
+    }
     let retdata_segment = execute_inner_call(entry_point, vm, syscall_handler)
         .map_err(|error| error.as_call_contract_execution_error(storage_address))?;
 
@@ -213,7 +256,28 @@ pub fn call_contract(
 
 // DelegateCall syscall.
 
-type DelegateCallRequest = CallContractRequest;
+#[derive(Debug, Eq, PartialEq)]
+pub struct DelegateCallRequest {
+    pub contract_address: ContractAddress,
+    pub function_selector: EntryPointSelector,
+    pub calldata: Calldata,
+}
+
+impl SyscallRequest for DelegateCallRequest {
+    const SIZE: usize = 4;
+
+    fn read(
+        vm: &VirtualMachine,
+        ptr: &mut Relocatable,
+        read_context: &SyscallReadContext,
+    ) -> DeprecatedSyscallResult<DelegateCallRequest> {
+        let contract_address = ContractAddress::try_from(stark_felt_from_ptr(vm, ptr)?)?;
+        let (function_selector, calldata) = read_call_params(vm, ptr, read_context)?;
+
+        Ok(DelegateCallRequest { contract_address, function_selector, calldata })
+    }
+}
+
 type DelegateCallResponse = CallContractResponse;
 
 pub fn delegate_call(
@@ -221,15 +285,20 @@ pub fn delegate_call(
     vm: &mut VirtualMachine,
     syscall_handler: &mut DeprecatedSyscallHintProcessor<'_>,
 ) -> DeprecatedSyscallResult<DelegateCallResponse> {
-    let call_to_external = true;
     let storage_address = request.contract_address;
     let class_hash = syscall_handler.state.get_class_hash_at(storage_address)?;
+    if class_hash == ClassHash::default() {
+        return Err(EntryPointExecutionError::from(
+            PreExecutionError::UninitializedStorageAddress(storage_address),
+        )
+        .into());
+    }
     let retdata_segment = execute_library_call(
         syscall_handler,
         vm,
         class_hash,
         Some(storage_address),
-        call_to_external,
+        LibraryCallKind::External,
         request.function_selector,
         request.calldata,
     )?;
@@ -244,7 +313,6 @@ pub fn delegate_l1_handler(
     vm: &mut VirtualMachine,
     syscall_handler: &mut DeprecatedSyscallHintProcessor<'_>,
 ) -> DeprecatedSyscallResult<DelegateCallResponse> {
-    let call_to_external = false;
     let storage_address = request.contract_address;
     let class_hash = syscall_handler.state.get_class_hash_at(storage_address)?;
     let retdata_segment = execute_library_call(
@@ -252,7 +320,7 @@ pub fn delegate_l1_handler(
         vm,
         class_hash,
         Some(storage_address),
-        call_to_external,
+        LibraryCallKind::L1Handler,
         request.function_selector,
         request.calldata,
     )?;
@@ -271,44 +339,65 @@ pub struct DeployRequest {
 }
 
 impl SyscallRequest for DeployRequest {
-    fn read(vm: &VirtualMachine, ptr: &mut Relocatable) -> DeprecatedSyscallResult<DeployRequest> {
+    const SIZE: usize = 5;
+
+    fn read(
+        vm: &VirtualMachine,
+        ptr: &mut Relocatable,
+        read_context: &SyscallReadContext,
+    ) -> DeprecatedSyscallResult<DeployRequest> {
         let class_hash = ClassHash(stark_felt_from_ptr(vm, ptr)?);
         let contract_address_salt = ContractAddressSalt(stark_felt_from_ptr(vm, ptr)?);
-        let constructor_calldata = read_calldata(vm, ptr)?;
+        let constructor_calldata = read_calldata(vm, ptr, read_context)?;
         let deploy_from_zero = stark_felt_from_ptr(vm, ptr)?;
 
         Ok(DeployRequest {
             class_hash,
             contract_address_salt,
             constructor_calldata,
-            deploy_from_zero: felt_to_bool(deploy_from_zero)?,
+            deploy_from_zero: felt_to_bool(
+                deploy_from_zero,
+                "The deploy_from_zero field in the deploy system call must be 0 or 1.",
+            )?,
         })
     }
 }
 
-#[derive(Debug, Eq, PartialEq)]
+#[derive(Debug)]
 pub struct DeployResponse {
     pub contract_address: ContractAddress,
+    /// Only given in the OS-aligned response layout.
+    pub constructor_retdata: Option<ReadOnlySegment>,
 }
 
 impl SyscallResponse for DeployResponse {
+    const SIZE: usize = 3;
+
     // The Cairo struct contains: `contract_address`, `constructor_retdata_size`,
     // `constructor_retdata`.
-    // Nonempty constructor retdata is currently not supported.
     fn write(self, vm: &mut VirtualMachine, ptr: &mut Relocatable) -> WriteResponseResult {
         write_stark_felt(vm, ptr, *self.contract_address.0.key())?;
-        write_maybe_relocatable(vm, ptr, 0)?;
-        write_maybe_relocatable(vm, ptr, 0)?;
+        match self.constructor_retdata {
+            Some(constructor_retdata) => {
+                write_maybe_relocatable(vm, ptr, constructor_retdata.length)?;
+                write_maybe_relocatable(vm, ptr, constructor_retdata.start_ptr)?;
+            }
+            // In the legacy layout, nonempty constructor retdata is not supported.
+            None => {
+                write_maybe_relocatable(vm, ptr, 0)?;
+                write_maybe_relocatable(vm, ptr, 0)?;
+            }
+        }
         Ok(())
     }
 }
 
 pub fn deploy(
     request: DeployRequest,
-    _vm: &mut VirtualMachine,
+    vm: &mut VirtualMachine,
     syscall_handler: &mut DeprecatedSyscallHintProcessor<'_>,
 ) -> DeprecatedSyscallResult<DeployResponse> {
-    let deployer_address = syscall_handler.storage_address;
+    let deployer_address = syscall_handler.storage_address();
     let deployer_address_for_calculation = match request.deploy_from_zero {
         true => ContractAddress::default(),
         false => deployer_address,
@@ -326,17 +415,26 @@ pub fn deploy(
         storage_address: deployed_contract_address,
         caller_address: deployer_address,
     };
-    let call_info = execute_deployment(
-        syscall_handler.state,
-        syscall_handler.resources,
-        syscall_handler.context,
-        ctor_context,
-        request.constructor_calldata,
-        syscall_handler.context.get_gas_cost("initial_gas_cost"),
-    )?;
-    syscall_handler.inner_calls.push(call_info);
+    let initial_gas = syscall_handler.context.get_gas_cost("initial_gas_cost");
+    let call_info = syscall_handler.execute_inner(|state, resources, context| {
+        execute_deployment(
+            state,
+            resources,
+            context,
+            ctor_context,
+            request.constructor_calldata,
+            initial_gas,
+        )
+    })?;
+    let constructor_retdata = match syscall_handler.context.syscall_response_layout {
+        SyscallResponseLayout::Legacy => None,
+        SyscallResponseLayout::OsAligned => {
+            Some(syscall_handler.write_felt_array(vm, &call_info.execution.retdata.0)?)
+        }
+    };
+    syscall_handler.add_inner_call(call_info);
 
-    Ok(DeployResponse { contract_address: deployed_contract_address })
+    Ok(DeployResponse { contract_address: deployed_contract_address, constructor_retdata })
 }
 
 // EmitEvent syscall.
@@ -347,16 +445,20 @@ pub struct EmitEventRequest {
 }
 
 impl SyscallRequest for EmitEventRequest {
+    const SIZE: usize = 4;
+
     // The Cairo struct contains: `keys_len`, `keys`, `data_len`, `data`·
     fn read(
         vm: &VirtualMachine,
         ptr: &mut Relocatable,
+        read_context: &SyscallReadContext,
     ) -> DeprecatedSyscallResult<EmitEventRequest> {
-        let keys = read_felt_array::<DeprecatedSyscallExecutionError>(vm, ptr)?
+        let keys = read_felt_array::<DeprecatedSyscallExecutionError>(vm, ptr, read_context)?
             .into_iter()
             .map(EventKey)
             .collect();
-        let data = EventData(read_felt_array::<DeprecatedSyscallExecutionError>(vm, ptr)?);
+        let data =
+            EventData(read_felt_array::<DeprecatedSyscallExecutionError>(vm, ptr, read_context)?);
 
         Ok(EmitEventRequest { content: EventContent { keys, data } })
     }
@@ -375,10 +477,11 @@ pub fn emit_event(
         execution_context.n_emitted_events + 1,
         &request.content,
     )?;
-    let ordered_event =
-        OrderedEvent { order: execution_context.n_emitted_events, event: request.content };
-    syscall_handler.events.push(ordered_event);
-    execution_context.n_emitted_events += 1;
+    syscall_handler.increment_linear_factor_by(
+        &DeprecatedSyscallSelector::EmitEvent,
+        request.content.data.0.len(),
+    );
+    syscall_handler.add_event(request.content);
 
     Ok(EmitEventResponse {})
 }
@@ -393,8 +496,10 @@ pub struct GetBlockNumberResponse {
 }
 
 impl SyscallResponse for GetBlockNumberResponse {
+    const SIZE: usize = 1;
+
     fn write(self, vm: &mut VirtualMachine, ptr: &mut Relocatable) -> WriteResponseResult {
-        write_maybe_relocatable(vm, ptr, Felt252::from(self.block_number.0))?;
+        write_u64(vm, ptr, self.block_number.0)?;
         Ok(())
     }
 }
@@ -426,8 +531,10 @@ pub struct GetBlockTimestampResponse {
 }
 
 impl SyscallResponse for GetBlockTimestampResponse {
+    const SIZE: usize = 1;
+
     fn write(self, vm: &mut VirtualMachine, ptr: &mut Relocatable) -> WriteResponseResult {
-        write_maybe_relocatable(vm, ptr, Felt252::from(self.block_timestamp.0))?;
+        write_u64(vm, ptr, self.block_timestamp.0)?;
         Ok(())
     }
 }
@@ -459,7 +566,7 @@ pub fn get_caller_address(
     _vm: &mut VirtualMachine,
     syscall_handler: &mut DeprecatedSyscallHintProcessor<'_>,
 ) -> DeprecatedSyscallResult<GetCallerAddressResponse> {
-    Ok(GetCallerAddressResponse { address: syscall_handler.caller_address })
+    Ok(GetCallerAddressResponse { address: syscall_handler.caller_address() })
 }
 
 // GetContractAddress syscall.
@@ -472,6 +579,8 @@ pub struct GetContractAddressResponse {
 }
 
 impl SyscallResponse for GetContractAddressResponse {
+    const SIZE: usize = 1;
+
     fn write(self, vm: &mut VirtualMachine, ptr: &mut Relocatable) -> WriteResponseResult {
         write_stark_felt(vm, ptr, *self.address.0.key())?;
         Ok(())
@@ -483,7 +592,7 @@ pub fn get_contract_address(
     _vm: &mut VirtualMachine,
     syscall_handler: &mut DeprecatedSyscallHintProcessor<'_>,
 ) -> DeprecatedSyscallResult<GetContractAddressResponse> {
-    Ok(GetContractAddressResponse { address: syscall_handler.storage_address })
+    Ok(GetContractAddressResponse { address: syscall_handler.storage_address() })
 }
 
 // GetSequencerAddress syscall.
@@ -510,11 +619,46 @@ pub struct GetTxInfoResponse {
 }
 
 impl SyscallResponse for GetTxInfoResponse {
+    const SIZE: usize = 1;
+
     fn write(self, vm: &mut VirtualMachine, ptr: &mut Relocatable) -> WriteResponseResult {
         write_maybe_relocatable(vm, ptr, self.tx_info_start_ptr)?;
         Ok(())
     }
 }
+
+/// The layout of the transaction info struct returned by the `get_tx_info` syscall.
+#[derive(Clone, Copy, Debug, Default, Eq, PartialEq)]
+pub enum TxInfoLayout {
+    /// Version, account address, max fee, signature (length and pointer), hash, chain ID and
+    /// nonce.
+    #[default]
+    Deprecated,
+    /// The deprecated layout, followed by the fields of V3 transactions: resource bounds, tip,
+    /// paymaster data, nonce and fee data availability modes, and account deployment data.
+    /// The arrays are given by (start, end) pointers; the fields of deprecated transactions are
+    /// zero.
+    Extended,
+}
+
+impl TxInfoLayout {
+    const TX_INFO_STRUCT: &'static str = "starkware.starknet.common.syscalls.TxInfo";
+    // The first member of the extended layout.
+    const EXTENDED_LAYOUT_MEMBER: &'static str = "resource_bounds_start";
+
+    /// Returns the layout expected by the given program, according to its declaration of the
+    /// transaction info struct.
+    pub fn from_program(program: &Program) -> Self {
+        let members = program
+            .get_identifier(Self::TX_INFO_STRUCT)
+            .and_then(|identifier| identifier.members.as_ref());
+        match members {
+            Some(members) if members.contains_key(Self::EXTENDED_LAYOUT_MEMBER) => Self::Extended,
+            _ => Self::Deprecated,
+        }
+    }
+}
+
 pub fn get_tx_info(
     _request: GetTxInfoRequest,
     vm: &mut VirtualMachine,
@@ -551,12 +695,15 @@ pub struct LibraryCallRequest {
 }
 
 impl SyscallRequest for LibraryCallRequest {
+    const SIZE: usize = 4;
+
     fn read(
         vm: &VirtualMachine,
         ptr: &mut Relocatable,
+        read_context: &SyscallReadContext,
     ) -> DeprecatedSyscallResult<LibraryCallRequest> {
         let class_hash = ClassHash(stark_felt_from_ptr(vm, ptr)?);
-        let (function_selector, calldata) = read_call_params(vm, ptr)?;
+        let (function_selector, calldata) = read_call_params(vm, ptr, read_context)?;
 
         Ok(LibraryCallRequest { class_hash, function_selector, calldata })
     }
@@ -569,13 +716,12 @@ pub fn library_call(
     vm: &mut VirtualMachine,
     syscall_handler: &mut DeprecatedSyscallHintProcessor<'_>,
 ) -> DeprecatedSyscallResult<LibraryCallResponse> {
-    let call_to_external = true;
     let retdata_segment = execute_library_call(
         syscall_handler,
         vm,
         request.class_hash,
         None,
-        call_to_external,
+        LibraryCallKind::External,
         request.function_selector,
         request.calldata,
     )?;
@@ -590,13 +736,12 @@ pub fn library_call_l1_handler(
     vm: &mut VirtualMachine,
     syscall_handler: &mut DeprecatedSyscallHintProcessor<'_>,
 ) -> DeprecatedSyscallResult<LibraryCallResponse> {
-    let call_to_external = false;
     let retdata_segment = execute_library_call(
         syscall_handler,
         vm,
         request.class_hash,
         None,
-        call_to_external,
+        LibraryCallKind::L1Handler,
         request.function_selector,
         request.calldata,
     )?;
@@ -612,9 +757,12 @@ pub struct ReplaceClassRequest {
 }
 
 impl SyscallRequest for ReplaceClassRequest {
+    const SIZE: usize = 1;
+
     fn read(
         vm: &VirtualMachine,
         ptr: &mut Relocatable,
+        _read_context: &SyscallReadContext,
     ) -> DeprecatedSyscallResult<ReplaceClassRequest> {
         let class_hash = ClassHash(stark_felt_from_ptr(vm, ptr)?);
 
@@ -631,7 +779,9 @@ pub fn replace_class(
 ) -> DeprecatedSyscallResult<ReplaceClassResponse> {
     // Ensure the class is declared (by reading it).
     syscall_handler.state.get_compiled_contract_class(request.class_hash)?;
-    syscall_handler.state.set_class_hash_at(syscall_handler.storage_address, request.class_hash)?;
+    syscall_handler
+        .state
+        .replace_class_hash_at(syscall_handler.storage_address(), request.class_hash)?;
 
     Ok(ReplaceClassResponse {})
 }
@@ -644,13 +794,20 @@ pub struct SendMessageToL1Request {
 }
 
 impl SyscallRequest for SendMessageToL1Request {
+    const SIZE: usize = 3;
+
     // The Cairo struct contains: `to_address`, `payload_size`, `payload`.
     fn read(
         vm: &VirtualMachine,
         ptr: &mut Relocatable,
+        read_context: &SyscallReadContext,
     ) -> DeprecatedSyscallResult<SendMessageToL1Request> {
         let to_address = EthAddress::try_from(stark_felt_from_ptr(vm, ptr)?)?;
-        let payload = L2ToL1Payload(read_felt_array::<DeprecatedSyscallExecutionError>(vm, ptr)?);
+        let payload = L2ToL1Payload(read_felt_array::<DeprecatedSyscallExecutionError>(
+            vm,
+            ptr,
+            read_context,
+        )?);
 
         Ok(SendMessageToL1Request { message: MessageToL1 { to_address, payload } })
     }
@@ -663,13 +820,11 @@ pub fn send_message_to_l1(
     _vm: &mut VirtualMachine,
     syscall_handler: &mut DeprecatedSyscallHintProcessor<'_>,
 ) -> DeprecatedSyscallResult<SendMessageToL1Response> {
-    let execution_context = &mut syscall_handler.context;
-    let ordered_message_to_l1 = OrderedL2ToL1Message {
-        order: execution_context.n_sent_messages_to_l1,
-        message: request.message,
-    };
-    syscall_handler.l2_to_l1_messages.push(ordered_message_to_l1);
-    execution_context.n_sent_messages_to_l1 += 1;
+    syscall_handler.increment_linear_factor_by(
+        &DeprecatedSyscallSelector::SendMessageToL1,
+        request.message.payload.0.len(),
+    );
+    syscall_handler.add_l2_to_l1_message(request.message);
 
     Ok(SendMessageToL1Response {})
 }
@@ -682,9 +837,12 @@ pub struct StorageReadRequest {
 }
 
 impl SyscallRequest for StorageReadRequest {
+    const SIZE: usize = 1;
+
     fn read(
         vm: &VirtualMachine,
         ptr: &mut Relocatable,
+        _read_context: &SyscallReadContext,
     ) -> DeprecatedSyscallResult<StorageReadRequest> {
         let address = StorageKey::try_from(stark_felt_from_ptr(vm, ptr)?)?;
         Ok(StorageReadRequest { address })
@@ -697,6 +855,8 @@ pub struct StorageReadResponse {
 }
 
 impl SyscallResponse for StorageReadResponse {
+    const SIZE: usize = 1;
+
     fn write(self, vm: &mut VirtualMachine, ptr: &mut Relocatable) -> WriteResponseResult {
         write_stark_felt(vm, ptr, self.value)?;
         Ok(())
@@ -720,9 +880,12 @@ pub struct StorageWriteRequest {
 }
 
 impl SyscallRequest for StorageWriteRequest {
+    const SIZE: usize = 2;
+
     fn read(
         vm: &VirtualMachine,
         ptr: &mut Relocatable,
+        _read_context: &SyscallReadContext,
     ) -> DeprecatedSyscallResult<StorageWriteRequest> {
         let address = StorageKey::try_from(stark_felt_from_ptr(vm, ptr)?)?;
         let value = stark_felt_from_ptr(vm, ptr)?;
@@ -1,5 +1,6 @@
 use std::cell::RefCell;
 use std::cmp::min;
+use std::rc::Rc;
 use std::sync::Arc;
 
 use cairo_vm::vm::runners::cairo_runner::{ExecutionResources, ResourceTracker, RunResources};
@@ -14,13 +15,21 @@ use crate::abi::constants;
 use crate::context::{BlockContext, TransactionContext};
 use crate::execution::call_info::CallInfo;
 use crate::execution::common_hints::ExecutionMode;
+use crate::execution::custom_hints::CustomHintRegistry;
+use crate::execution::deprecated_syscalls::hint_processor::SyscallOverrides;
 use crate::execution::errors::{EntryPointExecutionError, PreExecutionError};
 use crate::execution::execution_utils::execute_entry_point_call;
-use crate::state::state_api::State;
-use crate::transaction::objects::{HasRelatedFeeType, TransactionExecutionResult, TransactionInfo};
+use crate::execution::syscalls::hint_processor::{
+    SyscallExecutor, SyscallMetricsMap, SyscallTable,
+};
+use crate::state::state_api::{State, StateResult};
+use crate::transaction::errors::TransactionFeeError;
+use crate::transaction::objects::{
+    HasRelatedFeeType, Resource, TransactionExecutionResult, TransactionInfo,
+};
 use crate::transaction::transaction_types::TransactionType;
 use crate::utils::usize_from_u128;
-use crate::versioned_constants::VersionedConstants;
+use crate::versioned_constants::{OsResourcesError, SyscallResponseLayout, VersionedConstants};
 
 #[cfg(test)]
 #[path = "entry_point_test.rs"]
@@ -38,6 +47,40 @@ pub enum CallType {
     Call = 0,
     Delegate = 1,
 }
+
+/// The kind of entry point a library call (or a delegate call) runs.
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub enum LibraryCallKind {
+    External,
+    L1Handler,
+}
+
+impl LibraryCallKind {
+    pub fn entry_point_type(self) -> EntryPointType {
+        match self {
+            Self::External => EntryPointType::External,
+            Self::L1Handler => EntryPointType::L1Handler,
+        }
+    }
+
+    /// Returns whether the given class is known to lack the called entry point before the call
+    /// runs. Only L1 handler calls are checked here (where a Cairo 0 default entry point counts as
+    /// a match); a missing external entry point is detected when the call executes.
+    pub fn lacks_entry_point(
+        self,
+        state: &dyn State,
+        class_hash: ClassHash,
+        entry_point_selector: EntryPointSelector,
+    ) -> StateResult<bool> {
+        if self != Self::L1Handler {
+            return Ok(false);
+        }
+
+        let contract_class = state.get_compiled_contract_class(class_hash)?;
+        Ok(!contract_class.has_entry_point(self.entry_point_type(), entry_point_selector))
+    }
+}
+
 /// Represents a call to an entry point of a Starknet contract.
 #[derive(Clone, Debug, Default, Eq, PartialEq, Serialize)]
 pub struct CallEntryPoint {
@@ -58,6 +101,30 @@ pub struct CallEntryPoint {
 }
 
 impl CallEntryPoint {
+    /// Returns a call running the code of the given class in the context (storage and caller) of
+    /// the given addresses.
+    pub fn new_library_call(
+        class_hash: ClassHash,
+        kind: LibraryCallKind,
+        entry_point_selector: EntryPointSelector,
+        calldata: Calldata,
+        storage_address: ContractAddress,
+        caller_address: ContractAddress,
+        initial_gas: u64,
+    ) -> Self {
+        Self {
+            class_hash: Some(class_hash),
+            code_address: None,
+            entry_point_type: kind.entry_point_type(),
+            entry_point_selector,
+            calldata,
+            storage_address,
+            caller_address,
+            call_type: CallType::Delegate,
+            initial_gas,
+        }
+    }
+
     pub fn execute(
         mut self,
         state: &mut dyn State,
@@ -121,6 +188,26 @@ impl CallEntryPoint {
     }
 }
 
+/// The context of an executing call, as exposed to the syscalls it invokes.
+#[derive(Clone, Debug, Default, Eq, PartialEq)]
+pub struct CallContext {
+    pub storage_address: ContractAddress,
+    pub caller_address: ContractAddress,
+    pub entry_point_selector: EntryPointSelector,
+    pub class_hash: ClassHash,
+}
+
+impl From<&CallEntryPoint> for CallContext {
+    fn from(call: &CallEntryPoint) -> Self {
+        Self {
+            storage_address: call.storage_address,
+            caller_address: call.caller_address,
+            entry_point_selector: call.entry_point_selector,
+            class_hash: call.class_hash.expect("Class hash must be set before execution."),
+        }
+    }
+}
+
 pub struct ConstructorContext {
     pub class_hash: ClassHash,
     // Only relevant in deploy syscall.
@@ -140,6 +227,12 @@ pub struct EntryPointExecutionContext {
     pub n_emitted_events: usize,
     /// Used for tracking L2-to-L1 messages order during the current execution.
     pub n_sent_messages_to_l1: usize,
+    /// The number of syscalls executed so far by the transaction; carried over between its
+    /// validation and execution contexts.
+    pub n_executed_syscalls: usize,
+    /// The maximal number of syscalls the transaction may issue, as set by the versioned
+    /// constants; unlimited if unset.
+    pub max_syscalls_per_tx: Option<usize>,
     /// Used to track error stack for call chain.
     pub error_stack: Vec<(ContractAddress, String)>,
 
@@ -148,6 +241,22 @@ pub struct EntryPointExecutionContext {
 
     // The execution mode affects the behavior of the hint processor.
     pub execution_mode: ExecutionMode,
+    /// Custom hints for Cairo 0 classes; only used if allowed by the block context.
+    pub custom_hint_registry: Rc<CustomHintRegistry>,
+    /// Syscalls to execute instead of (or in addition to) the built-in ones, in Cairo 1 classes;
+    /// see [`Self::register_syscall`].
+    pub(crate) custom_syscalls: SyscallTable,
+    /// Per-syscall profiling metrics of Cairo 1 syscalls, accumulated over all calls of the
+    /// execution; only collected if set, which is the initial state if enabled by the block
+    /// context.
+    pub syscall_metrics: Option<SyscallMetricsMap>,
+    /// Stubbed syscalls for Cairo 0 classes, used instead of the built-in ones.
+    pub syscall_overrides: Rc<SyscallOverrides>,
+    /// The layout of the syscall responses; determined by the versioned constants of the block.
+    pub syscall_response_layout: SyscallResponseLayout,
+    /// Whether to check that each Cairo 0 syscall advances the syscall pointer by exactly the
+    /// sizes of its request and response; a debugging aid for syscall layout mismatches.
+    pub validate_syscall_sizes: bool,
 }
 
 impl EntryPointExecutionContext {
@@ -157,14 +266,28 @@ impl EntryPointExecutionContext {
         limit_steps_by_resources: bool,
     ) -> TransactionExecutionResult<Self> {
         let max_steps = Self::max_steps(&tx_context, &mode, limit_steps_by_resources)?;
+        let syscall_response_layout =
+            tx_context.block_context.versioned_constants.syscall_response_layout;
+        let max_syscalls_per_tx =
+            tx_context.block_context.versioned_constants.syscall_limits.max_syscalls_per_tx;
+        let syscall_metrics =
+            tx_context.block_context.collect_syscall_metrics().then(SyscallMetricsMap::default);
         Ok(Self {
             vm_run_resources: RunResources::new(max_steps),
             n_emitted_events: 0,
             n_sent_messages_to_l1: 0,
+            n_executed_syscalls: 0,
+            max_syscalls_per_tx,
             error_stack: vec![],
             tx_context: tx_context.clone(),
             current_recursion_depth: Default::default(),
             execution_mode: mode,
+            custom_hint_registry: Rc::default(),
+            custom_syscalls: SyscallTable::default(),
+            syscall_metrics,
+            syscall_overrides: Rc::default(),
+            syscall_response_layout,
+            validate_syscall_sizes: false,
         })
     }
 
@@ -211,10 +334,11 @@ impl EntryPointExecutionContext {
 
         let gas_per_step = versioned_constants
             .vm_resource_fee_cost()
-            .get(constants::N_STEPS_RESOURCE)
-            .unwrap_or_else(|| {
-                panic!("{} must appear in `vm_resource_fee_cost`.", constants::N_STEPS_RESOURCE)
-            });
+            .get(&Resource::Steps)
+            .copied()
+            .ok_or(TransactionFeeError::CairoResourcesNotContainedInFeeCosts {
+                missing_resources: vec![Resource::Steps],
+            })?;
 
         // New transactions derive the step limit by the L1 gas resource bounds; deprecated
         // transactions derive this value from the `max_fee`.
@@ -260,6 +384,13 @@ impl EntryPointExecutionContext {
         self.vm_run_resources.get_n_steps().expect("The number of steps must be initialized.")
     }
 
+    /// Counts a syscall executed by any of the calls of the transaction.
+    /// Returns the syscall limit if the count exceeds it.
+    pub fn count_syscall(&mut self) -> Option<usize> {
+        self.n_executed_syscalls += 1;
+        self.max_syscalls_per_tx.filter(|&max_n_syscalls| self.n_executed_syscalls > max_n_syscalls)
+    }
+
     /// Subtracts the given number of steps from the currently available run resources.
     /// Used for limiting the number of steps available during the execution stage, to leave enough
     /// steps available for the fee transfer stage.
@@ -286,15 +417,15 @@ impl EntryPointExecutionContext {
         validate_call_info: &Option<CallInfo>,
         tx_type: &TransactionType,
         calldata_length: usize,
-    ) -> usize {
+    ) -> Result<usize, OsResourcesError> {
         let validate_steps = validate_call_info
             .as_ref()
             .map(|call_info| call_info.resources.n_steps)
             .unwrap_or_default();
 
         let overhead_steps =
-            self.versioned_constants().os_resources_for_tx_type(tx_type, calldata_length).n_steps;
-        self.subtract_steps(validate_steps + overhead_steps)
+            self.versioned_constants().os_resources_for_tx_type(tx_type, calldata_length)?.n_steps;
+        Ok(self.subtract_steps(validate_steps + overhead_steps))
     }
 
     /// Combines individual errors into a single stack trace string, with contract addresses printed
@@ -321,6 +452,12 @@ impl EntryPointExecutionContext {
     pub fn get_gas_cost(&self, name: &str) -> u64 {
         self.versioned_constants().gas_cost(name)
     }
+
+    /// Registers an executor for the given syscall selector, for the calls executed in this
+    /// context; overrides the built-in syscall of the selector, if one exists.
+    pub fn register_syscall(&mut self, selector: StarkFelt, executor: SyscallExecutor) {
+        self.custom_syscalls.insert(selector, executor);
+    }
 }
 
 pub fn execute_constructor_entry_point(
@@ -353,6 +490,59 @@ pub fn execute_constructor_entry_point(
     constructor_call.execute(state, resources, context)
 }
 
+/// A run of the code of a class against the storage of an address, as a library call made by the
+/// given caller would; see [`execute_class_code`].
+pub struct ClassCodeCall {
+    pub class_hash: ClassHash,
+    pub kind: LibraryCallKind,
+    pub entry_point_selector: EntryPointSelector,
+    pub calldata: Calldata,
+    pub storage_address: ContractAddress,
+    pub caller_address: ContractAddress,
+    /// The address the class hash was resolved from, if any (as in Cairo 0 delegate calls).
+    pub code_address: Option<ContractAddress>,
+    pub initial_gas: u64,
+}
+
+/// Runs the code of the class of the given call; the class need not be deployed anywhere.
+/// The L1 handler kind fails before running any code if the class has no such L1 handler (nor,
+/// for Cairo 0 classes, a default L1 handler entry point).
+/// The library call syscalls (and the delegate call syscalls of Cairo 0) run through this function.
+pub fn execute_class_code(
+    state: &mut dyn State,
+    resources: &mut ExecutionResources,
+    context: &mut EntryPointExecutionContext,
+    class_code_call: ClassCodeCall,
+) -> EntryPointExecutionResult<CallInfo> {
+    let ClassCodeCall {
+        class_hash,
+        kind,
+        entry_point_selector,
+        calldata,
+        storage_address,
+        caller_address,
+        code_address,
+        initial_gas,
+    } = class_code_call;
+    if kind.lacks_entry_point(state, class_hash, entry_point_selector)? {
+        return Err(PreExecutionError::EntryPointNotFound(entry_point_selector).into());
+    }
+
+    let call = CallEntryPoint {
+        code_address,
+        ..CallEntryPoint::new_library_call(
+            class_hash,
+            kind,
+            entry_point_selector,
+            calldata,
+            storage_address,
+            caller_address,
+            initial_gas,
+        )
+    };
+    call.execute(state, resources, context)
+}
+
 pub fn handle_empty_constructor(
     ctor_context: ConstructorContext,
     calldata: Calldata,
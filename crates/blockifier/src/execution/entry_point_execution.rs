@@ -338,19 +338,21 @@ pub fn finalize_execution(
         .get_additional_os_syscall_resources(&syscall_handler.syscall_counter)?;
 
     let full_call_resources = &*syscall_handler.resources - &previous_resources;
+    let call = syscall_handler.call.clone();
+    let outputs = syscall_handler.finalize()?;
     Ok(CallInfo {
-        call: syscall_handler.call,
+        call,
         execution: CallExecution {
             retdata: call_result.retdata,
-            events: syscall_handler.events,
-            l2_to_l1_messages: syscall_handler.l2_to_l1_messages,
+            events: outputs.events,
+            l2_to_l1_messages: outputs.l2_to_l1_messages,
             failed: call_result.failed,
             gas_consumed: call_result.gas_consumed,
         },
         resources: full_call_resources.filter_unused_builtins(),
-        inner_calls: syscall_handler.inner_calls,
-        storage_read_values: syscall_handler.read_values,
-        accessed_storage_keys: syscall_handler.accessed_keys,
+        inner_calls: outputs.inner_calls,
+        storage_read_values: outputs.storage_read_values,
+        accessed_storage_keys: outputs.accessed_storage_keys,
     })
 }
 
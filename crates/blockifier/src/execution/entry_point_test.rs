@@ -1,31 +1,38 @@
-use std::collections::HashSet;
+use std::collections::{HashMap, HashSet};
 
+use assert_matches::assert_matches;
 use cairo_vm::serde::deserialize_program::BuiltinName;
+use cairo_vm::vm::runners::cairo_runner::ExecutionResources;
 use num_bigint::BigInt;
 use pretty_assertions::assert_eq;
 use rstest::rstest;
-use starknet_api::core::{EntryPointSelector, PatriciaKey};
+use starknet_api::core::{ClassHash, ContractAddress, EntryPointSelector, PatriciaKey};
 use starknet_api::deprecated_contract_class::{EntryPointOffset, EntryPointType};
 use starknet_api::hash::{StarkFelt, StarkHash};
 use starknet_api::state::StorageKey;
 use starknet_api::transaction::Calldata;
-use starknet_api::{calldata, patricia_key, stark_felt};
+use starknet_api::{calldata, class_hash, contract_address, patricia_key, stark_felt};
 
 use crate::abi::abi_utils::{get_storage_var_address, selector_from_name};
-use crate::context::ChainInfo;
+use crate::context::{BlockContext, ChainInfo};
 use crate::execution::call_info::{CallExecution, CallInfo, Retdata};
 use crate::execution::contract_class::ContractClass;
-use crate::execution::entry_point::CallEntryPoint;
-use crate::execution::errors::EntryPointExecutionError;
+use crate::execution::entry_point::{
+    execute_class_code, CallEntryPoint, CallType, ClassCodeCall, EntryPointExecutionContext,
+    EntryPointExecutionResult, LibraryCallKind,
+};
+use crate::execution::errors::{EntryPointExecutionError, PreExecutionError};
 use crate::retdata;
 use crate::state::cached_state::CachedState;
+use crate::state::state_api::{State, StateReader};
+use crate::state::strict_state_reader::StrictStateReader;
 use crate::test_utils::cached_state::{create_test_state, deprecated_create_test_state};
 use crate::test_utils::contracts::FeatureContract;
 use crate::test_utils::dict_state_reader::DictStateReader;
 use crate::test_utils::initial_test_state::test_state;
 use crate::test_utils::{
     create_calldata, trivial_external_entry_point, trivial_external_entry_point_with_address,
-    CairoVersion, BALANCE,
+    CairoVersion, BALANCE, TEST_CLASS_HASH, TEST_CONTRACT_ADDRESS, TEST_CONTRACT_ADDRESS_2,
 };
 use crate::versioned_constants::VersionedConstants;
 
@@ -69,6 +76,37 @@ fn test_call_info_iteration() {
     }
 }
 
+#[test]
+fn test_resources_without_inner_calls() {
+    let mut state = create_test_state();
+    let library_call_selector = selector_from_name("test_library_call");
+    let storage_selector = selector_from_name("test_storage_read_write");
+    let entry_point_call = CallEntryPoint {
+        entry_point_selector: selector_from_name("test_nested_library_call"),
+        calldata: calldata![
+            stark_felt!(TEST_CLASS_HASH), // Class hash.
+            library_call_selector.0,      // Library call function selector.
+            storage_selector.0,           // Storage function selector.
+            stark_felt!(255_u8),          // Calldata: address.
+            stark_felt!(44_u8)            // Calldata: value.
+        ],
+        ..trivial_external_entry_point()
+    };
+    let call_info = entry_point_call.execute_directly(&mut state).unwrap();
+    assert_eq!(call_info.inner_calls.len(), 2);
+
+    // The resources of each call are those of the call itself plus those of its inner calls.
+    for call in call_info.into_iter() {
+        let own_resources = call.resources_without_inner_calls();
+        assert!(own_resources.n_steps > 0);
+        let mut total_resources = own_resources;
+        for inner_call in &call.inner_calls {
+            total_resources += &inner_call.resources;
+        }
+        assert_eq!(total_resources, call.resources);
+    }
+}
+
 #[test]
 fn test_entry_point_without_arg() {
     let mut state = deprecated_create_test_state();
@@ -527,6 +565,28 @@ fn test_storage_related_members() {
     );
 }
 
+/// The syscall machinery is not generic over the state; states over different reader types share
+/// the same (single) execution path.
+#[test]
+fn test_execution_with_different_state_readers() {
+    fn storage_read_write(state: &mut dyn State) -> CallInfo {
+        let entry_point_call = CallEntryPoint {
+            calldata: calldata![stark_felt!(1234_u16), stark_felt!(18_u8)],
+            entry_point_selector: selector_from_name("test_storage_read_write"),
+            ..trivial_external_entry_point()
+        };
+        entry_point_call.execute_directly(state).unwrap()
+    }
+
+    let mut dict_reader_state = create_test_state();
+    let mut strict_reader_state =
+        CachedState::from(StrictStateReader::new(create_test_state().state));
+    assert_eq!(
+        storage_read_write(&mut strict_reader_state),
+        storage_read_write(&mut dict_reader_state)
+    );
+}
+
 #[test]
 fn test_cairo1_entry_point_segment_arena() {
     let mut state = create_test_state();
@@ -868,3 +928,214 @@ Execution failed. Failure reason: {expected_error}.
     let actual_trace = entry_point_call.execute_directly(&mut state).unwrap_err().to_string();
     assert_eq!(actual_trace, expected_trace);
 }
+
+fn execute_class_code_for_testing(
+    state: &mut dyn State,
+    kind: LibraryCallKind,
+    entry_point_selector: EntryPointSelector,
+    calldata: Calldata,
+) -> EntryPointExecutionResult<CallInfo> {
+    let mut context =
+        EntryPointExecutionContext::new_invoke_for_testing(BlockContext::create_for_testing());
+    let class_code_call = ClassCodeCall {
+        class_hash: class_hash!(TEST_CLASS_HASH),
+        kind,
+        entry_point_selector,
+        calldata,
+        storage_address: contract_address!(TEST_CONTRACT_ADDRESS_2),
+        caller_address: contract_address!(TEST_CONTRACT_ADDRESS),
+        code_address: None,
+        initial_gas: context.get_gas_cost("initial_gas_cost"),
+    };
+    execute_class_code(state, &mut ExecutionResources::default(), &mut context, class_code_call)
+}
+
+#[test]
+fn test_execute_class_code() {
+    let mut state = create_test_state();
+    let (key, value) = (stark_felt!(1234_u16), stark_felt!(18_u8));
+    let call_info = execute_class_code_for_testing(
+        &mut state,
+        LibraryCallKind::External,
+        selector_from_name("test_storage_read_write"),
+        calldata![key, value],
+    )
+    .unwrap();
+
+    assert_eq!(call_info.execution.retdata, retdata![value]);
+    assert_eq!(call_info.call.call_type, CallType::Delegate);
+    assert_eq!(call_info.call.entry_point_type, EntryPointType::External);
+    assert_eq!(call_info.call.caller_address, contract_address!(TEST_CONTRACT_ADDRESS));
+    // The code runs against the given storage address.
+    let storage_key = StorageKey(patricia_key!(key));
+    assert_eq!(
+        state.get_storage_at(contract_address!(TEST_CONTRACT_ADDRESS_2), storage_key).unwrap(),
+        value
+    );
+    assert_eq!(
+        state.get_storage_at(contract_address!(TEST_CONTRACT_ADDRESS), storage_key).unwrap(),
+        StarkFelt::ZERO
+    );
+}
+
+#[test]
+fn test_execute_class_code_l1_handler() {
+    let mut state = create_test_state();
+    let (from_address, arg) = (stark_felt!(1_u8), stark_felt!(2_u8));
+    let call_info = execute_class_code_for_testing(
+        &mut state,
+        LibraryCallKind::L1Handler,
+        selector_from_name("l1_handle"),
+        calldata![from_address, arg],
+    )
+    .unwrap();
+    assert_eq!(call_info.execution.retdata, retdata![arg]);
+    assert_eq!(call_info.call.entry_point_type, EntryPointType::L1Handler);
+
+    // External functions cannot be called as L1 handlers.
+    let function_selector = selector_from_name("test_storage_read_write");
+    let error = execute_class_code_for_testing(
+        &mut state,
+        LibraryCallKind::L1Handler,
+        function_selector,
+        calldata![from_address, arg],
+    )
+    .unwrap_err();
+    assert_matches!(
+        error,
+        EntryPointExecutionError::PreExecutionError(PreExecutionError::EntryPointNotFound(
+            selector
+        )) if selector == function_selector
+    );
+}
+
+/// Returns the orders of the events emitted directly by the given call.
+fn event_orders(call_info: &CallInfo) -> Vec<usize> {
+    call_info.execution.events.iter().map(|event| event.order).collect()
+}
+
+#[test]
+fn test_event_order_across_syscall_abis() {
+    let chain_info = ChainInfo::create_for_testing();
+    let cairo0_contract = FeatureContract::TestContract(CairoVersion::Cairo0);
+    let cairo1_contract = FeatureContract::TestContract(CairoVersion::Cairo1);
+    let mut state = test_state(&chain_info, BALANCE, &[(cairo0_contract, 1), (cairo1_contract, 1)]);
+    let cairo0_address = cairo0_contract.get_instance_address(0);
+    let cairo1_address = cairo1_contract.get_instance_address(0);
+
+    // All calls run in the same context, as the calls of a single transaction do.
+    let mut context =
+        EntryPointExecutionContext::new_invoke_for_testing(BlockContext::create_for_testing());
+    let mut resources = ExecutionResources::default();
+
+    // Calldata: n_events, keys_len, keys, data_len, data.
+    let (key, data) = (stark_felt!(2019_u16), stark_felt!(2020_u16));
+    let emit_events_calldata =
+        |n_events: u8| vec![stark_felt!(n_events), stark_felt!(1_u8), key, stark_felt!(1_u8), data];
+
+    // The Cairo 0 contract emits an event through the deprecated syscall ABI.
+    let emit_entry_point = CallEntryPoint {
+        entry_point_selector: selector_from_name("test_emit_events"),
+        calldata: Calldata(emit_events_calldata(1).into()),
+        ..trivial_external_entry_point_with_address(cairo0_address)
+    };
+    let emit_call_info =
+        emit_entry_point.execute(&mut state, &mut resources, &mut context).unwrap();
+
+    // The Cairo 0 contract calls the Cairo 1 contract, which emits events through the current
+    // syscall ABI.
+    let call_contract_entry_point = CallEntryPoint {
+        entry_point_selector: selector_from_name("test_call_contract"),
+        calldata: create_calldata(cairo1_address, "test_emit_events", &emit_events_calldata(2)),
+        ..trivial_external_entry_point_with_address(cairo0_address)
+    };
+    let call_contract_call_info =
+        call_contract_entry_point.execute(&mut state, &mut resources, &mut context).unwrap();
+
+    assert_eq!(event_orders(&emit_call_info), vec![0]);
+    assert!(call_contract_call_info.execution.events.is_empty());
+    let [inner_call_info] = &call_contract_call_info.inner_calls[..] else {
+        panic!("Expected a single inner call; got {:?}.", call_contract_call_info.inner_calls);
+    };
+    assert_eq!(inner_call_info.call.storage_address, cairo1_address);
+    assert_eq!(event_orders(inner_call_info), vec![1, 2]);
+    assert_eq!(context.n_emitted_events, 3);
+}
+
+#[rstest]
+fn test_max_syscalls_per_tx(
+    #[values(CairoVersion::Cairo0, CairoVersion::Cairo1)] cairo_version: CairoVersion,
+    #[values(true, false)] exceeds_limit: bool,
+) {
+    let chain_info = ChainInfo::create_for_testing();
+    let test_contract = FeatureContract::TestContract(cairo_version);
+    let mut state = test_state(&chain_info, BALANCE, &[(test_contract, 1)]);
+    let max_syscalls_per_tx = 5;
+    let mut block_context = BlockContext::create_for_testing();
+    block_context.versioned_constants.syscall_limits.max_syscalls_per_tx =
+        Some(max_syscalls_per_tx);
+    let mut context = EntryPointExecutionContext::new_invoke_for_testing(block_context);
+
+    // Emit events in a loop, each by a syscall; exceed the limit by a single syscall if required.
+    let n_events = if exceeds_limit { max_syscalls_per_tx + 1 } else { max_syscalls_per_tx };
+    let entry_point_call = CallEntryPoint {
+        entry_point_selector: selector_from_name("test_emit_events"),
+        // Calldata: n_events, keys_len, keys, data_len, data.
+        calldata: calldata![
+            stark_felt!(u8::try_from(n_events).unwrap()),
+            stark_felt!(0_u8),
+            stark_felt!(0_u8)
+        ],
+        ..trivial_external_entry_point_with_address(test_contract.get_instance_address(0))
+    };
+    let result =
+        entry_point_call.execute(&mut state, &mut ExecutionResources::default(), &mut context);
+
+    assert_eq!(context.n_executed_syscalls, n_events);
+    if exceeds_limit {
+        let error = result.unwrap_err().to_string();
+        assert!(error
+            .contains("The EmitEvent syscall exceeds the maximum of 5 syscalls per transaction."));
+    } else {
+        assert_eq!(result.unwrap().execution.events.len(), n_events);
+    }
+}
+
+#[test]
+fn test_visited_pcs_union_per_class() {
+    let chain_info = ChainInfo::create_for_testing();
+    let test_contract = FeatureContract::TestContract(CairoVersion::Cairo1);
+    let class_hash = test_contract.get_class_hash();
+    let storage_read_write_call = CallEntryPoint {
+        entry_point_selector: selector_from_name("test_storage_read_write"),
+        calldata: calldata![stark_felt!(1234_u16), stark_felt!(18_u8)],
+        ..trivial_external_entry_point_with_address(test_contract.get_instance_address(0))
+    };
+    let assert_eq_call = CallEntryPoint {
+        entry_point_selector: selector_from_name("assert_eq"),
+        calldata: calldata![stark_felt!(7_u8), stark_felt!(7_u8)],
+        ..trivial_external_entry_point_with_address(test_contract.get_instance_address(0))
+    };
+
+    // Collect the PCs visited by each entry point on its own.
+    let visited_pcs_of = |call: &CallEntryPoint| {
+        let mut state = test_state(&chain_info, BALANCE, &[(test_contract, 1)]);
+        call.clone().execute_directly(&mut state).unwrap();
+        state.visited_pcs[&class_hash].clone()
+    };
+    let storage_read_write_pcs = visited_pcs_of(&storage_read_write_call);
+    let assert_eq_pcs = visited_pcs_of(&assert_eq_call);
+    assert_ne!(storage_read_write_pcs, assert_eq_pcs);
+
+    // Execute both entry points, the second in a transactional state; on commit, the visited PCs
+    // of the class are the union of both.
+    let mut state = test_state(&chain_info, BALANCE, &[(test_contract, 1)]);
+    storage_read_write_call.execute_directly(&mut state).unwrap();
+    let mut transactional_state = CachedState::create_transactional(&mut state);
+    assert_eq_call.execute_directly(&mut transactional_state).unwrap();
+    transactional_state.commit();
+    assert_eq!(
+        state.visited_pcs,
+        HashMap::from([(class_hash, &storage_read_write_pcs | &assert_eq_pcs)])
+    );
+}
@@ -1,17 +1,21 @@
 use cairo_vm::types::errors::math_errors::MathError;
 use cairo_vm::vm::errors::cairo_run_errors::CairoRunError;
+use cairo_vm::vm::errors::hint_errors::HintError;
 use cairo_vm::vm::errors::memory_errors::MemoryError;
 use cairo_vm::vm::errors::runner_errors::RunnerError;
 use cairo_vm::vm::errors::trace_errors::TraceError;
 use cairo_vm::vm::errors::vm_errors::{VirtualMachineError, HINT_ERROR_STR};
 use num_bigint::{BigInt, TryFromBigIntError};
-use starknet_api::core::{ContractAddress, EntryPointSelector};
+use starknet_api::core::{CompiledClassHash, ContractAddress, EntryPointSelector};
 use starknet_api::deprecated_contract_class::EntryPointType;
 use starknet_api::hash::StarkFelt;
 use thiserror::Error;
 
+use crate::execution::deprecated_syscalls::hint_processor::DeprecatedSyscallExecutionError;
 use crate::execution::execution_utils::format_panic_data;
+use crate::execution::syscalls::hint_processor::SyscallExecutionError;
 use crate::state::errors::StateError;
+use crate::versioned_constants::OsResourcesError;
 
 // TODO(AlonH, 21/12/2022): Implement Display for all types that appear in errors.
 
@@ -63,6 +67,10 @@ pub enum PostExecutionError {
     VirtualMachineError(#[from] VirtualMachineError),
     #[error("Malformed return data : {error_message}.")]
     MalformedReturnData { error_message: String },
+    #[error("Non-contiguous {output_kind} orders; expected {expected_order}, got {actual_order}.")]
+    NonContiguousOrders { output_kind: &'static str, expected_order: usize, actual_order: usize },
+    #[error(transparent)]
+    OsResourcesError(#[from] OsResourcesError),
 }
 
 impl From<RunnerError> for PostExecutionError {
@@ -110,6 +118,20 @@ impl EntryPointExecutionError {
             _ => self.to_string(),
         }
     }
+
+    /// Returns the state error this error originates from, if any; e.g., a read of an undeclared
+    /// class, possibly by an inner call (whose error is wrapped by the hint that executed it).
+    pub fn state_error(&self) -> Option<&StateError> {
+        match self {
+            Self::StateError(error)
+            | Self::PreExecutionError(PreExecutionError::StateError(error)) => Some(error),
+            Self::CairoRunError(error)
+            | Self::VirtualMachineExecutionErrorWithTrace { source: error, .. } => {
+                cairo_run_error_state_error(error)
+            }
+            _ => None,
+        }
+    }
 }
 
 #[derive(Debug, Error)]
@@ -141,6 +163,34 @@ pub enum EntryPointExecutionError {
     },
 }
 
+fn cairo_run_error_state_error(error: &CairoRunError) -> Option<&StateError> {
+    match error {
+        CairoRunError::VmException(exception) => vm_error_state_error(&exception.inner_exc),
+        CairoRunError::VirtualMachine(error) => vm_error_state_error(error),
+        _ => None,
+    }
+}
+
+fn vm_error_state_error(error: &VirtualMachineError) -> Option<&StateError> {
+    match error {
+        VirtualMachineError::Hint(hint_error) => match &hint_error.1 {
+            HintError::Internal(error) => vm_error_state_error(error),
+            _ => None,
+        },
+        // Syscall errors are wrapped as such; see `From<SyscallExecutionError> for HintError`.
+        VirtualMachineError::Other(error) => {
+            if let Some(syscall_error) = error.downcast_ref::<SyscallExecutionError>() {
+                syscall_error.state_error()
+            } else {
+                error
+                    .downcast_ref::<DeprecatedSyscallExecutionError>()
+                    .and_then(DeprecatedSyscallExecutionError::state_error)
+            }
+        }
+        _ => None,
+    }
+}
+
 #[derive(Debug, Error)]
 pub enum ContractClassError {
     #[error(
@@ -151,4 +201,19 @@ pub enum ContractClassError {
         contract_class_version: u8,
         sierra_program_length: usize,
     },
+    #[error(
+        "The compiled class hash of the class, {actual}, does not match the declared one, \
+         {declared}."
+    )]
+    CompiledClassHashMismatch { declared: CompiledClassHash, actual: CompiledClassHash },
+}
+
+#[derive(Debug, Error)]
+pub enum BoundedFeltError {
+    #[error("Felt {felt} exceeds the bound of {max_bits} bits.")]
+    ExceedsBound { felt: StarkFelt, max_bits: u32 },
+    #[error(transparent)]
+    MemoryError(#[from] MemoryError),
+    #[error(transparent)]
+    VirtualMachineError(#[from] VirtualMachineError),
 }
@@ -1,10 +1,12 @@
-use std::collections::HashMap;
+use std::collections::{HashMap, HashSet};
+use std::ops::Range;
 
 use cairo_felt::Felt252;
 use cairo_lang_runner::casm_run::format_next_item;
 use cairo_vm::serde::deserialize_program::{
     deserialize_array_of_bigint_hex, Attribute, HintParams, Identifier, ReferenceManager,
 };
+use cairo_vm::types::errors::math_errors::MathError;
 use cairo_vm::types::errors::program_errors::ProgramError;
 use cairo_vm::types::program::Program;
 use cairo_vm::types::relocatable::{MaybeRelocatable, Relocatable};
@@ -14,18 +16,23 @@ use cairo_vm::vm::runners::builtin_runner::POSEIDON_BUILTIN_NAME;
 use cairo_vm::vm::runners::cairo_runner::{CairoArg, ExecutionResources};
 use cairo_vm::vm::vm_core::VirtualMachine;
 use num_bigint::BigUint;
+use num_traits::ToPrimitive;
 use starknet_api::core::ClassHash;
 use starknet_api::deprecated_contract_class::Program as DeprecatedProgram;
 use starknet_api::hash::StarkFelt;
-use starknet_api::transaction::Calldata;
+use starknet_api::state::StorageKey;
+use starknet_api::transaction::{Calldata, EventContent};
 
-use crate::execution::call_info::{CallInfo, Retdata};
+use crate::execution::call_info::{
+    validate_order_continuity, CallInfo, CallOutputs, MessageToL1, OrderedEvent,
+    OrderedL2ToL1Message, Retdata,
+};
 use crate::execution::contract_class::ContractClass;
 use crate::execution::entry_point::{
     execute_constructor_entry_point, CallEntryPoint, ConstructorContext,
     EntryPointExecutionContext, EntryPointExecutionResult,
 };
-use crate::execution::errors::PostExecutionError;
+use crate::execution::errors::{BoundedFeltError, PostExecutionError};
 use crate::execution::{deprecated_entry_point_execution, entry_point_execution};
 use crate::state::errors::StateError;
 use crate::state::state_api::State;
@@ -47,6 +54,8 @@ pub fn felt_to_stark_felt(felt: &Felt252) -> StarkFelt {
 }
 
 /// Executes a specific call to a contract entry point and returns its output.
+/// The syscall ABI is chosen per call by the version of the executed class; calls across versions
+/// share the given context, and thus the ordering of their events and L2-to-L1 messages.
 pub fn execute_entry_point_call(
     call: CallEntryPoint,
     contract_class: ContractClass,
@@ -106,6 +115,55 @@ pub fn felt_from_ptr(
     Ok(felt)
 }
 
+/// Reads a felt that must fit within the given number of bits.
+pub fn bounded_felt_from_ptr(
+    vm: &VirtualMachine,
+    ptr: &mut Relocatable,
+    max_bits: u32,
+) -> Result<Felt252, BoundedFeltError> {
+    let felt = felt_from_ptr(vm, ptr)?;
+    verify_felt_bound(&felt, max_bits)?;
+    Ok(felt)
+}
+
+pub fn u64_from_ptr(vm: &VirtualMachine, ptr: &mut Relocatable) -> Result<u64, BoundedFeltError> {
+    felt_to_u64(&felt_from_ptr(vm, ptr)?)
+}
+
+pub fn felt_to_u64(felt: &Felt252) -> Result<u64, BoundedFeltError> {
+    felt.to_u64().ok_or_else(|| BoundedFeltError::ExceedsBound {
+        felt: felt_to_stark_felt(felt),
+        max_bits: u64::BITS,
+    })
+}
+
+pub fn usize_from_ptr(
+    vm: &VirtualMachine,
+    ptr: &mut Relocatable,
+) -> Result<usize, BoundedFeltError> {
+    let felt = felt_from_ptr(vm, ptr)?;
+    felt.to_usize().ok_or_else(|| BoundedFeltError::ExceedsBound {
+        felt: felt_to_stark_felt(&felt),
+        max_bits: usize::BITS,
+    })
+}
+
+pub fn u128_from_ptr(vm: &VirtualMachine, ptr: &mut Relocatable) -> Result<u128, BoundedFeltError> {
+    let felt = felt_from_ptr(vm, ptr)?;
+    felt.to_u128().ok_or_else(|| BoundedFeltError::ExceedsBound {
+        felt: felt_to_stark_felt(&felt),
+        max_bits: u128::BITS,
+    })
+}
+
+fn verify_felt_bound(felt: &Felt252, max_bits: u32) -> Result<(), BoundedFeltError> {
+    if felt.bits() > u64::from(max_bits) {
+        return Err(BoundedFeltError::ExceedsBound { felt: felt_to_stark_felt(felt), max_bits });
+    }
+
+    Ok(())
+}
+
 pub fn write_u256(
     vm: &mut VirtualMachine,
     ptr: &mut Relocatable,
@@ -158,13 +216,19 @@ pub fn sn_api_to_cairo_vm_program(program: DeprecatedProgram) -> Result<Program,
     Ok(program)
 }
 
-#[derive(Debug)]
+#[derive(Debug, Eq, PartialEq)]
 // Invariant: read-only.
 pub struct ReadOnlySegment {
     pub start_ptr: Relocatable,
     pub length: usize,
 }
 
+impl ReadOnlySegment {
+    pub fn end_ptr(&self) -> Result<Relocatable, MathError> {
+        self.start_ptr + self.length
+    }
+}
+
 /// Represents read-only segments dynamically allocated during execution.
 #[derive(Debug, Default)]
 // Invariant: read-only.
@@ -182,6 +246,10 @@ impl ReadOnlySegments {
         Ok(start_ptr)
     }
 
+    pub fn segments(&self) -> &[ReadOnlySegment] {
+        &self.0
+    }
+
     pub fn validate(&self, vm: &VirtualMachine) -> Result<(), PostExecutionError> {
         for segment in &self.0 {
             let used_size = vm
@@ -212,6 +280,122 @@ impl ReadOnlySegments {
     }
 }
 
+/// Gathers the outputs of a single call as its syscalls run, ordering its events and messages
+/// within the transaction; shared by the syscall handlers of all Cairo versions.
+#[derive(Debug)]
+pub struct CallOutputsRecorder {
+    inner_calls: Vec<CallInfo>,
+    events: Vec<OrderedEvent>,
+    l2_to_l1_messages: Vec<OrderedL2ToL1Message>,
+    // The event and message orders at the beginning of the call, and those consumed by its inner
+    // calls; used to validate the continuity of the orders.
+    initial_n_emitted_events: usize,
+    initial_n_sent_messages_to_l1: usize,
+    inner_call_event_orders: Vec<Range<usize>>,
+    inner_call_message_orders: Vec<Range<usize>>,
+}
+
+impl CallOutputsRecorder {
+    pub fn new(context: &EntryPointExecutionContext) -> Self {
+        Self {
+            inner_calls: vec![],
+            events: vec![],
+            l2_to_l1_messages: vec![],
+            initial_n_emitted_events: context.n_emitted_events,
+            initial_n_sent_messages_to_l1: context.n_sent_messages_to_l1,
+            inner_call_event_orders: vec![],
+            inner_call_message_orders: vec![],
+        }
+    }
+
+    pub fn events(&self) -> &[OrderedEvent] {
+        &self.events
+    }
+
+    pub fn l2_to_l1_messages(&self) -> &[OrderedL2ToL1Message] {
+        &self.l2_to_l1_messages
+    }
+
+    pub fn inner_calls(&self) -> &[CallInfo] {
+        &self.inner_calls
+    }
+
+    /// Emits the given event, ordered after all events emitted so far in the transaction.
+    pub fn add_event(&mut self, context: &mut EntryPointExecutionContext, event: EventContent) {
+        self.events.push(OrderedEvent { order: context.n_emitted_events, event });
+        context.n_emitted_events += 1;
+    }
+
+    /// Sends the given message, ordered after all messages sent so far in the transaction.
+    pub fn add_l2_to_l1_message(
+        &mut self,
+        context: &mut EntryPointExecutionContext,
+        message: MessageToL1,
+    ) {
+        self.l2_to_l1_messages
+            .push(OrderedL2ToL1Message { order: context.n_sent_messages_to_l1, message });
+        context.n_sent_messages_to_l1 += 1;
+    }
+
+    pub fn add_inner_call(&mut self, call_info: CallInfo) {
+        self.inner_calls.push(call_info);
+    }
+
+    /// Runs an inner execution (e.g., a call or a deployment), recording the event and message
+    /// orders it consumes; those of failed inner calls are consumed even though their outputs are
+    /// dropped.
+    pub fn execute_inner<T>(
+        &mut self,
+        context: &mut EntryPointExecutionContext,
+        execute: impl FnOnce(&mut EntryPointExecutionContext) -> T,
+    ) -> T {
+        let n_emitted_events = context.n_emitted_events;
+        let n_sent_messages_to_l1 = context.n_sent_messages_to_l1;
+        let result = execute(context);
+        let event_orders = n_emitted_events..context.n_emitted_events;
+        if !event_orders.is_empty() {
+            self.inner_call_event_orders.push(event_orders);
+        }
+        let message_orders = n_sent_messages_to_l1..context.n_sent_messages_to_l1;
+        if !message_orders.is_empty() {
+            self.inner_call_message_orders.push(message_orders);
+        }
+
+        result
+    }
+
+    /// Validates that the orders of the events and messages emitted during the call (including by
+    /// inner calls) are contiguous, and returns the call outputs, together with the given storage
+    /// accesses of the call.
+    pub fn finalize(
+        self,
+        context: &EntryPointExecutionContext,
+        storage_read_values: Vec<StarkFelt>,
+        accessed_storage_keys: HashSet<StorageKey>,
+    ) -> Result<CallOutputs, PostExecutionError> {
+        validate_order_continuity(
+            "event",
+            self.events.iter().map(|event| event.order),
+            &self.inner_call_event_orders,
+            self.initial_n_emitted_events..context.n_emitted_events,
+        )?;
+        validate_order_continuity(
+            "L2-to-L1 message",
+            self.l2_to_l1_messages.iter().map(|message| message.order),
+            &self.inner_call_message_orders,
+            self.initial_n_sent_messages_to_l1..context.n_sent_messages_to_l1,
+        )?;
+
+        Ok(CallOutputs {
+            events: self.events,
+            l2_to_l1_messages: self.l2_to_l1_messages,
+            inner_calls: self.inner_calls,
+            storage_read_values,
+            accessed_storage_keys,
+        })
+    }
+}
+
 /// Instantiates the given class and assigns it an address.
 /// Returns the call info of the deployed class' constructor execution.
 pub fn execute_deployment(
@@ -252,6 +436,33 @@ pub fn write_stark_felt(
     write_felt(vm, ptr, stark_felt_to_felt(felt))
 }
 
+pub fn write_u64(
+    vm: &mut VirtualMachine,
+    ptr: &mut Relocatable,
+    value: u64,
+) -> Result<(), MemoryError> {
+    write_felt(vm, ptr, Felt252::from(value))
+}
+
+pub fn write_u128(
+    vm: &mut VirtualMachine,
+    ptr: &mut Relocatable,
+    value: u128,
+) -> Result<(), MemoryError> {
+    write_felt(vm, ptr, Felt252::from(value))
+}
+
+/// Writes a felt that must fit within the given number of bits.
+pub fn write_bounded_felt(
+    vm: &mut VirtualMachine,
+    ptr: &mut Relocatable,
+    felt: Felt252,
+    max_bits: u32,
+) -> Result<(), BoundedFeltError> {
+    verify_felt_bound(&felt, max_bits)?;
+    Ok(write_felt(vm, ptr, felt)?)
+}
+
 pub fn write_felt(
     vm: &mut VirtualMachine,
     ptr: &mut Relocatable,
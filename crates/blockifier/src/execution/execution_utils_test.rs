@@ -1,12 +1,20 @@
 use std::iter::zip;
 
+use assert_matches::assert_matches;
 use cairo_felt::Felt252;
+use cairo_vm::types::relocatable::Relocatable;
+use cairo_vm::vm::vm_core::VirtualMachine;
 use num_bigint::BigUint;
-use num_traits::{One, Zero};
+use num_traits::{One, Pow, Zero};
 use pretty_assertions::assert_eq;
 use starknet_api::hash::StarkFelt;
+use test_case::test_case;
 
-use crate::execution::execution_utils::{felt_to_stark_felt, stark_felt_to_felt};
+use crate::execution::errors::BoundedFeltError;
+use crate::execution::execution_utils::{
+    bounded_felt_from_ptr, felt_to_stark_felt, stark_felt_to_felt, u128_from_ptr, u64_from_ptr,
+    write_bounded_felt, write_felt, write_u128, write_u64,
+};
 
 fn starkfelt_to_felt_pairs() -> Vec<(StarkFelt, Felt252)> {
     // The STARK prime is 2 ^ 251 + 17 * 2 ^ 192 + 1.
@@ -45,3 +53,69 @@ fn test_felt_to_stark_felt() {
         assert_eq!(felt_to_stark_felt(&felt), equivalent_stark_felt);
     }
 }
+
+fn vm_with_segment() -> (VirtualMachine, Relocatable) {
+    let mut vm = VirtualMachine::new(false);
+    let segment_start_ptr = vm.add_memory_segment();
+    (vm, segment_start_ptr)
+}
+
+#[test]
+fn test_u64_round_trip() {
+    let (mut vm, start_ptr) = vm_with_segment();
+    let mut ptr = start_ptr;
+    write_u64(&mut vm, &mut ptr, u64::MAX).unwrap();
+    write_u128(&mut vm, &mut ptr, u128::MAX).unwrap();
+
+    let mut ptr = start_ptr;
+    assert_eq!(u64_from_ptr(&vm, &mut ptr).unwrap(), u64::MAX);
+    assert_eq!(u128_from_ptr(&vm, &mut ptr).unwrap(), u128::MAX);
+    assert_eq!(ptr, (start_ptr + 2_usize).unwrap());
+}
+
+#[test_case(64; "u64")]
+#[test_case(128; "u128")]
+fn test_read_out_of_bound_felt(n_bits: u32) {
+    // The smallest felt exceeding the bound.
+    let felt = Felt252::from(2_u8).pow(n_bits);
+    let (mut vm, start_ptr) = vm_with_segment();
+    write_felt(&mut vm, &mut start_ptr.clone(), felt.clone()).unwrap();
+
+    let mut ptr = start_ptr;
+    let error = match n_bits {
+        64 => u64_from_ptr(&vm, &mut ptr).unwrap_err(),
+        128 => u128_from_ptr(&vm, &mut ptr).unwrap_err(),
+        _ => unreachable!(),
+    };
+    assert_matches!(
+        error,
+        BoundedFeltError::ExceedsBound { felt: error_felt, max_bits }
+        if error_felt == felt_to_stark_felt(&felt) && max_bits == n_bits
+    );
+
+    // The felt fits within one more bit.
+    let mut ptr = start_ptr;
+    assert_eq!(bounded_felt_from_ptr(&vm, &mut ptr, n_bits + 1).unwrap(), felt);
+    assert_matches!(
+        bounded_felt_from_ptr(&vm, &mut start_ptr.clone(), n_bits),
+        Err(BoundedFeltError::ExceedsBound { .. })
+    );
+}
+
+#[test]
+fn test_write_bounded_felt() {
+    let (mut vm, start_ptr) = vm_with_segment();
+    let felt = Felt252::from(u32::MAX);
+
+    let mut ptr = start_ptr;
+    assert_matches!(
+        write_bounded_felt(&mut vm, &mut ptr, felt.clone(), 31),
+        Err(BoundedFeltError::ExceedsBound { max_bits: 31, .. })
+    );
+    // Nothing is written on failure.
+    assert_eq!(ptr, start_ptr);
+    assert!(vm.get_integer(start_ptr).is_err());
+
+    write_bounded_felt(&mut vm, &mut ptr, felt.clone(), 32).unwrap();
+    assert_eq!(bounded_felt_from_ptr(&vm, &mut start_ptr.clone(), 32).unwrap(), felt);
+}
@@ -1,5 +1,6 @@
 use std::any::Any;
 use std::collections::{HashMap, HashSet};
+use std::time::{Duration, Instant};
 
 use cairo_felt::Felt252;
 use cairo_lang_casm::hints::{Hint, StarknetHint};
@@ -17,21 +18,26 @@ use cairo_vm::vm::runners::cairo_runner::{ExecutionResources, ResourceTracker, R
 use cairo_vm::vm::vm_core::VirtualMachine;
 use num_traits::Zero;
 use starknet_api::core::{ClassHash, ContractAddress, EntryPointSelector};
-use starknet_api::deprecated_contract_class::EntryPointType;
 use starknet_api::hash::StarkFelt;
 use starknet_api::state::StorageKey;
-use starknet_api::transaction::{Calldata, Resource};
+use starknet_api::transaction::{Calldata, EventContent, Resource};
 use starknet_api::StarknetApiError;
 use thiserror::Error;
 
+use crate::abi::abi_utils::is_valid_selector;
 use crate::abi::sierra_types::SierraTypeError;
-use crate::execution::call_info::{CallInfo, OrderedEvent, OrderedL2ToL1Message};
+use crate::execution::call_info::{
+    CallInfo, CallOutputs, MessageToL1, OrderedEvent, OrderedL2ToL1Message,
+};
 use crate::execution::common_hints::{ExecutionMode, HintExecutionResult};
-use crate::execution::entry_point::{CallEntryPoint, CallType, EntryPointExecutionContext};
-use crate::execution::errors::EntryPointExecutionError;
+use crate::execution::entry_point::{
+    execute_class_code, CallEntryPoint, ClassCodeCall, EntryPointExecutionContext,
+    EntryPointExecutionResult, LibraryCallKind,
+};
+use crate::execution::errors::{BoundedFeltError, EntryPointExecutionError, PostExecutionError};
 use crate::execution::execution_utils::{
     felt_range_from_ptr, max_fee_for_execution_info, stark_felt_from_ptr, stark_felt_to_felt,
-    write_maybe_relocatable, ReadOnlySegment, ReadOnlySegments,
+    write_maybe_relocatable, CallOutputsRecorder, ReadOnlySegment, ReadOnlySegments,
 };
 use crate::execution::syscalls::secp::{
     secp256k1_add, secp256k1_get_point_from_x, secp256k1_get_xy, secp256k1_mul, secp256k1_new,
@@ -39,24 +45,110 @@ use crate::execution::syscalls::secp::{
     SecpHintProcessor,
 };
 use crate::execution::syscalls::{
-    call_contract, deploy, emit_event, get_block_hash, get_execution_info, keccak, library_call,
-    library_call_l1_handler, replace_class, send_message_to_l1, storage_read, storage_write,
-    StorageReadResponse, StorageWriteResponse, SyscallRequest, SyscallRequestWrapper,
-    SyscallResponse, SyscallResponseWrapper, SyscallResult, SyscallSelector,
+    call_contract, deploy, emit_event, get_block_hash, get_compiled_class_hash, get_execution_info,
+    keccak, library_call, library_call_l1_handler, replace_class, send_message_to_l1, storage_read,
+    storage_write, StorageReadResponse, StorageWriteResponse, SyscallRequest,
+    SyscallRequestWrapper, SyscallResponse, SyscallResponseWrapper, SyscallResult, SyscallSelector,
 };
 use crate::state::errors::StateError;
 use crate::state::state_api::State;
 use crate::transaction::objects::{CurrentTransactionInfo, TransactionInfo};
 use crate::transaction::transaction_utils::update_remaining_gas;
+use crate::versioned_constants::{SyscallGasCost, SyscallGasCostsError, VersionedConstants};
+
+/// The usage of a syscall throughout an execution: its number of invocations, and the total size of
+/// their inputs, by which the OS resources of input-dependent syscalls scale.
+#[derive(Clone, Copy, Debug, Default, Eq, PartialEq)]
+pub struct SyscallUsage {
+    pub call_count: usize,
+    pub linear_factor: usize,
+}
 
-pub type SyscallCounter = HashMap<SyscallSelector, usize>;
+pub type SyscallCounter = HashMap<SyscallSelector, SyscallUsage>;
+
+pub type SyscallMetricsMap = HashMap<SyscallSelector, SyscallMetrics>;
+
+/// Profiling metrics of a syscall, accumulated over its invocations.
+#[derive(Clone, Copy, Debug, Default, Eq, PartialEq)]
+pub struct SyscallMetrics {
+    pub n_invocations: usize,
+    /// The VM steps charged while handling the syscall; i.e., those of the inner executions of
+    /// call-type syscalls (including the resources of their own syscalls).
+    pub n_steps: usize,
+    /// The input size the OS resources of the syscall scale by; e.g., the rounds of `Keccak`.
+    pub linear_factor: usize,
+    pub wall_time: Duration,
+}
+
+/// Executes the syscall whose selector was just read, using the syscall pointer of the given
+/// processor (typically, via [`SyscallHintProcessor::execute_syscall`], as the built-in syscall
+/// whose request and response layouts it shares).
+pub type SyscallExecutor =
+    fn(&mut SyscallHintProcessor<'_>, &mut VirtualMachine) -> HintExecutionResult;
+
+/// Syscall executors, keyed by the raw syscall selector (which need not be a built-in one); filled
+/// via [`SyscallHintProcessor::register_syscall`].
+/// An entry takes precedence over the built-in syscall of its selector; the built-in syscalls are
+/// dispatched by a match over `SyscallSelector`, only for selectors without an entry.
+pub type SyscallTable = HashMap<StarkFelt, SyscallExecutor>;
+
+/// Whether the selector is of a built-in syscall; i.e., a syscall of gas-metered execution.
+pub fn is_builtin_syscall(selector: &SyscallSelector) -> bool {
+    matches!(
+        selector,
+        SyscallSelector::CallContract
+            | SyscallSelector::Deploy
+            | SyscallSelector::EmitEvent
+            | SyscallSelector::GetBlockHash
+            | SyscallSelector::GetCompiledClassHash
+            | SyscallSelector::GetExecutionInfo
+            | SyscallSelector::Keccak
+            | SyscallSelector::LibraryCall
+            | SyscallSelector::LibraryCallL1Handler
+            | SyscallSelector::ReplaceClass
+            | SyscallSelector::Secp256k1Add
+            | SyscallSelector::Secp256k1GetPointFromX
+            | SyscallSelector::Secp256k1GetXy
+            | SyscallSelector::Secp256k1Mul
+            | SyscallSelector::Secp256k1New
+            | SyscallSelector::Secp256r1Add
+            | SyscallSelector::Secp256r1GetPointFromX
+            | SyscallSelector::Secp256r1GetXy
+            | SyscallSelector::Secp256r1Mul
+            | SyscallSelector::Secp256r1New
+            | SyscallSelector::SendMessageToL1
+            | SyscallSelector::StorageRead
+            | SyscallSelector::StorageWrite
+    )
+}
+
+/// Returns the name of the syscall with the given selector, if known; otherwise, the selector.
+fn format_syscall_selector(selector: StarkFelt) -> String {
+    match SyscallSelector::try_from(selector) {
+        Ok(selector) => format!("{selector:?}"),
+        Err(_) => selector.to_string(),
+    }
+}
 
 #[derive(Debug, Error)]
 pub enum SyscallExecutionError {
     #[error("Bad syscall_ptr; expected: {expected_ptr:?}, got: {actual_ptr:?}.")]
     BadSyscallPointer { expected_ptr: Relocatable, actual_ptr: Relocatable },
     #[error(transparent)]
+    CalldataTooLong(#[from] CalldataLengthError),
+    #[error(transparent)]
+    BoundedFeltError(#[from] BoundedFeltError),
+    #[error(transparent)]
     EmitEventError(#[from] EmitEventError),
+    #[error(transparent)]
+    ArrayLengthError(#[from] ArrayLengthError),
+    #[error(transparent)]
+    SyscallGasCostsError(#[from] SyscallGasCostsError),
+    #[error(
+        "The {} syscall exceeds the maximum of {max_syscalls_per_tx} syscalls per transaction.",
+        format_syscall_selector(*.selector)
+    )]
+    ExceedsMaxSyscallsPerTx { selector: StarkFelt, max_syscalls_per_tx: usize },
     #[error("Cannot replace V1 class hash with V0 class hash: {class_hash}.")]
     ForbiddenClassReplacement { class_hash: ClassHash },
     #[error("Invalid address domain: {address_domain}.")]
@@ -74,6 +166,11 @@ pub enum SyscallExecutionError {
         storage_address: ContractAddress,
         error: Box<SyscallExecutionError>,
     },
+    #[error(
+        "Entry point selector {selector} in the {syscall:?} syscall request is out of range; \
+         selectors are at most 250 bits."
+    )]
+    InvalidEntryPointSelector { syscall: SyscallSelector, selector: StarkFelt },
     #[error("Invalid syscall input: {input:?}; {info}")]
     InvalidSyscallInput { input: StarkFelt, info: String },
     #[error("Invalid syscall selector: {0:?}.")]
@@ -94,6 +191,71 @@ pub enum SyscallExecutionError {
     VirtualMachineError(#[from] VirtualMachineError),
     #[error("Syscall error.")]
     SyscallError { error_data: Vec<StarkFelt> },
+    #[error(
+        "{selector:?} syscall at offset {syscall_ptr_offset} of the syscall segment failed: \
+         {error}"
+    )]
+    FailedSyscall {
+        selector: SyscallSelector,
+        syscall_ptr_offset: usize,
+        error: Box<SyscallExecutionError>,
+    },
+}
+
+#[derive(Debug, Error)]
+pub enum ArrayLengthError {
+    #[error("Array length {length} in the {syscall:?} syscall request is not a valid length.")]
+    InvalidLength { syscall: SyscallSelector, length: StarkFelt },
+    #[error(
+        "Array length {length} in the {syscall:?} syscall request exceeds the maximum of \
+         {max_length}."
+    )]
+    ExceedsMaxLength { syscall: SyscallSelector, length: usize, max_length: usize },
+    #[error(
+        "Array length {length} in the {syscall:?} syscall request exceeds the initialized memory \
+         of the array."
+    )]
+    ExceedsSegment { syscall: SyscallSelector, length: usize },
+}
+
+/// The context in which a syscall request is read: the dispatched syscall, by which read errors
+/// are labeled, and the limits on the request's input.
+#[derive(Clone, Copy, Debug)]
+pub struct SyscallReadContext {
+    pub selector: SyscallSelector,
+    pub max_array_length: usize,
+    pub max_calldata_length: usize,
+}
+
+impl SyscallReadContext {
+    pub fn new(selector: SyscallSelector, versioned_constants: &VersionedConstants) -> Self {
+        let limits = &versioned_constants.syscall_limits;
+        Self {
+            selector,
+            max_array_length: limits.max_array_length,
+            max_calldata_length: limits.max_calldata_length,
+        }
+    }
+
+    /// Returns an error if the calldata of a call issued by the syscall is too long.
+    pub fn verify_calldata_length(
+        &self,
+        calldata_length: usize,
+    ) -> Result<(), CalldataLengthError> {
+        let max_calldata_length = self.max_calldata_length;
+        if calldata_length > max_calldata_length {
+            return Err(CalldataLengthError { calldata_length, max_calldata_length });
+        }
+
+        Ok(())
+    }
+}
+
+#[derive(Debug, Error)]
+#[error("Calldata length {calldata_length} exceeds the maximum of {max_calldata_length}.")]
+pub struct CalldataLengthError {
+    pub calldata_length: usize,
+    pub max_calldata_length: usize,
 }
 
 #[derive(Debug, Error)]
@@ -139,6 +301,51 @@ impl SyscallExecutionError {
             error: Box::new(self),
         }
     }
+
+    /// Wraps the error of the given syscall, starting (with its selector) at the given pointer.
+    /// Errors of inner calls, which are attributed to the failing call in the call chain trace, are
+    /// returned as is.
+    pub fn as_failed_syscall_error(
+        self,
+        selector: SyscallSelector,
+        syscall_start_ptr: Relocatable,
+    ) -> Self {
+        if matches!(
+            self,
+            Self::CallContractExecutionError { .. } | Self::LibraryCallExecutionError { .. }
+        ) {
+            return self;
+        }
+
+        SyscallExecutionError::FailedSyscall {
+            selector,
+            syscall_ptr_offset: syscall_start_ptr.offset,
+            error: Box::new(self),
+        }
+    }
+
+    /// Reports a syscall input exceeding the bound of its type as an invalid input.
+    pub fn from_bounded_felt_error(error: BoundedFeltError, info: &str) -> Self {
+        match error {
+            BoundedFeltError::ExceedsBound { felt, .. } => {
+                Self::InvalidSyscallInput { input: felt, info: info.into() }
+            }
+            error => error.into(),
+        }
+    }
+
+    /// Returns the state error this error originates from, if any; e.g., a read of an undeclared
+    /// class by an inner call.
+    pub fn state_error(&self) -> Option<&StateError> {
+        match self {
+            Self::StateError(error) => Some(error),
+            Self::EntryPointExecutionError(error) => error.state_error(),
+            Self::CallContractExecutionError { error, .. }
+            | Self::LibraryCallExecutionError { error, .. }
+            | Self::FailedSyscall { error, .. } => error.state_error(),
+            _ => None,
+        }
+    }
 }
 
 /// Error codes returned by Cairo 1.0 code.
@@ -152,6 +359,9 @@ pub const BLOCK_NUMBER_OUT_OF_RANGE_ERROR: &str =
 // "Invalid input length";
 pub const INVALID_INPUT_LENGTH_ERROR: &str =
     "0x000000000000000000000000496e76616c696420696e707574206c656e677468";
+// "CONTRACT_NOT_DEPLOYED";
+pub const CONTRACT_NOT_DEPLOYED_ERROR: &str =
+    "0x0000000000000000000000434f4e54524143545f4e4f545f4445504c4f594544";
 // "Invalid argument";
 pub const INVALID_ARGUMENT: &str =
     "0x00000000000000000000000000000000496e76616c696420617267756d656e74";
@@ -170,10 +380,8 @@ pub struct SyscallHintProcessor<'a> {
     pub call: CallEntryPoint,
 
     // Execution results.
-    /// Inner calls invoked by the current execution.
-    pub inner_calls: Vec<CallInfo>,
-    pub events: Vec<OrderedEvent>,
-    pub l2_to_l1_messages: Vec<OrderedL2ToL1Message>,
+    /// The events, messages and inner calls of the current execution.
+    outputs: CallOutputsRecorder,
     pub syscall_counter: SyscallCounter,
 
     // Fields needed for execution and validation.
@@ -191,6 +399,8 @@ pub struct SyscallHintProcessor<'a> {
     // Additional fields.
     hints: &'a HashMap<String, Hint>,
     // Transaction info. and signature segments; allocated on-demand.
+    // Cached per call frame only: every entry point call runs in its own VM, so a pointer cannot
+    // be shared with (and would be meaningless in) the frames of inner calls.
     execution_info_ptr: Option<Relocatable>,
 }
 
@@ -204,14 +414,13 @@ impl<'a> SyscallHintProcessor<'a> {
         hints: &'a HashMap<String, Hint>,
         read_only_segments: ReadOnlySegments,
     ) -> Self {
+        let outputs = CallOutputsRecorder::new(context);
         SyscallHintProcessor {
             state,
             resources,
             context,
             call,
-            inner_calls: vec![],
-            events: vec![],
-            l2_to_l1_messages: vec![],
+            outputs,
             syscall_counter: SyscallCounter::default(),
             read_only_segments,
             syscall_ptr: initial_syscall_ptr,
@@ -228,6 +437,48 @@ impl<'a> SyscallHintProcessor<'a> {
         self.call.storage_address
     }
 
+    pub fn events(&self) -> &[OrderedEvent] {
+        self.outputs.events()
+    }
+
+    pub fn l2_to_l1_messages(&self) -> &[OrderedL2ToL1Message] {
+        self.outputs.l2_to_l1_messages()
+    }
+
+    pub fn inner_calls(&self) -> &[CallInfo] {
+        self.outputs.inner_calls()
+    }
+
+    pub fn add_event(&mut self, event: EventContent) {
+        self.outputs.add_event(self.context, event);
+    }
+
+    pub fn add_l2_to_l1_message(&mut self, message: MessageToL1) {
+        self.outputs.add_l2_to_l1_message(self.context, message);
+    }
+
+    pub fn add_inner_call(&mut self, call_info: CallInfo) {
+        self.outputs.add_inner_call(call_info);
+    }
+
+    /// See [`CallOutputsRecorder::execute_inner`].
+    pub fn execute_inner<T>(
+        &mut self,
+        execute: impl FnOnce(
+            &mut dyn State,
+            &mut ExecutionResources,
+            &mut EntryPointExecutionContext,
+        ) -> T,
+    ) -> T {
+        let (state, resources) = (&mut *self.state, &mut *self.resources);
+        self.outputs.execute_inner(self.context, |context| execute(state, resources, context))
+    }
+
+    /// See [`CallOutputsRecorder::finalize`].
+    pub fn finalize(self) -> Result<CallOutputs, PostExecutionError> {
+        self.outputs.finalize(self.context, self.read_values, self.accessed_keys)
+    }
+
     pub fn caller_address(&self) -> ContractAddress {
         self.call.caller_address
     }
@@ -270,95 +521,95 @@ impl<'a> SyscallHintProcessor<'a> {
         let initial_syscall_ptr = get_ptr_from_res_operand_unchecked(vm, syscall);
         self.verify_syscall_ptr(initial_syscall_ptr)?;
 
-        let selector = SyscallSelector::try_from(self.read_next_syscall_selector(vm)?)?;
+        let raw_selector = self.read_next_syscall_selector(vm)?;
+        if let Some(&execute) = self.context.custom_syscalls.get(&raw_selector) {
+            // Custom syscalls count towards the limit as well, built-in selector or not.
+            self.count_syscall(raw_selector)?;
+            return match SyscallSelector::try_from(raw_selector) {
+                Ok(selector) => {
+                    self.increment_syscall_count(&selector);
+                    self.execute_measured_syscall(selector, |syscall_handler| {
+                        execute(syscall_handler, vm)
+                    })
+                }
+                Err(_) => execute(self, vm),
+            };
+        }
 
-        // Keccak resource usage depends on the input length, so we increment the syscall count
-        // in the syscall execution callback.
-        if selector != SyscallSelector::Keccak {
-            self.increment_syscall_count(&selector);
+        let selector = SyscallSelector::try_from(raw_selector)?;
+        if selector == SyscallSelector::GetCompiledClassHash
+            && !self.context.tx_context.block_context.allow_get_compiled_class_hash()
+        {
+            // Not a Starknet syscall; behave as if it does not exist, unless enabled.
+            return Err(SyscallExecutionError::InvalidSyscallSelector(raw_selector).into());
         }
 
-        let get_gas_cost = |name: &str| -> u64 { self.context.get_gas_cost(name) };
+        self.count_syscall(raw_selector)?;
+        self.increment_syscall_count(&selector);
+
         match selector {
             SyscallSelector::CallContract => {
-                self.execute_syscall(vm, call_contract, get_gas_cost("call_contract_gas_cost"))
-            }
-            SyscallSelector::Deploy => {
-                self.execute_syscall(vm, deploy, get_gas_cost("deploy_gas_cost"))
-            }
-            SyscallSelector::EmitEvent => {
-                self.execute_syscall(vm, emit_event, get_gas_cost("emit_event_gas_cost"))
+                self.execute_builtin_syscall(vm, selector, call_contract)
             }
+            SyscallSelector::Deploy => self.execute_builtin_syscall(vm, selector, deploy),
+            SyscallSelector::EmitEvent => self.execute_builtin_syscall(vm, selector, emit_event),
             SyscallSelector::GetBlockHash => {
-                self.execute_syscall(vm, get_block_hash, get_gas_cost("get_block_hash_gas_cost"))
+                self.execute_builtin_syscall(vm, selector, get_block_hash)
+            }
+            SyscallSelector::GetCompiledClassHash => {
+                self.execute_builtin_syscall(vm, selector, get_compiled_class_hash)
             }
-            SyscallSelector::GetExecutionInfo => self.execute_syscall(
-                vm,
-                get_execution_info,
-                get_gas_cost("get_execution_info_gas_cost"),
-            ),
-            SyscallSelector::Keccak => {
-                self.execute_syscall(vm, keccak, get_gas_cost("keccak_gas_cost"))
+            SyscallSelector::GetExecutionInfo => {
+                self.execute_builtin_syscall(vm, selector, get_execution_info)
             }
+            SyscallSelector::Keccak => self.execute_builtin_syscall(vm, selector, keccak),
             SyscallSelector::LibraryCall => {
-                self.execute_syscall(vm, library_call, get_gas_cost("library_call_gas_cost"))
+                self.execute_builtin_syscall(vm, selector, library_call)
+            }
+            SyscallSelector::LibraryCallL1Handler => {
+                self.execute_builtin_syscall(vm, selector, library_call_l1_handler)
             }
-            SyscallSelector::LibraryCallL1Handler => self.execute_syscall(
-                vm,
-                library_call_l1_handler,
-                get_gas_cost("library_call_gas_cost"),
-            ),
             SyscallSelector::ReplaceClass => {
-                self.execute_syscall(vm, replace_class, get_gas_cost("replace_class_gas_cost"))
+                self.execute_builtin_syscall(vm, selector, replace_class)
             }
             SyscallSelector::Secp256k1Add => {
-                self.execute_syscall(vm, secp256k1_add, get_gas_cost("secp256k1_add_gas_cost"))
+                self.execute_builtin_syscall(vm, selector, secp256k1_add)
+            }
+            SyscallSelector::Secp256k1GetPointFromX => {
+                self.execute_builtin_syscall(vm, selector, secp256k1_get_point_from_x)
+            }
+            SyscallSelector::Secp256k1GetXy => {
+                self.execute_builtin_syscall(vm, selector, secp256k1_get_xy)
             }
-            SyscallSelector::Secp256k1GetPointFromX => self.execute_syscall(
-                vm,
-                secp256k1_get_point_from_x,
-                get_gas_cost("secp256k1_get_point_from_x_gas_cost"),
-            ),
-            SyscallSelector::Secp256k1GetXy => self.execute_syscall(
-                vm,
-                secp256k1_get_xy,
-                get_gas_cost("secp256k1_get_xy_gas_cost"),
-            ),
             SyscallSelector::Secp256k1Mul => {
-                self.execute_syscall(vm, secp256k1_mul, get_gas_cost("secp256k1_mul_gas_cost"))
+                self.execute_builtin_syscall(vm, selector, secp256k1_mul)
             }
             SyscallSelector::Secp256k1New => {
-                self.execute_syscall(vm, secp256k1_new, get_gas_cost("secp256k1_new_gas_cost"))
+                self.execute_builtin_syscall(vm, selector, secp256k1_new)
             }
             SyscallSelector::Secp256r1Add => {
-                self.execute_syscall(vm, secp256r1_add, get_gas_cost("secp256r1_add_gas_cost"))
+                self.execute_builtin_syscall(vm, selector, secp256r1_add)
+            }
+            SyscallSelector::Secp256r1GetPointFromX => {
+                self.execute_builtin_syscall(vm, selector, secp256r1_get_point_from_x)
+            }
+            SyscallSelector::Secp256r1GetXy => {
+                self.execute_builtin_syscall(vm, selector, secp256r1_get_xy)
             }
-            SyscallSelector::Secp256r1GetPointFromX => self.execute_syscall(
-                vm,
-                secp256r1_get_point_from_x,
-                get_gas_cost("secp256r1_get_point_from_x_gas_cost"),
-            ),
-            SyscallSelector::Secp256r1GetXy => self.execute_syscall(
-                vm,
-                secp256r1_get_xy,
-                get_gas_cost("secp256r1_get_xy_gas_cost"),
-            ),
             SyscallSelector::Secp256r1Mul => {
-                self.execute_syscall(vm, secp256r1_mul, get_gas_cost("secp256r1_mul_gas_cost"))
+                self.execute_builtin_syscall(vm, selector, secp256r1_mul)
             }
             SyscallSelector::Secp256r1New => {
-                self.execute_syscall(vm, secp256r1_new, get_gas_cost("secp256r1_new_gas_cost"))
+                self.execute_builtin_syscall(vm, selector, secp256r1_new)
+            }
+            SyscallSelector::SendMessageToL1 => {
+                self.execute_builtin_syscall(vm, selector, send_message_to_l1)
             }
-            SyscallSelector::SendMessageToL1 => self.execute_syscall(
-                vm,
-                send_message_to_l1,
-                get_gas_cost("send_message_to_l1_gas_cost"),
-            ),
             SyscallSelector::StorageRead => {
-                self.execute_syscall(vm, storage_read, get_gas_cost("storage_read_gas_cost"))
+                self.execute_builtin_syscall(vm, selector, storage_read)
             }
             SyscallSelector::StorageWrite => {
-                self.execute_syscall(vm, storage_write, get_gas_cost("storage_write_gas_cost"))
+                self.execute_builtin_syscall(vm, selector, storage_write)
             }
             _ => Err(HintError::UnknownHint(
                 format!("Unsupported syscall selector {selector:?}.").into(),
@@ -366,6 +617,73 @@ impl<'a> SyscallHintProcessor<'a> {
         }
     }
 
+    /// Executes a built-in syscall, charging its gas cost as listed in the versioned constants.
+    fn execute_builtin_syscall<Request, Response, ExecuteCallback>(
+        &mut self,
+        vm: &mut VirtualMachine,
+        selector: SyscallSelector,
+        execute_callback: ExecuteCallback,
+    ) -> HintExecutionResult
+    where
+        Request: SyscallRequest + std::fmt::Debug,
+        Response: SyscallResponse + std::fmt::Debug,
+        ExecuteCallback: FnOnce(
+            Request,
+            &mut VirtualMachine,
+            &mut SyscallHintProcessor<'_>,
+            &mut u64, // Remaining gas.
+        ) -> SyscallResult<Response>,
+    {
+        let syscall_gas_cost = self
+            .context
+            .versioned_constants()
+            .syscall_gas_costs
+            .get(&selector)
+            .map_err(SyscallExecutionError::from)?;
+        self.execute_measured_syscall(selector, |syscall_handler| {
+            syscall_handler.execute_syscall(vm, selector, execute_callback, syscall_gas_cost)
+        })
+    }
+
+    /// Executes the given syscall, recording its metrics if they are collected.
+    fn execute_measured_syscall(
+        &mut self,
+        selector: SyscallSelector,
+        execute: impl FnOnce(&mut Self) -> HintExecutionResult,
+    ) -> HintExecutionResult {
+        if self.context.syscall_metrics.is_none() {
+            return execute(self);
+        }
+
+        let linear_factor = |processor: &Self| {
+            processor.syscall_counter.get(&selector).map_or(0, |usage| usage.linear_factor)
+        };
+        let start_time = Instant::now();
+        let initial_n_steps = self.resources.n_steps;
+        let initial_linear_factor = linear_factor(self);
+        let result = execute(self);
+        let n_steps = self.resources.n_steps - initial_n_steps;
+        let wall_time = start_time.elapsed();
+        let added_linear_factor = linear_factor(self) - initial_linear_factor;
+
+        if let Some(syscall_metrics) = &mut self.context.syscall_metrics {
+            let metrics = syscall_metrics.entry(selector).or_default();
+            metrics.n_invocations += 1;
+            metrics.n_steps += n_steps;
+            metrics.linear_factor += added_linear_factor;
+            metrics.wall_time += wall_time;
+        }
+        result
+    }
+
+    /// Registers an executor for the given syscall selector, for the rest of the execution
+    /// (including inner calls, and later calls in the same execution context); overrides the
+    /// built-in syscall of the selector, if one exists. Before execution, register through
+    /// [`EntryPointExecutionContext::register_syscall`].
+    pub fn register_syscall(&mut self, selector: StarkFelt, executor: SyscallExecutor) {
+        self.context.register_syscall(selector, executor);
+    }
+
     pub fn get_or_allocate_execution_info_segment(
         &mut self,
         vm: &mut VirtualMachine,
@@ -385,34 +703,19 @@ impl<'a> SyscallHintProcessor<'a> {
         vm: &mut VirtualMachine,
         tx_info: &CurrentTransactionInfo,
     ) -> SyscallResult<(Relocatable, Relocatable)> {
-        let l1_gas = StarkFelt::try_from(L1_GAS).map_err(SyscallExecutionError::from)?;
-        let l2_gas = StarkFelt::try_from(L2_GAS).map_err(SyscallExecutionError::from)?;
-        let flat_resource_bounds: Vec<StarkFelt> = tx_info
-            .resource_bounds
-            .0
-            .iter()
-            .flat_map(|(resource, resource_bounds)| {
-                let resource = match resource {
-                    Resource::L1Gas => l1_gas,
-                    Resource::L2Gas => l2_gas,
-                };
-
-                vec![
-                    resource,
-                    StarkFelt::from(resource_bounds.max_amount),
-                    StarkFelt::from(resource_bounds.max_price_per_unit),
-                ]
-            })
-            .collect();
-
+        let flat_resource_bounds = flatten_resource_bounds(tx_info)?;
         self.allocate_data_segment(vm, &flat_resource_bounds)
     }
 
-    fn execute_syscall<Request, Response, ExecuteCallback>(
+    /// Executes the syscall whose selector was just read, as the given syscall: reads its request,
+    /// charges the given gas cost and writes the response to the syscall segment. On failure, the
+    /// error is attributed to the given syscall and its position in the syscall segment.
+    pub fn execute_syscall<Request, Response, ExecuteCallback>(
         &mut self,
         vm: &mut VirtualMachine,
+        selector: SyscallSelector,
         execute_callback: ExecuteCallback,
-        syscall_gas_cost: u64,
+        syscall_gas_cost: SyscallGasCost,
     ) -> HintExecutionResult
     where
         Request: SyscallRequest + std::fmt::Debug,
@@ -424,14 +727,41 @@ impl<'a> SyscallHintProcessor<'a> {
             &mut u64, // Remaining gas.
         ) -> SyscallResult<Response>,
     {
-        // Refund `SYSCALL_BASE_GAS_COST` as it was pre-charged.
-        let required_gas = syscall_gas_cost - self.context.get_gas_cost("syscall_base_gas_cost");
+        let syscall_start_ptr = (self.syscall_ptr - 1).map_err(SyscallExecutionError::from)?;
+        let read_context = SyscallReadContext::new(selector, self.context.versioned_constants());
+        self.run_syscall(vm, &read_context, execute_callback, syscall_gas_cost)
+            .map_err(|error| error.as_failed_syscall_error(selector, syscall_start_ptr).into())
+    }
 
+    fn run_syscall<Request, Response, ExecuteCallback>(
+        &mut self,
+        vm: &mut VirtualMachine,
+        read_context: &SyscallReadContext,
+        execute_callback: ExecuteCallback,
+        syscall_gas_cost: SyscallGasCost,
+    ) -> SyscallResult<()>
+    where
+        Request: SyscallRequest + std::fmt::Debug,
+        Response: SyscallResponse + std::fmt::Debug,
+        ExecuteCallback: FnOnce(
+            Request,
+            &mut VirtualMachine,
+            &mut SyscallHintProcessor<'_>,
+            &mut u64, // Remaining gas.
+        ) -> SyscallResult<Response>,
+    {
         let SyscallRequestWrapper { gas_counter, request } =
-            SyscallRequestWrapper::<Request>::read(vm, &mut self.syscall_ptr)?;
+            SyscallRequestWrapper::<Request>::read(vm, &mut self.syscall_ptr, read_context)?;
+
+        // Refund `SYSCALL_BASE_GAS_COST` as it was pre-charged (every syscall cost covers it).
+        let syscall_base_gas_cost = self.context.get_gas_cost("syscall_base_gas_cost");
+        let required_gas = syscall_gas_cost
+            .get_syscall_cost(request.input_size())
+            .map(|syscall_cost| syscall_cost - syscall_base_gas_cost)
+            .filter(|&required_gas| required_gas <= gas_counter);
 
-        if gas_counter < required_gas {
-            //  Out of gas failure.
+        let Some(required_gas) = required_gas else {
+            //  Out of gas failure (an overflowing cost cannot be covered either).
             let out_of_gas_error =
                 StarkFelt::try_from(OUT_OF_GAS_ERROR).map_err(SyscallExecutionError::from)?;
             let response: SyscallResponseWrapper<Response> =
@@ -439,7 +769,7 @@ impl<'a> SyscallHintProcessor<'a> {
             response.write(vm, &mut self.syscall_ptr)?;
 
             return Ok(());
-        }
+        };
 
         // Execute.
         let mut remaining_gas = gas_counter - required_gas;
@@ -451,7 +781,7 @@ impl<'a> SyscallHintProcessor<'a> {
             Err(SyscallExecutionError::SyscallError { error_data: data }) => {
                 SyscallResponseWrapper::Failure { gas_counter: remaining_gas, error_data: data }
             }
-            Err(error) => return Err(error.into()),
+            Err(error) => return Err(error),
         };
 
         response.write(vm, &mut self.syscall_ptr)?;
@@ -465,9 +795,26 @@ impl<'a> SyscallHintProcessor<'a> {
         Ok(selector)
     }
 
+    /// Counts the syscall towards the syscall limit of the transaction, shared by all its calls.
+    fn count_syscall(&mut self, selector: StarkFelt) -> SyscallResult<()> {
+        match self.context.count_syscall() {
+            Some(max_syscalls_per_tx) => Err(SyscallExecutionError::ExceedsMaxSyscallsPerTx {
+                selector,
+                max_syscalls_per_tx,
+            }),
+            None => Ok(()),
+        }
+    }
+
     pub fn increment_syscall_count_by(&mut self, selector: &SyscallSelector, n: usize) {
-        let syscall_count = self.syscall_counter.entry(*selector).or_default();
-        *syscall_count += n;
+        let syscall_usage = self.syscall_counter.entry(*selector).or_default();
+        syscall_usage.call_count += n;
+    }
+
+    /// Adds the given input size to the usage of the syscall, for its OS resources to scale by.
+    pub fn increment_linear_factor_by(&mut self, selector: &SyscallSelector, n: usize) {
+        let syscall_usage = self.syscall_counter.entry(*selector).or_default();
+        syscall_usage.linear_factor += n;
     }
 
     fn increment_syscall_count(&mut self, selector: &SyscallSelector) {
@@ -625,6 +972,34 @@ impl<'a> SyscallHintProcessor<'a> {
     }
 }
 
+/// Returns the resource bounds of the given transaction as laid out in the transaction info: a
+/// (resource, max amount, max price per unit) triplet per resource.
+pub fn flatten_resource_bounds(
+    tx_info: &CurrentTransactionInfo,
+) -> Result<Vec<StarkFelt>, StarknetApiError> {
+    let l1_gas = StarkFelt::try_from(L1_GAS)?;
+    let l2_gas = StarkFelt::try_from(L2_GAS)?;
+    let flat_resource_bounds = tx_info
+        .resource_bounds
+        .0
+        .iter()
+        .flat_map(|(resource, resource_bounds)| {
+            let resource = match resource {
+                Resource::L1Gas => l1_gas,
+                Resource::L2Gas => l2_gas,
+            };
+
+            vec![
+                resource,
+                StarkFelt::from(resource_bounds.max_amount),
+                StarkFelt::from(resource_bounds.max_price_per_unit),
+            ]
+        })
+        .collect();
+
+    Ok(flat_resource_bounds)
+}
+
 /// Retrieves a [Relocatable] from the VM given a [ResOperand].
 /// A [ResOperand] represents a CASM result expression, and is deserialized with the hint.
 fn get_ptr_from_res_operand_unchecked(vm: &mut VirtualMachine, res: &ResOperand) -> Relocatable {
@@ -700,16 +1075,33 @@ pub fn felt_to_bool(felt: StarkFelt, error_info: &str) -> SyscallResult<bool> {
     }
 }
 
-pub fn read_calldata(vm: &VirtualMachine, ptr: &mut Relocatable) -> SyscallResult<Calldata> {
-    Ok(Calldata(read_felt_array::<SyscallExecutionError>(vm, ptr)?.into()))
+pub fn read_calldata(
+    vm: &VirtualMachine,
+    ptr: &mut Relocatable,
+    read_context: &SyscallReadContext,
+) -> SyscallResult<Calldata> {
+    // Verified before the calldata is copied out of the VM memory.
+    let (calldata_start_ptr, calldata_length) =
+        read_array_bounds::<SyscallExecutionError>(vm, ptr, read_context)?;
+    read_context.verify_calldata_length(calldata_length)?;
+
+    Ok(Calldata(felt_range_from_ptr(vm, calldata_start_ptr, calldata_length)?.into()))
 }
 
 pub fn read_call_params(
     vm: &VirtualMachine,
     ptr: &mut Relocatable,
+    read_context: &SyscallReadContext,
 ) -> SyscallResult<(EntryPointSelector, Calldata)> {
-    let function_selector = EntryPointSelector(stark_felt_from_ptr(vm, ptr)?);
-    let calldata = read_calldata(vm, ptr)?;
+    let raw_selector = stark_felt_from_ptr(vm, ptr)?;
+    if !is_valid_selector(raw_selector) {
+        return Err(SyscallExecutionError::InvalidEntryPointSelector {
+            syscall: read_context.selector,
+            selector: raw_selector,
+        });
+    }
+    let function_selector = EntryPointSelector(raw_selector);
+    let calldata = read_calldata(vm, ptr, read_context)?;
 
     Ok((function_selector, calldata))
 }
@@ -720,8 +1112,23 @@ pub fn execute_inner_call(
     syscall_handler: &mut SyscallHintProcessor<'_>,
     remaining_gas: &mut u64,
 ) -> SyscallResult<ReadOnlySegment> {
-    let call_info =
-        call.execute(syscall_handler.state, syscall_handler.resources, syscall_handler.context)?;
+    execute_inner_call_with(vm, syscall_handler, remaining_gas, |state, resources, context| {
+        call.execute(state, resources, context)
+    })
+}
+
+/// Same as [`execute_inner_call`], with the inner call executed by the given function.
+fn execute_inner_call_with(
+    vm: &mut VirtualMachine,
+    syscall_handler: &mut SyscallHintProcessor<'_>,
+    remaining_gas: &mut u64,
+    execute: impl FnOnce(
+        &mut dyn State,
+        &mut ExecutionResources,
+        &mut EntryPointExecutionContext,
+    ) -> EntryPointExecutionResult<CallInfo>,
+) -> SyscallResult<ReadOnlySegment> {
+    let call_info = syscall_handler.execute_inner(execute)?;
     let raw_retdata = &call_info.execution.retdata.0;
 
     if call_info.execution.failed {
@@ -733,7 +1140,7 @@ pub fn execute_inner_call(
     let retdata_segment = create_retdata_segment(vm, syscall_handler, raw_retdata)?;
     update_remaining_gas(remaining_gas, &call_info);
 
-    syscall_handler.inner_calls.push(call_info);
+    syscall_handler.add_inner_call(call_info);
 
     Ok(retdata_segment)
 }
@@ -752,27 +1159,27 @@ pub fn execute_library_call(
     syscall_handler: &mut SyscallHintProcessor<'_>,
     vm: &mut VirtualMachine,
     class_hash: ClassHash,
-    call_to_external: bool,
+    kind: LibraryCallKind,
     entry_point_selector: EntryPointSelector,
     calldata: Calldata,
     remaining_gas: &mut u64,
 ) -> SyscallResult<ReadOnlySegment> {
-    let entry_point_type =
-        if call_to_external { EntryPointType::External } else { EntryPointType::L1Handler };
-    let entry_point = CallEntryPoint {
-        class_hash: Some(class_hash),
-        code_address: None,
-        entry_point_type,
+    // The call context remains the same in a library call.
+    let class_code_call = ClassCodeCall {
+        class_hash,
+        kind,
         entry_point_selector,
         calldata,
-        // The call context remains the same in a library call.
         storage_address: syscall_handler.storage_address(),
         caller_address: syscall_handler.caller_address(),
-        call_type: CallType::Delegate,
+        code_address: None,
         initial_gas: *remaining_gas,
     };
 
-    execute_inner_call(entry_point, vm, syscall_handler, remaining_gas).map_err(|error| {
+    execute_inner_call_with(vm, syscall_handler, remaining_gas, |state, resources, context| {
+        execute_class_code(state, resources, context, class_code_call)
+    })
+    .map_err(|error| {
         error.as_lib_call_execution_error(class_hash, syscall_handler.storage_address())
     })
 }
@@ -780,17 +1187,63 @@ pub fn execute_library_call(
 pub fn read_felt_array<TErr>(
     vm: &VirtualMachine,
     ptr: &mut Relocatable,
+    read_context: &SyscallReadContext,
 ) -> Result<Vec<StarkFelt>, TErr>
 where
-    TErr: From<StarknetApiError> + From<VirtualMachineError> + From<MemoryError> + From<MathError>,
+    TErr: From<StarknetApiError>
+        + From<VirtualMachineError>
+        + From<MemoryError>
+        + From<MathError>
+        + From<ArrayLengthError>,
+{
+    let (array_data_start_ptr, array_size) = read_array_bounds::<TErr>(vm, ptr, read_context)?;
+
+    Ok(felt_range_from_ptr(vm, array_data_start_ptr, array_size)?)
+}
+
+/// Reads the (start pointer, validated length) pair of an array in a syscall request, without
+/// reading the array itself.
+fn read_array_bounds<TErr>(
+    vm: &VirtualMachine,
+    ptr: &mut Relocatable,
+    read_context: &SyscallReadContext,
+) -> Result<(Relocatable, usize), TErr>
+where
+    TErr: From<VirtualMachineError> + From<MemoryError> + From<MathError> + From<ArrayLengthError>,
 {
     let array_data_start_ptr = vm.get_relocatable(*ptr)?;
     *ptr = (*ptr + 1)?;
     let array_data_end_ptr = vm.get_relocatable(*ptr)?;
     *ptr = (*ptr + 1)?;
     let array_size = (array_data_end_ptr - array_data_start_ptr)?;
+    validate_array_length(vm, array_data_start_ptr, array_size, read_context)?;
 
-    Ok(felt_range_from_ptr(vm, array_data_start_ptr, array_size)?)
+    Ok((array_data_start_ptr, array_size))
+}
+
+/// Validates the (untrusted) length of an array in a syscall request before reading it: it must
+/// not exceed the maximal length, nor the initialized memory starting at the array pointer.
+pub fn validate_array_length(
+    vm: &VirtualMachine,
+    array_start_ptr: Relocatable,
+    length: usize,
+    read_context: &SyscallReadContext,
+) -> Result<(), ArrayLengthError> {
+    let syscall = read_context.selector;
+    let max_length = read_context.max_array_length;
+    if length > max_length {
+        return Err(ArrayLengthError::ExceedsMaxLength { syscall, length, max_length });
+    }
+
+    // Checking the last cell suffices; memory gaps are detected when the array is read.
+    let exceeds_segment = length > 0
+        && (array_start_ptr + (length - 1))
+            .map_or(true, |last_ptr| vm.get_integer(last_ptr).is_err());
+    if exceeds_segment {
+        return Err(ArrayLengthError::ExceedsSegment { syscall, length });
+    }
+
+    Ok(())
 }
 
 pub fn write_segment(
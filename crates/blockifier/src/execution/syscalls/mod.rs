@@ -1,10 +1,9 @@
-use cairo_felt::Felt252;
 use cairo_vm::types::relocatable::Relocatable;
 use cairo_vm::vm::vm_core::VirtualMachine;
-use num_traits::ToPrimitive;
 use starknet_api::block::{BlockHash, BlockNumber};
 use starknet_api::core::{
-    calculate_contract_address, ClassHash, ContractAddress, EntryPointSelector, EthAddress,
+    calculate_contract_address, ClassHash, CompiledClassHash, ContractAddress, EntryPointSelector,
+    EthAddress,
 };
 use starknet_api::deprecated_contract_class::EntryPointType;
 use starknet_api::hash::StarkFelt;
@@ -15,19 +14,25 @@ use starknet_api::transaction::{
 
 use self::hint_processor::{
     create_retdata_segment, execute_inner_call, execute_library_call, felt_to_bool,
-    read_call_params, read_calldata, read_felt_array, write_segment, EmitEventError,
-    SyscallExecutionError, SyscallHintProcessor, BLOCK_NUMBER_OUT_OF_RANGE_ERROR,
+    read_call_params, read_calldata, read_felt_array, validate_array_length, write_segment,
+    EmitEventError, SyscallExecutionError, SyscallHintProcessor, SyscallReadContext,
+    BLOCK_NUMBER_OUT_OF_RANGE_ERROR,
 };
 use crate::abi::constants;
-use crate::execution::call_info::{MessageToL1, OrderedEvent, OrderedL2ToL1Message};
+use crate::execution::call_info::MessageToL1;
 use crate::execution::contract_class::ContractClass;
 use crate::execution::deprecated_syscalls::DeprecatedSyscallSelector;
-use crate::execution::entry_point::{CallEntryPoint, CallType, ConstructorContext};
+use crate::execution::entry_point::{
+    CallEntryPoint, CallType, ConstructorContext, LibraryCallKind,
+};
 use crate::execution::execution_utils::{
-    execute_deployment, felt_from_ptr, felt_to_stark_felt, stark_felt_from_ptr, stark_felt_to_felt,
-    write_felt, write_maybe_relocatable, write_stark_felt, ReadOnlySegment,
+    execute_deployment, felt_to_stark_felt, felt_to_u64, stark_felt_from_ptr, stark_felt_to_felt,
+    u64_from_ptr, write_maybe_relocatable, write_stark_felt, write_u128, write_u64,
+    ReadOnlySegment,
+};
+use crate::execution::syscalls::hint_processor::{
+    CONTRACT_NOT_DEPLOYED_ERROR, INVALID_INPUT_LENGTH_ERROR, OUT_OF_GAS_ERROR,
 };
-use crate::execution::syscalls::hint_processor::{INVALID_INPUT_LENGTH_ERROR, OUT_OF_GAS_ERROR};
 use crate::transaction::transaction_utils::update_remaining_gas;
 use crate::versioned_constants::VersionedConstants;
 
@@ -44,7 +49,17 @@ pub type WriteResponseResult = SyscallResult<()>;
 type SyscallSelector = DeprecatedSyscallSelector;
 
 pub trait SyscallRequest: Sized {
-    fn read(_vm: &VirtualMachine, _ptr: &mut Relocatable) -> SyscallResult<Self>;
+    fn read(
+        _vm: &VirtualMachine,
+        _ptr: &mut Relocatable,
+        _read_context: &SyscallReadContext,
+    ) -> SyscallResult<Self>;
+
+    /// The size of the variable-length input of the request (e.g., its calldata length), by which
+    /// the `linear_factor` of the syscall gas cost is charged.
+    fn input_size(&self) -> usize {
+        0
+    }
 }
 
 pub trait SyscallResponse {
@@ -57,14 +72,15 @@ pub struct SyscallRequestWrapper<T: SyscallRequest> {
     pub request: T,
 }
 impl<T: SyscallRequest> SyscallRequest for SyscallRequestWrapper<T> {
-    fn read(vm: &VirtualMachine, ptr: &mut Relocatable) -> SyscallResult<Self> {
-        let gas_counter = felt_from_ptr(vm, ptr)?;
-        let gas_counter =
-            gas_counter.to_u64().ok_or_else(|| SyscallExecutionError::InvalidSyscallInput {
-                input: felt_to_stark_felt(&gas_counter),
-                info: String::from("Unexpected gas."),
-            })?;
-        Ok(Self { gas_counter, request: T::read(vm, ptr)? })
+    fn read(
+        vm: &VirtualMachine,
+        ptr: &mut Relocatable,
+        read_context: &SyscallReadContext,
+    ) -> SyscallResult<Self> {
+        let gas_counter = u64_from_ptr(vm, ptr).map_err(|error| {
+            SyscallExecutionError::from_bounded_felt_error(error, "Unexpected gas.")
+        })?;
+        Ok(Self { gas_counter, request: T::read(vm, ptr, read_context)? })
     }
 }
 
@@ -76,13 +92,13 @@ impl<T: SyscallResponse> SyscallResponse for SyscallResponseWrapper<T> {
     fn write(self, vm: &mut VirtualMachine, ptr: &mut Relocatable) -> WriteResponseResult {
         match self {
             Self::Success { gas_counter, response } => {
-                write_felt(vm, ptr, Felt252::from(gas_counter))?;
+                write_u64(vm, ptr, gas_counter)?;
                 // 0 to indicate success.
                 write_stark_felt(vm, ptr, StarkFelt::from(0_u8))?;
                 response.write(vm, ptr)
             }
             Self::Failure { gas_counter, error_data } => {
-                write_felt(vm, ptr, Felt252::from(gas_counter))?;
+                write_u64(vm, ptr, gas_counter)?;
                 // 1 to indicate failure.
                 write_stark_felt(vm, ptr, StarkFelt::from(1_u8))?;
 
@@ -108,7 +124,11 @@ impl<T: SyscallResponse> SyscallResponse for SyscallResponseWrapper<T> {
 pub struct EmptyRequest;
 
 impl SyscallRequest for EmptyRequest {
-    fn read(_vm: &VirtualMachine, _ptr: &mut Relocatable) -> SyscallResult<EmptyRequest> {
+    fn read(
+        _vm: &VirtualMachine,
+        _ptr: &mut Relocatable,
+        _read_context: &SyscallReadContext,
+    ) -> SyscallResult<EmptyRequest> {
         Ok(EmptyRequest)
     }
 }
@@ -143,12 +163,20 @@ pub struct CallContractRequest {
 }
 
 impl SyscallRequest for CallContractRequest {
-    fn read(vm: &VirtualMachine, ptr: &mut Relocatable) -> SyscallResult<CallContractRequest> {
+    fn read(
+        vm: &VirtualMachine,
+        ptr: &mut Relocatable,
+        read_context: &SyscallReadContext,
+    ) -> SyscallResult<CallContractRequest> {
         let contract_address = ContractAddress::try_from(stark_felt_from_ptr(vm, ptr)?)?;
-        let (function_selector, calldata) = read_call_params(vm, ptr)?;
+        let (function_selector, calldata) = read_call_params(vm, ptr, read_context)?;
 
         Ok(CallContractRequest { contract_address, function_selector, calldata })
     }
+
+    fn input_size(&self) -> usize {
+        self.calldata.0.len()
+    }
 }
 
 pub type CallContractResponse = SingleSegmentResponse;
@@ -167,6 +195,13 @@ pub fn call_contract(
         };
         return Err(error.as_call_contract_execution_error(storage_address));
     }
+    if syscall_handler.state.get_class_hash_at(storage_address)? == ClassHash::default() {
+        let contract_not_deployed_error = StarkFelt::try_from(CONTRACT_NOT_DEPLOYED_ERROR)
+            .map_err(SyscallExecutionError::from)?;
+        return Err(SyscallExecutionError::SyscallError {
+            error_data: vec![contract_not_deployed_error],
+        });
+    }
     let entry_point = CallEntryPoint {
         class_hash: None,
         code_address: Some(storage_address),
@@ -195,10 +230,14 @@ pub struct DeployRequest {
 }
 
 impl SyscallRequest for DeployRequest {
-    fn read(vm: &VirtualMachine, ptr: &mut Relocatable) -> SyscallResult<DeployRequest> {
+    fn read(
+        vm: &VirtualMachine,
+        ptr: &mut Relocatable,
+        read_context: &SyscallReadContext,
+    ) -> SyscallResult<DeployRequest> {
         let class_hash = ClassHash(stark_felt_from_ptr(vm, ptr)?);
         let contract_address_salt = ContractAddressSalt(stark_felt_from_ptr(vm, ptr)?);
-        let constructor_calldata = read_calldata(vm, ptr)?;
+        let constructor_calldata = read_calldata(vm, ptr, read_context)?;
         let deploy_from_zero = stark_felt_from_ptr(vm, ptr)?;
 
         Ok(DeployRequest {
@@ -211,6 +250,10 @@ impl SyscallRequest for DeployRequest {
             )?,
         })
     }
+
+    fn input_size(&self) -> usize {
+        self.constructor_calldata.0.len()
+    }
 }
 
 #[derive(Debug)]
@@ -250,20 +293,23 @@ pub fn deploy(
         storage_address: deployed_contract_address,
         caller_address: deployer_address,
     };
-    let call_info = execute_deployment(
-        syscall_handler.state,
-        syscall_handler.resources,
-        syscall_handler.context,
-        ctor_context,
-        request.constructor_calldata,
-        *remaining_gas,
-    )?;
+    let initial_gas = *remaining_gas;
+    let call_info = syscall_handler.execute_inner(|state, resources, context| {
+        execute_deployment(
+            state,
+            resources,
+            context,
+            ctor_context,
+            request.constructor_calldata,
+            initial_gas,
+        )
+    })?;
 
     let constructor_retdata =
         create_retdata_segment(vm, syscall_handler, &call_info.execution.retdata.0)?;
     update_remaining_gas(remaining_gas, &call_info);
 
-    syscall_handler.inner_calls.push(call_info);
+    syscall_handler.add_inner_call(call_info);
 
     Ok(DeployResponse { contract_address: deployed_contract_address, constructor_retdata })
 }
@@ -277,13 +323,23 @@ pub struct EmitEventRequest {
 
 impl SyscallRequest for EmitEventRequest {
     // The Cairo struct contains: `keys_len`, `keys`, `data_len`, `data`·
-    fn read(vm: &VirtualMachine, ptr: &mut Relocatable) -> SyscallResult<EmitEventRequest> {
-        let keys =
-            read_felt_array::<SyscallExecutionError>(vm, ptr)?.into_iter().map(EventKey).collect();
-        let data = EventData(read_felt_array::<SyscallExecutionError>(vm, ptr)?);
+    fn read(
+        vm: &VirtualMachine,
+        ptr: &mut Relocatable,
+        read_context: &SyscallReadContext,
+    ) -> SyscallResult<EmitEventRequest> {
+        let keys = read_felt_array::<SyscallExecutionError>(vm, ptr, read_context)?
+            .into_iter()
+            .map(EventKey)
+            .collect();
+        let data = EventData(read_felt_array::<SyscallExecutionError>(vm, ptr, read_context)?);
 
         Ok(EmitEventRequest { content: EventContent { keys, data } })
     }
+
+    fn input_size(&self) -> usize {
+        self.content.keys.len() + self.content.data.0.len()
+    }
 }
 
 type EmitEventResponse = EmptyResponse;
@@ -326,10 +382,9 @@ pub fn emit_event(
         execution_context.n_emitted_events + 1,
         &request.content,
     )?;
-    let ordered_event =
-        OrderedEvent { order: execution_context.n_emitted_events, event: request.content };
-    syscall_handler.events.push(ordered_event);
-    execution_context.n_emitted_events += 1;
+    syscall_handler
+        .increment_linear_factor_by(&SyscallSelector::EmitEvent, request.content.data.0.len());
+    syscall_handler.add_event(request.content);
 
     Ok(EmitEventResponse {})
 }
@@ -342,13 +397,16 @@ pub struct GetBlockHashRequest {
 }
 
 impl SyscallRequest for GetBlockHashRequest {
-    fn read(vm: &VirtualMachine, ptr: &mut Relocatable) -> SyscallResult<GetBlockHashRequest> {
-        let felt = felt_from_ptr(vm, ptr)?;
-        let block_number = BlockNumber(felt.to_u64().ok_or_else(|| {
-            SyscallExecutionError::InvalidSyscallInput {
-                input: felt_to_stark_felt(&felt),
-                info: String::from("Block number must fit within 64 bits."),
-            }
+    fn read(
+        vm: &VirtualMachine,
+        ptr: &mut Relocatable,
+        _read_context: &SyscallReadContext,
+    ) -> SyscallResult<GetBlockHashRequest> {
+        let block_number = BlockNumber(u64_from_ptr(vm, ptr).map_err(|error| {
+            SyscallExecutionError::from_bounded_felt_error(
+                error,
+                "Block number must fit within 64 bits.",
+            )
         })?);
 
         Ok(GetBlockHashRequest { block_number })
@@ -404,6 +462,50 @@ pub fn get_block_hash(
     Ok(GetBlockHashResponse { block_hash })
 }
 
+// GetCompiledClassHash syscall.
+
+#[derive(Debug, Eq, PartialEq)]
+pub struct GetCompiledClassHashRequest {
+    pub class_hash: ClassHash,
+}
+
+impl SyscallRequest for GetCompiledClassHashRequest {
+    fn read(
+        vm: &VirtualMachine,
+        ptr: &mut Relocatable,
+        _read_context: &SyscallReadContext,
+    ) -> SyscallResult<GetCompiledClassHashRequest> {
+        let class_hash = ClassHash(stark_felt_from_ptr(vm, ptr)?);
+
+        Ok(GetCompiledClassHashRequest { class_hash })
+    }
+}
+
+#[derive(Debug, Eq, PartialEq)]
+pub struct GetCompiledClassHashResponse {
+    pub compiled_class_hash: CompiledClassHash,
+}
+
+impl SyscallResponse for GetCompiledClassHashResponse {
+    fn write(self, vm: &mut VirtualMachine, ptr: &mut Relocatable) -> WriteResponseResult {
+        write_stark_felt(vm, ptr, self.compiled_class_hash.0)?;
+        Ok(())
+    }
+}
+
+/// Returns the compiled class hash of the given class hash (zero, if it is not declared).
+/// Not a Starknet syscall; available only if enabled in the block context.
+pub fn get_compiled_class_hash(
+    request: GetCompiledClassHashRequest,
+    _vm: &mut VirtualMachine,
+    syscall_handler: &mut SyscallHintProcessor<'_>,
+    _remaining_gas: &mut u64,
+) -> SyscallResult<GetCompiledClassHashResponse> {
+    let compiled_class_hash = syscall_handler.state.get_compiled_class_hash(request.class_hash)?;
+
+    Ok(GetCompiledClassHashResponse { compiled_class_hash })
+}
+
 // GetExecutionInfo syscall.
 
 type GetExecutionInfoRequest = EmptyRequest;
@@ -440,12 +542,20 @@ pub struct LibraryCallRequest {
 }
 
 impl SyscallRequest for LibraryCallRequest {
-    fn read(vm: &VirtualMachine, ptr: &mut Relocatable) -> SyscallResult<LibraryCallRequest> {
+    fn read(
+        vm: &VirtualMachine,
+        ptr: &mut Relocatable,
+        read_context: &SyscallReadContext,
+    ) -> SyscallResult<LibraryCallRequest> {
         let class_hash = ClassHash(stark_felt_from_ptr(vm, ptr)?);
-        let (function_selector, calldata) = read_call_params(vm, ptr)?;
+        let (function_selector, calldata) = read_call_params(vm, ptr, read_context)?;
 
         Ok(LibraryCallRequest { class_hash, function_selector, calldata })
     }
+
+    fn input_size(&self) -> usize {
+        self.calldata.0.len()
+    }
 }
 
 type LibraryCallResponse = CallContractResponse;
@@ -456,12 +566,11 @@ pub fn library_call(
     syscall_handler: &mut SyscallHintProcessor<'_>,
     remaining_gas: &mut u64,
 ) -> SyscallResult<LibraryCallResponse> {
-    let call_to_external = true;
     let retdata_segment = execute_library_call(
         syscall_handler,
         vm,
         request.class_hash,
-        call_to_external,
+        LibraryCallKind::External,
         request.function_selector,
         request.calldata,
         remaining_gas,
@@ -478,12 +587,11 @@ pub fn library_call_l1_handler(
     syscall_handler: &mut SyscallHintProcessor<'_>,
     remaining_gas: &mut u64,
 ) -> SyscallResult<LibraryCallResponse> {
-    let call_to_external = false;
     let retdata_segment = execute_library_call(
         syscall_handler,
         vm,
         request.class_hash,
-        call_to_external,
+        LibraryCallKind::L1Handler,
         request.function_selector,
         request.calldata,
         remaining_gas,
@@ -500,7 +608,11 @@ pub struct ReplaceClassRequest {
 }
 
 impl SyscallRequest for ReplaceClassRequest {
-    fn read(vm: &VirtualMachine, ptr: &mut Relocatable) -> SyscallResult<ReplaceClassRequest> {
+    fn read(
+        vm: &VirtualMachine,
+        ptr: &mut Relocatable,
+        _read_context: &SyscallReadContext,
+    ) -> SyscallResult<ReplaceClassRequest> {
         let class_hash = ClassHash(stark_felt_from_ptr(vm, ptr)?);
 
         Ok(ReplaceClassRequest { class_hash })
@@ -526,7 +638,7 @@ pub fn replace_class(
         ContractClass::V1(_) => {
             syscall_handler
                 .state
-                .set_class_hash_at(syscall_handler.storage_address(), class_hash)?;
+                .replace_class_hash_at(syscall_handler.storage_address(), class_hash)?;
             Ok(ReplaceClassResponse {})
         }
     }
@@ -541,12 +653,21 @@ pub struct SendMessageToL1Request {
 
 impl SyscallRequest for SendMessageToL1Request {
     // The Cairo struct contains: `to_address`, `payload_size`, `payload`.
-    fn read(vm: &VirtualMachine, ptr: &mut Relocatable) -> SyscallResult<SendMessageToL1Request> {
+    fn read(
+        vm: &VirtualMachine,
+        ptr: &mut Relocatable,
+        read_context: &SyscallReadContext,
+    ) -> SyscallResult<SendMessageToL1Request> {
         let to_address = EthAddress::try_from(stark_felt_from_ptr(vm, ptr)?)?;
-        let payload = L2ToL1Payload(read_felt_array::<SyscallExecutionError>(vm, ptr)?);
+        let payload =
+            L2ToL1Payload(read_felt_array::<SyscallExecutionError>(vm, ptr, read_context)?);
 
         Ok(SendMessageToL1Request { message: MessageToL1 { to_address, payload } })
     }
+
+    fn input_size(&self) -> usize {
+        self.message.payload.0.len()
+    }
 }
 
 type SendMessageToL1Response = EmptyResponse;
@@ -557,13 +678,11 @@ pub fn send_message_to_l1(
     syscall_handler: &mut SyscallHintProcessor<'_>,
     _remaining_gas: &mut u64,
 ) -> SyscallResult<SendMessageToL1Response> {
-    let execution_context = &mut syscall_handler.context;
-    let ordered_message_to_l1 = OrderedL2ToL1Message {
-        order: execution_context.n_sent_messages_to_l1,
-        message: request.message,
-    };
-    syscall_handler.l2_to_l1_messages.push(ordered_message_to_l1);
-    execution_context.n_sent_messages_to_l1 += 1;
+    syscall_handler.increment_linear_factor_by(
+        &SyscallSelector::SendMessageToL1,
+        request.message.payload.0.len(),
+    );
+    syscall_handler.add_l2_to_l1_message(request.message);
 
     Ok(SendMessageToL1Response {})
 }
@@ -578,7 +697,11 @@ pub struct StorageReadRequest {
 }
 
 impl SyscallRequest for StorageReadRequest {
-    fn read(vm: &VirtualMachine, ptr: &mut Relocatable) -> SyscallResult<StorageReadRequest> {
+    fn read(
+        vm: &VirtualMachine,
+        ptr: &mut Relocatable,
+        _read_context: &SyscallReadContext,
+    ) -> SyscallResult<StorageReadRequest> {
         let address_domain = stark_felt_from_ptr(vm, ptr)?;
         if address_domain != StarkFelt::from(0_u8) {
             return Err(SyscallExecutionError::InvalidAddressDomain { address_domain });
@@ -619,7 +742,11 @@ pub struct StorageWriteRequest {
 }
 
 impl SyscallRequest for StorageWriteRequest {
-    fn read(vm: &VirtualMachine, ptr: &mut Relocatable) -> SyscallResult<StorageWriteRequest> {
+    fn read(
+        vm: &VirtualMachine,
+        ptr: &mut Relocatable,
+        _read_context: &SyscallReadContext,
+    ) -> SyscallResult<StorageWriteRequest> {
         let address_domain = stark_felt_from_ptr(vm, ptr)?;
         if address_domain != StarkFelt::from(0_u8) {
             return Err(SyscallExecutionError::InvalidAddressDomain { address_domain });
@@ -650,25 +777,30 @@ pub struct KeccakRequest {
 }
 
 impl SyscallRequest for KeccakRequest {
-    fn read(vm: &VirtualMachine, ptr: &mut Relocatable) -> SyscallResult<KeccakRequest> {
+    fn read(
+        vm: &VirtualMachine,
+        ptr: &mut Relocatable,
+        read_context: &SyscallReadContext,
+    ) -> SyscallResult<KeccakRequest> {
         let input_start = vm.get_relocatable(*ptr)?;
         *ptr = (*ptr + 1)?;
         let input_end = vm.get_relocatable(*ptr)?;
         *ptr = (*ptr + 1)?;
+        validate_array_length(vm, input_start, (input_end - input_start)?, read_context)?;
         Ok(KeccakRequest { input_start, input_end })
     }
 }
 
 #[derive(Debug, Eq, PartialEq)]
 pub struct KeccakResponse {
-    pub result_low: Felt252,
-    pub result_high: Felt252,
+    pub result_low: u128,
+    pub result_high: u128,
 }
 
 impl SyscallResponse for KeccakResponse {
     fn write(self, vm: &mut VirtualMachine, ptr: &mut Relocatable) -> WriteResponseResult {
-        write_felt(vm, ptr, self.result_low)?;
-        write_felt(vm, ptr, self.result_high)?;
+        write_u128(vm, ptr, self.result_low)?;
+        write_u128(vm, ptr, self.result_high)?;
         Ok(())
     }
 }
@@ -705,25 +837,27 @@ pub fn keccak(
     }
     *remaining_gas -= gas_cost;
 
-    // For the keccak system call we want to count the number of rounds rather than the number of
-    // syscall invocations.
-    syscall_handler.increment_syscall_count_by(&SyscallSelector::Keccak, n_rounds);
+    // The OS resources of the keccak system call are per round, as each round applies the keccak
+    // builtin once.
+    syscall_handler.increment_linear_factor_by(&SyscallSelector::Keccak, n_rounds);
 
     let data = vm.get_integer_range(request.input_start, input_length)?;
 
     let mut state = [0u64; 25];
     for chunk in data.chunks(KECCAK_FULL_RATE_IN_WORDS) {
         for (i, val) in chunk.iter().enumerate() {
-            state[i] ^= val.to_u64().ok_or_else(|| SyscallExecutionError::InvalidSyscallInput {
-                input: felt_to_stark_felt(val),
-                info: String::from("Invalid input for the keccak syscall."),
+            state[i] ^= felt_to_u64(val).map_err(|error| {
+                SyscallExecutionError::from_bounded_felt_error(
+                    error,
+                    "Invalid input for the keccak syscall.",
+                )
             })?;
         }
         keccak::f1600(&mut state)
     }
 
     Ok(KeccakResponse {
-        result_low: (Felt252::from(state[1]) << 64u32) + Felt252::from(state[0]),
-        result_high: (Felt252::from(state[3]) << 64u32) + Felt252::from(state[2]),
+        result_low: (u128::from(state[1]) << 64) + u128::from(state[0]),
+        result_high: (u128::from(state[3]) << 64) + u128::from(state[2]),
     })
 }
@@ -13,7 +13,7 @@ use crate::execution::execution_utils::{
     felt_from_ptr, stark_felt_from_ptr, write_maybe_relocatable, write_u256,
 };
 use crate::execution::syscalls::hint_processor::{
-    felt_to_bool, SyscallHintProcessor, INVALID_ARGUMENT,
+    felt_to_bool, SyscallHintProcessor, SyscallReadContext, INVALID_ARGUMENT,
 };
 use crate::execution::syscalls::{
     felt_to_stark_felt, SyscallExecutionError, SyscallRequest, SyscallResponse, SyscallResult,
@@ -177,7 +177,11 @@ pub struct SecpAddRequest {
 }
 
 impl SyscallRequest for SecpAddRequest {
-    fn read(vm: &VirtualMachine, ptr: &mut Relocatable) -> SyscallResult<SecpAddRequest> {
+    fn read(
+        vm: &VirtualMachine,
+        ptr: &mut Relocatable,
+        _read_context: &SyscallReadContext,
+    ) -> SyscallResult<SecpAddRequest> {
         Ok(SecpAddRequest { lhs_id: felt_from_ptr(vm, ptr)?, rhs_id: felt_from_ptr(vm, ptr)? })
     }
 }
@@ -213,7 +217,11 @@ pub struct SecpGetPointFromXRequest {
 }
 
 impl SyscallRequest for SecpGetPointFromXRequest {
-    fn read(vm: &VirtualMachine, ptr: &mut Relocatable) -> SyscallResult<SecpGetPointFromXRequest> {
+    fn read(
+        vm: &VirtualMachine,
+        ptr: &mut Relocatable,
+        _read_context: &SyscallReadContext,
+    ) -> SyscallResult<SecpGetPointFromXRequest> {
         let x = SierraU256::from_memory(vm, ptr)?.to_biguint();
 
         let y_parity = felt_to_bool(stark_felt_from_ptr(vm, ptr)?, "Invalid y parity")?;
@@ -249,7 +257,11 @@ pub struct SecpGetXyRequest {
 }
 
 impl SyscallRequest for SecpGetXyRequest {
-    fn read(vm: &VirtualMachine, ptr: &mut Relocatable) -> SyscallResult<SecpGetXyRequest> {
+    fn read(
+        vm: &VirtualMachine,
+        ptr: &mut Relocatable,
+        _read_context: &SyscallReadContext,
+    ) -> SyscallResult<SecpGetXyRequest> {
         Ok(SecpGetXyRequest { ec_point_id: felt_from_ptr(vm, ptr)? })
     }
 }
@@ -291,7 +303,11 @@ pub struct SecpMulRequest {
 }
 
 impl SyscallRequest for SecpMulRequest {
-    fn read(vm: &VirtualMachine, ptr: &mut Relocatable) -> SyscallResult<SecpMulRequest> {
+    fn read(
+        vm: &VirtualMachine,
+        ptr: &mut Relocatable,
+        _read_context: &SyscallReadContext,
+    ) -> SyscallResult<SecpMulRequest> {
         let ec_point_id = felt_from_ptr(vm, ptr)?;
         let multiplier = SierraU256::from_memory(vm, ptr)?.to_biguint();
         Ok(SecpMulRequest { ec_point_id, multiplier })
@@ -323,7 +339,11 @@ pub fn secp256r1_mul(
 type SecpNewRequest = EcPointCoordinates;
 
 impl SyscallRequest for SecpNewRequest {
-    fn read(vm: &VirtualMachine, ptr: &mut Relocatable) -> SyscallResult<SecpNewRequest> {
+    fn read(
+        vm: &VirtualMachine,
+        ptr: &mut Relocatable,
+        _read_context: &SyscallReadContext,
+    ) -> SyscallResult<SecpNewRequest> {
         let x = SierraU256::from_memory(vm, ptr)?.to_biguint();
         let y = SierraU256::from_memory(vm, ptr)?.to_biguint();
         Ok(SecpNewRequest { x, y })
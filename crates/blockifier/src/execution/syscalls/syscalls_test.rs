@@ -3,13 +3,19 @@ use std::collections::{BTreeMap, HashMap, HashSet};
 use assert_matches::assert_matches;
 use cairo_felt::Felt252;
 use cairo_lang_utils::byte_array::BYTE_ARRAY_MAGIC;
+use cairo_vm::types::relocatable::Relocatable;
+use cairo_vm::vm::errors::cairo_run_errors::CairoRunError;
+use cairo_vm::vm::errors::hint_errors::HintError;
+use cairo_vm::vm::errors::vm_errors::VirtualMachineError;
 use cairo_vm::vm::runners::builtin_runner::RANGE_CHECK_BUILTIN_NAME;
 use cairo_vm::vm::runners::cairo_runner::ExecutionResources;
+use cairo_vm::vm::vm_core::VirtualMachine;
 use itertools::concat;
 use num_traits::Pow;
 use pretty_assertions::assert_eq;
 use starknet_api::core::{
-    calculate_contract_address, ChainId, ClassHash, ContractAddress, EthAddress, Nonce, PatriciaKey,
+    calculate_contract_address, ChainId, ClassHash, CompiledClassHash, ContractAddress,
+    EntryPointSelector, EthAddress, Nonce, PatriciaKey,
 };
 use starknet_api::data_availability::DataAvailabilityMode;
 use starknet_api::hash::{StarkFelt, StarkHash};
@@ -24,33 +30,46 @@ use test_case::test_case;
 
 use crate::abi::abi_utils::selector_from_name;
 use crate::abi::constants;
-use crate::context::ChainInfo;
+use crate::context::{BlockContext, ChainInfo};
 use crate::execution::call_info::{
     CallExecution, CallInfo, MessageToL1, OrderedEvent, OrderedL2ToL1Message, Retdata,
 };
-use crate::execution::common_hints::ExecutionMode;
-use crate::execution::contract_class::ContractClassV0;
-use crate::execution::entry_point::{CallEntryPoint, CallType};
-use crate::execution::errors::EntryPointExecutionError;
-use crate::execution::execution_utils::{felt_to_stark_felt, stark_felt_to_felt};
+use crate::execution::common_hints::{ExecutionMode, HintExecutionResult};
+use crate::execution::contract_class::{ContractClassV0, ContractClassV1};
+use crate::execution::deprecated_syscalls::DeprecatedSyscallSelector;
+use crate::execution::entry_point::{CallEntryPoint, CallType, EntryPointExecutionContext};
+use crate::execution::errors::{
+    EntryPointExecutionError, PostExecutionError, PreExecutionError,
+};
+use crate::execution::execution_utils::{felt_to_stark_felt, stark_felt_to_felt, ReadOnlySegments};
 use crate::execution::syscalls::hint_processor::{
-    EmitEventError, BLOCK_NUMBER_OUT_OF_RANGE_ERROR, L1_GAS, L2_GAS, OUT_OF_GAS_ERROR,
+    ArrayLengthError, CalldataLengthError, EmitEventError, SyscallExecutionError,
+    SyscallHintProcessor, SyscallMetricsMap, SyscallReadContext, BLOCK_NUMBER_OUT_OF_RANGE_ERROR,
+    CONTRACT_NOT_DEPLOYED_ERROR, L1_GAS, L2_GAS, OUT_OF_GAS_ERROR,
+};
+use crate::execution::syscalls::{
+    emit_event, keccak, library_call_l1_handler, CallContractRequest, DeployRequest, GetBlockHashRequest,
+    KeccakRequest, LibraryCallRequest, SendMessageToL1Request, StorageReadRequest,
+    StorageReadResponse, SyscallRequest, SyscallRequestWrapper, SyscallSelector,
 };
+use crate::state::errors::StateError;
 use crate::state::state_api::{State, StateReader};
 use crate::test_utils::cached_state::{create_deploy_test_state, create_test_state};
 use crate::test_utils::contracts::FeatureContract;
 use crate::test_utils::initial_test_state::test_state;
 use crate::test_utils::{
-    create_calldata, trivial_external_entry_point, CairoVersion, BALANCE, CHAIN_ID_NAME,
-    CURRENT_BLOCK_NUMBER, CURRENT_BLOCK_NUMBER_FOR_VALIDATE, CURRENT_BLOCK_TIMESTAMP,
-    CURRENT_BLOCK_TIMESTAMP_FOR_VALIDATE, TEST_CLASS_HASH, TEST_CONTRACT_ADDRESS,
-    TEST_EMPTY_CONTRACT_CAIRO0_PATH, TEST_EMPTY_CONTRACT_CLASS_HASH, TEST_SEQUENCER_ADDRESS,
+    create_calldata, get_raw_contract_class, trivial_external_entry_point, CairoVersion, BALANCE,
+    CHAIN_ID_NAME, CURRENT_BLOCK_NUMBER, CURRENT_BLOCK_NUMBER_FOR_VALIDATE,
+    CURRENT_BLOCK_TIMESTAMP, CURRENT_BLOCK_TIMESTAMP_FOR_VALIDATE, TEST_CLASS_HASH,
+    TEST_CONTRACT_ADDRESS, TEST_CONTRACT_CAIRO1_PATH, TEST_EMPTY_CONTRACT_CAIRO0_PATH,
+    TEST_EMPTY_CONTRACT_CLASS_HASH, TEST_SEQUENCER_ADDRESS,
 };
 use crate::transaction::constants::QUERY_VERSION_BASE_BIT;
+use crate::transaction::errors::TransactionExecutionError;
 use crate::transaction::objects::{
     CommonAccountFields, CurrentTransactionInfo, DeprecatedTransactionInfo, TransactionInfo,
 };
-use crate::versioned_constants::VersionedConstants;
+use crate::versioned_constants::{SyscallGasCost, VersionedConstants};
 use crate::{check_entry_point_execution_error_for_custom_hint, retdata};
 
 pub const REQUIRED_GAS_STORAGE_READ_WRITE_TEST: u64 = 34650;
@@ -70,20 +89,131 @@ fn test_storage_read_write() {
         ..trivial_external_entry_point()
     };
     let storage_address = entry_point_call.storage_address;
+    let call_info = entry_point_call.execute_directly(&mut state).unwrap();
     assert_eq!(
-        entry_point_call.execute_directly(&mut state).unwrap().execution,
+        call_info.execution,
         CallExecution {
             retdata: retdata![stark_felt!(value)],
             gas_consumed: REQUIRED_GAS_STORAGE_READ_WRITE_TEST,
             ..CallExecution::default()
         }
     );
+    // Verify that the accessed key and the value read after the write are recorded.
+    assert_eq!(call_info.storage_read_values, vec![value]);
+    assert_eq!(
+        call_info.accessed_storage_keys,
+        HashSet::from([StorageKey::try_from(key).unwrap()])
+    );
     // Verify that the state has changed.
     let value_from_state =
         state.get_storage_at(storage_address, StorageKey::try_from(key).unwrap()).unwrap();
     assert_eq!(value_from_state, value);
 }
 
+/// A storage read deducts exactly its configured gas cost from the remaining gas.
+#[test_case(0, 0; "Shipped cost.")]
+#[test_case(1000, 0; "Increased base cost.")]
+#[test_case(0, 1000; "Linear factor of an input-less syscall.")]
+fn test_syscall_gas_cost(extra_base_cost: u64, linear_factor: u64) {
+    let mut state = create_test_state();
+    let mut block_context = BlockContext::create_for_testing();
+    let syscall_gas_costs = &mut block_context.versioned_constants.syscall_gas_costs;
+    let shipped_cost = syscall_gas_costs.get(&SyscallSelector::StorageRead).unwrap();
+    let storage_read_cost =
+        SyscallGasCost { base: shipped_cost.base + extra_base_cost, linear_factor };
+    *syscall_gas_costs = syscall_gas_costs
+        .clone()
+        .with_cost(SyscallSelector::StorageRead, storage_read_cost)
+        .unwrap();
+
+    let entry_point_call = CallEntryPoint {
+        calldata: calldata![stark_felt!(1234_u16), stark_felt!(18_u8)],
+        entry_point_selector: selector_from_name("test_storage_read_write"),
+        ..trivial_external_entry_point()
+    };
+    let mut context = EntryPointExecutionContext::new_invoke_for_testing(block_context);
+    let call_info = entry_point_call
+        .execute(&mut state, &mut ExecutionResources::default(), &mut context)
+        .unwrap();
+
+    assert_eq!(
+        call_info.execution.gas_consumed,
+        REQUIRED_GAS_STORAGE_READ_WRITE_TEST + extra_base_cost
+    );
+}
+
+/// An input-dependent syscall deducts its base cost, plus its linear factor per unit of its input
+/// size, from the remaining gas.
+#[test_case(SyscallSelector::CallContract; "Call contract.")]
+#[test_case(SyscallSelector::EmitEvent; "Emit event.")]
+#[test_case(SyscallSelector::SendMessageToL1; "Send message to L1.")]
+fn test_syscall_gas_cost_by_input_size(selector: SyscallSelector) {
+    let (entry_point_name, calldata, input_size) = match selector {
+        SyscallSelector::CallContract => (
+            "test_call_contract",
+            create_calldata(
+                contract_address!(TEST_CONTRACT_ADDRESS),
+                "test_storage_read_write",
+                &[stark_felt!(405_u16), stark_felt!(48_u8)],
+            ),
+            2,
+        ),
+        SyscallSelector::EmitEvent => (
+            "test_emit_events",
+            // A single event with 2 keys and 3 data elements.
+            calldata![
+                stark_felt!(1_u8),
+                stark_felt!(2_u8),
+                stark_felt!(2019_u16),
+                stark_felt!(2020_u16),
+                stark_felt!(3_u8),
+                stark_felt!(2021_u16),
+                stark_felt!(2022_u16),
+                stark_felt!(2023_u16)
+            ],
+            5,
+        ),
+        SyscallSelector::SendMessageToL1 => (
+            "test_send_message_to_l1",
+            // A message with a payload of 3 elements.
+            calldata![
+                stark_felt!(1234_u16),
+                stark_felt!(3_u8),
+                stark_felt!(2019_u16),
+                stark_felt!(2020_u16),
+                stark_felt!(2021_u16)
+            ],
+            3,
+        ),
+        _ => panic!("Unexpected syscall: {selector:?}."),
+    };
+    let gas_consumed = |extra_base_cost: u64, linear_factor: u64| {
+        let mut block_context = BlockContext::create_for_testing();
+        let syscall_gas_costs = &mut block_context.versioned_constants.syscall_gas_costs;
+        let shipped_cost = syscall_gas_costs.get(&selector).unwrap();
+        let cost = SyscallGasCost { base: shipped_cost.base + extra_base_cost, linear_factor };
+        *syscall_gas_costs = syscall_gas_costs.clone().with_cost(selector, cost).unwrap();
+
+        let entry_point_call = CallEntryPoint {
+            entry_point_selector: selector_from_name(entry_point_name),
+            calldata: calldata.clone(),
+            ..trivial_external_entry_point()
+        };
+        let mut context = EntryPointExecutionContext::new_invoke_for_testing(block_context);
+        entry_point_call
+            .execute(&mut create_test_state(), &mut ExecutionResources::default(), &mut context)
+            .unwrap()
+            .execution
+            .gas_consumed
+    };
+
+    let (extra_base_cost, linear_factor) = (1000, 100);
+    assert_eq!(
+        gas_consumed(extra_base_cost, linear_factor),
+        gas_consumed(0, 0) + extra_base_cost + linear_factor * input_size
+    );
+}
+
 #[test]
 fn test_call_contract() {
     let mut state = create_test_state();
@@ -112,6 +242,582 @@ fn test_call_contract() {
     );
 }
 
+#[test_case(true; "Collected.")]
+#[test_case(false; "Not collected.")]
+fn test_syscall_metrics(collect: bool) {
+    let mut state = create_test_state();
+    let calldata = create_calldata(
+        contract_address!(TEST_CONTRACT_ADDRESS),
+        "test_storage_read_write",
+        &[
+            stark_felt!(405_u16), // Calldata: address.
+            stark_felt!(48_u8),   // Calldata: value.
+        ],
+    );
+    let entry_point_call = CallEntryPoint {
+        entry_point_selector: selector_from_name("test_call_contract"),
+        calldata,
+        ..trivial_external_entry_point()
+    };
+    let mut context =
+        EntryPointExecutionContext::new_invoke_for_testing(BlockContext::create_for_testing());
+    if collect {
+        context.syscall_metrics = Some(SyscallMetricsMap::new());
+    }
+
+    let call_info = entry_point_call
+        .execute(&mut state, &mut ExecutionResources::default(), &mut context)
+        .unwrap();
+
+    let Some(syscall_metrics) = context.syscall_metrics else {
+        assert!(!collect);
+        return;
+    };
+    // The syscalls of the outer call and of its inner call.
+    let expected_n_invocations = HashMap::from([
+        (SyscallSelector::CallContract, 1),
+        (SyscallSelector::StorageRead, 1),
+        (SyscallSelector::StorageWrite, 1),
+    ]);
+    let n_invocations: HashMap<SyscallSelector, usize> = syscall_metrics
+        .iter()
+        .map(|(&selector, metrics)| (selector, metrics.n_invocations))
+        .collect();
+    assert_eq!(n_invocations, expected_n_invocations);
+
+    // Only the call is charged with VM steps: those of the inner execution.
+    assert_eq!(
+        syscall_metrics[&SyscallSelector::CallContract].n_steps,
+        call_info.inner_calls[0].resources.n_steps
+    );
+    assert_eq!(syscall_metrics[&SyscallSelector::StorageRead].n_steps, 0);
+    assert_eq!(syscall_metrics[&SyscallSelector::StorageWrite].n_steps, 0);
+    assert!(
+        syscall_metrics[&SyscallSelector::CallContract].wall_time
+            >= syscall_metrics[&SyscallSelector::StorageRead].wall_time
+    );
+}
+
+#[test_case(2, None; "Calldata length at the limit.")]
+#[test_case(1, Some("Calldata length 2 exceeds the maximum of 1."); "Calldata length over the limit.")]
+fn test_call_contract_calldata_length_limit(
+    max_calldata_length: usize,
+    expected_error: Option<&str>,
+) {
+    let mut state = create_test_state();
+
+    let calldata = create_calldata(
+        contract_address!(TEST_CONTRACT_ADDRESS),
+        "test_storage_read_write",
+        &[
+            stark_felt!(405_u16), // Calldata: address.
+            stark_felt!(48_u8),   // Calldata: value.
+        ],
+    );
+    let entry_point_call = CallEntryPoint {
+        entry_point_selector: selector_from_name("test_call_contract"),
+        calldata,
+        ..trivial_external_entry_point()
+    };
+    let mut block_context = BlockContext::create_for_testing();
+    block_context.versioned_constants.syscall_limits.max_calldata_length = max_calldata_length;
+    let mut context = EntryPointExecutionContext::new_invoke_for_testing(block_context);
+
+    let result =
+        entry_point_call.execute(&mut state, &mut ExecutionResources::default(), &mut context);
+    match expected_error {
+        None => assert_eq!(result.unwrap().execution.retdata, retdata![stark_felt!(48_u8)]),
+        Some(expected_error) => {
+            assert!(result.unwrap_err().to_string().contains(expected_error))
+        }
+    }
+}
+
+#[test]
+fn test_call_contract_of_undeployed_address() {
+    let mut state = create_test_state();
+
+    let calldata = create_calldata(
+        contract_address!("0x1234"),
+        "test_storage_read_write",
+        &[
+            stark_felt!(405_u16), // Calldata: address.
+            stark_felt!(48_u8),   // Calldata: value.
+        ],
+    );
+    let entry_point_call = CallEntryPoint {
+        entry_point_selector: selector_from_name("test_call_contract"),
+        calldata,
+        ..trivial_external_entry_point()
+    };
+    // The caller observes a failure response and panics with its data.
+    let error = entry_point_call.execute_directly(&mut state).unwrap_err();
+    assert_matches!(error, EntryPointExecutionError::ExecutionFailed{ error_data }
+        if error_data == vec![stark_felt!(CONTRACT_NOT_DEPLOYED_ERROR)]);
+}
+
+#[test_case("l1_handle", true; "L1 handler entry point.")]
+#[test_case("test_storage_read_write", false; "External entry point.")]
+fn test_library_call_l1_handler_entry_point_lookup(entry_point_name: &str, expect_found: bool) {
+    let mut state = create_test_state();
+    let mut resources = ExecutionResources::default();
+    let mut context =
+        EntryPointExecutionContext::new_invoke_for_testing(BlockContext::create_for_testing());
+    let hints = HashMap::new();
+    let mut syscall_handler = SyscallHintProcessor::new(
+        &mut state,
+        &mut resources,
+        &mut context,
+        Relocatable::from((0, 0)),
+        trivial_external_entry_point(),
+        &hints,
+        ReadOnlySegments::default(),
+    );
+
+    let function_selector = selector_from_name(entry_point_name);
+    let request = LibraryCallRequest {
+        class_hash: class_hash!(TEST_CLASS_HASH),
+        function_selector,
+        calldata: calldata![stark_felt!(0_u8), stark_felt!(1_u8)],
+    };
+    let result = library_call_l1_handler(
+        request,
+        &mut VirtualMachine::new(false),
+        &mut syscall_handler,
+        &mut VersionedConstants::create_for_testing().gas_cost("initial_gas_cost"),
+    );
+
+    if expect_found {
+        result.unwrap();
+    } else {
+        assert_matches!(
+            result.unwrap_err(),
+            SyscallExecutionError::LibraryCallExecutionError { class_hash, error, .. }
+            if class_hash == class_hash!(TEST_CLASS_HASH) && matches!(
+                *error,
+                SyscallExecutionError::EntryPointExecutionError(
+                    EntryPointExecutionError::PreExecutionError(
+                        PreExecutionError::EntryPointNotFound(selector)
+                    )
+                ) if selector == function_selector
+            )
+        );
+    }
+}
+
+/// Executes a custom storage read syscall, which always reads the same value.
+fn custom_storage_read(
+    syscall_handler: &mut SyscallHintProcessor<'_>,
+    vm: &mut VirtualMachine,
+) -> HintExecutionResult {
+    let syscall_gas_cost = syscall_handler
+        .context
+        .versioned_constants()
+        .syscall_gas_costs
+        .get(&SyscallSelector::StorageRead)
+        .unwrap();
+    syscall_handler.execute_syscall(
+        vm,
+        SyscallSelector::StorageRead,
+        |_request: StorageReadRequest, _vm, _syscall_handler, _remaining_gas| {
+            Ok(StorageReadResponse { value: stark_felt!(CUSTOM_STORAGE_VALUE) })
+        },
+        syscall_gas_cost,
+    )
+}
+
+const CUSTOM_STORAGE_VALUE: u16 = 1990;
+const CUSTOM_STORAGE_READ_SELECTOR: &str = "0x437573746f6d53746f7261676552656164"; // 'CustomStorageRead'.
+
+#[test_case(true; "Registered custom syscall.")]
+#[test_case(false; "Unregistered custom syscall.")]
+fn test_custom_syscall(register: bool) {
+    // Make the test contract issue a custom syscall instead of storage reads.
+    let storage_read_selector = "0x53746f7261676552656164"; // 'StorageRead'.
+    let raw_contract_class = get_raw_contract_class(TEST_CONTRACT_CAIRO1_PATH)
+        .replace(storage_read_selector, CUSTOM_STORAGE_READ_SELECTOR);
+    let contract_class = ContractClassV1::try_from_json_string(&raw_contract_class).unwrap();
+    let mut state = create_test_state();
+    state.set_contract_class(class_hash!(TEST_CLASS_HASH), contract_class.into()).unwrap();
+
+    let mut context =
+        EntryPointExecutionContext::new_invoke_for_testing(BlockContext::create_for_testing());
+    if register {
+        context.register_syscall(stark_felt!(CUSTOM_STORAGE_READ_SELECTOR), custom_storage_read);
+    }
+
+    let entry_point_call = CallEntryPoint {
+        calldata: calldata![stark_felt!(1234_u16), stark_felt!(18_u8)],
+        entry_point_selector: selector_from_name("test_storage_read_write"),
+        ..trivial_external_entry_point()
+    };
+    let result =
+        entry_point_call.execute(&mut state, &mut ExecutionResources::default(), &mut context);
+
+    if register {
+        assert_eq!(result.unwrap().execution.retdata, retdata![stark_felt!(CUSTOM_STORAGE_VALUE)]);
+    } else {
+        assert!(result.unwrap_err().to_string().contains("Invalid syscall selector"));
+    }
+}
+
+/// Executes a custom syscall, unknown to the blockifier, which emits an event.
+fn custom_emit_event(
+    syscall_handler: &mut SyscallHintProcessor<'_>,
+    vm: &mut VirtualMachine,
+) -> HintExecutionResult {
+    let syscall_gas_cost = syscall_handler
+        .context
+        .versioned_constants()
+        .syscall_gas_costs
+        .get(&SyscallSelector::EmitEvent)
+        .unwrap();
+    syscall_handler.execute_syscall(vm, SyscallSelector::EmitEvent, emit_event, syscall_gas_cost)
+}
+
+const CUSTOM_EMIT_EVENT_SELECTOR: &str = "0x437573746f6d456d69744576656e74"; // 'CustomEmitEvent'.
+
+/// Custom syscalls whose selectors are not built-in count towards the syscall limit as well.
+#[test]
+fn test_custom_syscall_exceeds_max_syscalls_per_tx() {
+    // Make the test contract emit events through a custom syscall.
+    let emit_event_selector = "0x456d69744576656e74"; // 'EmitEvent'.
+    let raw_contract_class = get_raw_contract_class(TEST_CONTRACT_CAIRO1_PATH)
+        .replace(emit_event_selector, CUSTOM_EMIT_EVENT_SELECTOR);
+    let contract_class = ContractClassV1::try_from_json_string(&raw_contract_class).unwrap();
+    let mut state = create_test_state();
+    state.set_contract_class(class_hash!(TEST_CLASS_HASH), contract_class.into()).unwrap();
+
+    let max_syscalls_per_tx = 5;
+    let mut block_context = BlockContext::create_for_testing();
+    block_context.versioned_constants.syscall_limits.max_syscalls_per_tx =
+        Some(max_syscalls_per_tx);
+    let mut context = EntryPointExecutionContext::new_invoke_for_testing(block_context);
+    context.register_syscall(stark_felt!(CUSTOM_EMIT_EVENT_SELECTOR), custom_emit_event);
+
+    // Emit one event more than the limit, in a loop.
+    let n_events = max_syscalls_per_tx + 1;
+    let entry_point_call = CallEntryPoint {
+        entry_point_selector: selector_from_name("test_emit_events"),
+        // Calldata: n_events, keys_len, keys, data_len, data.
+        calldata: calldata![
+            stark_felt!(u8::try_from(n_events).unwrap()),
+            stark_felt!(0_u8),
+            stark_felt!(0_u8)
+        ],
+        ..trivial_external_entry_point()
+    };
+    let error = entry_point_call
+        .execute(&mut state, &mut ExecutionResources::default(), &mut context)
+        .unwrap_err()
+        .to_string();
+
+    assert_eq!(context.n_executed_syscalls, n_events);
+    assert!(error.contains(&format!(
+        "The {} syscall exceeds the maximum of {max_syscalls_per_tx} syscalls per transaction.",
+        stark_felt!(CUSTOM_EMIT_EVENT_SELECTOR)
+    )));
+}
+
+/// Custom syscalls override the built-in syscalls of their selectors, in inner calls as well.
+#[test]
+fn test_custom_syscall_overrides_builtin_syscall() {
+    let mut state = create_test_state();
+    let mut context =
+        EntryPointExecutionContext::new_invoke_for_testing(BlockContext::create_for_testing());
+    let storage_read_selector = stark_felt!("0x53746f7261676552656164"); // 'StorageRead'.
+    context.register_syscall(storage_read_selector, custom_storage_read);
+
+    let entry_point_call = CallEntryPoint {
+        entry_point_selector: selector_from_name("test_call_contract"),
+        calldata: create_calldata(
+            contract_address!(TEST_CONTRACT_ADDRESS),
+            "test_storage_read_write",
+            &[stark_felt!(405_u16), stark_felt!(48_u8)],
+        ),
+        ..trivial_external_entry_point()
+    };
+    let call_info = entry_point_call
+        .execute(&mut state, &mut ExecutionResources::default(), &mut context)
+        .unwrap();
+
+    assert_eq!(call_info.execution.retdata, retdata![stark_felt!(CUSTOM_STORAGE_VALUE)]);
+}
+
+#[test_case(true, true; "Declared class.")]
+#[test_case(true, false; "Undeclared class.")]
+#[test_case(false, true; "Syscall disabled.")]
+fn test_get_compiled_class_hash(allow_syscall: bool, declared: bool) {
+    // Make the test contract query compiled class hashes instead of block hashes; both syscalls
+    // take a single felt and return a single felt.
+    // The 'GetBlockHash' and 'GetCompiledClassHash' selectors, respectively.
+    let block_hash_selector = "0x476574426c6f636b48617368";
+    let compiled_class_hash_selector = "0x476574436f6d70696c6564436c61737348617368";
+    let raw_contract_class = get_raw_contract_class(TEST_CONTRACT_CAIRO1_PATH)
+        .replace(block_hash_selector, compiled_class_hash_selector);
+    let contract_class = ContractClassV1::try_from_json_string(&raw_contract_class).unwrap();
+    let mut state = create_test_state();
+    state.set_contract_class(class_hash!(TEST_CLASS_HASH), contract_class.into()).unwrap();
+
+    let queried_class_hash = class_hash!(1234_u16);
+    let compiled_class_hash = CompiledClassHash(stark_felt!(5678_u16));
+    if declared {
+        state.set_compiled_class_hash(queried_class_hash, compiled_class_hash).unwrap();
+    }
+
+    let mut block_context = BlockContext::create_for_testing();
+    block_context.set_allow_get_compiled_class_hash(allow_syscall);
+    let mut context = EntryPointExecutionContext::new_invoke_for_testing(block_context);
+
+    let entry_point_call = CallEntryPoint {
+        calldata: calldata![queried_class_hash.0],
+        entry_point_selector: selector_from_name("test_get_block_hash"),
+        ..trivial_external_entry_point()
+    };
+    let result =
+        entry_point_call.execute(&mut state, &mut ExecutionResources::default(), &mut context);
+
+    if !allow_syscall {
+        assert!(result.unwrap_err().to_string().contains("Invalid syscall selector"));
+        return;
+    }
+    let expected_compiled_class_hash =
+        if declared { compiled_class_hash } else { CompiledClassHash::default() };
+    assert_eq!(result.unwrap().execution.retdata, retdata![expected_compiled_class_hash.0]);
+}
+
+const MAX_ARRAY_LENGTH: usize = 10;
+
+#[test_case(
+    5,
+    ArrayLengthError::ExceedsSegment { syscall: DeprecatedSyscallSelector::SendMessageToL1, length: 5 };
+    "Array length exceeds the initialized memory")]
+#[test_case(
+    MAX_ARRAY_LENGTH + 1,
+    ArrayLengthError::ExceedsMaxLength {
+        syscall: DeprecatedSyscallSelector::SendMessageToL1,
+        length: MAX_ARRAY_LENGTH + 1,
+        max_length: MAX_ARRAY_LENGTH,
+    };
+    "Array length exceeds the maximal length")]
+fn test_array_length_validation(length: usize, expected_error: ArrayLengthError) {
+    let mut vm = VirtualMachine::new(false);
+    // The payload has only 3 initialized elements, regardless of its declared end.
+    let payload_start_ptr = vm.add_memory_segment();
+    vm.load_data(payload_start_ptr, &vec![Felt252::from(1_u8).into(); 3]).unwrap();
+    let payload_end_ptr = (payload_start_ptr + length).unwrap();
+    let request_ptr = vm.add_memory_segment();
+    let request =
+        vec![Felt252::from(0_u8).into(), payload_start_ptr.into(), payload_end_ptr.into()];
+    vm.load_data(request_ptr, &request).unwrap();
+
+    let read_context = SyscallReadContext {
+        max_array_length: MAX_ARRAY_LENGTH,
+        ..SyscallReadContext::new(
+            SyscallSelector::SendMessageToL1,
+            VersionedConstants::latest_constants(),
+        )
+    };
+    let error =
+        SendMessageToL1Request::read(&vm, &mut request_ptr.clone(), &read_context).unwrap_err();
+    assert_matches!(error, SyscallExecutionError::ArrayLengthError(_));
+    assert_eq!(error.to_string(), expected_error.to_string());
+}
+
+#[test]
+fn test_keccak_input_length_validation() {
+    let mut vm = VirtualMachine::new(false);
+    let input_start_ptr = vm.add_memory_segment();
+    let input_length = MAX_ARRAY_LENGTH + 1;
+    vm.load_data(input_start_ptr, &vec![Felt252::from(1_u8).into(); input_length]).unwrap();
+    let input_end_ptr = (input_start_ptr + input_length).unwrap();
+    let request_ptr = vm.add_memory_segment();
+    vm.load_data(request_ptr, &vec![input_start_ptr.into(), input_end_ptr.into()]).unwrap();
+
+    let read_context = SyscallReadContext {
+        max_array_length: MAX_ARRAY_LENGTH,
+        ..SyscallReadContext::new(SyscallSelector::Keccak, VersionedConstants::latest_constants())
+    };
+    let error = KeccakRequest::read(&vm, &mut request_ptr.clone(), &read_context).unwrap_err();
+    assert_matches!(
+        error,
+        SyscallExecutionError::ArrayLengthError(ArrayLengthError::ExceedsMaxLength {
+            syscall: DeprecatedSyscallSelector::Keccak,
+            length,
+            max_length: MAX_ARRAY_LENGTH,
+        }) if length == input_length
+    );
+}
+
+#[test_case(selector_from_name("foo").0, true; "Valid selector.")]
+#[test_case(
+    stark_felt!("0x400000000000000000000000000000000000000000000000000000000000000"), false;
+    "Out-of-range selector.")]
+fn test_selector_validation(selector: StarkFelt, is_valid: bool) {
+    let mut vm = VirtualMachine::new(false);
+    let calldata_ptr = vm.add_memory_segment();
+    let request_ptr = vm.add_memory_segment();
+    let request = vec![
+        Felt252::from(1_u8).into(), // Contract address.
+        stark_felt_to_felt(selector).into(),
+        calldata_ptr.into(),
+        calldata_ptr.into(),
+    ];
+    vm.load_data(request_ptr, &request).unwrap();
+
+    let read_context = SyscallReadContext::new(
+        SyscallSelector::CallContract,
+        VersionedConstants::latest_constants(),
+    );
+    let result = CallContractRequest::read(&vm, &mut request_ptr.clone(), &read_context);
+    if is_valid {
+        assert_eq!(result.unwrap().function_selector, EntryPointSelector(selector));
+    } else {
+        assert_matches!(
+            result.unwrap_err(),
+            SyscallExecutionError::InvalidEntryPointSelector {
+                syscall: DeprecatedSyscallSelector::CallContract,
+                selector: error_selector,
+            } if error_selector == selector
+        );
+    }
+}
+
+#[test]
+fn test_calldata_length_verified_before_read() {
+    let mut vm = VirtualMachine::new(false);
+    // Only the first and last calldata elements are initialized; reading the calldata would fail.
+    let calldata_start_ptr = vm.add_memory_segment();
+    let calldata_end_ptr = (calldata_start_ptr + 5_usize).unwrap();
+    vm.load_data(calldata_start_ptr, &vec![Felt252::from(1_u8).into()]).unwrap();
+    vm.load_data((calldata_end_ptr - 1).unwrap(), &vec![Felt252::from(1_u8).into()]).unwrap();
+    let request_ptr = vm.add_memory_segment();
+    let request = vec![
+        Felt252::from(1_u8).into(), // Contract address.
+        stark_felt_to_felt(selector_from_name("foo").0).into(),
+        calldata_start_ptr.into(),
+        calldata_end_ptr.into(),
+    ];
+    vm.load_data(request_ptr, &request).unwrap();
+
+    let read_context = SyscallReadContext {
+        max_calldata_length: 2,
+        ..SyscallReadContext::new(
+            SyscallSelector::CallContract,
+            VersionedConstants::latest_constants(),
+        )
+    };
+    let error =
+        CallContractRequest::read(&vm, &mut request_ptr.clone(), &read_context).unwrap_err();
+    assert_matches!(
+        error,
+        SyscallExecutionError::CalldataTooLong(CalldataLengthError {
+            calldata_length: 5,
+            max_calldata_length: 2,
+        })
+    );
+}
+
+#[test_case(false; "Contiguous orders.")]
+#[test_case(true; "Gap in orders.")]
+fn test_finalize_message_order_continuity(with_gap: bool) {
+    let mut state = create_test_state();
+    let mut resources = ExecutionResources::default();
+    let mut context =
+        EntryPointExecutionContext::new_invoke_for_testing(BlockContext::create_for_testing());
+    // Orders are counted per transaction; start in the middle of one.
+    context.n_sent_messages_to_l1 = 3;
+    let hints = HashMap::new();
+    let mut syscall_handler = SyscallHintProcessor::new(
+        &mut state,
+        &mut resources,
+        &mut context,
+        Relocatable::from((0, 0)),
+        trivial_external_entry_point(),
+        &hints,
+        ReadOnlySegments::default(),
+    );
+
+    syscall_handler.add_l2_to_l1_message(MessageToL1::default());
+    if with_gap {
+        syscall_handler.context.n_sent_messages_to_l1 += 1;
+    }
+    syscall_handler.add_l2_to_l1_message(MessageToL1::default());
+
+    let result = syscall_handler.finalize();
+    if with_gap {
+        assert_matches!(
+            result.unwrap_err(),
+            PostExecutionError::NonContiguousOrders {
+                output_kind: "L2-to-L1 message",
+                expected_order: 4,
+                actual_order: 5,
+            }
+        );
+    } else {
+        let orders: Vec<usize> =
+            result.unwrap().l2_to_l1_messages.iter().map(|message| message.order).collect();
+        assert_eq!(orders, [3, 4]);
+    }
+}
+
+#[test_case(true, "Unexpected gas."; "Gas counter out of range.")]
+#[test_case(false, "Block number must fit within 64 bits."; "Block number out of range.")]
+fn test_out_of_range_u64_input(gas_out_of_range: bool, expected_info: &str) {
+    let mut vm = VirtualMachine::new(false);
+    let out_of_range = Felt252::from(u64::MAX) + Felt252::from(1_u8);
+    let (gas_counter, block_number) = if gas_out_of_range {
+        (out_of_range.clone(), Felt252::from(0_u8))
+    } else {
+        (Felt252::from(0_u8), out_of_range.clone())
+    };
+    let request_ptr = vm.add_memory_segment();
+    vm.load_data(request_ptr, &vec![gas_counter.into(), block_number.into()]).unwrap();
+
+    let read_context = SyscallReadContext::new(
+        SyscallSelector::GetBlockHash,
+        VersionedConstants::latest_constants(),
+    );
+    let result = SyscallRequestWrapper::<GetBlockHashRequest>::read(
+        &vm,
+        &mut request_ptr.clone(),
+        &read_context,
+    );
+    let Err(error) = result else { panic!("Expected the request read to fail.") };
+    assert_matches!(
+        error,
+        SyscallExecutionError::InvalidSyscallInput { input, info }
+        if input == felt_to_stark_felt(&out_of_range) && info == expected_info
+    );
+}
+
+#[test]
+fn test_invalid_deploy_from_zero() {
+    let mut vm = VirtualMachine::new(false);
+    let constructor_calldata_ptr = vm.add_memory_segment();
+    let deploy_from_zero = stark_felt!(2_u8);
+    let request_ptr = vm.add_memory_segment();
+    let request = vec![
+        Felt252::from(0_u8).into(), // Class hash.
+        Felt252::from(0_u8).into(), // Contract address salt.
+        constructor_calldata_ptr.into(),
+        constructor_calldata_ptr.into(),
+        stark_felt_to_felt(deploy_from_zero).into(),
+    ];
+    vm.load_data(request_ptr, &request).unwrap();
+
+    let read_context =
+        SyscallReadContext::new(SyscallSelector::Deploy, VersionedConstants::latest_constants());
+    let error = DeployRequest::read(&vm, &mut request_ptr.clone(), &read_context).unwrap_err();
+    assert_matches!(
+        error,
+        SyscallExecutionError::InvalidSyscallInput { input, .. } if input == deploy_from_zero
+    );
+    let error_message = error.to_string();
+    assert!(error_message.contains(&deploy_from_zero.to_string()));
+    assert!(error_message.contains("deploy_from_zero"));
+}
+
 #[test]
 fn test_emit_event() {
     let versioned_constants = VersionedConstants::create_for_testing();
@@ -193,6 +899,60 @@ fn emit_events(
     entry_point_call.execute_directly(&mut state)
 }
 
+/// Extracts the syscall error raised by the hint processor from an entry point execution error.
+fn extract_syscall_error(error: &EntryPointExecutionError) -> &SyscallExecutionError {
+    let EntryPointExecutionError::VirtualMachineExecutionErrorWithTrace {
+        source: CairoRunError::VmException(exception),
+        ..
+    } = error
+    else {
+        panic!("Unexpected structure for error: {error:?}");
+    };
+    let VirtualMachineError::Hint(hint) = &exception.inner_exc else {
+        panic!("Unexpected inner exception: {:?}", exception.inner_exc);
+    };
+    let HintError::Internal(VirtualMachineError::Other(error)) = &hint.1 else {
+        panic!("Unexpected hint error: {:?}", hint.1);
+    };
+    error.downcast_ref::<SyscallExecutionError>().expect("Expected a syscall execution error.")
+}
+
+#[test]
+fn test_failed_syscall_attribution() {
+    // Emit event with too much data.
+    let max_event_data_length =
+        VersionedConstants::create_for_testing().event_size_limit.max_data_length;
+    let data_too_long = vec![stark_felt!(2_u16); max_event_data_length + 1];
+    let error = emit_events(&[stark_felt!(1_u16)], &[], &data_too_long).unwrap_err();
+    assert!(error.to_string().contains("EmitEvent syscall at offset"));
+    assert_matches!(
+        extract_syscall_error(&error),
+        SyscallExecutionError::FailedSyscall { selector: SyscallSelector::EmitEvent, error, .. }
+        if matches!(
+            **error,
+            SyscallExecutionError::EmitEventError(EmitEventError::ExceedsMaxDataLength { .. })
+        )
+    );
+
+    // Get block hash in validate mode.
+    let mut state = create_test_state();
+    let entry_point_call = CallEntryPoint {
+        entry_point_selector: selector_from_name("test_get_block_hash"),
+        calldata: calldata![stark_felt!(CURRENT_BLOCK_NUMBER)],
+        ..trivial_external_entry_point()
+    };
+    let error = entry_point_call.execute_directly_in_validate_mode(&mut state).unwrap_err();
+    assert!(error.to_string().contains("GetBlockHash syscall at offset 0"));
+    assert_matches!(
+        extract_syscall_error(&error),
+        SyscallExecutionError::FailedSyscall {
+            selector: SyscallSelector::GetBlockHash,
+            syscall_ptr_offset: 0,
+            error,
+        } if matches!(**error, SyscallExecutionError::InvalidSyscallInExecutionMode { .. })
+    );
+}
+
 #[test]
 fn test_get_block_hash() {
     let mut state = create_test_state();
@@ -223,7 +983,8 @@ fn test_get_block_hash() {
     let error = entry_point_call.execute_directly_in_validate_mode(&mut state).unwrap_err();
     check_entry_point_execution_error_for_custom_hint!(
         &error,
-        "Unauthorized syscall get_block_hash in execution mode Validate.",
+        "GetBlockHash syscall at offset 0 of the syscall segment failed: Unauthorized syscall \
+         get_block_hash in execution mode Validate.",
     );
 
     // Negative flow: Block number out of range.
@@ -251,10 +1012,75 @@ fn test_keccak() {
         ..trivial_external_entry_point()
     };
 
+    let mut context =
+        EntryPointExecutionContext::new_invoke_for_testing(BlockContext::create_for_testing());
+    context.syscall_metrics = Some(SyscallMetricsMap::new());
+
     assert_eq!(
-        entry_point_call.execute_directly(&mut state).unwrap().execution,
+        entry_point_call
+            .execute(&mut state, &mut ExecutionResources::default(), &mut context)
+            .unwrap()
+            .execution,
         CallExecution { gas_consumed: 354940, ..CallExecution::from_retdata(retdata![]) }
     );
+
+    // A single (padded) block is hashed; the invocation with an invalid input length performs no
+    // rounds.
+    let keccak_metrics = context.syscall_metrics.unwrap()[&SyscallSelector::Keccak];
+    assert_eq!((keccak_metrics.n_invocations, keccak_metrics.linear_factor), (2, 1));
+}
+
+/// Executes the keccak syscall on the given number of (zero) blocks; returns the consumed gas and
+/// the OS resources charged for the syscall.
+fn execute_keccak_syscall(n_blocks: usize) -> (u64, ExecutionResources) {
+    const KECCAK_FULL_RATE_IN_WORDS: usize = 17;
+    let mut state = create_test_state();
+    let mut resources = ExecutionResources::default();
+    let mut context =
+        EntryPointExecutionContext::new_invoke_for_testing(BlockContext::create_for_testing());
+    let hints = HashMap::new();
+    let mut syscall_handler = SyscallHintProcessor::new(
+        &mut state,
+        &mut resources,
+        &mut context,
+        Relocatable::from((0, 0)),
+        trivial_external_entry_point(),
+        &hints,
+        ReadOnlySegments::default(),
+    );
+
+    let mut vm = VirtualMachine::new(false);
+    let input_start = vm.add_memory_segment();
+    let input = vec![Felt252::from(0_u8).into(); n_blocks * KECCAK_FULL_RATE_IN_WORDS];
+    let input_end = vm.load_data(input_start, &input).unwrap();
+    let initial_gas = VersionedConstants::create_for_testing().gas_cost("initial_gas_cost");
+    let mut remaining_gas = initial_gas;
+    keccak(
+        KeccakRequest { input_start, input_end },
+        &mut vm,
+        &mut syscall_handler,
+        &mut remaining_gas,
+    )
+    .unwrap();
+
+    let os_resources = VersionedConstants::create_for_testing()
+        .get_additional_os_syscall_resources(&syscall_handler.syscall_counter)
+        .unwrap();
+    (initial_gas - remaining_gas, os_resources)
+}
+
+#[test]
+/// Tests that the gas and OS resources of the keccak syscall scale by the number of hashed blocks.
+fn test_keccak_cost_by_n_blocks() {
+    let round_gas_cost =
+        VersionedConstants::create_for_testing().gas_cost("keccak_round_cost_gas_cost");
+
+    let (single_block_gas, single_block_os_resources) = execute_keccak_syscall(1);
+    let (gas, os_resources) = execute_keccak_syscall(17);
+    assert_eq!(single_block_gas, round_gas_cost);
+    assert_eq!(gas, 17 * round_gas_cost);
+    assert!(single_block_os_resources.n_steps > 0);
+    assert_eq!(os_resources, &single_block_os_resources * 17);
 }
 
 fn verify_compiler_version(contract: FeatureContract, expected_version: &str) {
@@ -539,6 +1365,37 @@ fn test_library_call_assert_fails() {
     );
 }
 
+#[test]
+fn test_library_call_undeclared_class() {
+    let mut state = create_test_state();
+    let undeclared_class_hash = class_hash!("0xdead");
+    let inner_entry_point_selector = selector_from_name("test_storage_read_write");
+    let calldata = calldata![
+        undeclared_class_hash.0,      // Class hash.
+        inner_entry_point_selector.0, // Function selector.
+        stark_felt!(2_u8),            // Calldata length.
+        stark_felt!(1234_u16),        // Calldata: address.
+        stark_felt!(91_u8)            // Calldata: value.
+    ];
+    let entry_point_call = CallEntryPoint {
+        entry_point_selector: selector_from_name("test_library_call"),
+        calldata,
+        class_hash: Some(class_hash!(TEST_CLASS_HASH)),
+        ..trivial_external_entry_point()
+    };
+
+    // The undeclared class hash is preserved through the library call, up to the transaction.
+    let error = entry_point_call.execute_directly(&mut state).unwrap_err();
+    assert_matches!(
+        error.state_error(),
+        Some(StateError::UndeclaredClassHash(class_hash)) if *class_hash == undeclared_class_hash
+    );
+    assert_matches!(
+        TransactionExecutionError::ExecutionError(error).state_error(),
+        Some(StateError::UndeclaredClassHash(class_hash)) if *class_hash == undeclared_class_hash
+    );
+}
+
 #[test]
 fn test_nested_library_call() {
     let mut state = create_test_state();
@@ -894,6 +1751,41 @@ fn test_out_of_gas() {
         if error_data == vec![stark_felt!(OUT_OF_GAS_ERROR)]);
 }
 
+#[test]
+fn test_out_of_gas_in_inner_call() {
+    let mut state = create_test_state();
+
+    // The outer call has enough gas to issue the call, but the inner call runs out of gas.
+    let calldata = create_calldata(
+        contract_address!(TEST_CONTRACT_ADDRESS),
+        "test_storage_read_write",
+        &[
+            stark_felt!(405_u16), // Calldata: address.
+            stark_felt!(48_u8),   // Calldata: value.
+        ],
+    );
+    let entry_point_call = CallEntryPoint {
+        entry_point_selector: selector_from_name("test_call_contract"),
+        calldata,
+        initial_gas: REQUIRED_GAS_CALL_CONTRACT_TEST - 1,
+        ..trivial_external_entry_point()
+    };
+    // The inner contract observes the out-of-gas failure response and panics, which fails the
+    // outer call as well.
+    let error = entry_point_call.execute_directly(&mut state).unwrap_err();
+    assert_matches!(
+        extract_syscall_error(&error),
+        SyscallExecutionError::CallContractExecutionError { error, .. }
+        if matches!(
+            **error,
+            SyscallExecutionError::EntryPointExecutionError(
+                EntryPointExecutionError::ExecutionFailed { ref error_data }
+            )
+            if *error_data == vec![stark_felt!(OUT_OF_GAS_ERROR)]
+        )
+    );
+}
+
 #[test]
 fn test_syscall_failure_format() {
     let error_data = vec![
@@ -4,7 +4,6 @@ use cairo_vm::vm::runners::cairo_runner::ExecutionResources;
 use starknet_api::core::ContractAddress;
 use starknet_api::transaction::Fee;
 
-use crate::abi::constants as abi_constants;
 use crate::context::TransactionContext;
 use crate::execution::call_info::CallInfo;
 use crate::execution::contract_class::ClassInfo;
@@ -15,10 +14,10 @@ use crate::fee::gas_usage::{
 use crate::state::cached_state::{CachedState, StateChanges, StateChangesCount};
 use crate::state::state_api::{StateReader, StateResult};
 use crate::transaction::objects::{
-    GasVector, HasRelatedFeeType, ResourcesMapping, TransactionExecutionResult,
+    GasVector, HasRelatedFeeType, Resource, ResourcesMapping, TransactionExecutionResult,
 };
 use crate::transaction::transaction_types::TransactionType;
-use crate::transaction::transaction_utils::calculate_tx_resources;
+use crate::transaction::transaction_utils::{calculate_tx_resources, TxInputSizes};
 use crate::versioned_constants::VersionedConstants;
 
 #[cfg(test)]
@@ -32,6 +31,27 @@ pub struct ActualCost {
     pub actual_fee: Fee,
     pub da_gas: GasVector,
     pub actual_resources: ResourcesMapping,
+    pub n_reverted_steps: usize,
+}
+
+/// How the steps consumed by a reverted execution are charged.
+#[derive(Clone, Copy, Debug, Default, Eq, PartialEq)]
+pub enum RevertedStepsPricing {
+    /// Charged as committed Cairo steps, as on mainnet.
+    #[default]
+    AsSteps,
+    /// Charged as the given percentage of their count (rounded up) in Cairo steps.
+    Percentage(usize),
+}
+
+impl RevertedStepsPricing {
+    /// Returns the number of Cairo steps charged for the given number of reverted steps.
+    pub fn charged_steps(&self, n_reverted_steps: usize) -> usize {
+        match self {
+            Self::AsSteps => n_reverted_steps,
+            Self::Percentage(percentage) => (n_reverted_steps * percentage).div_ceil(100),
+        }
+    }
 }
 
 impl ActualCost {
@@ -161,6 +181,10 @@ impl<'a> ActualCostBuilder<'a> {
         );
         // TODO(Dafna, 1/6/2024): Compute the DA size and pass it instead of state_changes_count.
         let da_gas = get_da_gas_cost(state_changes_count, use_kzg_da);
+        let class_size = self
+            .class_info
+            .as_ref()
+            .map(|class_info| class_info.bytecode_length() + class_info.sierra_program_length());
         let non_optional_call_infos =
             self.validate_call_info.into_iter().chain(self.execute_call_info);
         // Gas usage for SHARP costs and Starknet L1-L2 messages. Includes gas usage for data
@@ -181,14 +205,17 @@ impl<'a> ActualCostBuilder<'a> {
             execution_resources,
             gas_usage_vector,
             self.tx_type,
-            self.calldata_length,
+            TxInputSizes { calldata_length: self.calldata_length, class_size },
             state_changes_count,
             use_kzg_da,
         )?;
 
         // Add reverted steps to actual_resources' n_steps for correct fee charge.
-        *actual_resources.0.get_mut(&abi_constants::N_STEPS_RESOURCE.to_string()).unwrap() +=
-            self.n_reverted_steps;
+        *actual_resources.0.get_mut(&Resource::Steps).unwrap() += self
+            .tx_context
+            .block_context
+            .reverted_steps_pricing()
+            .charged_steps(self.n_reverted_steps);
 
         let tx_info = &self.tx_context.tx_info;
         let actual_fee = if tx_info.enforce_fee()?
@@ -200,7 +227,12 @@ impl<'a> ActualCostBuilder<'a> {
             Fee(0)
         };
 
-        Ok(ActualCost { actual_fee, da_gas, actual_resources })
+        Ok(ActualCost {
+            actual_fee,
+            da_gas,
+            actual_resources,
+            n_reverted_steps: self.n_reverted_steps,
+        })
     }
 
     /// Returns the gas usage of a transaction, specifically:
@@ -220,14 +252,16 @@ impl<'a> ActualCostBuilder<'a> {
         class_info: Option<ClassInfo>,
         use_kzg_da: bool,
     ) -> TransactionExecutionResult<GasVector> {
-        Ok(get_messages_gas_cost(call_infos.clone(), l1_handler_payload_size)?
-            + get_da_gas_cost(state_changes_count, use_kzg_da)
-            + get_calldata_and_signature_gas_cost(
+        Ok(GasVector::checked_sum([
+            get_messages_gas_cost(call_infos.clone(), l1_handler_payload_size)?,
+            get_da_gas_cost(state_changes_count, use_kzg_da),
+            get_calldata_and_signature_gas_cost(
                 calldata_length,
                 signature_length,
                 versioned_constants,
-            )
-            + get_code_gas_cost(class_info, versioned_constants)
-            + get_tx_events_gas_cost(call_infos, versioned_constants))
+            ),
+            get_code_gas_cost(class_info, versioned_constants),
+            get_tx_events_gas_cost(call_infos, versioned_constants),
+        ])?)
     }
 }
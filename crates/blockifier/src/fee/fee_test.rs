@@ -1,35 +1,34 @@
 use std::collections::HashMap;
+use std::sync::Arc;
 
 use assert_matches::assert_matches;
-use cairo_vm::vm::runners::builtin_runner::{
-    BITWISE_BUILTIN_NAME, HASH_BUILTIN_NAME, POSEIDON_BUILTIN_NAME, RANGE_CHECK_BUILTIN_NAME,
-    SIGNATURE_BUILTIN_NAME,
-};
 use rstest::rstest;
 use starknet_api::transaction::{Fee, TransactionVersion};
 
-use crate::abi::constants;
 use crate::context::BlockContext;
+use crate::execution::entry_point::EntryPointExecutionContext;
 use crate::fee::actual_cost::ActualCost;
 use crate::fee::fee_checks::{FeeCheckError, FeeCheckReportFields, PostExecutionReport};
-use crate::fee::fee_utils::calculate_l1_gas_by_vm_usage;
+use crate::fee::fee_utils::{calculate_l1_gas_by_vm_usage, get_fee_by_gas_vector};
+use crate::fee::gas_usage::get_da_gas_cost;
 use crate::invoke_tx_args;
+use crate::state::cached_state::StateChangesCount;
 use crate::test_utils::contracts::FeatureContract;
 use crate::test_utils::initial_test_state::test_state;
-use crate::test_utils::{CairoVersion, BALANCE};
-use crate::transaction::errors::TransactionFeeError;
-use crate::transaction::objects::{GasVector, ResourcesMapping};
+use crate::test_utils::{CairoVersion, BALANCE, MAX_FEE};
+use crate::transaction::errors::{TransactionExecutionError, TransactionFeeError};
+use crate::transaction::objects::{FeeType, GasVector, Resource, ResourcesMapping};
 use crate::transaction::test_utils::{account_invoke_tx, l1_resource_bounds};
 use crate::versioned_constants::VersionedConstants;
 
 fn get_vm_resource_usage() -> ResourcesMapping {
     ResourcesMapping(HashMap::from([
-        (constants::N_STEPS_RESOURCE.to_string(), 1800),
-        (HASH_BUILTIN_NAME.to_string(), 10),
-        (RANGE_CHECK_BUILTIN_NAME.to_string(), 24),
-        (SIGNATURE_BUILTIN_NAME.to_string(), 1),
-        (BITWISE_BUILTIN_NAME.to_string(), 1),
-        (POSEIDON_BUILTIN_NAME.to_string(), 1),
+        (Resource::Steps, 1800),
+        (Resource::PedersenBuiltin, 10),
+        (Resource::RangeCheckBuiltin, 24),
+        (Resource::EcdsaBuiltin, 1),
+        (Resource::BitwiseBuiltin, 1),
+        (Resource::PoseidonBuiltin, 1),
     ]))
 }
 
@@ -40,19 +39,73 @@ fn test_calculate_l1_gas_by_vm_usage() {
 
     // Positive flow.
     // Verify calculation - in our case, n_steps is the heaviest resource.
-    let l1_gas_by_vm_usage = vm_resource_usage.0.get(constants::N_STEPS_RESOURCE).unwrap();
+    let l1_gas_by_vm_usage = vm_resource_usage.0.get(&Resource::Steps).unwrap();
     assert_eq!(
         GasVector { l1_gas: *l1_gas_by_vm_usage as u128, l1_data_gas: 0 },
         calculate_l1_gas_by_vm_usage(&versioned_constants, &vm_resource_usage).unwrap()
     );
 
     // Negative flow.
-    // Pass dict with extra key.
+    // Pass resources without a weight: one the constants do not price, and one never priced.
     let mut invalid_vm_resource_usage = ResourcesMapping(vm_resource_usage.0.clone());
-    invalid_vm_resource_usage.0.insert(String::from("bad_resource_name"), 17);
+    invalid_vm_resource_usage.0.insert(Resource::KeccakBuiltin, 17);
+    invalid_vm_resource_usage.0.insert(Resource::MemoryHoles, 17);
     let error =
         calculate_l1_gas_by_vm_usage(&versioned_constants, &invalid_vm_resource_usage).unwrap_err();
-    assert_matches!(error, TransactionFeeError::CairoResourcesNotContainedInFeeCosts);
+    assert_matches!(
+        error,
+        TransactionFeeError::CairoResourcesNotContainedInFeeCosts { missing_resources }
+        if missing_resources == vec![Resource::MemoryHoles, Resource::KeccakBuiltin]
+    );
+}
+
+/// The VM usage is charged by its heaviest resource, as weighted by the configured fee costs.
+#[rstest]
+// 1800 steps * 1/16 = 112.5 > 24 range checks * 2.
+#[case::step_dominated(0.0625, 113)]
+// 1800 steps * 1/64 = 28.125 < 24 range checks * 2.
+#[case::builtin_dominated(0.015625, 48)]
+fn test_calculate_l1_gas_by_custom_vm_resource_fee_cost(
+    #[case] n_steps_weight: f64,
+    #[case] expected_l1_gas: u128,
+) {
+    let vm_resource_fee_cost = HashMap::from([
+        (Resource::Steps, n_steps_weight),
+        (Resource::PedersenBuiltin, 0.5),
+        (Resource::RangeCheckBuiltin, 2.0),
+        (Resource::EcdsaBuiltin, 8.0),
+        (Resource::BitwiseBuiltin, 2.0),
+        (Resource::PoseidonBuiltin, 4.0),
+    ]);
+    let versioned_constants = VersionedConstants::create_for_account_testing()
+        .with_vm_resource_fee_cost(Arc::new(vm_resource_fee_cost));
+
+    assert_eq!(
+        calculate_l1_gas_by_vm_usage(&versioned_constants, &get_vm_resource_usage()).unwrap(),
+        GasVector { l1_gas: expected_l1_gas, l1_data_gas: 0 }
+    );
+}
+
+/// A step limit cannot be derived from the fee bounds of a transaction without a weight for steps.
+#[test]
+fn test_max_steps_without_steps_fee_cost() {
+    let mut block_context = BlockContext::create_for_account_testing();
+    let mut vm_resource_fee_cost = block_context.versioned_constants.vm_resource_fee_cost().clone();
+    vm_resource_fee_cost.remove(&Resource::Steps);
+    block_context.versioned_constants = block_context
+        .versioned_constants
+        .with_vm_resource_fee_cost(Arc::new(vm_resource_fee_cost));
+    let tx = account_invoke_tx(invoke_tx_args! { max_fee: Fee(MAX_FEE) });
+
+    let error =
+        EntryPointExecutionContext::new_invoke(Arc::new(block_context.to_tx_context(&tx)), true)
+            .unwrap_err();
+    assert_matches!(
+        error,
+        TransactionExecutionError::TransactionFeeError(
+            TransactionFeeError::CairoResourcesNotContainedInFeeCosts { missing_resources }
+        ) if missing_resources == vec![Resource::Steps]
+    );
 }
 
 /// Test the L1 gas limit bound, as applied to the case where both gas and data gas are consumed.
@@ -77,7 +130,7 @@ fn test_discounted_gas_overdraft(
     block_context.block_info.gas_prices.strk_l1_data_gas_price = data_gas_price.try_into().unwrap();
 
     let account = FeatureContract::AccountWithoutValidations(CairoVersion::Cairo0);
-    let mut state = test_state(&block_context.chain_info, BALANCE, &[(account, 1)]);
+    let state = test_state(&block_context.chain_info, BALANCE, &[(account, 1)]);
     let tx = account_invoke_tx(invoke_tx_args! {
         sender_address: account.get_instance_address(0),
         resource_bounds: l1_resource_bounds(gas_bound, gas_price * 10),
@@ -86,14 +139,14 @@ fn test_discounted_gas_overdraft(
     let actual_cost = ActualCost {
         actual_fee: Fee(7),
         actual_resources: ResourcesMapping(HashMap::from([
-            (constants::L1_GAS_USAGE.to_string(), l1_gas_used),
-            (constants::BLOB_GAS_USAGE.to_string(), l1_data_gas_used),
+            (Resource::L1Gas, l1_gas_used),
+            (Resource::L1DataGas, l1_data_gas_used),
         ])),
         ..Default::default()
     };
     let charge_fee = true;
     let report = PostExecutionReport::new(
-        &mut state,
+        &state,
         &block_context.to_tx_context(&tx),
         &actual_cost,
         charge_fee,
@@ -112,3 +165,26 @@ fn test_discounted_gas_overdraft(
         assert_matches!(report.error(), None);
     }
 }
+
+/// The same state diff is charged by L1 gas when posted as calldata, and by L1 data gas when
+/// posted as blobs.
+#[rstest]
+#[case::calldata(false, 5197 * 100)]
+#[case::blob(true, 352 * 3)]
+fn test_state_diff_fee_by_da_mode(#[case] use_kzg_da: bool, #[case] expected_fee: u128) {
+    let mut block_context = BlockContext::create_for_account_testing_with_kzg(use_kzg_da);
+    block_context.block_info.gas_prices.eth_l1_gas_price = 100_u128.try_into().unwrap();
+    block_context.block_info.gas_prices.eth_l1_data_gas_price = 3_u128.try_into().unwrap();
+    let state_changes_count = StateChangesCount {
+        n_storage_updates: 3,
+        n_class_hash_updates: 1,
+        n_compiled_class_hash_updates: 0,
+        n_modified_contracts: 2,
+    };
+
+    let gas_vector = get_da_gas_cost(state_changes_count, use_kzg_da);
+    assert_eq!(
+        get_fee_by_gas_vector(&block_context.block_info, gas_vector, &FeeType::Eth),
+        Fee(expected_fee)
+    );
+}
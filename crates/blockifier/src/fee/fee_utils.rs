@@ -1,15 +1,13 @@
-use std::collections::HashSet;
-
 use starknet_api::hash::StarkFelt;
 use starknet_api::transaction::Fee;
 
-use crate::abi::constants;
 use crate::blockifier::block::BlockInfo;
 use crate::context::{BlockContext, TransactionContext};
 use crate::state::state_api::StateReader;
 use crate::transaction::errors::TransactionFeeError;
 use crate::transaction::objects::{
-    FeeType, GasVector, HasRelatedFeeType, ResourcesMapping, TransactionFeeResult, TransactionInfo,
+    FeeType, GasVector, HasRelatedFeeType, Resource, ResourcesMapping, TransactionFeeResult,
+    TransactionInfo,
 };
 use crate::utils::u128_from_usize;
 use crate::versioned_constants::VersionedConstants;
@@ -21,7 +19,7 @@ pub mod test;
 pub fn extract_l1_gas_and_vm_usage(resources: &ResourcesMapping) -> (usize, ResourcesMapping) {
     let mut vm_resource_usage = resources.0.clone();
     let l1_gas_usage = vm_resource_usage
-        .remove(constants::L1_GAS_USAGE)
+        .remove(&Resource::L1Gas)
         .expect("`ResourcesMapping` does not have the key `l1_gas_usage`.");
 
     (l1_gas_usage, ResourcesMapping(vm_resource_usage))
@@ -30,7 +28,7 @@ pub fn extract_l1_gas_and_vm_usage(resources: &ResourcesMapping) -> (usize, Reso
 pub fn extract_l1_blob_gas_usage(resources: &ResourcesMapping) -> (usize, ResourcesMapping) {
     let mut vm_resource_usage = resources.0.clone();
     let l1_blob_gas_usage = vm_resource_usage
-        .remove(constants::BLOB_GAS_USAGE)
+        .remove(&Resource::L1DataGas)
         .expect("`ResourcesMapping` does not have the key `blob_gas_usage`.");
 
     (l1_blob_gas_usage, ResourcesMapping(vm_resource_usage))
@@ -44,9 +42,19 @@ pub fn calculate_l1_gas_by_vm_usage(
     vm_resource_usage: &ResourcesMapping,
 ) -> TransactionFeeResult<GasVector> {
     let vm_resource_fee_costs = versioned_constants.vm_resource_fee_cost();
-    let vm_resource_names = HashSet::<&String>::from_iter(vm_resource_usage.0.keys());
-    if !vm_resource_names.is_subset(&HashSet::from_iter(vm_resource_fee_costs.keys())) {
-        return Err(TransactionFeeError::CairoResourcesNotContainedInFeeCosts);
+    let mut missing_resources: Vec<Resource> = vm_resource_usage
+        .0
+        .keys()
+        .filter(|resource| {
+            !(resource.has_fee_weight() && vm_resource_fee_costs.contains_key(*resource))
+        })
+        .copied()
+        .collect();
+    if !missing_resources.is_empty() {
+        missing_resources.sort();
+        return Err(TransactionFeeError::CairoResourcesNotContainedInFeeCosts {
+            missing_resources,
+        });
     };
 
     // Convert Cairo usage to L1 gas usage.
@@ -72,12 +80,13 @@ pub fn calculate_tx_gas_vector(
     let (l1_blob_gas_usage, vm_resources) = extract_l1_blob_gas_usage(&vm_resources);
     let vm_usage_gas_vector = calculate_l1_gas_by_vm_usage(versioned_constants, &vm_resources)?;
 
-    Ok(GasVector {
+    GasVector {
         l1_gas: u128_from_usize(l1_gas_usage)
             .expect("Conversion from usize to u128 should not fail."),
         l1_data_gas: u128_from_usize(l1_blob_gas_usage)
             .expect("Conversion from usize to u128 should not fail."),
-    } + vm_usage_gas_vector)
+    }
+    .checked_add(vm_usage_gas_vector)
 }
 
 /// Converts the gas vector to a fee.
@@ -104,7 +113,7 @@ pub fn calculate_tx_fee(
 
 /// Returns the current fee balance and a boolean indicating whether the balance covers the fee.
 pub fn get_balance_and_if_covers_fee(
-    state: &mut dyn StateReader,
+    state: &dyn StateReader,
     tx_context: &TransactionContext,
     fee: Fee,
 ) -> TransactionFeeResult<(StarkFelt, StarkFelt, bool)> {
@@ -125,7 +134,7 @@ pub fn get_balance_and_if_covers_fee(
 /// Verifies that, given the current state, the account can cover the resource upper bounds.
 /// Error may indicate insufficient balance, or some other error.
 pub fn verify_can_pay_committed_bounds(
-    state: &mut dyn StateReader,
+    state: &dyn StateReader,
     tx_context: &TransactionContext,
 ) -> TransactionFeeResult<()> {
     let tx_info = &tx_context.tx_info;
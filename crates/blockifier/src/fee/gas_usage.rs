@@ -9,8 +9,8 @@ use crate::fee::fee_utils::calculate_tx_gas_vector;
 use crate::state::cached_state::StateChangesCount;
 use crate::transaction::account_transaction::AccountTransaction;
 use crate::transaction::objects::{
-    GasVector, HasRelatedFeeType, ResourcesMapping, TransactionExecutionResult,
-    TransactionPreValidationResult,
+    GasVector, HasRelatedFeeType, Resource, ResourcesMapping, TransactionExecutionResult,
+    TransactionFeeResult, TransactionPreValidationResult,
 };
 use crate::utils::{u128_from_usize, usize_from_u128};
 use crate::versioned_constants::VersionedConstants;
@@ -19,12 +19,23 @@ use crate::versioned_constants::VersionedConstants;
 #[path = "gas_usage_test.rs"]
 pub mod test;
 
+/// Returns the L1 gas cost of the events emitted by the given calls, including their inner calls if
+/// the versioned constants say so.
 pub fn get_tx_events_gas_cost<'a>(
     call_infos: impl Iterator<Item = &'a CallInfo>,
     versioned_constants: &VersionedConstants,
 ) -> GasVector {
+    let call_milligas_cost = |call_info: &CallInfo| {
+        get_events_milligas_cost(&call_info.execution.events, versioned_constants)
+    };
     let l1_milligas: u128 = call_infos
-        .map(|call_info| get_events_milligas_cost(&call_info.execution.events, versioned_constants))
+        .map(|call_info| {
+            if versioned_constants.l2_resource_gas_costs.charge_inner_call_events {
+                call_info.into_iter().map(call_milligas_cost).sum()
+            } else {
+                call_milligas_cost(call_info)
+            }
+        })
         .sum();
     GasVector { l1_gas: l1_milligas / 1000_u128, l1_data_gas: 0_u128 }
 }
@@ -56,13 +67,24 @@ pub fn get_messages_gas_cost<'a>(
     call_infos: impl Iterator<Item = &'a CallInfo>,
     l1_handler_payload_size: Option<usize>,
 ) -> TransactionExecutionResult<GasVector> {
-    let MessageL1CostInfo { l2_to_l1_payload_lengths, message_segment_length } =
+    let MessageL1CostInfo { l2_to_l1_payload_lengths, .. } =
         MessageL1CostInfo::calculate(call_infos, l1_handler_payload_size)?;
 
+    Ok(get_payloads_messages_gas_cost(&l2_to_l1_payload_lengths, l1_handler_payload_size)?)
+}
+
+/// Same as `get_messages_gas_cost`, given the payload lengths of the sent L2-to-L1 messages rather
+/// than the calls sending them.
+pub fn get_payloads_messages_gas_cost(
+    l2_to_l1_payload_lengths: &[usize],
+    l1_handler_payload_size: Option<usize>,
+) -> TransactionFeeResult<GasVector> {
+    let message_segment_length =
+        get_message_segment_length(l2_to_l1_payload_lengths, l1_handler_payload_size);
     let n_l2_to_l1_messages = l2_to_l1_payload_lengths.len();
     let n_l1_to_l2_messages = usize::from(l1_handler_payload_size.is_some());
 
-    let starknet_gas_usage = GasVector {
+    let starknet_state_update_gas_usage = GasVector {
         // Starknet's updateState gets the message segment as an argument.
         l1_gas: u128_from_usize(
             message_segment_length * eth_gas_constants::GAS_PER_MEMORY_WORD
@@ -76,8 +98,12 @@ pub fn get_messages_gas_cost<'a>(
         )
         .expect("Could not convert starknet gas usage from usize to u128."),
         l1_data_gas: 0,
-    } + get_consumed_message_to_l2_emissions_cost(l1_handler_payload_size)
-        + get_log_message_to_l1_emissions_cost(&l2_to_l1_payload_lengths);
+    };
+    let starknet_gas_usage = GasVector::checked_sum([
+        starknet_state_update_gas_usage,
+        get_consumed_message_to_l2_emissions_cost(l1_handler_payload_size),
+        get_log_message_to_l1_emissions_cost(l2_to_l1_payload_lengths)?,
+    ])?;
 
     let sharp_gas_usage = GasVector {
         l1_gas: u128_from_usize(
@@ -87,7 +113,29 @@ pub fn get_messages_gas_cost<'a>(
         l1_data_gas: 0,
     };
 
-    Ok(starknet_gas_usage + sharp_gas_usage)
+    starknet_gas_usage.checked_add(sharp_gas_usage)
+}
+
+/// Returns the L1 gas usage of a transaction with the given messages and state changes, with data
+/// availability published as calldata; i.e., the costs of Starknet's state update and of SHARP,
+/// including the consumption of the L1-to-L2 message of an L1 handler transaction.
+/// For callers that do not execute the transaction, e.g., to estimate its L1 costs.
+pub fn calculate_tx_gas_usage(
+    l2_to_l1_payload_lengths: &[usize],
+    n_modified_contracts: usize,
+    n_storage_changes: usize,
+    l1_handler_payload_size: Option<usize>,
+) -> TransactionFeeResult<u128> {
+    let state_changes_count = StateChangesCount {
+        n_storage_updates: n_storage_changes,
+        n_modified_contracts,
+        ..Default::default()
+    };
+    let gas_usage =
+        get_payloads_messages_gas_cost(l2_to_l1_payload_lengths, l1_handler_payload_size)?
+            .checked_add(get_da_gas_cost(state_changes_count, false))?;
+
+    Ok(gas_usage.l1_gas)
 }
 
 // Returns the gas cost for transaction calldata and transaction signature. Each felt costs a fixed
@@ -235,17 +283,16 @@ pub fn get_consumed_message_to_l2_emissions_cost(
 }
 
 /// Returns the cost of LogMessageToL1 event emissions caused by the given messages payload length.
-pub fn get_log_message_to_l1_emissions_cost(l2_to_l1_payload_lengths: &[usize]) -> GasVector {
-    l2_to_l1_payload_lengths
-        .iter()
-        .map(|length| {
-            get_event_emission_cost(
-                constants::LOG_MSG_TO_L1_N_TOPICS,
-                // We're assuming the existence of one (not indexed) payload array.
-                constants::LOG_MSG_TO_L1_ENCODED_DATA_SIZE + *length,
-            )
-        })
-        .sum()
+pub fn get_log_message_to_l1_emissions_cost(
+    l2_to_l1_payload_lengths: &[usize],
+) -> TransactionFeeResult<GasVector> {
+    GasVector::checked_sum(l2_to_l1_payload_lengths.iter().map(|length| {
+        get_event_emission_cost(
+            constants::LOG_MSG_TO_L1_N_TOPICS,
+            // We're assuming the existence of one (not indexed) payload array.
+            constants::LOG_MSG_TO_L1_ENCODED_DATA_SIZE + *length,
+        )
+    }))
 }
 
 fn get_event_emission_cost(n_topics: usize, data_length: usize) -> GasVector {
@@ -260,7 +307,10 @@ fn get_event_emission_cost(n_topics: usize, data_length: usize) -> GasVector {
     }
 }
 
-/// Return an estimated lower bound for the L1 gas on an account transaction.
+/// Return an estimated lower bound for the L1 gas on an account transaction, without executing it:
+/// the OS resources for its type and calldata, and the data availability cost of the state changes
+/// every transaction of its type makes. Used by the fee pre-validation checks; must not exceed the
+/// gas vector charged for the executed transaction.
 pub fn estimate_minimal_gas_vector(
     block_context: &BlockContext,
     tx: &AccountTransaction,
@@ -295,21 +345,28 @@ pub fn estimate_minimal_gas_vector(
         get_da_gas_cost(state_changes_by_account_transaction, block_info.use_kzg_da);
 
     let data_segment_length = get_onchain_data_segment_length(state_changes_by_account_transaction);
-    let os_steps_for_type =
-        versioned_constants.os_resources_for_tx_type(&tx.tx_type(), tx.calldata_length()).n_steps
-            + versioned_constants.os_kzg_da_resources(data_segment_length).n_steps;
+    // Same as the additional OS resources of the executed transaction, except for its class size.
+    let os_steps_for_type = versioned_constants
+        .get_additional_os_tx_resources(
+            tx.tx_type(),
+            tx.calldata_length(),
+            None,
+            data_segment_length,
+            block_info.use_kzg_da,
+        )?
+        .n_steps;
 
     let resources = ResourcesMapping(HashMap::from([
         (
-            constants::L1_GAS_USAGE.to_string(),
+            Resource::L1Gas,
             usize_from_u128(gas_cost).expect("Failed to convert L1 gas cost from u128 to usize."),
         ),
         (
-            constants::BLOB_GAS_USAGE.to_string(),
+            Resource::L1DataGas,
             usize_from_u128(blob_gas_cost)
                 .expect("Failed to convert L1 blob gas cost from u128 to usize."),
         ),
-        (constants::N_STEPS_RESOURCE.to_string(), os_steps_for_type),
+        (Resource::Steps, os_steps_for_type),
     ]));
 
     Ok(calculate_tx_gas_vector(&resources, versioned_constants)?)
@@ -1,12 +1,20 @@
 use pretty_assertions::assert_eq;
 use rstest::{fixture, rstest};
-use starknet_api::hash::StarkFelt;
+use starknet_api::core::{ClassHash, ContractAddress, PatriciaKey};
+use starknet_api::hash::{StarkFelt, StarkHash};
+use starknet_api::state::StorageKey;
 use starknet_api::transaction::{EventContent, EventData, EventKey};
+use starknet_api::{class_hash, contract_address, patricia_key, stark_felt};
 
-use crate::execution::call_info::{CallExecution, CallInfo, OrderedEvent};
+use crate::execution::call_info::{CallExecution, CallInfo, EventSummary, OrderedEvent};
 use crate::fee::eth_gas_constants;
-use crate::fee::gas_usage::{get_da_gas_cost, get_tx_events_gas_cost};
-use crate::state::cached_state::StateChangesCount;
+use crate::fee::gas_usage::{
+    calculate_tx_gas_usage, get_da_gas_cost, get_onchain_data_segment_length,
+    get_tx_events_gas_cost,
+};
+use crate::state::cached_state::{CachedState, StateChangesCount};
+use crate::state::state_api::State;
+use crate::test_utils::dict_state_reader::DictStateReader;
 use crate::transaction::objects::GasVector;
 use crate::utils::u128_from_usize;
 use crate::versioned_constants::VersionedConstants;
@@ -25,7 +33,7 @@ fn test_get_event_gas_cost(versioned_constants: &VersionedConstants) {
     let call_info_1 = &CallInfo::default();
     let call_info_2 = &CallInfo::default();
     let call_info_3 = &CallInfo::default();
-    let call_infos = call_info_1.into_iter().chain(call_info_2).chain(call_info_3);
+    let call_infos = [call_info_1, call_info_2, call_info_3].into_iter();
     assert_eq!(GasVector::default(), get_tx_events_gas_cost(call_infos, versioned_constants));
 
     let create_event = |keys_size: usize, data_size: usize| OrderedEvent {
@@ -57,15 +65,25 @@ fn test_get_event_gas_cost(versioned_constants: &VersionedConstants) {
         }],
         ..Default::default()
     };
-    let call_infos = call_info_1.into_iter().chain(call_info_2).chain(call_info_3);
+    let call_infos = [call_info_1, call_info_2, call_info_3].into_iter();
     let expected = GasVector {
         // 4 keys and 6 data words overall.
         l1_gas: (event_key_factor * data_word_cost * 4_u128 + data_word_cost * 6_u128) / 1000,
         l1_data_gas: 0_u128,
     };
+    // The event of the inner call is charged as well.
     let gas_vector = get_tx_events_gas_cost(call_infos, versioned_constants);
     assert_eq!(expected, gas_vector);
-    assert_ne!(GasVector::default(), gas_vector)
+    assert_ne!(GasVector::default(), gas_vector);
+
+    let event_summary = [call_info_1, call_info_2, call_info_3]
+        .into_iter()
+        .map(|call_info| call_info.summarize_events())
+        .sum::<EventSummary>();
+    assert_eq!(
+        event_summary,
+        EventSummary { n_events: 6, total_event_keys: 4, total_event_data_size: 6 }
+    );
 }
 
 #[rstest]
@@ -161,3 +179,112 @@ fn test_onchain_data_discount() {
     assert!(cost_ratio <= 0.9);
     assert!(cost_ratio >= 0.88);
 }
+
+#[rstest]
+#[case::no_messages_or_state_changes(&[], 0, 0, None, 0)]
+// Message segment: (3 + 2) words. Starknet: 5 * 512 (segment) + 20000 (counter increase)
+// + 375 + (2 + 1) * 375 + (2 + 2) * 256 (LogMessageToL1 event). SHARP: 5 * 612 (segment).
+#[case::l2_to_l1_message(&[2], 0, 0, None, 2560 + 20000 + 2524 + 3060)]
+// Message segment: (5 + 1) words. Starknet: 6 * 512 (segment) + 5000 (counter decrease)
+// + 375 + (3 + 1) * 375 + (3 + 1) * 256 (ConsumedMessageToL2 event). SHARP: 6 * 612 (segment).
+#[case::l1_handler(&[], 0, 0, Some(1), 3072 + 5000 + 2899 + 3672)]
+// DA segment: 2 * 1 (contract) + 2 * 1 (storage) words, at 551 each, discounted by
+// (512 - 200) for the modified contract and (512 - 272) for the fee balance.
+#[case::state_changes(&[], 1, 1, None, 4 * 551 - 312 - 240)]
+#[case::all(&[2], 1, 1, Some(1), 5632 + 20000 + 5000 + 2524 + 2899 + 6732 + 1652)]
+fn test_calculate_tx_gas_usage(
+    #[case] l2_to_l1_payload_lengths: &[usize],
+    #[case] n_modified_contracts: usize,
+    #[case] n_storage_changes: usize,
+    #[case] l1_handler_payload_size: Option<usize>,
+    #[case] expected_l1_gas: u128,
+) {
+    assert_eq!(
+        calculate_tx_gas_usage(
+            l2_to_l1_payload_lengths,
+            n_modified_contracts,
+            n_storage_changes,
+            l1_handler_payload_size
+        )
+        .unwrap(),
+        expected_l1_gas
+    );
+}
+
+/// The data gas of a transaction writing two storage cells of its sender and deploying a contract.
+#[test]
+fn test_da_gas_cost_of_writes_and_deploy() {
+    let sender_address = contract_address!("0x100");
+    let deployed_address = contract_address!("0x200");
+    let fee_token_address = contract_address!("0x1000");
+    let mut state: CachedState<DictStateReader> = CachedState::default();
+    for key in [1_u8, 2] {
+        state
+            .set_storage_at(sender_address, StorageKey(patricia_key!(key)), stark_felt!(7_u8))
+            .unwrap();
+    }
+    state.set_class_hash_at(deployed_address, class_hash!("0x300")).unwrap();
+
+    // The balance of the sender, to be updated by the fee transfer, is counted; the fee token
+    // contract is not counted as modified, and the balance of the sequencer is not counted.
+    let state_changes_count = state
+        .get_actual_state_changes()
+        .unwrap()
+        .count_for_fee_charge(Some(sender_address), fee_token_address);
+    assert_eq!(
+        state_changes_count,
+        StateChangesCount {
+            n_storage_updates: 3,
+            n_class_hash_updates: 1,
+            n_compiled_class_hash_updates: 0,
+            n_modified_contracts: 2,
+        }
+    );
+
+    // 2 * 2 (modified contracts) + 1 (class hash update) + 3 * 2 (storage updates) words.
+    assert_eq!(get_onchain_data_segment_length(state_changes_count), 11);
+    // 32 blob gas per word.
+    assert_eq!(
+        get_da_gas_cost(state_changes_count, true),
+        GasVector { l1_gas: 0, l1_data_gas: 352 }
+    );
+    // 551 gas per word, discounted by (512 - 200) per modified contract and (512 - 272) for the
+    // fee balance value.
+    assert_eq!(
+        get_da_gas_cost(state_changes_count, false),
+        GasVector { l1_gas: 11 * 551 - 2 * 312 - 240, l1_data_gas: 0 }
+    );
+}
+
+/// Nonce updates are not counted on their own, since the new nonce of a contract is packed into the
+/// word describing its other updates; they only cost data gas by modifying the contract.
+#[test]
+fn test_da_gas_cost_of_nonce_updates() {
+    let sender_address = contract_address!("0x100");
+    let other_address = contract_address!("0x200");
+    let fee_token_address = contract_address!("0x1000");
+    let mut state: CachedState<DictStateReader> = CachedState::default();
+    state.increment_nonce(sender_address).unwrap();
+    state.increment_nonce(other_address).unwrap();
+
+    let state_changes_count = state
+        .get_actual_state_changes()
+        .unwrap()
+        .count_for_fee_charge(Some(sender_address), fee_token_address);
+    assert_eq!(
+        state_changes_count,
+        StateChangesCount {
+            n_storage_updates: 1,
+            n_class_hash_updates: 0,
+            n_compiled_class_hash_updates: 0,
+            n_modified_contracts: 2,
+        }
+    );
+
+    // 2 * 2 (modified contracts) + 1 * 2 (sender balance) words, at 32 blob gas per word; the
+    // nonces themselves add no words.
+    assert_eq!(
+        get_da_gas_cost(state_changes_count, true),
+        GasVector { l1_gas: 0, l1_data_gas: 6 * 32 }
+    );
+}
@@ -1,3 +1,8 @@
 pub mod cached_state;
+pub mod diff;
 pub mod errors;
+#[cfg(feature = "rpc-state-reader")]
+pub mod rpc_state_reader;
 pub mod state_api;
+pub mod strict_state_reader;
+pub mod versioned_state;
@@ -1,10 +1,12 @@
-use std::cell::RefCell;
-use std::collections::{HashMap, HashSet};
+use std::cell::{Cell, RefCell};
+use std::collections::{BTreeMap, HashMap, HashSet};
+use std::hash::Hash;
 use std::sync::{Arc, Mutex, MutexGuard};
 
-use cached::{Cached, SizedCache};
+use cached::Cached;
 use derive_more::IntoIterator;
 use indexmap::IndexMap;
+use serde::{Deserialize, Serialize};
 use starknet_api::core::{ClassHash, CompiledClassHash, ContractAddress, Nonce};
 use starknet_api::hash::StarkFelt;
 use starknet_api::state::StorageKey;
@@ -12,20 +14,26 @@ use starknet_api::state::StorageKey;
 use crate::abi::abi_utils::get_fee_token_var_address;
 use crate::execution::contract_class::ContractClass;
 use crate::state::errors::StateError;
+#[cfg(feature = "concurrency")]
+use crate::state::state_api::SyncStateReader;
 use crate::state::state_api::{State, StateReader, StateResult};
-use crate::utils::subtract_mappings;
+use crate::utils::{subtract_mappings, subtract_mappings_owned};
 
 #[cfg(test)]
 #[path = "cached_state_test.rs"]
 mod test;
 
 pub type ContractClassMapping = HashMap<ClassHash, ContractClass>;
+type StorageDiff = IndexMap<ContractAddress, IndexMap<StorageKey, StarkFelt>>;
 
 /// Caches read and write requests.
 ///
 /// Writer functionality is builtin, whereas Reader functionality is injected through
 /// initialization.
-#[derive(Debug)]
+///
+/// Cloning a state copies its caches (contract classes are shared, not deep-copied), and the
+/// clone's changes never affect the original; see [`CachedState::fork`] for non-`Clone` readers.
+#[derive(Clone, Debug)]
 pub struct CachedState<S: StateReader> {
     pub state: S,
     // Invariant: read/write access is managed by CachedState.
@@ -36,6 +44,13 @@ pub struct CachedState<S: StateReader> {
     global_class_hash_to_class: GlobalContractCache,
     /// A map from class hash to the set of PC values that were visited in the class.
     pub visited_pcs: HashMap<ClassHash, HashSet<usize>>,
+    stats: Cell<CacheStats>,
+    // The reads from the underlying state, in order; recorded only if enabled.
+    witness: RefCell<Option<Vec<StateAccess>>>,
+    journal: WriteJournal,
+    // Whether nonce reads of contracts the underlying state reports as not deployed fail, rather
+    // than read as zero.
+    strict_nonce_reads: bool,
 }
 
 impl<S: StateReader> CachedState<S> {
@@ -46,15 +61,31 @@ impl<S: StateReader> CachedState<S> {
             class_hash_to_class: RefCell::new(HashMap::default()),
             global_class_hash_to_class,
             visited_pcs: HashMap::default(),
+            stats: Cell::default(),
+            witness: RefCell::default(),
+            journal: WriteJournal::default(),
+            strict_nonce_reads: false,
         }
     }
 
+    /// Sets whether nonce reads fail with [`StateError::ContractNotDeployed`] when the underlying
+    /// state reports the contract as not deployed; by default, such nonces are read as zero, as
+    /// from readers that do not distinguish undeployed contracts.
+    pub fn with_strict_nonce_reads(self, strict_nonce_reads: bool) -> Self {
+        Self { strict_nonce_reads, ..self }
+    }
+
     /// Creates a transactional instance from the given cached state.
     /// It allows performing buffered modifying actions on the given state, which
     /// will either all happen (will be committed) or none of them (will be discarded).
+    /// Committing applies the changes to the caches of the given state, not to its underlying
+    /// reader. The given state is borrowed, and thus unusable, while the transactional one is
+    /// alive; transactional states may be nested.
     pub fn create_transactional(state: &mut CachedState<S>) -> TransactionalState<'_, S> {
         let global_class_hash_to_class = state.global_class_hash_to_class.clone();
+        let strict_nonce_reads = state.strict_nonce_reads;
         CachedState::new(MutRefState::new(state), global_class_hash_to_class)
+            .with_strict_nonce_reads(strict_nonce_reads)
     }
 
     /// Returns the storage changes done through this state.
@@ -74,6 +105,234 @@ impl<S: StateReader> CachedState<S> {
         })
     }
 
+    /// Returns the number of state changes this state would contribute when committed, without
+    /// materializing them; equals the count of the keys of [`Self::get_actual_state_changes`].
+    pub fn pending_write_counts(&mut self) -> StateResult<StateChangesCount> {
+        self.update_initial_values_of_write_only_access()?;
+        Ok(self.cache.borrow().count_updates())
+    }
+
+    /// Returns the contracts whose storage, nonce or class hash this state would change when
+    /// committed; writes of a cell's current value are not changes.
+    pub fn modified_contracts(&mut self) -> StateResult<HashSet<ContractAddress>> {
+        self.update_initial_values_of_write_only_access()?;
+        Ok(self.cache.borrow().get_modified_contracts())
+    }
+
+    /// Returns the contracts whose storage, nonce or class hash this state read or wrote (an
+    /// access list); a superset of [`Self::modified_contracts`].
+    pub fn accessed_contracts(&self) -> HashSet<ContractAddress> {
+        self.cache.borrow().get_accessed_contracts()
+    }
+
+    /// Returns statistics of the writes done through this state (and committed to it), with the
+    /// given number of most written cells; e.g., per transaction, of its transactional state.
+    /// Writes are counted even if rolled back to a savepoint.
+    pub fn write_metrics(&mut self, n_most_written_keys: usize) -> StateResult<WriteMetrics> {
+        self.update_initial_values_of_write_only_access()?;
+        Ok(self.cache.borrow().get_write_metrics(n_most_written_keys))
+    }
+
+    /// Returns the values this state read from its underlying state, as observed when first read;
+    /// together with the state changes, serves to validate transactions executed concurrently.
+    /// Note: cells that were only written are included once their initial values are fetched
+    /// (e.g., by [`Self::get_actual_state_changes`]); classes declared through this state are not
+    /// reads.
+    pub fn get_initial_reads(&self) -> StateReads {
+        let cache = self.cache.borrow();
+
+        StateReads {
+            storage: cache.storage_initial_values.clone(),
+            nonces: cache.nonce_initial_values.clone(),
+            class_hashes: cache.class_hash_initial_values.clone(),
+            compiled_class_hashes: cache.compiled_class_hash_initial_values.clone(),
+            classes: self
+                .class_hash_to_class
+                .borrow()
+                .keys()
+                .filter(|class_hash| !cache.declared_class_hashes.contains(class_hash))
+                .copied()
+                .collect(),
+            undeclared_classes: cache.undeclared_class_hashes.clone(),
+        }
+    }
+
+    /// Returns the number of reads served by the caches of this state, and of those fetched from
+    /// the underlying state, since its creation or the last reset.
+    pub fn stats(&self) -> CacheStats {
+        self.stats.get()
+    }
+
+    pub fn reset_stats(&mut self) {
+        self.stats.take();
+    }
+
+    /// Starts recording the reads from the underlying state (a witness of the execution); e.g.,
+    /// to prove the values read. Contract classes are recorded even if served by the global
+    /// contract cache.
+    pub fn enable_witness(&mut self) {
+        self.witness.get_mut().get_or_insert_with(Vec::new);
+    }
+
+    /// Returns the reads from the underlying state since the witness was enabled, in order, if
+    /// enabled. Cells are read at most once: the recorded values precede any write to them.
+    pub fn witness(&self) -> Option<Vec<StateAccess>> {
+        self.witness.borrow().clone()
+    }
+
+    fn record_access(&self, access: StateAccess) {
+        if let Some(witness) = self.witness.borrow_mut().as_mut() {
+            witness.push(access);
+        }
+    }
+
+    /// Creates a savepoint of the writes of this state; see [`Self::rollback_to`].
+    pub fn savepoint(&mut self) -> SavepointId {
+        let savepoint = SavepointId(self.journal.next_savepoint_id);
+        self.journal.next_savepoint_id += 1;
+        self.journal.savepoints.push((savepoint, self.journal.entries.len()));
+        savepoint
+    }
+
+    /// Restores the writes of this state (storage, nonces, class hashes, compiled class hashes and
+    /// declared classes, along with their write counts) to their values at the given savepoint.
+    /// The savepoint stays valid; savepoints created after it are invalidated.
+    pub fn rollback_to(&mut self, savepoint: SavepointId) -> StateResult<()> {
+        let index = self.journal.index_of(savepoint)?;
+        let (_, journal_length) = self.journal.savepoints[index];
+        self.journal.savepoints.truncate(index + 1);
+
+        let cache = self.cache.get_mut();
+        let class_hash_to_class = self.class_hash_to_class.get_mut();
+        for entry in self.journal.entries.drain(journal_length..).rev() {
+            let (key, previous_value, previous_n_writes) = match entry {
+                JournalEntry::Write { key, previous_value, previous_n_writes } => {
+                    (key, previous_value, previous_n_writes)
+                }
+                JournalEntry::Declare { class_hash, previous_class, was_declared } => {
+                    restore_write(class_hash_to_class, class_hash, previous_class);
+                    if !was_declared {
+                        cache.declared_class_hashes.remove(&class_hash);
+                    }
+                    continue;
+                }
+            };
+
+            restore_write(
+                &mut cache.write_counts,
+                key,
+                (previous_n_writes > 0).then_some(previous_n_writes),
+            );
+            match key {
+                StateKey::Storage(storage_entry) => {
+                    restore_write(&mut cache.storage_writes, storage_entry, previous_value)
+                }
+                StateKey::Nonce(contract_address) => restore_write(
+                    &mut cache.nonce_writes,
+                    contract_address,
+                    previous_value.map(Nonce),
+                ),
+                StateKey::ClassHash(contract_address) => restore_write(
+                    &mut cache.class_hash_writes,
+                    contract_address,
+                    previous_value.map(ClassHash),
+                ),
+                StateKey::CompiledClassHash(class_hash) => restore_write(
+                    &mut cache.compiled_class_hash_writes,
+                    class_hash,
+                    previous_value.map(CompiledClassHash),
+                ),
+                StateKey::ContractClass(_) => unreachable!("Contract classes are not journaled."),
+            }
+        }
+
+        Ok(())
+    }
+
+    /// Discards the given savepoint, and the savepoints created after it, keeping the writes.
+    pub fn release_savepoint(&mut self, savepoint: SavepointId) -> StateResult<()> {
+        let index = self.journal.index_of(savepoint)?;
+        self.journal.savepoints.truncate(index);
+        if self.journal.savepoints.is_empty() {
+            self.journal.entries.clear();
+        }
+
+        Ok(())
+    }
+
+    /// Journals the current write of the given cell before it is overwritten, if there is a live
+    /// savepoint to roll back to.
+    fn journal_write(&mut self, key: StateKey) {
+        if self.journal.savepoints.is_empty() {
+            return;
+        }
+
+        let cache = self.cache.get_mut();
+        let previous_value = match key {
+            StateKey::Storage(storage_entry) => cache.storage_writes.get(&storage_entry).copied(),
+            StateKey::Nonce(contract_address) => {
+                cache.nonce_writes.get(&contract_address).map(|nonce| nonce.0)
+            }
+            StateKey::ClassHash(contract_address) => {
+                cache.class_hash_writes.get(&contract_address).map(|class_hash| class_hash.0)
+            }
+            StateKey::CompiledClassHash(class_hash) => cache
+                .compiled_class_hash_writes
+                .get(&class_hash)
+                .map(|compiled_class_hash| compiled_class_hash.0),
+            StateKey::ContractClass(_) => unreachable!("Contract classes are not journaled."),
+        };
+        let previous_n_writes = cache.write_counts.get(&key).copied().unwrap_or_default();
+        self.journal.entries.push(JournalEntry::Write { key, previous_value, previous_n_writes });
+    }
+
+    /// Journals the current local entry of the given class before it is declared, if there is a
+    /// live savepoint to roll back to.
+    fn journal_declare(&mut self, class_hash: ClassHash) {
+        if self.journal.savepoints.is_empty() {
+            return;
+        }
+
+        let previous_class = self.class_hash_to_class.get_mut().get(&class_hash).cloned();
+        let was_declared = self.cache.get_mut().declared_class_hashes.contains(&class_hash);
+        self.journal.entries.push(JournalEntry::Declare {
+            class_hash,
+            previous_class,
+            was_declared,
+        });
+    }
+
+    fn record_read(&self, counts: impl FnOnce(&mut CacheStats) -> &mut CacheCounts, hit: bool) {
+        let mut stats = self.stats.get();
+        let counts = counts(&mut stats);
+        if hit {
+            counts.hits += 1;
+        } else {
+            counts.misses += 1;
+        }
+        self.stats.set(stats);
+    }
+
+    /// Reads the given cells from the underlying state, unless already cached, so that subsequent
+    /// reads of them are served by the cache. Storage values are fetched in a single batch.
+    pub fn prefetch(
+        &mut self,
+        storage_keys: &[StorageEntry],
+        contracts: &[ContractAddress],
+        classes: &[ClassHash],
+    ) -> StateResult<()> {
+        self.get_storage_at_batch(storage_keys)?;
+        for &contract_address in contracts {
+            self.get_nonce_at(contract_address)?;
+            self.get_class_hash_at(contract_address)?;
+        }
+        for &class_hash in classes {
+            self.get_compiled_contract_class(class_hash)?;
+        }
+
+        Ok(())
+    }
+
     /// Drains contract-class cache collected during execution and updates the global cache.
     pub fn move_classes_to_global_cache(&mut self) {
         let contract_class_updates: Vec<_> = self.class_hash_to_class.get_mut().drain().collect();
@@ -89,13 +348,26 @@ impl<S: StateReader> CachedState<S> {
         self.global_class_hash_to_class.lock()
     }
 
+    /// Applies the writes of the given cache, e.g., that of a committed transactional state.
+    /// Initial values are not taken from it: a wrapping state reads through this state, which
+    /// caches the values it reads as its own initial values.
     pub fn update_cache(&mut self, cache_updates: StateCache) {
+        for key in cache_updates.write_keys() {
+            self.journal_write(key);
+        }
+        for &class_hash in &cache_updates.declared_class_hashes {
+            self.journal_declare(class_hash);
+        }
         let mut cache = self.cache.borrow_mut();
 
         cache.nonce_writes.extend(cache_updates.nonce_writes);
         cache.class_hash_writes.extend(cache_updates.class_hash_writes);
         cache.storage_writes.extend(cache_updates.storage_writes);
         cache.compiled_class_hash_writes.extend(cache_updates.compiled_class_hash_writes);
+        cache.declared_class_hashes.extend(cache_updates.declared_class_hashes);
+        for (key, n_writes) in cache_updates.write_counts {
+            *cache.write_counts.entry(key).or_default() += n_writes;
+        }
     }
 
     pub fn update_contract_class_caches(
@@ -107,12 +379,54 @@ impl<S: StateReader> CachedState<S> {
         self.global_class_hash_to_class = global_contract_cache;
     }
 
+    /// Returns the classes declared through this state; classes that were only read are not
+    /// included.
+    pub fn declared_classes(&self) -> StateResult<ContractClassMapping> {
+        self.cache
+            .borrow()
+            .declared_class_hashes
+            .iter()
+            .map(|&class_hash| Ok((class_hash, self.get_declared_class(class_hash)?)))
+            .collect()
+    }
+
+    // Declared classes are in the local class cache, unless moved to the global one (e.g., once
+    // the block is finalized).
+    fn get_declared_class(&self, class_hash: ClassHash) -> StateResult<ContractClass> {
+        let local_contract_class = self.class_hash_to_class.borrow().get(&class_hash).cloned();
+        local_contract_class
+            .or_else(|| self.global_class_hash_to_class().cache_get(&class_hash).cloned())
+            .ok_or(StateError::UndeclaredClassHash(class_hash))
+    }
+
+    /// Returns the compiled bytecode length of each class declared through this state; classes that
+    /// were only read are not included.
+    /// Not used for fee charging: the OS resources of a declare are computed from its
+    /// [`crate::execution::contract_class::ClassInfo`], which also counts the length of the Sierra
+    /// program, unknown to the state.
+    pub fn declared_class_sizes(&self) -> StateResult<HashMap<ClassHash, usize>> {
+        Ok(self
+            .declared_classes()?
+            .into_iter()
+            .map(|(class_hash, contract_class)| (class_hash, contract_class.bytecode_length()))
+            .collect())
+    }
+
     pub fn update_visited_pcs_cache(&mut self, visited_pcs: &HashMap<ClassHash, HashSet<usize>>) {
         for (class_hash, class_visited_pcs) in visited_pcs {
             self.add_visited_pcs(*class_hash, class_visited_pcs);
         }
     }
 
+    fn read_nonce_from_state(&self, contract_address: ContractAddress) -> StateResult<Nonce> {
+        match self.state.get_nonce_at(contract_address) {
+            Err(StateError::ContractNotDeployed(_)) if !self.strict_nonce_reads => {
+                Ok(Nonce::default())
+            }
+            nonce => nonce,
+        }
+    }
+
     /// Updates cache with initial cell values for write-only access.
     /// If written values match the original, the cell is unchanged and not counted as a
     /// storage-change for fee calculation.
@@ -126,28 +440,28 @@ impl<S: StateReader> CachedState<S> {
         for contract_storage_key in cache.storage_writes.keys() {
             if !cache.storage_initial_values.contains_key(contract_storage_key) {
                 // First access to this cell was write; cache initial value.
-                cache.storage_initial_values.insert(
-                    *contract_storage_key,
-                    self.state.get_storage_at(contract_storage_key.0, contract_storage_key.1)?,
-                );
+                let value =
+                    self.state.get_storage_at(contract_storage_key.0, contract_storage_key.1)?;
+                self.record_access(StateAccess::Storage(*contract_storage_key, value));
+                cache.storage_initial_values.insert(*contract_storage_key, value);
             }
         }
 
         for contract_address in cache.class_hash_writes.keys() {
             if !cache.class_hash_initial_values.contains_key(contract_address) {
                 // First access to this cell was write; cache initial value.
-                cache
-                    .class_hash_initial_values
-                    .insert(*contract_address, self.state.get_class_hash_at(*contract_address)?);
+                let class_hash = self.state.get_class_hash_at(*contract_address)?;
+                self.record_access(StateAccess::ClassHash(*contract_address, class_hash));
+                cache.class_hash_initial_values.insert(*contract_address, class_hash);
             }
         }
 
         for contract_address in cache.nonce_writes.keys() {
             if !cache.nonce_initial_values.contains_key(contract_address) {
                 // First access to this cell was write; cache initial value.
-                cache
-                    .nonce_initial_values
-                    .insert(*contract_address, self.state.get_nonce_at(*contract_address)?);
+                let nonce = self.read_nonce_from_state(*contract_address)?;
+                self.record_access(StateAccess::Nonce(*contract_address, nonce));
+                cache.nonce_initial_values.insert(*contract_address, nonce);
             }
         }
 
@@ -155,24 +469,128 @@ impl<S: StateReader> CachedState<S> {
     }
 
     pub fn to_state_diff(&mut self) -> CommitmentStateDiff {
-        type StorageDiff = IndexMap<ContractAddress, IndexMap<StorageKey, StarkFelt>>;
+        self.update_initial_values_for_state_diff();
+
+        let state_cache = self.cache.borrow();
+        StateDiffParts {
+            class_hash_updates: state_cache.get_class_hash_updates(),
+            replaced_classes: state_cache.get_replaced_classes(),
+            storage_updates: state_cache.get_storage_updates(),
+            compiled_class_hash_updates: state_cache.compiled_class_hash_writes.clone(),
+            nonce_updates: state_cache.get_nonce_updates(),
+        }
+        .into()
+    }
+
+    /// Same as [`Self::to_state_diff`], but consumes the state, moving its writes into the diff
+    /// rather than copying them; e.g., once the block is finalized.
+    pub fn into_state_diff(mut self) -> CommitmentStateDiff {
+        self.update_initial_values_for_state_diff();
+
+        let state_cache = self.cache.into_inner();
+        let replaced_classes = state_cache.get_replaced_classes();
+        let StateCache {
+            nonce_initial_values,
+            class_hash_initial_values,
+            storage_initial_values,
+            nonce_writes,
+            class_hash_writes,
+            storage_writes,
+            compiled_class_hash_writes,
+            ..
+        } = state_cache;
+        StateDiffParts {
+            class_hash_updates: subtract_mappings_owned(
+                class_hash_writes,
+                &class_hash_initial_values,
+            ),
+            replaced_classes,
+            storage_updates: subtract_mappings_owned(storage_writes, &storage_initial_values),
+            compiled_class_hash_updates: compiled_class_hash_writes,
+            nonce_updates: subtract_mappings_owned(nonce_writes, &nonce_initial_values),
+        }
+        .into()
+    }
 
+    fn update_initial_values_for_state_diff(&mut self) {
         // TODO(Gilad): Consider returning an error here, would require changing the API though.
         self.update_initial_values_of_write_only_access()
             .unwrap_or_else(|_| panic!("Cannot convert stateDiff to CommitmentStateDiff."));
+    }
 
-        let state_cache = self.cache.borrow();
-        let class_hash_updates = state_cache.get_class_hash_updates();
-        let storage_diffs = state_cache.get_storage_updates();
-        let nonces = state_cache.get_nonce_updates();
-        let declared_classes = state_cache.compiled_class_hash_writes.clone();
-
-        CommitmentStateDiff {
-            address_to_class_hash: IndexMap::from_iter(class_hash_updates),
-            storage_updates: StorageDiff::from(StorageView(storage_diffs)),
-            class_hash_to_compiled_class_hash: IndexMap::from_iter(declared_classes),
-            address_to_nonce: IndexMap::from_iter(nonces),
+    /// Applies the changes of this state (e.g., of a built block) to the given state, in canonical
+    /// order: declared classes, deployed (and replaced) classes, storage, and then nonces, each
+    /// sorted by key. Returns the applied state diff.
+    /// Fails before writing anything if a declared class is no longer cached (e.g., evicted from
+    /// the global cache). Otherwise, stops at the first write that fails, reporting its key; the
+    /// writes preceding it stay applied, so the caller must discard the partially written state.
+    pub fn commit_block(mut self, writer: &mut impl State) -> StateResult<CommitmentStateDiff> {
+        let failed_at =
+            |key| move |error| StateError::CommitFailure { key, error: Box::new(error) };
+        let mut declared_class_hashes: Vec<_> =
+            self.cache.get_mut().declared_class_hashes.iter().copied().collect();
+        declared_class_hashes.sort();
+        let declared_classes = declared_class_hashes
+            .into_iter()
+            .map(|class_hash| {
+                let contract_class = self
+                    .get_declared_class(class_hash)
+                    .map_err(failed_at(StateKey::ContractClass(class_hash)))?;
+                Ok((class_hash, contract_class))
+            })
+            .collect::<StateResult<Vec<_>>>()?;
+        let mut state_diff = self.into_state_diff();
+        state_diff.sort();
+
+        for (class_hash, contract_class) in declared_classes {
+            writer
+                .set_contract_class(class_hash, contract_class)
+                .map_err(failed_at(StateKey::ContractClass(class_hash)))?;
+        }
+        for (&class_hash, &compiled_class_hash) in &state_diff.class_hash_to_compiled_class_hash {
+            writer
+                .set_compiled_class_hash(class_hash, compiled_class_hash)
+                .map_err(failed_at(StateKey::CompiledClassHash(class_hash)))?;
         }
+        for (&contract_address, &class_hash) in &state_diff.address_to_class_hash {
+            // Deployments are write-once; replaced classes overwrite an existing deployment.
+            if state_diff.replaced_classes.contains_key(&contract_address) {
+                writer.replace_class_hash_at(contract_address, class_hash)
+            } else {
+                writer.set_class_hash_at(contract_address, class_hash)
+            }
+            .map_err(failed_at(StateKey::ClassHash(contract_address)))?;
+        }
+        for (&contract_address, storage) in &state_diff.storage_updates {
+            for (&key, &value) in storage {
+                writer
+                    .set_storage_at(contract_address, key, value)
+                    .map_err(failed_at(StateKey::Storage((contract_address, key))))?;
+            }
+        }
+        for (&contract_address, &nonce) in &state_diff.address_to_nonce {
+            writer
+                .set_nonce_at(contract_address, nonce)
+                .map_err(failed_at(StateKey::Nonce(contract_address)))?;
+        }
+
+        Ok(state_diff)
+    }
+
+    /// Returns the state diff as (pretty) JSON, sorted by key, with felts as 0x-prefixed hex.
+    /// Useful for comparing the pending diffs of different nodes.
+    pub fn dump_state_diff_json(&mut self) -> serde_json::Result<String> {
+        let mut state_diff = self.to_state_diff();
+        state_diff.sort();
+        serde_json::to_string_pretty(&state_diff)
+    }
+}
+
+impl<S: StateReader> CachedState<Arc<S>> {
+    /// Forks this state, sharing its reader; e.g., to simulate a transaction on top of it and
+    /// discard the result. The fork's changes never affect this state.
+    pub fn fork(&self) -> Self {
+        self.clone()
     }
 }
 
@@ -194,8 +612,11 @@ impl<S: StateReader> StateReader for CachedState<S> {
     ) -> StateResult<StarkFelt> {
         let mut cache = self.cache.borrow_mut();
 
-        if cache.get_storage_at(contract_address, key).is_none() {
+        let hit = cache.get_storage_at(contract_address, key).is_some();
+        self.record_read(|stats| &mut stats.storage, hit);
+        if !hit {
             let storage_value = self.state.get_storage_at(contract_address, key)?;
+            self.record_access(StateAccess::Storage((contract_address, key), storage_value));
             cache.set_storage_initial_value(contract_address, key, storage_value);
         }
 
@@ -205,11 +626,46 @@ impl<S: StateReader> StateReader for CachedState<S> {
         Ok(*value)
     }
 
+    fn get_storage_at_batch(&self, storage_keys: &[StorageEntry]) -> StateResult<Vec<StarkFelt>> {
+        let mut cache = self.cache.borrow_mut();
+
+        let mut missing_keys = Vec::new();
+        for &(contract_address, key) in storage_keys {
+            let hit = cache.get_storage_at(contract_address, key).is_some()
+                || missing_keys.contains(&(contract_address, key));
+            self.record_read(|stats| &mut stats.storage, hit);
+            if !hit {
+                missing_keys.push((contract_address, key));
+            }
+        }
+
+        if !missing_keys.is_empty() {
+            let missing_values = self.state.get_storage_at_batch(&missing_keys)?;
+            for ((contract_address, key), value) in missing_keys.into_iter().zip(missing_values) {
+                self.record_access(StateAccess::Storage((contract_address, key), value));
+                cache.set_storage_initial_value(contract_address, key, value);
+            }
+        }
+
+        let values = storage_keys
+            .iter()
+            .map(|&(contract_address, key)| {
+                *cache.get_storage_at(contract_address, key).unwrap_or_else(|| {
+                    panic!("Cannot retrieve '{contract_address:?}' and '{key:?}' from the cache.")
+                })
+            })
+            .collect();
+        Ok(values)
+    }
+
     fn get_nonce_at(&self, contract_address: ContractAddress) -> StateResult<Nonce> {
         let mut cache = self.cache.borrow_mut();
 
-        if cache.get_nonce_at(contract_address).is_none() {
-            let nonce = self.state.get_nonce_at(contract_address)?;
+        let hit = cache.get_nonce_at(contract_address).is_some();
+        self.record_read(|stats| &mut stats.nonce, hit);
+        if !hit {
+            let nonce = self.read_nonce_from_state(contract_address)?;
+            self.record_access(StateAccess::Nonce(contract_address, nonce));
             cache.set_nonce_initial_value(contract_address, nonce);
         }
 
@@ -223,8 +679,11 @@ impl<S: StateReader> StateReader for CachedState<S> {
     fn get_class_hash_at(&self, contract_address: ContractAddress) -> StateResult<ClassHash> {
         let mut cache = self.cache.borrow_mut();
 
-        if cache.get_class_hash_at(contract_address).is_none() {
+        let hit = cache.get_class_hash_at(contract_address).is_some();
+        self.record_read(|stats| &mut stats.class_hash, hit);
+        if !hit {
             let class_hash = self.state.get_class_hash_at(contract_address)?;
+            self.record_access(StateAccess::ClassHash(contract_address, class_hash));
             cache.set_class_hash_initial_value(contract_address, class_hash);
         }
 
@@ -241,6 +700,7 @@ impl<S: StateReader> StateReader for CachedState<S> {
             class_hash_to_class.entry(class_hash)
         {
             let contract_class = self.global_class_hash_to_class().cache_get(&class_hash).cloned();
+            self.record_read(|stats| &mut stats.contract_class, contract_class.is_some());
 
             match contract_class {
                 Some(contract_class_from_global_cache) => {
@@ -248,10 +708,20 @@ impl<S: StateReader> StateReader for CachedState<S> {
                 }
                 None => {
                     let contract_class_from_db =
-                        self.state.get_compiled_contract_class(class_hash)?;
+                        match self.state.get_compiled_contract_class(class_hash) {
+                            Err(StateError::UndeclaredClassHash(_)) => {
+                                // A failed read is a read as well; e.g., of a class declared later.
+                                self.cache.borrow_mut().undeclared_class_hashes.insert(class_hash);
+                                return Err(StateError::UndeclaredClassHash(class_hash));
+                            }
+                            contract_class => contract_class?,
+                        };
                     vacant_entry.insert(contract_class_from_db);
                 }
             }
+            self.record_access(StateAccess::ContractClass(class_hash));
+        } else {
+            self.record_read(|stats| &mut stats.contract_class, true);
         }
 
         let contract_class = class_hash_to_class
@@ -265,8 +735,11 @@ impl<S: StateReader> StateReader for CachedState<S> {
     fn get_compiled_class_hash(&self, class_hash: ClassHash) -> StateResult<CompiledClassHash> {
         let mut cache = self.cache.borrow_mut();
 
-        if cache.get_compiled_class_hash(class_hash).is_none() {
+        let hit = cache.get_compiled_class_hash(class_hash).is_some();
+        self.record_read(|stats| &mut stats.compiled_class_hash, hit);
+        if !hit {
             let compiled_class_hash = self.state.get_compiled_class_hash(class_hash)?;
+            self.record_access(StateAccess::CompiledClassHash(class_hash, compiled_class_hash));
             cache.set_compiled_class_hash_initial_value(class_hash, compiled_class_hash);
         }
 
@@ -284,6 +757,7 @@ impl<S: StateReader> State for CachedState<S> {
         key: StorageKey,
         value: StarkFelt,
     ) -> StateResult<()> {
+        self.journal_write(StateKey::Storage((contract_address, key)));
         self.cache.get_mut().set_storage_value(contract_address, key, value);
 
         Ok(())
@@ -291,17 +765,19 @@ impl<S: StateReader> State for CachedState<S> {
 
     fn increment_nonce(&mut self, contract_address: ContractAddress) -> StateResult<()> {
         let current_nonce = self.get_nonce_at(contract_address)?;
-        // TODO(Ori, 1/2/2024): Write an indicative expect message explaining why the conversion
-        // works.
-        let current_nonce_as_u64: u64 =
-            usize::try_from(current_nonce.0)?.try_into().expect("Failed to convert usize to u64.");
-        let next_nonce_val = 1_u64 + current_nonce_as_u64;
-        let next_nonce = Nonce(StarkFelt::from(next_nonce_val));
+        let next_nonce = current_nonce.try_increment()?;
+        self.journal_write(StateKey::Nonce(contract_address));
         self.cache.get_mut().set_nonce_value(contract_address, next_nonce);
 
         Ok(())
     }
 
+    fn set_nonce_at(&mut self, contract_address: ContractAddress, nonce: Nonce) -> StateResult<()> {
+        self.journal_write(StateKey::Nonce(contract_address));
+        self.cache.get_mut().set_nonce_value(contract_address, nonce);
+        Ok(())
+    }
+
     fn set_class_hash_at(
         &mut self,
         contract_address: ContractAddress,
@@ -311,6 +787,21 @@ impl<S: StateReader> State for CachedState<S> {
             return Err(StateError::OutOfRangeContractAddress);
         }
 
+        self.journal_write(StateKey::ClassHash(contract_address));
+        self.cache.get_mut().set_class_hash_write(contract_address, class_hash);
+        Ok(())
+    }
+
+    fn replace_class_hash_at(
+        &mut self,
+        contract_address: ContractAddress,
+        class_hash: ClassHash,
+    ) -> StateResult<()> {
+        if self.get_class_hash_at(contract_address)? == ClassHash::default() {
+            return Err(StateError::UndeployedContractClassReplacement(contract_address));
+        }
+
+        self.journal_write(StateKey::ClassHash(contract_address));
         self.cache.get_mut().set_class_hash_write(contract_address, class_hash);
         Ok(())
     }
@@ -320,7 +811,9 @@ impl<S: StateReader> State for CachedState<S> {
         class_hash: ClassHash,
         contract_class: ContractClass,
     ) -> StateResult<()> {
+        self.journal_declare(class_hash);
         self.class_hash_to_class.get_mut().insert(class_hash, contract_class);
+        self.cache.get_mut().declared_class_hashes.insert(class_hash);
         Ok(())
     }
 
@@ -329,6 +822,7 @@ impl<S: StateReader> State for CachedState<S> {
         class_hash: ClassHash,
         compiled_class_hash: CompiledClassHash,
     ) -> StateResult<()> {
+        self.journal_write(StateKey::CompiledClassHash(class_hash));
         self.cache.get_mut().set_compiled_class_hash_write(class_hash, compiled_class_hash);
         Ok(())
     }
@@ -349,6 +843,10 @@ impl Default for CachedState<crate::test_utils::dict_state_reader::DictStateRead
                 GLOBAL_CONTRACT_CACHE_SIZE_FOR_TEST,
             ),
             visited_pcs: Default::default(),
+            stats: Default::default(),
+            witness: Default::default(),
+            journal: Default::default(),
+            strict_nonce_reads: false,
         }
     }
 }
@@ -358,17 +856,27 @@ pub type StorageEntry = (ContractAddress, StorageKey);
 #[derive(Debug, Default, IntoIterator)]
 pub struct StorageView(pub HashMap<StorageEntry, StarkFelt>);
 
-/// Converts a `CachedState`'s storage mapping into a `StateDiff`'s storage mapping.
+/// Converts a `CachedState`'s storage mapping into a `StateDiff`'s storage mapping, ordered by
+/// address and then by key; hence deterministic, as [`CommitmentStateDiff::sort`] would order it.
 impl From<StorageView> for IndexMap<ContractAddress, IndexMap<StorageKey, StarkFelt>> {
     fn from(storage_view: StorageView) -> Self {
+        let mut storage_entries: Vec<_> = storage_view.into_iter().collect();
+        storage_entries.sort_unstable_by_key(|(storage_entry, _)| *storage_entry);
+
+        // Group the (sorted) entries by address, creating each inner map at its final size.
         let mut storage_updates = Self::new();
-        for ((address, key), value) in storage_view.into_iter() {
-            storage_updates
-                .entry(address)
-                .and_modify(|map| {
-                    map.insert(key, value);
-                })
-                .or_insert_with(|| IndexMap::from([(key, value)]));
+        let mut remaining_entries = storage_entries.as_slice();
+        while let Some(((address, _), _)) = remaining_entries.first() {
+            let n_address_entries = remaining_entries
+                .iter()
+                .take_while(|((entry_address, _), _)| entry_address == address)
+                .count();
+            let (address_entries, rest) = remaining_entries.split_at(n_address_entries);
+            storage_updates.insert(
+                *address,
+                address_entries.iter().map(|&((_, key), value)| (key, value)).collect(),
+            );
+            remaining_entries = rest;
         }
 
         storage_updates
@@ -378,20 +886,27 @@ impl From<StorageView> for IndexMap<ContractAddress, IndexMap<StorageKey, StarkF
 /// Caches read and write requests.
 /// The tracked changes are needed for block state commitment.
 
-// Invariant: keys cannot be deleted from fields (only used internally by the cached state).
-#[derive(Debug, Default, PartialEq, Eq)]
+// Invariant: keys cannot be deleted from fields (only used internally by the cached state), except
+// by a rollback to a savepoint, which restores the writer's fields to their contents at the time.
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
 pub struct StateCache {
     // Reader's cached information; initial values, read before any write operation (per cell).
     nonce_initial_values: HashMap<ContractAddress, Nonce>,
     class_hash_initial_values: HashMap<ContractAddress, ClassHash>,
     storage_initial_values: HashMap<StorageEntry, StarkFelt>,
     compiled_class_hash_initial_values: HashMap<ClassHash, CompiledClassHash>,
+    // Classes the reader reported as not declared.
+    undeclared_class_hashes: HashSet<ClassHash>,
 
     // Writer's cached information.
     nonce_writes: HashMap<ContractAddress, Nonce>,
     class_hash_writes: HashMap<ContractAddress, ClassHash>,
     storage_writes: HashMap<StorageEntry, StarkFelt>,
     compiled_class_hash_writes: HashMap<ClassHash, CompiledClassHash>,
+    // Classes set explicitly (i.e., declared), as opposed to classes cached on read.
+    declared_class_hashes: HashSet<ClassHash>,
+    // The number of writes of each written cell.
+    write_counts: HashMap<StateKey, usize>,
 }
 
 impl StateCache {
@@ -429,6 +944,7 @@ impl StateCache {
         value: StarkFelt,
     ) {
         let contract_storage_key = (contract_address, key);
+        self.count_write(StateKey::Storage(contract_storage_key));
         self.storage_writes.insert(contract_storage_key, value);
     }
 
@@ -437,6 +953,7 @@ impl StateCache {
     }
 
     fn set_nonce_value(&mut self, contract_address: ContractAddress, nonce: Nonce) {
+        self.count_write(StateKey::Nonce(contract_address));
         self.nonce_writes.insert(contract_address, nonce);
     }
 
@@ -455,6 +972,7 @@ impl StateCache {
     }
 
     fn set_class_hash_write(&mut self, contract_address: ContractAddress, class_hash: ClassHash) {
+        self.count_write(StateKey::ClassHash(contract_address));
         self.class_hash_writes.insert(contract_address, class_hash);
     }
 
@@ -477,9 +995,59 @@ impl StateCache {
         class_hash: ClassHash,
         compiled_class_hash: CompiledClassHash,
     ) {
+        self.count_write(StateKey::CompiledClassHash(class_hash));
         self.compiled_class_hash_writes.insert(class_hash, compiled_class_hash);
     }
 
+    fn count_write(&mut self, key: StateKey) {
+        *self.write_counts.entry(key).or_default() += 1;
+    }
+
+    fn get_write_metrics(&self, n_most_written_keys: usize) -> WriteMetrics {
+        let mut most_written_keys: Vec<(StateKey, usize)> =
+            self.write_counts.iter().map(|(&key, &n_writes)| (key, n_writes)).collect();
+        most_written_keys
+            .sort_unstable_by_key(|&(key, n_writes)| (std::cmp::Reverse(n_writes), key));
+        let n_rewritten_keys =
+            most_written_keys.iter().take_while(|(_, n_writes)| *n_writes > 1).count();
+        most_written_keys.truncate(n_most_written_keys);
+
+        let n_diff_entries = updated_keys(&self.storage_writes, &self.storage_initial_values)
+            .count()
+            + updated_keys(&self.nonce_writes, &self.nonce_initial_values).count()
+            + updated_keys(&self.class_hash_writes, &self.class_hash_initial_values).count()
+            + updated_keys(
+                &self.compiled_class_hash_writes,
+                &self.compiled_class_hash_initial_values,
+            )
+            .count();
+
+        WriteMetrics {
+            n_writes: self.write_counts.values().sum(),
+            n_written_keys: self.write_counts.len(),
+            n_rewritten_keys,
+            n_diff_entries,
+            most_written_keys,
+        }
+    }
+
+    fn write_keys(&self) -> Vec<StateKey> {
+        let storage_keys = self.storage_writes.keys().map(|&key| StateKey::Storage(key));
+        let nonce_keys = self.nonce_writes.keys().map(|&address| StateKey::Nonce(address));
+        let class_hash_keys =
+            self.class_hash_writes.keys().map(|&address| StateKey::ClassHash(address));
+        let compiled_class_hash_keys = self
+            .compiled_class_hash_writes
+            .keys()
+            .map(|&class_hash| StateKey::CompiledClassHash(class_hash));
+
+        storage_keys
+            .chain(nonce_keys)
+            .chain(class_hash_keys)
+            .chain(compiled_class_hash_keys)
+            .collect()
+    }
+
     fn get_storage_updates(&self) -> HashMap<StorageEntry, StarkFelt> {
         subtract_mappings(&self.storage_writes, &self.storage_initial_values)
     }
@@ -488,6 +1056,19 @@ impl StateCache {
         subtract_mappings(&self.class_hash_writes, &self.class_hash_initial_values)
     }
 
+    /// Returns the class hash updates of contracts that were deployed before this state; i.e., the
+    /// updates made by the `replace_class` syscall, as opposed to deployments.
+    fn get_replaced_classes(&self) -> HashMap<ContractAddress, ClassHash> {
+        self.get_class_hash_updates()
+            .into_iter()
+            .filter(|(address, _)| {
+                self.class_hash_initial_values
+                    .get(address)
+                    .is_some_and(|initial_class_hash| *initial_class_hash != ClassHash::default())
+            })
+            .collect()
+    }
+
     fn get_nonce_updates(&self) -> HashMap<ContractAddress, Nonce> {
         subtract_mappings(&self.nonce_writes, &self.nonce_initial_values)
     }
@@ -498,6 +1079,69 @@ impl StateCache {
             &self.compiled_class_hash_initial_values,
         )
     }
+
+    fn get_modified_contracts(&self) -> HashSet<ContractAddress> {
+        let storage_updates = updated_keys(&self.storage_writes, &self.storage_initial_values)
+            .map(|(contract_address, _)| contract_address);
+        let nonce_updates = updated_keys(&self.nonce_writes, &self.nonce_initial_values);
+        let class_hash_updates =
+            updated_keys(&self.class_hash_writes, &self.class_hash_initial_values);
+
+        storage_updates.chain(nonce_updates).chain(class_hash_updates).copied().collect()
+    }
+
+    fn get_accessed_contracts(&self) -> HashSet<ContractAddress> {
+        let storage_accesses = self
+            .storage_initial_values
+            .keys()
+            .chain(self.storage_writes.keys())
+            .map(|(contract_address, _)| contract_address);
+        let nonce_accesses = self.nonce_initial_values.keys().chain(self.nonce_writes.keys());
+        let class_hash_accesses =
+            self.class_hash_initial_values.keys().chain(self.class_hash_writes.keys());
+
+        storage_accesses.chain(nonce_accesses).chain(class_hash_accesses).copied().collect()
+    }
+
+    /// Counts the writes that differ from the initial values, as [`Self::get_storage_updates`]
+    /// and friends would return them.
+    fn count_updates(&self) -> StateChangesCount {
+        let mut modified_contracts: HashSet<&ContractAddress> = HashSet::new();
+        let mut n_storage_updates = 0;
+        for (contract_address, _) in
+            updated_keys(&self.storage_writes, &self.storage_initial_values)
+        {
+            n_storage_updates += 1;
+            modified_contracts.insert(contract_address);
+        }
+        let mut n_class_hash_updates = 0;
+        for contract_address in
+            updated_keys(&self.class_hash_writes, &self.class_hash_initial_values)
+        {
+            n_class_hash_updates += 1;
+            modified_contracts.insert(contract_address);
+        }
+        modified_contracts.extend(updated_keys(&self.nonce_writes, &self.nonce_initial_values));
+
+        StateChangesCount {
+            n_storage_updates,
+            n_class_hash_updates,
+            n_compiled_class_hash_updates: updated_keys(
+                &self.compiled_class_hash_writes,
+                &self.compiled_class_hash_initial_values,
+            )
+            .count(),
+            n_modified_contracts: modified_contracts.len(),
+        }
+    }
+}
+
+/// Returns the keys written with a value other than their initial one.
+fn updated_keys<'a, K: Eq + Hash, V: PartialEq>(
+    writes: &'a HashMap<K, V>,
+    initial_values: &'a HashMap<K, V>,
+) -> impl Iterator<Item = &'a K> {
+    writes.iter().filter(|(k, v)| initial_values.get(k) != Some(v)).map(|(k, _)| k)
 }
 
 /// Wraps a mutable reference to a `State` object, exposing its API.
@@ -520,6 +1164,10 @@ impl<'a, S: State + ?Sized> StateReader for MutRefState<'a, S> {
         self.0.get_storage_at(contract_address, key)
     }
 
+    fn get_storage_at_batch(&self, storage_keys: &[StorageEntry]) -> StateResult<Vec<StarkFelt>> {
+        self.0.get_storage_at_batch(storage_keys)
+    }
+
     fn get_nonce_at(&self, contract_address: ContractAddress) -> StateResult<Nonce> {
         self.0.get_nonce_at(contract_address)
     }
@@ -551,6 +1199,10 @@ impl<'a, S: State + ?Sized> State for MutRefState<'a, S> {
         self.0.increment_nonce(contract_address)
     }
 
+    fn set_nonce_at(&mut self, contract_address: ContractAddress, nonce: Nonce) -> StateResult<()> {
+        self.0.set_nonce_at(contract_address, nonce)
+    }
+
     fn set_class_hash_at(
         &mut self,
         contract_address: ContractAddress,
@@ -559,6 +1211,14 @@ impl<'a, S: State + ?Sized> State for MutRefState<'a, S> {
         self.0.set_class_hash_at(contract_address, class_hash)
     }
 
+    fn replace_class_hash_at(
+        &mut self,
+        contract_address: ContractAddress,
+        class_hash: ClassHash,
+    ) -> StateResult<()> {
+        self.0.replace_class_hash_at(contract_address, class_hash)
+    }
+
     fn set_contract_class(
         &mut self,
         class_hash: ClassHash,
@@ -641,10 +1301,13 @@ pub struct StagedTransactionalState {
 }
 
 /// Holds uncommitted changes induced on Starknet contracts.
-#[derive(Clone, Debug, Eq, PartialEq)]
+#[derive(Clone, Debug, Deserialize, Eq, PartialEq, Serialize)]
 pub struct CommitmentStateDiff {
     // Contract instance attributes (per address).
+    // Includes both newly deployed contracts and replaced classes.
     pub address_to_class_hash: IndexMap<ContractAddress, ClassHash>,
+    // The subset of `address_to_class_hash` whose contracts were already deployed.
+    pub replaced_classes: IndexMap<ContractAddress, ClassHash>,
     pub address_to_nonce: IndexMap<ContractAddress, Nonce>,
     pub storage_updates: IndexMap<ContractAddress, IndexMap<StorageKey, StarkFelt>>,
 
@@ -652,6 +1315,18 @@ pub struct CommitmentStateDiff {
     pub class_hash_to_compiled_class_hash: IndexMap<ClassHash, CompiledClassHash>,
 }
 
+impl CommitmentStateDiff {
+    /// Sorts all mappings by key, making the order of the diff canonical.
+    pub fn sort(&mut self) {
+        self.address_to_class_hash.sort_keys();
+        self.replaced_classes.sort_keys();
+        self.address_to_nonce.sort_keys();
+        self.storage_updates.sort_keys();
+        self.storage_updates.values_mut().for_each(IndexMap::sort_keys);
+        self.class_hash_to_compiled_class_hash.sort_keys();
+    }
+}
+
 /// Used to track the state diff size, which is determined by the number of new keys.
 /// Also, can be used to accuratly measure the contribution of a single (say, transactional)
 /// state to a cumulative state diff - provides set-like functionallities for this porpuse.
@@ -714,6 +1389,236 @@ impl StateChangesKeys {
     }
 }
 
+/// The number of reads of a category of state cells served by a cache (hits), and of those fetched
+/// from the underlying state (misses).
+#[derive(Clone, Copy, Debug, Default, Eq, PartialEq)]
+pub struct CacheCounts {
+    pub hits: usize,
+    pub misses: usize,
+}
+
+/// Read statistics of the caches of a [`CachedState`], per category.
+/// Contract classes served by the global contract cache count as hits.
+#[derive(Clone, Copy, Debug, Default, Eq, PartialEq)]
+pub struct CacheStats {
+    pub storage: CacheCounts,
+    pub nonce: CacheCounts,
+    pub class_hash: CacheCounts,
+    pub compiled_class_hash: CacheCounts,
+    pub contract_class: CacheCounts,
+}
+
+/// A cell of Starknet state.
+#[derive(Clone, Copy, Debug, Eq, Hash, Ord, PartialEq, PartialOrd)]
+pub enum StateKey {
+    Storage(StorageEntry),
+    Nonce(ContractAddress),
+    ClassHash(ContractAddress),
+    CompiledClassHash(ClassHash),
+    // The contract class declared under the given class hash.
+    ContractClass(ClassHash),
+}
+
+/// Statistics of the writes done through a state; see [`CachedState::write_metrics`].
+#[derive(Clone, Debug, Default, Eq, PartialEq)]
+pub struct WriteMetrics {
+    /// The number of writes, including rewrites of cells.
+    pub n_writes: usize,
+    pub n_written_keys: usize,
+    /// The number of cells written more than once.
+    pub n_rewritten_keys: usize,
+    /// The number of cells whose values the writes changed; i.e., the entries of the state diff.
+    pub n_diff_entries: usize,
+    /// The most written cells, with their number of writes; most written first.
+    pub most_written_keys: Vec<(StateKey, usize)>,
+}
+
+impl WriteMetrics {
+    /// Returns the number of writes per state diff entry, if there are any.
+    pub fn write_amplification(&self) -> Option<f64> {
+        (self.n_diff_entries > 0).then(|| self.n_writes as f64 / self.n_diff_entries as f64)
+    }
+}
+
+/// Identifies a savepoint of a [`CachedState`]; see [`CachedState::savepoint`].
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub struct SavepointId(usize);
+
+/// Journals the writes to the cache of a [`CachedState`], while it has savepoints.
+#[derive(Clone, Debug, Default)]
+struct WriteJournal {
+    // The contents overwritten by each write, in order.
+    entries: Vec<JournalEntry>,
+    // The live savepoints, oldest first, with the number of journal entries at their creation.
+    savepoints: Vec<(SavepointId, usize)>,
+    next_savepoint_id: usize,
+}
+
+impl WriteJournal {
+    fn index_of(&self, savepoint: SavepointId) -> StateResult<usize> {
+        self.savepoints
+            .iter()
+            .position(|&(live_savepoint, _)| live_savepoint == savepoint)
+            .ok_or(StateError::InvalidSavepoint(savepoint))
+    }
+}
+
+#[derive(Clone, Debug)]
+enum JournalEntry {
+    // The previous write of a cell (`None` if unwritten), and its number of writes.
+    Write { key: StateKey, previous_value: Option<StarkFelt>, previous_n_writes: usize },
+    // The previous local entry of a declared class, and whether it was declared already.
+    Declare { class_hash: ClassHash, previous_class: Option<ContractClass>, was_declared: bool },
+}
+
+fn restore_write<K: Eq + Hash, V>(writes: &mut HashMap<K, V>, key: K, previous_value: Option<V>) {
+    match previous_value {
+        Some(value) => writes.insert(key, value),
+        None => writes.remove(&key),
+    };
+}
+
+/// A read of a cell from the underlying state of a [`CachedState`], with the value read.
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub enum StateAccess {
+    Storage(StorageEntry, StarkFelt),
+    Nonce(ContractAddress, Nonce),
+    ClassHash(ContractAddress, ClassHash),
+    CompiledClassHash(ClassHash, CompiledClassHash),
+    ContractClass(ClassHash),
+}
+
+/// Holds the values read by a state from its underlying state (its read set).
+#[derive(Clone, Debug, Default, Eq, PartialEq)]
+pub struct StateReads {
+    pub storage: HashMap<StorageEntry, StarkFelt>,
+    pub nonces: HashMap<ContractAddress, Nonce>,
+    pub class_hashes: HashMap<ContractAddress, ClassHash>,
+    pub compiled_class_hashes: HashMap<ClassHash, CompiledClassHash>,
+    pub classes: HashSet<ClassHash>,
+    // Classes whose reads failed, as not declared.
+    pub undeclared_classes: HashSet<ClassHash>,
+}
+
+impl FromIterator<StateAccess> for StateReads {
+    fn from_iter<I: IntoIterator<Item = StateAccess>>(accesses: I) -> Self {
+        let mut reads = Self::default();
+        for access in accesses {
+            match access {
+                StateAccess::Storage(key, value) => {
+                    reads.storage.insert(key, value);
+                }
+                StateAccess::Nonce(contract_address, nonce) => {
+                    reads.nonces.insert(contract_address, nonce);
+                }
+                StateAccess::ClassHash(contract_address, class_hash) => {
+                    reads.class_hashes.insert(contract_address, class_hash);
+                }
+                StateAccess::CompiledClassHash(class_hash, compiled_class_hash) => {
+                    reads.compiled_class_hashes.insert(class_hash, compiled_class_hash);
+                }
+                StateAccess::ContractClass(class_hash) => {
+                    reads.classes.insert(class_hash);
+                }
+            }
+        }
+
+        reads
+    }
+}
+
+/// A read of a state that is inconsistent with another reader of the same (underlying) state.
+#[derive(Clone, Copy, Debug, Eq, Hash, PartialEq)]
+pub enum ReadMismatch {
+    Value { key: StateKey, read: StarkFelt, expected: StarkFelt },
+    UndeclaredClass(ClassHash),
+    // A class read as not declared, which the other reader has.
+    DeclaredClass(ClassHash),
+}
+
+impl StateReads {
+    /// Re-reads each cell of this read set from the given reader, and returns the cells whose
+    /// values differ; e.g., to validate a re-executed block against an independent pre-state.
+    pub fn mismatches(&self, reader: &impl StateReader) -> StateResult<Vec<ReadMismatch>> {
+        let mut mismatches = Vec::new();
+        let mut compare = |key, read, expected| {
+            if read != expected {
+                mismatches.push(ReadMismatch::Value { key, read, expected });
+            }
+        };
+        for (&(contract_address, storage_key), &value) in &self.storage {
+            let expected = reader.get_storage_at(contract_address, storage_key)?;
+            compare(StateKey::Storage((contract_address, storage_key)), value, expected);
+        }
+        for (&contract_address, nonce) in &self.nonces {
+            let expected = reader.get_nonce_at(contract_address)?;
+            compare(StateKey::Nonce(contract_address), nonce.0, expected.0);
+        }
+        for (&contract_address, class_hash) in &self.class_hashes {
+            let expected = reader.get_class_hash_at(contract_address)?;
+            compare(StateKey::ClassHash(contract_address), class_hash.0, expected.0);
+        }
+        for (&class_hash, compiled_class_hash) in &self.compiled_class_hashes {
+            let expected = reader.get_compiled_class_hash(class_hash)?;
+            compare(StateKey::CompiledClassHash(class_hash), compiled_class_hash.0, expected.0);
+        }
+        for &class_hash in &self.classes {
+            match reader.get_compiled_contract_class(class_hash) {
+                Ok(_) => {}
+                Err(StateError::UndeclaredClassHash(_)) => {
+                    mismatches.push(ReadMismatch::UndeclaredClass(class_hash))
+                }
+                Err(error) => return Err(error),
+            }
+        }
+        for &class_hash in &self.undeclared_classes {
+            match reader.get_compiled_contract_class(class_hash) {
+                Ok(_) => mismatches.push(ReadMismatch::DeclaredClass(class_hash)),
+                Err(StateError::UndeclaredClassHash(_)) => {}
+                Err(error) => return Err(error),
+            }
+        }
+
+        Ok(mismatches)
+    }
+
+    /// Returns the keys read by this state and changed by the given state changes; i.e., the reads
+    /// that are invalidated if the changes are applied first.
+    pub fn conflicts_with(&self, writes: &StateChanges) -> HashSet<StateKey> {
+        let storage_conflicts = writes
+            .storage_updates
+            .keys()
+            .filter(|key| self.storage.contains_key(key))
+            .map(|&key| StateKey::Storage(key));
+        let nonce_conflicts = writes
+            .nonce_updates
+            .keys()
+            .filter(|address| self.nonces.contains_key(address))
+            .map(|&address| StateKey::Nonce(address));
+        let class_hash_conflicts = writes
+            .class_hash_updates
+            .keys()
+            .filter(|address| self.class_hashes.contains_key(address))
+            .map(|&address| StateKey::ClassHash(address));
+        // A declaration also invalidates reads of the declared class itself, failed ones included.
+        let compiled_class_hash_conflicts = writes
+            .compiled_class_hash_updates
+            .keys()
+            .filter(|class_hash| {
+                self.compiled_class_hashes.contains_key(class_hash)
+                    || self.classes.contains(class_hash)
+                    || self.undeclared_classes.contains(class_hash)
+            })
+            .map(|&class_hash| StateKey::CompiledClassHash(class_hash));
+
+        storage_conflicts
+            .chain(nonce_conflicts)
+            .chain(class_hash_conflicts)
+            .chain(compiled_class_hash_conflicts)
+            .collect()
+    }
+}
+
 /// Holds the state changes.
 #[derive(Clone, Debug, Default, Eq, PartialEq)]
 pub struct StateChanges {
@@ -796,7 +1701,33 @@ impl StateChanges {
     }
 }
 
+/// The updates of a state diff, before their conversion into a [`CommitmentStateDiff`].
+struct StateDiffParts {
+    class_hash_updates: HashMap<ContractAddress, ClassHash>,
+    replaced_classes: HashMap<ContractAddress, ClassHash>,
+    storage_updates: HashMap<StorageEntry, StarkFelt>,
+    compiled_class_hash_updates: HashMap<ClassHash, CompiledClassHash>,
+    nonce_updates: HashMap<ContractAddress, Nonce>,
+}
+
+impl From<StateDiffParts> for CommitmentStateDiff {
+    fn from(parts: StateDiffParts) -> Self {
+        Self {
+            address_to_class_hash: IndexMap::from_iter(parts.class_hash_updates),
+            replaced_classes: IndexMap::from_iter(parts.replaced_classes),
+            storage_updates: StorageDiff::from(StorageView(parts.storage_updates)),
+            class_hash_to_compiled_class_hash: IndexMap::from_iter(
+                parts.compiled_class_hash_updates,
+            ),
+            address_to_nonce: IndexMap::from_iter(parts.nonce_updates),
+        }
+    }
+}
+
 /// Holds the number of state changes.
+/// Nonce updates are not counted on their own: the OS packs the new nonce of a contract into the
+/// word describing its updates, so they only cost data availability through the modified contracts
+/// (see [`crate::fee::gas_usage::get_onchain_data_segment_length`]).
 #[derive(Clone, Copy, Debug, Default, Eq, PartialEq)]
 pub struct StateChangesCount {
     pub n_storage_updates: usize,
@@ -805,8 +1736,233 @@ pub struct StateChangesCount {
     pub n_modified_contracts: usize,
 }
 
-// Note: `ContractClassLRUCache` key-value types must align with `ContractClassMapping`.
-type ContractClassLRUCache = SizedCache<ClassHash, ContractClass>;
+/// Bounds the contents of a [`GlobalContractCache`]. Pinned classes are never evicted, and do not
+/// count against the bounds.
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct CacheConfig {
+    pub max_entries: usize,
+    /// A bound on the total estimated size of the cached classes; see
+    /// [`ContractClassLRUCache::estimated_size`].
+    pub max_bytes: usize,
+    pub pinned: Vec<ClassHash>,
+}
+
+impl CacheConfig {
+    pub fn with_max_entries(max_entries: usize) -> Self {
+        Self { max_entries, max_bytes: usize::MAX, pinned: Vec::new() }
+    }
+}
+
+#[derive(Debug)]
+struct ContractClassCacheEntry {
+    contract_class: ContractClass,
+    last_used: u64,
+}
+
+/// A least-recently-used cache of contract classes, bounded as configured by a [`CacheConfig`].
+// Note: the key-value types must align with `ContractClassMapping`.
+#[derive(Debug)]
+pub struct ContractClassLRUCache {
+    max_entries: usize,
+    max_bytes: usize,
+    pinned: HashSet<ClassHash>,
+    entries: HashMap<ClassHash, ContractClassCacheEntry>,
+    // The unpinned entries, keyed by their last use; i.e., in eviction order.
+    unpinned_by_last_use: BTreeMap<u64, ClassHash>,
+    // A logical clock, ticking on each access; orders the entries by recency deterministically.
+    clock: u64,
+    n_unpinned_entries: usize,
+    n_unpinned_bytes: usize,
+    hits: u64,
+    misses: u64,
+}
+
+impl ContractClassLRUCache {
+    const FELT_SIZE_IN_BYTES: usize = 32;
+
+    pub fn new(config: CacheConfig) -> Self {
+        Self {
+            max_entries: config.max_entries,
+            max_bytes: config.max_bytes,
+            pinned: config.pinned.into_iter().collect(),
+            entries: HashMap::new(),
+            unpinned_by_last_use: BTreeMap::new(),
+            clock: 0,
+            n_unpinned_entries: 0,
+            n_unpinned_bytes: 0,
+            hits: 0,
+            misses: 0,
+        }
+    }
+
+    /// Returns the size of the given class, as accounted against [`CacheConfig::max_bytes`]; the
+    /// size of its bytecode.
+    pub fn estimated_size(contract_class: &ContractClass) -> usize {
+        contract_class.bytecode_length() * Self::FELT_SIZE_IN_BYTES
+    }
+
+    /// Returns whether the given class is cached; unlike a lookup, neither counted nor considered a
+    /// use of the class.
+    pub fn contains(&self, class_hash: &ClassHash) -> bool {
+        self.entries.contains_key(class_hash)
+    }
+
+    /// Returns the unpinned cached classes, in eviction order; i.e., least recently used first.
+    pub fn eviction_order(&self) -> Vec<ClassHash> {
+        self.unpinned_by_last_use.values().copied().collect()
+    }
+
+    fn tick(&mut self) -> u64 {
+        self.clock += 1;
+        self.clock
+    }
+
+    fn get_entry<Q>(&mut self, class_hash: &Q) -> Option<&mut ContractClassCacheEntry>
+    where
+        ClassHash: std::borrow::Borrow<Q>,
+        Q: Hash + Eq + ?Sized,
+    {
+        let now = self.tick();
+        match self.entries.get_mut(class_hash) {
+            Some(entry) => {
+                self.hits += 1;
+                if let Some(class_hash) = self.unpinned_by_last_use.remove(&entry.last_used) {
+                    self.unpinned_by_last_use.insert(now, class_hash);
+                }
+                entry.last_used = now;
+                Some(entry)
+            }
+            None => {
+                self.misses += 1;
+                None
+            }
+        }
+    }
+
+    fn insert(
+        &mut self,
+        class_hash: ClassHash,
+        contract_class: ContractClass,
+    ) -> Option<ContractClass> {
+        let previous_class = self.remove(&class_hash);
+        let last_used = self.tick();
+        if !self.pinned.contains(&class_hash) {
+            self.n_unpinned_entries += 1;
+            self.n_unpinned_bytes += Self::estimated_size(&contract_class);
+            self.unpinned_by_last_use.insert(last_used, class_hash);
+        }
+        self.entries.insert(class_hash, ContractClassCacheEntry { contract_class, last_used });
+        self.evict_to_bounds(class_hash);
+        previous_class
+    }
+
+    fn remove<Q>(&mut self, class_hash: &Q) -> Option<ContractClass>
+    where
+        ClassHash: std::borrow::Borrow<Q>,
+        Q: Hash + Eq + ?Sized,
+    {
+        let (class_hash, entry) = self.entries.remove_entry(class_hash)?;
+        if !self.pinned.contains::<ClassHash>(&class_hash) {
+            self.n_unpinned_entries -= 1;
+            self.n_unpinned_bytes -= Self::estimated_size(&entry.contract_class);
+            self.unpinned_by_last_use.remove(&entry.last_used);
+        }
+        Some(entry.contract_class)
+    }
+
+    /// Evicts the least recently used unpinned classes until the cache is within its bounds; the
+    /// given (just inserted) class is kept regardless.
+    fn evict_to_bounds(&mut self, inserted_class_hash: ClassHash) {
+        while self.n_unpinned_entries > self.max_entries || self.n_unpinned_bytes > self.max_bytes {
+            match self.unpinned_by_last_use.first_key_value() {
+                Some((_, &class_hash)) if class_hash != inserted_class_hash => {
+                    self.remove(&class_hash);
+                }
+                // Only the inserted class is left to evict.
+                _ => break,
+            }
+        }
+    }
+}
+
+impl Cached<ClassHash, ContractClass> for ContractClassLRUCache {
+    fn cache_get<Q>(&mut self, class_hash: &Q) -> Option<&ContractClass>
+    where
+        ClassHash: std::borrow::Borrow<Q>,
+        Q: Hash + Eq + ?Sized,
+    {
+        self.get_entry(class_hash).map(|entry| &entry.contract_class)
+    }
+
+    fn cache_get_mut<Q>(&mut self, class_hash: &Q) -> Option<&mut ContractClass>
+    where
+        ClassHash: std::borrow::Borrow<Q>,
+        Q: Hash + Eq + ?Sized,
+    {
+        self.get_entry(class_hash).map(|entry| &mut entry.contract_class)
+    }
+
+    fn cache_set(
+        &mut self,
+        class_hash: ClassHash,
+        contract_class: ContractClass,
+    ) -> Option<ContractClass> {
+        self.insert(class_hash, contract_class)
+    }
+
+    fn cache_get_or_set_with<F: FnOnce() -> ContractClass>(
+        &mut self,
+        class_hash: ClassHash,
+        f: F,
+    ) -> &mut ContractClass {
+        if self.get_entry(&class_hash).is_none() {
+            self.insert(class_hash, f());
+        }
+        &mut self.entries.get_mut(&class_hash).expect("The class was just cached.").contract_class
+    }
+
+    fn cache_remove<Q>(&mut self, class_hash: &Q) -> Option<ContractClass>
+    where
+        ClassHash: std::borrow::Borrow<Q>,
+        Q: Hash + Eq + ?Sized,
+    {
+        self.remove(class_hash)
+    }
+
+    fn cache_clear(&mut self) {
+        self.entries.clear();
+        self.unpinned_by_last_use.clear();
+        self.n_unpinned_entries = 0;
+        self.n_unpinned_bytes = 0;
+    }
+
+    fn cache_reset(&mut self) {
+        self.cache_clear();
+        self.cache_reset_metrics();
+    }
+
+    fn cache_reset_metrics(&mut self) {
+        self.hits = 0;
+        self.misses = 0;
+    }
+
+    fn cache_size(&self) -> usize {
+        self.entries.len()
+    }
+
+    fn cache_hits(&self) -> Option<u64> {
+        Some(self.hits)
+    }
+
+    fn cache_misses(&self) -> Option<u64> {
+        Some(self.misses)
+    }
+
+    fn cache_capacity(&self) -> Option<usize> {
+        Some(self.max_entries)
+    }
+}
+
 type LockedContractClassCache<'a> = MutexGuard<'a, ContractClassLRUCache>;
 #[derive(Debug, Clone)]
 // Thread-safe LRU cache for contract classes, optimized for inter-language sharing when
@@ -826,7 +1982,58 @@ impl GlobalContractCache {
         self.lock().cache_clear();
     }
 
+    /// Removes the given class from the cache, even if pinned, so that it is read again from the
+    /// state it is requested from; e.g., if the cached compiled class is stale, or was upgraded.
+    pub fn invalidate(&self, class_hash: ClassHash) -> Option<ContractClass> {
+        self.lock().cache_remove(&class_hash)
+    }
+
     pub fn new(cache_size: usize) -> Self {
-        Self(Arc::new(Mutex::new(ContractClassLRUCache::with_size(cache_size))))
+        Self::with_config(CacheConfig::with_max_entries(cache_size))
+    }
+
+    pub fn with_config(config: CacheConfig) -> Self {
+        Self(Arc::new(Mutex::new(ContractClassLRUCache::new(config))))
+    }
+
+    /// Reads the given classes from the given state into the cache, unless already cached; e.g.,
+    /// at startup, to spare the first blocks their deserialization. Returns the result per class;
+    /// failing to read a class does not stop the others from being read.
+    pub fn warm_up(
+        &self,
+        reader: &impl StateReader,
+        class_hashes: &[ClassHash],
+    ) -> Vec<(ClassHash, StateResult<()>)> {
+        class_hashes
+            .iter()
+            .map(|&class_hash| (class_hash, self.warm_up_class(reader, class_hash)))
+            .collect()
+    }
+
+    /// Same as [`Self::warm_up`], reading (and deserializing) the classes in parallel, on the
+    /// global rayon pool. The results are in the order of the given class hashes.
+    #[cfg(feature = "concurrency")]
+    pub fn par_warm_up(
+        &self,
+        reader: &impl SyncStateReader,
+        class_hashes: &[ClassHash],
+    ) -> Vec<(ClassHash, StateResult<()>)> {
+        use rayon::prelude::*;
+
+        class_hashes
+            .par_iter()
+            .map(|&class_hash| (class_hash, self.warm_up_class(reader, class_hash)))
+            .collect()
+    }
+
+    fn warm_up_class(&self, reader: &impl StateReader, class_hash: ClassHash) -> StateResult<()> {
+        if self.lock().contains(&class_hash) {
+            return Ok(());
+        }
+
+        // Read without holding the lock, as reading may be slow.
+        let contract_class = reader.get_compiled_contract_class(class_hash)?;
+        self.lock().cache_set(class_hash, contract_class);
+        Ok(())
     }
 }
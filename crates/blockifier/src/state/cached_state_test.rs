@@ -1,18 +1,34 @@
-use std::collections::HashMap;
+use std::cell::Cell;
+use std::collections::{HashMap, HashSet};
+use std::sync::Arc;
+use std::thread;
 
 use assert_matches::assert_matches;
 use indexmap::indexmap;
+use num_bigint::BigUint;
 use pretty_assertions::assert_eq;
 use rstest::rstest;
 use starknet_api::core::PatriciaKey;
 use starknet_api::hash::StarkHash;
-use starknet_api::{class_hash, contract_address, patricia_key, stark_felt};
+use starknet_api::transaction::Calldata;
+use starknet_api::{
+    calldata, class_hash, contract_address, patricia_key, stark_felt, StarknetApiError,
+};
 
+use crate::abi::abi_utils::{get_fee_token_var_address, selector_from_name};
+use crate::abi::sierra_types::next_storage_key;
 use crate::context::BlockContext;
+use crate::execution::entry_point::CallEntryPoint;
 use crate::state::cached_state::*;
+#[cfg(feature = "concurrency")]
+use crate::state::state_api::SyncStateReader;
 use crate::test_utils::cached_state::deprecated_create_test_state;
+use crate::test_utils::contracts::FeatureContract;
 use crate::test_utils::dict_state_reader::DictStateReader;
-use crate::test_utils::{get_test_contract_class, TEST_CLASS_HASH, TEST_EMPTY_CONTRACT_CLASS_HASH};
+use crate::test_utils::{
+    get_test_contract_class, trivial_external_entry_point_with_address, CairoVersion,
+    TEST_CLASS_HASH, TEST_EMPTY_CONTRACT_CLASS_HASH,
+};
 
 const CONTRACT_ADDRESS: &str = "0x100";
 
@@ -83,17 +99,33 @@ fn cast_between_storage_mapping_types() {
     let storage_val1: StarkFelt = stark_felt!("0x5");
     let storage_val2: StarkFelt = stark_felt!("0xa");
 
+    let key2 = StorageKey(patricia_key!("0x30"));
+
     let storage_map = StorageView(HashMap::from([
-        ((contract_address0, key0), storage_val0),
+        ((contract_address1, key2), storage_val0),
         ((contract_address0, key1), storage_val1),
         ((contract_address1, key0), storage_val2),
+        ((contract_address0, key0), storage_val0),
+        ((contract_address1, key1), storage_val1),
     ]));
 
     let expected_indexed_map = IndexMap::from([
         (contract_address0, indexmap!(key0 => storage_val0, key1 => storage_val1)),
-        (contract_address1, indexmap!(key0 => storage_val2)),
+        (
+            contract_address1,
+            indexmap!(key0 => storage_val2, key1 => storage_val1, key2 => storage_val0),
+        ),
     ]);
-    assert_eq!(expected_indexed_map, IndexMap::from(storage_map));
+    let indexed_map = IndexMap::from(storage_map);
+    assert_eq!(expected_indexed_map, indexed_map);
+
+    // The conversion orders by address, and then by key.
+    let flatten = |map: &IndexMap<ContractAddress, IndexMap<StorageKey, StarkFelt>>| {
+        map.iter()
+            .flat_map(|(address, storage)| storage.keys().map(move |key| (*address, *key)))
+            .collect::<Vec<_>>()
+    };
+    assert_eq!(flatten(&indexed_map), flatten(&expected_indexed_map));
 }
 
 #[test]
@@ -110,13 +142,11 @@ fn get_and_increment_nonce() {
     let contract_address2 = contract_address!("0x200");
     let initial_nonce = Nonce(stark_felt!("0x1"));
 
-    let mut state = CachedState::from(DictStateReader {
-        address_to_nonce: HashMap::from([
-            (contract_address1, initial_nonce),
-            (contract_address2, initial_nonce),
-        ]),
-        ..Default::default()
-    });
+    let mut state = CachedState::from(
+        DictStateReader::default()
+            .with_nonce(contract_address1, initial_nonce)
+            .with_nonce(contract_address2, initial_nonce),
+    );
     assert_eq!(state.get_nonce_at(contract_address1).unwrap(), initial_nonce);
     assert_eq!(state.get_nonce_at(contract_address2).unwrap(), initial_nonce);
 
@@ -136,6 +166,69 @@ fn get_and_increment_nonce() {
     assert_eq!(state.get_nonce_at(contract_address2).unwrap(), nonce2_plus_one);
 }
 
+#[rstest]
+#[case::near_u64_max(Nonce(stark_felt!(u64::MAX)), Nonce(stark_felt!("0x10000000000000000")))]
+#[case::beyond_u128_max(
+    Nonce(stark_felt!("0x100000000000000000000000000000000")),
+    Nonce(stark_felt!("0x100000000000000000000000000000001"))
+)]
+fn increment_large_nonce(#[case] initial_nonce: Nonce, #[case] expected_nonce: Nonce) {
+    let contract_address = contract_address!(CONTRACT_ADDRESS);
+    let mut state: CachedState<DictStateReader> = CachedState::default();
+
+    state.set_nonce_at(contract_address, initial_nonce).unwrap();
+    state.increment_nonce(contract_address).unwrap();
+    assert_eq!(state.get_nonce_at(contract_address).unwrap(), expected_nonce);
+}
+
+#[test]
+fn cannot_increment_nonce_beyond_field() {
+    let contract_address = contract_address!(CONTRACT_ADDRESS);
+    // The field prime minus one.
+    let max_nonce =
+        Nonce(stark_felt!("0x800000000000011000000000000000000000000000000000000000000000000"));
+    let mut state =
+        CachedState::from(DictStateReader::default().with_nonce(contract_address, max_nonce));
+
+    assert_matches!(
+        state.increment_nonce(contract_address).unwrap_err(),
+        StateError::StarknetApiError(StarknetApiError::OutOfRange { .. })
+    );
+    assert_eq!(state.get_nonce_at(contract_address).unwrap(), max_nonce);
+}
+
+#[rstest]
+fn transactional_nonce_increments(#[values(true, false)] commit: bool) {
+    let contract_address = contract_address!(CONTRACT_ADDRESS);
+    let mut state = CachedState::from(DictStateReader {
+        address_to_nonce: HashMap::from([(contract_address, Nonce(stark_felt!("0x1")))]),
+        ..Default::default()
+    });
+    // A pending (uncommitted to the reader) nonce write in the parent state.
+    state.increment_nonce(contract_address).unwrap();
+
+    let mut transactional_state = CachedState::create_transactional(&mut state);
+    transactional_state.increment_nonce(contract_address).unwrap();
+    transactional_state.increment_nonce(contract_address).unwrap();
+    assert_eq!(
+        transactional_state.get_nonce_at(contract_address).unwrap(),
+        Nonce(stark_felt!("0x4"))
+    );
+
+    let expected_nonce = if commit {
+        transactional_state.commit();
+        Nonce(stark_felt!("0x4"))
+    } else {
+        transactional_state.abort();
+        Nonce(stark_felt!("0x2"))
+    };
+    assert_eq!(state.get_nonce_at(contract_address).unwrap(), expected_nonce);
+    assert_eq!(
+        state.to_state_diff().address_to_nonce,
+        indexmap! {contract_address => expected_nonce}
+    );
+}
+
 #[test]
 fn get_contract_class() {
     // Positive flow.
@@ -154,6 +247,26 @@ fn get_contract_class() {
     );
 }
 
+#[test]
+fn declared_class_sizes() {
+    let mut state = deprecated_create_test_state();
+
+    // Reading a class does not declare it.
+    state.get_compiled_contract_class(class_hash!(TEST_CLASS_HASH)).unwrap();
+    assert!(state.declared_class_sizes().unwrap().is_empty());
+
+    // Declarations in a transactional state count once committed.
+    let class_hash = class_hash!("0x101");
+    let contract_class = FeatureContract::TestContract(CairoVersion::Cairo1).get_class();
+    let mut transactional_state = CachedState::create_transactional(&mut state);
+    transactional_state.set_contract_class(class_hash, contract_class.clone()).unwrap();
+    transactional_state.commit();
+    assert_eq!(
+        state.declared_class_sizes().unwrap(),
+        HashMap::from([(class_hash, contract_class.bytecode_length())])
+    );
+}
+
 #[test]
 fn get_uninitialized_class_hash_value() {
     let state: CachedState<DictStateReader> = CachedState::default();
@@ -184,6 +297,216 @@ fn cannot_set_class_hash_to_uninitialized_contract() {
     );
 }
 
+#[test]
+fn replace_class_hash_of_deployed_contracts() {
+    let (deployed_address, new_address) = (contract_address!("0x100"), contract_address!("0x200"));
+    let (class_hash, replacing_class_hash) = (class_hash!("0x10"), class_hash!("0x20"));
+    let mut state = CachedState::from(
+        DictStateReader::default().with_deployed_contract(deployed_address, class_hash),
+    );
+
+    // Replace the class of a contract deployed before this state.
+    state.replace_class_hash_at(deployed_address, replacing_class_hash).unwrap();
+    // Deploy a new contract, then replace its class.
+    state.set_class_hash_at(new_address, class_hash).unwrap();
+    state.replace_class_hash_at(new_address, replacing_class_hash).unwrap();
+
+    let state_diff = state.to_state_diff();
+    assert_eq!(
+        state_diff.address_to_class_hash,
+        indexmap! {deployed_address => replacing_class_hash, new_address => replacing_class_hash}
+    );
+    // The newly deployed contract is not reported as a replacement.
+    assert_eq!(state_diff.replaced_classes, indexmap! {deployed_address => replacing_class_hash});
+}
+
+#[test]
+fn cannot_replace_class_hash_of_undeployed_contract() {
+    let mut state: CachedState<DictStateReader> = CachedState::default();
+
+    let undeployed_address = contract_address!("0x100");
+    assert_matches!(
+        state.replace_class_hash_at(undeployed_address, class_hash!("0x10")).unwrap_err(),
+        StateError::UndeployedContractClassReplacement(contract_address)
+            if contract_address == undeployed_address
+    );
+    assert!(state.to_state_diff().address_to_class_hash.is_empty());
+}
+
+/// Counts the requests to the wrapped reader; a batch of storage reads is a single request.
+struct CountingStateReader {
+    state: DictStateReader,
+    n_requests: Cell<usize>,
+}
+
+impl CountingStateReader {
+    fn count<T>(&self, result: StateResult<T>) -> StateResult<T> {
+        self.n_requests.set(self.n_requests.get() + 1);
+        result
+    }
+}
+
+impl StateReader for CountingStateReader {
+    fn get_storage_at(
+        &self,
+        contract_address: ContractAddress,
+        key: StorageKey,
+    ) -> StateResult<StarkFelt> {
+        self.count(self.state.get_storage_at(contract_address, key))
+    }
+
+    fn get_storage_at_batch(&self, storage_keys: &[StorageEntry]) -> StateResult<Vec<StarkFelt>> {
+        self.count(self.state.get_storage_at_batch(storage_keys))
+    }
+
+    fn get_nonce_at(&self, contract_address: ContractAddress) -> StateResult<Nonce> {
+        self.count(self.state.get_nonce_at(contract_address))
+    }
+
+    fn get_class_hash_at(&self, contract_address: ContractAddress) -> StateResult<ClassHash> {
+        self.count(self.state.get_class_hash_at(contract_address))
+    }
+
+    fn get_compiled_contract_class(&self, class_hash: ClassHash) -> StateResult<ContractClass> {
+        self.count(self.state.get_compiled_contract_class(class_hash))
+    }
+
+    fn get_compiled_class_hash(&self, class_hash: ClassHash) -> StateResult<CompiledClassHash> {
+        self.count(self.state.get_compiled_class_hash(class_hash))
+    }
+}
+
+#[test]
+fn prefetch() {
+    let contract_address = contract_address!(CONTRACT_ADDRESS);
+    let class_hash = class_hash!(TEST_CLASS_HASH);
+    let storage_keys: Vec<StorageEntry> = ["0x10", "0x11", "0x12"]
+        .into_iter()
+        .map(|key| (contract_address, StorageKey(patricia_key!(key))))
+        .collect();
+    let reader = CountingStateReader {
+        state: DictStateReader::default()
+            .with_storage(contract_address, storage_keys[0].1, stark_felt!(1_u8))
+            .with_deployed_contract(contract_address, class_hash)
+            .with_class(class_hash, get_test_contract_class()),
+        n_requests: Cell::new(0),
+    };
+    let mut state = CachedState::from(reader);
+
+    // A cell already cached is not fetched again.
+    state.get_storage_at(contract_address, storage_keys[1].1).unwrap();
+    state.prefetch(&storage_keys, &[contract_address], &[class_hash]).unwrap();
+    // The read, a storage batch, a nonce, a class hash and a class.
+    assert_eq!(state.state.n_requests.get(), 5);
+
+    assert_eq!(
+        state.get_storage_at_batch(&storage_keys).unwrap(),
+        vec![stark_felt!(1_u8), StarkFelt::ZERO, StarkFelt::ZERO]
+    );
+    assert_eq!(state.get_nonce_at(contract_address).unwrap(), Nonce::default());
+    assert_eq!(state.get_class_hash_at(contract_address).unwrap(), class_hash);
+    assert_eq!(state.get_compiled_contract_class(class_hash).unwrap(), get_test_contract_class());
+    assert_eq!(state.state.n_requests.get(), 5);
+}
+
+#[test]
+fn write_metrics() {
+    let test_contract = FeatureContract::TestContract(CairoVersion::Cairo1);
+    let (contract_address, class_hash) =
+        (test_contract.get_instance_address(0), test_contract.get_class_hash());
+    let (key, other_key) = (StorageKey(patricia_key!(5_u8)), StorageKey(patricia_key!(6_u8)));
+    let mut state = CachedState::from(
+        DictStateReader::default()
+            .with_class(class_hash, test_contract.get_class())
+            .with_deployed_contract(contract_address, class_hash)
+            .with_storage(contract_address, other_key, stark_felt!(3_u8)),
+    );
+
+    // The contract writes the same cell repeatedly; another cell is rewritten with its value.
+    let n_writes = 5_u8;
+    for value in 1..=n_writes {
+        let entry_point_call = CallEntryPoint {
+            calldata: calldata![*key.0.key(), stark_felt!(value)],
+            entry_point_selector: selector_from_name("test_storage_read_write"),
+            ..trivial_external_entry_point_with_address(contract_address)
+        };
+        entry_point_call.execute_directly(&mut state).unwrap();
+    }
+    state.set_storage_at(contract_address, other_key, stark_felt!(3_u8)).unwrap();
+
+    let write_metrics = state.write_metrics(1).unwrap();
+    assert_eq!(
+        write_metrics,
+        WriteMetrics {
+            n_writes: usize::from(n_writes) + 1,
+            n_written_keys: 2,
+            n_rewritten_keys: 1,
+            n_diff_entries: 1,
+            most_written_keys: vec![(
+                StateKey::Storage((contract_address, key)),
+                usize::from(n_writes)
+            )],
+        }
+    );
+    assert_eq!(write_metrics.write_amplification(), Some(6.0));
+
+    let mut empty_state = CachedState::from(DictStateReader::default());
+    assert_eq!(empty_state.write_metrics(1).unwrap().write_amplification(), None);
+}
+
+#[test]
+fn witness_of_entry_point_execution() {
+    let test_contract = FeatureContract::TestContract(CairoVersion::Cairo1);
+    let (contract_address, class_hash) =
+        (test_contract.get_instance_address(0), test_contract.get_class_hash());
+    let read_entry = (contract_address, StorageKey(patricia_key!(5_u8)));
+    let written_entry = (contract_address, StorageKey(patricia_key!(6_u8)));
+    let mut state = CachedState::from(
+        DictStateReader::default()
+            .with_class(class_hash, test_contract.get_class())
+            .with_deployed_contract(contract_address, class_hash)
+            .with_storage(contract_address, read_entry.1, stark_felt!(3_u8)),
+    );
+    assert_eq!(state.witness(), None);
+    state.enable_witness();
+
+    // Read a cell which is then overwritten; and write a cell without reading it.
+    state.get_storage_at(contract_address, read_entry.1).unwrap();
+    for (_, key) in [read_entry, written_entry] {
+        let entry_point_call = CallEntryPoint {
+            calldata: calldata![*key.0.key(), stark_felt!(7_u8)],
+            entry_point_selector: selector_from_name("test_storage_read_write"),
+            ..trivial_external_entry_point_with_address(contract_address)
+        };
+        entry_point_call.execute_directly(&mut state).unwrap();
+    }
+    // Computing the state diff reads the initial value of the write-only cell.
+    state.to_state_diff();
+
+    let witness = state.witness().unwrap();
+    assert_eq!(
+        witness,
+        vec![
+            StateAccess::Storage(read_entry, stark_felt!(3_u8)),
+            StateAccess::ClassHash(contract_address, class_hash),
+            StateAccess::ContractClass(class_hash),
+            StateAccess::Storage(written_entry, StarkFelt::ZERO),
+        ]
+    );
+    assert_eq!(
+        StateReads::from_iter(witness),
+        StateReads {
+            storage: HashMap::from([
+                (read_entry, stark_felt!(3_u8)),
+                (written_entry, StarkFelt::ZERO)
+            ]),
+            class_hashes: HashMap::from([(contract_address, class_hash)]),
+            classes: HashSet::from([class_hash]),
+            ..Default::default()
+        }
+    );
+}
+
 #[test]
 fn cached_state_state_diff_conversion() {
     // This will not appear in the diff, since this mapping is immutable for the current version we
@@ -250,6 +573,7 @@ fn cached_state_state_diff_conversion() {
     // and contract_address_1 was changed but ended up with the original values.
     let expected_state_diff = CommitmentStateDiff {
         address_to_class_hash: IndexMap::from_iter([(contract_address2, new_class_hash)]),
+        replaced_classes: IndexMap::new(),
         storage_updates: IndexMap::from_iter([(contract_address2, indexmap! {key_y => new_value})]),
         class_hash_to_compiled_class_hash: IndexMap::from_iter([(class_hash, compiled_class_hash)]),
         address_to_nonce: IndexMap::from_iter([(contract_address2, Nonce(StarkFelt::from(1_u64)))]),
@@ -258,6 +582,363 @@ fn cached_state_state_diff_conversion() {
     assert_eq!(expected_state_diff, state.to_state_diff());
 }
 
+#[test]
+fn state_diff_json_golden_file() {
+    const STATE_DIFF_FIXTURE: &str = include_str!("../../resources/state_diff_fixture.json");
+    let mut state: CachedState<DictStateReader> = CachedState::default();
+    let (contract_address0, contract_address1) =
+        (contract_address!("0x100"), contract_address!("0x200"));
+    let (key_x, key_y) = (StorageKey(patricia_key!("0x10")), StorageKey(patricia_key!("0x20")));
+
+    // Write out of order, to verify the dump is sorted.
+    state.set_class_hash_at(contract_address1, class_hash!("0x2")).unwrap();
+    state.set_class_hash_at(contract_address0, class_hash!("0x1")).unwrap();
+    state.set_storage_at(contract_address1, key_y, stark_felt!("0x6")).unwrap();
+    state.set_storage_at(contract_address1, key_x, stark_felt!("0x5")).unwrap();
+    state.set_storage_at(contract_address0, key_x, stark_felt!("0x1234")).unwrap();
+    state.increment_nonce(contract_address0).unwrap();
+    state
+        .set_compiled_class_hash(class_hash!("0x2"), CompiledClassHash(stark_felt!("0x22")))
+        .unwrap();
+
+    assert_eq!(state.dump_state_diff_json().unwrap(), STATE_DIFF_FIXTURE.trim_end());
+    let deserialized_state_diff: CommitmentStateDiff =
+        serde_json::from_str(STATE_DIFF_FIXTURE).unwrap();
+    assert_eq!(deserialized_state_diff, state.to_state_diff());
+}
+
+#[test]
+fn savepoints() {
+    let mut state: CachedState<DictStateReader> = CachedState::default();
+    let contract_address = contract_address!(CONTRACT_ADDRESS);
+    let (key_x, key_y) = (StorageKey(patricia_key!("0x10")), StorageKey(patricia_key!("0x20")));
+    let class_hash = class_hash!("0x11");
+    state.set_storage_at(contract_address, key_x, stark_felt!(1_u8)).unwrap();
+
+    let outer_savepoint = state.savepoint();
+    state.set_storage_at(contract_address, key_x, stark_felt!(2_u8)).unwrap();
+    state.increment_nonce(contract_address).unwrap();
+
+    let inner_savepoint = state.savepoint();
+    state.set_storage_at(contract_address, key_x, stark_felt!(3_u8)).unwrap();
+    state.set_storage_at(contract_address, key_y, stark_felt!(4_u8)).unwrap();
+    state.set_class_hash_at(contract_address, class_hash).unwrap();
+    state.increment_nonce(contract_address).unwrap();
+
+    // Partial rollback: only the writes after the inner savepoint are undone.
+    state.rollback_to(inner_savepoint).unwrap();
+    assert_eq!(state.get_storage_at(contract_address, key_x).unwrap(), stark_felt!(2_u8));
+    assert_eq!(state.get_nonce_at(contract_address).unwrap(), Nonce(stark_felt!(1_u8)));
+    assert!(!state.cache.borrow().storage_writes.contains_key(&(contract_address, key_y)));
+    assert!(!state.cache.borrow().class_hash_writes.contains_key(&contract_address));
+
+    // The inner savepoint stays valid after a rollback to it.
+    state.set_storage_at(contract_address, key_y, stark_felt!(5_u8)).unwrap();
+    state.rollback_to(inner_savepoint).unwrap();
+    assert!(!state.cache.borrow().storage_writes.contains_key(&(contract_address, key_y)));
+
+    // Rolling back the outer savepoint restores the writes before it and invalidates the inner.
+    state.rollback_to(outer_savepoint).unwrap();
+    assert_eq!(
+        state.cache.borrow().storage_writes,
+        HashMap::from([((contract_address, key_x), stark_felt!(1_u8))])
+    );
+    assert!(state.cache.borrow().nonce_writes.is_empty());
+    assert_matches!(
+        state.rollback_to(inner_savepoint).unwrap_err(),
+        StateError::InvalidSavepoint(savepoint) if savepoint == inner_savepoint
+    );
+}
+
+#[test]
+fn savepoint_rollback_of_committed_transactional_writes() {
+    let mut state: CachedState<DictStateReader> = CachedState::default();
+    let contract_address = contract_address!(CONTRACT_ADDRESS);
+    let key = StorageKey(patricia_key!("0x10"));
+
+    let savepoint = state.savepoint();
+    let mut transactional_state = CachedState::create_transactional(&mut state);
+    transactional_state.set_storage_at(contract_address, key, stark_felt!(1_u8)).unwrap();
+    transactional_state.commit();
+    assert_eq!(state.get_storage_at(contract_address, key).unwrap(), stark_felt!(1_u8));
+
+    state.rollback_to(savepoint).unwrap();
+    assert!(state.cache.borrow().storage_writes.is_empty());
+    state.release_savepoint(savepoint).unwrap();
+    assert_matches!(state.rollback_to(savepoint), Err(StateError::InvalidSavepoint(_)));
+}
+
+#[test]
+fn savepoint_rollback_of_declared_class() {
+    let mut state: CachedState<DictStateReader> = CachedState::default();
+    let contract_address = contract_address!(CONTRACT_ADDRESS);
+    let key = StorageKey(patricia_key!("0x10"));
+    let class_hash = class_hash!(TEST_CLASS_HASH);
+    state.set_storage_at(contract_address, key, stark_felt!(1_u8)).unwrap();
+    let state_diff = state.to_state_diff();
+
+    let savepoint = state.savepoint();
+    state.set_storage_at(contract_address, key, stark_felt!(2_u8)).unwrap();
+    let mut transactional_state = CachedState::create_transactional(&mut state);
+    transactional_state.set_contract_class(class_hash, get_test_contract_class()).unwrap();
+    transactional_state
+        .set_compiled_class_hash(class_hash, CompiledClassHash(stark_felt!("0x1")))
+        .unwrap();
+    transactional_state.commit();
+    assert_eq!(state.declared_classes().unwrap().len(), 1);
+
+    state.rollback_to(savepoint).unwrap();
+    assert_eq!(state.to_state_diff(), state_diff);
+    assert!(state.declared_classes().unwrap().is_empty());
+    assert_matches!(
+        state.get_compiled_contract_class(class_hash).unwrap_err(),
+        StateError::UndeclaredClassHash(undeclared) if undeclared == class_hash
+    );
+    let write_metrics = state.write_metrics(1).unwrap();
+    assert_eq!((write_metrics.n_writes, write_metrics.n_written_keys), (1, 1));
+}
+
+#[test]
+fn into_state_diff_matches_to_state_diff() {
+    let (deployed_address, new_address) = (contract_address!("0x100"), contract_address!("0x200"));
+    let (key_x, key_y) = (StorageKey(patricia_key!("0x10")), StorageKey(patricia_key!("0x20")));
+    let create_state = || {
+        let mut state = CachedState::from(
+            DictStateReader::default()
+                .with_deployed_contract(deployed_address, class_hash!("0x1"))
+                .with_storage(deployed_address, key_x, stark_felt!(1_u8)),
+        );
+        // Rewrite an initial value, which is not a change.
+        state.set_storage_at(deployed_address, key_x, stark_felt!(1_u8)).unwrap();
+        state.set_storage_at(deployed_address, key_y, stark_felt!(2_u8)).unwrap();
+        state.increment_nonce(deployed_address).unwrap();
+        state.replace_class_hash_at(deployed_address, class_hash!("0x2")).unwrap();
+        state.set_class_hash_at(new_address, class_hash!("0x3")).unwrap();
+        state.set_storage_at(new_address, key_x, stark_felt!(3_u8)).unwrap();
+        state
+            .set_compiled_class_hash(class_hash!("0x3"), CompiledClassHash(stark_felt!(4_u8)))
+            .unwrap();
+        state
+    };
+
+    let state_diff = create_state().to_state_diff();
+    assert_eq!(state_diff.replaced_classes.len(), 1);
+    assert_eq!(create_state().into_state_diff(), state_diff);
+}
+
+/// Records the order of the writes to the wrapped state; fails the write of `failing_key`, and
+/// deployments to addresses that are already deployed.
+#[derive(Default)]
+struct RecordingState {
+    state: CachedState<DictStateReader>,
+    writes: Vec<StateKey>,
+    failing_key: Option<StateKey>,
+}
+
+impl RecordingState {
+    fn record(&mut self, key: StateKey) -> StateResult<()> {
+        if self.failing_key == Some(key) {
+            return Err(StateError::StateReadError("Injected failure.".to_string()));
+        }
+        self.writes.push(key);
+        Ok(())
+    }
+}
+
+impl StateReader for RecordingState {
+    fn get_storage_at(
+        &self,
+        contract_address: ContractAddress,
+        key: StorageKey,
+    ) -> StateResult<StarkFelt> {
+        self.state.get_storage_at(contract_address, key)
+    }
+
+    fn get_nonce_at(&self, contract_address: ContractAddress) -> StateResult<Nonce> {
+        self.state.get_nonce_at(contract_address)
+    }
+
+    fn get_class_hash_at(&self, contract_address: ContractAddress) -> StateResult<ClassHash> {
+        self.state.get_class_hash_at(contract_address)
+    }
+
+    fn get_compiled_contract_class(&self, class_hash: ClassHash) -> StateResult<ContractClass> {
+        self.state.get_compiled_contract_class(class_hash)
+    }
+
+    fn get_compiled_class_hash(&self, class_hash: ClassHash) -> StateResult<CompiledClassHash> {
+        self.state.get_compiled_class_hash(class_hash)
+    }
+}
+
+impl State for RecordingState {
+    fn set_storage_at(
+        &mut self,
+        contract_address: ContractAddress,
+        key: StorageKey,
+        value: StarkFelt,
+    ) -> StateResult<()> {
+        self.record(StateKey::Storage((contract_address, key)))?;
+        self.state.set_storage_at(contract_address, key, value)
+    }
+
+    fn increment_nonce(&mut self, contract_address: ContractAddress) -> StateResult<()> {
+        self.record(StateKey::Nonce(contract_address))?;
+        self.state.increment_nonce(contract_address)
+    }
+
+    fn set_nonce_at(&mut self, contract_address: ContractAddress, nonce: Nonce) -> StateResult<()> {
+        self.record(StateKey::Nonce(contract_address))?;
+        self.state.set_nonce_at(contract_address, nonce)
+    }
+
+    fn set_class_hash_at(
+        &mut self,
+        contract_address: ContractAddress,
+        class_hash: ClassHash,
+    ) -> StateResult<()> {
+        if self.state.get_class_hash_at(contract_address)? != ClassHash::default() {
+            return Err(StateError::UnavailableContractAddress(contract_address));
+        }
+        self.record(StateKey::ClassHash(contract_address))?;
+        self.state.set_class_hash_at(contract_address, class_hash)
+    }
+
+    fn replace_class_hash_at(
+        &mut self,
+        contract_address: ContractAddress,
+        class_hash: ClassHash,
+    ) -> StateResult<()> {
+        self.record(StateKey::ClassHash(contract_address))?;
+        self.state.replace_class_hash_at(contract_address, class_hash)
+    }
+
+    fn set_contract_class(
+        &mut self,
+        class_hash: ClassHash,
+        contract_class: ContractClass,
+    ) -> StateResult<()> {
+        self.record(StateKey::ContractClass(class_hash))?;
+        self.state.set_contract_class(class_hash, contract_class)
+    }
+
+    fn set_compiled_class_hash(
+        &mut self,
+        class_hash: ClassHash,
+        compiled_class_hash: CompiledClassHash,
+    ) -> StateResult<()> {
+        self.record(StateKey::CompiledClassHash(class_hash))?;
+        self.state.set_compiled_class_hash(class_hash, compiled_class_hash)
+    }
+
+    fn add_visited_pcs(&mut self, class_hash: ClassHash, pcs: &HashSet<usize>) {
+        self.state.add_visited_pcs(class_hash, pcs)
+    }
+}
+
+fn create_block_state() -> CachedState<DictStateReader> {
+    let (contract_address0, contract_address1) =
+        (contract_address!("0x100"), contract_address!("0x200"));
+    let (key_x, key_y) = (StorageKey(patricia_key!("0x10")), StorageKey(patricia_key!("0x20")));
+    let mut state: CachedState<DictStateReader> = CachedState::default();
+    state.increment_nonce(contract_address1).unwrap();
+    state.set_storage_at(contract_address1, key_y, stark_felt!(1_u8)).unwrap();
+    state.set_storage_at(contract_address0, key_x, stark_felt!(2_u8)).unwrap();
+    state.set_class_hash_at(contract_address1, class_hash!(TEST_CLASS_HASH)).unwrap();
+    state.set_contract_class(class_hash!(TEST_CLASS_HASH), get_test_contract_class()).unwrap();
+    let compiled_class_hash = CompiledClassHash(stark_felt!(3_u8));
+    state.set_compiled_class_hash(class_hash!(TEST_CLASS_HASH), compiled_class_hash).unwrap();
+    state
+}
+
+#[test]
+fn commit_block() {
+    let mut writer = RecordingState::default();
+    let state_diff = create_block_state().commit_block(&mut writer).unwrap();
+
+    assert_eq!(state_diff, create_block_state().to_state_diff());
+    let (contract_address0, contract_address1) =
+        (contract_address!("0x100"), contract_address!("0x200"));
+    assert_eq!(
+        writer.writes,
+        vec![
+            StateKey::ContractClass(class_hash!(TEST_CLASS_HASH)),
+            StateKey::CompiledClassHash(class_hash!(TEST_CLASS_HASH)),
+            StateKey::ClassHash(contract_address1),
+            StateKey::Storage((contract_address0, StorageKey(patricia_key!("0x10")))),
+            StateKey::Storage((contract_address1, StorageKey(patricia_key!("0x20")))),
+            StateKey::Nonce(contract_address1),
+        ]
+    );
+    assert_eq!(writer.state.to_state_diff(), state_diff);
+    assert_eq!(
+        writer.state.get_compiled_contract_class(class_hash!(TEST_CLASS_HASH)).unwrap(),
+        get_test_contract_class()
+    );
+}
+
+#[test]
+fn commit_block_stops_at_failing_write() {
+    let failing_key =
+        StateKey::Storage((contract_address!("0x100"), StorageKey(patricia_key!("0x10"))));
+    let mut writer = RecordingState { failing_key: Some(failing_key), ..Default::default() };
+
+    assert_matches!(
+        create_block_state().commit_block(&mut writer).unwrap_err(),
+        StateError::CommitFailure { key, .. } if key == failing_key
+    );
+    // The writes preceding the failing one were applied (the writer is left partially written);
+    // the following were not.
+    assert_eq!(writer.writes.len(), 3);
+    assert!(!writer.writes.contains(&StateKey::Nonce(contract_address!("0x200"))));
+}
+
+#[test]
+fn commit_block_with_replaced_class() {
+    let (deployed_address, new_address) = (contract_address!("0x100"), contract_address!("0x200"));
+    let create_reader =
+        || DictStateReader::default().with_deployed_contract(deployed_address, class_hash!("0x1"));
+    let mut state = CachedState::from(create_reader());
+    state.replace_class_hash_at(deployed_address, class_hash!("0x2")).unwrap();
+    state.set_class_hash_at(new_address, class_hash!("0x3")).unwrap();
+
+    let mut writer =
+        RecordingState { state: CachedState::from(create_reader()), ..Default::default() };
+    let state_diff = state.commit_block(&mut writer).unwrap();
+    assert_eq!(state_diff.replaced_classes, indexmap! {deployed_address => class_hash!("0x2")});
+    assert_eq!(
+        writer.writes,
+        vec![StateKey::ClassHash(deployed_address), StateKey::ClassHash(new_address)]
+    );
+    assert_eq!(writer.state.get_class_hash_at(deployed_address).unwrap(), class_hash!("0x2"));
+    assert_eq!(writer.state.get_class_hash_at(new_address).unwrap(), class_hash!("0x3"));
+}
+
+#[test]
+fn commit_block_reports_failing_class_write() {
+    let failing_key = StateKey::ContractClass(class_hash!(TEST_CLASS_HASH));
+    let mut writer = RecordingState { failing_key: Some(failing_key), ..Default::default() };
+
+    assert_matches!(
+        create_block_state().commit_block(&mut writer).unwrap_err(),
+        StateError::CommitFailure { key, .. } if key == failing_key
+    );
+    assert!(writer.writes.is_empty());
+}
+
+#[test]
+fn commit_block_after_moving_classes_to_global_cache() {
+    let mut state = create_block_state();
+    state.move_classes_to_global_cache();
+    let mut writer = RecordingState::default();
+    state.commit_block(&mut writer).unwrap();
+
+    assert_eq!(
+        writer.state.get_compiled_contract_class(class_hash!(TEST_CLASS_HASH)).unwrap(),
+        get_test_contract_class()
+    );
+}
+
 fn create_state_changes_for_test<S: StateReader>(
     state: &mut CachedState<S>,
     sender_address: Option<ContractAddress>,
@@ -378,6 +1059,404 @@ fn test_state_changes_merge(
     );
 }
 
+#[rstest]
+fn transactional_write_of_original_value(#[values(true, false)] read_before_write: bool) {
+    let contract_address = contract_address!(CONTRACT_ADDRESS);
+    let key = StorageKey(patricia_key!("0x10"));
+    let original_value = stark_felt!("0x1");
+    let mut state = CachedState::from(DictStateReader::default().with_storage(
+        contract_address,
+        key,
+        original_value,
+    ));
+
+    let mut transactional_state = CachedState::create_transactional(&mut state);
+    if read_before_write {
+        transactional_state.get_storage_at(contract_address, key).unwrap();
+    }
+    transactional_state.set_storage_at(contract_address, key, stark_felt!("0x2")).unwrap();
+    transactional_state.set_storage_at(contract_address, key, original_value).unwrap();
+    transactional_state.commit();
+
+    // Writing back the original value is not a change.
+    assert!(state.to_state_diff().storage_updates.is_empty());
+}
+
+#[test]
+fn transactional_reads_warm_parent_cache() {
+    let contract_address = contract_address!(CONTRACT_ADDRESS);
+    let key = StorageKey(patricia_key!("0x10"));
+    let value = stark_felt!("0x1");
+    let mut state = CachedState::from(DictStateReader {
+        storage_view: HashMap::from([((contract_address, key), value)]),
+        ..Default::default()
+    });
+
+    let transactional_state = CachedState::create_transactional(&mut state);
+    assert_eq!(transactional_state.get_storage_at(contract_address, key).unwrap(), value);
+    transactional_state.commit();
+
+    // The value read by the child is cached by the parent, and is not re-fetched.
+    assert_eq!(
+        state.cache.borrow().storage_initial_values,
+        HashMap::from([((contract_address, key), value)])
+    );
+    state.state.storage_view.clear();
+    assert_eq!(state.get_storage_at(contract_address, key).unwrap(), value);
+}
+
+#[rstest]
+fn transactional_declare_compiled_class_hash(#[values(true, false)] commit: bool) {
+    let (declared_class_hash, new_class_hash) = (class_hash!("0x10"), class_hash!("0x20"));
+    let declared_compiled_class_hash = CompiledClassHash(stark_felt!("0x11"));
+    let new_compiled_class_hash = CompiledClassHash(stark_felt!("0x21"));
+    let mut state = CachedState::from(
+        DictStateReader::default()
+            .with_compiled_class_hash(declared_class_hash, declared_compiled_class_hash),
+    );
+
+    let mut transactional_state = CachedState::create_transactional(&mut state);
+    assert_eq!(
+        transactional_state.get_compiled_class_hash(new_class_hash).unwrap(),
+        CompiledClassHash::default()
+    );
+    transactional_state.set_compiled_class_hash(new_class_hash, new_compiled_class_hash).unwrap();
+    assert_eq!(
+        transactional_state.get_compiled_class_hash(new_class_hash).unwrap(),
+        new_compiled_class_hash
+    );
+    assert_eq!(
+        transactional_state.get_compiled_class_hash(declared_class_hash).unwrap(),
+        declared_compiled_class_hash
+    );
+    if commit {
+        transactional_state.commit();
+    } else {
+        transactional_state.abort();
+    }
+
+    let (expected_compiled_class_hash, expected_declared_classes) = if commit {
+        (new_compiled_class_hash, indexmap! {new_class_hash => new_compiled_class_hash})
+    } else {
+        (CompiledClassHash::default(), IndexMap::new())
+    };
+    assert_eq!(
+        state.get_compiled_class_hash(new_class_hash).unwrap(),
+        expected_compiled_class_hash
+    );
+    assert_eq!(state.to_state_diff().class_hash_to_compiled_class_hash, expected_declared_classes);
+}
+
+#[rstest]
+fn nested_transactional_states(
+    #[values(true, false)] commit_inner: bool,
+    #[values(true, false)] commit_outer: bool,
+) {
+    let contract_address = contract_address!(CONTRACT_ADDRESS);
+    let (outer_key, inner_key) =
+        (StorageKey(patricia_key!("0x10")), StorageKey(patricia_key!("0x11")));
+    let value = stark_felt!("0x1");
+    let mut state: CachedState<DictStateReader> = CachedState::default();
+
+    let mut outer_state = CachedState::create_transactional(&mut state);
+    outer_state.set_storage_at(contract_address, outer_key, value).unwrap();
+    let mut inner_state = CachedState::create_transactional(&mut outer_state);
+    // The inner state reads the writes of the outer one.
+    assert_eq!(inner_state.get_storage_at(contract_address, outer_key).unwrap(), value);
+    inner_state.set_storage_at(contract_address, inner_key, value).unwrap();
+    if commit_inner {
+        inner_state.commit();
+    } else {
+        inner_state.abort();
+    }
+
+    let expected_inner_value = if commit_inner { value } else { StarkFelt::ZERO };
+    assert_eq!(
+        outer_state.get_storage_at(contract_address, inner_key).unwrap(),
+        expected_inner_value
+    );
+    if commit_outer {
+        outer_state.commit();
+    } else {
+        outer_state.abort();
+    }
+
+    // Changes reach the parent caches only, and only if all states on the way are committed.
+    let (expected_outer_value, expected_inner_value) = if commit_outer {
+        (value, expected_inner_value)
+    } else {
+        (StarkFelt::ZERO, StarkFelt::ZERO)
+    };
+    assert_eq!(state.get_storage_at(contract_address, outer_key).unwrap(), expected_outer_value);
+    assert_eq!(state.get_storage_at(contract_address, inner_key).unwrap(), expected_inner_value);
+    assert!(state.state.storage_view.is_empty());
+}
+
+#[test]
+fn modified_and_accessed_contracts() {
+    let [read_address, written_address, rewritten_address] =
+        [contract_address!("0x100"), contract_address!("0x101"), contract_address!("0x102")];
+    let key = StorageKey(patricia_key!("0x10"));
+    let value = stark_felt!("0x1");
+    let mut state = CachedState::from(DictStateReader {
+        storage_view: HashMap::from([((rewritten_address, key), value)]),
+        ..Default::default()
+    });
+
+    // Reads one contract, writes another, and writes the current value of a third.
+    state.get_storage_at(read_address, key).unwrap();
+    state.set_storage_at(written_address, key, value).unwrap();
+    state.increment_nonce(written_address).unwrap();
+    state.set_storage_at(rewritten_address, key, value).unwrap();
+
+    assert_eq!(state.modified_contracts().unwrap(), HashSet::from([written_address]));
+    assert_eq!(
+        state.accessed_contracts(),
+        HashSet::from([read_address, written_address, rewritten_address])
+    );
+    assert_eq!(
+        state.modified_contracts().unwrap(),
+        state.get_actual_state_changes().unwrap().get_modified_contracts()
+    );
+}
+
+#[test]
+fn initial_reads_mismatches() {
+    let contract_address = contract_address!(CONTRACT_ADDRESS);
+    let (key_x, key_y) = (StorageKey(patricia_key!("0x10")), StorageKey(patricia_key!("0x20")));
+    let class_hash = class_hash!(TEST_CLASS_HASH);
+    let state = CachedState::from(
+        DictStateReader::default()
+            .with_class(class_hash, get_test_contract_class())
+            .with_deployed_contract(contract_address, class_hash)
+            .with_nonce(contract_address, Nonce(stark_felt!(1_u8)))
+            .with_storage(contract_address, key_x, stark_felt!(1_u8))
+            .with_storage(contract_address, key_y, stark_felt!(2_u8)),
+    );
+    state.get_storage_at(contract_address, key_x).unwrap();
+    state.get_storage_at(contract_address, key_y).unwrap();
+    state.get_nonce_at(contract_address).unwrap();
+    state.get_class_hash_at(contract_address).unwrap();
+    state.get_compiled_contract_class(class_hash).unwrap();
+    let reads = state.get_initial_reads();
+
+    // A reader that agrees on the storage at `key_x` and the nonce only.
+    let inconsistent_reader = DictStateReader::default()
+        .with_deployed_contract(contract_address, class_hash!("0x1"))
+        .with_nonce(contract_address, Nonce(stark_felt!(1_u8)))
+        .with_storage(contract_address, key_x, stark_felt!(1_u8))
+        .with_storage(contract_address, key_y, stark_felt!(3_u8));
+    let mismatches: HashSet<_> =
+        reads.mismatches(&inconsistent_reader).unwrap().into_iter().collect();
+    assert_eq!(
+        mismatches,
+        HashSet::from([
+            ReadMismatch::Value {
+                key: StateKey::Storage((contract_address, key_y)),
+                read: stark_felt!(2_u8),
+                expected: stark_felt!(3_u8),
+            },
+            ReadMismatch::Value {
+                key: StateKey::ClassHash(contract_address),
+                read: class_hash.0,
+                expected: stark_felt!(1_u8),
+            },
+            ReadMismatch::UndeclaredClass(class_hash),
+        ])
+    );
+    assert!(reads.mismatches(&state.state).unwrap().is_empty());
+}
+
+/// Executes a transaction that reads `read_key` and writes (a different value) to `write_key` of
+/// the given contract, and increments the nonce of the given account; returns its read set and its
+/// state changes.
+fn read_and_write_storage(
+    state: &mut CachedState<DictStateReader>,
+    contract_address: ContractAddress,
+    account_address: ContractAddress,
+    (read_key, write_key): (StorageKey, StorageKey),
+) -> (StateReads, StateChanges) {
+    let mut transactional_state = CachedState::create_transactional(state);
+    let value = transactional_state.get_storage_at(contract_address, read_key).unwrap();
+    let new_value = stark_felt!(u64::try_from(value).unwrap() + 1);
+    transactional_state.set_storage_at(contract_address, write_key, new_value).unwrap();
+    transactional_state.increment_nonce(account_address).unwrap();
+
+    let reads = transactional_state.get_initial_reads();
+    let changes = transactional_state.get_actual_state_changes().unwrap();
+    transactional_state.abort();
+    (reads, changes)
+}
+
+#[rstest]
+#[case::disjoint_keys(("0x10", "0x11"), ("0x20", "0x21"), None)]
+#[case::read_after_write(("0x10", "0x11"), ("0x11", "0x21"), Some("0x11"))]
+#[case::write_after_read(("0x10", "0x11"), ("0x20", "0x10"), None)]
+fn test_read_set_conflicts(
+    #[case] tx1_keys: (&str, &str),
+    #[case] tx2_keys: (&str, &str),
+    #[case] expected_conflicting_key: Option<&str>,
+) {
+    let contract_address = contract_address!(CONTRACT_ADDRESS);
+    let (account1, account2) = (contract_address!("0x201"), contract_address!("0x202"));
+    let storage_key = |key: &str| StorageKey(patricia_key!(key));
+    let mut state = CachedState::from(DictStateReader::default().with_storage(
+        contract_address,
+        storage_key("0x10"),
+        stark_felt!(7_u8),
+    ));
+
+    // Both transactions execute on the same (block) state.
+    let (tx1_reads, tx1_changes) = read_and_write_storage(
+        &mut state,
+        contract_address,
+        account1,
+        (storage_key(tx1_keys.0), storage_key(tx1_keys.1)),
+    );
+    let (tx2_reads, _) = read_and_write_storage(
+        &mut state,
+        contract_address,
+        account2,
+        (storage_key(tx2_keys.0), storage_key(tx2_keys.1)),
+    );
+
+    // Reads are recorded with the observed values.
+    assert_eq!(
+        tx1_reads.storage.get(&(contract_address, storage_key(tx1_keys.0))),
+        Some(&stark_felt!(7_u8))
+    );
+
+    // Validate the second transaction against the first, which precedes it.
+    let expected_conflicts: HashSet<StateKey> = expected_conflicting_key
+        .map(|key| StateKey::Storage((contract_address, storage_key(key))))
+        .into_iter()
+        .collect();
+    assert_eq!(tx2_reads.conflicts_with(&tx1_changes), expected_conflicts);
+}
+
+#[test]
+fn reader_shared_across_threads() {
+    let contract_address = contract_address!(CONTRACT_ADDRESS);
+    let key = StorageKey(patricia_key!("0x10"));
+    let reader =
+        Arc::new(DictStateReader::default().with_storage(contract_address, key, stark_felt!(1_u8)));
+
+    // Each thread executes on a state of its own, on top of the same reader.
+    thread::scope(|scope| {
+        for value in [2_u8, 3_u8] {
+            let reader = Arc::clone(&reader);
+            scope.spawn(move || {
+                let mut state = CachedState::from(reader);
+                assert_eq!(state.get_storage_at(contract_address, key).unwrap(), stark_felt!(1_u8));
+                state.set_storage_at(contract_address, key, stark_felt!(value)).unwrap();
+                assert_eq!(
+                    state.get_storage_at(contract_address, key).unwrap(),
+                    stark_felt!(value)
+                );
+            });
+        }
+    });
+    assert_eq!(reader.get_storage_at(contract_address, key).unwrap(), stark_felt!(1_u8));
+}
+
+#[cfg(feature = "concurrency")]
+#[test]
+fn states_over_sync_reader_across_threads() {
+    fn assert_send<T: Send>() {}
+    fn assert_send_and_sync<T: Send + Sync>() {}
+    fn assert_sync_reader<S: SyncStateReader>() {}
+    assert_send::<CachedState<Arc<DictStateReader>>>();
+    assert_send_and_sync::<ContractClass>();
+    assert_sync_reader::<Arc<DictStateReader>>();
+
+    let class_hash = class_hash!(TEST_CLASS_HASH);
+    let contract_class = get_test_contract_class();
+    let base_state = CachedState::from(Arc::new(
+        DictStateReader::default().with_class(class_hash, contract_class.clone()),
+    ));
+
+    // Each thread reads through a fork of the same base state; classes are shared, not copied.
+    thread::scope(|scope| {
+        for _ in 0..2 {
+            let state = base_state.fork();
+            let contract_class = &contract_class;
+            scope.spawn(move || {
+                let ContractClass::V0(read_class) =
+                    state.get_compiled_contract_class(class_hash).unwrap()
+                else {
+                    panic!("Expected a V0 class.");
+                };
+                let ContractClass::V0(expected_class) = contract_class else {
+                    panic!("Expected a V0 class.");
+                };
+                assert!(Arc::ptr_eq(&read_class.0, &expected_class.0));
+            });
+        }
+    });
+}
+
+#[test]
+fn fee_token_balance_exceeding_low_word() {
+    let (account_address, fee_token_address) =
+        (contract_address!("0x100"), contract_address!("0x1001"));
+    let low_key = get_fee_token_var_address(account_address);
+    let high_key = next_storage_key(&low_key).unwrap();
+    let state = CachedState::from(
+        DictStateReader::default()
+            .with_storage(fee_token_address, low_key, stark_felt!(u128::MAX))
+            .with_storage(fee_token_address, high_key, stark_felt!(1_u8)),
+    );
+
+    assert_eq!(
+        state.get_fee_token_balance(account_address, fee_token_address).unwrap(),
+        (stark_felt!(u128::MAX), stark_felt!(1_u8))
+    );
+    // 2^128 + (2^128 - 1).
+    assert_eq!(
+        state.get_fee_token_balance_as_biguint(account_address, fee_token_address).unwrap(),
+        (BigUint::from(1_u8) << 129) - 1_u8
+    );
+}
+
+#[test]
+fn fork() {
+    let contract_address = contract_address!(CONTRACT_ADDRESS);
+    let key = StorageKey(patricia_key!("0x10"));
+    let class_hash = class_hash!(TEST_CLASS_HASH);
+    let mut state = CachedState::from(Arc::new(
+        DictStateReader::default().with_class(class_hash, get_test_contract_class()),
+    ));
+    state.set_storage_at(contract_address, key, stark_felt!(1_u8)).unwrap();
+    let original_class = state.get_compiled_contract_class(class_hash).unwrap();
+
+    let mut forked_state = state.fork();
+    forked_state.set_storage_at(contract_address, key, stark_felt!(2_u8)).unwrap();
+    forked_state.increment_nonce(contract_address).unwrap();
+    let new_class_hash = class_hash!("0x101");
+    forked_state.set_contract_class(new_class_hash, get_test_contract_class()).unwrap();
+    let compiled_class_hash = CompiledClassHash(stark_felt!(1_u8));
+    forked_state.set_compiled_class_hash(new_class_hash, compiled_class_hash).unwrap();
+
+    // The changes of the fork do not leak into the original.
+    assert_eq!(forked_state.get_storage_at(contract_address, key).unwrap(), stark_felt!(2_u8));
+    assert_eq!(state.get_storage_at(contract_address, key).unwrap(), stark_felt!(1_u8));
+    assert_eq!(state.get_nonce_at(contract_address).unwrap(), Nonce::default());
+    assert!(state.get_compiled_contract_class(new_class_hash).is_err());
+    assert_eq!(
+        state.get_compiled_class_hash(new_class_hash).unwrap(),
+        CompiledClassHash::default()
+    );
+    assert!(state.declared_class_sizes().unwrap().is_empty());
+
+    // Cached classes are shared with the fork.
+    let (ContractClass::V0(original_class), ContractClass::V0(forked_class)) =
+        (original_class, forked_state.get_compiled_contract_class(class_hash).unwrap())
+    else {
+        panic!("Expected Cairo 0 classes.");
+    };
+    assert!(Arc::ptr_eq(&original_class.0, &forked_class.0));
+}
+
 #[test]
 fn global_contract_cache_is_used() {
     // Initialize the global cache with a single class, and initialize an empty state with this
@@ -403,6 +1482,206 @@ fn global_contract_cache_is_used() {
     assert_eq!(state.get_compiled_contract_class(class_hash).unwrap(), contract_class);
     assert_eq!(global_cache.lock().cache_hits().unwrap(), 1);
     assert_eq!(global_cache.lock().cache_size(), 1);
+    // The class was never read from the underlying state.
+    assert_eq!(state.stats().contract_class, CacheCounts { hits: 2, misses: 0 });
+}
+
+#[test]
+fn cache_stats() {
+    let contract_address = contract_address!(CONTRACT_ADDRESS);
+    let class_hash = class_hash!(TEST_CLASS_HASH);
+    let (read_key, written_key) =
+        (StorageKey(patricia_key!("0x10")), StorageKey(patricia_key!("0x11")));
+    let mut state = CachedState::from(
+        DictStateReader::default()
+            .with_storage(contract_address, read_key, stark_felt!(1_u8))
+            .with_deployed_contract(contract_address, class_hash)
+            .with_class(class_hash, get_test_contract_class()),
+    );
+
+    // Storage: a read, a repeated read and a read of a written cell.
+    state.get_storage_at(contract_address, read_key).unwrap();
+    state.get_storage_at(contract_address, read_key).unwrap();
+    state.set_storage_at(contract_address, written_key, stark_felt!(2_u8)).unwrap();
+    state.get_storage_at(contract_address, written_key).unwrap();
+    // Nonce: incrementing reads the nonce.
+    state.increment_nonce(contract_address).unwrap();
+    state.get_nonce_at(contract_address).unwrap();
+    // Class hash and class.
+    let class_hash = state.get_class_hash_at(contract_address).unwrap();
+    state.get_compiled_contract_class(class_hash).unwrap();
+    state.get_compiled_contract_class(class_hash).unwrap();
+    state.get_compiled_class_hash(class_hash).unwrap();
+
+    assert_eq!(
+        state.stats(),
+        CacheStats {
+            storage: CacheCounts { hits: 2, misses: 1 },
+            nonce: CacheCounts { hits: 1, misses: 1 },
+            class_hash: CacheCounts { hits: 0, misses: 1 },
+            compiled_class_hash: CacheCounts { hits: 0, misses: 1 },
+            contract_class: CacheCounts { hits: 1, misses: 1 },
+        }
+    );
+
+    // Resetting the statistics does not clear the caches.
+    state.reset_stats();
+    assert_eq!(state.stats(), CacheStats::default());
+    state.get_storage_at(contract_address, read_key).unwrap();
+    assert_eq!(state.stats().storage, CacheCounts { hits: 1, misses: 0 });
+}
+
+#[test]
+fn global_contract_cache_is_shared_across_states() {
+    let global_cache = GlobalContractCache::new(GLOBAL_CONTRACT_CACHE_SIZE_FOR_TEST);
+    let class_hash = class_hash!(TEST_CLASS_HASH);
+    let contract_class = get_test_contract_class();
+
+    // A class read from the reader of one state is moved to the global cache.
+    let mut state = CachedState::new(
+        DictStateReader {
+            class_hash_to_class: HashMap::from([(class_hash, contract_class.clone())]),
+            ..Default::default()
+        },
+        global_cache.clone(),
+    );
+    assert_eq!(state.get_compiled_contract_class(class_hash).unwrap(), contract_class);
+    assert_eq!(global_cache.lock().cache_size(), 0);
+    state.move_classes_to_global_cache();
+
+    // Another state reads the class without reaching its (empty) reader.
+    let state = CachedState::new(DictStateReader::default(), global_cache.clone());
+    assert_eq!(state.get_compiled_contract_class(class_hash).unwrap(), contract_class);
+    assert_eq!(global_cache.lock().cache_hits().unwrap(), 1);
+}
+
+#[test]
+fn global_contract_cache_eviction_and_invalidation() {
+    let global_cache = GlobalContractCache::new(2);
+    let [class_hash0, class_hash1, class_hash2] =
+        [class_hash!("0x10"), class_hash!("0x11"), class_hash!("0x12")];
+    let contract_class = get_test_contract_class();
+    let is_cached = |class_hash| {
+        let state = CachedState::new(DictStateReader::default(), global_cache.clone());
+        state.get_compiled_contract_class(class_hash).is_ok()
+    };
+
+    // The least recently used class is evicted once the capacity is exceeded.
+    global_cache.lock().cache_set(class_hash0, contract_class.clone());
+    global_cache.lock().cache_set(class_hash1, contract_class.clone());
+    assert!(is_cached(class_hash0));
+    global_cache.lock().cache_set(class_hash2, contract_class.clone());
+    assert!(!is_cached(class_hash1));
+    assert!(is_cached(class_hash0));
+    assert!(is_cached(class_hash2));
+
+    // An invalidated class is no longer served.
+    assert_eq!(global_cache.invalidate(class_hash0), Some(contract_class));
+    assert_eq!(global_cache.invalidate(class_hash0), None);
+    assert!(!is_cached(class_hash0));
+    assert!(is_cached(class_hash2));
+}
+
+#[test]
+fn global_contract_cache_pinning() {
+    let [class_hash0, class_hash1, class_hash2] =
+        [class_hash!("0x10"), class_hash!("0x11"), class_hash!("0x12")];
+    let global_cache = GlobalContractCache::with_config(CacheConfig {
+        pinned: vec![class_hash0],
+        ..CacheConfig::with_max_entries(1)
+    });
+    let contract_class = get_test_contract_class();
+
+    // Pinned classes are never evicted, nor counted against the bounds.
+    for class_hash in [class_hash0, class_hash1, class_hash2] {
+        global_cache.lock().cache_set(class_hash, contract_class.clone());
+    }
+    assert_eq!(global_cache.lock().cache_size(), 2);
+    assert_eq!(global_cache.lock().eviction_order(), vec![class_hash2]);
+    assert!(global_cache.lock().cache_get(&class_hash0).is_some());
+
+    // Yet they may be explicitly evicted; e.g., on an upgrade.
+    assert_eq!(global_cache.invalidate(class_hash0), Some(contract_class));
+    assert!(global_cache.lock().cache_get(&class_hash0).is_none());
+}
+
+#[test]
+fn global_contract_cache_byte_bound() {
+    let [class_hash0, class_hash1, class_hash2] =
+        [class_hash!("0x10"), class_hash!("0x11"), class_hash!("0x12")];
+    let contract_class = get_test_contract_class();
+    let class_size = ContractClassLRUCache::estimated_size(&contract_class);
+    let global_cache = GlobalContractCache::with_config(CacheConfig {
+        max_bytes: 2 * class_size,
+        ..CacheConfig::with_max_entries(usize::MAX)
+    });
+
+    // Eviction is by recency of use, which is deterministic.
+    global_cache.lock().cache_set(class_hash0, contract_class.clone());
+    global_cache.lock().cache_set(class_hash1, contract_class.clone());
+    assert!(global_cache.lock().cache_get(&class_hash0).is_some());
+    assert_eq!(global_cache.lock().eviction_order(), vec![class_hash1, class_hash0]);
+
+    // The least recently used class is evicted once the byte bound is exceeded.
+    global_cache.lock().cache_set(class_hash2, contract_class);
+    assert_eq!(global_cache.lock().eviction_order(), vec![class_hash0, class_hash2]);
+}
+
+#[test]
+fn global_contract_cache_warm_up() {
+    let [declared_class_hash, undeclared_class_hash, cached_class_hash] =
+        [class_hash!("0x10"), class_hash!("0x11"), class_hash!("0x12")];
+    let contract_class = get_test_contract_class();
+    let reader = CountingStateReader {
+        state: DictStateReader::default().with_class(declared_class_hash, contract_class.clone()),
+        n_requests: Cell::default(),
+    };
+    let global_cache = GlobalContractCache::new(GLOBAL_CONTRACT_CACHE_SIZE_FOR_TEST);
+    global_cache.lock().cache_set(cached_class_hash, contract_class.clone());
+
+    // Classes that fail to be read are reported, without stopping the others.
+    let results = global_cache
+        .warm_up(&reader, &[undeclared_class_hash, declared_class_hash, cached_class_hash]);
+    assert_eq!(results.len(), 3);
+    assert_matches!(
+        &results[0],
+        (class_hash, Err(StateError::UndeclaredClassHash(undeclared)))
+            if *class_hash == undeclared_class_hash && *undeclared == undeclared_class_hash
+    );
+    assert_matches!(results[1], (class_hash, Ok(())) if class_hash == declared_class_hash);
+    assert_matches!(results[2], (class_hash, Ok(())) if class_hash == cached_class_hash);
+
+    // Cached classes are not read again.
+    assert_eq!(reader.n_requests.get(), 2);
+    assert_eq!(global_cache.lock().cache_get(&declared_class_hash), Some(&contract_class));
+    assert!(!global_cache.lock().contains(&undeclared_class_hash));
+}
+
+#[cfg(feature = "concurrency")]
+#[test]
+fn global_contract_cache_par_warm_up() {
+    let class_hashes: Vec<ClassHash> = (0x10_u8..0x20).map(|i| class_hash!(i)).collect();
+    let contract_class = get_test_contract_class();
+    let reader = class_hashes[..8].iter().fold(DictStateReader::default(), |reader, &hash| {
+        reader.with_class(hash, contract_class.clone())
+    });
+    let global_cache = GlobalContractCache::new(GLOBAL_CONTRACT_CACHE_SIZE_FOR_TEST);
+
+    // The results follow the order of the class hashes, however they were read.
+    let results = global_cache.par_warm_up(&reader, &class_hashes);
+    let result_class_hashes: Vec<ClassHash> = results.iter().map(|(hash, _)| *hash).collect();
+    assert_eq!(result_class_hashes, class_hashes);
+    for (class_hash, result) in &results[..8] {
+        assert_matches!(result, Ok(()));
+        assert_eq!(global_cache.lock().cache_get(class_hash), Some(&contract_class));
+    }
+    for (class_hash, result) in &results[8..] {
+        assert_matches!(
+            result,
+            Err(StateError::UndeclaredClassHash(undeclared)) if undeclared == class_hash
+        );
+        assert!(!global_cache.lock().contains(class_hash));
+    }
 }
 
 #[test]
@@ -555,3 +1834,110 @@ fn test_state_changes_keys() {
         }
     )
 }
+
+#[rstest]
+fn blind_writes_of_underlying_values(#[values(true, false)] changed: bool) {
+    let contract_address = contract_address!(CONTRACT_ADDRESS);
+    let key = StorageKey(patricia_key!("0x10"));
+    let (value, class_hash, nonce) =
+        (stark_felt!(1_u8), class_hash!("0x10"), Nonce(stark_felt!(2_u8)));
+    let mut state = CachedState::from(
+        DictStateReader::default()
+            .with_storage(contract_address, key, value)
+            .with_deployed_contract(contract_address, class_hash)
+            .with_nonce(contract_address, nonce),
+    );
+
+    // Write (without reading first) either the underlying values or different ones.
+    let (new_value, new_class_hash, new_nonce) = if changed {
+        (stark_felt!(3_u8), class_hash!("0x11"), Nonce(stark_felt!(4_u8)))
+    } else {
+        (value, class_hash, nonce)
+    };
+    state.set_storage_at(contract_address, key, new_value).unwrap();
+    state.set_class_hash_at(contract_address, new_class_hash).unwrap();
+    state.set_nonce_at(contract_address, new_nonce).unwrap();
+
+    let state_changes = state.get_actual_state_changes().unwrap();
+    let state_diff = state.to_state_diff();
+    if changed {
+        assert_eq!(
+            state_diff.storage_updates,
+            indexmap! {contract_address => indexmap! {key => new_value}}
+        );
+        assert_eq!(
+            state_diff.address_to_class_hash,
+            indexmap! {contract_address => new_class_hash}
+        );
+        assert_eq!(state_diff.address_to_nonce, indexmap! {contract_address => new_nonce});
+        assert_eq!(state_changes.get_modified_contracts(), HashSet::from([contract_address]));
+    } else {
+        assert!(state_diff.storage_updates.is_empty());
+        assert!(state_diff.address_to_class_hash.is_empty());
+        assert!(state_diff.address_to_nonce.is_empty());
+        assert_eq!(state_changes, StateChanges::default());
+    }
+}
+
+#[test]
+fn test_pending_write_counts_exclude_no_op_writes() {
+    let (contract_address0, contract_address1, contract_address2) =
+        (contract_address!("0x100"), contract_address!("0x200"), contract_address!("0x300"));
+    let key = StorageKey(patricia_key!("0x10"));
+    let initial_value = stark_felt!("0x1");
+    let mut state = CachedState::from(DictStateReader {
+        storage_view: HashMap::from([
+            ((contract_address1, key), initial_value),
+            ((contract_address2, key), initial_value),
+        ]),
+        ..Default::default()
+    });
+
+    // A contract that only had its nonce bumped.
+    state.increment_nonce(contract_address0).unwrap();
+    // Three writes to the same key count as a single update.
+    for value in ["0x2", "0x3", "0x4"] {
+        state.set_storage_at(contract_address1, key, stark_felt!(value)).unwrap();
+    }
+    // Writing back the initial value is not an update.
+    state.set_storage_at(contract_address2, key, stark_felt!("0x5")).unwrap();
+    state.set_storage_at(contract_address2, key, initial_value).unwrap();
+
+    assert_eq!(
+        state.pending_write_counts().unwrap(),
+        StateChangesCount {
+            n_storage_updates: 1,
+            n_class_hash_updates: 0,
+            n_compiled_class_hash_updates: 0,
+            n_modified_contracts: 2,
+        }
+    );
+}
+
+#[test]
+fn test_pending_write_counts() {
+    let mut state: CachedState<DictStateReader> = CachedState::default();
+    let mut transactional_state = CachedState::create_transactional(&mut state);
+    let fee_token_address = contract_address!("0x17");
+    let state_changes =
+        create_state_changes_for_test(&mut transactional_state, None, fee_token_address);
+    let pending_counts = transactional_state.pending_write_counts().unwrap();
+    assert_eq!(pending_counts, state_changes.into_keys().count());
+    transactional_state.commit();
+
+    let state_diff = state.to_state_diff();
+    let modified_contracts: HashSet<ContractAddress> = state_diff
+        .address_to_class_hash
+        .keys()
+        .chain(state_diff.address_to_nonce.keys())
+        .chain(state_diff.storage_updates.keys())
+        .copied()
+        .collect();
+    let expected_counts = StateChangesCount {
+        n_storage_updates: state_diff.storage_updates.values().map(IndexMap::len).sum(),
+        n_class_hash_updates: state_diff.address_to_class_hash.len(),
+        n_compiled_class_hash_updates: state_diff.class_hash_to_compiled_class_hash.len(),
+        n_modified_contracts: modified_contracts.len(),
+    };
+    assert_eq!(pending_counts, expected_counts);
+}
@@ -0,0 +1,200 @@
+use std::collections::BTreeMap;
+use std::fmt::{self, Display};
+
+use indexmap::IndexMap;
+use starknet_api::core::{ClassHash, CompiledClassHash, ContractAddress, Nonce};
+use starknet_api::hash::StarkFelt;
+
+use crate::state::cached_state::{CommitmentStateDiff, StorageEntry};
+
+#[cfg(test)]
+#[path = "diff_test.rs"]
+mod test;
+
+/// The entries of a mapping of a state diff that differ from the expected ones, sorted by key.
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct MappingMismatch<K, V> {
+    /// Expected entries that are absent.
+    pub missing: Vec<(K, V)>,
+    /// Entries that are not expected.
+    pub extra: Vec<(K, V)>,
+    /// Entries of different values; as (key, expected value, actual value).
+    pub changed: Vec<(K, V, V)>,
+}
+
+impl<K: Copy + Ord, V: Copy + Eq> MappingMismatch<K, V> {
+    fn new(
+        expected: impl IntoIterator<Item = (K, V)>,
+        actual: impl IntoIterator<Item = (K, V)>,
+    ) -> Self {
+        let expected: BTreeMap<K, V> = expected.into_iter().collect();
+        let mut actual: BTreeMap<K, V> = actual.into_iter().collect();
+
+        let (mut missing, mut changed) = (Vec::new(), Vec::new());
+        for (key, expected_value) in expected {
+            match actual.remove(&key) {
+                None => missing.push((key, expected_value)),
+                Some(actual_value) if actual_value != expected_value => {
+                    changed.push((key, expected_value, actual_value))
+                }
+                Some(_) => {}
+            }
+        }
+
+        Self { missing, extra: actual.into_iter().collect(), changed }
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.missing.is_empty() && self.extra.is_empty() && self.changed.is_empty()
+    }
+}
+
+/// The differences of a state diff from the expected one, per mapping; ordering is ignored.
+/// Displayed as a report of the mismatching entries, one per line, in hex.
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct StateDiffMismatch {
+    pub address_to_class_hash: MappingMismatch<ContractAddress, ClassHash>,
+    pub replaced_classes: MappingMismatch<ContractAddress, ClassHash>,
+    pub address_to_nonce: MappingMismatch<ContractAddress, Nonce>,
+    pub storage_updates: MappingMismatch<StorageEntry, StarkFelt>,
+    pub class_hash_to_compiled_class_hash: MappingMismatch<ClassHash, CompiledClassHash>,
+}
+
+impl StateDiffMismatch {
+    pub fn is_empty(&self) -> bool {
+        self.address_to_class_hash.is_empty()
+            && self.replaced_classes.is_empty()
+            && self.address_to_nonce.is_empty()
+            && self.storage_updates.is_empty()
+            && self.class_hash_to_compiled_class_hash.is_empty()
+    }
+}
+
+/// Compares the given state diffs; e.g., of a re-executed block to the one recorded on chain.
+pub fn diff_state_diffs(
+    expected: &CommitmentStateDiff,
+    actual: &CommitmentStateDiff,
+) -> StateDiffMismatch {
+    fn storage_entries(
+        state_diff: &CommitmentStateDiff,
+    ) -> impl Iterator<Item = (StorageEntry, StarkFelt)> + '_ {
+        state_diff.storage_updates.iter().flat_map(|(&address, storage)| {
+            storage.iter().map(move |(&key, &value)| ((address, key), value))
+        })
+    }
+
+    fn entries<K: Copy, V: Copy>(mapping: &IndexMap<K, V>) -> impl Iterator<Item = (K, V)> + '_ {
+        mapping.iter().map(|(&key, &value)| (key, value))
+    }
+
+    StateDiffMismatch {
+        address_to_class_hash: MappingMismatch::new(
+            entries(&expected.address_to_class_hash),
+            entries(&actual.address_to_class_hash),
+        ),
+        replaced_classes: MappingMismatch::new(
+            entries(&expected.replaced_classes),
+            entries(&actual.replaced_classes),
+        ),
+        address_to_nonce: MappingMismatch::new(
+            entries(&expected.address_to_nonce),
+            entries(&actual.address_to_nonce),
+        ),
+        storage_updates: MappingMismatch::new(storage_entries(expected), storage_entries(actual)),
+        class_hash_to_compiled_class_hash: MappingMismatch::new(
+            entries(&expected.class_hash_to_compiled_class_hash),
+            entries(&actual.class_hash_to_compiled_class_hash),
+        ),
+    }
+}
+
+/// Formats the keys and values of state diffs in hex.
+trait HexDisplay {
+    fn fmt_hex(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result;
+}
+
+impl HexDisplay for StarkFelt {
+    fn fmt_hex(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{self}")
+    }
+}
+
+impl HexDisplay for ContractAddress {
+    fn fmt_hex(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        self.0.key().fmt_hex(f)
+    }
+}
+
+impl HexDisplay for ClassHash {
+    fn fmt_hex(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        self.0.fmt_hex(f)
+    }
+}
+
+impl HexDisplay for CompiledClassHash {
+    fn fmt_hex(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        self.0.fmt_hex(f)
+    }
+}
+
+impl HexDisplay for Nonce {
+    fn fmt_hex(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        self.0.fmt_hex(f)
+    }
+}
+
+impl HexDisplay for StorageEntry {
+    fn fmt_hex(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let (address, key) = self;
+        address.fmt_hex(f)?;
+        write!(f, "[")?;
+        key.0.key().fmt_hex(f)?;
+        write!(f, "]")
+    }
+}
+
+struct Hex<'a, T>(&'a T);
+
+impl<T: HexDisplay> Display for Hex<'_, T> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        self.0.fmt_hex(f)
+    }
+}
+
+fn fmt_mapping_mismatch<K: HexDisplay, V: HexDisplay>(
+    f: &mut fmt::Formatter<'_>,
+    mapping_name: &str,
+    mismatch: &MappingMismatch<K, V>,
+) -> fmt::Result {
+    for (key, value) in &mismatch.missing {
+        writeln!(f, "{mapping_name}: missing {}: {}", Hex(key), Hex(value))?;
+    }
+    for (key, value) in &mismatch.extra {
+        writeln!(f, "{mapping_name}: extra {}: {}", Hex(key), Hex(value))?;
+    }
+    for (key, expected_value, actual_value) in &mismatch.changed {
+        writeln!(
+            f,
+            "{mapping_name}: changed {}: expected {}, actual {}",
+            Hex(key),
+            Hex(expected_value),
+            Hex(actual_value)
+        )?;
+    }
+
+    Ok(())
+}
+
+impl Display for StateDiffMismatch {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        fmt_mapping_mismatch(f, "address_to_class_hash", &self.address_to_class_hash)?;
+        fmt_mapping_mismatch(f, "replaced_classes", &self.replaced_classes)?;
+        fmt_mapping_mismatch(f, "address_to_nonce", &self.address_to_nonce)?;
+        fmt_mapping_mismatch(f, "storage_updates", &self.storage_updates)?;
+        fmt_mapping_mismatch(
+            f,
+            "class_hash_to_compiled_class_hash",
+            &self.class_hash_to_compiled_class_hash,
+        )
+    }
+}
@@ -0,0 +1,147 @@
+use indexmap::{indexmap, IndexMap};
+use pretty_assertions::assert_eq;
+use starknet_api::core::{ClassHash, CompiledClassHash, ContractAddress, Nonce, PatriciaKey};
+use starknet_api::hash::{StarkFelt, StarkHash};
+use starknet_api::state::StorageKey;
+use starknet_api::{class_hash, contract_address, patricia_key, stark_felt};
+
+use crate::state::cached_state::CommitmentStateDiff;
+use crate::state::diff::{diff_state_diffs, MappingMismatch};
+
+fn state_diff() -> CommitmentStateDiff {
+    CommitmentStateDiff {
+        address_to_class_hash: indexmap! {
+            contract_address!("0x100") => class_hash!("0x10"),
+            contract_address!("0x200") => class_hash!("0x20"),
+        },
+        replaced_classes: indexmap! { contract_address!("0x200") => class_hash!("0x20") },
+        address_to_nonce: indexmap! { contract_address!("0x100") => Nonce(stark_felt!(1_u8)) },
+        storage_updates: indexmap! {
+            contract_address!("0x100") => indexmap! {
+                StorageKey(patricia_key!("0x1")) => stark_felt!(1_u8),
+                StorageKey(patricia_key!("0x2")) => stark_felt!(2_u8),
+            },
+            contract_address!("0x200") => indexmap! {
+                StorageKey(patricia_key!("0x1")) => stark_felt!(3_u8),
+            },
+        },
+        class_hash_to_compiled_class_hash: indexmap! {
+            class_hash!("0x10") => CompiledClassHash(stark_felt!("0x11")),
+        },
+    }
+}
+
+#[test]
+fn equal_state_diffs_of_different_orderings() {
+    let expected = state_diff();
+    let mut actual = state_diff();
+    actual.address_to_class_hash.reverse();
+    actual.storage_updates.reverse();
+    actual.storage_updates.values_mut().for_each(IndexMap::reverse);
+
+    let mismatch = diff_state_diffs(&expected, &actual);
+    assert!(mismatch.is_empty());
+    assert_eq!(mismatch.to_string(), "");
+}
+
+#[test]
+fn missing_entries() {
+    let expected = state_diff();
+    let mut actual = state_diff();
+    actual.address_to_class_hash.shift_remove(&contract_address!("0x100"));
+    actual.storage_updates.shift_remove(&contract_address!("0x200"));
+
+    let mismatch = diff_state_diffs(&expected, &actual);
+    assert_eq!(
+        mismatch.address_to_class_hash,
+        MappingMismatch {
+            missing: vec![(contract_address!("0x100"), class_hash!("0x10"))],
+            extra: vec![],
+            changed: vec![],
+        }
+    );
+    assert_eq!(
+        mismatch.storage_updates.missing,
+        vec![((contract_address!("0x200"), StorageKey(patricia_key!("0x1"))), stark_felt!(3_u8))]
+    );
+    assert!(mismatch.address_to_nonce.is_empty());
+    assert!(!mismatch.is_empty());
+}
+
+#[test]
+fn extra_entries() {
+    let expected = state_diff();
+    let mut actual = state_diff();
+    actual.address_to_nonce.insert(contract_address!("0x200"), Nonce(stark_felt!(1_u8)));
+    actual.replaced_classes.insert(contract_address!("0x100"), class_hash!("0x10"));
+
+    let mismatch = diff_state_diffs(&expected, &actual);
+    assert_eq!(
+        mismatch.address_to_nonce.extra,
+        vec![(contract_address!("0x200"), Nonce(stark_felt!(1_u8)))]
+    );
+    assert_eq!(
+        mismatch.replaced_classes.extra,
+        vec![(contract_address!("0x100"), class_hash!("0x10"))]
+    );
+    assert!(mismatch.address_to_nonce.missing.is_empty());
+    assert!(mismatch.address_to_class_hash.is_empty());
+}
+
+#[test]
+fn changed_entries() {
+    let expected = state_diff();
+    let mut actual = state_diff();
+    actual.storage_updates[&contract_address!("0x100")]
+        .insert(StorageKey(patricia_key!("0x2")), stark_felt!(7_u8));
+    actual
+        .class_hash_to_compiled_class_hash
+        .insert(class_hash!("0x10"), CompiledClassHash(stark_felt!("0x12")));
+
+    let mismatch = diff_state_diffs(&expected, &actual);
+    assert_eq!(
+        mismatch.storage_updates.changed,
+        vec![(
+            (contract_address!("0x100"), StorageKey(patricia_key!("0x2"))),
+            stark_felt!(2_u8),
+            stark_felt!(7_u8)
+        )]
+    );
+    assert_eq!(
+        mismatch.class_hash_to_compiled_class_hash.changed,
+        vec![(
+            class_hash!("0x10"),
+            CompiledClassHash(stark_felt!("0x11")),
+            CompiledClassHash(stark_felt!("0x12"))
+        )]
+    );
+    assert!(mismatch.storage_updates.missing.is_empty());
+    assert!(mismatch.storage_updates.extra.is_empty());
+}
+
+#[test]
+fn mismatch_report() {
+    let expected = state_diff();
+    let mut actual = state_diff();
+    actual.address_to_class_hash.shift_remove(&contract_address!("0x100"));
+    actual.address_to_nonce.insert(contract_address!("0x100"), Nonce(stark_felt!(2_u8)));
+    actual.storage_updates[&contract_address!("0x200")]
+        .insert(StorageKey(patricia_key!("0x5")), stark_felt!(5_u8));
+
+    let hex = |value: u16| format!("0x{value:064x}");
+    assert_eq!(
+        diff_state_diffs(&expected, &actual).to_string(),
+        format!(
+            "address_to_class_hash: missing {}: {}\naddress_to_nonce: changed {}: expected {}, \
+             actual {}\nstorage_updates: extra {}[{}]: {}\n",
+            hex(0x100),
+            hex(0x10),
+            hex(0x100),
+            hex(1),
+            hex(2),
+            hex(0x200),
+            hex(5),
+            hex(5),
+        )
+    );
+}
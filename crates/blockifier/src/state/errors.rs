@@ -4,6 +4,7 @@ use starknet_api::StarknetApiError;
 use thiserror::Error;
 
 use crate::abi::constants;
+use crate::state::cached_state::{SavepointId, StateKey};
 
 #[derive(Debug, Error)]
 pub enum StateError {
@@ -16,8 +17,16 @@ pub enum StateError {
     OutOfRangeContractAddress,
     #[error(transparent)]
     ProgramError(#[from] ProgramError),
+    #[error("Cannot replace the class of undeployed contract {0:?}.")]
+    UndeployedContractClassReplacement(ContractAddress),
     #[error("Requested {0:?} is unavailable for deployment.")]
     UnavailableContractAddress(ContractAddress),
+    #[error("Savepoint {0:?} was rolled back past or released.")]
+    InvalidSavepoint(SavepointId),
+    #[error("Failed to commit {key:?}: {error}")]
+    CommitFailure { key: StateKey, error: Box<StateError> },
+    #[error("Contract {0:?} is not deployed.")]
+    ContractNotDeployed(ContractAddress),
     #[error("Class with hash {0:#?} is not declared.")]
     UndeclaredClassHash(ClassHash),
     #[error(transparent)]
@@ -0,0 +1,300 @@
+use std::cell::RefCell;
+use std::collections::HashMap;
+use std::io::Read;
+
+use base64::engine::general_purpose::STANDARD;
+use base64::Engine;
+use cairo_lang_starknet_classes::casm_contract_class::{
+    CasmContractClass, StarknetSierraCompilationError,
+};
+use cairo_lang_starknet_classes::contract_class::{
+    ContractClass as SierraContractClass, ContractEntryPoints,
+};
+use cairo_lang_utils::bigint::BigUintAsHex;
+use cairo_vm::types::errors::program_errors::ProgramError;
+use flate2::read::GzDecoder;
+use serde::Deserialize;
+use serde_json::{json, Value};
+use starknet_api::block::{BlockHash, BlockNumber};
+use starknet_api::core::{ClassHash, CompiledClassHash, ContractAddress, Nonce};
+use starknet_api::hash::StarkFelt;
+use starknet_api::state::StorageKey;
+use thiserror::Error;
+
+use crate::execution::contract_class::{ContractClass, ContractClassV0, ContractClassV1};
+use crate::execution::execution_utils::felt_to_stark_felt;
+use crate::state::cached_state::StorageEntry;
+use crate::state::errors::StateError;
+use crate::state::state_api::{StateReader, StateResult};
+
+#[cfg(test)]
+#[path = "rpc_state_reader_test.rs"]
+mod test;
+
+// JSON-RPC error codes of the Starknet specification, for entities missing in the state.
+const CONTRACT_NOT_FOUND: i64 = 20;
+const CLASS_HASH_NOT_FOUND: i64 = 28;
+
+#[derive(Debug, Error)]
+pub enum RpcStateReaderError {
+    #[error("Invalid class {class_hash}: {info}")]
+    InvalidClass { class_hash: ClassHash, info: String },
+    #[error("Node responded to {method} with error {code}: {message}")]
+    Rpc { method: String, code: i64, message: String },
+    #[error(transparent)]
+    ProgramError(#[from] ProgramError),
+    #[error(transparent)]
+    SerdeError(#[from] serde_json::Error),
+    #[error(transparent)]
+    SierraCompilationError(#[from] StarknetSierraCompilationError),
+    #[error("Failed to send {method} request: {error}")]
+    TransportError { method: String, error: String },
+}
+
+pub type RpcStateReaderResult<T> = Result<T, RpcStateReaderError>;
+
+impl From<RpcStateReaderError> for StateError {
+    fn from(error: RpcStateReaderError) -> Self {
+        StateError::StateReadError(error.to_string())
+    }
+}
+
+/// Sends a JSON-RPC request to a Starknet node and returns the JSON-RPC response.
+/// Abstracts the HTTP client, which is chosen by the user of the reader.
+pub trait RpcTransport {
+    fn send_request(&self, request: &Value) -> Result<Value, String>;
+}
+
+impl<F: Fn(&Value) -> Result<Value, String>> RpcTransport for F {
+    fn send_request(&self, request: &Value) -> Result<Value, String> {
+        self(request)
+    }
+}
+
+/// The block whose state is read.
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub enum BlockId {
+    Hash(BlockHash),
+    Number(BlockNumber),
+}
+
+impl BlockId {
+    fn to_json(self) -> Value {
+        match self {
+            BlockId::Hash(block_hash) => json!({ "block_hash": block_hash }),
+            BlockId::Number(block_number) => json!({ "block_number": block_number }),
+        }
+    }
+}
+
+#[derive(Default)]
+struct RpcResponseCache {
+    storage: HashMap<StorageEntry, StarkFelt>,
+    nonces: HashMap<ContractAddress, Nonce>,
+    class_hashes: HashMap<ContractAddress, ClassHash>,
+    classes: HashMap<ClassHash, (ContractClass, CompiledClassHash)>,
+}
+
+/// Reads the state of a Starknet node at a pinned block, through its JSON-RPC API; e.g., to
+/// execute transactions on top of a live network (fork mode).
+/// Responses are memoized, as the state of the pinned block never changes.
+///
+/// The node's API does not expose compiled classes; Cairo 1 classes are compiled locally from
+/// their Sierra program, and their compiled class hash is computed from the local compilation.
+/// It may thus differ from the one declared on-chain, if the node compiled with another compiler
+/// version.
+pub struct RpcStateReader<T: RpcTransport> {
+    transport: T,
+    block_id: BlockId,
+    cache: RefCell<RpcResponseCache>,
+}
+
+impl<T: RpcTransport> RpcStateReader<T> {
+    pub fn new(transport: T, block_id: BlockId) -> Self {
+        Self { transport, block_id, cache: RefCell::default() }
+    }
+
+    /// Sends a request at the pinned block; returns `None` if the requested contract or class
+    /// does not exist in it.
+    fn send_request(&self, method: &str, mut params: Value) -> RpcStateReaderResult<Option<Value>> {
+        params["block_id"] = self.block_id.to_json();
+        let request = json!({ "jsonrpc": "2.0", "id": 0, "method": method, "params": params });
+        let mut response = self.transport.send_request(&request).map_err(|error| {
+            RpcStateReaderError::TransportError { method: method.to_string(), error }
+        })?;
+
+        if let Some(error) = response.get("error") {
+            let code = error["code"].as_i64().unwrap_or_default();
+            if code == CONTRACT_NOT_FOUND || code == CLASS_HASH_NOT_FOUND {
+                return Ok(None);
+            }
+            return Err(RpcStateReaderError::Rpc {
+                method: method.to_string(),
+                code,
+                message: error["message"].as_str().unwrap_or_default().to_string(),
+            });
+        }
+        Ok(Some(response["result"].take()))
+    }
+
+    fn read_storage(
+        &self,
+        contract_address: ContractAddress,
+        key: StorageKey,
+    ) -> RpcStateReaderResult<StarkFelt> {
+        let params = json!({ "contract_address": contract_address, "key": key });
+        let value = self.send_request("starknet_getStorageAt", params)?;
+        Ok(value.map(serde_json::from_value).transpose()?.unwrap_or_default())
+    }
+
+    fn read_nonce(&self, contract_address: ContractAddress) -> RpcStateReaderResult<Nonce> {
+        let params = json!({ "contract_address": contract_address });
+        let nonce = self.send_request("starknet_getNonce", params)?;
+        Ok(nonce.map(serde_json::from_value).transpose()?.unwrap_or_default())
+    }
+
+    fn read_class_hash(
+        &self,
+        contract_address: ContractAddress,
+    ) -> RpcStateReaderResult<ClassHash> {
+        let params = json!({ "contract_address": contract_address });
+        let class_hash = self.send_request("starknet_getClassHashAt", params)?;
+        Ok(class_hash.map(serde_json::from_value).transpose()?.unwrap_or_default())
+    }
+
+    /// Returns the class and its compiled class hash, or `None` if the class is not declared.
+    fn read_class(
+        &self,
+        class_hash: ClassHash,
+    ) -> RpcStateReaderResult<Option<(ContractClass, CompiledClassHash)>> {
+        let params = json!({ "class_hash": class_hash });
+        let Some(raw_class) = self.send_request("starknet_getClass", params)? else {
+            return Ok(None);
+        };
+
+        let class = if raw_class.get("sierra_program").is_some() {
+            let (class, compiled_class_hash) =
+                compile_sierra_class(serde_json::from_value(raw_class)?)?;
+            (ContractClass::V1(class), compiled_class_hash)
+        } else {
+            let class =
+                decompress_deprecated_class(class_hash, serde_json::from_value(raw_class)?)?;
+            // Cairo 0 classes have no compiled class hash.
+            (ContractClass::V0(class), CompiledClassHash::default())
+        };
+        Ok(Some(class))
+    }
+
+    fn get_class(&self, class_hash: ClassHash) -> StateResult<(ContractClass, CompiledClassHash)> {
+        if let Some(class) = self.cache.borrow().classes.get(&class_hash) {
+            return Ok(class.clone());
+        }
+
+        let class =
+            self.read_class(class_hash)?.ok_or(StateError::UndeclaredClassHash(class_hash))?;
+        self.cache.borrow_mut().classes.insert(class_hash, class.clone());
+        Ok(class)
+    }
+}
+
+impl<T: RpcTransport> StateReader for RpcStateReader<T> {
+    fn get_storage_at(
+        &self,
+        contract_address: ContractAddress,
+        key: StorageKey,
+    ) -> StateResult<StarkFelt> {
+        if let Some(value) = self.cache.borrow().storage.get(&(contract_address, key)) {
+            return Ok(*value);
+        }
+
+        let value = self.read_storage(contract_address, key)?;
+        self.cache.borrow_mut().storage.insert((contract_address, key), value);
+        Ok(value)
+    }
+
+    fn get_nonce_at(&self, contract_address: ContractAddress) -> StateResult<Nonce> {
+        if let Some(nonce) = self.cache.borrow().nonces.get(&contract_address) {
+            return Ok(*nonce);
+        }
+
+        let nonce = self.read_nonce(contract_address)?;
+        self.cache.borrow_mut().nonces.insert(contract_address, nonce);
+        Ok(nonce)
+    }
+
+    fn get_class_hash_at(&self, contract_address: ContractAddress) -> StateResult<ClassHash> {
+        if let Some(class_hash) = self.cache.borrow().class_hashes.get(&contract_address) {
+            return Ok(*class_hash);
+        }
+
+        let class_hash = self.read_class_hash(contract_address)?;
+        self.cache.borrow_mut().class_hashes.insert(contract_address, class_hash);
+        Ok(class_hash)
+    }
+
+    fn get_compiled_contract_class(&self, class_hash: ClassHash) -> StateResult<ContractClass> {
+        Ok(self.get_class(class_hash)?.0)
+    }
+
+    fn get_compiled_class_hash(&self, class_hash: ClassHash) -> StateResult<CompiledClassHash> {
+        match self.get_class(class_hash) {
+            Ok((_, compiled_class_hash)) => Ok(compiled_class_hash),
+            // Undeclared classes have no compiled class hash.
+            Err(StateError::UndeclaredClassHash(_)) => Ok(CompiledClassHash::default()),
+            Err(error) => Err(error),
+        }
+    }
+}
+
+/// A Cairo 1 class, as returned by `starknet_getClass`.
+#[derive(Deserialize)]
+struct RpcSierraClass {
+    sierra_program: Vec<BigUintAsHex>,
+    contract_class_version: String,
+    entry_points_by_type: ContractEntryPoints,
+}
+
+/// A Cairo 0 class, as returned by `starknet_getClass`.
+#[derive(Deserialize)]
+struct RpcDeprecatedClass {
+    /// The base64 encoding of the gzip-compressed program.
+    program: String,
+    entry_points_by_type: Value,
+}
+
+/// Compiles the given class to CASM; returns the compiled class and its hash, computed locally
+/// rather than fetched from the node.
+fn compile_sierra_class(
+    class: RpcSierraClass,
+) -> RpcStateReaderResult<(ContractClassV1, CompiledClassHash)> {
+    let sierra_class = SierraContractClass {
+        sierra_program: class.sierra_program,
+        sierra_program_debug_info: None,
+        contract_class_version: class.contract_class_version,
+        entry_points_by_type: class.entry_points_by_type,
+        abi: None,
+    };
+    let casm_class = CasmContractClass::from_contract_class(sierra_class, false, usize::MAX)?;
+    let compiled_class_hash =
+        CompiledClassHash(felt_to_stark_felt(&casm_class.compiled_class_hash()));
+    Ok((ContractClassV1::try_from(casm_class)?, compiled_class_hash))
+}
+
+fn decompress_deprecated_class(
+    class_hash: ClassHash,
+    class: RpcDeprecatedClass,
+) -> RpcStateReaderResult<ContractClassV0> {
+    let invalid_class =
+        |info: &str| RpcStateReaderError::InvalidClass { class_hash, info: info.to_string() };
+    let compressed_program =
+        STANDARD.decode(&class.program).map_err(|_| invalid_class("invalid base64 program."))?;
+    let mut program = String::new();
+    GzDecoder::new(&compressed_program[..])
+        .read_to_string(&mut program)
+        .map_err(|_| invalid_class("invalid compressed program."))?;
+
+    let program: Value = serde_json::from_str(&program)?;
+    let raw_class =
+        json!({ "program": program, "entry_points_by_type": class.entry_points_by_type });
+    Ok(ContractClassV0::try_from_json_string(&raw_class.to_string())?)
+}
@@ -0,0 +1,128 @@
+use std::cell::Cell;
+
+use assert_matches::assert_matches;
+use pretty_assertions::assert_eq;
+use serde_json::Value;
+use starknet_api::block::BlockNumber;
+use starknet_api::core::{ClassHash, CompiledClassHash, ContractAddress, Nonce, PatriciaKey};
+use starknet_api::hash::{StarkFelt, StarkHash};
+use starknet_api::state::StorageKey;
+use starknet_api::{class_hash, contract_address, patricia_key, stark_felt};
+
+use crate::abi::abi_utils::selector_from_name;
+use crate::execution::contract_class::{ContractClass, ContractClassV0};
+use crate::state::errors::StateError;
+use crate::state::rpc_state_reader::{BlockId, RpcStateReader, RpcTransport};
+use crate::state::state_api::StateReader;
+use crate::test_utils::get_raw_contract_class;
+
+const FIXTURES: &str = include_str!("../../resources/rpc_state_reader_fixtures.json");
+const BLOCK_ID: BlockId = BlockId::Number(BlockNumber(100));
+
+/// Responds to requests by fixtures of the responses of a node; counts the requests sent.
+struct FixtureTransport {
+    fixtures: Vec<Value>,
+    n_requests: Cell<usize>,
+}
+
+impl FixtureTransport {
+    fn new() -> Self {
+        Self { fixtures: serde_json::from_str(FIXTURES).unwrap(), n_requests: Cell::new(0) }
+    }
+}
+
+impl RpcTransport for &FixtureTransport {
+    fn send_request(&self, request: &Value) -> Result<Value, String> {
+        self.n_requests.set(self.n_requests.get() + 1);
+        self.fixtures
+            .iter()
+            .find(|fixture| {
+                fixture["method"] == request["method"] && fixture["params"] == request["params"]
+            })
+            .map(|fixture| fixture["response"].clone())
+            .ok_or_else(|| format!("No fixture for request {request}."))
+    }
+}
+
+#[test]
+fn test_contract_state_reads() {
+    let transport = FixtureTransport::new();
+    let reader = RpcStateReader::new(&transport, BLOCK_ID);
+    let contract_address = contract_address!("0x1001");
+    let key = StorageKey(patricia_key!("0x10"));
+
+    assert_eq!(reader.get_storage_at(contract_address, key).unwrap(), stark_felt!("0x2a"));
+    assert_eq!(reader.get_nonce_at(contract_address).unwrap(), Nonce(stark_felt!("0x3")));
+    assert_eq!(reader.get_class_hash_at(contract_address).unwrap(), class_hash!("0x110"));
+    assert_eq!(transport.n_requests.get(), 3);
+
+    // Repeated reads are memoized.
+    assert_eq!(reader.get_storage_at(contract_address, key).unwrap(), stark_felt!("0x2a"));
+    assert_eq!(reader.get_nonce_at(contract_address).unwrap(), Nonce(stark_felt!("0x3")));
+    assert_eq!(reader.get_class_hash_at(contract_address).unwrap(), class_hash!("0x110"));
+    assert_eq!(transport.n_requests.get(), 3);
+}
+
+#[test]
+fn test_missing_entities() {
+    let transport = FixtureTransport::new();
+    let reader = RpcStateReader::new(&transport, BLOCK_ID);
+    let undeployed_address = contract_address!("0x2000");
+
+    // Reads of undeployed contracts default to zero.
+    let key = StorageKey(patricia_key!("0x10"));
+    assert_eq!(reader.get_storage_at(undeployed_address, key).unwrap(), StarkFelt::ZERO);
+    assert_eq!(reader.get_nonce_at(undeployed_address).unwrap(), Nonce::default());
+    assert_eq!(reader.get_class_hash_at(undeployed_address).unwrap(), ClassHash::default());
+
+    let undeclared_class_hash = class_hash!("0x999");
+    assert_matches!(
+        reader.get_compiled_contract_class(undeclared_class_hash).unwrap_err(),
+        StateError::UndeclaredClassHash(class_hash) if class_hash == undeclared_class_hash
+    );
+    assert_eq!(
+        reader.get_compiled_class_hash(undeclared_class_hash).unwrap(),
+        CompiledClassHash::default()
+    );
+
+    // Other errors of the node are propagated.
+    assert_matches!(
+        reader.get_nonce_at(contract_address!("0x3000")).unwrap_err(),
+        StateError::StateReadError(message)
+            if message == "Node responded to starknet_getNonce with error -32603: Internal error"
+    );
+}
+
+#[test]
+fn test_deprecated_class() {
+    let transport = FixtureTransport::new();
+    let reader = RpcStateReader::new(&transport, BLOCK_ID);
+    let class_hash = class_hash!("0x110");
+
+    let expected_class = ContractClassV0::try_from_json_string(&get_raw_contract_class(
+        "./feature_contracts/cairo0/compiled/empty_contract_compiled.json",
+    ))
+    .unwrap();
+    assert_eq!(
+        reader.get_compiled_contract_class(class_hash).unwrap(),
+        ContractClass::V0(expected_class)
+    );
+    assert_eq!(reader.get_compiled_class_hash(class_hash).unwrap(), CompiledClassHash::default());
+    assert_eq!(transport.n_requests.get(), 1);
+}
+
+#[test]
+fn test_sierra_class() {
+    let transport = FixtureTransport::new();
+    let reader = RpcStateReader::new(&transport, BLOCK_ID);
+    let class_hash = class_hash!("0x120");
+
+    let ContractClass::V1(class) = reader.get_compiled_contract_class(class_hash).unwrap() else {
+        panic!("Expected a Cairo 1 class.");
+    };
+    let selector = selector_from_name("empty");
+    assert!(class.entry_points_by_type.values().flatten().any(|ep| ep.selector == selector));
+    assert_ne!(reader.get_compiled_class_hash(class_hash).unwrap(), CompiledClassHash::default());
+    // The class is compiled once.
+    assert_eq!(transport.n_requests.get(), 1);
+}
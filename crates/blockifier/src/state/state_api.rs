@@ -1,5 +1,7 @@
 use std::collections::HashSet;
+use std::sync::Arc;
 
+use num_bigint::BigUint;
 use starknet_api::core::{ClassHash, CompiledClassHash, ContractAddress, Nonce};
 use starknet_api::hash::StarkFelt;
 use starknet_api::state::StorageKey;
@@ -7,6 +9,7 @@ use starknet_api::state::StorageKey;
 use crate::abi::abi_utils::get_fee_token_var_address;
 use crate::abi::sierra_types::next_storage_key;
 use crate::execution::contract_class::ContractClass;
+use crate::state::cached_state::StorageEntry;
 use crate::state::errors::StateError;
 
 pub type StateResult<T> = Result<T, StateError>;
@@ -19,8 +22,8 @@ pub enum DataAvailabilityMode {
 
 /// A read-only API for accessing Starknet global state.
 ///
-/// The `self` argument is mutable for flexibility during reads (for example, caching reads),
-/// and to allow for the `State` trait below to also be considered a `StateReader`.
+/// Reads take `&self`; readers that cache their reads (e.g., `CachedState`) use interior
+/// mutability. Thus, a reader may be shared between states, e.g., behind an `Arc`.
 pub trait StateReader {
     /// Returns the storage value under the given key in the given contract instance (represented by
     /// its address).
@@ -31,8 +34,20 @@ pub trait StateReader {
         key: StorageKey,
     ) -> StateResult<StarkFelt>;
 
+    /// Returns the storage values under the given keys, in order; see [`Self::get_storage_at`].
+    /// Readers of remote storage may override it, to fetch the values in a single request.
+    fn get_storage_at_batch(&self, storage_keys: &[StorageEntry]) -> StateResult<Vec<StarkFelt>> {
+        storage_keys
+            .iter()
+            .map(|&(contract_address, key)| self.get_storage_at(contract_address, key))
+            .collect()
+    }
+
     /// Returns the nonce of the given contract instance.
-    /// Default: 0 for an uninitialized contract address.
+    /// Default: 0 for an uninitialized contract address; readers that distinguish contracts that
+    /// are not deployed may fail with [`StateError::ContractNotDeployed`] instead, which
+    /// `CachedState` reads as 0 unless its nonce reads are strict. Readers may check these
+    /// semantics using `test_utils::state_reader_conformance` (of the `testing` feature).
     fn get_nonce_at(&self, contract_address: ContractAddress) -> StateResult<Nonce>;
 
     /// Returns the class hash of the contract class at the given contract instance.
@@ -40,9 +55,15 @@ pub trait StateReader {
     fn get_class_hash_at(&self, contract_address: ContractAddress) -> StateResult<ClassHash>;
 
     /// Returns the contract class of the given class hash.
+    /// Fails with [`StateError::UndeclaredClassHash`] if the class is not declared; other errors
+    /// signify failures to read the class.
     fn get_compiled_contract_class(&self, class_hash: ClassHash) -> StateResult<ContractClass>;
 
     /// Returns the compiled class hash of the given class hash.
+    /// Default: 0 for an undeclared class hash (and for Cairo 0 classes, which have none), rather
+    /// than an error, as node storage reads missing entries as 0 and the `GetCompiledClassHash`
+    /// syscall returns 0 for undeclared classes. To tell whether a class is declared, use
+    /// [`Self::get_compiled_contract_class`].
     fn get_compiled_class_hash(&self, class_hash: ClassHash) -> StateResult<CompiledClassHash>;
 
     /// Returns the storage value representing the balance (in fee token) at the given address.
@@ -51,7 +72,7 @@ pub trait StateReader {
     // TODO(Dori, 1/9/2023): NEW_TOKEN_SUPPORT Determine fee token address based on tx version,
     //   once v3 is introduced.
     fn get_fee_token_balance(
-        &mut self,
+        &self,
         contract_address: ContractAddress,
         fee_token_address: ContractAddress,
     ) -> Result<(StarkFelt, StarkFelt), StateError> {
@@ -62,6 +83,57 @@ pub trait StateReader {
 
         Ok((low, high))
     }
+
+    /// Returns the balance (in fee token) at the given address, combining the low and high words
+    /// returned by [`Self::get_fee_token_balance`].
+    fn get_fee_token_balance_as_biguint(
+        &self,
+        contract_address: ContractAddress,
+        fee_token_address: ContractAddress,
+    ) -> StateResult<BigUint> {
+        let (low, high) = self.get_fee_token_balance(contract_address, fee_token_address)?;
+        let (low, high) =
+            (BigUint::from_bytes_be(low.bytes()), BigUint::from_bytes_be(high.bytes()));
+        Ok((high << 128) + low)
+    }
+}
+
+/// A reader that may be read from multiple threads concurrently; e.g., the base state of a block
+/// whose transactions are executed in parallel, each on a `CachedState` over an `Arc` of it.
+#[cfg(feature = "concurrency")]
+pub trait SyncStateReader: StateReader + Send + Sync {}
+
+#[cfg(feature = "concurrency")]
+impl<S: StateReader + Send + Sync + ?Sized> SyncStateReader for S {}
+
+impl<S: StateReader + ?Sized> StateReader for Arc<S> {
+    fn get_storage_at(
+        &self,
+        contract_address: ContractAddress,
+        key: StorageKey,
+    ) -> StateResult<StarkFelt> {
+        self.as_ref().get_storage_at(contract_address, key)
+    }
+
+    fn get_storage_at_batch(&self, storage_keys: &[StorageEntry]) -> StateResult<Vec<StarkFelt>> {
+        self.as_ref().get_storage_at_batch(storage_keys)
+    }
+
+    fn get_nonce_at(&self, contract_address: ContractAddress) -> StateResult<Nonce> {
+        self.as_ref().get_nonce_at(contract_address)
+    }
+
+    fn get_class_hash_at(&self, contract_address: ContractAddress) -> StateResult<ClassHash> {
+        self.as_ref().get_class_hash_at(contract_address)
+    }
+
+    fn get_compiled_contract_class(&self, class_hash: ClassHash) -> StateResult<ContractClass> {
+        self.as_ref().get_compiled_contract_class(class_hash)
+    }
+
+    fn get_compiled_class_hash(&self, class_hash: ClassHash) -> StateResult<CompiledClassHash> {
+        self.as_ref().get_compiled_class_hash(class_hash)
+    }
 }
 
 /// A class defining the API for writing to Starknet global state.
@@ -78,8 +150,12 @@ pub trait State: StateReader {
     ) -> StateResult<()>;
 
     /// Increments the nonce of the given contract instance.
+    /// Raises an exception if the incremented nonce exceeds the field.
     fn increment_nonce(&mut self, contract_address: ContractAddress) -> StateResult<()>;
 
+    /// Sets the nonce of the given contract instance.
+    fn set_nonce_at(&mut self, contract_address: ContractAddress, nonce: Nonce) -> StateResult<()>;
+
     /// Allocates the given address to the given class hash.
     /// Raises an exception if the address is already assigned;
     /// meaning: this is a write once action.
@@ -89,6 +165,14 @@ pub trait State: StateReader {
         class_hash: ClassHash,
     ) -> StateResult<()>;
 
+    /// Replaces the class hash of the given, already deployed, contract instance.
+    /// Raises an exception if the address is not assigned to a class hash.
+    fn replace_class_hash_at(
+        &mut self,
+        contract_address: ContractAddress,
+        class_hash: ClassHash,
+    ) -> StateResult<()>;
+
     /// Sets the given contract class under the given class hash.
     fn set_contract_class(
         &mut self,
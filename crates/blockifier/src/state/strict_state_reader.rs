@@ -0,0 +1,71 @@
+use starknet_api::core::{ClassHash, CompiledClassHash, ContractAddress, Nonce};
+use starknet_api::hash::StarkFelt;
+use starknet_api::state::StorageKey;
+
+use crate::execution::contract_class::ContractClass;
+use crate::state::errors::StateError;
+use crate::state::state_api::{StateReader, StateResult};
+
+#[cfg(test)]
+#[path = "strict_state_reader_test.rs"]
+mod test;
+
+/// Wraps a state reader, failing reads of the nonce or class hash of contracts that are not
+/// deployed in it, rather than reading them as zero; e.g., to catch test fixtures that forgot to
+/// deploy a contract. Note that deploying new contracts on top of it fails as well, as the
+/// availability of their address is checked by reading their class hash.
+#[derive(Debug)]
+pub struct StrictStateReader<S: StateReader> {
+    pub state: S,
+    /// Whether reads of the storage of contracts that are not deployed fail too.
+    pub strict_storage: bool,
+}
+
+impl<S: StateReader> StrictStateReader<S> {
+    pub fn new(state: S) -> Self {
+        Self { state, strict_storage: false }
+    }
+
+    pub fn with_strict_storage(self, strict_storage: bool) -> Self {
+        Self { strict_storage, ..self }
+    }
+
+    fn deployed_class_hash(&self, contract_address: ContractAddress) -> StateResult<ClassHash> {
+        let class_hash = self.state.get_class_hash_at(contract_address)?;
+        if class_hash == ClassHash::default() {
+            return Err(StateError::ContractNotDeployed(contract_address));
+        }
+
+        Ok(class_hash)
+    }
+}
+
+impl<S: StateReader> StateReader for StrictStateReader<S> {
+    fn get_storage_at(
+        &self,
+        contract_address: ContractAddress,
+        key: StorageKey,
+    ) -> StateResult<StarkFelt> {
+        if self.strict_storage {
+            self.deployed_class_hash(contract_address)?;
+        }
+        self.state.get_storage_at(contract_address, key)
+    }
+
+    fn get_nonce_at(&self, contract_address: ContractAddress) -> StateResult<Nonce> {
+        self.deployed_class_hash(contract_address)?;
+        self.state.get_nonce_at(contract_address)
+    }
+
+    fn get_class_hash_at(&self, contract_address: ContractAddress) -> StateResult<ClassHash> {
+        self.deployed_class_hash(contract_address)
+    }
+
+    fn get_compiled_contract_class(&self, class_hash: ClassHash) -> StateResult<ContractClass> {
+        self.state.get_compiled_contract_class(class_hash)
+    }
+
+    fn get_compiled_class_hash(&self, class_hash: ClassHash) -> StateResult<CompiledClassHash> {
+        self.state.get_compiled_class_hash(class_hash)
+    }
+}
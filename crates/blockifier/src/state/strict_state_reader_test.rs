@@ -0,0 +1,108 @@
+use assert_matches::assert_matches;
+use rstest::rstest;
+use starknet_api::core::{ClassHash, ContractAddress, Nonce, PatriciaKey};
+use starknet_api::hash::{StarkFelt, StarkHash};
+use starknet_api::state::StorageKey;
+use starknet_api::{class_hash, contract_address, patricia_key, stark_felt};
+
+use crate::state::cached_state::CachedState;
+use crate::state::errors::StateError;
+use crate::state::state_api::{State, StateReader, StateResult};
+use crate::state::strict_state_reader::StrictStateReader;
+use crate::test_utils::dict_state_reader::DictStateReader;
+use crate::test_utils::state_reader_conformance::{
+    check_undeclared_class_reads, check_undeployed_contract_reads,
+};
+
+const DEPLOYED_ADDRESS: &str = "0x100";
+const UNDEPLOYED_ADDRESS: &str = "0x200";
+
+fn initial_state() -> DictStateReader {
+    DictStateReader::default()
+        .with_deployed_contract(contract_address!(DEPLOYED_ADDRESS), class_hash!("0x1"))
+        .with_storage(
+            contract_address!(DEPLOYED_ADDRESS),
+            StorageKey(patricia_key!("0x10")),
+            stark_felt!(7_u8),
+        )
+}
+
+/// Mimics a transaction sent by the given account, which writes to the storage of the deployed
+/// contract.
+fn execute(state: &mut dyn State, account_address: ContractAddress) -> StateResult<()> {
+    state.increment_nonce(account_address)?;
+    let key = StorageKey(patricia_key!("0x10"));
+    let value = state.get_storage_at(contract_address!(DEPLOYED_ADDRESS), key)?;
+    state.set_storage_at(contract_address!(DEPLOYED_ADDRESS), key, value)
+}
+
+#[test]
+fn lenient_reads_of_undeployed_contract() {
+    let mut state = CachedState::from(initial_state());
+    execute(&mut state, contract_address!(UNDEPLOYED_ADDRESS)).unwrap();
+    assert_eq!(
+        state.get_nonce_at(contract_address!(UNDEPLOYED_ADDRESS)).unwrap(),
+        Nonce(stark_felt!(1_u8))
+    );
+}
+
+#[test]
+fn strict_reads_of_undeployed_contract() {
+    let mut state =
+        CachedState::from(StrictStateReader::new(initial_state())).with_strict_nonce_reads(true);
+    let undeployed_address = contract_address!(UNDEPLOYED_ADDRESS);
+    assert_matches!(
+        execute(&mut state, undeployed_address).unwrap_err(),
+        StateError::ContractNotDeployed(address) if address == undeployed_address
+    );
+    assert_matches!(
+        state.get_class_hash_at(undeployed_address).unwrap_err(),
+        StateError::ContractNotDeployed(_)
+    );
+
+    // Deployed contracts are read as usual.
+    let deployed_address = contract_address!(DEPLOYED_ADDRESS);
+    execute(&mut state, deployed_address).unwrap();
+    assert_eq!(state.get_nonce_at(deployed_address).unwrap(), Nonce(stark_felt!(1_u8)));
+}
+
+#[test]
+fn nonce_reads_of_undeployed_contract_read_as_zero_by_default() {
+    let state = CachedState::from(StrictStateReader::new(initial_state()));
+    let undeployed_address = contract_address!(UNDEPLOYED_ADDRESS);
+    assert_eq!(state.get_nonce_at(undeployed_address).unwrap(), Nonce::default());
+    assert_matches!(
+        state.get_class_hash_at(undeployed_address).unwrap_err(),
+        StateError::ContractNotDeployed(_)
+    );
+}
+
+#[rstest]
+fn state_reader_conformance(#[values(true, false)] strict_storage: bool) {
+    let undeployed_address = contract_address!(UNDEPLOYED_ADDRESS);
+    let undeclared_class_hash = class_hash!("0x2");
+
+    let reader = StrictStateReader::new(initial_state()).with_strict_storage(strict_storage);
+    check_undeclared_class_reads(&reader, undeclared_class_hash);
+    check_undeployed_contract_reads(reader, undeployed_address);
+
+    check_undeclared_class_reads(&initial_state(), undeclared_class_hash);
+    check_undeployed_contract_reads(initial_state(), undeployed_address);
+    check_undeployed_contract_reads(CachedState::from(initial_state()), undeployed_address);
+}
+
+#[rstest]
+fn strict_storage_reads_of_undeployed_contract(#[values(true, false)] strict_storage: bool) {
+    let reader = StrictStateReader::new(initial_state()).with_strict_storage(strict_storage);
+    let key = StorageKey(patricia_key!("0x10"));
+    let storage_read = reader.get_storage_at(contract_address!(UNDEPLOYED_ADDRESS), key);
+    if strict_storage {
+        assert_matches!(storage_read.unwrap_err(), StateError::ContractNotDeployed(_));
+    } else {
+        assert_eq!(storage_read.unwrap(), StarkFelt::ZERO);
+    }
+    assert_eq!(
+        reader.get_storage_at(contract_address!(DEPLOYED_ADDRESS), key).unwrap(),
+        stark_felt!(7_u8)
+    );
+}
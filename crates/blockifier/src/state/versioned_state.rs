@@ -0,0 +1,327 @@
+use std::collections::{BTreeMap, HashMap, HashSet};
+use std::hash::Hash;
+use std::sync::{Mutex, MutexGuard};
+
+use starknet_api::core::{ClassHash, CompiledClassHash, ContractAddress, Nonce};
+use starknet_api::hash::StarkFelt;
+use starknet_api::state::StorageKey;
+
+use crate::execution::contract_class::ContractClass;
+use crate::state::cached_state::{ContractClassMapping, StateChanges, StateReads, StorageEntry};
+use crate::state::state_api::{State, StateReader, StateResult};
+
+#[cfg(test)]
+#[path = "versioned_state_test.rs"]
+mod test;
+
+/// The index of a transaction in a block.
+pub type TxIndex = usize;
+
+/// Holds the versions of a single type of state cells; a version per transaction that wrote the
+/// cell.
+#[derive(Debug)]
+struct VersionedStorage<K, V> {
+    // Values read from the initial state; i.e., the values before any transaction of the block.
+    initial_values: HashMap<K, V>,
+    // The values written by each transaction, per cell.
+    writes: HashMap<K, BTreeMap<TxIndex, V>>,
+    // The cells written by each transaction; used to discard its writes upon re-execution.
+    written_keys: HashMap<TxIndex, HashSet<K>>,
+}
+
+impl<K, V> Default for VersionedStorage<K, V> {
+    fn default() -> Self {
+        Self {
+            initial_values: HashMap::default(),
+            writes: HashMap::default(),
+            written_keys: HashMap::default(),
+        }
+    }
+}
+
+impl<K: Copy + Eq + Hash, V: Clone> VersionedStorage<K, V> {
+    /// Returns the value of the cell as observed by the given transaction: the value written by the
+    /// last transaction preceding it, or the initial value, if cached.
+    fn read(&self, tx_index: TxIndex, key: K) -> Option<V> {
+        self.writes
+            .get(&key)
+            .and_then(|writes| writes.range(..tx_index).next_back())
+            .map(|(_, value)| value.clone())
+            .or_else(|| self.initial_values.get(&key).cloned())
+    }
+
+    /// Returns whether any transaction wrote the cell.
+    fn is_written(&self, key: &K) -> bool {
+        self.writes.get(key).is_some_and(|writes| !writes.is_empty())
+    }
+
+    fn write(&mut self, tx_index: TxIndex, key: K, value: V) {
+        self.writes.entry(key).or_default().insert(tx_index, value);
+        self.written_keys.entry(tx_index).or_default().insert(key);
+    }
+
+    fn delete_writes(&mut self, tx_index: TxIndex) {
+        for key in self.written_keys.remove(&tx_index).unwrap_or_default() {
+            if let Some(writes) = self.writes.get_mut(&key) {
+                writes.remove(&tx_index);
+            }
+        }
+    }
+
+    fn set_initial_value(&mut self, key: K, value: V) {
+        self.initial_values.insert(key, value);
+    }
+
+    /// Returns the last value written to each cell by the transactions preceding the given one.
+    fn writes_before(&self, tx_index: TxIndex) -> HashMap<K, V> {
+        self.writes
+            .iter()
+            .filter_map(|(key, writes)| {
+                writes.range(..tx_index).next_back().map(|(_, value)| (*key, value.clone()))
+            })
+            .collect()
+    }
+
+    /// Returns whether all the given reads are still observed by the given transaction.
+    fn validate_reads(&self, tx_index: TxIndex, reads: &HashMap<K, V>) -> bool
+    where
+        V: PartialEq,
+    {
+        reads.iter().all(|(key, value)| self.read(tx_index, *key).as_ref() == Some(value))
+    }
+}
+
+#[derive(Debug, Default)]
+struct VersionedCells {
+    storage: VersionedStorage<StorageEntry, StarkFelt>,
+    nonces: VersionedStorage<ContractAddress, Nonce>,
+    class_hashes: VersionedStorage<ContractAddress, ClassHash>,
+    compiled_class_hashes: VersionedStorage<ClassHash, CompiledClassHash>,
+    classes: VersionedStorage<ClassHash, ContractClass>,
+}
+
+/// A state shared by the transactions of a block, executed concurrently.
+/// Each transaction observes the writes of the transactions preceding it (in the block), made so
+/// far. After execution, its reads are validated against these writes, which may have changed in
+/// the meantime; on failure, the transaction should be re-executed.
+///
+/// Transactions are executed on top of a [`VersionedStateProxy`], and their writes (including the
+/// classes they declare) are applied through [`Self::apply_writes`].
+#[derive(Debug)]
+pub struct VersionedState<S: StateReader> {
+    initial_state: S,
+    cells: Mutex<VersionedCells>,
+}
+
+impl<S: StateReader> VersionedState<S> {
+    pub fn new(initial_state: S) -> Self {
+        Self { initial_state, cells: Mutex::new(VersionedCells::default()) }
+    }
+
+    /// Returns a view of this state by the transaction with the given index.
+    pub fn proxy(&self, tx_index: TxIndex) -> VersionedStateProxy<'_, S> {
+        VersionedStateProxy { tx_index, state: self }
+    }
+
+    fn cells(&self) -> MutexGuard<'_, VersionedCells> {
+        self.cells.lock().expect("Versioned state is poisoned.")
+    }
+
+    /// Reads the value of a cell, as observed by the given transaction; falls back to the initial
+    /// state, and caches the value read from it.
+    fn read<K: Copy + Eq + Hash, V: Clone>(
+        &self,
+        tx_index: TxIndex,
+        key: K,
+        storage: impl Fn(&mut VersionedCells) -> &mut VersionedStorage<K, V>,
+        read_initial_value: impl FnOnce(&S) -> StateResult<V>,
+    ) -> StateResult<V> {
+        if let Some(value) = storage(&mut self.cells()).read(tx_index, key) {
+            return Ok(value);
+        }
+
+        // The lock is not held while reading the initial state.
+        let initial_value = read_initial_value(&self.initial_state)?;
+        let mut cells = self.cells();
+        let storage = storage(&mut cells);
+        storage.set_initial_value(key, initial_value.clone());
+        // A preceding transaction may have written the cell in the meantime.
+        Ok(storage.read(tx_index, key).unwrap_or(initial_value))
+    }
+
+    pub fn get_storage_at(
+        &self,
+        tx_index: TxIndex,
+        contract_address: ContractAddress,
+        key: StorageKey,
+    ) -> StateResult<StarkFelt> {
+        self.read(
+            tx_index,
+            (contract_address, key),
+            |cells| &mut cells.storage,
+            |state| state.get_storage_at(contract_address, key),
+        )
+    }
+
+    pub fn get_nonce_at(
+        &self,
+        tx_index: TxIndex,
+        contract_address: ContractAddress,
+    ) -> StateResult<Nonce> {
+        self.read(
+            tx_index,
+            contract_address,
+            |cells| &mut cells.nonces,
+            |state| state.get_nonce_at(contract_address),
+        )
+    }
+
+    pub fn get_class_hash_at(
+        &self,
+        tx_index: TxIndex,
+        contract_address: ContractAddress,
+    ) -> StateResult<ClassHash> {
+        self.read(
+            tx_index,
+            contract_address,
+            |cells| &mut cells.class_hashes,
+            |state| state.get_class_hash_at(contract_address),
+        )
+    }
+
+    pub fn get_compiled_class_hash(
+        &self,
+        tx_index: TxIndex,
+        class_hash: ClassHash,
+    ) -> StateResult<CompiledClassHash> {
+        self.read(
+            tx_index,
+            class_hash,
+            |cells| &mut cells.compiled_class_hashes,
+            |state| state.get_compiled_class_hash(class_hash),
+        )
+    }
+
+    pub fn get_compiled_contract_class(
+        &self,
+        tx_index: TxIndex,
+        class_hash: ClassHash,
+    ) -> StateResult<ContractClass> {
+        self.read(
+            tx_index,
+            class_hash,
+            |cells| &mut cells.classes,
+            |state| state.get_compiled_contract_class(class_hash),
+        )
+    }
+
+    /// Records the writes of the given transaction, and the classes it declared; replaces those of
+    /// its previous execution, if any.
+    pub fn apply_writes(
+        &self,
+        tx_index: TxIndex,
+        writes: &StateChanges,
+        declared_classes: &ContractClassMapping,
+    ) {
+        let mut cells = self.cells();
+        cells.storage.delete_writes(tx_index);
+        cells.nonces.delete_writes(tx_index);
+        cells.class_hashes.delete_writes(tx_index);
+        cells.compiled_class_hashes.delete_writes(tx_index);
+        cells.classes.delete_writes(tx_index);
+
+        for (&key, &value) in &writes.storage_updates {
+            cells.storage.write(tx_index, key, value);
+        }
+        for (&contract_address, &nonce) in &writes.nonce_updates {
+            cells.nonces.write(tx_index, contract_address, nonce);
+        }
+        for (&contract_address, &class_hash) in &writes.class_hash_updates {
+            cells.class_hashes.write(tx_index, contract_address, class_hash);
+        }
+        for (&class_hash, &compiled_class_hash) in &writes.compiled_class_hash_updates {
+            cells.compiled_class_hashes.write(tx_index, class_hash, compiled_class_hash);
+        }
+        for (&class_hash, contract_class) in declared_classes {
+            cells.classes.write(tx_index, class_hash, contract_class.clone());
+        }
+    }
+
+    /// Returns whether the given reads of the given transaction are still valid; i.e., whether it
+    /// would observe the same values if executed now.
+    /// A class read is valid if it is still declared by a preceding transaction, or by none of the
+    /// block (i.e., read from the initial state); a failed class read, if still undeclared.
+    pub fn validate_reads(&self, tx_index: TxIndex, reads: &StateReads) -> bool {
+        let cells = self.cells();
+
+        cells.storage.validate_reads(tx_index, &reads.storage)
+            && cells.nonces.validate_reads(tx_index, &reads.nonces)
+            && cells.class_hashes.validate_reads(tx_index, &reads.class_hashes)
+            && cells.compiled_class_hashes.validate_reads(tx_index, &reads.compiled_class_hashes)
+            && reads.classes.iter().all(|class_hash| {
+                cells.classes.read(tx_index, *class_hash).is_some()
+                    || !cells.classes.is_written(class_hash)
+            })
+            && reads
+                .undeclared_classes
+                .iter()
+                .all(|class_hash| cells.classes.read(tx_index, *class_hash).is_none())
+    }
+
+    /// Writes the state changes of the transactions preceding the given one to the given state;
+    /// e.g., a cached state on top of the initial state, once these transactions are validated.
+    pub fn apply_up_to(&self, tx_index: TxIndex, state: &mut dyn State) -> StateResult<()> {
+        let cells = self.cells();
+
+        for ((contract_address, key), value) in cells.storage.writes_before(tx_index) {
+            state.set_storage_at(contract_address, key, value)?;
+        }
+        for (contract_address, nonce) in cells.nonces.writes_before(tx_index) {
+            state.set_nonce_at(contract_address, nonce)?;
+        }
+        for (contract_address, class_hash) in cells.class_hashes.writes_before(tx_index) {
+            state.set_class_hash_at(contract_address, class_hash)?;
+        }
+        for (class_hash, contract_class) in cells.classes.writes_before(tx_index) {
+            state.set_contract_class(class_hash, contract_class)?;
+        }
+        for (class_hash, compiled_class_hash) in cells.compiled_class_hashes.writes_before(tx_index)
+        {
+            state.set_compiled_class_hash(class_hash, compiled_class_hash)?;
+        }
+
+        Ok(())
+    }
+}
+
+/// The view of a [`VersionedState`] by a single transaction.
+pub struct VersionedStateProxy<'a, S: StateReader> {
+    pub tx_index: TxIndex,
+    state: &'a VersionedState<S>,
+}
+
+impl<S: StateReader> StateReader for VersionedStateProxy<'_, S> {
+    fn get_storage_at(
+        &self,
+        contract_address: ContractAddress,
+        key: StorageKey,
+    ) -> StateResult<StarkFelt> {
+        self.state.get_storage_at(self.tx_index, contract_address, key)
+    }
+
+    fn get_nonce_at(&self, contract_address: ContractAddress) -> StateResult<Nonce> {
+        self.state.get_nonce_at(self.tx_index, contract_address)
+    }
+
+    fn get_class_hash_at(&self, contract_address: ContractAddress) -> StateResult<ClassHash> {
+        self.state.get_class_hash_at(self.tx_index, contract_address)
+    }
+
+    fn get_compiled_contract_class(&self, class_hash: ClassHash) -> StateResult<ContractClass> {
+        self.state.get_compiled_contract_class(self.tx_index, class_hash)
+    }
+
+    fn get_compiled_class_hash(&self, class_hash: ClassHash) -> StateResult<CompiledClassHash> {
+        self.state.get_compiled_class_hash(self.tx_index, class_hash)
+    }
+}
@@ -0,0 +1,266 @@
+use std::collections::HashMap;
+use std::thread;
+
+use indexmap::indexmap;
+use pretty_assertions::assert_eq;
+use rstest::{fixture, rstest};
+use starknet_api::core::{ClassHash, CompiledClassHash, ContractAddress, Nonce, PatriciaKey};
+use starknet_api::hash::{StarkFelt, StarkHash};
+use starknet_api::state::StorageKey;
+use starknet_api::{class_hash, contract_address, patricia_key, stark_felt};
+
+use crate::state::cached_state::{CachedState, ContractClassMapping, StateChanges, StateReads};
+use crate::state::state_api::{State, StateReader};
+use crate::state::versioned_state::{TxIndex, VersionedState};
+use crate::test_utils::dict_state_reader::DictStateReader;
+use crate::test_utils::{TEST_CLASS_HASH, get_test_contract_class};
+
+const CONTRACT_ADDRESS: &str = "0x100";
+const DEPLOYED_CONTRACT_ADDRESS: &str = "0x200";
+const COMPILED_CLASS_HASH: u64 = 3;
+const INITIAL_VALUE: u64 = 7;
+
+fn storage_key(key: &str) -> StorageKey {
+    StorageKey(patricia_key!(key))
+}
+
+fn initial_state() -> DictStateReader {
+    DictStateReader::default().with_storage(
+        contract_address!(CONTRACT_ADDRESS),
+        storage_key("0x10"),
+        stark_felt!(INITIAL_VALUE),
+    )
+}
+
+#[fixture]
+fn versioned_state() -> VersionedState<DictStateReader> {
+    VersionedState::new(initial_state())
+}
+
+fn storage_writes(writes: &[(&str, u64)]) -> StateChanges {
+    let contract_address = contract_address!(CONTRACT_ADDRESS);
+    StateChanges {
+        storage_updates: writes
+            .iter()
+            .map(|&(key, value)| ((contract_address, storage_key(key)), stark_felt!(value)))
+            .collect(),
+        ..Default::default()
+    }
+}
+
+/// Executes a transaction that increments the storage value under the given key, and records its
+/// writes; returns its reads.
+fn execute_increment(
+    versioned_state: &VersionedState<DictStateReader>,
+    tx_index: TxIndex,
+    key: &str,
+) -> StateReads {
+    let contract_address = contract_address!(CONTRACT_ADDRESS);
+    let mut state = CachedState::from(versioned_state.proxy(tx_index));
+    let value = state.get_storage_at(contract_address, storage_key(key)).unwrap();
+    let incremented_value = stark_felt!(u64::try_from(value).unwrap() + 1);
+    state.set_storage_at(contract_address, storage_key(key), incremented_value).unwrap();
+
+    let reads = state.get_initial_reads();
+    versioned_state.apply_writes(
+        tx_index,
+        &state.get_actual_state_changes().unwrap(),
+        &state.declared_classes().unwrap(),
+    );
+    reads
+}
+
+/// Executes a transaction that declares the given class, and records its writes; returns its reads.
+fn execute_declare(
+    versioned_state: &VersionedState<DictStateReader>,
+    tx_index: TxIndex,
+    class_hash: ClassHash,
+) -> StateReads {
+    let mut state = CachedState::from(versioned_state.proxy(tx_index));
+    // As a declare transaction, checks that the class is not declared yet.
+    assert!(state.get_compiled_contract_class(class_hash).is_err());
+    state.set_contract_class(class_hash, get_test_contract_class()).unwrap();
+    state
+        .set_compiled_class_hash(class_hash, CompiledClassHash(stark_felt!(COMPILED_CLASS_HASH)))
+        .unwrap();
+
+    let reads = state.get_initial_reads();
+    versioned_state.apply_writes(
+        tx_index,
+        &state.get_actual_state_changes().unwrap(),
+        &state.declared_classes().unwrap(),
+    );
+    reads
+}
+
+/// Executes a transaction that deploys a contract of the given class, reading the class as its
+/// constructor would, and records its writes; returns its reads and whether it succeeded.
+fn execute_deploy(
+    versioned_state: &VersionedState<DictStateReader>,
+    tx_index: TxIndex,
+    class_hash: ClassHash,
+) -> (StateReads, bool) {
+    let mut state = CachedState::from(versioned_state.proxy(tx_index));
+    let deployed = state.get_compiled_contract_class(class_hash).is_ok();
+    if deployed {
+        state.set_class_hash_at(contract_address!(DEPLOYED_CONTRACT_ADDRESS), class_hash).unwrap();
+    }
+
+    let reads = state.get_initial_reads();
+    versioned_state.apply_writes(
+        tx_index,
+        &state.get_actual_state_changes().unwrap(),
+        &state.declared_classes().unwrap(),
+    );
+    (reads, deployed)
+}
+
+#[rstest]
+fn test_read_observes_preceding_writes(versioned_state: VersionedState<DictStateReader>) {
+    let contract_address = contract_address!(CONTRACT_ADDRESS);
+    let no_classes = ContractClassMapping::default();
+    versioned_state.apply_writes(1, &storage_writes(&[("0x10", 10)]), &no_classes);
+    versioned_state.apply_writes(3, &storage_writes(&[("0x10", 30)]), &no_classes);
+
+    let expected_values = [INITIAL_VALUE, INITIAL_VALUE, 10, 10, 30];
+    for (tx_index, expected_value) in expected_values.into_iter().enumerate() {
+        assert_eq!(
+            versioned_state
+                .get_storage_at(tx_index, contract_address, storage_key("0x10"))
+                .unwrap(),
+            stark_felt!(expected_value),
+            "Unexpected value read by transaction {tx_index}."
+        );
+    }
+
+    // Cells not written by any transaction are read from the initial state.
+    assert_eq!(versioned_state.get_nonce_at(4, contract_address).unwrap(), Nonce::default());
+}
+
+#[rstest]
+fn test_write_after_read_invalidation(versioned_state: VersionedState<DictStateReader>) {
+    // The second transaction executes first, and observes the initial value.
+    let tx2_reads = execute_increment(&versioned_state, 2, "0x10");
+    assert!(versioned_state.validate_reads(2, &tx2_reads));
+
+    // The first transaction writes the value read by the second one, which is invalidated.
+    let tx1_reads = execute_increment(&versioned_state, 1, "0x10");
+    assert!(versioned_state.validate_reads(1, &tx1_reads));
+    assert!(!versioned_state.validate_reads(2, &tx2_reads));
+
+    // Re-execution observes the write of the first transaction, and replaces the previous writes.
+    let tx2_reads = execute_increment(&versioned_state, 2, "0x10");
+    assert!(versioned_state.validate_reads(2, &tx2_reads));
+
+    let contract_address = contract_address!(CONTRACT_ADDRESS);
+    for (tx_index, expected_value) in [(2, INITIAL_VALUE + 1), (3, INITIAL_VALUE + 2)] {
+        let mut state = CachedState::from(initial_state());
+        versioned_state.apply_up_to(tx_index, &mut state).unwrap();
+        assert_eq!(
+            state.to_state_diff().storage_updates[&contract_address][&storage_key("0x10")],
+            stark_felt!(expected_value)
+        );
+    }
+}
+
+#[rstest]
+fn test_reexecution_discards_previous_writes(versioned_state: VersionedState<DictStateReader>) {
+    let contract_address = contract_address!(CONTRACT_ADDRESS);
+    let no_classes = ContractClassMapping::default();
+    versioned_state.apply_writes(1, &storage_writes(&[("0x10", 10), ("0x20", 20)]), &no_classes);
+    let tx2_reads = execute_increment(&versioned_state, 2, "0x20");
+    assert!(versioned_state.validate_reads(2, &tx2_reads));
+
+    // The re-execution of the first transaction no longer writes the value read by the second.
+    versioned_state.apply_writes(1, &storage_writes(&[("0x10", 11)]), &no_classes);
+    assert!(!versioned_state.validate_reads(2, &tx2_reads));
+    assert_eq!(
+        versioned_state.get_storage_at(2, contract_address, storage_key("0x20")).unwrap(),
+        StarkFelt::ZERO
+    );
+
+    let tx2_reads = execute_increment(&versioned_state, 2, "0x20");
+    assert!(versioned_state.validate_reads(2, &tx2_reads));
+    let mut state = CachedState::from(initial_state());
+    versioned_state.apply_up_to(3, &mut state).unwrap();
+    assert_eq!(
+        state.to_state_diff().storage_updates[&contract_address],
+        indexmap! {
+            storage_key("0x10") => stark_felt!(11_u8),
+            storage_key("0x20") => stark_felt!(1_u8),
+        }
+    );
+}
+
+#[rstest]
+fn test_concurrent_execution(versioned_state: VersionedState<DictStateReader>) {
+    const N_TXS: usize = 8;
+    // Half of the transactions increment the same cell; the rest are independent of each other.
+    let key_of = |tx_index: TxIndex| {
+        if tx_index % 2 == 0 { "0x10".to_string() } else { format!("{:#x}", 0x100 + tx_index) }
+    };
+
+    let mut tx_reads: HashMap<TxIndex, StateReads> = thread::scope(|scope| {
+        let handles: Vec<_> = (0..N_TXS)
+            .map(|tx_index| {
+                let (versioned_state, key) = (&versioned_state, key_of(tx_index));
+                scope.spawn(move || (tx_index, execute_increment(versioned_state, tx_index, &key)))
+            })
+            .collect();
+        handles.into_iter().map(|handle| handle.join().unwrap()).collect()
+    });
+
+    // Validate in order, re-executing invalidated transactions.
+    for tx_index in 0..N_TXS {
+        if !versioned_state.validate_reads(tx_index, &tx_reads[&tx_index]) {
+            tx_reads
+                .insert(tx_index, execute_increment(&versioned_state, tx_index, &key_of(tx_index)));
+            assert!(versioned_state.validate_reads(tx_index, &tx_reads[&tx_index]));
+        }
+    }
+
+    let contract_address = contract_address!(CONTRACT_ADDRESS);
+    let mut state = CachedState::from(initial_state());
+    versioned_state.apply_up_to(N_TXS, &mut state).unwrap();
+    let storage_updates = &state.to_state_diff().storage_updates[&contract_address];
+    assert_eq!(storage_updates[&storage_key("0x10")], stark_felt!(INITIAL_VALUE + 4));
+    for tx_index in (1..N_TXS).step_by(2) {
+        assert_eq!(storage_updates[&storage_key(&key_of(tx_index))], stark_felt!(1_u8));
+    }
+}
+
+#[rstest]
+fn test_deploy_of_class_declared_in_block(versioned_state: VersionedState<DictStateReader>) {
+    let class_hash = class_hash!(TEST_CLASS_HASH);
+
+    // The deployment executes first, before the class is declared, and fails to read it.
+    let (tx2_reads, deployed) = execute_deploy(&versioned_state, 2, class_hash);
+    assert!(!deployed);
+    assert!(versioned_state.validate_reads(2, &tx2_reads));
+
+    // The declaration of the first transaction invalidates the failed read.
+    let tx1_reads = execute_declare(&versioned_state, 1, class_hash);
+    assert!(versioned_state.validate_reads(1, &tx1_reads));
+    assert!(!versioned_state.validate_reads(2, &tx2_reads));
+
+    // Re-execution observes the declared class.
+    let (tx2_reads, deployed) = execute_deploy(&versioned_state, 2, class_hash);
+    assert!(deployed);
+    assert!(versioned_state.validate_reads(2, &tx2_reads));
+
+    let mut state = CachedState::from(initial_state());
+    versioned_state.apply_up_to(3, &mut state).unwrap();
+    assert_eq!(
+        state.declared_classes().unwrap(),
+        HashMap::from([(class_hash, get_test_contract_class())])
+    );
+    let state_diff = state.to_state_diff();
+    assert_eq!(
+        state_diff.class_hash_to_compiled_class_hash,
+        indexmap! { class_hash => CompiledClassHash(stark_felt!(COMPILED_CLASS_HASH)) }
+    );
+    assert_eq!(
+        state_diff.address_to_class_hash,
+        indexmap! { contract_address!(DEPLOYED_CONTRACT_ADDRESS) => class_hash }
+    );
+}
@@ -6,6 +6,7 @@ pub mod dict_state_reader;
 pub mod initial_test_state;
 pub mod invoke;
 pub mod prices;
+pub mod state_reader_conformance;
 pub mod struct_impls;
 
 use std::collections::HashMap;
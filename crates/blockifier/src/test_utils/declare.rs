@@ -7,8 +7,12 @@ use starknet_api::transaction::{
 };
 
 use crate::execution::contract_class::ClassInfo;
+#[cfg(feature = "devnet")]
+use crate::test_utils::contracts::FeatureContract;
 use crate::test_utils::default_testing_resource_bounds;
 use crate::transaction::account_transaction::AccountTransaction;
+#[cfg(feature = "devnet")]
+use crate::transaction::system_declare_batch::SystemDeclareBatch;
 use crate::transaction::transactions::DeclareTransaction;
 
 #[derive(Clone)]
@@ -122,3 +126,18 @@ pub fn declare_tx(declare_tx_args: DeclareTxArgs, class_info: ClassInfo) -> Acco
         .unwrap(),
     )
 }
+
+/// Creates a batch declaring the given feature contracts, with the given compiled class hashes.
+#[cfg(feature = "devnet")]
+pub fn system_declare_batch(
+    contracts: &[(FeatureContract, CompiledClassHash)],
+) -> SystemDeclareBatch {
+    SystemDeclareBatch::new(
+        contracts
+            .iter()
+            .map(|(contract, compiled_class_hash)| {
+                (contract.get_class_hash(), *compiled_class_hash, contract.get_class())
+            })
+            .collect(),
+    )
+}
@@ -10,6 +10,32 @@ use crate::state::errors::StateError;
 use crate::state::state_api::{StateReader, StateResult};
 
 /// A simple implementation of `StateReader` using `HashMap`s as storage.
+/// Missing entries are read as zero (default) values, except for classes, which are undeclared.
+///
+/// ```
+/// use blockifier::state::cached_state::CachedState;
+/// use blockifier::state::state_api::StateReader;
+/// use blockifier::test_utils::dict_state_reader::DictStateReader;
+/// use blockifier::test_utils::get_test_contract_class;
+/// use starknet_api::core::{ClassHash, ContractAddress, Nonce, PatriciaKey};
+/// use starknet_api::hash::{StarkFelt, StarkHash};
+/// use starknet_api::state::StorageKey;
+/// use starknet_api::{class_hash, contract_address, patricia_key, stark_felt};
+///
+/// let (contract_address, class_hash) = (contract_address!("0x100"), class_hash!("0x110"));
+/// let key = StorageKey(patricia_key!("0x10"));
+/// let state = CachedState::from(
+///     DictStateReader::default()
+///         .with_class(class_hash, get_test_contract_class())
+///         .with_deployed_contract(contract_address, class_hash)
+///         .with_storage(contract_address, key, stark_felt!(7_u8)),
+/// );
+///
+/// assert_eq!(state.get_class_hash_at(contract_address).unwrap(), class_hash);
+/// assert_eq!(state.get_storage_at(contract_address, key).unwrap(), stark_felt!(7_u8));
+/// assert_eq!(state.get_nonce_at(contract_address).unwrap(), Nonce::default());
+/// assert!(state.get_compiled_contract_class(class_hash!("0x111")).is_err());
+/// ```
 #[derive(Debug, Default)]
 pub struct DictStateReader {
     pub storage_view: HashMap<StorageEntry, StarkFelt>,
@@ -19,6 +45,46 @@ pub struct DictStateReader {
     pub class_hash_to_compiled_class_hash: HashMap<ClassHash, CompiledClassHash>,
 }
 
+impl DictStateReader {
+    pub fn with_storage(
+        mut self,
+        contract_address: ContractAddress,
+        key: StorageKey,
+        value: StarkFelt,
+    ) -> Self {
+        self.storage_view.insert((contract_address, key), value);
+        self
+    }
+
+    pub fn with_nonce(mut self, contract_address: ContractAddress, nonce: Nonce) -> Self {
+        self.address_to_nonce.insert(contract_address, nonce);
+        self
+    }
+
+    pub fn with_deployed_contract(
+        mut self,
+        contract_address: ContractAddress,
+        class_hash: ClassHash,
+    ) -> Self {
+        self.address_to_class_hash.insert(contract_address, class_hash);
+        self
+    }
+
+    pub fn with_class(mut self, class_hash: ClassHash, contract_class: ContractClass) -> Self {
+        self.class_hash_to_class.insert(class_hash, contract_class);
+        self
+    }
+
+    pub fn with_compiled_class_hash(
+        mut self,
+        class_hash: ClassHash,
+        compiled_class_hash: CompiledClassHash,
+    ) -> Self {
+        self.class_hash_to_compiled_class_hash.insert(class_hash, compiled_class_hash);
+        self
+    }
+}
+
 impl StateReader for DictStateReader {
     fn get_storage_at(
         &self,
@@ -0,0 +1,53 @@
+//! Checks that a [`StateReader`] implementation follows the semantics expected by the blockifier;
+//! e.g., of reads of contracts that are not deployed. Readers run these against a state in which
+//! the given address is not deployed, and the given class is not declared.
+
+use std::sync::Arc;
+
+use starknet_api::core::{ClassHash, ContractAddress, Nonce};
+use starknet_api::state::StorageKey;
+
+use crate::state::cached_state::CachedState;
+use crate::state::errors::StateError;
+use crate::state::state_api::{StateReader, StateResult};
+
+/// Asserts that the given read is either of the default value, or fails as the contract is not
+/// deployed.
+fn assert_undeployed_read<T: Default + PartialEq + std::fmt::Debug>(
+    read: StateResult<T>,
+    contract_address: ContractAddress,
+) {
+    match read {
+        Ok(value) => assert_eq!(value, T::default()),
+        Err(StateError::ContractNotDeployed(address)) => assert_eq!(address, contract_address),
+        Err(error) => panic!("Unexpected error reading undeployed {contract_address:?}: {error}"),
+    }
+}
+
+/// Checks reads of the given contract, which must not be deployed in the given reader.
+pub fn check_undeployed_contract_reads<S: StateReader>(
+    reader: S,
+    undeployed_address: ContractAddress,
+) {
+    let key = StorageKey::default();
+    assert_undeployed_read(reader.get_storage_at(undeployed_address, key), undeployed_address);
+    assert_undeployed_read(reader.get_nonce_at(undeployed_address), undeployed_address);
+    assert_undeployed_read(reader.get_class_hash_at(undeployed_address), undeployed_address);
+
+    // A cached state reads its nonce as zero, unless its nonce reads are strict.
+    let reader = Arc::new(reader);
+    let state = CachedState::from(Arc::clone(&reader));
+    assert_eq!(state.get_nonce_at(undeployed_address).unwrap(), Nonce::default());
+    let strict_state = CachedState::from(reader).with_strict_nonce_reads(true);
+    assert_undeployed_read(strict_state.get_nonce_at(undeployed_address), undeployed_address);
+}
+
+/// Checks reads of the given class, which must not be declared in the given reader.
+pub fn check_undeclared_class_reads<S: StateReader>(reader: &S, undeclared_class_hash: ClassHash) {
+    match reader.get_compiled_contract_class(undeclared_class_hash) {
+        Err(StateError::UndeclaredClassHash(class_hash)) => {
+            assert_eq!(class_hash, undeclared_class_hash)
+        }
+        read => panic!("Expected an undeclared class {undeclared_class_hash:?}, got {read:?}."),
+    }
+}
@@ -78,6 +78,18 @@ impl CallEntryPoint {
     }
 }
 
+impl EntryPointExecutionContext {
+    /// Returns an invoke context of a default (deprecated) transaction in the given block. Limits
+    /// the number of steps by resource bounds.
+    pub fn new_invoke_for_testing(block_context: BlockContext) -> Self {
+        let tx_context = TransactionContext {
+            block_context,
+            tx_info: TransactionInfo::Deprecated(DeprecatedTransactionInfo::default()),
+        };
+        Self::new_invoke(Arc::new(tx_context), true).unwrap()
+    }
+}
+
 impl VersionedConstants {
     pub fn create_for_testing() -> Self {
         Self::latest_constants().clone()
@@ -119,19 +131,19 @@ impl BlockInfo {
 
 impl BlockContext {
     pub fn create_for_testing() -> Self {
-        Self {
-            block_info: BlockInfo::create_for_testing(),
-            chain_info: ChainInfo::create_for_testing(),
-            versioned_constants: VersionedConstants::create_for_testing(),
-        }
+        Self::new(
+            BlockInfo::create_for_testing(),
+            ChainInfo::create_for_testing(),
+            VersionedConstants::create_for_testing(),
+        )
     }
 
     pub fn create_for_account_testing() -> Self {
-        Self {
-            block_info: BlockInfo::create_for_testing(),
-            chain_info: ChainInfo::create_for_testing(),
-            versioned_constants: VersionedConstants::create_for_account_testing(),
-        }
+        Self::new(
+            BlockInfo::create_for_testing(),
+            ChainInfo::create_for_testing(),
+            VersionedConstants::create_for_account_testing(),
+        )
     }
 
     pub fn create_for_account_testing_with_kzg(use_kzg_da: bool) -> Self {
@@ -2,6 +2,8 @@ pub mod account_transaction;
 pub mod constants;
 pub mod errors;
 pub mod objects;
+#[cfg(feature = "devnet")]
+pub mod system_declare_batch;
 #[cfg(any(feature = "testing", test))]
 pub mod test_utils;
 pub mod transaction_execution;
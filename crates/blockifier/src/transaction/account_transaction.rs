@@ -4,15 +4,18 @@ use cairo_vm::vm::runners::cairo_runner::ExecutionResources;
 use itertools::concat;
 use starknet_api::calldata;
 use starknet_api::core::{ContractAddress, EntryPointSelector};
+use starknet_api::data_availability::DataAvailabilityMode;
 use starknet_api::deprecated_contract_class::EntryPointType;
 use starknet_api::hash::StarkFelt;
 use starknet_api::transaction::{Calldata, Fee, ResourceBounds, TransactionVersion};
 
 use crate::abi::abi_utils::selector_from_name;
+use crate::blockifier::block::GasPriceProvider;
 use crate::context::{BlockContext, TransactionContext};
 use crate::execution::call_info::{CallInfo, Retdata};
 use crate::execution::contract_class::ContractClass;
 use crate::execution::entry_point::{CallEntryPoint, CallType, EntryPointExecutionContext};
+use crate::execution::syscalls::hint_processor::SyscallMetricsMap;
 use crate::fee::actual_cost::{ActualCost, ActualCostBuilder};
 use crate::fee::fee_checks::{FeeCheckReportFields, PostExecutionReport};
 use crate::fee::fee_utils::{get_fee_by_gas_vector, verify_can_pay_committed_bounds};
@@ -29,10 +32,12 @@ use crate::transaction::objects::{
     TransactionInfoCreator, TransactionPreValidationResult,
 };
 use crate::transaction::transaction_types::TransactionType;
-use crate::transaction::transaction_utils::update_remaining_gas;
+use crate::transaction::transaction_utils::{
+    replace_fee_transfer_approximation, update_remaining_gas,
+};
 use crate::transaction::transactions::{
     DeclareTransaction, DeployAccountTransaction, Executable, ExecutableTransaction,
-    InvokeTransaction, ValidatableTransaction,
+    ExecutionFlags, InvokeTransaction, ValidatableTransaction,
 };
 
 #[cfg(test)]
@@ -164,6 +169,7 @@ impl AccountTransaction {
         strict_nonce_check: bool,
     ) -> TransactionPreValidationResult<()> {
         let tx_info = &tx_context.tx_info;
+        Self::verify_supported_fields(tx_info)?;
         Self::handle_nonce(state, tx_info, strict_nonce_check)?;
 
         if charge_fee && tx_info.enforce_fee()? {
@@ -207,7 +213,7 @@ impl AccountTransaction {
                     })?;
                 }
 
-                let actual_l1_gas_price = block_info.gas_prices.get_gas_price_by_fee_type(fee_type);
+                let actual_l1_gas_price = block_context.gas_price(fee_type);
                 if max_l1_gas_price < actual_l1_gas_price.into() {
                     return Err(TransactionFeeError::MaxL1GasPriceTooLow {
                         max_l1_gas_price,
@@ -227,6 +233,33 @@ impl AccountTransaction {
         Ok(())
     }
 
+    /// Rejects V3 fields the protocol does not support yet: paymasters and non-L1 data
+    /// availability modes.
+    fn verify_supported_fields(tx_info: &TransactionInfo) -> TransactionPreValidationResult<()> {
+        let TransactionInfo::Current(context) = tx_info else {
+            return Ok(());
+        };
+
+        if !context.paymaster_data.0.is_empty() {
+            return Err(TransactionPreValidationError::UnsupportedPaymasterData {
+                paymaster_data: context.paymaster_data.clone(),
+            });
+        }
+        for (field, mode) in [
+            ("nonce", context.nonce_data_availability_mode),
+            ("fee", context.fee_data_availability_mode),
+        ] {
+            if mode != DataAvailabilityMode::L1 {
+                return Err(TransactionPreValidationError::UnsupportedDataAvailabilityMode {
+                    field: field.to_string(),
+                    mode,
+                });
+            }
+        }
+
+        Ok(())
+    }
+
     fn handle_nonce(
         state: &mut dyn State,
         tx_info: &TransactionInfo,
@@ -254,20 +287,95 @@ impl AccountTransaction {
         })
     }
 
+    /// Runs the validation, if required, ahead of the execution in the given context; the syscalls
+    /// of the validation count towards the syscall limit of the execution, and their metrics (if
+    /// collected) are accumulated with those of the execution.
     fn handle_validate_tx(
         &self,
         state: &mut dyn State,
         resources: &mut ExecutionResources,
-        tx_context: Arc<TransactionContext>,
+        execution_context: &mut EntryPointExecutionContext,
         remaining_gas: &mut u64,
         validate: bool,
         limit_steps_by_resources: bool,
     ) -> TransactionExecutionResult<Option<CallInfo>> {
-        if validate {
-            self.validate_tx(state, resources, tx_context, remaining_gas, limit_steps_by_resources)
-        } else {
-            Ok(None)
+        if !validate {
+            Self::charge_skipped_validation(resources, &execution_context.tx_context);
+            return Ok(None);
+        }
+
+        let mut validate_context = EntryPointExecutionContext::new_validate(
+            execution_context.tx_context.clone(),
+            limit_steps_by_resources,
+        )?;
+        validate_context.syscall_metrics = execution_context.syscall_metrics.take();
+        let validate_call_info =
+            self.validate_tx_in_context(state, resources, &mut validate_context, remaining_gas)?;
+        execution_context.n_executed_syscalls = validate_context.n_executed_syscalls;
+        execution_context.syscall_metrics = validate_context.syscall_metrics;
+
+        Ok(validate_call_info)
+    }
+
+    /// Adds the validation approximation to the resources of a transaction executed without
+    /// validation, if the block context charges it.
+    fn charge_skipped_validation(
+        resources: &mut ExecutionResources,
+        tx_context: &TransactionContext,
+    ) {
+        let block_context = &tx_context.block_context;
+        if block_context.charge_skipped_validation() {
+            *resources += block_context.versioned_constants.validate_approximation();
+        }
+    }
+
+    /// Runs the validation entry point within the given (validation) execution context.
+    fn validate_tx_in_context(
+        &self,
+        state: &mut dyn State,
+        resources: &mut ExecutionResources,
+        context: &mut EntryPointExecutionContext,
+        remaining_gas: &mut u64,
+    ) -> TransactionExecutionResult<Option<CallInfo>> {
+        let tx_info = &context.tx_context.tx_info;
+        if tx_info.is_v0() {
+            return Ok(None);
+        }
+
+        let storage_address = tx_info.sender_address();
+        let validate_call = CallEntryPoint {
+            entry_point_type: EntryPointType::External,
+            entry_point_selector: self.validate_entry_point_selector(),
+            calldata: self.validate_entrypoint_calldata(),
+            class_hash: None,
+            code_address: None,
+            storage_address,
+            caller_address: ContractAddress::default(),
+            call_type: CallType::Call,
+            initial_gas: *remaining_gas,
+        };
+
+        let validate_call_info = validate_call
+            .execute(state, resources, context)
+            .map_err(TransactionExecutionError::ValidateTransactionError)?;
+
+        // Validate return data.
+        let class_hash = state.get_class_hash_at(storage_address)?;
+        let contract_class = state.get_compiled_contract_class(class_hash)?;
+        if let ContractClass::V1(_) = contract_class {
+            // The account contract class is a Cairo 1.0 contract; the `validate` entry point should
+            // return `VALID`.
+            let expected_retdata = retdata![StarkFelt::try_from(constants::VALIDATE_RETDATA)?];
+            if validate_call_info.execution.retdata != expected_retdata {
+                return Err(TransactionExecutionError::InvalidValidateReturnData {
+                    actual: validate_call_info.execution.retdata,
+                });
+            }
         }
+
+        update_remaining_gas(remaining_gas, &validate_call_info);
+
+        Ok(Some(validate_call_info))
     }
 
     fn handle_fee(
@@ -301,7 +409,7 @@ impl AccountTransaction {
         let TransactionContext { block_context, tx_info } = tx_context.as_ref();
 
         // TODO(Gilad): add test that correct fee address is taken, once we add V3 test support.
-        let storage_address = block_context.chain_info.fee_token_address(&tx_info.fee_type());
+        let storage_address = block_context.fee_token_address(&tx_info.fee_type());
         let fee_transfer_call = CallEntryPoint {
             class_hash: None,
             code_address: None,
@@ -320,6 +428,8 @@ impl AccountTransaction {
         };
 
         let mut context = EntryPointExecutionContext::new_invoke(tx_context, true)?;
+        // The fee transfer is not issued by the account, hence not subject to the syscall limit.
+        context.max_syscalls_per_tx = None;
 
         Ok(fee_transfer_call
             .execute(state, &mut ExecutionResources::default(), &mut context)
@@ -351,6 +461,7 @@ impl AccountTransaction {
         let mut resources = ExecutionResources::default();
         let validate_call_info: Option<CallInfo>;
         let execute_call_info: Option<CallInfo>;
+        let syscall_metrics: Option<SyscallMetricsMap>;
         if matches!(self, Self::DeployAccount(_)) {
             // Handle `DeployAccount` transactions separately, due to different order of things.
             // Also, the execution context required form the `DeployAccount` execute phase is
@@ -359,27 +470,42 @@ impl AccountTransaction {
                 EntryPointExecutionContext::new_validate(tx_context.clone(), charge_fee)?;
             execute_call_info =
                 self.run_execute(state, &mut resources, &mut execution_context, remaining_gas)?;
-            validate_call_info = self.handle_validate_tx(
-                state,
-                &mut resources,
-                tx_context.clone(),
-                remaining_gas,
-                validate,
-                charge_fee,
-            )?;
+            validate_call_info = if validate {
+                // The events and messages of the validation are ordered after those of the
+                // constructor, whose syscalls count towards the syscall limit of the validation.
+                let mut validate_context =
+                    EntryPointExecutionContext::new_validate(tx_context.clone(), charge_fee)?;
+                validate_context.n_emitted_events = execution_context.n_emitted_events;
+                validate_context.n_sent_messages_to_l1 = execution_context.n_sent_messages_to_l1;
+                validate_context.n_executed_syscalls = execution_context.n_executed_syscalls;
+                validate_context.syscall_metrics = execution_context.syscall_metrics.take();
+                let validate_call_info = self.validate_tx_in_context(
+                    state,
+                    &mut resources,
+                    &mut validate_context,
+                    remaining_gas,
+                )?;
+                execution_context.syscall_metrics = validate_context.syscall_metrics;
+                validate_call_info
+            } else {
+                Self::charge_skipped_validation(&mut resources, &tx_context);
+                None
+            };
+            syscall_metrics = execution_context.syscall_metrics;
         } else {
             let mut execution_context =
                 EntryPointExecutionContext::new_invoke(tx_context.clone(), charge_fee)?;
             validate_call_info = self.handle_validate_tx(
                 state,
                 &mut resources,
-                tx_context.clone(),
+                &mut execution_context,
                 remaining_gas,
                 validate,
                 charge_fee,
             )?;
             execute_call_info =
                 self.run_execute(state, &mut resources, &mut execution_context, remaining_gas)?;
+            syscall_metrics = execution_context.syscall_metrics;
         }
 
         let actual_cost = self
@@ -397,6 +523,7 @@ impl AccountTransaction {
                 validate_call_info,
                 execute_call_info,
                 actual_cost,
+                syscall_metrics.unwrap_or_default(),
             )),
         }
     }
@@ -416,7 +543,7 @@ impl AccountTransaction {
         let validate_call_info = self.handle_validate_tx(
             state,
             &mut resources,
-            tx_context.clone(),
+            &mut execution_context,
             remaining_gas,
             validate,
             charge_fee,
@@ -426,7 +553,7 @@ impl AccountTransaction {
             &validate_call_info,
             &self.tx_type(),
             self.calldata_length(),
-        );
+        )?;
 
         // Save the state changes resulting from running `validate_tx`, to be used later for
         // resource and fee calculation.
@@ -469,12 +596,19 @@ impl AccountTransaction {
 
                 // Post-execution checks.
                 let post_execution_report = PostExecutionReport::new(
-                    &mut execution_state,
+                    &execution_state,
                     &tx_context,
                     &actual_cost,
                     charge_fee,
                 )?;
                 match post_execution_report.error() {
+                    Some(post_execution_error)
+                        if tx_context.block_context.reject_fee_check_failures() =>
+                    {
+                        // Post-execution check failed, and the block rejects such transactions.
+                        execution_state.abort();
+                        Err(post_execution_error.into())
+                    }
                     Some(post_execution_error) => {
                         // Post-execution check failed. Revert the execution, compute the final fee
                         // to charge and recompute resources used (to be consistent with other
@@ -488,6 +622,7 @@ impl AccountTransaction {
                                 actual_fee: post_execution_report.recommended_fee(),
                                 ..revert_cost
                             },
+                            execution_context.syscall_metrics.unwrap_or_default(),
                         ))
                     }
                     None => {
@@ -497,6 +632,7 @@ impl AccountTransaction {
                             validate_call_info,
                             execute_call_info,
                             actual_cost,
+                            execution_context.syscall_metrics.unwrap_or_default(),
                         ))
                     }
                 }
@@ -513,6 +649,7 @@ impl AccountTransaction {
                         actual_fee: post_execution_report.recommended_fee(),
                         ..revert_cost
                     },
+                    execution_context.syscall_metrics.unwrap_or_default(),
                 ))
             }
         }
@@ -547,6 +684,52 @@ impl AccountTransaction {
         self.run_revertible(state, tx_context, remaining_gas, validate, charge_fee)
     }
 
+    /// Returns the transaction with the query bit set in its version.
+    pub fn into_query(self) -> Self {
+        match self {
+            Self::Declare(tx) => Self::Declare(tx.into_query()),
+            Self::DeployAccount(tx) => {
+                Self::DeployAccount(DeployAccountTransaction { only_query: true, ..tx })
+            }
+            Self::Invoke(tx) => Self::Invoke(InvokeTransaction { only_query: true, ..tx }),
+        }
+    }
+
+    /// Estimates the fee of the transaction, at the gas prices of the given block: executes it as a
+    /// query, with validation but without the fee checks and the fee transfer, and discards its
+    /// state changes. The actual fee is computed even if the transaction does not enforce a fee.
+    pub fn estimate_fee<S: StateReader>(
+        self,
+        state: &mut CachedState<S>,
+        block_context: &BlockContext,
+    ) -> TransactionExecutionResult<TransactionExecutionInfo> {
+        let query_tx = self.into_query();
+        let tx_info = query_tx.create_tx_info();
+        let mut transactional_state = CachedState::create_transactional(state);
+        let execution_flags = ExecutionFlags { charge_fee: false, ..Default::default() };
+        let execution_result =
+            query_tx.execute_raw(&mut transactional_state, block_context, execution_flags);
+        transactional_state.abort();
+
+        let mut tx_execution_info = execution_result?;
+        tx_execution_info.actual_fee =
+            tx_info.calculate_tx_fee(&tx_execution_info.actual_resources, block_context)?;
+        Ok(tx_execution_info)
+    }
+
+    /// Same as `estimate_fee`, at the gas prices of the given provider rather than those of the
+    /// block.
+    pub fn estimate_fee_with_gas_prices<S: StateReader>(
+        self,
+        state: &mut CachedState<S>,
+        block_context: &BlockContext,
+        gas_price_provider: &impl GasPriceProvider,
+    ) -> TransactionExecutionResult<TransactionExecutionInfo> {
+        let mut block_context = block_context.clone();
+        block_context.block_info.gas_prices = gas_price_provider.gas_prices();
+        self.estimate_fee(state, &block_context)
+    }
+
     pub fn to_actual_cost_builder(
         &self,
         tx_context: Arc<TransactionContext>,
@@ -569,14 +752,13 @@ impl<S: StateReader> ExecutableTransaction<S> for AccountTransaction {
         self,
         state: &mut TransactionalState<'_, S>,
         block_context: &BlockContext,
-        charge_fee: bool,
-        validate: bool,
+        execution_flags: ExecutionFlags,
     ) -> TransactionExecutionResult<TransactionExecutionInfo> {
+        let ExecutionFlags { charge_fee, validate, strict_nonce_check } = execution_flags;
         let tx_context = Arc::new(block_context.to_tx_context(&self));
         self.verify_tx_version(tx_context.tx_info.version())?;
 
         // Nonce and fee check should be done before running user code.
-        let strict_nonce_check = true;
         self.perform_pre_validation_stage(state, &tx_context, charge_fee, strict_nonce_check)?;
 
         // Run validation and execution.
@@ -585,11 +767,13 @@ impl<S: StateReader> ExecutableTransaction<S> for AccountTransaction {
             validate_call_info,
             execute_call_info,
             revert_error,
+            syscall_metrics,
             final_cost:
                 ActualCost {
                     actual_fee: final_fee,
                     da_gas: final_da_gas,
-                    actual_resources: final_resources,
+                    actual_resources: mut final_resources,
+                    n_reverted_steps,
                 },
         } = self.run_or_revert(
             state,
@@ -600,6 +784,15 @@ impl<S: StateReader> ExecutableTransaction<S> for AccountTransaction {
         )?;
 
         let fee_transfer_call_info = self.handle_fee(state, tx_context, final_fee, charge_fee)?;
+        if let Some(fee_transfer_call_info) = &fee_transfer_call_info {
+            if block_context.count_fee_transfer_resources() {
+                replace_fee_transfer_approximation(
+                    &mut final_resources,
+                    block_context.versioned_constants.fee_transfer_approximation(),
+                    &fee_transfer_call_info.resources,
+                )?;
+            }
+        }
 
         let tx_execution_info = TransactionExecutionInfo {
             validate_call_info,
@@ -608,7 +801,9 @@ impl<S: StateReader> ExecutableTransaction<S> for AccountTransaction {
             actual_fee: final_fee,
             da_gas: final_da_gas,
             actual_resources: final_resources,
+            n_reverted_steps,
             revert_error,
+            syscall_metrics,
         };
         Ok(tx_execution_info)
     }
@@ -630,6 +825,7 @@ struct ValidateExecuteCallInfo {
     execute_call_info: Option<CallInfo>,
     revert_error: Option<String>,
     final_cost: ActualCost,
+    syscall_metrics: SyscallMetricsMap,
 }
 
 impl ValidateExecuteCallInfo {
@@ -637,20 +833,29 @@ impl ValidateExecuteCallInfo {
         validate_call_info: Option<CallInfo>,
         execute_call_info: Option<CallInfo>,
         final_cost: ActualCost,
+        syscall_metrics: SyscallMetricsMap,
     ) -> Self {
-        Self { validate_call_info, execute_call_info, revert_error: None, final_cost }
+        Self {
+            validate_call_info,
+            execute_call_info,
+            revert_error: None,
+            final_cost,
+            syscall_metrics,
+        }
     }
 
     pub fn new_reverted(
         validate_call_info: Option<CallInfo>,
         revert_error: String,
         final_cost: ActualCost,
+        syscall_metrics: SyscallMetricsMap,
     ) -> Self {
         Self {
             validate_call_info,
             execute_call_info: None,
             revert_error: Some(revert_error),
             final_cost,
+            syscall_metrics,
         }
     }
 }
@@ -666,44 +871,6 @@ impl ValidatableTransaction for AccountTransaction {
     ) -> TransactionExecutionResult<Option<CallInfo>> {
         let mut context =
             EntryPointExecutionContext::new_validate(tx_context, limit_steps_by_resources)?;
-        let tx_info = &context.tx_context.tx_info;
-        if tx_info.is_v0() {
-            return Ok(None);
-        }
-
-        let storage_address = tx_info.sender_address();
-        let validate_call = CallEntryPoint {
-            entry_point_type: EntryPointType::External,
-            entry_point_selector: self.validate_entry_point_selector(),
-            calldata: self.validate_entrypoint_calldata(),
-            class_hash: None,
-            code_address: None,
-            storage_address,
-            caller_address: ContractAddress::default(),
-            call_type: CallType::Call,
-            initial_gas: *remaining_gas,
-        };
-
-        let validate_call_info = validate_call
-            .execute(state, resources, &mut context)
-            .map_err(TransactionExecutionError::ValidateTransactionError)?;
-
-        // Validate return data.
-        let class_hash = state.get_class_hash_at(storage_address)?;
-        let contract_class = state.get_compiled_contract_class(class_hash)?;
-        if let ContractClass::V1(_) = contract_class {
-            // The account contract class is a Cairo 1.0 contract; the `validate` entry point should
-            // return `VALID`.
-            let expected_retdata = retdata![StarkFelt::try_from(constants::VALIDATE_RETDATA)?];
-            if validate_call_info.execution.retdata != expected_retdata {
-                return Err(TransactionExecutionError::InvalidValidateReturnData {
-                    actual: validate_call_info.execution.retdata,
-                });
-            }
-        }
-
-        update_remaining_gas(remaining_gas, &validate_call_info);
-
-        Ok(Some(validate_call_info))
+        self.validate_tx_in_context(state, resources, &mut context, remaining_gas)
     }
 }
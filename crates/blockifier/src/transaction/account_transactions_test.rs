@@ -1,4 +1,5 @@
 use std::collections::{HashMap, HashSet};
+use std::num::NonZeroU128;
 use std::sync::Arc;
 
 use assert_matches::assert_matches;
@@ -9,10 +10,11 @@ use rstest::rstest;
 use starknet_api::core::{
     calculate_contract_address, ClassHash, ContractAddress, Nonce, PatriciaKey,
 };
+use starknet_api::data_availability::DataAvailabilityMode;
 use starknet_api::hash::{StarkFelt, StarkHash};
 use starknet_api::state::StorageKey;
 use starknet_api::transaction::{
-    Calldata, ContractAddressSalt, DeclareTransactionV2, Fee, ResourceBoundsMapping,
+    Calldata, ContractAddressSalt, DeclareTransactionV2, Fee, PaymasterData, ResourceBoundsMapping,
     TransactionHash, TransactionVersion,
 };
 use starknet_api::{calldata, class_hash, contract_address, patricia_key, stark_felt};
@@ -20,15 +22,17 @@ use starknet_api::{calldata, class_hash, contract_address, patricia_key, stark_f
 use crate::abi::abi_utils::{
     get_fee_token_var_address, get_storage_var_address, selector_from_name,
 };
-use crate::abi::constants as abi_constants;
+use crate::blockifier::block::GasPrices;
 use crate::context::BlockContext;
-use crate::execution::contract_class::{ContractClass, ContractClassV1};
+use crate::execution::contract_class::{ContractClass, ContractClassV0, ContractClassV1};
+use crate::execution::deprecated_syscalls::DeprecatedSyscallSelector;
 use crate::execution::entry_point::EntryPointExecutionContext;
 use crate::execution::errors::EntryPointExecutionError;
 use crate::execution::execution_utils::{felt_to_stark_felt, stark_felt_to_felt};
+use crate::fee::actual_cost::RevertedStepsPricing;
 use crate::fee::fee_utils::{calculate_tx_gas_vector, get_fee_by_gas_vector};
 use crate::fee::gas_usage::estimate_minimal_gas_vector;
-use crate::state::cached_state::{CachedState, StateChangesCount};
+use crate::state::cached_state::{CachedState, StateChanges, StateChangesCount};
 use crate::state::state_api::{State, StateReader};
 use crate::test_utils::contracts::FeatureContract;
 use crate::test_utils::declare::declare_tx;
@@ -36,21 +40,24 @@ use crate::test_utils::deploy_account::deploy_account_tx;
 use crate::test_utils::initial_test_state::{fund_account, test_state};
 use crate::test_utils::invoke::InvokeTxArgs;
 use crate::test_utils::{
-    create_calldata, create_trivial_calldata, CairoVersion, NonceManager, BALANCE,
-    DEFAULT_STRK_L1_GAS_PRICE, MAX_FEE, MAX_L1_GAS_AMOUNT, MAX_L1_GAS_PRICE,
+    create_calldata, create_trivial_calldata, get_raw_contract_class, CairoVersion, NonceManager,
+    BALANCE, DEFAULT_STRK_L1_GAS_PRICE, ERC20_CONTRACT_PATH, MAX_FEE, MAX_L1_GAS_AMOUNT,
+    MAX_L1_GAS_PRICE, TEST_CONTRACT_CAIRO0_PATH,
 };
 use crate::transaction::account_transaction::AccountTransaction;
-use crate::transaction::constants::TRANSFER_ENTRY_POINT_NAME;
-use crate::transaction::errors::TransactionExecutionError;
-use crate::transaction::objects::{FeeType, HasRelatedFeeType, TransactionInfoCreator};
+use crate::transaction::constants::{self, TRANSFER_ENTRY_POINT_NAME};
+use crate::transaction::errors::{
+    TransactionExecutionError, TransactionFeeError, TransactionPreValidationError,
+};
+use crate::transaction::objects::{FeeType, HasRelatedFeeType, Resource, TransactionInfoCreator};
 use crate::transaction::test_utils::{
     account_invoke_tx, block_context, calculate_class_info_for_testing,
     create_account_tx_for_validate_test, create_test_init_data, deploy_and_fund_account,
     l1_resource_bounds, max_fee, max_resource_bounds, run_invoke_tx, FaultyAccountTxCreatorArgs,
-    TestInitData, INVALID,
+    TestInitData, INVALID, VALID,
 };
 use crate::transaction::transaction_types::TransactionType;
-use crate::transaction::transactions::{DeclareTransaction, ExecutableTransaction};
+use crate::transaction::transactions::{DeclareTransaction, ExecutableTransaction, ExecutionFlags};
 use crate::{
     check_transaction_execution_error_for_invalid_scenario, declare_tx_args,
     deploy_account_tx_args, invoke_tx_args,
@@ -76,7 +83,7 @@ fn test_fee_enforcement(
 
     let account_tx = AccountTransaction::DeployAccount(deploy_account_tx);
     let enforce_fee = account_tx.create_tx_info().enforce_fee().unwrap();
-    let result = account_tx.execute(state, &block_context, true, true);
+    let result = account_tx.execute(state, &block_context, ExecutionFlags::default());
     assert_eq!(result.is_err(), enforce_fee);
 }
 
@@ -104,6 +111,213 @@ fn test_enforce_fee_false_works(block_context: BlockContext, #[case] version: Tr
     assert_eq!(tx_execution_info.actual_fee, Fee(0));
 }
 
+/// A fee transfer exceeding the balance of the sender fails with a typed error.
+#[rstest]
+fn test_fee_transfer_exceeding_balance(block_context: BlockContext) {
+    let account = FeatureContract::AccountWithoutValidations(CairoVersion::Cairo0);
+    let test_contract = FeatureContract::TestContract(CairoVersion::Cairo0);
+    let state = &mut test_state(&block_context.chain_info, 0, &[(account, 1), (test_contract, 1)]);
+    let account_tx = account_invoke_tx(invoke_tx_args! {
+        max_fee: Fee(MAX_FEE),
+        sender_address: account.get_instance_address(0),
+        calldata: create_trivial_calldata(test_contract.get_instance_address(0)),
+        version: TransactionVersion::ONE,
+    });
+    let tx_context = Arc::new(block_context.to_tx_context(&account_tx));
+
+    assert_matches!(
+        AccountTransaction::execute_fee_transfer(state, tx_context, Fee(1)).unwrap_err(),
+        TransactionExecutionError::TransactionFeeError(
+            TransactionFeeError::ExecuteFeeTransferError(_)
+        )
+    );
+}
+
+#[rstest]
+fn test_estimate_fee(
+    block_context: BlockContext,
+    #[values(TransactionVersion::ONE, TransactionVersion::THREE)] version: TransactionVersion,
+) {
+    let TestInitData { mut state, account_address, contract_address, mut nonce_manager } =
+        create_test_init_data(&block_context.chain_info, CairoVersion::Cairo0);
+    let invoke_args = invoke_tx_args! {
+        max_fee: Fee(MAX_FEE),
+        resource_bounds: l1_resource_bounds(MAX_L1_GAS_AMOUNT, MAX_L1_GAS_PRICE),
+        sender_address: account_address,
+        calldata: create_trivial_calldata(contract_address),
+        version,
+        nonce: nonce_manager.next(account_address),
+    };
+
+    // Estimate with no fee bounds, which would not be charged in real execution.
+    let estimation_info = account_invoke_tx(invoke_tx_args! {
+        max_fee: Fee(0),
+        resource_bounds: l1_resource_bounds(0, 0),
+        ..invoke_args.clone()
+    })
+    .estimate_fee(&mut state, &block_context)
+    .unwrap();
+    assert!(estimation_info.fee_transfer_call_info.is_none());
+    // Estimation does not change the state; e.g., the nonce is not incremented.
+    assert_eq!(state.get_nonce_at(account_address).unwrap(), Nonce::default());
+
+    let execution_info = account_invoke_tx(invoke_args)
+        .execute(&mut state, &block_context, ExecutionFlags::default())
+        .unwrap();
+    assert!(execution_info.fee_transfer_call_info.is_some());
+    assert_eq!(estimation_info.actual_resources, execution_info.actual_resources);
+    assert_eq!(estimation_info.actual_fee, execution_info.actual_fee);
+    assert_ne!(estimation_info.actual_fee, Fee(0));
+}
+
+/// Each execution flag controls its own stage, independently of the others.
+#[rstest]
+fn test_execution_flags(
+    block_context: BlockContext,
+    #[values(true, false)] charge_fee: bool,
+    #[values(true, false)] validate: bool,
+    #[values(true, false)] strict_nonce_check: bool,
+) {
+    let TestInitData { mut state, account_address, contract_address, .. } =
+        create_test_init_data(&block_context.chain_info, CairoVersion::Cairo0);
+    let execution_flags = ExecutionFlags { charge_fee, validate, strict_nonce_check };
+    let mut execute = |nonce: u8| {
+        let tx = account_invoke_tx(invoke_tx_args! {
+            max_fee: Fee(MAX_FEE),
+            sender_address: account_address,
+            calldata: create_trivial_calldata(contract_address),
+            version: TransactionVersion::ONE,
+            nonce: Nonce(stark_felt!(nonce)),
+        });
+        // Discard the state changes.
+        let mut transactional_state = CachedState::create_transactional(&mut state);
+        tx.execute_raw(&mut transactional_state, &block_context, execution_flags)
+    };
+
+    // The account nonce is zero; a nonce ahead of it is only accepted by the non-strict check.
+    let future_nonce_result = execute(1);
+    let execution_info = if strict_nonce_check {
+        assert_matches!(
+            future_nonce_result,
+            Err(TransactionExecutionError::TransactionPreValidationError(
+                TransactionPreValidationError::InvalidNonce { .. }
+            ))
+        );
+        execute(0).unwrap()
+    } else {
+        future_nonce_result.unwrap()
+    };
+
+    assert!(!execution_info.is_reverted());
+    assert_eq!(execution_info.validate_call_info.is_some(), validate);
+    assert!(execution_info.execute_call_info.is_some());
+    assert_eq!(execution_info.fee_transfer_call_info.is_some(), charge_fee);
+    // The fee is computed whether or not it is charged.
+    assert_ne!(execution_info.actual_fee, Fee(0));
+}
+
+/// Fees estimated at overriding gas prices are proportional to those prices.
+#[rstest]
+fn test_estimate_fee_with_gas_prices(
+    block_context: BlockContext,
+    #[values(TransactionVersion::ONE, TransactionVersion::THREE)] version: TransactionVersion,
+) {
+    let TestInitData { mut state, account_address, contract_address, .. } =
+        create_test_init_data(&block_context.chain_info, CairoVersion::Cairo0);
+    let tx = || {
+        account_invoke_tx(invoke_tx_args! {
+            max_fee: Fee(MAX_FEE),
+            resource_bounds: l1_resource_bounds(MAX_L1_GAS_AMOUNT, MAX_L1_GAS_PRICE),
+            sender_address: account_address,
+            calldata: create_trivial_calldata(contract_address),
+            version,
+        })
+    };
+    let mut estimate = |gas_prices: &GasPrices| {
+        tx().estimate_fee_with_gas_prices(&mut state, &block_context, gas_prices).unwrap()
+    };
+
+    let block_gas_prices = block_context.block_info.gas_prices.clone();
+    let double = |price: NonZeroU128| price.checked_mul(NonZeroU128::new(2).unwrap()).unwrap();
+    let doubled_gas_prices = GasPrices {
+        eth_l1_gas_price: double(block_gas_prices.eth_l1_gas_price),
+        strk_l1_gas_price: double(block_gas_prices.strk_l1_gas_price),
+        eth_l1_data_gas_price: double(block_gas_prices.eth_l1_data_gas_price),
+        strk_l1_data_gas_price: double(block_gas_prices.strk_l1_data_gas_price),
+    };
+    let block_prices_estimation_info = estimate(&block_gas_prices);
+    let doubled_prices_estimation_info = estimate(&doubled_gas_prices);
+
+    assert_eq!(
+        block_prices_estimation_info.actual_fee,
+        tx().estimate_fee(&mut state, &block_context).unwrap().actual_fee
+    );
+    assert_eq!(
+        doubled_prices_estimation_info.actual_resources,
+        block_prices_estimation_info.actual_resources
+    );
+    assert_eq!(
+        doubled_prices_estimation_info.actual_fee,
+        Fee(2 * block_prices_estimation_info.actual_fee.0)
+    );
+}
+
+/// V3 fields the protocol does not support yet are rejected before validation.
+#[rstest]
+fn test_unsupported_v3_fields(
+    block_context: BlockContext,
+    max_resource_bounds: ResourceBoundsMapping,
+) {
+    let TestInitData { mut state, account_address, contract_address, .. } =
+        create_test_init_data(&block_context.chain_info, CairoVersion::Cairo0);
+    let mut execute = |paymaster_data: PaymasterData,
+                       nonce_data_availability_mode: DataAvailabilityMode,
+                       fee_data_availability_mode: DataAvailabilityMode| {
+        account_invoke_tx(invoke_tx_args! {
+            resource_bounds: max_resource_bounds.clone(),
+            sender_address: account_address,
+            calldata: create_trivial_calldata(contract_address),
+            version: TransactionVersion::THREE,
+            paymaster_data,
+            nonce_data_availability_mode,
+            fee_data_availability_mode,
+        })
+        .execute(&mut state, &block_context, ExecutionFlags::default())
+    };
+
+    assert_matches!(
+        execute(
+            PaymasterData(vec![stark_felt!(1_u8)]),
+            DataAvailabilityMode::L1,
+            DataAvailabilityMode::L1
+        ),
+        Err(TransactionExecutionError::TransactionPreValidationError(
+            TransactionPreValidationError::UnsupportedPaymasterData { .. }
+        ))
+    );
+    for (expected_field, nonce_mode, fee_mode) in [
+        ("nonce", DataAvailabilityMode::L2, DataAvailabilityMode::L1),
+        ("fee", DataAvailabilityMode::L1, DataAvailabilityMode::L2),
+    ] {
+        assert_matches!(
+            execute(PaymasterData::default(), nonce_mode, fee_mode),
+            Err(TransactionExecutionError::TransactionPreValidationError(
+                TransactionPreValidationError::UnsupportedDataAvailabilityMode {
+                    field,
+                    mode: DataAvailabilityMode::L2,
+                }
+            ))
+            if field == expected_field
+        );
+    }
+
+    // Rejected transactions leave the nonce untouched, so the same nonce is still valid.
+    let execution_info =
+        execute(PaymasterData::default(), DataAvailabilityMode::L1, DataAvailabilityMode::L1)
+            .unwrap();
+    assert!(!execution_info.is_reverted());
+}
+
 // TODO(Dori, 15/9/2023): Convert version variance to attribute macro.
 // TODO(Dori, 10/10/2023): Add V3 case once `create_tx_info` is supported for V3.
 #[rstest]
@@ -266,7 +480,7 @@ fn test_max_fee_limit_validate(
         },
         class_info,
     );
-    account_tx.execute(&mut state, &block_context, true, true).unwrap();
+    account_tx.execute(&mut state, &block_context, ExecutionFlags::default()).unwrap();
 
     // Deploy grindy account with a lot of grind in the constructor.
     // Expect this to fail without bumping nonce, so pass a temporary nonce manager.
@@ -282,7 +496,9 @@ fn test_max_fee_limit_validate(
             constructor_calldata: calldata![ctor_grind_arg, ctor_storage_arg],
         },
     );
-    let error = deploy_account_tx.execute(&mut state, &block_context, true, true).unwrap_err();
+    let error = deploy_account_tx
+        .execute(&mut state, &block_context, ExecutionFlags::default())
+        .unwrap_err();
     assert_matches!(
         error,
         TransactionExecutionError::ValidateTransactionError(
@@ -303,7 +519,7 @@ fn test_max_fee_limit_validate(
             constructor_calldata: calldata![ctor_grind_arg, ctor_storage_arg],
         },
     );
-    deploy_account_tx.execute(&mut state, &block_context, true, true).unwrap();
+    deploy_account_tx.execute(&mut state, &block_context, ExecutionFlags::default()).unwrap();
 
     // Invoke a function that grinds validate (any function will do); set bounds low enough to fail
     // on this grind.
@@ -432,6 +648,7 @@ fn test_recursion_depth_exceeded(
 fn test_revert_invoke(
     block_context: BlockContext,
     max_fee: Fee,
+    max_resource_bounds: ResourceBoundsMapping,
     #[case] transaction_version: TransactionVersion,
     #[case] fee_type: FeeType,
 ) {
@@ -450,6 +667,7 @@ fn test_revert_invoke(
         &block_context,
         invoke_tx_args! {
             max_fee,
+            resource_bounds: max_resource_bounds,
             sender_address: account_address,
             calldata: create_calldata(
                 test_contract_address,
@@ -466,17 +684,27 @@ fn test_revert_invoke(
     // TODO(Dori, 1/7/2023): Verify that the actual fee collected is exactly the fee computed for
     // the validate and fee transfer calls.
 
-    // Check that the transaction was reverted.
-    assert!(tx_execution_info.revert_error.is_some());
+    // Check that the transaction was reverted, with the reason of the failure and the failing
+    // contract.
+    let revert_error = tx_execution_info.revert_error.as_ref().unwrap();
+    assert!(revert_error.contains("An ASSERT_EQ instruction failed: 1 != 0."));
+    let failing_contract =
+        format!("Error in the called contract ({}):", test_contract_address.0.key());
+    assert!(revert_error.contains(&failing_contract));
+
+    // Check that the resources consumed up to the revert were charged, within the bounds (which
+    // are equal to the max fee for both versions).
+    let actual_fee = tx_execution_info.actual_fee;
+    assert!(Fee(0) < actual_fee && actual_fee < max_fee);
 
     // Check that the nonce was increased and the fee was deducted.
+    let fee_token_address = chain_info.fee_token_address(&fee_type);
     assert_eq!(
-        state
-            .get_fee_token_balance(account_address, chain_info.fee_token_address(&fee_type))
-            .unwrap(),
-        (stark_felt!(BALANCE - tx_execution_info.actual_fee.0), stark_felt!(0_u8))
+        state.get_fee_token_balance(account_address, fee_token_address).unwrap(),
+        (stark_felt!(BALANCE - actual_fee.0), stark_felt!(0_u8))
     );
-    assert_eq!(state.get_nonce_at(account_address).unwrap(), nonce_manager.next(account_address));
+    let expected_nonce = nonce_manager.next(account_address);
+    assert_eq!(state.get_nonce_at(account_address).unwrap(), expected_nonce);
 
     // Check that execution state changes were reverted.
     assert_eq!(
@@ -485,6 +713,109 @@ fn test_revert_invoke(
             .get_storage_at(test_contract_address, StorageKey::try_from(storage_key).unwrap())
             .unwrap()
     );
+
+    // Check that the state diff consists of the fee transfer and the nonce increment only.
+    let sequencer_address = block_context.block_info.sequencer_address;
+    assert_eq!(
+        state.get_actual_state_changes().unwrap(),
+        StateChanges {
+            storage_updates: HashMap::from([
+                (
+                    (fee_token_address, get_fee_token_var_address(account_address)),
+                    stark_felt!(BALANCE - actual_fee.0),
+                ),
+                (
+                    (fee_token_address, get_fee_token_var_address(sequencer_address)),
+                    stark_felt!(actual_fee.0),
+                ),
+            ]),
+            nonce_updates: HashMap::from([(account_address, expected_nonce)]),
+            ..Default::default()
+        }
+    );
+}
+
+#[rstest]
+#[case::within_limit(3, false)]
+#[case::exceeds_limit(2, true)]
+/// Tests that the syscalls of the validation count towards the syscall limit of the execution.
+fn test_max_syscalls_per_tx_shared_by_validate_and_execute(
+    mut block_context: BlockContext,
+    #[case] max_syscalls_per_tx: usize,
+    #[case] exceeds_limit: bool,
+) {
+    let faulty_account = FeatureContract::FaultyAccount(CairoVersion::Cairo1);
+    let state = &mut test_state(&block_context.chain_info, BALANCE, &[(faulty_account, 1)]);
+    block_context.versioned_constants.syscall_limits.max_syscalls_per_tx =
+        Some(max_syscalls_per_tx);
+
+    // The validation issues two syscalls (sending a message and reading the execution info), and
+    // the execution a single one (sending a message).
+    let invoke_tx = create_account_tx_for_validate_test(
+        &mut NonceManager::default(),
+        FaultyAccountTxCreatorArgs {
+            tx_type: TransactionType::InvokeFunction,
+            scenario: VALID,
+            sender_address: faulty_account.get_instance_address(0),
+            class_hash: faulty_account.get_class_hash(),
+            max_fee: Fee(MAX_FEE),
+            ..Default::default()
+        },
+    );
+    let tx_execution_info =
+        invoke_tx.execute(state, &block_context, ExecutionFlags::default()).unwrap();
+
+    match tx_execution_info.revert_error {
+        Some(revert_error) => {
+            assert!(exceeds_limit);
+            assert!(revert_error.contains(
+                "The SendMessageToL1 syscall exceeds the maximum of 2 syscalls per transaction."
+            ));
+        }
+        None => assert!(!exceeds_limit),
+    }
+}
+
+#[rstest]
+/// Tests that the syscall metrics of the validation and execution are reported in the execution
+/// info, if collected.
+fn test_syscall_metrics_in_execution_info(
+    mut block_context: BlockContext,
+    #[values(false, true)] collect_syscall_metrics: bool,
+) {
+    let faulty_account = FeatureContract::FaultyAccount(CairoVersion::Cairo1);
+    let state = &mut test_state(&block_context.chain_info, BALANCE, &[(faulty_account, 1)]);
+    block_context.set_collect_syscall_metrics(collect_syscall_metrics);
+
+    let invoke_tx = create_account_tx_for_validate_test(
+        &mut NonceManager::default(),
+        FaultyAccountTxCreatorArgs {
+            tx_type: TransactionType::InvokeFunction,
+            scenario: VALID,
+            sender_address: faulty_account.get_instance_address(0),
+            class_hash: faulty_account.get_class_hash(),
+            max_fee: Fee(MAX_FEE),
+            ..Default::default()
+        },
+    );
+    let tx_execution_info =
+        invoke_tx.execute(state, &block_context, ExecutionFlags::default()).unwrap();
+
+    let n_invocations: HashMap<DeprecatedSyscallSelector, usize> = tx_execution_info
+        .syscall_metrics
+        .iter()
+        .map(|(selector, metrics)| (*selector, metrics.n_invocations))
+        .collect();
+    let expected_n_invocations = if collect_syscall_metrics {
+        // Both phases send a message; the validation also reads the execution info.
+        HashMap::from([
+            (DeprecatedSyscallSelector::SendMessageToL1, 2),
+            (DeprecatedSyscallSelector::GetExecutionInfo, 1),
+        ])
+    } else {
+        HashMap::new()
+    };
+    assert_eq!(n_invocations, expected_n_invocations);
 }
 
 #[rstest]
@@ -518,7 +849,8 @@ fn test_fail_deploy_account(
 
     let initial_balance = state.get_fee_token_balance(deploy_address, fee_token_address).unwrap();
 
-    let error = deploy_account_tx.execute(state, &block_context, true, true).unwrap_err();
+    let error =
+        deploy_account_tx.execute(state, &block_context, ExecutionFlags::default()).unwrap_err();
     // Check the error is as expected. Assure the error message is not nonce or fee related.
     check_transaction_execution_error_for_invalid_scenario!(cairo_version, error, false);
 
@@ -531,6 +863,120 @@ fn test_fail_deploy_account(
     assert_eq!(state.get_class_hash_at(deploy_address).unwrap(), ClassHash::default());
 }
 
+/// Returns the given Cairo 0 class, with its constructor and `__validate_deploy__` entry points
+/// replaced by the given external entry points; this allows deploying it as an account.
+fn cairo0_account_class(
+    contract_path: &str,
+    constructor_name: &str,
+    validate_deploy_name: &str,
+) -> ContractClassV0 {
+    let mut raw_class: serde_json::Value =
+        serde_json::from_str(&get_raw_contract_class(contract_path)).unwrap();
+    let entry_points = &mut raw_class["entry_points_by_type"];
+    let external_entry_point = |name: &str| {
+        let selector = selector_from_name(name).0;
+        entry_points["EXTERNAL"]
+            .as_array()
+            .unwrap()
+            .iter()
+            .find(|entry_point| {
+                StarkFelt::try_from(entry_point["selector"].as_str().unwrap()).unwrap() == selector
+            })
+            .unwrap()
+            .clone()
+    };
+    let mut constructor = external_entry_point(constructor_name);
+    let mut validate_deploy = external_entry_point(validate_deploy_name);
+    constructor["selector"] = selector_from_name("constructor").0.to_string().into();
+    validate_deploy["selector"] =
+        selector_from_name(constants::VALIDATE_DEPLOY_ENTRY_POINT_NAME).0.to_string().into();
+    entry_points["CONSTRUCTOR"] = serde_json::json!([constructor]);
+    entry_points["EXTERNAL"].as_array_mut().unwrap().push(validate_deploy);
+
+    ContractClassV0::try_from_json_string(&raw_class.to_string()).unwrap()
+}
+
+#[rstest]
+/// Tests that the storage writes and events of a `DeployAccount` constructor are rolled back if
+/// the validation that follows it fails.
+fn test_deploy_account_constructor_rollback_on_failed_validation(block_context: BlockContext) {
+    let chain_info = &block_context.chain_info;
+    let state = &mut test_state(chain_info, BALANCE, &[]);
+    // The constructor transfers tokens (writing to the storage and emitting an event) on behalf
+    // of its caller (the zero address), and the validation fails on unexpected calldata.
+    let class_hash = class_hash!(1_u8);
+    let account_class = cairo0_account_class(ERC20_CONTRACT_PATH, "transferFrom", "decimals");
+    state.set_contract_class(class_hash, account_class.into()).unwrap();
+
+    let (sender, recipient, amount) = (stark_felt!(10_u8), stark_felt!(11_u8), stark_felt!(5_u8));
+    let constructor_calldata = calldata![sender, recipient, amount, stark_felt!(0_u8)];
+    let deploy_account_tx = deploy_account_tx(
+        deploy_account_tx_args! { class_hash, constructor_calldata },
+        &mut NonceManager::default(),
+    );
+    let deploy_address = deploy_account_tx.contract_address;
+
+    // Fund the sender and allow the caller to transfer on its behalf.
+    let sender_balance_key = get_storage_var_address("ERC20_balances", &[sender]);
+    let recipient_balance_key = get_storage_var_address("ERC20_balances", &[recipient]);
+    let allowance_key =
+        get_storage_var_address("ERC20_allowances", &[sender, *ContractAddress::default().0.key()]);
+    state.set_storage_at(deploy_address, sender_balance_key, amount).unwrap();
+    state.set_storage_at(deploy_address, allowance_key, amount).unwrap();
+
+    let error = AccountTransaction::DeployAccount(deploy_account_tx)
+        .execute(state, &block_context, ExecutionFlags { charge_fee: false, ..Default::default() })
+        .unwrap_err();
+    assert_matches!(error, TransactionExecutionError::ValidateTransactionError(_));
+    assert!(error.to_string().starts_with("Transaction validation has failed"));
+
+    // Assert that nothing was deployed, and that the constructor writes were rolled back.
+    assert_eq!(state.get_class_hash_at(deploy_address).unwrap(), ClassHash::default());
+    assert_eq!(state.get_nonce_at(deploy_address).unwrap(), Nonce::default());
+    assert_eq!(state.get_storage_at(deploy_address, sender_balance_key).unwrap(), amount);
+    assert_eq!(state.get_storage_at(deploy_address, allowance_key).unwrap(), amount);
+    assert_eq!(
+        state.get_storage_at(deploy_address, recipient_balance_key).unwrap(),
+        StarkFelt::default()
+    );
+}
+
+#[rstest]
+/// Tests that the events of a `DeployAccount` constructor are attributed to the constructor call,
+/// and are ordered before the events of the validation.
+fn test_deploy_account_event_order(block_context: BlockContext) {
+    let chain_info = &block_context.chain_info;
+    let state = &mut test_state(chain_info, BALANCE, &[]);
+    let account_class =
+        cairo0_account_class(TEST_CONTRACT_CAIRO0_PATH, "test_emit_events", "test_emit_events");
+    // The validation calldata is prefixed by the class hash and salt; these act as the events
+    // count and the keys length, respectively.
+    let class_hash = class_hash!(1_u8);
+    let contract_address_salt = ContractAddressSalt(stark_felt!(2_u8));
+    state.set_contract_class(class_hash, account_class.into()).unwrap();
+
+    // A single event with no keys and the given data.
+    let data = stark_felt!(2024_u16);
+    let constructor_calldata =
+        calldata![stark_felt!(1_u8), stark_felt!(0_u8), stark_felt!(1_u8), data];
+    let deploy_account_tx = deploy_account_tx(
+        deploy_account_tx_args! { class_hash, contract_address_salt, constructor_calldata },
+        &mut NonceManager::default(),
+    );
+
+    let execution_info = AccountTransaction::DeployAccount(deploy_account_tx)
+        .execute(state, &block_context, ExecutionFlags { charge_fee: false, ..Default::default() })
+        .unwrap();
+    let constructor_events = &execution_info.execute_call_info.unwrap().execution.events;
+    let validate_events = &execution_info.validate_call_info.unwrap().execution.events;
+    assert_eq!(constructor_events.len(), 1);
+    assert_eq!(constructor_events[0].order, 0);
+    assert_eq!(constructor_events[0].event.keys, vec![]);
+    assert_eq!(constructor_events[0].event.data.0, vec![data]);
+    assert_eq!(validate_events.len(), 1);
+    assert_eq!(validate_events[0].order, 1);
+}
+
 #[rstest]
 /// Tests that a failing declare transaction should not change state (no fee charge or nonce bump).
 fn test_fail_declare(block_context: BlockContext, max_fee: Fee) {
@@ -568,9 +1014,17 @@ fn test_fail_declare(block_context: BlockContext, max_fee: Fee) {
     let initial_balance = state
         .get_fee_token_balance(account_address, chain_info.fee_token_address(&tx_info.fee_type()))
         .unwrap();
-    declare_account_tx.execute(&mut state, &block_context, true, true).unwrap_err();
+    let error = declare_account_tx
+        .execute(&mut state, &block_context, ExecutionFlags::default())
+        .unwrap_err();
+    assert_matches!(
+        error,
+        TransactionExecutionError::DeclareTransactionError { class_hash: declared_class_hash }
+        if declared_class_hash == class_hash
+    );
 
     assert_eq!(state.get_nonce_at(account_address).unwrap(), next_nonce);
+    assert_eq!(state.get_compiled_class_hash(class_hash).unwrap(), declare_tx.compiled_class_hash);
     assert_eq!(
         state
             .get_fee_token_balance(
@@ -736,6 +1190,7 @@ fn test_n_reverted_steps(
     assert!(result.is_reverted());
     let mut actual_resources_0 = result.actual_resources.clone();
     let n_steps_0 = result.actual_resources.n_steps();
+    let n_reverted_steps_0 = result.n_reverted_steps;
     let actual_fee_0 = result.actual_fee.0;
 
     // Invoke the `recursive_fail` function with 1 iterations. This call should fail.
@@ -753,6 +1208,7 @@ fn test_n_reverted_steps(
     assert!(result.is_reverted());
     let actual_resources_1 = result.actual_resources;
     let n_steps_1 = actual_resources_1.n_steps();
+    let n_reverted_steps_1 = result.n_reverted_steps;
     let actual_fee_1 = result.actual_fee.0;
 
     // Invoke the `recursive_fail` function with 2 iterations. This call should fail.
@@ -780,14 +1236,15 @@ fn test_n_reverted_steps(
     let single_call_fee_delta = actual_fee_1 - actual_fee_0;
     assert!(single_call_steps_delta > 0);
     assert!(single_call_fee_delta > 0);
+    // All additional steps are reverted ones.
+    assert!(n_reverted_steps_0 > 0);
+    assert_eq!(n_reverted_steps_1 - n_reverted_steps_0, single_call_steps_delta);
 
     // Make sure the resources in block of invocation 0 and 1 are the same, except for the number
     // of cairo steps.
-    actual_resources_0
-        .0
-        .insert(abi_constants::N_STEPS_RESOURCE.to_string(), n_steps_0 + single_call_steps_delta);
+    actual_resources_0.0.insert(Resource::Steps, n_steps_0 + single_call_steps_delta);
     assert_eq!(actual_resources_0, actual_resources_1);
-    actual_resources_0.0.insert(abi_constants::N_STEPS_RESOURCE.to_string(), n_steps_0);
+    actual_resources_0.0.insert(Resource::Steps, n_steps_0);
 
     // Invoke the `recursive_fail` function with 100 iterations. This call should fail.
     let result = run_invoke_tx(
@@ -808,6 +1265,49 @@ fn test_n_reverted_steps(
     // Make sure that n_steps and actual_fee grew as expected.
     assert!(n_steps_100 - n_steps_0 == 100 * single_call_steps_delta);
     assert!(actual_fee_100 - actual_fee_0 == 100 * single_call_fee_delta);
+    assert_eq!(result.n_reverted_steps - n_reverted_steps_0, 100 * single_call_steps_delta);
+}
+
+/// The reverted steps are reported the same way under every pricing, but are charged according to
+/// the block's reverted steps pricing.
+#[rstest]
+#[case::as_steps(RevertedStepsPricing::AsSteps)]
+#[case::free(RevertedStepsPricing::Percentage(0))]
+#[case::half(RevertedStepsPricing::Percentage(50))]
+fn test_reverted_steps_pricing(
+    max_fee: Fee,
+    mut block_context: BlockContext,
+    #[case] reverted_steps_pricing: RevertedStepsPricing,
+) {
+    let TestInitData { mut state, account_address, contract_address, .. } =
+        create_test_init_data(&block_context.chain_info, CairoVersion::Cairo0);
+    let mut run_reverted_tx = |block_context: &BlockContext| {
+        let tx = account_invoke_tx(invoke_tx_args! {
+            max_fee,
+            sender_address: account_address,
+            calldata: recursive_function_calldata(&contract_address, 10, true),
+            version: TransactionVersion::ONE,
+        });
+        // Discard the state changes, to rerun the same transaction.
+        let mut transactional_state = CachedState::create_transactional(&mut state);
+        let execution_info =
+            tx.execute_raw(&mut transactional_state, block_context, ExecutionFlags::default());
+        execution_info.unwrap()
+    };
+
+    let as_steps_execution_info = run_reverted_tx(&block_context);
+    block_context.set_reverted_steps_pricing(reverted_steps_pricing);
+    let execution_info = run_reverted_tx(&block_context);
+    assert!(execution_info.is_reverted());
+
+    let n_reverted_steps = as_steps_execution_info.n_reverted_steps;
+    assert!(n_reverted_steps > 0);
+    assert_eq!(execution_info.n_reverted_steps, n_reverted_steps);
+    assert_eq!(
+        execution_info.actual_resources.n_steps() + n_reverted_steps,
+        as_steps_execution_info.actual_resources.n_steps()
+            + reverted_steps_pricing.charged_steps(n_reverted_steps)
+    );
 }
 
 #[rstest]
@@ -843,7 +1343,8 @@ fn test_max_fee_to_max_steps_conversion(
     let tx_context1 = Arc::new(block_context.to_tx_context(&account_tx1));
     let execution_context1 = EntryPointExecutionContext::new_invoke(tx_context1, true).unwrap();
     let max_steps_limit1 = execution_context1.vm_run_resources.get_n_steps();
-    let tx_execution_info1 = account_tx1.execute(&mut state, &block_context, true, true).unwrap();
+    let tx_execution_info1 =
+        account_tx1.execute(&mut state, &block_context, ExecutionFlags::default()).unwrap();
     let n_steps1 = tx_execution_info1.actual_resources.n_steps();
     let gas_used_vector1 = calculate_tx_gas_vector(
         &tx_execution_info1.actual_resources,
@@ -863,7 +1364,8 @@ fn test_max_fee_to_max_steps_conversion(
     let tx_context2 = Arc::new(block_context.to_tx_context(&account_tx2));
     let execution_context2 = EntryPointExecutionContext::new_invoke(tx_context2, true).unwrap();
     let max_steps_limit2 = execution_context2.vm_run_resources.get_n_steps();
-    let tx_execution_info2 = account_tx2.execute(&mut state, &block_context, true, true).unwrap();
+    let tx_execution_info2 =
+        account_tx2.execute(&mut state, &block_context, ExecutionFlags::default()).unwrap();
     let n_steps2 = tx_execution_info2.actual_resources.n_steps();
     let gas_used_vector2 = calculate_tx_gas_vector(
         &tx_execution_info2.actual_resources,
@@ -977,7 +1479,7 @@ fn test_deploy_account_constructor_storage_write(
             constructor_calldata: constructor_calldata.clone(),
         },
     );
-    deploy_account_tx.execute(state, &block_context, true, true).unwrap();
+    deploy_account_tx.execute(state, &block_context, ExecutionFlags::default()).unwrap();
 
     // Check that the constructor wrote ctor_arg to the storage.
     let storage_key = get_storage_var_address("ctor_arg", &[]);
@@ -1039,6 +1541,7 @@ fn test_count_actual_storage_changes(
     // transaction.
     // First transaction: storage cell value changes from 0 to 1.
     let mut state = CachedState::create_transactional(&mut state);
+    let execution_flags = ExecutionFlags::default();
     let invoke_args = invoke_tx_args! {
         max_fee,
         resource_bounds: l1_resource_bounds(MAX_L1_GAS_AMOUNT, MAX_L1_GAS_PRICE),
@@ -1048,7 +1551,8 @@ fn test_count_actual_storage_changes(
         nonce: nonce_manager.next(account_address),
     };
     let account_tx = account_invoke_tx(invoke_args.clone());
-    let execution_info = account_tx.execute_raw(&mut state, &block_context, true, true).unwrap();
+    let execution_info =
+        account_tx.execute_raw(&mut state, &block_context, execution_flags).unwrap();
 
     let fee_1 = execution_info.actual_fee;
     let state_changes_1 = state.get_actual_state_changes().unwrap();
@@ -1093,7 +1597,8 @@ fn test_count_actual_storage_changes(
         nonce: nonce_manager.next(account_address),
         ..invoke_args.clone()
     });
-    let execution_info = account_tx.execute_raw(&mut state, &block_context, true, true).unwrap();
+    let execution_info =
+        account_tx.execute_raw(&mut state, &block_context, execution_flags).unwrap();
 
     let fee_2 = execution_info.actual_fee;
     let state_changes_2 = state.get_actual_state_changes().unwrap();
@@ -1130,7 +1635,8 @@ fn test_count_actual_storage_changes(
         calldata: transfer_calldata,
         ..invoke_args
     });
-    let execution_info = account_tx.execute_raw(&mut state, &block_context, true, true).unwrap();
+    let execution_info =
+        account_tx.execute_raw(&mut state, &block_context, execution_flags).unwrap();
 
     let fee_transfer = execution_info.actual_fee;
     let state_changes_transfer = state.get_actual_state_changes().unwrap();
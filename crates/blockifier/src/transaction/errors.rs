@@ -1,6 +1,7 @@
 use starknet_api::core::{ClassHash, ContractAddress, Nonce};
+use starknet_api::data_availability::DataAvailabilityMode;
 use starknet_api::hash::StarkFelt;
-use starknet_api::transaction::{Fee, TransactionVersion};
+use starknet_api::transaction::{Fee, PaymasterData, TransactionVersion};
 use starknet_api::StarknetApiError;
 use thiserror::Error;
 
@@ -8,15 +9,28 @@ use crate::execution::call_info::Retdata;
 use crate::execution::errors::EntryPointExecutionError;
 use crate::fee::fee_checks::FeeCheckError;
 use crate::state::errors::StateError;
+use crate::transaction::objects::{GasVector, Resource};
+use crate::versioned_constants::OsResourcesError;
 
 #[derive(Debug, Error)]
 pub enum TransactionFeeError {
-    #[error("Cairo resource names must be contained in fee cost dict.")]
-    CairoResourcesNotContainedInFeeCosts,
+    #[error(
+        "Cairo resource names must be contained in fee cost dict; missing: {missing_resources:?}."
+    )]
+    CairoResourcesNotContainedInFeeCosts { missing_resources: Vec<Resource> },
     #[error(transparent)]
     ExecuteFeeTransferError(#[from] EntryPointExecutionError),
+    #[error(
+        "Fee of {gas_vector:?} overflowed at L1 gas price {gas_price} and L1 data gas price \
+         {data_gas_price}."
+    )]
+    FeeOverflow { gas_vector: GasVector, gas_price: u128, data_gas_price: u128 },
     #[error("Actual fee ({actual_fee:?}) exceeded max fee ({max_fee:?}).")]
     FeeTransferError { max_fee: Fee, actual_fee: Fee },
+    #[error("Gas vector addition overflowed: {lhs:?} + {rhs:?}.")]
+    GasVectorAdditionOverflow { lhs: GasVector, rhs: GasVector },
+    #[error("Gas vector multiplication overflowed: {gas_vector:?} * {factor}.")]
+    GasVectorMultiplicationOverflow { gas_vector: GasVector, factor: u128 },
     #[error("Actual fee ({actual_fee:?}) exceeded paid fee on L1 ({paid_fee:?}).")]
     InsufficientL1Fee { paid_fee: Fee, actual_fee: Fee },
     #[error(
@@ -77,6 +91,10 @@ pub enum TransactionExecutionError {
     )]
     InvalidVersion { version: TransactionVersion, allowed_versions: Vec<TransactionVersion> },
     #[error(transparent)]
+    OsResourcesError(#[from] OsResourcesError),
+    #[error(transparent)]
+    ParseError(#[from] ParseError),
+    #[error(transparent)]
     StarknetApiError(#[from] StarknetApiError),
     #[error(transparent)]
     StateError(#[from] StateError),
@@ -92,6 +110,27 @@ pub enum TransactionExecutionError {
     ValidateTransactionError(#[source] EntryPointExecutionError),
 }
 
+impl TransactionExecutionError {
+    /// Returns the state error this error originates from, if any; e.g., a read of an undeclared
+    /// class, possibly by an inner call of the executed entry point.
+    pub fn state_error(&self) -> Option<&StateError> {
+        match self {
+            Self::StateError(error)
+            | Self::TransactionFeeError(TransactionFeeError::StateError(error))
+            | Self::TransactionPreValidationError(TransactionPreValidationError::StateError(
+                error,
+            )) => Some(error),
+            Self::ContractConstructorExecutionFailed(error)
+            | Self::ExecutionError(error)
+            | Self::ValidateTransactionError(error)
+            | Self::TransactionFeeError(TransactionFeeError::ExecuteFeeTransferError(error)) => {
+                error.state_error()
+            }
+            _ => None,
+        }
+    }
+}
+
 #[derive(Debug, Error)]
 pub enum TransactionPreValidationError {
     #[error(
@@ -100,13 +139,21 @@ pub enum TransactionPreValidationError {
     )]
     InvalidNonce { address: ContractAddress, account_nonce: Nonce, incoming_tx_nonce: Nonce },
     #[error(transparent)]
+    OsResourcesError(#[from] OsResourcesError),
+    #[error(transparent)]
     StateError(#[from] StateError),
     #[error(transparent)]
     TransactionFeeError(#[from] TransactionFeeError),
+    #[error("Unsupported {field} data availability mode: {mode:?}. Only L1 is supported.")]
+    UnsupportedDataAvailabilityMode { field: String, mode: DataAvailabilityMode },
+    #[error("Paymaster data is not supported. Got: {paymaster_data:?}.")]
+    UnsupportedPaymasterData { paymaster_data: PaymasterData },
 }
 
 #[derive(Debug, Error)]
 pub enum ParseError {
+    #[error("Unknown resource: {0}")]
+    UnknownResource(String),
     #[error("Unsupported transaction type: {0}")]
     UnknownTransactionType(String),
 }
@@ -26,7 +26,7 @@ use crate::transaction::errors::{
 };
 use crate::transaction::objects::{FeeType, GasVector, TransactionExecutionInfo};
 use crate::transaction::test_utils::{account_invoke_tx, l1_resource_bounds, INVALID};
-use crate::transaction::transactions::ExecutableTransaction;
+use crate::transaction::transactions::{ExecutableTransaction, ExecutionFlags};
 const VALIDATE_GAS_OVERHEAD: u64 = 21;
 
 struct FlavorTestInitialState {
@@ -144,6 +144,7 @@ fn test_simulate_validate_charge_fee_pre_validate(
     #[case] fee_type: FeeType,
     #[case] is_deprecated: bool,
 ) {
+    let execution_flags = ExecutionFlags { charge_fee, validate, ..Default::default() };
     let block_context = BlockContext::create_for_account_testing();
     let max_fee = Fee(MAX_FEE);
     let gas_price = block_context.block_info.gas_prices.get_gas_price_by_fee_type(&fee_type);
@@ -176,7 +177,7 @@ fn test_simulate_validate_charge_fee_pre_validate(
     let result = account_invoke_tx(
         invoke_tx_args! {nonce: invalid_nonce, ..pre_validation_base_args.clone()},
     )
-    .execute(&mut state, &block_context, charge_fee, validate);
+    .execute(&mut state, &block_context, execution_flags);
     assert_matches!(
         result.unwrap_err(),
         TransactionExecutionError::TransactionPreValidationError(
@@ -196,7 +197,7 @@ fn test_simulate_validate_charge_fee_pre_validate(
         nonce: nonce_manager.next(account_address),
         ..pre_validation_base_args.clone()
     })
-    .execute(&mut state, &block_context, charge_fee, validate);
+    .execute(&mut state, &block_context, execution_flags);
     if !charge_fee {
         check_gas_and_fee(
             &block_context,
@@ -240,7 +241,7 @@ fn test_simulate_validate_charge_fee_pre_validate(
         nonce: nonce_manager.next(account_address),
         ..pre_validation_base_args.clone()
     })
-    .execute(&mut state, &block_context, charge_fee, validate);
+    .execute(&mut state, &block_context, execution_flags);
     if !charge_fee {
         check_gas_and_fee(
             &block_context,
@@ -280,7 +281,7 @@ fn test_simulate_validate_charge_fee_pre_validate(
             nonce: nonce_manager.next(account_address),
             ..pre_validation_base_args
         })
-        .execute(&mut state, &block_context, charge_fee, validate);
+        .execute(&mut state, &block_context, execution_flags);
         if !charge_fee {
             check_gas_and_fee(
                 &block_context,
@@ -317,6 +318,7 @@ fn test_simulate_validate_charge_fee_fail_validate(
     #[case] version: TransactionVersion,
     #[case] fee_type: FeeType,
 ) {
+    let execution_flags = ExecutionFlags { charge_fee, validate, ..Default::default() };
     let block_context = BlockContext::create_for_account_testing();
     let max_fee = Fee(MAX_FEE);
 
@@ -343,12 +345,15 @@ fn test_simulate_validate_charge_fee_fail_validate(
         nonce: nonce_manager.next(faulty_account_address),
         only_query,
     })
-    .execute(&mut falliable_state, &block_context, charge_fee, validate);
+    .execute(&mut falliable_state, &block_context, execution_flags);
     if !validate {
+        // The validation entry point was not run at all, so the invalid signature is accepted.
+        let tx_execution_info = result.unwrap();
+        assert!(tx_execution_info.validate_call_info.is_none());
         // The reported fee should be the actual cost, regardless of whether or not fee is charged.
         check_gas_and_fee(
             &block_context,
-            &result.unwrap(),
+            &tx_execution_info,
             &fee_type,
             actual_gas_used,
             actual_fee,
@@ -378,6 +383,7 @@ fn test_simulate_validate_charge_fee_mid_execution(
     #[case] version: TransactionVersion,
     #[case] fee_type: FeeType,
 ) {
+    let execution_flags = ExecutionFlags { charge_fee, validate, ..Default::default() };
     let block_context = BlockContext::create_for_account_testing();
     let chain_info = &block_context.chain_info;
     let gas_price = block_context.block_info.gas_prices.get_gas_price_by_fee_type(&fee_type);
@@ -413,7 +419,7 @@ fn test_simulate_validate_charge_fee_mid_execution(
         nonce: nonce_manager.next(account_address),
         ..execution_base_args.clone()
     })
-    .execute(&mut state, &block_context, charge_fee, validate)
+    .execute(&mut state, &block_context, execution_flags)
     .unwrap();
     assert!(tx_execution_info.is_reverted());
     check_gas_and_fee(
@@ -447,7 +453,7 @@ fn test_simulate_validate_charge_fee_mid_execution(
         nonce: nonce_manager.next(account_address),
         ..execution_base_args.clone()
     })
-    .execute(&mut state, &block_context, charge_fee, validate)
+    .execute(&mut state, &block_context, execution_flags)
     .unwrap();
     assert_eq!(tx_execution_info.is_reverted(), charge_fee);
     if charge_fee {
@@ -499,7 +505,7 @@ fn test_simulate_validate_charge_fee_mid_execution(
         nonce: nonce_manager.next(account_address),
         ..execution_base_args
     })
-    .execute(&mut state, &low_step_block_context, charge_fee, validate)
+    .execute(&mut state, &low_step_block_context, execution_flags)
     .unwrap();
     assert!(tx_execution_info.revert_error.clone().unwrap().contains("no remaining steps"));
     // Complete resources used are reported as actual_resources; but only the charged final fee is
@@ -529,6 +535,7 @@ fn test_simulate_validate_charge_fee_post_execution(
     #[case] fee_type: FeeType,
     #[case] is_deprecated: bool,
 ) {
+    let execution_flags = ExecutionFlags { charge_fee, validate, ..Default::default() };
     let block_context = BlockContext::create_for_account_testing();
     let gas_price = block_context.block_info.gas_prices.get_gas_price_by_fee_type(&fee_type);
     let chain_info = &block_context.chain_info;
@@ -570,7 +577,7 @@ fn test_simulate_validate_charge_fee_post_execution(
         version,
         only_query,
     })
-    .execute(&mut state, &block_context, charge_fee, validate)
+    .execute(&mut state, &block_context, execution_flags)
     .unwrap();
     assert_eq!(tx_execution_info.is_reverted(), charge_fee);
     if charge_fee {
@@ -622,7 +629,7 @@ fn test_simulate_validate_charge_fee_post_execution(
         version,
         only_query,
     })
-    .execute(&mut state, &block_context, charge_fee, validate)
+    .execute(&mut state, &block_context, execution_flags)
     .unwrap();
     assert_eq!(tx_execution_info.is_reverted(), charge_fee);
     if charge_fee {
@@ -1,26 +1,36 @@
 use std::collections::{HashMap, HashSet};
+use std::fmt;
+use std::str::FromStr;
 
 use cairo_felt::Felt252;
+use cairo_vm::vm::runners::builtin_runner;
+use cairo_vm::vm::runners::cairo_runner::ExecutionResources as VmExecutionResources;
 use itertools::concat;
 use num_traits::Pow;
-use serde::Serialize;
+use serde::de::Error as DeserializationError;
+use serde::{Deserialize, Deserializer, Serialize, Serializer};
 use starknet_api::core::{ClassHash, ContractAddress, Nonce};
 use starknet_api::data_availability::DataAvailabilityMode;
 use starknet_api::transaction::{
-    AccountDeploymentData, Fee, PaymasterData, Resource, ResourceBounds, ResourceBoundsMapping,
-    Tip, TransactionHash, TransactionSignature, TransactionVersion,
+    AccountDeploymentData, Fee, PaymasterData, ResourceBounds, ResourceBoundsMapping, Tip,
+    TransactionHash, TransactionSignature, TransactionVersion,
 };
+use strum::IntoEnumIterator;
 use strum_macros::EnumIter;
 
+use crate::abi::constants as abi_constants;
+use crate::blockifier::block::GasPrices;
 use crate::context::BlockContext;
-use crate::execution::call_info::CallInfo;
+use crate::execution::call_info::{CallInfo, EventSummary};
 use crate::execution::execution_utils::{felt_to_stark_felt, stark_felt_to_felt};
-use crate::fee::fee_utils::calculate_tx_fee;
+use crate::execution::syscalls::hint_processor::SyscallMetricsMap;
+use crate::fee::fee_utils::{calculate_tx_fee, calculate_tx_gas_vector};
 use crate::state::cached_state::StorageEntry;
 use crate::transaction::constants;
 use crate::transaction::errors::{
-    TransactionExecutionError, TransactionFeeError, TransactionPreValidationError,
+    ParseError, TransactionExecutionError, TransactionFeeError, TransactionPreValidationError,
 };
+use crate::versioned_constants::VersionedConstants;
 
 #[cfg(test)]
 #[path = "objects_test.rs"]
@@ -115,7 +125,7 @@ pub struct CurrentTransactionInfo {
 impl CurrentTransactionInfo {
     /// Fetch the L1 resource bounds, if they exist.
     pub fn l1_resource_bounds(&self) -> TransactionFeeResult<ResourceBounds> {
-        match self.resource_bounds.0.get(&Resource::L1Gas).copied() {
+        match self.resource_bounds.0.get(&starknet_api::transaction::Resource::L1Gas).copied() {
             Some(bounds) => Ok(bounds),
             None => Err(TransactionFeeError::MissingL1GasBounds),
         }
@@ -128,15 +138,51 @@ pub struct DeprecatedTransactionInfo {
     pub max_fee: Fee,
 }
 
-#[derive(
-    derive_more::Add, derive_more::Sum, Clone, Copy, Debug, Default, Eq, PartialEq, Serialize,
-)]
+#[derive(Clone, Copy, Debug, Default, Eq, PartialEq, Serialize)]
 pub struct GasVector {
     pub l1_gas: u128,
     pub l1_data_gas: u128,
 }
 
 impl GasVector {
+    /// Adds two gas vectors, failing on overflow.
+    pub fn checked_add(self, rhs: Self) -> TransactionFeeResult<Self> {
+        match (self.l1_gas.checked_add(rhs.l1_gas), self.l1_data_gas.checked_add(rhs.l1_data_gas)) {
+            (Some(l1_gas), Some(l1_data_gas)) => Ok(Self { l1_gas, l1_data_gas }),
+            _ => Err(TransactionFeeError::GasVectorAdditionOverflow { lhs: self, rhs }),
+        }
+    }
+
+    /// Sums the given gas vectors, failing on overflow.
+    pub fn checked_sum(gas_vectors: impl IntoIterator<Item = Self>) -> TransactionFeeResult<Self> {
+        gas_vectors.into_iter().try_fold(Self::default(), Self::checked_add)
+    }
+
+    /// Multiplies both gas components by the given factor, failing on overflow.
+    pub fn checked_scalar_mul(self, factor: u128) -> TransactionFeeResult<Self> {
+        match (self.l1_gas.checked_mul(factor), self.l1_data_gas.checked_mul(factor)) {
+            (Some(l1_gas), Some(l1_data_gas)) => Ok(Self { l1_gas, l1_data_gas }),
+            _ => Err(TransactionFeeError::GasVectorMultiplicationOverflow {
+                gas_vector: self,
+                factor,
+            }),
+        }
+    }
+
+    /// Computes the cost (in fee token units) of the gas vector, failing on overflow.
+    pub fn checked_cost(&self, gas_price: u128, blob_gas_price: u128) -> TransactionFeeResult<Fee> {
+        self.l1_gas
+            .checked_mul(gas_price)
+            .zip(self.l1_data_gas.checked_mul(blob_gas_price))
+            .and_then(|(l1_gas_cost, l1_data_gas_cost)| l1_gas_cost.checked_add(l1_data_gas_cost))
+            .map(Fee)
+            .ok_or(TransactionFeeError::FeeOverflow {
+                gas_vector: *self,
+                gas_price,
+                data_gas_price: blob_gas_price,
+            })
+    }
+
     /// Computes the cost (in fee token units) of the gas vector (saturating on overflow).
     pub fn saturated_cost(&self, gas_price: u128, blob_gas_price: u128) -> Fee {
         let l1_gas_cost = self.l1_gas.checked_mul(gas_price).unwrap_or_else(|| {
@@ -193,10 +239,18 @@ pub struct TransactionExecutionInfo {
     /// Actual execution resources the transaction is charged for,
     /// including L1 gas and additional OS resources estimation.
     pub actual_resources: ResourcesMapping,
+    /// The Cairo steps consumed by the execute phase of a reverted transaction, before it was
+    /// reverted; charged as part of the steps in `actual_resources`, according to the block's
+    /// reverted steps pricing. Zero for transactions that were not reverted.
+    pub n_reverted_steps: usize,
     /// Error string for reverted transactions; [None] if transaction execution was successful.
     // TODO(Dori, 1/8/2023): If the `Eq` and `PartialEq` traits are removed, or implemented on all
     //   internal structs in this enum, this field should be `Option<TransactionExecutionError>`.
     pub revert_error: Option<String>,
+    /// Per-syscall profiling metrics of the Cairo 1 syscalls issued by the validation and
+    /// execution (including reverted executions); empty unless collected by the block context.
+    #[serde(skip)]
+    pub syscall_metrics: SyscallMetricsMap,
 }
 
 impl TransactionExecutionInfo {
@@ -226,30 +280,162 @@ impl TransactionExecutionInfo {
         self.non_optional_call_infos().map(|call_info| call_info.get_number_of_events()).sum()
     }
 
+    /// Returns the number and total size of the events emitted in this transaction execution,
+    /// including those of the fee transfer.
+    pub fn summarize_events(&self) -> EventSummary {
+        self.non_optional_call_infos().map(|call_info| call_info.summarize_events()).sum()
+    }
+
     pub fn is_reverted(&self) -> bool {
         self.revert_error.is_some()
     }
+
+    /// Returns the L1 gas vector the transaction is charged for: the L1 gas of its VM resources,
+    /// plus the L1 gas and L1 data gas of its messages and state diff.
+    pub fn actual_gas_vector(
+        &self,
+        versioned_constants: &VersionedConstants,
+    ) -> TransactionFeeResult<GasVector> {
+        calculate_tx_gas_vector(&self.actual_resources, versioned_constants)
+    }
+
+    /// Returns the fee of the actual gas vector at the given gas prices, in the given fee token.
+    pub fn fee(
+        &self,
+        versioned_constants: &VersionedConstants,
+        gas_prices: &GasPrices,
+        fee_type: &FeeType,
+    ) -> TransactionFeeResult<Fee> {
+        self.actual_gas_vector(versioned_constants)?.checked_cost(
+            gas_prices.get_gas_price_by_fee_type(fee_type).into(),
+            gas_prices.get_data_gas_price_by_fee_type(fee_type).into(),
+        )
+    }
+}
+
+/// A transaction execution resource: a Cairo VM resource (steps, memory holes or a builtin), or
+/// the L1 gas consumed by the transaction.
+#[derive(Clone, Copy, Debug, EnumIter, Eq, Hash, Ord, PartialEq, PartialOrd)]
+pub enum Resource {
+    Steps,
+    MemoryHoles,
+    OutputBuiltin,
+    PedersenBuiltin,
+    RangeCheckBuiltin,
+    EcdsaBuiltin,
+    BitwiseBuiltin,
+    EcOpBuiltin,
+    KeccakBuiltin,
+    PoseidonBuiltin,
+    SegmentArenaBuiltin,
+    L1Gas,
+    L1DataGas,
+}
+
+impl Resource {
+    /// The resource name, as it appears in execution outputs and in the versioned constants.
+    pub fn name(&self) -> &'static str {
+        match self {
+            Self::Steps => abi_constants::N_STEPS_RESOURCE,
+            Self::MemoryHoles => "n_memory_holes",
+            Self::OutputBuiltin => builtin_runner::OUTPUT_BUILTIN_NAME,
+            Self::PedersenBuiltin => builtin_runner::HASH_BUILTIN_NAME,
+            Self::RangeCheckBuiltin => builtin_runner::RANGE_CHECK_BUILTIN_NAME,
+            Self::EcdsaBuiltin => builtin_runner::SIGNATURE_BUILTIN_NAME,
+            Self::BitwiseBuiltin => builtin_runner::BITWISE_BUILTIN_NAME,
+            Self::EcOpBuiltin => builtin_runner::EC_OP_BUILTIN_NAME,
+            Self::KeccakBuiltin => builtin_runner::KECCAK_BUILTIN_NAME,
+            Self::PoseidonBuiltin => builtin_runner::POSEIDON_BUILTIN_NAME,
+            Self::SegmentArenaBuiltin => builtin_runner::SEGMENT_ARENA_BUILTIN_NAME,
+            Self::L1Gas => abi_constants::L1_GAS_USAGE,
+            Self::L1DataGas => abi_constants::BLOB_GAS_USAGE,
+        }
+    }
+
+    /// Whether the resource is charged by an L1 gas weight in `vm_resource_fee_cost`.
+    /// Memory holes and segment arena instances are charged as steps, and L1 gas is charged
+    /// directly; see `calculate_tx_resources`.
+    pub fn has_fee_weight(&self) -> bool {
+        match self {
+            Self::Steps
+            | Self::OutputBuiltin
+            | Self::PedersenBuiltin
+            | Self::RangeCheckBuiltin
+            | Self::EcdsaBuiltin
+            | Self::BitwiseBuiltin
+            | Self::EcOpBuiltin
+            | Self::KeccakBuiltin
+            | Self::PoseidonBuiltin => true,
+            Self::MemoryHoles | Self::SegmentArenaBuiltin | Self::L1Gas | Self::L1DataGas => false,
+        }
+    }
+}
+
+impl fmt::Display for Resource {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(self.name())
+    }
+}
+
+impl FromStr for Resource {
+    type Err = ParseError;
+
+    fn from_str(name: &str) -> Result<Self, Self::Err> {
+        Self::iter()
+            .find(|resource| resource.name() == name)
+            .ok_or_else(|| ParseError::UnknownResource(name.to_string()))
+    }
+}
+
+impl Serialize for Resource {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        serializer.serialize_str(self.name())
+    }
+}
+
+impl<'de> Deserialize<'de> for Resource {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        let name = String::deserialize(deserializer)?;
+        name.parse().map_err(DeserializationError::custom)
+    }
 }
 
 /// A mapping from a transaction execution resource to its actual usage.
 #[cfg_attr(test, derive(Clone))]
 #[derive(Debug, Default, Eq, PartialEq, Serialize)]
-pub struct ResourcesMapping(pub HashMap<String, usize>);
+pub struct ResourcesMapping(pub HashMap<Resource, usize>);
 
 impl ResourcesMapping {
     #[cfg(test)]
     pub fn n_steps(&self) -> usize {
-        *self.0.get(crate::abi::constants::N_STEPS_RESOURCE).unwrap()
+        *self.0.get(&Resource::Steps).unwrap()
     }
 
     #[cfg(test)]
     pub fn gas_usage(&self) -> usize {
-        *self.0.get(crate::abi::constants::L1_GAS_USAGE).unwrap()
+        *self.0.get(&Resource::L1Gas).unwrap()
     }
 
     #[cfg(test)]
     pub fn blob_gas_usage(&self) -> usize {
-        *self.0.get(crate::abi::constants::BLOB_GAS_USAGE).unwrap()
+        *self.0.get(&Resource::L1DataGas).unwrap()
+    }
+}
+
+impl TryFrom<&VmExecutionResources> for ResourcesMapping {
+    type Error = ParseError;
+
+    /// Maps the VM resources to their usage; fails on a builtin unknown to the Blockifier.
+    fn try_from(vm_resources: &VmExecutionResources) -> Result<Self, Self::Error> {
+        let mut resources = HashMap::from([
+            (Resource::Steps, vm_resources.n_steps),
+            (Resource::MemoryHoles, vm_resources.n_memory_holes),
+        ]);
+        for (builtin_name, &usage) in &vm_resources.builtin_instance_counter {
+            resources.insert(builtin_name.parse()?, usage);
+        }
+
+        Ok(Self(resources))
     }
 }
 
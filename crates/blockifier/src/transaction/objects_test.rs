@@ -1,7 +1,22 @@
+use std::collections::HashMap;
+
+use assert_matches::assert_matches;
+use cairo_vm::vm::runners::builtin_runner::{RANGE_CHECK_BUILTIN_NAME, SEGMENT_ARENA_BUILTIN_NAME};
+use cairo_vm::vm::runners::cairo_runner::ExecutionResources as VmExecutionResources;
 use rstest::rstest;
+use starknet_api::transaction::Fee;
+use strum::IntoEnumIterator;
 
+use crate::blockifier::block::GasPrices;
 use crate::execution::call_info::{CallExecution, CallInfo, OrderedEvent};
-use crate::transaction::objects::TransactionExecutionInfo;
+use crate::fee::fee_utils::calculate_l1_gas_by_vm_usage;
+use crate::fee::gas_usage::{get_da_gas_cost, get_payloads_messages_gas_cost};
+use crate::state::cached_state::StateChangesCount;
+use crate::transaction::errors::{ParseError, TransactionFeeError};
+use crate::transaction::objects::{
+    FeeType, GasVector, Resource, ResourcesMapping, TransactionExecutionInfo,
+};
+use crate::versioned_constants::VersionedConstants;
 
 #[rstest]
 #[case(0, 0)]
@@ -42,3 +57,166 @@ fn test_transaction_execution_info_with_different_event_scenarios(
             + num_of_inner_calls
     );
 }
+
+#[test]
+fn test_resource_name_round_trip() {
+    for resource in Resource::iter() {
+        let name = resource.to_string();
+        assert_eq!(name, resource.name());
+        assert_eq!(name.parse::<Resource>().unwrap(), resource);
+
+        let serialized = serde_json::to_value(resource).unwrap();
+        assert_eq!(serialized, serde_json::Value::String(name));
+        assert_eq!(serde_json::from_value::<Resource>(serialized).unwrap(), resource);
+    }
+
+    assert_matches!(
+        "bad_resource_name".parse::<Resource>(),
+        Err(ParseError::UnknownResource(name)) if name == "bad_resource_name"
+    );
+    assert!(serde_json::from_str::<Resource>("\"bad_resource_name\"").is_err());
+}
+
+#[test]
+fn test_resources_mapping_serializes_by_name() {
+    let resources = ResourcesMapping(HashMap::from([(Resource::Steps, 7), (Resource::L1Gas, 3)]));
+
+    assert_eq!(
+        serde_json::to_value(&resources).unwrap(),
+        serde_json::json!({"n_steps": 7, "l1_gas_usage": 3})
+    );
+}
+
+#[test]
+fn test_resources_mapping_from_vm_resources() {
+    let vm_resources = VmExecutionResources {
+        n_steps: 100,
+        n_memory_holes: 5,
+        builtin_instance_counter: HashMap::from([
+            (RANGE_CHECK_BUILTIN_NAME.to_string(), 7),
+            (SEGMENT_ARENA_BUILTIN_NAME.to_string(), 2),
+        ]),
+    };
+
+    assert_eq!(
+        ResourcesMapping::try_from(&vm_resources).unwrap(),
+        ResourcesMapping(HashMap::from([
+            (Resource::Steps, 100),
+            (Resource::MemoryHoles, 5),
+            (Resource::RangeCheckBuiltin, 7),
+            (Resource::SegmentArenaBuiltin, 2),
+        ]))
+    );
+
+    let unknown_builtin_resources = VmExecutionResources {
+        builtin_instance_counter: HashMap::from([("bad_builtin".to_string(), 1)]),
+        ..vm_resources
+    };
+    assert_matches!(
+        ResourcesMapping::try_from(&unknown_builtin_resources),
+        Err(ParseError::UnknownResource(name)) if name == "bad_builtin"
+    );
+}
+
+/// Every resource charged by weight is priced by the shipped constants.
+#[test]
+fn test_fee_weights_cover_resources() {
+    let vm_resource_fee_cost = VersionedConstants::latest_constants().vm_resource_fee_cost();
+    for resource in Resource::iter() {
+        assert_eq!(
+            vm_resource_fee_cost.contains_key(&resource),
+            resource.has_fee_weight(),
+            "Unexpected fee weight of {resource}."
+        );
+    }
+}
+
+#[test]
+fn test_gas_vector_checked_arithmetic() {
+    let gas_vector = GasVector { l1_gas: 3, l1_data_gas: 5 };
+
+    assert_eq!(
+        gas_vector.checked_add(GasVector { l1_gas: 7, l1_data_gas: 11 }).unwrap(),
+        GasVector { l1_gas: 10, l1_data_gas: 16 }
+    );
+    assert_eq!(
+        gas_vector.checked_scalar_mul(4).unwrap(),
+        GasVector { l1_gas: 12, l1_data_gas: 20 }
+    );
+    assert_eq!(gas_vector.checked_cost(2, 10).unwrap(), Fee(56));
+
+    let large_gas_vector = GasVector { l1_gas: 1, l1_data_gas: u128::MAX };
+    assert_matches!(
+        gas_vector.checked_add(large_gas_vector),
+        Err(TransactionFeeError::GasVectorAdditionOverflow { lhs, rhs })
+        if lhs == gas_vector && rhs == large_gas_vector
+    );
+    assert_matches!(
+        large_gas_vector.checked_scalar_mul(2),
+        Err(TransactionFeeError::GasVectorMultiplicationOverflow { gas_vector, factor: 2 })
+        if gas_vector == large_gas_vector
+    );
+    assert_matches!(
+        large_gas_vector.checked_cost(1, 1),
+        Err(TransactionFeeError::FeeOverflow { gas_price: 1, data_gas_price: 1, .. })
+    );
+    assert_matches!(
+        GasVector { l1_gas: u128::MAX, l1_data_gas: 1 }.checked_cost(1, 1),
+        Err(TransactionFeeError::FeeOverflow { .. })
+    );
+}
+
+#[rstest]
+#[case::low_prices(1, 2, 3, 4)]
+#[case::high_prices(100_000_000_000, 200_000_000_000, 1_000_000, 2_000_000)]
+fn test_actual_gas_vector_and_fee(
+    #[case] eth_l1_gas_price: u128,
+    #[case] strk_l1_gas_price: u128,
+    #[case] eth_l1_data_gas_price: u128,
+    #[case] strk_l1_data_gas_price: u128,
+) {
+    let versioned_constants = VersionedConstants::create_for_testing();
+    let vm_resources = ResourcesMapping(HashMap::from([
+        (Resource::Steps, 10_000),
+        (Resource::RangeCheckBuiltin, 100),
+    ]));
+    let vm_gas = calculate_l1_gas_by_vm_usage(&versioned_constants, &vm_resources).unwrap();
+    let messages_gas = get_payloads_messages_gas_cost(&[3], None).unwrap();
+    let state_changes_count =
+        StateChangesCount { n_storage_updates: 2, n_modified_contracts: 1, ..Default::default() };
+    let da_gas = get_da_gas_cost(state_changes_count, true);
+    for gas_vector in [vm_gas, messages_gas] {
+        assert!(gas_vector.l1_gas > 0 && gas_vector.l1_data_gas == 0);
+    }
+    assert!(da_gas.l1_gas == 0 && da_gas.l1_data_gas > 0);
+
+    let mut actual_resources = vm_resources;
+    actual_resources.0.extend([
+        (Resource::L1Gas, usize::try_from(messages_gas.l1_gas + da_gas.l1_gas).unwrap()),
+        (Resource::L1DataGas, usize::try_from(da_gas.l1_data_gas).unwrap()),
+    ]);
+    let tx_execution_info = TransactionExecutionInfo { actual_resources, ..Default::default() };
+    let expected_gas_vector =
+        vm_gas.checked_add(messages_gas).unwrap().checked_add(da_gas).unwrap();
+    assert_eq!(
+        tx_execution_info.actual_gas_vector(&versioned_constants).unwrap(),
+        expected_gas_vector
+    );
+
+    let gas_prices = GasPrices {
+        eth_l1_gas_price: eth_l1_gas_price.try_into().unwrap(),
+        strk_l1_gas_price: strk_l1_gas_price.try_into().unwrap(),
+        eth_l1_data_gas_price: eth_l1_data_gas_price.try_into().unwrap(),
+        strk_l1_data_gas_price: strk_l1_data_gas_price.try_into().unwrap(),
+    };
+    for (fee_type, gas_price, data_gas_price) in [
+        (FeeType::Eth, eth_l1_gas_price, eth_l1_data_gas_price),
+        (FeeType::Strk, strk_l1_gas_price, strk_l1_data_gas_price),
+    ] {
+        assert_eq!(
+            tx_execution_info.fee(&versioned_constants, &gas_prices, &fee_type).unwrap(),
+            Fee(expected_gas_vector.l1_gas * gas_price
+                + expected_gas_vector.l1_data_gas * data_gas_price)
+        );
+    }
+}
@@ -22,7 +22,7 @@ use crate::transaction::test_utils::{
     account_invoke_tx, block_context, l1_resource_bounds, max_fee, max_resource_bounds,
     run_invoke_tx, TestInitData,
 };
-use crate::transaction::transactions::ExecutableTransaction;
+use crate::transaction::transactions::{ExecutableTransaction, ExecutionFlags};
 
 fn init_data_by_version(chain_info: &ChainInfo, cairo_version: CairoVersion) -> TestInitData {
     let test_contract = FeatureContract::TestContract(cairo_version);
@@ -110,7 +110,7 @@ fn test_revert_on_overdraft(
     });
     let tx_info = approve_tx.create_tx_info();
     let approval_execution_info =
-        approve_tx.execute(&mut state, &block_context, true, true).unwrap();
+        approve_tx.execute(&mut state, &block_context, ExecutionFlags::default()).unwrap();
     assert!(!approval_execution_info.is_reverted());
 
     // Transfer a valid amount of funds to compute the cost of a successful
@@ -200,6 +200,93 @@ fn test_revert_on_overdraft(
     );
 }
 
+/// Tests that when a transaction drains an account's balance before fee transfer, and the block
+/// rejects transactions failing the fee checks, the transaction fails and leaves no trace.
+#[rstest]
+#[case(TransactionVersion::ONE, FeeType::Eth)]
+#[case(TransactionVersion::THREE, FeeType::Strk)]
+fn test_reject_on_overdraft(
+    max_fee: Fee,
+    max_resource_bounds: ResourceBoundsMapping,
+    mut block_context: BlockContext,
+    #[case] version: TransactionVersion,
+    #[case] fee_type: FeeType,
+) {
+    block_context.set_reject_fee_check_failures(true);
+    let chain_info = &block_context.chain_info;
+    let fee_token_address = chain_info.fee_token_address(&fee_type);
+    let storage_address = stark_felt!(10_u8);
+    let storage_key = StorageKey::try_from(storage_address).unwrap();
+    let recipient = stark_felt!(7_u8);
+    let recipient_address = ContractAddress(patricia_key!(recipient));
+
+    let TestInitData { mut state, account_address, contract_address, mut nonce_manager } =
+        init_data_by_version(chain_info, CairoVersion::Cairo0);
+
+    // Approve the test contract to transfer funds.
+    let approve_calldata = create_calldata(
+        fee_token_address,
+        "approve",
+        &[*contract_address.0.key(), stark_felt!(BALANCE), stark_felt!(0_u8)],
+    );
+    let approval_execution_info = run_invoke_tx(
+        &mut state,
+        &block_context,
+        invoke_tx_args! {
+            max_fee,
+            sender_address: account_address,
+            calldata: approve_calldata,
+            version,
+            resource_bounds: max_resource_bounds.clone(),
+            nonce: nonce_manager.next(account_address),
+        },
+    )
+    .unwrap();
+    assert!(!approval_execution_info.is_reverted());
+
+    // Attempt to transfer the entire balance, such that no funds remain to pay transaction fee.
+    let (balance, _) = state.get_fee_token_balance(account_address, fee_token_address).unwrap();
+    let nonce = state.get_nonce_at(account_address).unwrap();
+    let error = run_invoke_tx(
+        &mut state,
+        &block_context,
+        invoke_tx_args! {
+            max_fee,
+            sender_address: account_address,
+            calldata: calldata_for_write_and_transfer(
+                contract_address,
+                storage_address,
+                stark_felt!(77_u8),
+                recipient,
+                balance,
+                fee_token_address
+            ),
+            version,
+            resource_bounds: max_resource_bounds,
+            nonce: nonce_manager.next(account_address),
+        },
+    )
+    .unwrap_err();
+    assert_matches!(
+        error,
+        TransactionExecutionError::FeeCheckError(FeeCheckError::InsufficientFeeTokenBalance {
+            balance_low, ..
+        }) if balance_low == stark_felt!(0_u8)
+    );
+
+    // Verify the nonce, the storage and the balances are all unchanged (no fee was charged).
+    assert_eq!(state.get_nonce_at(account_address).unwrap(), nonce);
+    assert_eq!(state.get_storage_at(contract_address, storage_key).unwrap(), stark_felt!(0_u8));
+    assert_eq!(
+        state.get_fee_token_balance(account_address, fee_token_address).unwrap(),
+        (balance, stark_felt!(0_u8))
+    );
+    assert_eq!(
+        state.get_fee_token_balance(recipient_address, fee_token_address).unwrap(),
+        (stark_felt!(0_u8), stark_felt!(0_u8))
+    );
+}
+
 /// Tests that when a transaction requires more resources than what the sender bounds allow, the
 /// execution is reverted; in the non-revertible case, checks for the correct error.
 // TODO(Aner, 21/01/24) modify for 4844 (taking blob_gas into account).
@@ -0,0 +1,74 @@
+use starknet_api::core::{ClassHash, CompiledClassHash};
+
+use crate::execution::contract_class::ContractClass;
+use crate::state::errors::StateError;
+use crate::state::state_api::State;
+use crate::transaction::errors::TransactionExecutionError;
+use crate::transaction::objects::TransactionExecutionResult;
+
+#[cfg(test)]
+#[path = "system_declare_batch_test.rs"]
+mod test;
+
+/// A class to be declared as part of a [`SystemDeclareBatch`].
+#[derive(Clone, Debug)]
+pub struct SystemDeclareEntry {
+    pub class_hash: ClassHash,
+    /// Ignored for Cairo 0 classes.
+    pub compiled_class_hash: CompiledClassHash,
+    pub contract_class: ContractClass,
+}
+
+/// A non-protocol transaction that declares many classes at once, e.g., at devnet genesis.
+/// Classes are written through the same state paths as a regular declare, but no fee is charged,
+/// no account validation is run and no nonce is incremented.
+#[derive(Clone, Debug, Default)]
+pub struct SystemDeclareBatch {
+    pub classes: Vec<SystemDeclareEntry>,
+}
+
+impl SystemDeclareBatch {
+    pub fn new(classes: Vec<(ClassHash, CompiledClassHash, ContractClass)>) -> Self {
+        let classes = classes
+            .into_iter()
+            .map(|(class_hash, compiled_class_hash, contract_class)| SystemDeclareEntry {
+                class_hash,
+                compiled_class_hash,
+                contract_class,
+            })
+            .collect();
+
+        Self { classes }
+    }
+
+    /// Declares the classes in order. A failed declaration does not affect the rest of the batch.
+    /// Returns the result of each declaration, in the order the classes were given.
+    pub fn execute(
+        &self,
+        state: &mut dyn State,
+    ) -> Vec<(ClassHash, TransactionExecutionResult<()>)> {
+        self.classes.iter().map(|entry| (entry.class_hash, declare_class(state, entry))).collect()
+    }
+}
+
+fn declare_class(
+    state: &mut dyn State,
+    entry: &SystemDeclareEntry,
+) -> TransactionExecutionResult<()> {
+    let class_hash = entry.class_hash;
+    match state.get_compiled_contract_class(class_hash) {
+        Err(StateError::UndeclaredClassHash(_)) => {}
+        Err(error) => Err(error)?,
+        Ok(_) => {
+            // Class is already declared, cannot redeclare.
+            return Err(TransactionExecutionError::DeclareTransactionError { class_hash });
+        }
+    }
+
+    state.set_contract_class(class_hash, entry.contract_class.clone())?;
+    if let ContractClass::V1(_) = entry.contract_class {
+        state.set_compiled_class_hash(class_hash, entry.compiled_class_hash)?;
+    }
+
+    Ok(())
+}
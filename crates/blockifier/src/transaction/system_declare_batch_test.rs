@@ -0,0 +1,68 @@
+use assert_matches::assert_matches;
+use indexmap::IndexMap;
+use pretty_assertions::assert_eq;
+use starknet_api::core::CompiledClassHash;
+use starknet_api::hash::StarkFelt;
+use starknet_api::stark_felt;
+
+use crate::state::cached_state::CachedState;
+use crate::state::state_api::StateReader;
+use crate::test_utils::contracts::FeatureContract;
+use crate::test_utils::declare::system_declare_batch;
+use crate::test_utils::dict_state_reader::DictStateReader;
+use crate::test_utils::CairoVersion;
+use crate::transaction::errors::TransactionExecutionError;
+
+#[test]
+fn test_system_declare_batch() {
+    let mut state = CachedState::from(DictStateReader::default());
+    let test_contract_cairo0 = FeatureContract::TestContract(CairoVersion::Cairo0);
+    let test_contract_cairo1 = FeatureContract::TestContract(CairoVersion::Cairo1);
+    let empty_contract_cairo0 = FeatureContract::Empty(CairoVersion::Cairo0);
+    let empty_contract_cairo1 = FeatureContract::Empty(CairoVersion::Cairo1);
+    let test_compiled_class_hash = CompiledClassHash(stark_felt!("0x11"));
+    let empty_compiled_class_hash = CompiledClassHash(stark_felt!("0x12"));
+
+    let batch = system_declare_batch(&[
+        (test_contract_cairo0, CompiledClassHash::default()),
+        (test_contract_cairo1, test_compiled_class_hash),
+        (empty_contract_cairo0, CompiledClassHash::default()),
+        // Duplicate; should fail without affecting the rest of the batch.
+        (test_contract_cairo1, CompiledClassHash(stark_felt!("0x13"))),
+        (empty_contract_cairo1, empty_compiled_class_hash),
+    ]);
+    let results = batch.execute(&mut state);
+
+    assert_eq!(results.len(), 5);
+    for (i, (class_hash, result)) in results.iter().enumerate() {
+        assert_eq!(*class_hash, batch.classes[i].class_hash);
+        if i == 3 {
+            assert_matches!(
+                result,
+                Err(TransactionExecutionError::DeclareTransactionError { class_hash })
+                if *class_hash == test_contract_cairo1.get_class_hash()
+            );
+        } else {
+            assert_matches!(result, Ok(()));
+        }
+    }
+
+    for contract in
+        [test_contract_cairo0, test_contract_cairo1, empty_contract_cairo0, empty_contract_cairo1]
+    {
+        let class_hash = contract.get_class_hash();
+        assert_eq!(state.get_compiled_contract_class(class_hash).unwrap(), contract.get_class());
+    }
+
+    // Only Cairo 1 classes have a compiled class hash; the duplicate did not override it.
+    let state_diff = state.to_state_diff();
+    assert_eq!(
+        state_diff.class_hash_to_compiled_class_hash,
+        [
+            (test_contract_cairo1.get_class_hash(), test_compiled_class_hash),
+            (empty_contract_cairo1.get_class_hash(), empty_compiled_class_hash),
+        ]
+        .into_iter()
+        .collect::<IndexMap<_, _>>()
+    );
+}
@@ -34,7 +34,7 @@ use crate::transaction::account_transaction::AccountTransaction;
 use crate::transaction::constants;
 use crate::transaction::objects::{FeeType, TransactionExecutionInfo, TransactionExecutionResult};
 use crate::transaction::transaction_types::TransactionType;
-use crate::transaction::transactions::{ExecutableTransaction, InvokeTransaction};
+use crate::transaction::transactions::{ExecutableTransaction, ExecutionFlags, InvokeTransaction};
 use crate::{declare_tx_args, deploy_account_tx_args, invoke_tx_args};
 
 // Corresponding constants to the ones in faulty_account.
@@ -311,7 +311,7 @@ pub fn run_invoke_tx(
     block_context: &BlockContext,
     invoke_args: InvokeTxArgs,
 ) -> TransactionExecutionResult<TransactionExecutionInfo> {
-    account_invoke_tx(invoke_args).execute(state, block_context, true, true)
+    account_invoke_tx(invoke_args).execute(state, block_context, ExecutionFlags::default())
 }
 
 /// Creates a `ResourceBoundsMapping` with the given `max_amount` and `max_price` for L1 gas limits.
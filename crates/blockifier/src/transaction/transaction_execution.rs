@@ -17,7 +17,7 @@ use crate::transaction::objects::{
 };
 use crate::transaction::transactions::{
     DeclareTransaction, DeployAccountTransaction, Executable, ExecutableTransaction,
-    InvokeTransaction, L1HandlerTransaction,
+    ExecutionFlags, InvokeTransaction, L1HandlerTransaction,
 };
 
 // TODO: Move into transaction.rs, makes more sense to be defined there.
@@ -104,8 +104,7 @@ impl<S: StateReader> ExecutableTransaction<S> for L1HandlerTransaction {
         self,
         state: &mut TransactionalState<'_, S>,
         block_context: &BlockContext,
-        _charge_fee: bool,
-        _validate: bool,
+        _execution_flags: ExecutionFlags,
     ) -> TransactionExecutionResult<TransactionExecutionInfo> {
         let tx_context = Arc::new(block_context.to_tx_context(&self));
 
@@ -116,16 +115,21 @@ impl<S: StateReader> ExecutableTransaction<S> for L1HandlerTransaction {
             self.run_execute(state, &mut execution_resources, &mut context, &mut remaining_gas)?;
         let l1_handler_payload_size = self.payload_size();
 
-        let ActualCost { actual_fee, da_gas, actual_resources } =
+        let ActualCost { actual_fee, da_gas, actual_resources, .. } =
             ActualCost::builder_for_l1_handler(tx_context, l1_handler_payload_size)
                 .with_execute_call_info(&execute_call_info)
                 .try_add_state_changes(state)?
                 .build(&execution_resources)?;
 
         let paid_fee = self.paid_fee_on_l1;
-        // For now, assert only that any amount of fee was paid.
+        // Unless enforced, assert only that any amount of fee was paid.
         // The error message still indicates the required fee.
-        if paid_fee == Fee(0) {
+        let insufficient_fee = if block_context.enforce_l1_handler_fee() {
+            paid_fee < actual_fee
+        } else {
+            paid_fee == Fee(0)
+        };
+        if insufficient_fee {
             return Err(TransactionFeeError::InsufficientL1Fee { paid_fee, actual_fee })?;
         }
 
@@ -136,7 +140,9 @@ impl<S: StateReader> ExecutableTransaction<S> for L1HandlerTransaction {
             actual_fee: Fee::default(),
             da_gas,
             actual_resources,
+            n_reverted_steps: 0,
             revert_error: None,
+            syscall_metrics: context.syscall_metrics.unwrap_or_default(),
         })
     }
 }
@@ -146,16 +152,13 @@ impl<S: StateReader> ExecutableTransaction<S> for Transaction {
         self,
         state: &mut TransactionalState<'_, S>,
         block_context: &BlockContext,
-        charge_fee: bool,
-        validate: bool,
+        execution_flags: ExecutionFlags,
     ) -> TransactionExecutionResult<TransactionExecutionInfo> {
         match self {
             Self::AccountTransaction(account_tx) => {
-                account_tx.execute_raw(state, block_context, charge_fee, validate)
-            }
-            Self::L1HandlerTransaction(tx) => {
-                tx.execute_raw(state, block_context, charge_fee, validate)
+                account_tx.execute_raw(state, block_context, execution_flags)
             }
+            Self::L1HandlerTransaction(tx) => tx.execute_raw(state, block_context, execution_flags),
         }
     }
 }
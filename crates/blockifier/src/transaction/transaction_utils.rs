@@ -1,20 +1,26 @@
-use std::collections::HashMap;
-
-use cairo_vm::vm::runners::builtin_runner::SEGMENT_ARENA_BUILTIN_NAME;
 use cairo_vm::vm::runners::cairo_runner::ExecutionResources;
 use starknet_api::transaction::TransactionVersion;
 
-use crate::abi::constants;
 use crate::execution::call_info::CallInfo;
 use crate::execution::contract_class::ContractClass;
 use crate::fee::gas_usage::get_onchain_data_segment_length;
 use crate::state::cached_state::StateChangesCount;
 use crate::transaction::errors::TransactionExecutionError;
-use crate::transaction::objects::{GasVector, ResourcesMapping, TransactionExecutionResult};
+use crate::transaction::objects::{
+    GasVector, Resource, ResourcesMapping, TransactionExecutionResult,
+};
 use crate::transaction::transaction_types::TransactionType;
 use crate::utils::usize_from_u128;
 use crate::versioned_constants::VersionedConstants;
 
+/// The sizes of the transaction input that its OS resources scale with.
+#[derive(Clone, Copy, Debug, Default)]
+pub struct TxInputSizes {
+    pub calldata_length: usize,
+    // The size (in felts) of the declared class; only given for declare transactions.
+    pub class_size: Option<usize>,
+}
+
 /// Calculates the total resources needed to include the transaction in a Starknet block as
 /// most-recent (recent w.r.t. application on the given state).
 /// I.e., Cairo VM execution resources.
@@ -23,7 +29,7 @@ pub fn calculate_tx_resources(
     execution_resources: &ExecutionResources,
     gas_vector: GasVector,
     tx_type: TransactionType,
-    calldata_length: usize,
+    input_sizes: TxInputSizes,
     state_changes_count: StateChangesCount,
     use_kzg_da: bool,
 ) -> TransactionExecutionResult<ResourcesMapping> {
@@ -36,28 +42,53 @@ pub fn calculate_tx_resources(
     let total_vm_usage = execution_resources
         + &versioned_constants.get_additional_os_tx_resources(
             tx_type,
-            calldata_length,
+            input_sizes.calldata_length,
+            input_sizes.class_size,
             data_segment_length,
             use_kzg_da,
         )?;
-    let mut total_vm_usage = total_vm_usage.filter_unused_builtins();
+    let mut tx_resources = to_charged_resources(&total_vm_usage)?;
+    tx_resources
+        .0
+        .extend([(Resource::L1Gas, l1_gas_usage), (Resource::L1DataGas, l1_blob_gas_usage)]);
+
+    Ok(tx_resources)
+}
+
+/// Replaces the fee transfer approximation included in the OS resources of the transaction with
+/// the VM resources of the executed fee transfer call.
+/// Used when the block context counts the actual fee transfer resources.
+pub fn replace_fee_transfer_approximation(
+    tx_resources: &mut ResourcesMapping,
+    fee_transfer_approximation: &ExecutionResources,
+    fee_transfer_resources: &ExecutionResources,
+) -> TransactionExecutionResult<()> {
+    for (resource, value) in to_charged_resources(fee_transfer_approximation)?.0 {
+        let tx_value = tx_resources.0.entry(resource).or_default();
+        *tx_value = tx_value.saturating_sub(value);
+    }
+    for (resource, value) in to_charged_resources(fee_transfer_resources)?.0 {
+        *tx_resources.0.entry(resource).or_default() += value;
+    }
+    Ok(())
+}
+
+/// Converts VM resources into the resources charged for them.
+fn to_charged_resources(
+    vm_resources: &ExecutionResources,
+) -> TransactionExecutionResult<ResourcesMapping> {
+    let mut resources = ResourcesMapping::try_from(&vm_resources.filter_unused_builtins())?;
+    // Memory holes are counted as steps.
+    let n_memory_holes = resources.0.remove(&Resource::MemoryHoles).unwrap_or_default();
     // The segment arena" builtin is not part of SHARP (not in any proof layout).
     // Each instance requires approximately 10 steps in the OS.
     // TODO(Noa, 01/07/23): Verify the removal of the segmen_arena builtin.
-    let n_steps = total_vm_usage.n_steps
-        + 10 * total_vm_usage
-            .builtin_instance_counter
-            .remove(SEGMENT_ARENA_BUILTIN_NAME)
-            .unwrap_or_default();
-
-    let mut tx_resources = HashMap::from([
-        (constants::L1_GAS_USAGE.to_string(), l1_gas_usage),
-        (constants::BLOB_GAS_USAGE.to_string(), l1_blob_gas_usage),
-        (constants::N_STEPS_RESOURCE.to_string(), n_steps + total_vm_usage.n_memory_holes),
-    ]);
-    tx_resources.extend(total_vm_usage.builtin_instance_counter);
+    let n_segment_arena_instances =
+        resources.0.remove(&Resource::SegmentArenaBuiltin).unwrap_or_default();
+    *resources.0.entry(Resource::Steps).or_default() +=
+        n_memory_holes + 10 * n_segment_arena_instances;
 
-    Ok(ResourcesMapping(tx_resources))
+    Ok(resources)
 }
 
 pub fn update_remaining_gas(remaining_gas: &mut u64, call_info: &CallInfo) {
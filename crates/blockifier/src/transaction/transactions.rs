@@ -39,6 +39,26 @@ macro_rules! implement_inner_tx_getter_calls {
     };
 }
 
+/// Controls which of the fee-related and validation stages of a transaction run; all are enforced
+/// by default.
+#[derive(Clone, Copy, Debug)]
+pub struct ExecutionFlags {
+    /// Whether to check the fee bounds and balance of the sender and to transfer the fee; fees are
+    /// still computed if not.
+    pub charge_fee: bool,
+    /// Whether to run the validate entry point of the account.
+    pub validate: bool,
+    /// Whether the transaction nonce must equal the account nonce; otherwise, any nonce not below
+    /// the account nonce is accepted (e.g., for a transaction queued behind others).
+    pub strict_nonce_check: bool,
+}
+
+impl Default for ExecutionFlags {
+    fn default() -> Self {
+        Self { charge_fee: true, validate: true, strict_nonce_check: true }
+    }
+}
+
 pub trait ExecutableTransaction<S: StateReader>: Sized {
     /// Executes the transaction in a transactional manner
     /// (if it fails, given state does not modify).
@@ -46,13 +66,12 @@ pub trait ExecutableTransaction<S: StateReader>: Sized {
         self,
         state: &mut CachedState<S>,
         block_context: &BlockContext,
-        charge_fee: bool,
-        validate: bool,
+        execution_flags: ExecutionFlags,
     ) -> TransactionExecutionResult<TransactionExecutionInfo> {
         log::debug!("Executing Transaction...");
         let mut transactional_state = CachedState::create_transactional(state);
         let execution_result =
-            self.execute_raw(&mut transactional_state, block_context, charge_fee, validate);
+            self.execute_raw(&mut transactional_state, block_context, execution_flags);
 
         match execution_result {
             Ok(value) => {
@@ -76,8 +95,7 @@ pub trait ExecutableTransaction<S: StateReader>: Sized {
         self,
         state: &mut TransactionalState<'_, S>,
         block_context: &BlockContext,
-        charge_fee: bool,
-        validate: bool,
+        execution_flags: ExecutionFlags,
     ) -> TransactionExecutionResult<TransactionExecutionInfo>;
 }
 
@@ -157,6 +175,11 @@ impl DeclareTransaction {
     pub fn only_query(&self) -> bool {
         self.only_query
     }
+
+    /// Returns the transaction with the query bit set in its version.
+    pub fn into_query(self) -> Self {
+        Self { only_query: true, ..self }
+    }
 }
 
 impl<S: State> Executable<S> for DeclareTransaction {
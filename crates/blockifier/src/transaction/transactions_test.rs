@@ -1,4 +1,4 @@
-use std::collections::{HashMap, HashSet};
+use std::collections::{BTreeMap, HashMap, HashSet};
 use std::sync::Arc;
 
 use assert_matches::assert_matches;
@@ -10,13 +10,15 @@ use num_traits::Pow;
 use once_cell::sync::Lazy;
 use pretty_assertions::assert_eq;
 use rstest::{fixture, rstest};
-use starknet_api::core::{ChainId, ClassHash, ContractAddress, EthAddress, Nonce, PatriciaKey};
+use starknet_api::core::{
+    ChainId, ClassHash, CompiledClassHash, ContractAddress, EthAddress, Nonce, PatriciaKey,
+};
 use starknet_api::deprecated_contract_class::EntryPointType;
 use starknet_api::hash::{StarkFelt, StarkHash};
 use starknet_api::state::StorageKey;
 use starknet_api::transaction::{
-    Calldata, EventContent, EventData, EventKey, Fee, L2ToL1Payload, TransactionHash,
-    TransactionSignature, TransactionVersion,
+    Calldata, EventContent, EventData, EventKey, Fee, L2ToL1Payload, ResourceBounds,
+    ResourceBoundsMapping, TransactionHash, TransactionSignature, TransactionVersion,
 };
 use starknet_api::{calldata, class_hash, contract_address, patricia_key, stark_felt};
 use strum::IntoEnumIterator;
@@ -29,11 +31,11 @@ use crate::abi::constants as abi_constants;
 use crate::abi::sierra_types::next_storage_key;
 use crate::context::{BlockContext, ChainInfo, FeeTokenAddresses, TransactionContext};
 use crate::execution::call_info::{
-    CallExecution, CallInfo, MessageToL1, OrderedEvent, OrderedL2ToL1Message, Retdata,
+    CallExecution, CallInfo, EventSummary, MessageToL1, OrderedEvent, OrderedL2ToL1Message, Retdata,
 };
 use crate::execution::contract_class::{ContractClass, ContractClassV0, ContractClassV1};
 use crate::execution::entry_point::{CallEntryPoint, CallType};
-use crate::execution::errors::EntryPointExecutionError;
+use crate::execution::errors::{EntryPointExecutionError, PreExecutionError};
 use crate::execution::execution_utils::{felt_to_stark_felt, stark_felt_to_felt};
 use crate::execution::syscalls::hint_processor::EmitEventError;
 use crate::fee::fee_utils::calculate_tx_fee;
@@ -68,7 +70,7 @@ use crate::transaction::errors::{
     TransactionExecutionError, TransactionFeeError, TransactionPreValidationError,
 };
 use crate::transaction::objects::{
-    FeeType, GasVector, HasRelatedFeeType, ResourcesMapping, TransactionExecutionInfo,
+    FeeType, GasVector, HasRelatedFeeType, Resource, ResourcesMapping, TransactionExecutionInfo,
     TransactionInfo,
 };
 use crate::transaction::test_utils::{
@@ -78,7 +80,10 @@ use crate::transaction::test_utils::{
     GET_SEQUENCER_ADDRESS, INVALID, VALID,
 };
 use crate::transaction::transaction_types::TransactionType;
-use crate::transaction::transactions::{ExecutableTransaction, L1HandlerTransaction};
+use crate::transaction::transaction_utils::{calculate_tx_resources, TxInputSizes};
+use crate::transaction::transactions::{
+    ExecutableTransaction, ExecutionFlags, L1HandlerTransaction,
+};
 use crate::utils::usize_from_u128;
 use crate::versioned_constants::VersionedConstants;
 use crate::{
@@ -310,12 +315,11 @@ fn add_kzg_da_resources(
     let data_segment_length = get_onchain_data_segment_length(state_changes_count);
     let os_kzg_da_resources = versioned_constants.os_kzg_da_resources(data_segment_length);
 
-    let mut resources_to_add = os_kzg_da_resources.builtin_instance_counter;
-    resources_to_add
-        .insert(abi_constants::N_STEPS_RESOURCE.to_string(), os_kzg_da_resources.n_steps);
+    let mut resources_to_add = ResourcesMapping::try_from(&os_kzg_da_resources).unwrap();
+    resources_to_add.0.remove(&Resource::MemoryHoles);
 
-    resources_to_add.into_iter().for_each(|(key, value)| {
-        resources.0.entry(key.to_string()).and_modify(|v| *v += value).or_insert(value);
+    resources_to_add.0.into_iter().for_each(|(key, value)| {
+        resources.0.entry(key).and_modify(|v| *v += value).or_insert(value);
     });
 }
 
@@ -378,7 +382,8 @@ fn test_invoke_tx(
     let account_tx = AccountTransaction::Invoke(invoke_tx);
     let tx_context = block_context.to_tx_context(&account_tx);
 
-    let actual_execution_info = account_tx.execute(state, block_context, true, true).unwrap();
+    let actual_execution_info =
+        account_tx.execute(state, block_context, ExecutionFlags::default()).unwrap();
 
     // Build expected validate call info.
     let expected_account_class_hash = account_contract.get_class_hash();
@@ -454,19 +459,18 @@ fn test_invoke_tx(
         actual_fee: expected_actual_fee,
         da_gas,
         actual_resources: ResourcesMapping(HashMap::from([
+            (Resource::L1DataGas, usize_from_u128(da_gas.l1_data_gas).unwrap()),
             (
-                abi_constants::BLOB_GAS_USAGE.to_string(),
-                usize_from_u128(da_gas.l1_data_gas).unwrap(),
-            ),
-            (
-                abi_constants::L1_GAS_USAGE.to_string(),
+                Resource::L1Gas,
                 usize_from_u128(da_gas.l1_gas + calldata_and_signature_gas.l1_gas).unwrap(),
             ),
-            (HASH_BUILTIN_NAME.to_string(), 14 + calldata_length),
-            (RANGE_CHECK_BUILTIN_NAME.to_string(), expected_arguments.range_check),
-            (abi_constants::N_STEPS_RESOURCE.to_string(), expected_arguments.n_steps),
+            (Resource::PedersenBuiltin, 14 + calldata_length),
+            (Resource::RangeCheckBuiltin, expected_arguments.range_check),
+            (Resource::Steps, expected_arguments.n_steps),
         ])),
+        n_reverted_steps: 0,
         revert_error: None,
+        ..Default::default()
     };
 
     add_kzg_da_resources(
@@ -555,7 +559,7 @@ fn test_invoke_tx_advanced_operations(
             create_calldata(contract_address, "advance_counter", &calldata_args),
         ..base_tx_args.clone()
     });
-    account_tx.execute(state, block_context, true, true).unwrap();
+    account_tx.execute(state, block_context, ExecutionFlags::default()).unwrap();
 
     let next_nonce = nonce_manager.next(account_address);
     let initial_ec_point = [StarkFelt::ZERO, StarkFelt::ZERO];
@@ -584,7 +588,7 @@ fn test_invoke_tx_advanced_operations(
             create_calldata(contract_address, "call_xor_counters", &calldata_args),
         ..base_tx_args.clone()
     });
-    account_tx.execute(state, block_context, true, true).unwrap();
+    account_tx.execute(state, block_context, ExecutionFlags::default()).unwrap();
 
     let expected_counters = [
         stark_felt!(counter_diffs[0] ^ xor_values[0]),
@@ -608,7 +612,7 @@ fn test_invoke_tx_advanced_operations(
             create_calldata(contract_address, "test_ec_op", &[]),
         ..base_tx_args.clone()
     });
-    account_tx.execute(state, block_context, true, true).unwrap();
+    account_tx.execute(state, block_context, ExecutionFlags::default()).unwrap();
 
     let expected_ec_point = [
         StarkFelt::new([
@@ -646,7 +650,7 @@ fn test_invoke_tx_advanced_operations(
             create_calldata(contract_address, "add_signature_to_counters", &[index]),
         ..base_tx_args.clone()
     });
-    account_tx.execute(state, block_context, true, true).unwrap();
+    account_tx.execute(state, block_context, ExecutionFlags::default()).unwrap();
 
     let expected_counters = [
         felt_to_stark_felt(
@@ -675,7 +679,8 @@ fn test_invoke_tx_advanced_operations(
             create_calldata(contract_address, "send_message", &[felt_to_stark_felt(&to_address)]),
         ..base_tx_args
     });
-    let execution_info = account_tx.execute(state, block_context, true, true).unwrap();
+    let execution_info =
+        account_tx.execute(state, block_context, ExecutionFlags::default()).unwrap();
     let next_nonce = nonce_manager.next(account_address);
     verify_storage_after_invoke_advanced_operations(
         state,
@@ -736,7 +741,7 @@ fn test_state_get_fee_token_balance(
         version: tx_version,
         nonce: Nonce::default(),
     });
-    account_tx.execute(state, block_context, true, true).unwrap();
+    account_tx.execute(state, block_context, ExecutionFlags::default()).unwrap();
 
     // Get balance from state, and validate.
     let (low, high) =
@@ -754,7 +759,7 @@ fn assert_failure_if_resource_bounds_exceed_balance(
     match block_context.to_tx_context(&invalid_tx).tx_info {
         TransactionInfo::Deprecated(context) => {
             assert_matches!(
-                invalid_tx.execute(state, block_context, true, true).unwrap_err(),
+                invalid_tx.execute(state, block_context, ExecutionFlags::default()).unwrap_err(),
                 TransactionExecutionError::TransactionPreValidationError(
                     TransactionPreValidationError::TransactionFeeError(
                         TransactionFeeError::MaxFeeExceedsBalance{ max_fee, .. }))
@@ -764,7 +769,7 @@ fn assert_failure_if_resource_bounds_exceed_balance(
         TransactionInfo::Current(context) => {
             let l1_bounds = context.l1_resource_bounds().unwrap();
             assert_matches!(
-                invalid_tx.execute(state, block_context, true, true).unwrap_err(),
+                invalid_tx.execute(state, block_context, ExecutionFlags::default()).unwrap_err(),
                 TransactionExecutionError::TransactionPreValidationError(
                     TransactionPreValidationError::TransactionFeeError(
                         TransactionFeeError::L1GasBoundsExceedBalance{ max_amount, max_price, .. }))
@@ -837,6 +842,364 @@ fn test_max_fee_exceeds_balance(account_cairo_version: CairoVersion) {
     assert_failure_if_resource_bounds_exceed_balance(state, block_context, invalid_tx);
 }
 
+/// The max fee passes the pre-validation checks exactly at the minimal fee and at the balance of
+/// the sender; the checks are skipped if the fee is not charged, e.g., in fee estimation.
+#[rstest]
+fn test_max_fee_pre_validation_boundaries(#[values(true, false)] charge_fee: bool) {
+    let block_context = &BlockContext::create_for_account_testing();
+    let account_contract = FeatureContract::AccountWithoutValidations(CairoVersion::Cairo0);
+    let test_contract = FeatureContract::TestContract(CairoVersion::Cairo0);
+    let state = &mut test_state(
+        &block_context.chain_info,
+        BALANCE,
+        &[(account_contract, 1), (test_contract, 1)],
+    );
+    let invoke_args = invoke_tx_args! {
+        sender_address: account_contract.get_instance_address(0),
+        calldata: create_trivial_calldata(test_contract.get_instance_address(0)),
+        version: TransactionVersion::ONE,
+    };
+    let minimal_l1_gas = estimate_minimal_gas_vector(
+        block_context,
+        &account_invoke_tx(invoke_tx_args! { max_fee: Fee(MAX_FEE), ..invoke_args.clone() }),
+    )
+    .unwrap()
+    .l1_gas;
+    let minimal_fee =
+        Fee(minimal_l1_gas * u128::from(block_context.block_info.gas_prices.eth_l1_gas_price));
+
+    let mut pre_validate = |max_fee: Fee| {
+        let tx = account_invoke_tx(invoke_tx_args! { max_fee, ..invoke_args.clone() });
+        let tx_context = block_context.to_tx_context(&tx);
+        // Discard the nonce increment.
+        let mut transactional_state = CachedState::create_transactional(state);
+        tx.perform_pre_validation_stage(&mut transactional_state, &tx_context, charge_fee, false)
+    };
+
+    assert_matches!(pre_validate(minimal_fee), Ok(()));
+    assert_matches!(pre_validate(Fee(BALANCE)), Ok(()));
+
+    let too_low_max_fee = Fee(minimal_fee.0 - 1);
+    let too_high_max_fee = Fee(BALANCE + 1);
+    if charge_fee {
+        assert_matches!(
+            pre_validate(too_low_max_fee),
+            Err(TransactionPreValidationError::TransactionFeeError(
+                TransactionFeeError::MaxFeeTooLow { min_fee, max_fee }
+            ))
+            if min_fee == minimal_fee && max_fee == too_low_max_fee
+        );
+        assert_matches!(
+            pre_validate(too_high_max_fee),
+            Err(TransactionPreValidationError::TransactionFeeError(
+                TransactionFeeError::MaxFeeExceedsBalance { max_fee, .. }
+            ))
+            if max_fee == too_high_max_fee
+        );
+    } else {
+        assert_matches!(pre_validate(too_low_max_fee), Ok(()));
+        assert_matches!(pre_validate(too_high_max_fee), Ok(()));
+    }
+}
+
+/// V1 and V3 transactions in the same block are charged in ETH and in STRK, respectively.
+#[rstest]
+fn test_fee_token_by_tx_version() {
+    let block_context = &BlockContext::create_for_account_testing();
+    let account_contract = FeatureContract::AccountWithoutValidations(CairoVersion::Cairo0);
+    let test_contract = FeatureContract::TestContract(CairoVersion::Cairo0);
+    let state = &mut test_state(
+        &block_context.chain_info,
+        BALANCE,
+        &[(account_contract, 1), (test_contract, 1)],
+    );
+    let account_address = account_contract.get_instance_address(0);
+    let sequencer_address = block_context.block_info.sequencer_address;
+    let mut nonce_manager = NonceManager::default();
+
+    for (version, fee_type) in
+        [(TransactionVersion::ONE, FeeType::Eth), (TransactionVersion::THREE, FeeType::Strk)]
+    {
+        let tx = account_invoke_tx(invoke_tx_args! {
+            max_fee: Fee(MAX_FEE),
+            resource_bounds: l1_resource_bounds(MAX_L1_GAS_AMOUNT, MAX_L1_GAS_PRICE),
+            sender_address: account_address,
+            calldata: create_trivial_calldata(test_contract.get_instance_address(0)),
+            version,
+            nonce: nonce_manager.next(account_address),
+        });
+        assert!(block_context.to_tx_context(&tx).tx_info.fee_type() == fee_type);
+        let fee_token_address = block_context.fee_token_address(&fee_type);
+
+        let execution_info = tx.execute(state, block_context, ExecutionFlags::default()).unwrap();
+        let actual_fee = execution_info.actual_fee;
+        assert_ne!(actual_fee, Fee(0));
+        assert_eq!(
+            execution_info.fee_transfer_call_info.unwrap().call.storage_address,
+            fee_token_address
+        );
+        assert_eq!(
+            state.get_fee_token_balance(account_address, fee_token_address).unwrap(),
+            (stark_felt!(BALANCE - actual_fee.0), StarkFelt::ZERO)
+        );
+        assert_eq!(
+            state.get_fee_token_balance(sequencer_address, fee_token_address).unwrap(),
+            (stark_felt!(actual_fee.0), StarkFelt::ZERO)
+        );
+    }
+}
+
+/// The resources of the executed fee transfer replace its approximation in the OS resources only if
+/// the block context counts them. The charged fee, based on the approximation, is the same either
+/// way.
+#[rstest]
+fn test_count_fee_transfer_resources() {
+    let account_contract = FeatureContract::AccountWithoutValidations(CairoVersion::Cairo0);
+    let test_contract = FeatureContract::TestContract(CairoVersion::Cairo0);
+    let account_address = account_contract.get_instance_address(0);
+    let execute = |count_fee_transfer_resources: bool| {
+        let mut block_context = BlockContext::create_for_account_testing();
+        block_context.set_count_fee_transfer_resources(count_fee_transfer_resources);
+        let state = &mut test_state(
+            &block_context.chain_info,
+            BALANCE,
+            &[(account_contract, 1), (test_contract, 1)],
+        );
+        let tx = account_invoke_tx(invoke_tx_args! {
+            max_fee: Fee(MAX_FEE),
+            sender_address: account_address,
+            calldata: create_trivial_calldata(test_contract.get_instance_address(0)),
+            version: TransactionVersion::ONE,
+        });
+        tx.execute(state, &block_context, ExecutionFlags::default()).unwrap()
+    };
+
+    let approximated_execution_info = execute(false);
+    let counted_execution_info = execute(true);
+    assert_eq!(counted_execution_info.actual_fee, approximated_execution_info.actual_fee);
+
+    let fee_transfer_resources: ExecutionResources =
+        Prices::FeeTransfer(account_address, FeeType::Eth).into();
+    assert_eq!(
+        counted_execution_info.fee_transfer_call_info.unwrap().resources,
+        fee_transfer_resources
+    );
+    let block_context = BlockContext::create_for_account_testing();
+    let fee_transfer_approximation = block_context.versioned_constants.fee_transfer_approximation();
+    let approximated_resources = &approximated_execution_info.actual_resources.0;
+    let counted_resources = &counted_execution_info.actual_resources.0;
+    assert_eq!(
+        counted_resources[&Resource::Steps],
+        approximated_resources[&Resource::Steps]
+            - (fee_transfer_approximation.n_steps + fee_transfer_approximation.n_memory_holes)
+            + fee_transfer_resources.n_steps
+            + fee_transfer_resources.n_memory_holes
+    );
+    for builtin_name in [HASH_BUILTIN_NAME, RANGE_CHECK_BUILTIN_NAME] {
+        let resource: Resource = builtin_name.parse().unwrap();
+        assert_eq!(
+            counted_resources[&resource],
+            approximated_resources[&resource]
+                - fee_transfer_approximation.builtin_instance_counter[builtin_name]
+                + fee_transfer_resources.builtin_instance_counter[builtin_name]
+        );
+    }
+}
+
+/// Transactions executed without validation are charged the validation approximation of the OS
+/// resources only if the block context says so.
+#[rstest]
+fn test_charge_skipped_validation() {
+    let account_contract = FeatureContract::AccountWithoutValidations(CairoVersion::Cairo0);
+    let test_contract = FeatureContract::TestContract(CairoVersion::Cairo0);
+    let execute = |charge_skipped_validation: bool| {
+        let mut block_context = BlockContext::create_for_account_testing();
+        block_context.set_charge_skipped_validation(charge_skipped_validation);
+        let state = &mut test_state(
+            &block_context.chain_info,
+            BALANCE,
+            &[(account_contract, 1), (test_contract, 1)],
+        );
+        let tx = account_invoke_tx(invoke_tx_args! {
+            max_fee: Fee(MAX_FEE),
+            sender_address: account_contract.get_instance_address(0),
+            calldata: create_trivial_calldata(test_contract.get_instance_address(0)),
+            version: TransactionVersion::ONE,
+        });
+        let execution_flags = ExecutionFlags { validate: false, ..Default::default() };
+        let execution_info = tx.execute(state, &block_context, execution_flags).unwrap();
+        assert!(execution_info.validate_call_info.is_none());
+        execution_info
+    };
+
+    let uncharged_execution_info = execute(false);
+    let charged_execution_info = execute(true);
+    assert!(charged_execution_info.actual_fee > uncharged_execution_info.actual_fee);
+
+    let block_context = BlockContext::create_for_account_testing();
+    let validate_approximation = block_context.versioned_constants.validate_approximation();
+    let uncharged_resources = &uncharged_execution_info.actual_resources.0;
+    let charged_resources = &charged_execution_info.actual_resources.0;
+    assert_eq!(
+        charged_resources[&Resource::Steps],
+        uncharged_resources[&Resource::Steps]
+            + validate_approximation.n_steps
+            + validate_approximation.n_memory_holes
+    );
+    for (builtin_name, n_instances) in &validate_approximation.builtin_instance_counter {
+        let resource: Resource = builtin_name.parse().unwrap();
+        assert_eq!(
+            charged_resources[&resource],
+            uncharged_resources.get(&resource).copied().unwrap_or_default() + n_instances
+        );
+    }
+}
+
+/// V3 transactions commit to `max_amount * max_price_per_unit` of their L1 gas bounds, regardless
+/// of their max fee; L2 gas is not charged, so its bounds are not required.
+#[rstest]
+fn test_l1_resource_bounds_pre_validation_boundaries() {
+    let block_context = &BlockContext::create_for_account_testing();
+    let account_contract = FeatureContract::AccountWithoutValidations(CairoVersion::Cairo0);
+    let test_contract = FeatureContract::TestContract(CairoVersion::Cairo0);
+    let state = &mut test_state(
+        &block_context.chain_info,
+        BALANCE,
+        &[(account_contract, 1), (test_contract, 1)],
+    );
+    let invoke_args = invoke_tx_args! {
+        sender_address: account_contract.get_instance_address(0),
+        calldata: create_trivial_calldata(test_contract.get_instance_address(0)),
+        version: TransactionVersion::THREE,
+    };
+    let minimal_l1_gas = estimate_minimal_gas_vector(
+        block_context,
+        &account_invoke_tx(invoke_tx_args! { max_fee: Fee(MAX_FEE), ..invoke_args.clone() }),
+    )
+    .unwrap()
+    .l1_gas;
+    let minimal_l1_gas = u64::try_from(minimal_l1_gas).expect("Failed to convert u128 to u64.");
+    let actual_l1_gas_price = u128::from(block_context.block_info.gas_prices.strk_l1_gas_price);
+
+    let mut pre_validate = |resource_bounds: ResourceBoundsMapping, max_fee: Fee| {
+        let tx_args = invoke_tx_args! { resource_bounds, max_fee, ..invoke_args.clone() };
+        let tx = account_invoke_tx(tx_args);
+        let tx_context = block_context.to_tx_context(&tx);
+        // Discard the nonce increment.
+        let mut transactional_state = CachedState::create_transactional(state);
+        tx.perform_pre_validation_stage(&mut transactional_state, &tx_context, true, false)
+    };
+
+    // The minimal amount at the actual price passes, whatever the max fee.
+    let minimal_bounds = l1_resource_bounds(minimal_l1_gas, actual_l1_gas_price);
+    assert_matches!(pre_validate(minimal_bounds.clone(), Fee(0)), Ok(()));
+    assert_matches!(pre_validate(minimal_bounds, Fee(BALANCE + 1)), Ok(()));
+
+    // The committed fee may be up to the balance of the sender.
+    let max_l1_gas_price = BALANCE / u128::from(minimal_l1_gas);
+    assert!(max_l1_gas_price >= actual_l1_gas_price);
+    assert_matches!(
+        pre_validate(l1_resource_bounds(minimal_l1_gas, max_l1_gas_price), Fee(0)),
+        Ok(())
+    );
+    assert_matches!(
+        pre_validate(l1_resource_bounds(minimal_l1_gas, max_l1_gas_price + 1), Fee(0)),
+        Err(TransactionPreValidationError::TransactionFeeError(
+            TransactionFeeError::L1GasBoundsExceedBalance { max_amount, max_price, .. }
+        ))
+        if max_amount == minimal_l1_gas && max_price == max_l1_gas_price + 1
+    );
+
+    // Only L1 gas bounds are required.
+    let l1_gas_bounds =
+        ResourceBounds { max_amount: minimal_l1_gas, max_price_per_unit: actual_l1_gas_price };
+    let only_l1_gas_bounds = ResourceBoundsMapping(BTreeMap::from([(
+        starknet_api::transaction::Resource::L1Gas,
+        l1_gas_bounds,
+    )]));
+    assert_matches!(pre_validate(only_l1_gas_bounds, Fee(0)), Ok(()));
+    let only_l2_gas_bounds = ResourceBoundsMapping(BTreeMap::from([(
+        starknet_api::transaction::Resource::L2Gas,
+        l1_gas_bounds,
+    )]));
+    assert_matches!(
+        pre_validate(only_l2_gas_bounds, Fee(MAX_FEE)),
+        Err(TransactionPreValidationError::TransactionFeeError(
+            TransactionFeeError::MissingL1GasBounds
+        ))
+    );
+}
+
+/// The minimal gas estimate of a transaction, used by the fee pre-validation checks, bounds the
+/// gas it is charged for once executed.
+#[rstest]
+fn test_minimal_gas_vector_bounds_actual(
+    #[values(
+        TransactionType::Declare,
+        TransactionType::DeployAccount,
+        TransactionType::InvokeFunction
+    )]
+    tx_type: TransactionType,
+    #[values(CairoVersion::Cairo0, CairoVersion::Cairo1)] account_cairo_version: CairoVersion,
+    #[values(false, true)] use_kzg_da: bool,
+) {
+    let block_context = &BlockContext::create_for_account_testing_with_kzg(use_kzg_da);
+    let chain_info = &block_context.chain_info;
+    let account = FeatureContract::AccountWithoutValidations(account_cairo_version);
+    let test_contract = FeatureContract::TestContract(CairoVersion::Cairo0);
+    let state = &mut test_state(chain_info, BALANCE, &[(account, 1), (test_contract, 1)]);
+    let sender_address = account.get_instance_address(0);
+
+    let account_tx = match tx_type {
+        TransactionType::Declare => {
+            let empty_contract = FeatureContract::Empty(CairoVersion::Cairo1);
+            declare_tx(
+                declare_tx_args! {
+                    max_fee: Fee(MAX_FEE),
+                    sender_address,
+                    version: TransactionVersion::TWO,
+                    class_hash: empty_contract.get_class_hash(),
+                },
+                calculate_class_info_for_testing(empty_contract.get_class()),
+            )
+        }
+        TransactionType::DeployAccount => {
+            let deploy_account = deploy_account_tx(
+                deploy_account_tx_args! {
+                    max_fee: Fee(MAX_FEE),
+                    class_hash: account.get_class_hash(),
+                },
+                &mut NonceManager::default(),
+            );
+            // Fund the address of the account to be deployed.
+            state
+                .set_storage_at(
+                    chain_info.fee_token_address(&FeeType::Eth),
+                    get_fee_token_var_address(deploy_account.contract_address),
+                    stark_felt!(BALANCE),
+                )
+                .unwrap();
+            AccountTransaction::DeployAccount(deploy_account)
+        }
+        TransactionType::InvokeFunction => account_invoke_tx(invoke_tx_args! {
+            max_fee: Fee(MAX_FEE),
+            sender_address,
+            calldata: create_trivial_calldata(test_contract.get_instance_address(0)),
+            version: TransactionVersion::ONE,
+        }),
+        _ => unreachable!("Not an account transaction type: {tx_type:?}."),
+    };
+
+    let minimal_gas_vector = estimate_minimal_gas_vector(block_context, &account_tx).unwrap();
+    let execution_info =
+        account_tx.execute(state, block_context, ExecutionFlags::default()).unwrap();
+    assert!(!execution_info.is_reverted());
+    let actual_gas_vector =
+        execution_info.actual_gas_vector(&block_context.versioned_constants).unwrap();
+    assert!(minimal_gas_vector.l1_gas <= actual_gas_vector.l1_gas);
+    assert!(minimal_gas_vector.l1_data_gas <= actual_gas_vector.l1_data_gas);
+}
+
 // TODO(Aner, 21/01/24) modify for 4844 (taking blob_gas into account).
 #[test_case(CairoVersion::Cairo0; "With Cairo0 account")]
 #[test_case(CairoVersion::Cairo1; "With Cairo1 account")]
@@ -869,7 +1232,8 @@ fn test_insufficient_resource_bounds(account_cairo_version: CairoVersion) {
     let invalid_v1_tx = account_invoke_tx(
         invoke_tx_args! { max_fee: invalid_max_fee, ..valid_invoke_tx_args.clone() },
     );
-    let execution_error = invalid_v1_tx.execute(state, block_context, true, true).unwrap_err();
+    let execution_error =
+        invalid_v1_tx.execute(state, block_context, ExecutionFlags::default()).unwrap_err();
 
     // Test error.
     assert_matches!(
@@ -892,7 +1256,8 @@ fn test_insufficient_resource_bounds(account_cairo_version: CairoVersion) {
         version: TransactionVersion::THREE,
         ..valid_invoke_tx_args.clone()
     });
-    let execution_error = invalid_v3_tx.execute(state, block_context, true, true).unwrap_err();
+    let execution_error =
+        invalid_v3_tx.execute(state, block_context, ExecutionFlags::default()).unwrap_err();
     // TODO(Ori, 1/2/2024): Write an indicative expect message explaining why the conversion works.
     let minimal_l1_gas_as_u64 =
         u64::try_from(minimal_l1_gas).expect("Failed to convert u128 to u64.");
@@ -915,7 +1280,8 @@ fn test_insufficient_resource_bounds(account_cairo_version: CairoVersion) {
         version: TransactionVersion::THREE,
         ..valid_invoke_tx_args
     });
-    let execution_error = invalid_v3_tx.execute(state, block_context, true, true).unwrap_err();
+    let execution_error =
+        invalid_v3_tx.execute(state, block_context, ExecutionFlags::default()).unwrap_err();
     assert_matches!(
         execution_error,
         TransactionExecutionError::TransactionPreValidationError(
@@ -950,7 +1316,8 @@ fn test_actual_fee_gt_resource_bounds(account_cairo_version: CairoVersion) {
     // The estimated minimal fee is lower than the actual fee.
     let invalid_tx = account_invoke_tx(invoke_tx_args! { max_fee: minimal_fee, ..invoke_tx_args });
 
-    let execution_result = invalid_tx.execute(state, block_context, true, true).unwrap();
+    let execution_result =
+        invalid_tx.execute(state, block_context, ExecutionFlags::default()).unwrap();
     let execution_error = execution_result.revert_error.unwrap();
     // Test error.
     assert!(execution_error.starts_with("Insufficient max fee:"));
@@ -1115,6 +1482,7 @@ fn test_declare_tx(
     let chain_info = &block_context.chain_info;
     let state = &mut test_state(chain_info, BALANCE, &[(account, 1)]);
     let class_hash = empty_contract.get_class_hash();
+    let compiled_class_hash = CompiledClassHash(stark_felt!("0x7"));
     let class_info = calculate_class_info_for_testing(empty_contract.get_class());
     let sender_address = account.get_instance_address(0);
 
@@ -1125,6 +1493,7 @@ fn test_declare_tx(
             version: tx_version,
             resource_bounds: l1_resource_bounds(MAX_L1_GAS_AMOUNT, MAX_L1_GAS_PRICE),
             class_hash,
+            compiled_class_hash,
         },
         class_info.clone(),
     );
@@ -1137,7 +1506,8 @@ fn test_declare_tx(
     );
     let fee_type = &account_tx.fee_type();
     let tx_context = &block_context.to_tx_context(&account_tx);
-    let actual_execution_info = account_tx.execute(state, block_context, true, true).unwrap();
+    let actual_execution_info =
+        account_tx.execute(state, block_context, ExecutionFlags::default()).unwrap();
 
     // Build expected validate call info.
     let expected_validate_call_info = declare_validate_callinfo(
@@ -1161,7 +1531,7 @@ fn test_declare_tx(
     let state_changes_count = declare_expected_state_changes_count(tx_version);
     let da_gas = get_da_gas_cost(state_changes_count, use_kzg_da);
     let code_gas = get_code_gas_cost(Some(class_info.clone()), versioned_constants);
-    let gas_usage = code_gas + da_gas;
+    let gas_usage = code_gas.checked_add(da_gas).unwrap();
 
     let mut expected_execution_info = TransactionExecutionInfo {
         validate_call_info: expected_validate_call_info,
@@ -1169,20 +1539,19 @@ fn test_declare_tx(
         fee_transfer_call_info: expected_fee_transfer_call_info,
         actual_fee: expected_actual_fee,
         da_gas,
+        n_reverted_steps: 0,
         revert_error: None,
         actual_resources: ResourcesMapping(HashMap::from([
-            (abi_constants::L1_GAS_USAGE.to_string(), gas_usage.l1_gas.try_into().unwrap()),
-            (abi_constants::BLOB_GAS_USAGE.to_string(), gas_usage.l1_data_gas.try_into().unwrap()),
-            (HASH_BUILTIN_NAME.to_string(), 16),
+            (Resource::L1Gas, gas_usage.l1_gas.try_into().unwrap()),
+            (Resource::L1DataGas, gas_usage.l1_data_gas.try_into().unwrap()),
+            (Resource::PedersenBuiltin, 16),
             (
-                RANGE_CHECK_BUILTIN_NAME.to_string(),
+                Resource::RangeCheckBuiltin,
                 declare_expected_range_check_builtin(tx_version, account_cairo_version),
             ),
-            (
-                abi_constants::N_STEPS_RESOURCE.to_string(),
-                declare_n_steps(tx_version, account_cairo_version),
-            ),
+            (Resource::Steps, declare_n_steps(tx_version, account_cairo_version)),
         ])),
+        ..Default::default()
     };
 
     add_kzg_da_resources(
@@ -1215,6 +1584,19 @@ fn test_declare_tx(
     // Verify class declaration.
     let contract_class_from_state = state.get_compiled_contract_class(class_hash).unwrap();
     assert_eq!(contract_class_from_state, class_info.contract_class());
+    // The state only knows the compiled part of the class size charged for.
+    assert_eq!(
+        state.declared_class_sizes().unwrap(),
+        HashMap::from([(class_hash, class_info.bytecode_length())])
+    );
+
+    // Only V2 and above commit to a compiled class hash.
+    let expected_compiled_class_hash = if tx_version >= TransactionVersion::TWO {
+        compiled_class_hash
+    } else {
+        CompiledClassHash::default()
+    };
+    assert_eq!(state.get_compiled_class_hash(class_hash).unwrap(), expected_compiled_class_hash);
 }
 
 #[rstest]
@@ -1261,7 +1643,8 @@ fn test_deploy_account_tx(
     let account_tx = AccountTransaction::DeployAccount(deploy_account);
     let fee_type = &account_tx.fee_type();
     let tx_context = &block_context.to_tx_context(&account_tx);
-    let actual_execution_info = account_tx.execute(state, block_context, true, true).unwrap();
+    let actual_execution_info =
+        account_tx.execute(state, block_context, ExecutionFlags::default()).unwrap();
 
     // Build expected validate call info.
     let validate_calldata =
@@ -1314,17 +1697,16 @@ fn test_deploy_account_tx(
         fee_transfer_call_info: expected_fee_transfer_call_info,
         actual_fee: expected_actual_fee,
         da_gas,
+        n_reverted_steps: 0,
         revert_error: None,
         actual_resources: ResourcesMapping(HashMap::from([
-            (abi_constants::L1_GAS_USAGE.to_string(), usize_from_u128(da_gas.l1_gas).unwrap()),
-            (
-                abi_constants::BLOB_GAS_USAGE.to_string(),
-                usize_from_u128(da_gas.l1_data_gas).unwrap(),
-            ),
-            (HASH_BUILTIN_NAME.to_string(), 23),
-            (RANGE_CHECK_BUILTIN_NAME.to_string(), expected_range_check_builtin),
-            (abi_constants::N_STEPS_RESOURCE.to_string(), expected_n_steps_resource),
+            (Resource::L1Gas, usize_from_u128(da_gas.l1_gas).unwrap()),
+            (Resource::L1DataGas, usize_from_u128(da_gas.l1_data_gas).unwrap()),
+            (Resource::PedersenBuiltin, 23),
+            (Resource::RangeCheckBuiltin, expected_range_check_builtin),
+            (Resource::Steps, expected_n_steps_resource),
         ])),
+        ..Default::default()
     };
 
     add_kzg_da_resources(
@@ -1363,7 +1745,7 @@ fn test_deploy_account_tx(
         &mut nonce_manager,
     );
     let account_tx = AccountTransaction::DeployAccount(deploy_account);
-    let error = account_tx.execute(state, block_context, true, true).unwrap_err();
+    let error = account_tx.execute(state, block_context, ExecutionFlags::default()).unwrap_err();
     assert_matches!(
         error,
         TransactionExecutionError::ContractConstructorExecutionFailed(
@@ -1394,7 +1776,7 @@ fn test_fail_deploy_account_undeclared_class_hash() {
         .unwrap();
 
     let account_tx = AccountTransaction::DeployAccount(deploy_account);
-    let error = account_tx.execute(state, block_context, true, true).unwrap_err();
+    let error = account_tx.execute(state, block_context, ExecutionFlags::default()).unwrap_err();
     assert_matches!(
         error,
         TransactionExecutionError::ContractConstructorExecutionFailed(
@@ -1404,6 +1786,17 @@ fn test_fail_deploy_account_undeclared_class_hash() {
     );
 }
 
+/// The size of a Cairo 1 syscall in the syscall segment: the selector and the gas counter, the
+/// request, the remaining gas and the failure flag, and the (successful) response.
+const fn syscall_size(request_size: usize, response_size: usize) -> usize {
+    2 + request_size + 2 + response_size
+}
+
+// The recipient and the payload (start and end pointers); no response.
+const SEND_MESSAGE_TO_L1_SYSCALL_SIZE: usize = syscall_size(3, 0);
+// No request; the execution info pointer.
+const GET_EXECUTION_INFO_SYSCALL_SIZE: usize = syscall_size(0, 1);
+
 // TODO(Arni, 1/1/2024): Consider converting this test to use V3 txs.
 #[rstest]
 #[case::validate(TransactionType::InvokeFunction, false)]
@@ -1443,7 +1836,7 @@ fn test_validate_accounts_tx(
             ..default_args
         },
     );
-    let error = account_tx.execute(state, block_context, true, true).unwrap_err();
+    let error = account_tx.execute(state, block_context, ExecutionFlags::default()).unwrap_err();
     check_transaction_execution_error_for_invalid_scenario!(
         cairo_version,
         error,
@@ -1462,7 +1855,7 @@ fn test_validate_accounts_tx(
             ..default_args
         },
     );
-    let error = account_tx.execute(state, block_context, true, true).unwrap_err();
+    let error = account_tx.execute(state, block_context, ExecutionFlags::default()).unwrap_err();
     check_transaction_execution_error_for_custom_hint!(
         &error,
         "Unauthorized syscall call_contract in execution mode Validate.",
@@ -1480,10 +1873,22 @@ fn test_validate_accounts_tx(
                 ..default_args
             },
         );
-        let error = account_tx.execute(state, block_context, true, true).unwrap_err();
+        let error =
+            account_tx.execute(state, block_context, ExecutionFlags::default()).unwrap_err();
+        // The offset depends on the syscalls the entry point invokes before get_block_hash: all
+        // read the execution info, and `__validate__` sends a message beforehand.
+        let syscall_ptr_offset = match tx_type {
+            TransactionType::InvokeFunction => {
+                SEND_MESSAGE_TO_L1_SYSCALL_SIZE + GET_EXECUTION_INFO_SYSCALL_SIZE
+            }
+            _ => GET_EXECUTION_INFO_SYSCALL_SIZE,
+        };
         check_transaction_execution_error_for_custom_hint!(
             &error,
-            "Unauthorized syscall get_block_hash in execution mode Validate.",
+            &format!(
+                "GetBlockHash syscall at offset {syscall_ptr_offset} of the syscall segment \
+                 failed: Unauthorized syscall get_block_hash in execution mode Validate."
+            ),
             validate_constructor,
         );
     }
@@ -1497,7 +1902,8 @@ fn test_validate_accounts_tx(
                 ..default_args
             },
         );
-        let error = account_tx.execute(state, block_context, true, true).unwrap_err();
+        let error =
+            account_tx.execute(state, block_context, ExecutionFlags::default()).unwrap_err();
         check_transaction_execution_error_for_custom_hint!(
             &error,
             "Unauthorized syscall get_sequencer_address in execution mode Validate.",
@@ -1518,7 +1924,7 @@ fn test_validate_accounts_tx(
             ..default_args
         },
     );
-    assert!(account_tx.execute(state, block_context, true, true).err().is_none());
+    assert!(account_tx.execute(state, block_context, ExecutionFlags::default()).err().is_none());
 
     if tx_type != TransactionType::DeployAccount {
         // Call self (allowed).
@@ -1530,7 +1936,9 @@ fn test_validate_accounts_tx(
                 ..default_args
             },
         );
-        assert!(account_tx.execute(state, block_context, true, true).err().is_none());
+        assert!(
+            account_tx.execute(state, block_context, ExecutionFlags::default()).err().is_none()
+        );
     }
 
     if let CairoVersion::Cairo0 = cairo_version {
@@ -1545,7 +1953,9 @@ fn test_validate_accounts_tx(
                 ..default_args
             },
         );
-        assert!(account_tx.execute(state, block_context, true, true).err().is_none());
+        assert!(
+            account_tx.execute(state, block_context, ExecutionFlags::default()).err().is_none()
+        );
         // Call the syscall get_block_timestamp and assert the returned timestamp was modified
         // for validate.
         let account_tx = create_account_tx_for_validate_test(
@@ -1557,7 +1967,9 @@ fn test_validate_accounts_tx(
                 ..default_args
             },
         );
-        assert!(account_tx.execute(state, block_context, true, true).err().is_none());
+        assert!(
+            account_tx.execute(state, block_context, ExecutionFlags::default()).err().is_none()
+        );
     }
 
     if let CairoVersion::Cairo1 = cairo_version {
@@ -1576,7 +1988,9 @@ fn test_validate_accounts_tx(
                 ..default_args
             },
         );
-        assert!(account_tx.execute(state, block_context, true, true).err().is_none());
+        assert!(
+            account_tx.execute(state, block_context, ExecutionFlags::default()).err().is_none()
+        );
     }
 }
 
@@ -1600,7 +2014,9 @@ fn test_valid_flag(
         max_fee: Fee(MAX_FEE)
     });
 
-    let actual_execution_info = account_tx.execute(state, block_context, true, false).unwrap();
+    let actual_execution_info = account_tx
+        .execute(state, block_context, ExecutionFlags { validate: false, ..Default::default() })
+        .unwrap();
 
     assert!(actual_execution_info.validate_call_info.is_none());
 }
@@ -1677,7 +2093,8 @@ fn test_only_query_flag(#[case] only_query: bool) {
     );
     let account_tx = AccountTransaction::Invoke(invoke_tx);
 
-    let tx_execution_info = account_tx.execute(state, block_context, true, true).unwrap();
+    let tx_execution_info =
+        account_tx.execute(state, block_context, ExecutionFlags::default()).unwrap();
     assert!(!tx_execution_info.is_reverted())
 }
 
@@ -1707,7 +2124,8 @@ fn test_l1_handler(#[values(false, true)] use_kzg_da: bool) {
     let tx = l1_handler_tx(&calldata, Fee(1));
     let payload_size = tx.payload_size();
 
-    let actual_execution_info = tx.execute(state, block_context, true, true).unwrap();
+    let actual_execution_info =
+        tx.execute(state, block_context, ExecutionFlags::default()).unwrap();
 
     // Build the expected call info.
     let accessed_storage_key = StorageKey::try_from(key).unwrap();
@@ -1754,14 +2172,11 @@ fn test_l1_handler(#[values(false, true)] use_kzg_da: bool) {
     };
 
     let mut expected_resource_mapping = ResourcesMapping(HashMap::from([
-        (HASH_BUILTIN_NAME.to_string(), 11 + payload_size),
-        (abi_constants::N_STEPS_RESOURCE.to_string(), 1405),
-        (RANGE_CHECK_BUILTIN_NAME.to_string(), 23),
-        (abi_constants::L1_GAS_USAGE.to_string(), usize_from_u128(expected_gas.l1_gas).unwrap()),
-        (
-            abi_constants::BLOB_GAS_USAGE.to_string(),
-            usize_from_u128(expected_gas.l1_data_gas).unwrap(),
-        ),
+        (Resource::PedersenBuiltin, 11 + payload_size),
+        (Resource::Steps, 1405),
+        (Resource::RangeCheckBuiltin, 23),
+        (Resource::L1Gas, usize_from_u128(expected_gas.l1_gas).unwrap()),
+        (Resource::L1DataGas, usize_from_u128(expected_gas.l1_data_gas).unwrap()),
     ]));
 
     add_kzg_da_resources(
@@ -1779,7 +2194,9 @@ fn test_l1_handler(#[values(false, true)] use_kzg_da: bool) {
         actual_fee: Fee(0),
         da_gas: expected_da_gas,
         actual_resources: expected_resource_mapping,
+        n_reverted_steps: 0,
         revert_error: None,
+        ..Default::default()
     };
 
     // Check the actual returned execution info.
@@ -1798,7 +2215,7 @@ fn test_l1_handler(#[values(false, true)] use_kzg_da: bool) {
 
     // Negative flow: not enough fee paid on L1.
     let tx_no_fee = l1_handler_tx(&calldata, Fee(0));
-    let error = tx_no_fee.execute(state, block_context, true, true).unwrap_err();
+    let error = tx_no_fee.execute(state, block_context, ExecutionFlags::default()).unwrap_err();
     // Today, we check that the paid_fee is positive, no matter what was the actual fee.
     let expected_actual_fee =
         if use_kzg_da { Fee(1744900000000000) } else { Fee(1742800000000000) };
@@ -1810,6 +2227,57 @@ fn test_l1_handler(#[values(false, true)] use_kzg_da: bool) {
     );
 }
 
+#[rstest]
+fn test_l1_handler_enforced_fee(#[values(false, true)] use_kzg_da: bool) {
+    let state = &mut create_test_state();
+    let mut block_context = BlockContext::create_for_account_testing_with_kzg(use_kzg_da);
+    block_context.set_enforce_l1_handler_fee(true);
+    let calldata = calldata![
+        StarkFelt::from_u128(0x123), // From address.
+        StarkFelt::from_u128(0x876), // Key.
+        StarkFelt::from_u128(0x44)   // Value.
+    ];
+
+    // Once enforced, a positive paid fee is not enough; it must cover the actual fee.
+    let error = l1_handler_tx(&calldata, Fee(1))
+        .execute(state, &block_context, ExecutionFlags::default())
+        .unwrap_err();
+    let TransactionExecutionError::TransactionFeeError(TransactionFeeError::InsufficientL1Fee {
+        paid_fee,
+        actual_fee,
+    }) = error
+    else {
+        panic!("Unexpected error: {error:?}.");
+    };
+    assert_eq!(paid_fee, Fee(1));
+
+    let execution_info = l1_handler_tx(&calldata, actual_fee)
+        .execute(state, &block_context, ExecutionFlags::default())
+        .unwrap();
+    assert!(!execution_info.is_reverted());
+    // The paid fee is not charged on L2.
+    assert_eq!(execution_info.actual_fee, Fee(0));
+}
+
+#[test]
+fn test_l1_handler_missing_entry_point() {
+    let block_context = &BlockContext::create_for_account_testing();
+    let empty_contract = FeatureContract::Empty(CairoVersion::Cairo1);
+    let state = &mut test_state(&block_context.chain_info, BALANCE, &[(empty_contract, 1)]);
+    let mut tx = l1_handler_tx(&calldata![StarkFelt::from_u128(0x123)], Fee(1));
+    tx.tx.contract_address = empty_contract.get_instance_address(0);
+    let selector = tx.tx.entry_point_selector;
+
+    let error = tx.execute(state, block_context, ExecutionFlags::default()).unwrap_err();
+    assert_matches!(
+        error,
+        TransactionExecutionError::ExecutionError(EntryPointExecutionError::PreExecutionError(
+            PreExecutionError::EntryPointNotFound(not_found_selector)
+        ))
+        if not_found_selector == selector
+    );
+}
+
 #[test]
 fn test_execute_tx_with_invalid_transaction_version() {
     let cairo_version = CairoVersion::Cairo0;
@@ -1830,7 +2298,8 @@ fn test_execute_tx_with_invalid_transaction_version() {
         calldata,
     });
 
-    let execution_info = account_tx.execute(state, block_context, true, true).unwrap();
+    let execution_info =
+        account_tx.execute(state, block_context, ExecutionFlags::default()).unwrap();
     assert!(
         execution_info
             .revert_error
@@ -1942,7 +2411,8 @@ fn test_emit_event_exceeds_limit(
             version: TransactionVersion::ONE,
             nonce: Nonce(stark_felt!(0_u8)),
         });
-        let execution_info = account_tx.execute(state, block_context, true, true).unwrap();
+        let execution_info =
+            account_tx.execute(state, block_context, ExecutionFlags::default()).unwrap();
         match &expected_error {
             Some(expected_error) => {
                 let error_string = execution_info.revert_error.unwrap();
@@ -1954,3 +2424,97 @@ fn test_emit_event_exceeds_limit(
         }
     }
 }
+
+/// Events emitted by inner calls (here, by the contract called from `__execute__`) are charged
+/// only if the versioned constants say so.
+#[test]
+fn test_inner_call_events_gas() {
+    let account_contract = FeatureContract::AccountWithoutValidations(CairoVersion::Cairo1);
+    let test_contract = FeatureContract::TestContract(CairoVersion::Cairo1);
+    // 3 events, with 2 keys and 4 data felts each.
+    let emit_events_args = [
+        vec![stark_felt!(3_u8), stark_felt!(2_u8)],
+        vec![stark_felt!(1_u8); 2],
+        vec![stark_felt!(4_u8)],
+        vec![stark_felt!(2_u8); 4],
+    ]
+    .concat();
+    let execute = |charge_inner_call_events: bool| {
+        let mut block_context = BlockContext::create_for_account_testing();
+        block_context.versioned_constants.l2_resource_gas_costs.charge_inner_call_events =
+            charge_inner_call_events;
+        let state = &mut test_state(
+            &block_context.chain_info,
+            BALANCE,
+            &[(account_contract, 1), (test_contract, 1)],
+        );
+        let account_tx = account_invoke_tx(invoke_tx_args! {
+            sender_address: account_contract.get_instance_address(0),
+            calldata: create_calldata(
+                test_contract.get_instance_address(0),
+                "test_emit_events",
+                &emit_events_args,
+            ),
+            max_fee: Fee(MAX_FEE),
+        });
+        let execution_info =
+            account_tx.execute(state, &block_context, ExecutionFlags::default()).unwrap();
+        assert!(!execution_info.is_reverted());
+        // Including the transfer event of the fee transfer.
+        assert_eq!(
+            execution_info.summarize_events(),
+            EventSummary { n_events: 4, total_event_keys: 7, total_event_data_size: 16 }
+        );
+        execution_info
+    };
+
+    let uncharged_execution_info = execute(false);
+    let charged_execution_info = execute(true);
+
+    // Each event costs 2 * 128 milligas per key and 128 milligas per data felt.
+    let l2_resource_gas_costs =
+        &VersionedConstants::create_for_account_testing().l2_resource_gas_costs;
+    assert_eq!(
+        (l2_resource_gas_costs.event_key_factor, l2_resource_gas_costs.milligas_per_data_felt),
+        (2, 128)
+    );
+    let events_gas = 3 * (2 * 2 * 128 + 4 * 128) / 1000;
+    let l1_gas = |execution_info: &TransactionExecutionInfo| {
+        execution_info.actual_resources.0[&Resource::L1Gas]
+    };
+    assert_eq!(l1_gas(&charged_execution_info), l1_gas(&uncharged_execution_info) + events_gas);
+    assert!(charged_execution_info.actual_fee > uncharged_execution_info.actual_fee);
+}
+
+/// Memory holes are charged at the Cairo steps weight: they are folded into the steps count of
+/// the transaction resources rather than listed as a separate resource.
+#[rstest]
+fn test_memory_holes_charged_as_steps(#[values(0, 1, 17)] n_memory_holes: usize) {
+    let versioned_constants = VersionedConstants::create_for_testing();
+    let tx_resources = |n_memory_holes| {
+        let execution_resources = ExecutionResources {
+            n_steps: 100,
+            n_memory_holes,
+            builtin_instance_counter: HashMap::from([(RANGE_CHECK_BUILTIN_NAME.to_string(), 2)]),
+        };
+        calculate_tx_resources(
+            &versioned_constants,
+            &execution_resources,
+            GasVector::default(),
+            TransactionType::InvokeFunction,
+            TxInputSizes::default(),
+            StateChangesCount::default(),
+            false,
+        )
+        .unwrap()
+    };
+
+    let without_holes = tx_resources(0);
+    let with_holes = tx_resources(n_memory_holes);
+    assert!(!with_holes.0.contains_key(&Resource::MemoryHoles));
+    assert_eq!(with_holes.0[&Resource::Steps], without_holes.0[&Resource::Steps] + n_memory_holes);
+    assert_eq!(
+        with_holes.0[&Resource::RangeCheckBuiltin],
+        without_holes.0[&Resource::RangeCheckBuiltin]
+    );
+}
@@ -17,6 +17,16 @@ where
     lhs.iter().filter(|(k, v)| rhs.get(k) != Some(v)).map(|(k, v)| (k.clone(), v.clone())).collect()
 }
 
+/// Same as [`subtract_mappings`], but consumes `lhs` rather than cloning its items.
+pub fn subtract_mappings_owned<K, V>(mut lhs: HashMap<K, V>, rhs: &HashMap<K, V>) -> HashMap<K, V>
+where
+    K: Eq + std::hash::Hash,
+    V: PartialEq,
+{
+    lhs.retain(|k, v| rhs.get(k) != Some(v));
+    lhs
+}
+
 /// Returns the max value of two constants, at compile time.
 pub const fn const_max(a: u128, b: u128) -> u128 {
     [a, b][(a < b) as usize]
@@ -2,7 +2,7 @@ use std::collections::HashMap;
 
 use pretty_assertions::assert_eq;
 
-use crate::utils::subtract_mappings;
+use crate::utils::{subtract_mappings, subtract_mappings_owned};
 
 #[test]
 fn test_subtract_mappings() {
@@ -17,4 +17,5 @@ fn test_subtract_mappings() {
 
     let expected = HashMap::from([("red", 1), ("blue", 3)]);
     assert_eq!(expected, subtract_mappings(&map1, &map2));
+    assert_eq!(expected, subtract_mappings_owned(map1, &map2));
 }
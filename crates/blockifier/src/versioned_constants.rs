@@ -1,13 +1,13 @@
-use std::collections::{HashMap, HashSet};
-use std::io;
+use std::collections::{BTreeSet, HashMap, HashSet};
 use std::path::Path;
 use std::sync::Arc;
+use std::{fmt, io};
 
 use cairo_vm::vm::runners::builtin_runner;
 use cairo_vm::vm::runners::cairo_runner::ExecutionResources;
 use indexmap::{IndexMap, IndexSet};
 use once_cell::sync::Lazy;
-use serde::de::Error as DeserializationError;
+use serde::de::{Error as DeserializationError, MapAccess, Visitor};
 use serde::{Deserialize, Deserializer, Serialize};
 use serde_json::{Number, Value};
 use strum::IntoEnumIterator;
@@ -17,7 +17,8 @@ use crate::execution::deprecated_syscalls::hint_processor::SyscallCounter;
 use crate::execution::deprecated_syscalls::DeprecatedSyscallSelector;
 use crate::execution::errors::PostExecutionError;
 use crate::execution::execution_utils::poseidon_hash_many_cost;
-use crate::transaction::errors::TransactionExecutionError;
+use crate::execution::syscalls::hint_processor::is_builtin_syscall;
+use crate::transaction::objects::Resource;
 use crate::transaction::transaction_types::TransactionType;
 
 #[cfg(test)]
@@ -34,6 +35,9 @@ static DEFAULT_CONSTANTS: Lazy<VersionedConstants> = Lazy::new(|| {
 /// Additional constants in the JSON file, not used by Blockifier but included for transparency, are
 /// automatically ignored during deserialization.
 #[derive(Clone, Debug, Default, Deserialize)]
+// Serde trick for deriving the syscall gas costs after deserialization, without forgoing the
+// derive. See: https://github.com/serde-rs/serde/issues/1220.
+#[serde(remote = "Self")]
 pub struct VersionedConstants {
     // Limits.
     pub event_size_limit: EventSizeLimit,
@@ -45,6 +49,17 @@ pub struct VersionedConstants {
     pub validate_max_n_steps: u32,
     // Flooring factor for timestamp in validate mode.
     pub validate_timestamp_rounding: u64,
+    // Absent in the constants of versions that predate the OS-aligned layout.
+    #[serde(default)]
+    pub syscall_response_layout: SyscallResponseLayout,
+    // The gas charged per unit of the input size of gas-metered syscalls, on top of their base
+    // cost; zero for absent syscalls.
+    #[serde(default)]
+    syscall_gas_linear_factors: HashMap<DeprecatedSyscallSelector, u64>,
+    // Derived from the syscall gas costs of the OS constants and the linear factors above.
+    #[serde(skip)]
+    pub syscall_gas_costs: SyscallGasCosts,
+    pub syscall_limits: SyscallLimits,
 
     // Cairo OS constants.
     // Note: if loaded from a json file, there are some assumptions made on its structure.
@@ -57,7 +72,7 @@ pub struct VersionedConstants {
     // Fee related.
     // TODO: Consider making this a struct, this will require change the way we access these
     // values.
-    vm_resource_fee_cost: Arc<HashMap<String, f64>>,
+    vm_resource_fee_cost: Arc<HashMap<Resource, f64>>,
 }
 
 impl VersionedConstants {
@@ -73,10 +88,19 @@ impl VersionedConstants {
         os_consts.gas_costs["initial_gas_cost"] - os_consts.gas_costs["transaction_gas_cost"]
     }
 
-    pub fn vm_resource_fee_cost(&self) -> &HashMap<String, f64> {
+    pub fn vm_resource_fee_cost(&self) -> &HashMap<Resource, f64> {
         &self.vm_resource_fee_cost
     }
 
+    /// Replaces the L1 gas weights of the Cairo resources (steps and builtins), by which the fee of
+    /// the VM usage of a transaction is computed; see `calculate_l1_gas_by_vm_usage`.
+    pub fn with_vm_resource_fee_cost(
+        self,
+        vm_resource_fee_cost: Arc<HashMap<Resource, f64>>,
+    ) -> Self {
+        Self { vm_resource_fee_cost, ..self }
+    }
+
     pub fn gas_cost(&self, name: &str) -> u64 {
         match self.os_constants.gas_costs.get(name) {
             Some(&cost) => cost,
@@ -97,28 +121,42 @@ impl VersionedConstants {
         }
     }
 
+    /// The OS resources of the given transaction type, excluding those that scale with the size
+    /// of a declared class.
     pub fn os_resources_for_tx_type(
         &self,
         tx_type: &TransactionType,
         calldata_length: usize,
-    ) -> ExecutionResources {
-        self.os_resources.resources_for_tx_type(tx_type, calldata_length)
+    ) -> Result<ExecutionResources, OsResourcesError> {
+        self.os_resources.resources_for_tx_type(tx_type, calldata_length, None)
     }
 
     pub fn os_kzg_da_resources(&self, data_segment_length: usize) -> ExecutionResources {
         self.os_resources.os_kzg_da_resources(data_segment_length)
     }
 
+    /// The fee transfer resources included in the OS resources of account transactions.
+    pub fn fee_transfer_approximation(&self) -> &ExecutionResources {
+        &self.os_resources.fee_transfer_approximation
+    }
+
+    /// The resources charged for the validation of transactions executed without it, if charged.
+    pub fn validate_approximation(&self) -> &ExecutionResources {
+        &self.os_resources.validate_approximation
+    }
+
     pub fn get_additional_os_tx_resources(
         &self,
         tx_type: TransactionType,
         calldata_length: usize,
+        class_size: Option<usize>,
         data_segment_length: usize,
         use_kzg_da: bool,
-    ) -> Result<ExecutionResources, TransactionExecutionError> {
+    ) -> Result<ExecutionResources, OsResourcesError> {
         self.os_resources.get_additional_os_tx_resources(
             tx_type,
             calldata_length,
+            class_size,
             data_segment_length,
             use_kzg_da,
         )
@@ -131,23 +169,46 @@ impl VersionedConstants {
         self.os_resources.get_additional_os_syscall_resources(syscall_counter)
     }
 
+    /// Replaces the OS resources of these constants, e.g., with ones loaded through
+    /// `OsResources::from_json_file` for a network whose OS costs differ from the shipped ones.
+    pub fn with_os_resources(self, os_resources: Arc<OsResources>) -> Self {
+        Self { os_resources, ..self }
+    }
+
     #[cfg(any(feature = "testing", test))]
     pub fn create_for_account_testing() -> Self {
         let vm_resource_fee_cost = Arc::new(HashMap::from([
-            (crate::abi::constants::N_STEPS_RESOURCE.to_string(), 1_f64),
-            (cairo_vm::vm::runners::builtin_runner::HASH_BUILTIN_NAME.to_string(), 1_f64),
-            (cairo_vm::vm::runners::builtin_runner::RANGE_CHECK_BUILTIN_NAME.to_string(), 1_f64),
-            (cairo_vm::vm::runners::builtin_runner::SIGNATURE_BUILTIN_NAME.to_string(), 1_f64),
-            (cairo_vm::vm::runners::builtin_runner::BITWISE_BUILTIN_NAME.to_string(), 1_f64),
-            (cairo_vm::vm::runners::builtin_runner::POSEIDON_BUILTIN_NAME.to_string(), 1_f64),
-            (cairo_vm::vm::runners::builtin_runner::OUTPUT_BUILTIN_NAME.to_string(), 1_f64),
-            (cairo_vm::vm::runners::builtin_runner::EC_OP_BUILTIN_NAME.to_string(), 1_f64),
+            (Resource::Steps, 1_f64),
+            (Resource::PedersenBuiltin, 1_f64),
+            (Resource::RangeCheckBuiltin, 1_f64),
+            (Resource::EcdsaBuiltin, 1_f64),
+            (Resource::BitwiseBuiltin, 1_f64),
+            (Resource::PoseidonBuiltin, 1_f64),
+            (Resource::OutputBuiltin, 1_f64),
+            (Resource::EcOpBuiltin, 1_f64),
         ]));
 
         Self { vm_resource_fee_cost, ..Self::create_for_testing() }
     }
 }
 
+impl<'de> Deserialize<'de> for VersionedConstants {
+    fn deserialize<D>(deserializer: D) -> Result<Self, D::Error>
+    where
+        D: Deserializer<'de>,
+    {
+        let mut versioned_constants = Self::deserialize(deserializer)?;
+
+        versioned_constants.syscall_gas_costs = SyscallGasCosts::from_os_constants(
+            &versioned_constants.os_constants,
+            &versioned_constants.syscall_gas_linear_factors,
+        )
+        .map_err(DeserializationError::custom)?;
+
+        Ok(versioned_constants)
+    }
+}
+
 impl TryFrom<&Path> for VersionedConstants {
     type Error = VersionedConstantsError;
 
@@ -156,6 +217,161 @@ impl TryFrom<&Path> for VersionedConstants {
     }
 }
 
+/// The layout in which syscall responses are written to the syscall segment; it changed between OS
+/// versions, and replaying old blocks requires the layout of their version.
+#[derive(Clone, Copy, Debug, Default, Deserialize, Eq, PartialEq)]
+pub enum SyscallResponseLayout {
+    /// Cairo 0 deploy responses do not include the constructor retdata.
+    #[default]
+    Legacy,
+    /// Cairo 0 deploy responses include the constructor retdata, as written by the OS.
+    OsAligned,
+}
+
+/// The gas charged for a syscall in gas-metered (Cairo 1) execution, before its handler runs: a
+/// base cost (including the pre-charged `syscall_base_gas_cost`), and a cost per unit of the input
+/// size of its request (e.g., per felt of calldata).
+#[derive(Clone, Copy, Debug, Default, Eq, PartialEq)]
+pub struct SyscallGasCost {
+    pub base: u64,
+    pub linear_factor: u64,
+}
+
+impl SyscallGasCost {
+    /// Returns the gas cost of the syscall for the given input size; [None] on overflow.
+    pub fn get_syscall_cost(&self, input_size: usize) -> Option<u64> {
+        let input_size = u64::try_from(input_size).ok()?;
+        self.linear_factor.checked_mul(input_size)?.checked_add(self.base)
+    }
+}
+
+/// The gas costs of exactly the syscalls of gas-metered execution, keyed by their selector. Each
+/// base cost covers the pre-charged `syscall_base_gas_cost`.
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct SyscallGasCosts {
+    costs: HashMap<DeprecatedSyscallSelector, SyscallGasCost>,
+    syscall_base_gas_cost: u64,
+}
+
+impl SyscallGasCosts {
+    /// Derives the gas costs from the syscall gas costs of the OS constants (e.g.,
+    /// `storage_read_gas_cost`), adding the given linear factors.
+    pub fn from_os_constants(
+        os_constants: &OSConstants,
+        linear_factors: &HashMap<DeprecatedSyscallSelector, u64>,
+    ) -> Result<Self, SyscallGasCostsError> {
+        let os_gas_cost = |gas_cost_name: &'static str| {
+            os_constants
+                .gas_costs
+                .get(gas_cost_name)
+                .copied()
+                .ok_or(SyscallGasCostsError::MissingOsGasCost(gas_cost_name))
+        };
+
+        let unknown_syscalls: Vec<_> = DeprecatedSyscallSelector::iter()
+            .filter(|selector| {
+                linear_factors.contains_key(selector) && !is_builtin_syscall(selector)
+            })
+            .collect();
+        if !unknown_syscalls.is_empty() {
+            return Err(SyscallGasCostsError::UnknownSyscalls(unknown_syscalls));
+        }
+
+        let mut costs = HashMap::new();
+        for selector in DeprecatedSyscallSelector::iter() {
+            let Some(gas_cost_name) = os_gas_cost_name(&selector) else {
+                continue;
+            };
+            let cost = SyscallGasCost {
+                base: os_gas_cost(gas_cost_name)?,
+                linear_factor: linear_factors.get(&selector).copied().unwrap_or_default(),
+            };
+            costs.insert(selector, cost);
+        }
+
+        Self::new(costs, os_gas_cost("syscall_base_gas_cost")?)
+    }
+
+    fn new(
+        costs: HashMap<DeprecatedSyscallSelector, SyscallGasCost>,
+        syscall_base_gas_cost: u64,
+    ) -> Result<Self, SyscallGasCostsError> {
+        let unknown_syscalls: Vec<_> = DeprecatedSyscallSelector::iter()
+            .filter(|selector| costs.contains_key(selector) && !is_builtin_syscall(selector))
+            .collect();
+        if !unknown_syscalls.is_empty() {
+            return Err(SyscallGasCostsError::UnknownSyscalls(unknown_syscalls));
+        }
+
+        for (&selector, cost) in &costs {
+            if cost.base < syscall_base_gas_cost {
+                return Err(SyscallGasCostsError::BaseCostTooLow {
+                    selector,
+                    base: cost.base,
+                    syscall_base_gas_cost,
+                });
+            }
+        }
+
+        Ok(Self { costs, syscall_base_gas_cost })
+    }
+
+    /// Returns the gas cost of the given syscall; fails if it has none, e.g., for constants not
+    /// loaded from a file, such as the default ones.
+    pub fn get(
+        &self,
+        selector: &DeprecatedSyscallSelector,
+    ) -> Result<SyscallGasCost, SyscallGasCostsError> {
+        self.costs
+            .get(selector)
+            .copied()
+            .ok_or(SyscallGasCostsError::MissingSyscallGasCost(*selector))
+    }
+
+    /// Replaces the gas cost of the given syscall; fails if it is not gas-metered, or if its base
+    /// cost does not cover the pre-charged `syscall_base_gas_cost`.
+    pub fn with_cost(
+        self,
+        selector: DeprecatedSyscallSelector,
+        cost: SyscallGasCost,
+    ) -> Result<Self, SyscallGasCostsError> {
+        let mut costs = self.costs;
+        costs.insert(selector, cost);
+        Self::new(costs, self.syscall_base_gas_cost)
+    }
+}
+
+/// The name of the OS constant holding the gas cost of the given syscall; [None] if it is not
+/// gas-metered.
+fn os_gas_cost_name(selector: &DeprecatedSyscallSelector) -> Option<&'static str> {
+    use DeprecatedSyscallSelector::*;
+    Some(match selector {
+        CallContract => "call_contract_gas_cost",
+        Deploy => "deploy_gas_cost",
+        EmitEvent => "emit_event_gas_cost",
+        GetBlockHash => "get_block_hash_gas_cost",
+        GetCompiledClassHash => "get_compiled_class_hash_gas_cost",
+        GetExecutionInfo => "get_execution_info_gas_cost",
+        Keccak => "keccak_gas_cost",
+        LibraryCall | LibraryCallL1Handler => "library_call_gas_cost",
+        ReplaceClass => "replace_class_gas_cost",
+        Secp256k1Add => "secp256k1_add_gas_cost",
+        Secp256k1GetPointFromX => "secp256k1_get_point_from_x_gas_cost",
+        Secp256k1GetXy => "secp256k1_get_xy_gas_cost",
+        Secp256k1Mul => "secp256k1_mul_gas_cost",
+        Secp256k1New => "secp256k1_new_gas_cost",
+        Secp256r1Add => "secp256r1_add_gas_cost",
+        Secp256r1GetPointFromX => "secp256r1_get_point_from_x_gas_cost",
+        Secp256r1GetXy => "secp256r1_get_xy_gas_cost",
+        Secp256r1Mul => "secp256r1_mul_gas_cost",
+        Secp256r1New => "secp256r1_new_gas_cost",
+        SendMessageToL1 => "send_message_to_l1_gas_cost",
+        StorageRead => "storage_read_gas_cost",
+        StorageWrite => "storage_write_gas_cost",
+        _ => return None,
+    })
+}
+
 #[derive(Clone, Debug, Default, Deserialize)]
 pub struct L2ResourceGasCosts {
     // TODO(barak, 18/03/2024): Once we start charging per byte change to milligas_per_data_byte,
@@ -163,6 +379,10 @@ pub struct L2ResourceGasCosts {
     pub milligas_per_data_felt: u128,
     pub event_key_factor: u128,
     pub milligas_per_code_byte: u128,
+    // Whether the events emitted by inner calls are charged, and not only those of the top-level
+    // calls; absent in the constants of versions that only charged the latter.
+    #[serde(default)]
+    pub charge_inner_call_events: bool,
 }
 
 #[derive(Clone, Debug, Default, Deserialize)]
@@ -172,16 +392,28 @@ pub struct EventSizeLimit {
     pub max_n_emitted_events: usize,
 }
 
+/// Limits on the (untrusted) input of syscall requests, enforced when a request is read.
+#[derive(Clone, Debug, Default, Deserialize)]
+pub struct SyscallLimits {
+    pub max_array_length: usize,
+    // The maximal calldata length of calls issued by syscalls (call, library call and deploy).
+    pub max_calldata_length: usize,
+    // The maximal number of syscalls a transaction may issue, in its validation and execution
+    // combined; unlimited if absent.
+    #[serde(default)]
+    pub max_syscalls_per_tx: Option<usize>,
+}
+
 #[derive(Clone, Debug, Default, Deserialize)]
 // Serde trick for adding validations via a customr deserializer, without forgoing the derive.
 // See: https://github.com/serde-rs/serde/issues/1220.
 #[serde(remote = "Self")]
 pub struct OsResources {
     // Mapping from every syscall to its execution resources in the OS (e.g., amount of Cairo
-    // steps).
+    // steps). Syscalls whose OS cost depends on their input (e.g., the data length of an event)
+    // also specify resources per unit of input size.
     // TODO(Arni, 14/6/2023): Update `GetBlockHash` values.
-    // TODO(ilya): Consider moving the resources of a keccak round to a seperate dict.
-    execute_syscalls: HashMap<DeprecatedSyscallSelector, ExecutionResources>,
+    execute_syscalls: HashMap<DeprecatedSyscallSelector, SyscallResources>,
     // Mapping from every transaction to its extra execution resources in the OS,
     // i.e., resources that don't count during the execution itself.
     // For each transaction the OS uses a constant amount of VM resources, and an
@@ -191,21 +423,47 @@ pub struct OsResources {
     // Resources needed for the OS to compute the KZG commitment info, as a factor of the data
     // segment length. Does not include poseidon_hash_many cost.
     compute_os_kzg_commitment_info: ExecutionResources,
+
+    // The share of the fee transfer call in the constant resources of account transactions in
+    // `execute_txs_inner`; replaced by the executed fee transfer resources when those are
+    // counted.
+    #[serde(default)]
+    fee_transfer_approximation: ExecutionResources,
+
+    // An approximation of the resources of the validation entry point of an account; charged for
+    // transactions executed without validation, if the block context says so.
+    #[serde(default)]
+    validate_approximation: ExecutionResources,
 }
 
 impl OsResources {
+    /// Loads and validates OS resources from a JSON file, structured as the `os_resources` entry
+    /// of the versioned constants file.
+    pub fn from_json_file(path: &Path) -> Result<Self, VersionedConstantsError> {
+        Ok(serde_json::from_reader(std::fs::File::open(path)?)?)
+    }
+
+    /// Same as `from_json_file`, for a JSON string.
+    pub fn from_json_str(json: &str) -> Result<Self, VersionedConstantsError> {
+        Ok(serde_json::from_str(json)?)
+    }
+
     /// Calculates the additional resources needed for the OS to run the given transaction;
     /// i.e., the resources of the Starknet OS function `execute_transactions_inner`.
     /// Also adds the resources needed for the fee transfer execution, performed in the end·
     /// of every transaction.
+    /// The class size (in felts) is given for declare transactions, whose OS cost also scales
+    /// with the size of the declared class.
     fn get_additional_os_tx_resources(
         &self,
         tx_type: TransactionType,
         calldata_length: usize,
+        class_size: Option<usize>,
         data_segment_length: usize,
         use_kzg_da: bool,
-    ) -> Result<ExecutionResources, TransactionExecutionError> {
-        let mut os_additional_vm_resources = self.resources_for_tx_type(&tx_type, calldata_length);
+    ) -> Result<ExecutionResources, OsResourcesError> {
+        let mut os_additional_vm_resources =
+            self.resources_for_tx_type(&tx_type, calldata_length, class_size)?;
 
         if use_kzg_da {
             os_additional_vm_resources += &self.os_kzg_da_resources(data_segment_length);
@@ -221,64 +479,59 @@ impl OsResources {
         syscall_counter: &SyscallCounter,
     ) -> Result<ExecutionResources, PostExecutionError> {
         let mut os_additional_resources = ExecutionResources::default();
-        for (syscall_selector, count) in syscall_counter {
-            let syscall_resources =
-                self.execute_syscalls.get(syscall_selector).unwrap_or_else(|| {
-                    panic!("OS resources of syscall '{syscall_selector:?}' are unknown.")
-                });
-            os_additional_resources += &(syscall_resources * *count);
+        for (syscall_selector, syscall_usage) in syscall_counter {
+            let syscall_resources = self
+                .execute_syscalls
+                .get(syscall_selector)
+                .ok_or(OsResourcesError::UnknownSyscall(*syscall_selector))?;
+            os_additional_resources += &(&syscall_resources.constant * syscall_usage.call_count);
+            os_additional_resources +=
+                &(&syscall_resources.linear_factor * syscall_usage.linear_factor);
         }
 
         Ok(os_additional_resources)
     }
 
-    fn resources_params_for_tx_type(&self, tx_type: &TransactionType) -> &ResourcesParams {
+    fn resources_params_for_tx_type(
+        &self,
+        tx_type: &TransactionType,
+    ) -> Result<&ResourcesParams, OsResourcesError> {
         self.execute_txs_inner
             .get(tx_type)
-            .unwrap_or_else(|| panic!("should contain transaction type '{tx_type:?}'."))
+            .ok_or(OsResourcesError::UnknownTransactionType(*tx_type))
     }
 
     fn resources_for_tx_type(
         &self,
         tx_type: &TransactionType,
         calldata_length: usize,
-    ) -> ExecutionResources {
-        let resources_vector = self.resources_params_for_tx_type(tx_type);
-        &resources_vector.constant + &(&(resources_vector.calldata_factor) * calldata_length)
+        class_size: Option<usize>,
+    ) -> Result<ExecutionResources, OsResourcesError> {
+        let resources_vector = self.resources_params_for_tx_type(tx_type)?;
+        let mut resources =
+            &resources_vector.constant + &(&(resources_vector.calldata_factor) * calldata_length);
+        if let Some(class_size) = class_size {
+            resources += &(&resources_vector.class_size_factor * class_size);
+        }
+
+        Ok(resources)
     }
 
     fn os_kzg_da_resources(&self, data_segment_length: usize) -> ExecutionResources {
         &(&self.compute_os_kzg_commitment_info * data_segment_length)
             + &poseidon_hash_many_cost(data_segment_length)
     }
-}
-
-impl<'de> Deserialize<'de> for OsResources {
-    fn deserialize<D>(deserializer: D) -> Result<Self, D::Error>
-    where
-        D: Deserializer<'de>,
-    {
-        let os_resources = Self::deserialize(deserializer)?;
 
-        // Validations.
-
-        for tx_type in TransactionType::iter() {
-            if !os_resources.execute_txs_inner.contains_key(&tx_type) {
-                return Err(DeserializationError::custom(format!(
-                    "ValidationError: os_resources.execute_tx_inner is missing transaction_type: \
-                     {tx_type:?}"
-                )));
-            }
-        }
-
-        for syscall_handler in DeprecatedSyscallSelector::iter() {
-            if !os_resources.execute_syscalls.contains_key(&syscall_handler) {
-                return Err(DeserializationError::custom(format!(
-                    "ValidationError: os_resources.execute_syscalls are missing syscall handler: \
-                     {syscall_handler:?}"
-                )));
-            }
-        }
+    /// Checks that every transaction type and syscall has resources, and that only known builtins
+    /// are used; run on deserialization, so that an incomplete table is rejected on load rather
+    /// than when a transaction hits its missing entry.
+    pub fn validate(&self) -> Result<(), OsResourcesError> {
+        let missing_tx_types: Vec<TransactionType> = TransactionType::iter()
+            .filter(|tx_type| !self.execute_txs_inner.contains_key(tx_type))
+            .collect();
+        let missing_syscalls: Vec<DeprecatedSyscallSelector> = DeprecatedSyscallSelector::iter()
+            .filter(|syscall_selector| !self.execute_syscalls.contains_key(syscall_selector))
+            .collect();
 
         let known_builtin_names: HashSet<&str> = HashSet::from([
             builtin_runner::OUTPUT_BUILTIN_NAME,
@@ -291,24 +544,50 @@ impl<'de> Deserialize<'de> for OsResources {
             builtin_runner::POSEIDON_BUILTIN_NAME,
             builtin_runner::SEGMENT_ARENA_BUILTIN_NAME,
         ]);
-
-        let execution_resources = os_resources
+        let execution_resources = self
             .execute_txs_inner
             .values()
             .flat_map(|resources_vector| {
-                [&resources_vector.constant, &resources_vector.calldata_factor]
+                [
+                    &resources_vector.constant,
+                    &resources_vector.calldata_factor,
+                    &resources_vector.class_size_factor,
+                ]
             })
-            .chain(os_resources.execute_syscalls.values())
-            .chain(std::iter::once(&os_resources.compute_os_kzg_commitment_info));
-        let builtin_names =
-            execution_resources.flat_map(|resources| resources.builtin_instance_counter.keys());
-        for builtin_name in builtin_names {
-            if !(known_builtin_names.contains(builtin_name.as_str())) {
-                return Err(DeserializationError::custom(format!(
-                    "ValidationError: unknown os resource {builtin_name}"
-                )));
-            }
+            .chain(self.execute_syscalls.values().flat_map(|syscall_resources| {
+                [&syscall_resources.constant, &syscall_resources.linear_factor]
+            }))
+            .chain([
+                &self.compute_os_kzg_commitment_info,
+                &self.fee_transfer_approximation,
+                &self.validate_approximation,
+            ]);
+        let unknown_builtins: BTreeSet<String> = execution_resources
+            .flat_map(|resources| resources.builtin_instance_counter.keys())
+            .filter(|builtin_name| !known_builtin_names.contains(builtin_name.as_str()))
+            .cloned()
+            .collect();
+
+        if missing_tx_types.is_empty() && missing_syscalls.is_empty() && unknown_builtins.is_empty()
+        {
+            return Ok(());
         }
+        Err(OsResourcesError::InvalidTable {
+            missing_tx_types,
+            missing_syscalls,
+            unknown_builtins: unknown_builtins.into_iter().collect(),
+        })
+    }
+}
+
+impl<'de> Deserialize<'de> for OsResources {
+    fn deserialize<D>(deserializer: D) -> Result<Self, D::Error>
+    where
+        D: Deserializer<'de>,
+    {
+        let os_resources = Self::deserialize(deserializer)?;
+
+        os_resources.validate().map_err(DeserializationError::custom)?;
 
         Ok(os_resources)
     }
@@ -335,7 +614,7 @@ pub struct OSConstants {
 impl OSConstants {
     // List of all gas cost constants that *must* be present in the JSON file, all other consts are
     // ignored. See documentation in core/os/constants.cairo.
-    const ALLOWED_GAS_COST_NAMES: [&'static str; 31] = [
+    const ALLOWED_GAS_COST_NAMES: [&'static str; 32] = [
         "step_gas_cost",
         "range_check_gas_cost",
         "memory_hole_gas_cost",
@@ -358,6 +637,7 @@ impl OSConstants {
         "get_execution_info_gas_cost",
         "library_call_gas_cost",
         "replace_class_gas_cost",
+        "get_compiled_class_hash_gas_cost",
         "storage_read_gas_cost",
         "storage_write_gas_cost",
         "emit_event_gas_cost",
@@ -469,6 +749,38 @@ pub enum VersionedConstantsError {
     ParseError(#[from] serde_json::Error),
 }
 
+#[derive(Debug, Error, PartialEq)]
+pub enum OsResourcesError {
+    #[error(
+        "ValidationError: os_resources are missing transaction types {missing_tx_types:?} and \
+         syscalls {missing_syscalls:?}, and use unknown builtins {unknown_builtins:?}."
+    )]
+    InvalidTable {
+        missing_tx_types: Vec<TransactionType>,
+        missing_syscalls: Vec<DeprecatedSyscallSelector>,
+        unknown_builtins: Vec<String>,
+    },
+    #[error("OS resources of syscall '{0:?}' are unknown.")]
+    UnknownSyscall(DeprecatedSyscallSelector),
+    #[error("OS resources of transaction type '{0:?}' are unknown.")]
+    UnknownTransactionType(TransactionType),
+}
+
+#[derive(Debug, Error, PartialEq)]
+pub enum SyscallGasCostsError {
+    #[error(
+        "ValidationError: the base gas cost {base} of syscall {selector:?} is below the \
+         pre-charged syscall_base_gas_cost {syscall_base_gas_cost}."
+    )]
+    BaseCostTooLow { selector: DeprecatedSyscallSelector, base: u64, syscall_base_gas_cost: u64 },
+    #[error("ValidationError: the OS constants are missing the gas cost {0}.")]
+    MissingOsGasCost(&'static str),
+    #[error("Syscall {0:?} has no gas cost.")]
+    MissingSyscallGasCost(DeprecatedSyscallSelector),
+    #[error("ValidationError: syscalls {0:?} are not gas-metered.")]
+    UnknownSyscalls(Vec<DeprecatedSyscallSelector>),
+}
+
 #[derive(Debug, Error)]
 pub enum OsConstantsSerdeError {
     #[error("Value cannot be cast into u64: {0}")]
@@ -492,4 +804,78 @@ pub enum OsConstantsSerdeError {
 pub struct ResourcesParams {
     pub constant: ExecutionResources,
     pub calldata_factor: ExecutionResources,
+    // Resources per felt of the declared class (its bytecode and Sierra program); only applies to
+    // declare transactions.
+    #[serde(default)]
+    pub class_size_factor: ExecutionResources,
+}
+
+/// The OS resources of a syscall: a constant amount per invocation, and an amount per unit of its
+/// input size (e.g., the data length of an emitted event).
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct SyscallResources {
+    pub constant: ExecutionResources,
+    pub linear_factor: ExecutionResources,
+}
+
+impl<'de> Deserialize<'de> for SyscallResources {
+    fn deserialize<D>(deserializer: D) -> Result<Self, D::Error>
+    where
+        D: Deserializer<'de>,
+    {
+        deserializer.deserialize_map(SyscallResourcesVisitor)
+    }
+}
+
+/// Reads syscall resources given either as `{ constant, linear_factor }`, or, for syscalls whose
+/// cost does not depend on their input, as flat execution resources; unknown keys, and keys of
+/// both forms, are rejected. Values are deserialized directly (rather than through an intermediate
+/// `Value`), to keep their errors intact.
+struct SyscallResourcesVisitor;
+
+impl SyscallResourcesVisitor {
+    const FIELDS: &'static [&'static str] =
+        &["constant", "linear_factor", "n_steps", "n_memory_holes", "builtin_instance_counter"];
+}
+
+impl<'de> Visitor<'de> for SyscallResourcesVisitor {
+    type Value = SyscallResources;
+
+    fn expecting(&self, formatter: &mut fmt::Formatter<'_>) -> fmt::Result {
+        formatter.write_str("execution resources, or a constant and a linear factor of them")
+    }
+
+    fn visit_map<A>(self, mut map: A) -> Result<Self::Value, A::Error>
+    where
+        A: MapAccess<'de>,
+    {
+        let mut syscall_resources = SyscallResources::default();
+        let (mut has_factored_fields, mut has_flat_fields) = (false, false);
+        while let Some(key) = map.next_key::<String>()? {
+            let constant = &mut syscall_resources.constant;
+            match key.as_str() {
+                "constant" => *constant = map.next_value()?,
+                "linear_factor" => syscall_resources.linear_factor = map.next_value()?,
+                "n_steps" => constant.n_steps = map.next_value()?,
+                "n_memory_holes" => constant.n_memory_holes = map.next_value()?,
+                "builtin_instance_counter" => {
+                    constant.builtin_instance_counter = map.next_value()?
+                }
+                _ => return Err(A::Error::unknown_field(&key, Self::FIELDS)),
+            }
+            match key.as_str() {
+                "constant" | "linear_factor" => has_factored_fields = true,
+                _ => has_flat_fields = true,
+            }
+        }
+
+        if has_factored_fields && has_flat_fields {
+            return Err(A::Error::custom(
+                "syscall resources must be given either as a constant and a linear factor, or as \
+                 flat execution resources, not both",
+            ));
+        }
+
+        Ok(syscall_resources)
+    }
 }
@@ -1,6 +1,9 @@
+use assert_matches::assert_matches;
 use pretty_assertions::assert_eq;
+use serde_json::{json, Value};
 
 use super::*;
+use crate::execution::syscalls::hint_processor::SyscallUsage;
 
 // TODO: Test Starknet OS validation.
 
@@ -102,3 +105,481 @@ fn test_invalid_number() {
          cannot be cast into u64",
     );
 }
+
+#[test]
+fn test_syscall_response_layout() {
+    // Constants that do not specify a layout use the legacy one.
+    assert_eq!(
+        VersionedConstants::latest_constants().syscall_response_layout,
+        SyscallResponseLayout::Legacy
+    );
+
+    let json_data =
+        DEFAULT_CONSTANTS_JSON.replacen('{', r#"{"syscall_response_layout": "OsAligned","#, 1);
+    let versioned_constants: VersionedConstants = serde_json::from_str(&json_data).unwrap();
+    assert_eq!(versioned_constants.syscall_response_layout, SyscallResponseLayout::OsAligned);
+}
+
+#[test]
+fn test_syscall_gas_costs_match_os_constants() {
+    let versioned_constants = VersionedConstants::latest_constants();
+    let gas_cost_names = [
+        (DeprecatedSyscallSelector::CallContract, "call_contract_gas_cost"),
+        (DeprecatedSyscallSelector::Deploy, "deploy_gas_cost"),
+        (DeprecatedSyscallSelector::EmitEvent, "emit_event_gas_cost"),
+        (DeprecatedSyscallSelector::GetBlockHash, "get_block_hash_gas_cost"),
+        (DeprecatedSyscallSelector::GetCompiledClassHash, "get_compiled_class_hash_gas_cost"),
+        (DeprecatedSyscallSelector::GetExecutionInfo, "get_execution_info_gas_cost"),
+        (DeprecatedSyscallSelector::Keccak, "keccak_gas_cost"),
+        (DeprecatedSyscallSelector::LibraryCall, "library_call_gas_cost"),
+        (DeprecatedSyscallSelector::LibraryCallL1Handler, "library_call_gas_cost"),
+        (DeprecatedSyscallSelector::ReplaceClass, "replace_class_gas_cost"),
+        (DeprecatedSyscallSelector::Secp256k1Add, "secp256k1_add_gas_cost"),
+        (DeprecatedSyscallSelector::Secp256k1GetPointFromX, "secp256k1_get_point_from_x_gas_cost"),
+        (DeprecatedSyscallSelector::Secp256k1GetXy, "secp256k1_get_xy_gas_cost"),
+        (DeprecatedSyscallSelector::Secp256k1Mul, "secp256k1_mul_gas_cost"),
+        (DeprecatedSyscallSelector::Secp256k1New, "secp256k1_new_gas_cost"),
+        (DeprecatedSyscallSelector::Secp256r1Add, "secp256r1_add_gas_cost"),
+        (DeprecatedSyscallSelector::Secp256r1GetPointFromX, "secp256r1_get_point_from_x_gas_cost"),
+        (DeprecatedSyscallSelector::Secp256r1GetXy, "secp256r1_get_xy_gas_cost"),
+        (DeprecatedSyscallSelector::Secp256r1Mul, "secp256r1_mul_gas_cost"),
+        (DeprecatedSyscallSelector::Secp256r1New, "secp256r1_new_gas_cost"),
+        (DeprecatedSyscallSelector::SendMessageToL1, "send_message_to_l1_gas_cost"),
+        (DeprecatedSyscallSelector::StorageRead, "storage_read_gas_cost"),
+        (DeprecatedSyscallSelector::StorageWrite, "storage_write_gas_cost"),
+    ];
+
+    for (selector, gas_cost_name) in gas_cost_names {
+        assert_eq!(
+            versioned_constants.syscall_gas_costs.get(&selector).unwrap(),
+            SyscallGasCost { base: versioned_constants.gas_cost(gas_cost_name), linear_factor: 0 },
+            "Unexpected gas cost of {selector:?}."
+        );
+    }
+}
+
+/// Constants not loaded from a file have no syscall gas costs; requesting one fails rather than
+/// panics.
+#[test]
+fn test_missing_syscall_gas_cost() {
+    assert_eq!(
+        VersionedConstants::default()
+            .syscall_gas_costs
+            .get(&DeprecatedSyscallSelector::StorageRead)
+            .unwrap_err(),
+        SyscallGasCostsError::MissingSyscallGasCost(DeprecatedSyscallSelector::StorageRead)
+    );
+}
+
+#[test]
+fn test_os_gas_cost_names_cover_gas_metered_syscalls() {
+    for selector in DeprecatedSyscallSelector::iter() {
+        assert_eq!(
+            os_gas_cost_name(&selector).is_some(),
+            is_builtin_syscall(&selector),
+            "Unexpected OS gas cost name of {selector:?}."
+        );
+    }
+}
+
+#[test]
+fn test_invalid_syscall_gas_costs() {
+    // Modified textually, as the OS constants are order-dependent.
+    let modified_constants_json = |from: &str, to: &str| {
+        assert!(DEFAULT_CONSTANTS_JSON.contains(from));
+        DEFAULT_CONSTANTS_JSON.replacen(from, to, 1)
+    };
+    let check_error = |json_data: &str, expected_error_message: &str| {
+        let error = serde_json::from_str::<VersionedConstants>(json_data).unwrap_err();
+        assert!(
+            error.to_string().contains(expected_error_message),
+            "Expected '{expected_error_message}' in '{error}'."
+        );
+    };
+
+    let linear_factors_key = r#""syscall_gas_linear_factors": {"#;
+    let unknown_syscall = modified_constants_json(
+        linear_factors_key,
+        r#""syscall_gas_linear_factors": {"NotASyscall": 1,"#,
+    );
+    check_error(&unknown_syscall, "unknown variant `NotASyscall`");
+
+    let not_gas_metered = modified_constants_json(
+        linear_factors_key,
+        r#""syscall_gas_linear_factors": {"GetCallerAddress": 1,"#,
+    );
+    let expected_error =
+        SyscallGasCostsError::UnknownSyscalls(vec![DeprecatedSyscallSelector::GetCallerAddress]);
+    check_error(&not_gas_metered, &expected_error.to_string());
+
+    // The OS constants give the base costs, which must cover the pre-charged base gas cost.
+    let base_cost_too_low = modified_constants_json(
+        r#""storage_read_gas_cost": {"#,
+        r#""storage_read_gas_cost": 1, "ignored_storage_read_gas_cost": {"#,
+    );
+    let syscall_base_gas_cost =
+        VersionedConstants::latest_constants().gas_cost("syscall_base_gas_cost");
+    let expected_error = SyscallGasCostsError::BaseCostTooLow {
+        selector: DeprecatedSyscallSelector::StorageRead,
+        base: 1,
+        syscall_base_gas_cost,
+    };
+    check_error(&base_cost_too_low, &expected_error.to_string());
+
+    // Replacing a cost keeps the table valid.
+    let syscall_gas_costs = VersionedConstants::latest_constants().syscall_gas_costs.clone();
+    let cost = SyscallGasCost { base: syscall_base_gas_cost, linear_factor: 2 };
+    assert_eq!(
+        syscall_gas_costs
+            .clone()
+            .with_cost(DeprecatedSyscallSelector::StorageRead, cost)
+            .unwrap()
+            .get(&DeprecatedSyscallSelector::StorageRead)
+            .unwrap(),
+        cost
+    );
+    assert_eq!(
+        syscall_gas_costs
+            .clone()
+            .with_cost(DeprecatedSyscallSelector::GetCallerAddress, cost)
+            .unwrap_err(),
+        SyscallGasCostsError::UnknownSyscalls(vec![DeprecatedSyscallSelector::GetCallerAddress])
+    );
+    let low_cost = SyscallGasCost { base: syscall_base_gas_cost - 1, linear_factor: 0 };
+    assert_eq!(
+        syscall_gas_costs.with_cost(DeprecatedSyscallSelector::StorageRead, low_cost).unwrap_err(),
+        SyscallGasCostsError::BaseCostTooLow {
+            selector: DeprecatedSyscallSelector::StorageRead,
+            base: low_cost.base,
+            syscall_base_gas_cost,
+        }
+    );
+}
+
+#[test]
+fn test_syscall_gas_cost_overflow() {
+    let cost = SyscallGasCost { base: 1, linear_factor: u64::MAX / 2 };
+    assert_eq!(cost.get_syscall_cost(2), Some(u64::MAX));
+    assert_eq!(cost.get_syscall_cost(3), None);
+}
+
+/// Returns the shipped OS resources as JSON, after applying `modify` to them.
+fn modified_os_resources_json(modify: impl FnOnce(&mut Value)) -> String {
+    let mut constants: Value = serde_json::from_str(DEFAULT_CONSTANTS_JSON).unwrap();
+    let os_resources = &mut constants["os_resources"];
+    modify(os_resources);
+    os_resources.to_string()
+}
+
+fn check_os_resources_error(json_data: &str, expected_error_message: &str) {
+    let error = OsResources::from_json_str(json_data).unwrap_err();
+    assert!(
+        error.to_string().contains(expected_error_message),
+        "Expected '{expected_error_message}' in '{error}'."
+    );
+}
+
+#[test]
+fn test_custom_os_resources() {
+    let default_constants = VersionedConstants::latest_constants();
+    let json_data = modified_os_resources_json(|os_resources| {
+        os_resources["execute_syscalls"]["CallContract"]["n_steps"] = 1000.into();
+        os_resources["execute_txs_inner"]["InvokeFunction"]["constant"]["n_steps"] = 5000.into();
+    });
+    let custom_constants = default_constants
+        .clone()
+        .with_os_resources(Arc::new(OsResources::from_json_str(&json_data).unwrap()));
+
+    let syscall_counter = SyscallCounter::from([(
+        DeprecatedSyscallSelector::CallContract,
+        SyscallUsage { call_count: 2, linear_factor: 0 },
+    )]);
+    let default_syscall_resources =
+        default_constants.get_additional_os_syscall_resources(&syscall_counter).unwrap();
+    assert_ne!(default_syscall_resources.n_steps, 2 * 1000);
+    assert_eq!(
+        custom_constants.get_additional_os_syscall_resources(&syscall_counter).unwrap(),
+        ExecutionResources { n_steps: 2 * 1000, ..default_syscall_resources }
+    );
+
+    let tx_resources = |constants: &VersionedConstants, tx_type| {
+        constants.get_additional_os_tx_resources(tx_type, 0, None, 0, false).unwrap()
+    };
+    let default_invoke_resources = tx_resources(default_constants, TransactionType::InvokeFunction);
+    assert_eq!(
+        tx_resources(&custom_constants, TransactionType::InvokeFunction),
+        ExecutionResources { n_steps: 5000, ..default_invoke_resources }
+    );
+    // Untouched entries are charged as before.
+    assert_eq!(
+        tx_resources(&custom_constants, TransactionType::Declare),
+        tx_resources(default_constants, TransactionType::Declare)
+    );
+}
+
+#[test]
+fn test_os_resources_from_file() {
+    let path = std::env::temp_dir().join("blockifier_test_os_resources.json");
+    std::fs::write(&path, modified_os_resources_json(|_| {})).unwrap();
+    let os_resources = OsResources::from_json_file(&path).unwrap();
+    std::fs::remove_file(&path).unwrap();
+
+    let syscall_counter = SyscallCounter::from([(
+        DeprecatedSyscallSelector::StorageRead,
+        SyscallUsage { call_count: 3, linear_factor: 0 },
+    )]);
+    assert_eq!(
+        os_resources.get_additional_os_syscall_resources(&syscall_counter).unwrap(),
+        VersionedConstants::latest_constants()
+            .get_additional_os_syscall_resources(&syscall_counter)
+            .unwrap()
+    );
+
+    assert_matches!(
+        OsResources::from_json_file(Path::new("non_existent_os_resources.json")),
+        Err(VersionedConstantsError::IoError(_))
+    );
+}
+
+#[test]
+fn test_linear_syscall_resources() {
+    let json_data = modified_os_resources_json(|os_resources| {
+        let emit_event = &mut os_resources["execute_syscalls"]["EmitEvent"];
+        *emit_event = json!({
+            "constant": emit_event.clone(),
+            "linear_factor": {
+                "builtin_instance_counter": {"range_check_builtin": 1},
+                "n_memory_holes": 0,
+                "n_steps": 10
+            }
+        });
+    });
+    let os_resources = OsResources::from_json_str(&json_data).unwrap();
+    let emit_event_counter = |data_length| {
+        SyscallCounter::from([(
+            DeprecatedSyscallSelector::EmitEvent,
+            SyscallUsage { call_count: 1, linear_factor: data_length },
+        )])
+    };
+    let expected_resources = |n_steps, n_range_checks| ExecutionResources {
+        n_steps,
+        n_memory_holes: 0,
+        builtin_instance_counter: HashMap::from([(
+            builtin_runner::RANGE_CHECK_BUILTIN_NAME.to_string(),
+            n_range_checks,
+        )]),
+    };
+
+    // The shipped constant of 61 steps and a range check, plus 10 steps and a range check per
+    // data element.
+    assert_eq!(
+        os_resources.get_additional_os_syscall_resources(&emit_event_counter(2)).unwrap(),
+        expected_resources(61 + 2 * 10, 1 + 2)
+    );
+    assert_eq!(
+        os_resources.get_additional_os_syscall_resources(&emit_event_counter(200)).unwrap(),
+        expected_resources(61 + 200 * 10, 1 + 200)
+    );
+
+    // Flat resources, as shipped, do not scale with the input size.
+    let versioned_constants = VersionedConstants::latest_constants();
+    assert_eq!(
+        versioned_constants.get_additional_os_syscall_resources(&emit_event_counter(2)).unwrap(),
+        expected_resources(61, 1)
+    );
+    assert_eq!(
+        versioned_constants.get_additional_os_syscall_resources(&emit_event_counter(200)).unwrap(),
+        expected_resources(61, 1)
+    );
+}
+
+/// Syscall resources with unknown keys, or mixing the flat and the factored forms, are rejected.
+#[test]
+fn test_invalid_syscall_resources() {
+    let parse_with_emit_event_resources = |emit_event_resources: Value| {
+        let json_data = modified_os_resources_json(|os_resources| {
+            os_resources["execute_syscalls"]["EmitEvent"] = emit_event_resources;
+        });
+        OsResources::from_json_str(&json_data).unwrap_err().to_string()
+    };
+
+    let error = parse_with_emit_event_resources(json!({
+        "n_steps": 61,
+        "n_memory_holes": 0,
+        "builtin_instance_counter": {},
+        "n_stepz": 10
+    }));
+    assert!(error.contains("unknown field `n_stepz`"), "{error}");
+
+    let error = parse_with_emit_event_resources(json!({
+        "constant": {"n_steps": 61, "n_memory_holes": 0, "builtin_instance_counter": {}},
+        "n_steps": 10
+    }));
+    assert!(error.contains("either as a constant and a linear factor"), "{error}");
+}
+
+/// The OS resources of the keccak syscall are per round; i.e., per hashed block of 17 words.
+#[test]
+fn test_keccak_rounds_resources() {
+    let versioned_constants = VersionedConstants::latest_constants();
+    let keccak_resources = |n_rounds| {
+        let keccak_counter = SyscallCounter::from([(
+            DeprecatedSyscallSelector::Keccak,
+            SyscallUsage { call_count: 1, linear_factor: n_rounds },
+        )]);
+        versioned_constants.get_additional_os_syscall_resources(&keccak_counter).unwrap()
+    };
+    let round_resources = ExecutionResources {
+        n_steps: 381,
+        n_memory_holes: 0,
+        builtin_instance_counter: HashMap::from([
+            (builtin_runner::BITWISE_BUILTIN_NAME.to_string(), 6),
+            (builtin_runner::KECCAK_BUILTIN_NAME.to_string(), 1),
+            (builtin_runner::RANGE_CHECK_BUILTIN_NAME.to_string(), 56),
+        ]),
+    };
+
+    assert_eq!(keccak_resources(1), round_resources);
+    assert_eq!(keccak_resources(17), &round_resources * 17);
+    // An invocation that performs no rounds (e.g., due to an invalid input) costs nothing.
+    assert_eq!(keccak_resources(0).filter_unused_builtins(), ExecutionResources::default());
+}
+
+#[test]
+fn test_declare_class_size_resources() {
+    let json_data = modified_os_resources_json(|os_resources| {
+        os_resources["execute_txs_inner"]["Declare"]["class_size_factor"] = json!({
+            "builtin_instance_counter": {"pedersen_builtin": 1},
+            "n_memory_holes": 0,
+            "n_steps": 5
+        });
+    });
+    let custom_constants = VersionedConstants::latest_constants()
+        .clone()
+        .with_os_resources(Arc::new(OsResources::from_json_str(&json_data).unwrap()));
+    let declare_resources = |constants: &VersionedConstants, class_size| {
+        constants
+            .get_additional_os_tx_resources(TransactionType::Declare, 0, class_size, 0, false)
+            .unwrap()
+    };
+    let default_declare_resources =
+        declare_resources(VersionedConstants::latest_constants(), Some(1000));
+    // The shipped constants do not scale declares by their class size.
+    assert_eq!(
+        default_declare_resources,
+        declare_resources(VersionedConstants::latest_constants(), None)
+    );
+
+    // 5 steps and a Pedersen hash per felt of the class.
+    let class_size_resources = |class_size| ExecutionResources {
+        n_steps: 5 * class_size,
+        n_memory_holes: 0,
+        builtin_instance_counter: HashMap::from([(
+            builtin_runner::HASH_BUILTIN_NAME.to_string(),
+            class_size,
+        )]),
+    };
+    let small_class_resources = declare_resources(&custom_constants, Some(10));
+    let large_class_resources = declare_resources(&custom_constants, Some(1000));
+    assert_eq!(small_class_resources, &default_declare_resources + &class_size_resources(10));
+    assert_eq!(large_class_resources, &default_declare_resources + &class_size_resources(1000));
+    assert!(large_class_resources.n_steps > small_class_resources.n_steps);
+}
+
+#[test]
+fn test_invalid_os_resources() {
+    let unknown_syscall = modified_os_resources_json(|os_resources| {
+        os_resources["execute_syscalls"]["NotASyscall"] =
+            os_resources["execute_syscalls"]["CallContract"].clone();
+    });
+    check_os_resources_error(&unknown_syscall, "unknown variant `NotASyscall`");
+
+    let unknown_tx_type = modified_os_resources_json(|os_resources| {
+        os_resources["execute_txs_inner"]["NotATxType"] =
+            os_resources["execute_txs_inner"]["Declare"].clone();
+    });
+    check_os_resources_error(&unknown_tx_type, "unknown variant `NotATxType`");
+
+    let negative_steps = modified_os_resources_json(|os_resources| {
+        os_resources["execute_syscalls"]["CallContract"]["n_steps"] = (-1).into();
+    });
+    check_os_resources_error(&negative_steps, "invalid value: integer `-1`, expected usize");
+
+    let negative_builtin_count = modified_os_resources_json(|os_resources| {
+        os_resources["execute_syscalls"]["CallContract"]["builtin_instance_counter"]
+            ["range_check_builtin"] = (-20).into();
+    });
+    check_os_resources_error(
+        &negative_builtin_count,
+        "invalid value: integer `-20`, expected usize",
+    );
+
+    // Values beyond `u64::MAX` are parsed as floats, which are not valid amounts.
+    let overflowing_steps = modified_os_resources_json(|_| {}).replacen(
+        r#""n_steps":760"#,
+        r#""n_steps":18446744073709551616"#,
+        1,
+    );
+    assert_ne!(overflowing_steps, modified_os_resources_json(|_| {}));
+    check_os_resources_error(&overflowing_steps, "invalid type: floating point");
+
+    let missing_syscall = modified_os_resources_json(|os_resources| {
+        os_resources["execute_syscalls"].as_object_mut().unwrap().remove("CallContract");
+    });
+    check_os_resources_error(
+        &missing_syscall,
+        "missing transaction types [] and syscalls [CallContract]",
+    );
+}
+
+#[test]
+fn test_incomplete_os_resources() {
+    let json_data = modified_os_resources_json(|os_resources| {
+        let execute_syscalls = os_resources["execute_syscalls"].as_object_mut().unwrap();
+        execute_syscalls.remove("Keccak");
+        execute_syscalls.remove("CallContract");
+        execute_syscalls["StorageRead"]["builtin_instance_counter"]["not_a_builtin"] = 1.into();
+        os_resources["execute_txs_inner"].as_object_mut().unwrap().remove("Declare");
+    });
+
+    // All problems are reported at once, rather than the first one found.
+    let expected_error = OsResourcesError::InvalidTable {
+        missing_tx_types: vec![TransactionType::Declare],
+        missing_syscalls: vec![
+            DeprecatedSyscallSelector::CallContract,
+            DeprecatedSyscallSelector::Keccak,
+        ],
+        unknown_builtins: vec!["not_a_builtin".to_string()],
+    };
+    let unvalidated_os_resources =
+        OsResources::deserialize(serde_json::from_str::<Value>(&json_data).unwrap()).unwrap();
+    assert_eq!(unvalidated_os_resources.validate().unwrap_err(), expected_error);
+    check_os_resources_error(&json_data, &expected_error.to_string());
+}
+
+#[test]
+fn test_missing_os_resources_at_runtime() {
+    // Tables that skipped validation fail with typed errors, rather than panic.
+    let versioned_constants = VersionedConstants::default();
+
+    let syscall_counter = SyscallCounter::from([(
+        DeprecatedSyscallSelector::Keccak,
+        SyscallUsage { call_count: 1, linear_factor: 0 },
+    )]);
+    assert_matches!(
+        versioned_constants.get_additional_os_syscall_resources(&syscall_counter),
+        Err(PostExecutionError::OsResourcesError(OsResourcesError::UnknownSyscall(
+            DeprecatedSyscallSelector::Keccak
+        )))
+    );
+    assert_matches!(
+        versioned_constants.get_additional_os_tx_resources(
+            TransactionType::Declare,
+            0,
+            None,
+            0,
+            false
+        ),
+        Err(OsResourcesError::UnknownTransactionType(TransactionType::Declare))
+    );
+}
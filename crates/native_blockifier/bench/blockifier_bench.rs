@@ -4,6 +4,10 @@
 //!
 //! The main benchmark function is `transfers_benchmark`, which measures the performance
 //! of transfers between randomly created accounts, which are iterated over round-robin.
+//! `state_diff_benchmark` compares computing the state diff of a large cache by copying it and by
+//! consuming the state.
+//! `storage_view_conversion_benchmark` compares converting a large storage view into a state
+//! diff's nested storage mapping, to inserting its writes one by one.
 //!
 //! Run the benchmarks using `cargo bench --bench blockifier_bench`.
 
@@ -12,7 +16,7 @@ use std::collections::HashMap;
 use blockifier::abi::abi_utils::{get_fee_token_var_address, selector_from_name};
 use blockifier::context::{BlockContext, ChainInfo};
 use blockifier::execution::contract_class::ContractClassV0;
-use blockifier::state::cached_state::CachedState;
+use blockifier::state::cached_state::{CachedState, StorageView};
 use blockifier::state::state_api::State;
 use blockifier::test_utils::deploy_account::deploy_account_tx;
 use blockifier::test_utils::dict_state_reader::DictStateReader;
@@ -22,15 +26,19 @@ use blockifier::test_utils::{
     TEST_ACCOUNT_CONTRACT_CLASS_HASH, TEST_ERC20_CONTRACT_CLASS_HASH,
 };
 use blockifier::transaction::account_transaction::AccountTransaction;
-use blockifier::transaction::transactions::ExecutableTransaction;
+use blockifier::transaction::transactions::{ExecutableTransaction, ExecutionFlags};
 use blockifier::{deploy_account_tx_args, invoke_tx_args};
-use criterion::{criterion_group, criterion_main, Criterion};
-use starknet_api::core::{ClassHash, ContractAddress, Nonce};
+use criterion::{criterion_group, criterion_main, BatchSize, Criterion};
+use indexmap::IndexMap;
+use starknet_api::core::{ClassHash, ContractAddress, Nonce, PatriciaKey};
 use starknet_api::hash::{StarkFelt, StarkHash};
+use starknet_api::state::StorageKey;
 use starknet_api::transaction::{Calldata, ContractAddressSalt, Fee, TransactionVersion};
-use starknet_api::{calldata, class_hash, stark_felt};
+use starknet_api::{calldata, class_hash, contract_address, patricia_key, stark_felt};
 
 const N_ACCOUNTS: usize = 10000;
+const N_STORAGE_WRITES: u64 = 100000;
+const N_STORAGE_WRITES_PER_CONTRACT: u64 = 10;
 
 fn create_state() -> CachedState<DictStateReader> {
     // Declare all the needed contracts.
@@ -105,9 +113,9 @@ fn do_transfer(
         nonce: Nonce(stark_felt!(nonce)),
     });
     let account_tx = AccountTransaction::Invoke(tx);
-    let charge_fee = false;
-    let validate = false;
-    account_tx.execute(state, block_context, charge_fee, validate).unwrap();
+    let execution_flags =
+        ExecutionFlags { charge_fee: false, validate: false, ..Default::default() };
+    account_tx.execute(state, block_context, execution_flags).unwrap();
 }
 
 fn prepare_accounts(
@@ -150,13 +158,88 @@ fn prepare_accounts(
             .unwrap();
 
         let account_tx = AccountTransaction::DeployAccount(deploy_account_tx);
-        let charge_fee = false;
-        let validate = false;
-        account_tx.execute(state, block_context, charge_fee, validate).unwrap();
+        let execution_flags =
+            ExecutionFlags { charge_fee: false, validate: false, ..Default::default() };
+        account_tx.execute(state, block_context, execution_flags).unwrap();
     }
 
     (addresses, nonces)
 }
 
-criterion_group!(benches, transfers_benchmark);
+fn create_state_with_storage_writes() -> CachedState<DictStateReader> {
+    let mut state = CachedState::from(DictStateReader::default());
+    let contract_address = contract_address!("0x100");
+    for i in 0..N_STORAGE_WRITES {
+        let key = StorageKey(PatriciaKey::try_from(stark_felt!(i)).unwrap());
+        state.set_storage_at(contract_address, key, stark_felt!(i + 1)).unwrap();
+    }
+
+    state
+}
+
+pub fn state_diff_benchmark(c: &mut Criterion) {
+    let mut group = c.benchmark_group("state_diff");
+    group.sample_size(10);
+    group.bench_function("to_state_diff", |benchmark| {
+        benchmark.iter_batched_ref(
+            create_state_with_storage_writes,
+            |state| state.to_state_diff(),
+            BatchSize::LargeInput,
+        )
+    });
+    group.bench_function("into_state_diff", |benchmark| {
+        benchmark.iter_batched(
+            create_state_with_storage_writes,
+            CachedState::into_state_diff,
+            BatchSize::LargeInput,
+        )
+    });
+    group.finish();
+}
+
+fn create_storage_view() -> StorageView {
+    StorageView(
+        (0..N_STORAGE_WRITES)
+            .map(|i| {
+                let address = contract_address!(i / N_STORAGE_WRITES_PER_CONTRACT + 1);
+                let key = StorageKey(PatriciaKey::try_from(stark_felt!(i)).unwrap());
+                ((address, key), stark_felt!(i + 1))
+            })
+            .collect(),
+    )
+}
+
+pub fn storage_view_conversion_benchmark(c: &mut Criterion) {
+    let mut group = c.benchmark_group("storage_view_conversion");
+    group.sample_size(10);
+    group.bench_function("from", |benchmark| {
+        benchmark.iter_batched(
+            create_storage_view,
+            IndexMap::<ContractAddress, IndexMap<StorageKey, StarkFelt>>::from,
+            BatchSize::LargeInput,
+        )
+    });
+    // The baseline: a map lookup per write.
+    group.bench_function("insert_per_write", |benchmark| {
+        benchmark.iter_batched(
+            create_storage_view,
+            |storage_view| {
+                let mut storage_updates = IndexMap::<ContractAddress, IndexMap<_, _>>::new();
+                for ((address, key), value) in storage_view {
+                    storage_updates.entry(address).or_default().insert(key, value);
+                }
+                storage_updates
+            },
+            BatchSize::LargeInput,
+        )
+    });
+    group.finish();
+}
+
+criterion_group!(
+    benches,
+    transfers_benchmark,
+    state_diff_benchmark,
+    storage_view_conversion_benchmark
+);
 criterion_main!(benches);
@@ -23,6 +23,7 @@ use py_transaction_execution_info::{
     PyBouncerInfo, PyCallInfo, PyExecutionResources, PyOrderedEvent, PyOrderedL2ToL1Message,
     PyTransactionExecutionInfo,
 };
+use py_utils::PyFeltArrayFormat;
 use py_validator::PyValidator;
 use pyo3::prelude::*;
 use storage::StorageConfig;
@@ -48,6 +49,7 @@ fn native_blockifier(py: Python<'_>, py_module: &PyModule) -> PyResult<()> {
     py_module.add_class::<PyTransactionExecutionInfo>()?;
     py_module.add_class::<PyValidator>()?;
     py_module.add_class::<PyExecutionResources>()?;
+    py_module.add_class::<PyFeltArrayFormat>()?;
     py_module.add_class::<StorageConfig>()?;
     py_module.add("UndeclaredClassHashError", py.get_type::<UndeclaredClassHashError>())?;
     add_py_exceptions(py, py_module)?;
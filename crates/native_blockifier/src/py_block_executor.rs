@@ -3,17 +3,29 @@ use std::collections::HashMap;
 use blockifier::blockifier::block::{
     pre_process_block as pre_process_block_blockifier, BlockInfo, BlockNumberHashPair, GasPrices,
 };
+use blockifier::blockifier::bouncer::BouncerInfo;
 use blockifier::blockifier::transaction_executor::TransactionExecutor;
 use blockifier::context::{BlockContext, ChainInfo, FeeTokenAddresses};
+#[cfg(feature = "devnet")]
+use blockifier::execution::contract_class::{
+    verify_compiled_class_hash, ContractClass, ContractClassV0, ContractClassV1,
+};
 use blockifier::state::cached_state::{CachedState, GlobalContractCache};
 use blockifier::state::state_api::State;
 use blockifier::transaction::objects::TransactionExecutionInfo;
+#[cfg(feature = "devnet")]
+use blockifier::transaction::system_declare_batch::SystemDeclareBatch;
 use blockifier::transaction::transaction_execution::Transaction;
+use blockifier::transaction::transactions::ExecutionFlags;
 use blockifier::versioned_constants::VersionedConstants;
+#[cfg(feature = "devnet")]
+use cairo_lang_starknet_classes::casm_contract_class::CasmContractClass;
 use pyo3::prelude::*;
 use serde::Serialize;
 use starknet_api::block::{BlockNumber, BlockTimestamp};
-use starknet_api::core::{ChainId, ContractAddress};
+#[cfg(feature = "devnet")]
+use starknet_api::core::CompiledClassHash;
+use starknet_api::core::{ChainId, ClassHash, ContractAddress};
 use starknet_api::hash::StarkFelt;
 
 use crate::errors::{
@@ -22,8 +34,10 @@ use crate::errors::{
 };
 use crate::py_state_diff::{PyBlockInfo, PyStateDiff};
 use crate::py_transaction::{py_tx, PyClassInfo};
-use crate::py_transaction_execution_info::PyBouncerInfo;
-use crate::py_utils::{int_to_chain_id, py_attr, versioned_constants_with_overrides, PyFelt};
+use crate::py_transaction_execution_info::{PyBouncerInfo, PyTransactionExecutionInfo};
+use crate::py_utils::{
+    int_to_chain_id, py_attr, versioned_constants_with_overrides, PyFelt, PyFeltArrayFormat,
+};
 use crate::state_readers::papyrus_state::PapyrusReader;
 use crate::storage::{PapyrusStorage, Storage, StorageConfig};
 
@@ -52,18 +66,25 @@ pub struct PyBlockExecutor {
     /// `Send` trait is required for `pyclass` compatibility as Python objects must be threadsafe.
     pub storage: Box<dyn Storage + Send>,
     pub global_contract_cache: GlobalContractCache,
+    /// The format of the felt arrays of the execution infos returned as Python objects.
+    pub felt_array_format: PyFeltArrayFormat,
+    /// Whether transactions executed without validation are charged the OS resources of their
+    /// validation, as if it had run.
+    pub charge_skipped_validation: bool,
 }
 
 #[pymethods]
 impl PyBlockExecutor {
     #[new]
-    #[pyo3(signature = (general_config, validate_max_n_steps, max_recursion_depth, global_contract_cache_size, target_storage_config))]
+    #[pyo3(signature = (general_config, validate_max_n_steps, max_recursion_depth, global_contract_cache_size, target_storage_config, felt_array_format = PyFeltArrayFormat::Ints, charge_skipped_validation = false))]
     pub fn create(
         general_config: PyGeneralConfig,
         validate_max_n_steps: u32,
         max_recursion_depth: usize,
         global_contract_cache_size: usize,
         target_storage_config: StorageConfig,
+        felt_array_format: PyFeltArrayFormat,
+        charge_skipped_validation: bool,
     ) -> Self {
         log::debug!("Initializing Block Executor...");
         let storage =
@@ -78,6 +99,8 @@ impl PyBlockExecutor {
             tx_executor: None,
             storage: Box::new(storage),
             global_contract_cache: GlobalContractCache::new(global_contract_cache_size),
+            felt_array_format,
+            charge_skipped_validation,
         }
     }
 
@@ -93,13 +116,14 @@ impl PyBlockExecutor {
         let papyrus_reader = self.get_aligned_reader(next_block_info.block_number);
         let global_contract_cache = self.global_contract_cache.clone();
         let mut state = CachedState::new(papyrus_reader, global_contract_cache);
-        let block_context = pre_process_block(
+        let mut block_context = pre_process_block(
             &mut state,
             old_block_number_and_hash,
             &self.general_config,
             &next_block_info,
             &self.versioned_constants,
         )?;
+        block_context.set_charge_skipped_validation(self.charge_skipped_validation);
 
         let tx_executor = TransactionExecutor::new(state, block_context);
         self.tx_executor = Some(tx_executor);
@@ -111,16 +135,16 @@ impl PyBlockExecutor {
         self.tx_executor = None;
     }
 
-    #[pyo3(signature = (tx, optional_py_class_info))]
+    #[pyo3(signature = (tx, optional_py_class_info, skip_validate = false))]
     pub fn execute(
         &mut self,
         tx: &PyAny,
         optional_py_class_info: Option<PyClassInfo>,
+        skip_validate: bool,
     ) -> NativeBlockifierResult<(RawTransactionExecutionInfo, PyBouncerInfo)> {
-        let charge_fee = true;
         let tx_type: &str = tx.getattr("tx_type")?.getattr("name")?.extract()?;
         let tx: Transaction = py_tx(tx, optional_py_class_info)?;
-        let (tx_execution_info, bouncer_info) = self.tx_executor().execute(tx, charge_fee)?;
+        let (tx_execution_info, bouncer_info) = self.execute_tx(tx, skip_validate)?;
         let typed_tx_execution_info =
             TypedTransactionExecutionInfo { info: tx_execution_info, tx_type: tx_type.to_string() };
         let raw_tx_execution_info = serde_json::to_vec(&typed_tx_execution_info)?;
@@ -129,6 +153,21 @@ impl PyBlockExecutor {
         Ok((raw_tx_execution_info, py_bouncer_info))
     }
 
+    /// Like `execute`, but returns the execution info as a Python object, with its felt arrays in
+    /// the format of the executor, rather than serialized.
+    #[pyo3(signature = (tx, optional_py_class_info, skip_validate = false))]
+    pub fn execute_to_py_info(
+        &mut self,
+        tx: &PyAny,
+        optional_py_class_info: Option<PyClassInfo>,
+        skip_validate: bool,
+    ) -> NativeBlockifierResult<(PyTransactionExecutionInfo, PyBouncerInfo)> {
+        let tx: Transaction = py_tx(tx, optional_py_class_info)?;
+        let (tx_execution_info, bouncer_info) = self.execute_tx(tx, skip_validate)?;
+
+        Ok((self.to_py_execution_info(tx_execution_info), PyBouncerInfo::from(bouncer_info)))
+    }
+
     /// Returns the state diff and a list of contract class hash with the corresponding list of
     /// visited PC values.
     pub fn finalize(&mut self, is_pending_block: bool) -> (PyStateDiff, Vec<(PyFelt, Vec<usize>)>) {
@@ -154,6 +193,43 @@ impl PyBlockExecutor {
         self.tx_executor().abort()
     }
 
+    /// Declares the given classes directly on the block state, without charging fees or running
+    /// account validation; Cairo 0 classes are given without a compiled class hash, and the CASM of
+    /// Cairo 1 classes must match the given one.
+    /// Returns, per class and in the given order, the error message if its declaration failed.
+    #[cfg(feature = "devnet")]
+    #[pyo3(signature = (classes))]
+    pub fn system_declare_batch(
+        &mut self,
+        classes: Vec<(PyFelt, Option<PyFelt>, String)>,
+    ) -> NativeBlockifierResult<Vec<Option<String>>> {
+        let mut batch_classes = Vec::with_capacity(classes.len());
+        for (class_hash, compiled_class_hash, raw_contract_class) in classes {
+            let (compiled_class_hash, contract_class): (CompiledClassHash, ContractClass) =
+                match compiled_class_hash {
+                    Some(compiled_class_hash) => {
+                        let compiled_class_hash = CompiledClassHash(compiled_class_hash.0);
+                        let casm_contract_class: CasmContractClass =
+                            serde_json::from_str(&raw_contract_class)?;
+                        verify_compiled_class_hash(&casm_contract_class, compiled_class_hash)?;
+                        let contract_class = ContractClassV1::try_from(casm_contract_class)?;
+                        (compiled_class_hash, contract_class.into())
+                    }
+                    None => (
+                        CompiledClassHash::default(),
+                        ContractClassV0::try_from_json_string(&raw_contract_class)?.into(),
+                    ),
+                };
+            batch_classes.push((ClassHash(class_hash.0), compiled_class_hash, contract_class));
+        }
+
+        let results = SystemDeclareBatch::new(batch_classes).execute(&mut self.tx_executor().state);
+        Ok(results
+            .into_iter()
+            .map(|(_, result)| result.err().map(|error| error.to_string()))
+            .collect())
+    }
+
     // Storage Alignment API.
 
     /// Appends state diff and block header into Papyrus storage.
@@ -220,6 +296,28 @@ impl PyBlockExecutor {
         self.storage.revert_block(block_number)
     }
 
+    /// Reads the given classes, at the state marker, into the global contract cache; returns the
+    /// error of reading each class, if any.
+    #[pyo3(signature = (class_hashes))]
+    pub fn warm_up_global_contract_cache(
+        &self,
+        class_hashes: Vec<PyFelt>,
+    ) -> NativeBlockifierResult<Vec<(PyFelt, Option<String>)>> {
+        let state_marker = self.storage.get_state_marker()?;
+        let reader = PapyrusReader::new(self.storage.reader().clone(), BlockNumber(state_marker));
+        let class_hashes: Vec<ClassHash> =
+            class_hashes.into_iter().map(|class_hash| ClassHash(class_hash.0)).collect();
+
+        Ok(self
+            .global_contract_cache
+            .warm_up(&reader, &class_hashes)
+            .into_iter()
+            .map(|(class_hash, result)| {
+                (PyFelt(class_hash.0), result.err().map(|error| error.to_string()))
+            })
+            .collect())
+    }
+
     /// Deallocate the transaction executor and close storage connections.
     pub fn close(&mut self) {
         log::debug!("Closing Block Executor.");
@@ -243,6 +341,8 @@ impl PyBlockExecutor {
             versioned_constants: VersionedConstants::latest_constants().clone(),
             tx_executor: None,
             global_contract_cache: GlobalContractCache::new(GLOBAL_CONTRACT_CACHE_SIZE_FOR_TEST),
+            felt_array_format: PyFeltArrayFormat::default(),
+            charge_skipped_validation: false,
         }
     }
 }
@@ -252,6 +352,25 @@ impl PyBlockExecutor {
         self.tx_executor.as_mut().expect("Transaction executor should be initialized")
     }
 
+    /// Executes the given transaction on the state of the current block, charging its fee.
+    pub fn execute_tx(
+        &mut self,
+        tx: Transaction,
+        skip_validate: bool,
+    ) -> NativeBlockifierResult<(TransactionExecutionInfo, BouncerInfo)> {
+        let execution_flags =
+            ExecutionFlags { charge_fee: true, validate: !skip_validate, ..Default::default() };
+        Ok(self.tx_executor().execute(tx, execution_flags)?)
+    }
+
+    /// Converts the given execution info, with its felt arrays in the format of the executor.
+    pub fn to_py_execution_info(
+        &self,
+        info: TransactionExecutionInfo,
+    ) -> PyTransactionExecutionInfo {
+        PyTransactionExecutionInfo::new(info, self.felt_array_format)
+    }
+
     fn get_aligned_reader(&self, next_block_number: u64) -> PapyrusReader {
         // Full-node storage must be aligned to the Python storage before initializing a reader.
         self.storage.validate_aligned(next_block_number);
@@ -267,6 +386,8 @@ impl PyBlockExecutor {
             versioned_constants: VersionedConstants::latest_constants().clone(),
             tx_executor: None,
             global_contract_cache: GlobalContractCache::new(GLOBAL_CONTRACT_CACHE_SIZE_FOR_TEST),
+            felt_array_format: PyFeltArrayFormat::default(),
+            charge_skipped_validation: false,
         }
     }
 }
@@ -1,16 +1,26 @@
 use std::collections::HashMap;
 
+use blockifier::execution::call_info::{CallExecution, CallInfo, Retdata};
 use blockifier::state::state_api::State;
-use blockifier::test_utils::{get_test_contract_class, TEST_CLASS_HASH};
+use blockifier::test_utils::contracts::FeatureContract;
+use blockifier::test_utils::{
+    get_test_contract_class, CairoVersion, NonceManager, TEST_CLASS_HASH,
+};
+use blockifier::transaction::objects::TransactionExecutionInfo;
+use blockifier::transaction::test_utils::{
+    create_account_tx_for_validate_test, FaultyAccountTxCreatorArgs, INVALID,
+};
+use blockifier::transaction::transaction_execution::Transaction;
 use cached::Cached;
 use pretty_assertions::assert_eq;
-use starknet_api::class_hash;
 use starknet_api::core::ClassHash;
 use starknet_api::hash::{StarkFelt, StarkHash};
+use starknet_api::transaction::Fee;
+use starknet_api::{class_hash, stark_felt};
 
 use crate::py_block_executor::{PyBlockExecutor, PyGeneralConfig};
 use crate::py_state_diff::PyBlockInfo;
-use crate::py_utils::PyFelt;
+use crate::py_utils::{PyFelt, PyFeltArray, PyFeltArrayFormat};
 use crate::test_utils::MockStorage;
 
 #[test]
@@ -67,3 +77,91 @@ fn get_block_id() {
         expected_max_class_hash_as_py_felt
     );
 }
+
+#[test]
+fn warm_up_global_contract_cache() {
+    let temp_storage_path = tempfile::tempdir().unwrap().into_path();
+    let block_executor =
+        PyBlockExecutor::create_for_testing(PyGeneralConfig::default(), temp_storage_path);
+    let undeclared_class_hash = PyFelt(stark_felt!(TEST_CLASS_HASH));
+
+    let results =
+        block_executor.warm_up_global_contract_cache(vec![undeclared_class_hash]).unwrap();
+    assert_eq!(results.len(), 1);
+    assert_eq!(results[0].0, undeclared_class_hash);
+    assert!(results[0].1.is_some());
+    assert_eq!(block_executor.global_contract_cache.lock().cache_size(), 0);
+}
+
+#[test]
+fn execution_info_felt_array_format() {
+    let retdata = vec![stark_felt!(1_u8), stark_felt!("0x1234")];
+    let execution_info = || TransactionExecutionInfo {
+        execute_call_info: Some(CallInfo {
+            execution: CallExecution { retdata: Retdata(retdata.clone()), ..Default::default() },
+            ..Default::default()
+        }),
+        ..Default::default()
+    };
+    let storage = MockStorage { block_number_to_class_hash: HashMap::new() };
+    let mut block_executor = PyBlockExecutor::create_for_testing_with_storage(storage);
+
+    // Lists of ints by default.
+    let py_execution_info = block_executor.to_py_execution_info(execution_info());
+    assert_eq!(
+        py_execution_info.execute_call_info.unwrap().retdata,
+        PyFeltArray::Ints(retdata.iter().copied().map(PyFelt).collect())
+    );
+
+    block_executor.felt_array_format = PyFeltArrayFormat::Bytes;
+    let py_execution_info = block_executor.to_py_execution_info(execution_info());
+    assert_eq!(
+        py_execution_info.execute_call_info.unwrap().retdata,
+        PyFeltArray::Bytes(retdata.iter().flat_map(|felt| *felt.bytes()).collect())
+    );
+}
+
+#[test]
+fn skip_validate() {
+    let temp_storage_path = tempfile::tempdir().unwrap().into_path();
+    let mut block_executor =
+        PyBlockExecutor::create_for_testing(PyGeneralConfig::default(), temp_storage_path);
+    block_executor.charge_skipped_validation = true;
+    let sentinel_block_number_and_hash = None; // Information does not exist for block 0.
+    block_executor
+        .setup_block_execution(PyBlockInfo::default(), sentinel_block_number_and_hash)
+        .unwrap();
+    assert!(block_executor.tx_executor().block_context.charge_skipped_validation());
+
+    // Deploy an account whose validation fails.
+    let faulty_account = FeatureContract::FaultyAccount(CairoVersion::Cairo0);
+    let class_hash = faulty_account.get_class_hash();
+    let sender_address = faulty_account.get_instance_address(0);
+    let state = &mut block_executor.tx_executor().state;
+    state.set_contract_class(class_hash, faulty_account.get_class()).unwrap();
+    state.set_class_hash_at(sender_address, class_hash).unwrap();
+
+    // The fee of a transaction with a zero max fee is not enforced, so the account needs no
+    // balance.
+    let invalid_tx = || {
+        Transaction::AccountTransaction(create_account_tx_for_validate_test(
+            &mut NonceManager::default(),
+            FaultyAccountTxCreatorArgs {
+                scenario: INVALID,
+                sender_address,
+                class_hash,
+                max_fee: Fee(0),
+                ..Default::default()
+            },
+        ))
+    };
+
+    let skip_validate = false;
+    assert!(block_executor.execute_tx(invalid_tx(), skip_validate).is_err());
+
+    let skip_validate = true;
+    let (tx_execution_info, _bouncer_info) =
+        block_executor.execute_tx(invalid_tx(), skip_validate).unwrap();
+    assert!(tx_execution_info.validate_call_info.is_none());
+    assert!(!tx_execution_info.is_reverted());
+}
@@ -9,7 +9,7 @@ use crate::py_transaction_execution_info::PyExecutionResources;
 #[pyfunction]
 pub fn raise_error_for_testing() -> NativeBlockifierResult<()> {
     Err(TransactionExecutionError::TransactionFeeError(
-        TransactionFeeError::CairoResourcesNotContainedInFeeCosts,
+        TransactionFeeError::CairoResourcesNotContainedInFeeCosts { missing_resources: vec![] },
     )
     .into())
 }
@@ -1,11 +1,12 @@
 use std::collections::BTreeMap;
 
 use blockifier::execution::contract_class::{
-    ClassInfo, ContractClass, ContractClassV0, ContractClassV1,
+    verify_compiled_class_hash, ClassInfo, ContractClass, ContractClassV0, ContractClassV1,
 };
 use blockifier::transaction::account_transaction::AccountTransaction;
 use blockifier::transaction::transaction_execution::Transaction;
 use blockifier::transaction::transaction_types::TransactionType;
+use cairo_lang_starknet_classes::casm_contract_class::CasmContractClass;
 use pyo3::exceptions::PyValueError;
 use pyo3::prelude::*;
 use starknet_api::transaction::{Resource, ResourceBounds};
@@ -158,9 +159,17 @@ impl PyClassInfo {
             | starknet_api::transaction::DeclareTransaction::V1(_) => {
                 ContractClassV0::try_from_json_string(&py_class_info.raw_contract_class)?.into()
             }
-            starknet_api::transaction::DeclareTransaction::V2(_)
-            | starknet_api::transaction::DeclareTransaction::V3(_) => {
-                ContractClassV1::try_from_json_string(&py_class_info.raw_contract_class)?.into()
+            starknet_api::transaction::DeclareTransaction::V2(
+                starknet_api::transaction::DeclareTransactionV2 { compiled_class_hash, .. },
+            )
+            | starknet_api::transaction::DeclareTransaction::V3(
+                starknet_api::transaction::DeclareTransactionV3 { compiled_class_hash, .. },
+            ) => {
+                // Only the compiled class hash is committed to; verify the given CASM matches it.
+                let casm_contract_class: CasmContractClass =
+                    serde_json::from_str(&py_class_info.raw_contract_class)?;
+                verify_compiled_class_hash(&casm_contract_class, *compiled_class_hash)?;
+                ContractClassV1::try_from(casm_contract_class)?.into()
             }
         };
         let class_info = ClassInfo::new(
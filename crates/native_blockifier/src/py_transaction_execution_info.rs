@@ -8,7 +8,11 @@ use cairo_vm::vm::runners::cairo_runner::ExecutionResources;
 use pyo3::prelude::*;
 use starknet_api::deprecated_contract_class::EntryPointType;
 
-use crate::py_utils::{to_py_vec, PyFelt};
+use crate::py_utils::{to_py_vec, PyFelt, PyFeltArray, PyFeltArrayFormat};
+
+#[cfg(test)]
+#[path = "py_transaction_execution_info_test.rs"]
+mod py_transaction_execution_info_test;
 
 #[pyclass]
 #[derive(Clone)]
@@ -24,23 +28,39 @@ pub struct PyTransactionExecutionInfo {
     #[pyo3(get)]
     pub actual_resources: HashMap<String, usize>,
     #[pyo3(get)]
+    pub n_reverted_steps: usize,
+    #[pyo3(get)]
     pub revert_error: Option<String>,
 }
 
-impl From<TransactionExecutionInfo> for PyTransactionExecutionInfo {
-    // TODO(Gilad, 1/4/2023): Check that everything can't fail, recursively.
-    fn from(info: TransactionExecutionInfo) -> Self {
+impl PyTransactionExecutionInfo {
+    /// Converts the given execution info, exposing all felt arrays in the given format.
+    pub fn new(info: TransactionExecutionInfo, felt_array_format: PyFeltArrayFormat) -> Self {
+        let to_py_call_info = |call_info| PyCallInfo::new(call_info, felt_array_format);
         Self {
-            validate_call_info: info.validate_call_info.map(PyCallInfo::from),
-            execute_call_info: info.execute_call_info.map(PyCallInfo::from),
-            fee_transfer_call_info: info.fee_transfer_call_info.map(PyCallInfo::from),
+            validate_call_info: info.validate_call_info.map(to_py_call_info),
+            execute_call_info: info.execute_call_info.map(to_py_call_info),
+            fee_transfer_call_info: info.fee_transfer_call_info.map(to_py_call_info),
             actual_fee: info.actual_fee.0,
-            actual_resources: info.actual_resources.0,
+            actual_resources: info
+                .actual_resources
+                .0
+                .into_iter()
+                .map(|(resource, usage)| (resource.to_string(), usage))
+                .collect(),
+            n_reverted_steps: info.n_reverted_steps,
             revert_error: info.revert_error,
         }
     }
 }
 
+impl From<TransactionExecutionInfo> for PyTransactionExecutionInfo {
+    // TODO(Gilad, 1/4/2023): Check that everything can't fail, recursively.
+    fn from(info: TransactionExecutionInfo) -> Self {
+        Self::new(info, PyFeltArrayFormat::default())
+    }
+}
+
 #[pyclass]
 #[derive(Clone)]
 pub struct PyCallInfo {
@@ -56,7 +76,7 @@ pub struct PyCallInfo {
     #[pyo3(get)]
     pub entry_point_type: PyEntryPointType,
     #[pyo3(get)]
-    pub calldata: Vec<PyFelt>,
+    pub calldata: PyFeltArray,
     #[pyo3(get)]
     pub call_type: PyCallType,
 
@@ -66,7 +86,7 @@ pub struct PyCallInfo {
     #[pyo3(get)]
     pub failure_flag: bool, // Currently not in use.
     #[pyo3(get)]
-    pub retdata: Vec<PyFelt>,
+    pub retdata: PyFeltArray,
     #[pyo3(get)]
     pub execution_resources: PyExecutionResources,
     #[pyo3(get)]
@@ -80,7 +100,7 @@ pub struct PyCallInfo {
 
     // Information kept for following flows (fee, OS).
     #[pyo3(get)]
-    pub storage_read_values: Vec<PyFelt>,
+    pub storage_read_values: PyFeltArray,
     #[pyo3(get)]
     pub accessed_storage_keys: HashSet<PyFelt>,
 
@@ -123,10 +143,12 @@ impl From<EntryPointType> for PyEntryPointType {
     }
 }
 
-impl From<CallInfo> for PyCallInfo {
-    fn from(call_info: CallInfo) -> Self {
+impl PyCallInfo {
+    /// Converts the given call info (recursively), exposing all felt arrays in the given format.
+    pub fn new(call_info: CallInfo, felt_array_format: PyFeltArrayFormat) -> Self {
         let call = call_info.call;
         let execution = call_info.execution;
+        let to_py_felt_array = |felts| PyFeltArray::new(felts, felt_array_format);
 
         Self {
             caller_address: PyFelt::from(call.caller_address),
@@ -134,15 +156,21 @@ impl From<CallInfo> for PyCallInfo {
             class_hash: call.class_hash.map(PyFelt::from),
             entry_point_selector: PyFelt(call.entry_point_selector.0),
             entry_point_type: PyEntryPointType::from(call.entry_point_type),
-            calldata: to_py_vec(call.calldata.0.to_vec(), PyFelt),
+            calldata: to_py_felt_array(call.calldata.0.to_vec()),
             gas_consumed: execution.gas_consumed,
             failure_flag: execution.failed,
-            retdata: to_py_vec(execution.retdata.0, PyFelt),
+            retdata: to_py_felt_array(execution.retdata.0),
             execution_resources: PyExecutionResources::from(call_info.resources),
-            events: to_py_vec(execution.events, PyOrderedEvent::from),
-            l2_to_l1_messages: to_py_vec(execution.l2_to_l1_messages, PyOrderedL2ToL1Message::from),
-            internal_calls: to_py_vec(call_info.inner_calls, PyCallInfo::from),
-            storage_read_values: to_py_vec(call_info.storage_read_values, PyFelt),
+            events: to_py_vec(execution.events, |event| {
+                PyOrderedEvent::new(event, felt_array_format)
+            }),
+            l2_to_l1_messages: to_py_vec(execution.l2_to_l1_messages, |message| {
+                PyOrderedL2ToL1Message::new(message, felt_array_format)
+            }),
+            internal_calls: to_py_vec(call_info.inner_calls, |inner_call| {
+                PyCallInfo::new(inner_call, felt_array_format)
+            }),
+            storage_read_values: to_py_felt_array(call_info.storage_read_values),
             accessed_storage_keys: call_info
                 .accessed_storage_keys
                 .into_iter()
@@ -154,27 +182,40 @@ impl From<CallInfo> for PyCallInfo {
     }
 }
 
+impl From<CallInfo> for PyCallInfo {
+    fn from(call_info: CallInfo) -> Self {
+        Self::new(call_info, PyFeltArrayFormat::default())
+    }
+}
+
 #[pyclass]
 #[derive(Clone)]
 pub struct PyOrderedEvent {
     #[pyo3(get)]
     pub order: usize,
     #[pyo3(get)]
-    pub keys: Vec<PyFelt>,
+    pub keys: PyFeltArray,
     #[pyo3(get)]
-    pub data: Vec<PyFelt>,
+    pub data: PyFeltArray,
 }
 
-impl From<OrderedEvent> for PyOrderedEvent {
-    fn from(ordered_event: OrderedEvent) -> Self {
+impl PyOrderedEvent {
+    pub fn new(ordered_event: OrderedEvent, felt_array_format: PyFeltArrayFormat) -> Self {
+        let keys = ordered_event.event.keys.into_iter().map(|key| key.0).collect();
         Self {
             order: ordered_event.order,
-            keys: to_py_vec(ordered_event.event.keys, |x| PyFelt(x.0)),
-            data: to_py_vec(ordered_event.event.data.0, PyFelt),
+            keys: PyFeltArray::new(keys, felt_array_format),
+            data: PyFeltArray::new(ordered_event.event.data.0, felt_array_format),
         }
     }
 }
 
+impl From<OrderedEvent> for PyOrderedEvent {
+    fn from(ordered_event: OrderedEvent) -> Self {
+        Self::new(ordered_event, PyFeltArrayFormat::default())
+    }
+}
+
 #[pyclass]
 #[derive(Clone)]
 pub struct PyOrderedL2ToL1Message {
@@ -183,19 +224,28 @@ pub struct PyOrderedL2ToL1Message {
     #[pyo3(get)]
     pub to_address: PyFelt,
     #[pyo3(get)]
-    pub payload: Vec<PyFelt>,
+    pub payload: PyFeltArray,
 }
 
-impl From<OrderedL2ToL1Message> for PyOrderedL2ToL1Message {
-    fn from(ordered_message: OrderedL2ToL1Message) -> Self {
+impl PyOrderedL2ToL1Message {
+    pub fn new(
+        ordered_message: OrderedL2ToL1Message,
+        felt_array_format: PyFeltArrayFormat,
+    ) -> Self {
         Self {
             order: ordered_message.order,
             to_address: PyFelt::from(ordered_message.message.to_address),
-            payload: to_py_vec(ordered_message.message.payload.0, PyFelt),
+            payload: PyFeltArray::new(ordered_message.message.payload.0, felt_array_format),
         }
     }
 }
 
+impl From<OrderedL2ToL1Message> for PyOrderedL2ToL1Message {
+    fn from(ordered_message: OrderedL2ToL1Message) -> Self {
+        Self::new(ordered_message, PyFeltArrayFormat::default())
+    }
+}
+
 #[pyclass]
 #[derive(Clone, Default)]
 pub struct PyExecutionResources {
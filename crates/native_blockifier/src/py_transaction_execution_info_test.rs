@@ -0,0 +1,47 @@
+use blockifier::execution::call_info::{CallExecution, CallInfo, Retdata};
+use pretty_assertions::assert_eq;
+use starknet_api::hash::StarkFelt;
+use starknet_api::stark_felt;
+
+use crate::py_transaction_execution_info::PyCallInfo;
+use crate::py_utils::{PyFelt, PyFeltArray, PyFeltArrayFormat};
+
+fn call_info_with_retdata(retdata: Retdata) -> CallInfo {
+    CallInfo {
+        execution: CallExecution { retdata, ..Default::default() },
+        inner_calls: vec![CallInfo::default()],
+        ..Default::default()
+    }
+}
+
+#[test]
+fn felt_array_formats() {
+    let retdata =
+        vec![stark_felt!(0_u8), stark_felt!(1_u8), stark_felt!("0x1234"), stark_felt!(7_u8)];
+    let call_info = call_info_with_retdata(Retdata(retdata.clone()));
+
+    // The default format is a list of ints.
+    let py_call_info = PyCallInfo::from(call_info);
+    assert_eq!(
+        py_call_info.retdata,
+        PyFeltArray::Ints(retdata.iter().copied().map(PyFelt).collect())
+    );
+
+    // The bytes format is the concatenation of the felts' 32-byte big-endian encodings.
+    let call_info = call_info_with_retdata(Retdata(retdata.clone()));
+    let py_call_info = PyCallInfo::new(call_info, PyFeltArrayFormat::Bytes);
+    let PyFeltArray::Bytes(bytes) = &py_call_info.retdata else {
+        panic!("Expected retdata in bytes format, got: {:?}.", py_call_info.retdata);
+    };
+    assert_eq!(bytes.len(), 32 * retdata.len());
+    assert_eq!(bytes[32 * 3 - 2..32 * 3], [0x12, 0x34]);
+    let decoded_retdata: Vec<StarkFelt> = bytes
+        .chunks_exact(32)
+        .map(|felt_bytes| StarkFelt::new(felt_bytes.try_into().unwrap()).unwrap())
+        .collect();
+    assert_eq!(decoded_retdata, retdata);
+
+    // The format applies recursively; empty arrays are empty bytes.
+    assert_eq!(py_call_info.internal_calls[0].retdata, PyFeltArray::Bytes(vec![]));
+    assert_eq!(py_call_info.calldata, PyFeltArray::Bytes(vec![]));
+}
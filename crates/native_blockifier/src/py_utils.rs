@@ -4,6 +4,7 @@ use blockifier::versioned_constants::VersionedConstants;
 use num_bigint::BigUint;
 use pyo3::exceptions::PyValueError;
 use pyo3::prelude::*;
+use pyo3::types::PyBytes;
 use starknet_api::core::{ChainId, ClassHash, CompiledClassHash, ContractAddress, EthAddress};
 use starknet_api::hash::StarkFelt;
 use starknet_api::state::StorageKey;
@@ -83,6 +84,43 @@ where
     values.into_iter().map(converter).collect()
 }
 
+/// The Python representation of felt arrays (calldata, retdata, event keys and data, etc.).
+#[pyclass]
+#[derive(Clone, Copy, Debug, Default, Eq, PartialEq)]
+pub enum PyFeltArrayFormat {
+    /// A list of ints; one Python int is allocated per felt.
+    #[default]
+    Ints,
+    /// A single `bytes` object: the concatenation of the felts' 32-byte big-endian encodings.
+    Bytes,
+}
+
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub enum PyFeltArray {
+    Ints(Vec<PyFelt>),
+    Bytes(Vec<u8>),
+}
+
+impl PyFeltArray {
+    pub fn new(felts: Vec<StarkFelt>, format: PyFeltArrayFormat) -> Self {
+        match format {
+            PyFeltArrayFormat::Ints => Self::Ints(to_py_vec(felts, PyFelt)),
+            PyFeltArrayFormat::Bytes => {
+                Self::Bytes(felts.iter().flat_map(|felt| *felt.bytes()).collect())
+            }
+        }
+    }
+}
+
+impl IntoPy<PyObject> for PyFeltArray {
+    fn into_py(self, py: Python<'_>) -> PyObject {
+        match self {
+            Self::Ints(felts) => felts.into_py(py),
+            Self::Bytes(bytes) => PyBytes::new(py, &bytes).into_py(py),
+        }
+    }
+}
+
 pub fn from_py_felts(py_felts: Vec<PyFelt>) -> Vec<StarkFelt> {
     py_felts.into_iter().map(|felt| felt.0).collect()
 }
@@ -10,6 +10,7 @@ use blockifier::transaction::objects::{
     TransactionExecutionInfo, TransactionExecutionResult, TransactionInfo,
 };
 use blockifier::transaction::transaction_execution::Transaction;
+use blockifier::transaction::transactions::ExecutionFlags;
 use pyo3::{pyclass, pymethods, PyAny};
 use starknet_api::core::Nonce;
 use starknet_api::hash::StarkFelt;
@@ -146,9 +147,14 @@ impl PyValidator {
         optional_class_info: Option<PyClassInfo>,
     ) -> NativeBlockifierResult<(TransactionExecutionInfo, PyBouncerInfo)> {
         let limit_execution_steps_by_resource_bounds = true;
+        let validate = true;
         let tx: Transaction = py_tx(tx, optional_class_info)?;
-        let (tx_execution_info, bouncer_info) =
-            self.tx_executor.execute(tx, limit_execution_steps_by_resource_bounds)?;
+        let execution_flags = ExecutionFlags {
+            charge_fee: limit_execution_steps_by_resource_bounds,
+            validate,
+            ..Default::default()
+        };
+        let (tx_execution_info, bouncer_info) = self.tx_executor.execute(tx, execution_flags)?;
         let py_bouncer_info = PyBouncerInfo::from(bouncer_info);
 
         Ok((tx_execution_info, py_bouncer_info))
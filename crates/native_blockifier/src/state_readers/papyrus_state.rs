@@ -31,6 +31,21 @@ impl PapyrusReader {
             .begin_ro_txn()
             .map_err(|error| StateError::StateReadError(error.to_string()))
     }
+
+    /// Returns the block in which the given Cairo 1 class was declared, if it is declared in the
+    /// state the reader is pinned to.
+    fn get_class_declaration_block(
+        &self,
+        class_hash: ClassHash,
+    ) -> StateResult<Option<BlockNumber>> {
+        let state_number = StateNumber(self.latest_block);
+        let class_declaration_block_number = self
+            .reader()?
+            .get_state_reader()
+            .and_then(|sr| sr.get_class_definition_block_number(&class_hash))
+            .map_err(|err| StateError::StateReadError(err.to_string()))?;
+        Ok(class_declaration_block_number.filter(|&block_number| block_number <= state_number.0))
+    }
 }
 
 // Currently unused - will soon replace the same `impl` for `PapyrusStateReader`.
@@ -77,15 +92,7 @@ impl StateReader for PapyrusReader {
     /// found, or an `Error` otherwise.
     fn get_compiled_contract_class(&self, class_hash: ClassHash) -> StateResult<ContractClass> {
         let state_number = StateNumber(self.latest_block);
-        let class_declaration_block_number = self
-            .reader()?
-            .get_state_reader()
-            .and_then(|sr| sr.get_class_definition_block_number(&class_hash))
-            .map_err(|err| StateError::StateReadError(err.to_string()))?;
-        let class_is_declared: bool = matches!(class_declaration_block_number,
-                    Some(block_number) if block_number <= state_number.0);
-
-        if class_is_declared {
+        if self.get_class_declaration_block(class_hash)?.is_some() {
             let casm_contract_class = self
                 .reader()?
                 .get_casm(&class_hash)
@@ -112,7 +119,25 @@ impl StateReader for PapyrusReader {
         }
     }
 
-    fn get_compiled_class_hash(&self, _class_hash: ClassHash) -> StateResult<CompiledClassHash> {
-        todo!()
+    /// Returns the compiled class hash of a Cairo 1 class, as declared, or the default value if
+    /// no such class is declared.
+    fn get_compiled_class_hash(&self, class_hash: ClassHash) -> StateResult<CompiledClassHash> {
+        let Some(block_number) = self.get_class_declaration_block(class_hash)? else {
+            return Ok(CompiledClassHash::default());
+        };
+
+        let state_diff = self
+            .reader()?
+            .get_state_diff(block_number)
+            .map_err(|err| StateError::StateReadError(err.to_string()))?
+            .expect(
+                "Should be able to fetch the state diff of the block declaring a class, database \
+                 is inconsistent.",
+            );
+        let compiled_class_hash = state_diff.declared_classes.get(&class_hash).expect(
+            "A class should be declared in the state diff of its declaration block, database is \
+             inconsistent.",
+        );
+        Ok(*compiled_class_hash)
     }
 }
@@ -1,19 +1,24 @@
+use assert_matches::assert_matches;
 use blockifier::abi::abi_utils::selector_from_name;
 use blockifier::execution::call_info::{CallExecution, Retdata};
+use blockifier::execution::contract_class::{ContractClass, ContractClassV1};
 use blockifier::execution::entry_point::CallEntryPoint;
 use blockifier::retdata;
 use blockifier::state::cached_state::CachedState;
+use blockifier::state::errors::StateError;
 use blockifier::state::state_api::StateReader;
 use blockifier::test_utils::{
-    get_deprecated_contract_class, trivial_external_entry_point, TEST_CLASS_HASH,
-    TEST_CONTRACT_ADDRESS, TEST_CONTRACT_CAIRO0_PATH,
+    get_deprecated_contract_class, get_raw_contract_class, trivial_external_entry_point,
+    TEST_CLASS_HASH, TEST_CONTRACT_ADDRESS, TEST_CONTRACT_CAIRO0_PATH, TEST_CONTRACT_CAIRO1_PATH,
 };
+use cairo_lang_starknet_classes::casm_contract_class::CasmContractClass;
 use indexmap::IndexMap;
+use papyrus_storage::compiled_class::CasmStorageWriter;
 use papyrus_storage::state::StateStorageWriter;
 use starknet_api::block::BlockNumber;
-use starknet_api::core::{ClassHash, ContractAddress, PatriciaKey};
+use starknet_api::core::{ClassHash, CompiledClassHash, ContractAddress, Nonce, PatriciaKey};
 use starknet_api::hash::{StarkFelt, StarkHash};
-use starknet_api::state::{StateDiff, StorageKey};
+use starknet_api::state::{ContractClass as SierraContractClass, StateDiff, StorageKey};
 use starknet_api::transaction::Calldata;
 use starknet_api::{calldata, class_hash, contract_address, patricia_key, stark_felt};
 
@@ -63,3 +68,67 @@ fn test_entry_point_with_papyrus_state() -> papyrus_storage::StorageResult<()> {
 
     Ok(())
 }
+
+#[test]
+fn test_cairo1_class_with_papyrus_state() -> papyrus_storage::StorageResult<()> {
+    let ((storage_reader, mut storage_writer), _) = papyrus_storage::test_utils::get_test_storage();
+
+    // Initialize Storage: declare a Cairo 1 class, with its CASM, and deploy a contract of it.
+    let class_hash = class_hash!(TEST_CLASS_HASH);
+    let compiled_class_hash = CompiledClassHash(stark_felt!(1234_u16));
+    let contract_address = contract_address!(TEST_CONTRACT_ADDRESS);
+    let state_diff = StateDiff {
+        deployed_contracts: IndexMap::from([(contract_address, class_hash)]),
+        declared_classes: IndexMap::from([(
+            class_hash,
+            (compiled_class_hash, SierraContractClass::default()),
+        )]),
+        ..Default::default()
+    };
+    let casm: CasmContractClass =
+        serde_json::from_str(&get_raw_contract_class(TEST_CONTRACT_CAIRO1_PATH)).unwrap();
+    storage_writer
+        .begin_rw_txn()?
+        .append_state_diff(BlockNumber::default(), state_diff, IndexMap::new())?
+        .append_casm(&class_hash, &casm)?
+        .commit()?;
+
+    let papyrus_reader = PapyrusReader::new(storage_reader, BlockNumber(1));
+    let mut state = CachedState::from(papyrus_reader);
+
+    assert_eq!(state.get_class_hash_at(contract_address).unwrap(), class_hash);
+    assert_eq!(
+        state.get_compiled_contract_class(class_hash).unwrap(),
+        ContractClass::V1(ContractClassV1::try_from(casm).unwrap())
+    );
+    assert_eq!(state.get_compiled_class_hash(class_hash).unwrap(), compiled_class_hash);
+
+    // Missing entities have default values, except for undeclared classes.
+    let undeployed_address = contract_address!("0x200");
+    let undeclared_class_hash = class_hash!("0x210");
+    let key = StorageKey(patricia_key!("0x10"));
+    assert_eq!(state.get_storage_at(undeployed_address, key).unwrap(), StarkFelt::default());
+    assert_eq!(state.get_nonce_at(undeployed_address).unwrap(), Nonce::default());
+    assert_eq!(state.get_class_hash_at(undeployed_address).unwrap(), ClassHash::default());
+    assert_eq!(
+        state.get_compiled_class_hash(undeclared_class_hash).unwrap(),
+        CompiledClassHash::default()
+    );
+    assert_matches!(
+        state.get_compiled_contract_class(undeclared_class_hash).unwrap_err(),
+        StateError::UndeclaredClassHash(class_hash) if class_hash == undeclared_class_hash
+    );
+
+    // Execute an entry point of the contract through the state.
+    let key = stark_felt!(1234_u16);
+    let value = stark_felt!(18_u8);
+    let entry_point_call = CallEntryPoint {
+        calldata: calldata![key, value],
+        entry_point_selector: selector_from_name("test_storage_read_write"),
+        ..trivial_external_entry_point()
+    };
+    let execution = entry_point_call.execute_directly(&mut state).unwrap().execution;
+    assert_eq!(execution.retdata, retdata![value]);
+
+    Ok(())
+}